@@ -0,0 +1,45 @@
+use std::hint::black_box;
+
+use criterion::{Criterion, criterion_group, criterion_main};
+use mca_rs::{chunk::Chunk, region::Region};
+
+fn cache(c: &mut Criterion) {
+    let region = Region::parse_bytes(include_bytes!("../tests/data/r.0.0.mca")).unwrap();
+    let slots: Vec<_> = (0..32)
+        .flat_map(|x| (0..32).map(move |z| (x, z)))
+        .filter(|&(x, z)| region.get_chunk(x, z).is_some())
+        .collect();
+    let cached: Vec<_> = slots
+        .iter()
+        .map(|&(x, z)| region.get_chunk(x, z).unwrap().to_cache_bytes())
+        .collect();
+
+    let mut group = c.benchmark_group("cache");
+    group.sample_size(10);
+    group.bench_function("decode raw", |b| {
+        b.iter(|| {
+            for &(x, z) in &slots {
+                black_box(region.get_chunk_raw(x, z).unwrap().decode().unwrap());
+            }
+        })
+    });
+    group.bench_function("round trip", |b| {
+        b.iter(|| {
+            for &(x, z) in &slots {
+                let bytes = region.get_chunk(x, z).unwrap().to_cache_bytes();
+                black_box(Chunk::from_cache_bytes(&bytes).unwrap());
+            }
+        })
+    });
+    group.bench_function("from cache", |b| {
+        b.iter(|| {
+            for bytes in &cached {
+                black_box(Chunk::from_cache_bytes(bytes).unwrap());
+            }
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, cache);
+criterion_main!(benches);
@@ -0,0 +1,106 @@
+use std::hint::black_box;
+
+use criterion::{Criterion, criterion_group, criterion_main};
+use mca_rs::region::Region;
+
+fn iter_blocks(c: &mut Criterion) {
+    let region = Region::parse_bytes(include_bytes!("../tests/data/r.0.0.mca")).unwrap();
+    let chunks: Vec<_> = (0..32)
+        .flat_map(|x| (0..32).map(move |z| (x, z)))
+        .filter_map(|(x, z)| region.get_chunk(x, z))
+        .collect();
+
+    let mut group = c.benchmark_group("iter_blocks");
+    group.bench_function("all filtered", |b| {
+        b.iter(|| {
+            chunks
+                .iter()
+                .flat_map(|chunk| chunk.iter_blocks())
+                .filter(|(.., block)| !block.is_air())
+                .for_each(|block| {
+                    black_box(block);
+                })
+        })
+    });
+    group.bench_function("non air", |b| {
+        b.iter(|| {
+            chunks
+                .iter()
+                .flat_map(|chunk| chunk.iter_blocks_non_air())
+                .for_each(|block| {
+                    black_box(block);
+                })
+        })
+    });
+    // what iter_blocks replaces, for comparison
+    group.bench_function("get per block", |b| {
+        b.iter(|| {
+            for chunk in &chunks {
+                let range = chunk.section_y_range();
+                for y in range.start * 16..range.end * 16 {
+                    for z in 0..16 {
+                        for x in 0..16 {
+                            if let Some(block) = chunk.get(x, y, z)
+                                && !block.is_air()
+                            {
+                                black_box((x, y, z, block));
+                            }
+                        }
+                    }
+                }
+            }
+        })
+    });
+    group.finish();
+
+    let sections: Vec<_> = (chunks.iter())
+        .flat_map(|chunk| chunk.sections().map(|(_, section)| section))
+        .collect();
+    let mut group = c.benchmark_group("sections");
+    group.bench_function("copy_blocks_into", |b| {
+        let (mut indices, mut palette) = ([0; 4096], Vec::new());
+        b.iter(|| {
+            for section in &sections {
+                section.copy_blocks_into(&mut indices, &mut palette);
+                black_box((&indices, &palette));
+            }
+        })
+    });
+    group.bench_function("get_block", |b| {
+        b.iter(|| {
+            for section in &sections {
+                for y in 0..16 {
+                    for z in 0..16 {
+                        for x in 0..16 {
+                            black_box(section.get_block(x, y, z));
+                        }
+                    }
+                }
+            }
+        })
+    });
+    group.finish();
+
+    // the same blocks in no particular order, like lookups of a renderer
+    let positions: Vec<_> = (0..4096u32)
+        .scan(1u32, |state, _| {
+            *state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+            Some(*state >> 8)
+        })
+        .map(|r| (r % 512, (r >> 9) % 384, (r >> 18) % 512))
+        .map(|(x, y, z)| (x as usize, y as i32 - 64, z as usize))
+        .collect();
+    c.bench_function("get random", |b| {
+        b.iter(|| {
+            for &(x, y, z) in &positions {
+                let Some(chunk) = region.get_chunk(x / 16, z / 16) else {
+                    continue;
+                };
+                black_box(chunk.get((x % 16) as u8, y, (z % 16) as u8));
+            }
+        })
+    });
+}
+
+criterion_group!(benches, iter_blocks);
+criterion_main!(benches);
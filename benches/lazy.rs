@@ -0,0 +1,42 @@
+use std::hint::black_box;
+
+use criterion::{Criterion, criterion_group, criterion_main};
+use mca_rs::{LazyRegion, region::Region};
+
+// about as many chunks as a renderer looking at a corner of the region needs
+const SLOTS: [(usize, usize); 30] = {
+    let mut slots = [(0, 0); 30];
+    let mut i = 0;
+    while i < 30 {
+        slots[i] = (i % 6, i / 6);
+        i += 1;
+    }
+    slots
+};
+
+fn lazy(c: &mut Criterion) {
+    let bytes = include_bytes!("../tests/data/r.0.0.mca");
+
+    let mut group = c.benchmark_group("lazy");
+    group.sample_size(10);
+    group.bench_function("eager, 30 chunks", |b| {
+        b.iter(|| {
+            let region = Region::parse_bytes(bytes).unwrap();
+            for (x, z) in SLOTS {
+                black_box(region.get_chunk(x, z));
+            }
+        })
+    });
+    group.bench_function("lazy, 30 chunks", |b| {
+        b.iter(|| {
+            let region = LazyRegion::parse_bytes(bytes).unwrap();
+            for (x, z) in SLOTS {
+                black_box(region.get_chunk(x, z));
+            }
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, lazy);
+criterion_main!(benches);
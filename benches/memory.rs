@@ -0,0 +1,103 @@
+//! Prints how much heap memory parsed chunks take, and how much decoding
+//! them allocates, rather than timing anything. Run with
+//! `cargo bench --bench memory`.
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    hint::black_box,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use mca_rs::{chunk::ChunkParseOptions, region::Region};
+
+struct Counting;
+
+static LIVE: AtomicUsize = AtomicUsize::new(0);
+// everything allocated, including what was freed since
+static TOTAL: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        LIVE.fetch_add(layout.size(), Ordering::Relaxed);
+        TOTAL.fetch_add(layout.size(), Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        LIVE.fetch_sub(layout.size(), Ordering::Relaxed);
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: Counting = Counting;
+
+fn main() {
+    let bytes = include_bytes!("../tests/data/r.0.0.mca");
+
+    let before = LIVE.load(Ordering::Relaxed);
+    let region = Region::parse_bytes(bytes).unwrap();
+    let region_bytes = LIVE.load(Ordering::Relaxed) - before;
+
+    let before = LIVE.load(Ordering::Relaxed);
+    let chunks: Vec<_> = (0..1024)
+        .filter_map(|i| region.get_chunk_raw(i % 32, i / 32))
+        .map(|raw| raw.decode().unwrap())
+        .collect();
+    let chunks_bytes = LIVE.load(Ordering::Relaxed) - before;
+
+    // like the regions at the edge of an explored world
+    let before = LIVE.load(Ordering::Relaxed);
+    let mut sparse = Region::parse_bytes(bytes).unwrap();
+    let mut kept = 0;
+    sparse.retain_chunks(|_, _, _| {
+        kept += 1;
+        kept <= 20
+    });
+    let sparse_bytes = LIVE.load(Ordering::Relaxed) - before;
+
+    let sections: usize = chunks.iter().map(|chunk| chunk.sections().len()).sum();
+    println!(
+        "region: {} KiB, {} bytes inline",
+        region_bytes / 1024,
+        size_of::<Region>()
+    );
+    println!(
+        "region with {} chunks: {} KiB",
+        sparse.count_chunks(),
+        sparse_bytes / 1024
+    );
+    black_box(sparse);
+    println!(
+        "{} chunks: {} KiB, {} bytes per section",
+        chunks.len(),
+        chunks_bytes / 1024,
+        chunks_bytes / sections
+    );
+    black_box(chunks);
+
+    // decompressing into one buffer instead of a new one for each chunk
+    let options = ChunkParseOptions::default();
+    let raw_chunks: Vec<_> = (0..1024)
+        .filter_map(|i| region.get_chunk_raw(i % 32, i / 32))
+        .collect();
+    let before = TOTAL.load(Ordering::Relaxed);
+    for raw in &raw_chunks {
+        black_box(raw.decode_with(&options).unwrap());
+    }
+    let fresh_bytes = TOTAL.load(Ordering::Relaxed) - before;
+
+    let mut buffer = Vec::new();
+    let before = TOTAL.load(Ordering::Relaxed);
+    for raw in &raw_chunks {
+        black_box(raw.decode_with_buffer(&options, &mut buffer).unwrap());
+    }
+    let reused_bytes = TOTAL.load(Ordering::Relaxed) - before;
+
+    println!(
+        "allocated while decoding: {} MiB with a new buffer per chunk, {} MiB reusing one",
+        fresh_bytes >> 20,
+        reused_bytes >> 20
+    );
+    black_box(region);
+}
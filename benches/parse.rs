@@ -0,0 +1,34 @@
+use std::hint::black_box;
+
+use criterion::{Criterion, criterion_group, criterion_main};
+use mca_rs::{
+    chunk::parse_chunk,
+    region::{Region, RegionParseOptions},
+};
+
+fn parse(c: &mut Criterion) {
+    let bytes = include_bytes!("../tests/data/r.0.0.mca");
+    let interned = RegionParseOptions {
+        intern_blocks: true,
+        ..RegionParseOptions::default()
+    };
+
+    let mut group = c.benchmark_group("parse");
+    group.sample_size(10);
+    group.bench_function("region", |b| {
+        b.iter(|| black_box(Region::parse_bytes(bytes).unwrap()))
+    });
+    group.bench_function("region, interned", |b| {
+        b.iter(|| black_box(Region::parse_bytes_with(bytes, &interned).unwrap()))
+    });
+    group.finish();
+
+    let region = Region::parse_bytes(bytes).unwrap();
+    let chunk = region.get_chunk(5, 9).unwrap().to_bytes();
+    c.bench_function("parse chunk", |b| {
+        b.iter(|| black_box(parse_chunk(&chunk).unwrap()))
+    });
+}
+
+criterion_group!(benches, parse);
+criterion_main!(benches);
@@ -0,0 +1,34 @@
+//! Renders a region file from above, shaded like vanilla maps, into a PPM
+//! image: `cargo run --example render_map --features map_colors -- r.0.0.mca map.ppm`
+
+use std::{env, fs};
+
+use mca_rs::{
+    Region,
+    map_color::{Brightness, map_color_of},
+};
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    let [_, input, output] = &args[..] else {
+        eprintln!("usage: render_map <region file> <output.ppm>");
+        return;
+    };
+    let region = Region::open(input).expect("failed to read the region");
+    let map = region.surface_map(|block| map_color_of(block).is_some());
+
+    let mut image = b"P6 512 512 255\n".to_vec();
+    for (i, sample) in map.iter().enumerate() {
+        // lighter where the ground rises going south, darker where it falls
+        let north = (i >= 512).then(|| map[i - 512]).flatten();
+        let brightness = match (sample, north) {
+            (Some(here), Some(north)) if here.y > north.y => Brightness::High,
+            (Some(here), Some(north)) if here.y < north.y => Brightness::Low,
+            _ => Brightness::Normal,
+        };
+        let color = sample.and_then(|sample| map_color_of(sample.block));
+        let [r, g, b, _] = color.map_or([0; 4], |color| color.rgba(brightness));
+        image.extend([r, g, b]);
+    }
+    fs::write(output, image).expect("failed to write the image");
+}
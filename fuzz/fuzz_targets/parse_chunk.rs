@@ -0,0 +1,9 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use mca_rs::{chunk::parse_chunk, legacy::parse_alpha_chunk};
+
+fuzz_target!(|bytes: &[u8]| {
+    let _ = parse_chunk(bytes);
+    let _ = parse_alpha_chunk(bytes);
+});
@@ -0,0 +1,20 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use mca_rs::{
+    entity::EntityRegion,
+    lazy::LazyRegion,
+    region::{Region, fragmentation_stats, validate_header},
+};
+
+fuzz_target!(|bytes: &[u8]| {
+    let _ = Region::parse_bytes(bytes);
+    let _ = EntityRegion::parse_bytes(bytes);
+    let _ = validate_header(bytes);
+    let _ = fragmentation_stats(bytes);
+    if let Ok(region) = LazyRegion::parse_bytes(bytes) {
+        for (x, z) in (0..32).flat_map(|x| (0..32).map(move |z| (x, z))) {
+            let _ = region.get_chunk(x, z);
+        }
+    }
+});
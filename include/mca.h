@@ -0,0 +1,92 @@
+/*
+ * C bindings for mca-rs, generated by cbindgen from src/ffi.rs.
+ * Regenerate with: cbindgen --config cbindgen.toml --output include/mca.h
+ *
+ * Thread safety: none of the objects handed out by this library are
+ * thread-safe. A region or chunk, and every pointer borrowed from it, must
+ * only be used from one thread at a time, and must not be freed while
+ * another thread is using it.
+ *
+ * Building: the library is built as a Rust library only, so crates
+ * depending on it don't build a shared one too. Build the shared library
+ * with: cargo rustc --release --lib --features ffi --crate-type cdylib
+ *
+ * Ownership: regions and chunks returned through `mca_region_parse` and
+ * `mca_chunk_parse` are owned by the caller and released with the matching
+ * `_free` function. Chunks returned by `mca_region_get_chunk` and block names
+ * in `mca_block_info` are borrowed and live as long as their owner.
+ */
+
+#ifndef MCA_H
+#define MCA_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+typedef enum mca_error {
+  MCA_ERROR_OK = 0,
+  MCA_ERROR_NULL_POINTER = 1,
+  MCA_ERROR_OUT_OF_BOUNDS = 2,
+  MCA_ERROR_CHUNK_NOT_FOUND = 3,
+  MCA_ERROR_INPUT_TOO_SHORT = 4,
+  MCA_ERROR_INPUT_INVALID_SIZE = 5,
+  MCA_ERROR_UNSUPPORTED_COMPRESSION = 6,
+  MCA_ERROR_DECOMPRESSION_FAILED = 7,
+  MCA_ERROR_PARSE_FAILED = 8,
+  MCA_ERROR_INVALID_FIELD = 9,
+  MCA_ERROR_INVALID_PALETTE = 10,
+  MCA_ERROR_INVALID_SECTION_DATA = 11,
+  MCA_ERROR_PANIC = 12,
+  MCA_ERROR_LIMIT_EXCEEDED = 13,
+  MCA_ERROR_INVALID_HEIGHTMAP = 14,
+  MCA_ERROR_SECTOR_OVERLAP = 15,
+} mca_error;
+
+typedef struct mca_chunk mca_chunk;
+
+typedef struct mca_region mca_region;
+
+/**
+ * Borrowed view of a block. `name` is not NUL-terminated and stays valid
+ * for as long as the chunk the block was read from.
+ */
+typedef struct mca_block_info {
+  const uint8_t *name;
+  size_t name_len;
+  size_t property_count;
+} mca_block_info;
+
+/**
+ * Takes the error as an integer, since C may pass values that aren't an
+ * `mca_error`. Those get a message saying so.
+ */
+const char *mca_error_message(uint32_t err);
+
+enum mca_error mca_region_parse(const uint8_t *data, size_t len, struct mca_region **out);
+
+void mca_region_free(struct mca_region *region);
+
+uint16_t mca_region_count_chunks(const struct mca_region *region);
+
+/**
+ * The returned chunk is borrowed from the region and must not be freed.
+ */
+enum mca_error mca_region_get_chunk(const struct mca_region *region,
+                                    size_t x,
+                                    size_t z,
+                                    const struct mca_chunk **out);
+
+enum mca_error mca_chunk_parse(const uint8_t *data, size_t len, struct mca_chunk **out);
+
+/**
+ * Only chunks returned by `mca_chunk_parse` may be freed.
+ */
+void mca_chunk_free(struct mca_chunk *chunk);
+
+enum mca_error mca_chunk_get_block(const struct mca_chunk *chunk,
+                                   uint8_t x,
+                                   int32_t y,
+                                   uint8_t z,
+                                   struct mca_block_info *out);
+
+#endif  /* MCA_H */
@@ -0,0 +1,46 @@
+use std::{fmt, ops::Range};
+
+use thiserror::Error;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Axis {
+    X,
+    Y,
+    Z,
+}
+
+impl fmt::Display for Axis {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Axis::X => "x",
+            Axis::Y => "y",
+            Axis::Z => "z",
+        })
+    }
+}
+
+/// Returned by setters when a coordinate falls outside of what the target
+/// covers. Getters signal the same condition by returning `None`.
+#[derive(Error, Debug, Clone, PartialEq, Eq, Hash)]
+#[error("{axis} coordinate {value} is out of bounds, expected {}..{}", range.start, range.end)]
+pub struct OutOfBounds {
+    pub axis: Axis,
+    pub value: i64,
+    pub range: Range<i64>,
+}
+
+impl OutOfBounds {
+    pub(crate) fn check(
+        axis: Axis,
+        value: impl Into<i64>,
+        range: Range<impl Into<i64>>,
+    ) -> Result<(), Self> {
+        let value = value.into();
+        let range = range.start.into()..range.end.into();
+        if range.contains(&value) {
+            Ok(())
+        } else {
+            Err(OutOfBounds { axis, value, range })
+        }
+    }
+}
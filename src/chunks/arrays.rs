@@ -0,0 +1,70 @@
+//! Blocks as [`ndarray`] arrays of palette indices, for numerical analysis.
+//!
+//! The axes are `y`, `z`, `x`, the order blocks are stored in, so
+//! `array[[y, z, x]]` is the palette index of the block at `(x, y, z)`.
+
+use std::collections::HashMap;
+
+use ndarray::Array3;
+
+use crate::chunks::{block::Block, chunk::Chunk, section::Section};
+
+/// A palette shared by several sections, without duplicates.
+#[derive(Default)]
+struct MergedPalette<'a> {
+    blocks: Vec<&'a Block>,
+    indices: HashMap<&'a Block, u32>,
+}
+
+impl<'a> MergedPalette<'a> {
+    /// Appends the merged palette index of every block of `section`, in `y`,
+    /// `z`, `x` order, to `out`.
+    fn push_section(&mut self, section: &'a Section, out: &mut Vec<u32>) {
+        // palette entries are only merged once a block uses them
+        let mut remap: Vec<Option<u32>> = vec![None; section.palette.len()];
+        out.extend(section.iter_palette_indices().map(|index| {
+            *remap[index].get_or_insert_with(|| {
+                let block = &*section.palette[index];
+                *self.indices.entry(block).or_insert_with(|| {
+                    self.blocks.push(block);
+                    (self.blocks.len() - 1) as u32
+                })
+            })
+        }));
+    }
+}
+
+impl Section {
+    /// The palette index of every block as a 16×16×16 array indexed by `y`,
+    /// `z`, `x`, and the palette it indexes. The palette only holds the
+    /// blocks the section uses, each once, in the order they first appear.
+    pub fn to_index_array(&self) -> (Array3<u16>, Vec<&Block>) {
+        let mut palette = MergedPalette::default();
+        let mut indices = Vec::with_capacity(4096);
+        palette.push_section(self, &mut indices);
+        // a section has at most 4096 distinct blocks
+        let indices = indices.into_iter().map(|index| index as u16).collect();
+        let array = Array3::from_shape_vec((16, 16, 16), indices).unwrap();
+        (array, palette.blocks)
+    }
+}
+
+impl Chunk {
+    /// Like [`Section::to_index_array`], but for every section of the chunk
+    /// stacked from the bottom up, with one palette for all of them. The
+    /// array is indexed by `y`, `z`, `x` with `y` counted from the bottom of
+    /// the lowest section, i.e. `16 * chunk.section_y_range().start`.
+    ///
+    /// The indices are `u32`, since sections with distinct blocks can add
+    /// up to more than a `u16` can index.
+    pub fn to_index_array(&self) -> (Array3<u32>, Vec<&Block>) {
+        let mut palette = MergedPalette::default();
+        let mut indices = Vec::with_capacity(self.sections.len() * 4096);
+        for (_, section) in self.sections() {
+            palette.push_section(section, &mut indices);
+        }
+        let shape = (self.sections.len() * 16, 16, 16);
+        let array = Array3::from_shape_vec(shape, indices).unwrap();
+        (array, palette.blocks)
+    }
+}
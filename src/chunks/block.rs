@@ -1,17 +1,572 @@
-use nbt_rs::types::{NbtCompound, NbtString};
+#[cfg(not(feature = "sync"))]
+use std::cell::OnceCell;
+#[cfg(feature = "sync")]
+use std::sync::OnceLock;
+use std::{
+    cmp::Ordering,
+    collections::HashSet,
+    fmt,
+    hash::{Hash, Hasher},
+    mem,
+    str::FromStr,
+};
 
-#[derive(Debug, Clone, PartialEq, PartialOrd)]
+use nbt_rs::{
+    parse_nbt,
+    traits::NbtSerialize,
+    types::{NbtCompound, NbtString, NbtTag},
+};
+use thiserror::Error;
+
+/// The pointer blocks and biomes are shared through. With the `sync` feature
+/// it's an [`std::sync::Arc`], so chunks and regions can be sent and shared
+/// between threads, otherwise it's the cheaper [`std::rc::Rc`].
+#[cfg(not(feature = "sync"))]
+pub type Shared<T> = std::rc::Rc<T>;
+#[cfg(feature = "sync")]
+pub type Shared<T> = std::sync::Arc<T>;
+
+/// A block state. Blocks are equal if their names and properties are, in
+/// whatever order the properties are stored, since the game doesn't keep
+/// them in any particular one. Ordering and hashing ignore it the same way.
+#[derive(Debug, Clone)]
 pub struct Block {
-    pub(crate) name: NbtString,
+    // shared by the blocks of a parse, see `BlockInterner::block`, and always
+    // short enough for an nbt string
+    pub(crate) name: Shared<str>,
     pub(crate) properties: Option<NbtCompound>,
+    // the names of `properties`, sorted, as compounds don't lend out their
+    // keys
+    keys: Box<[NbtString]>,
 }
 
 impl Block {
-    pub fn get_name(&self) -> &NbtString {
+    pub fn new(name: NbtString, properties: Option<NbtCompound>) -> Self {
+        Self::with_shared_name(Shared::from(&*name), properties)
+    }
+
+    /// Like [`Block::new`], for a name that's already shared and known to be
+    /// short enough for an nbt string.
+    pub(crate) fn with_shared_name(name: Shared<str>, properties: Option<NbtCompound>) -> Self {
+        let keys = properties.as_ref().map_or_else(Box::default, sorted_keys);
+        Self {
+            name,
+            properties,
+            keys,
+        }
+    }
+
+    pub(crate) fn air() -> Self {
+        Self::with_shared_name(Shared::from("minecraft:air"), None)
+    }
+
+    pub fn get_name(&self) -> &str {
         &self.name
     }
 
+    pub(crate) fn nbt_name(&self) -> NbtString {
+        self.name.to_string().try_into().unwrap()
+    }
+
     pub fn get_properties(&self) -> &Option<NbtCompound> {
         &self.properties
     }
+
+    /// The value of a property, which the game always stores as a string.
+    /// Returns `None` if the block has no such property, or it isn't a
+    /// string.
+    pub fn get_property(&self, key: &str) -> Option<&str> {
+        match self.properties.as_ref()?.get(key)? {
+            NbtTag::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Like [`Block::get_property`], but parses `true` or `false`, as used by
+    /// e.g. `waterlogged` and `powered`.
+    pub fn get_property_bool(&self, key: &str) -> Option<bool> {
+        match self.get_property(key)? {
+            "true" => Some(true),
+            "false" => Some(false),
+            _ => None,
+        }
+    }
+
+    /// Like [`Block::get_property`], but parses a number, as used by e.g.
+    /// `age`, `power` and `level`.
+    pub fn get_property_int(&self, key: &str) -> Option<i32> {
+        self.get_property(key)?.parse().ok()
+    }
+
+    /// The block's properties with their values typed by
+    /// [`PropertyValue::infer`], sorted by name. Properties that aren't
+    /// strings are left out.
+    pub fn properties_typed(&self) -> impl Iterator<Item = (&str, PropertyValue<'_>)> {
+        self.sorted_properties()
+            .filter_map(|(key, value)| match value {
+                NbtTag::String(value) => Some((&**key, PropertyValue::infer(value))),
+                _ => None,
+            })
+    }
+
+    /// Whether the block has the property with the value, compared as
+    /// [`PropertyValue::infer`] types it, so `age=7` matches
+    /// `PropertyValue::Int(7)` but not `PropertyValue::Str("7")`.
+    pub fn matches(&self, key: &str, value: &PropertyValue) -> bool {
+        self.get_property(key).map(PropertyValue::infer).as_ref() == Some(value)
+    }
+
+    /// The names of the block's properties, sorted.
+    pub fn property_keys(&self) -> &[NbtString] {
+        &self.keys
+    }
+
+    /// The properties in the order of their names, for comparing them
+    /// regardless of the order they're stored in.
+    pub(crate) fn sorted_properties(&self) -> impl Iterator<Item = (&NbtString, &NbtTag)> {
+        let properties = self.properties.as_ref();
+        // there are only keys if there are properties
+        (self.keys.iter()).map(move |key| (key, properties.unwrap().get(key).unwrap()))
+    }
+
+    /// Whether this is any of the air blocks, including cave and void air.
+    pub fn is_air(&self) -> bool {
+        matches!(
+            &*self.name,
+            "minecraft:air" | "minecraft:cave_air" | "minecraft:void_air"
+        )
+    }
+
+    /// Whether this is water or lava, flowing or not, which the game tells
+    /// apart by the `level` property rather than the name. Bubble columns
+    /// count as water. Waterlogged blocks don't, see
+    /// [`Block::is_waterlogged`].
+    pub fn is_fluid(&self) -> bool {
+        matches!(
+            &*self.name,
+            "minecraft:water" | "minecraft:lava" | "minecraft:bubble_column"
+        )
+    }
+
+    /// Whether the block's `waterlogged` property is `true`.
+    pub fn is_waterlogged(&self) -> bool {
+        self.get_property_bool("waterlogged") == Some(true)
+    }
+}
+
+/// The names of the properties, sorted. The compound is only copied here,
+/// once for every block made, since it can't be looked into otherwise.
+fn sorted_keys(properties: &NbtCompound) -> Box<[NbtString]> {
+    let mut keys: Vec<NbtString> = Vec::<(NbtString, NbtTag)>::from(properties.clone())
+        .into_iter()
+        .map(|(key, _)| key)
+        .collect();
+    keys.sort();
+    keys.into()
+}
+
+/// Air to lend out where no block is stored, see [`Chunk::get_or_air`].
+/// With the `sync` feature it's one block for the whole process, otherwise
+/// blocks can't be in a static, so each chunk makes its own when it's
+/// first asked for.
+///
+/// [`Chunk::get_or_air`]: crate::chunk::Chunk::get_or_air
+#[derive(Default)]
+pub(crate) struct LazyAir(#[cfg(not(feature = "sync"))] OnceCell<Block>);
+
+impl LazyAir {
+    pub(crate) fn get(&self) -> &Block {
+        #[cfg(feature = "sync")]
+        {
+            static AIR: OnceLock<Block> = OnceLock::new();
+            AIR.get_or_init(Block::air)
+        }
+        #[cfg(not(feature = "sync"))]
+        self.0.get_or_init(Block::air)
+    }
+}
+
+// not part of what the chunk holds, so it's left out of cloning, comparing
+// and printing chunks
+impl Clone for LazyAir {
+    fn clone(&self) -> Self {
+        Self::default()
+    }
+}
+
+impl PartialEq for LazyAir {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+impl PartialOrd for LazyAir {
+    fn partial_cmp(&self, _: &Self) -> Option<Ordering> {
+        Some(Ordering::Equal)
+    }
+}
+
+impl fmt::Debug for LazyAir {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("LazyAir")
+    }
+}
+
+/// The value of a block property with its type, see
+/// [`Block::properties_typed`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PropertyValue<'a> {
+    Bool(bool),
+    Int(i32),
+    Str(&'a str),
+}
+
+impl<'a> PropertyValue<'a> {
+    /// Types a value as the game stores it: `true` and `false` are booleans,
+    /// digits that fit an `i32` are numbers, and anything else, like `-1` or
+    /// `True`, stays a string. `0` and `1` are numbers, never booleans.
+    pub fn infer(value: &'a str) -> Self {
+        match value {
+            "true" => PropertyValue::Bool(true),
+            "false" => PropertyValue::Bool(false),
+            _ if !value.is_empty() && value.bytes().all(|byte| byte.is_ascii_digit()) => value
+                .parse()
+                .map_or(PropertyValue::Str(value), PropertyValue::Int),
+            _ => PropertyValue::Str(value),
+        }
+    }
+}
+
+impl PartialEq for Block {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+            && self.properties.is_some() == other.properties.is_some()
+            && self.sorted_properties().eq(other.sorted_properties())
+    }
+}
+
+// property values are strings in practice, the float tags that would break
+// this never show up
+impl Eq for Block {}
+
+impl PartialOrd for Block {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Blocks are ordered by name, then by their properties sorted by name.
+/// Sorting blocks gives the same order whatever order their properties are
+/// stored in. Values are compared by their tag type first, so a string
+/// never equals a number, like with `==`.
+impl Ord for Block {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.name.cmp(&other.name))
+            .then_with(|| self.properties.is_some().cmp(&other.properties.is_some()))
+            .then_with(|| {
+                (self.sorted_properties().zip(other.sorted_properties()))
+                    .map(|((a_key, a), (b_key, b))| a_key.cmp(b_key).then_with(|| cmp_tags(a, b)))
+                    .find(|ordering| ordering.is_ne())
+                    .unwrap_or_else(|| self.keys.len().cmp(&other.keys.len()))
+            })
+    }
+}
+
+/// Orders tags by type, in the order of their ids, then by value.
+fn cmp_tags(a: &NbtTag, b: &NbtTag) -> Ordering {
+    let rank = |tag: &NbtTag| match tag {
+        NbtTag::End => 0,
+        NbtTag::Byte(_) => 1,
+        NbtTag::Short(_) => 2,
+        NbtTag::Int(_) => 3,
+        NbtTag::Long(_) => 4,
+        NbtTag::Float(_) => 5,
+        NbtTag::Double(_) => 6,
+        NbtTag::ByteArray(_) => 7,
+        NbtTag::String(_) => 8,
+        NbtTag::List(_) => 9,
+        NbtTag::Compound(_) => 10,
+        NbtTag::IntArray(_) => 11,
+        NbtTag::LongArray(_) => 12,
+    };
+    rank(a).cmp(&rank(b)).then_with(|| match (a, b) {
+        (NbtTag::Byte(a), NbtTag::Byte(b)) => a.cmp(b),
+        (NbtTag::Short(a), NbtTag::Short(b)) => a.cmp(b),
+        (NbtTag::Int(a), NbtTag::Int(b)) => a.cmp(b),
+        (NbtTag::Long(a), NbtTag::Long(b)) => a.cmp(b),
+        (NbtTag::Float(a), NbtTag::Float(b)) => a.total_cmp(b),
+        (NbtTag::Double(a), NbtTag::Double(b)) => a.total_cmp(b),
+        (NbtTag::String(a), NbtTag::String(b)) => a.cmp(b),
+        // never seen in block states, the text tells them apart well enough
+        (a, b) => a.to_string().cmp(&b.to_string()),
+    })
+}
+
+impl Hash for Block {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.name.hash(state);
+        self.properties.is_some().hash(state);
+        for (key, value) in self.sorted_properties() {
+            key.hash(state);
+            hash_tag(value, state);
+        }
+    }
+}
+
+/// Hashes a property value where it is. Equal tags hash the same, floats
+/// and the tags block states never hold only by their type.
+fn hash_tag<H: Hasher>(tag: &NbtTag, state: &mut H) {
+    mem::discriminant(tag).hash(state);
+    match tag {
+        NbtTag::Byte(value) => value.hash(state),
+        NbtTag::Short(value) => value.hash(state),
+        NbtTag::Int(value) => value.hash(state),
+        NbtTag::Long(value) => value.hash(state),
+        NbtTag::String(value) => value.hash(state),
+        _ => {}
+    }
+}
+
+/// Writes the block state like the game's commands take it, e.g.
+/// `minecraft:oak_log[axis=y]`, with the properties sorted by name.
+impl fmt::Display for Block {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.name)?;
+        let Some(properties) = &self.properties else {
+            return Ok(());
+        };
+
+        f.write_str("[")?;
+        for (i, key) in self.keys.iter().enumerate() {
+            if i > 0 {
+                f.write_str(",")?;
+            }
+            let value = properties.get(key).unwrap();
+            // strings without the quotes the tag's own format adds
+            match value {
+                NbtTag::String(value) => write!(f, "{key}={value}")?,
+                value => write!(f, "{key}={value}")?,
+            }
+        }
+        f.write_str("]")
+    }
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum BlockParseError {
+    #[error("the block name is empty")]
+    EmptyName,
+
+    #[error("the properties aren't closed with `]`")]
+    UnclosedProperties,
+
+    #[error("unexpected {0:?} after the properties")]
+    TrailingCharacters(String),
+
+    #[error("the property {0:?} has no value")]
+    MissingValue(String),
+
+    #[error("a property has an empty name")]
+    EmptyKey,
+
+    #[error("the property {0:?} has an empty value")]
+    EmptyValue(String),
+
+    #[error("the property {0:?} is given more than once")]
+    DuplicateKey(String),
+
+    #[error("{0:?} is too long for an nbt string")]
+    TooLong(String),
+}
+
+/// Reads the format [`Block`]'s `Display` writes. Properties keep the order
+/// they're given in.
+impl FromStr for Block {
+    type Err = BlockParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let string = |value: &str| {
+            NbtString::try_from(value.to_owned())
+                .map_err(|_| BlockParseError::TooLong(value.to_owned()))
+        };
+
+        let (name, properties) = match s.split_once('[') {
+            Some((name, rest)) => {
+                let (properties, trailing) = rest
+                    .split_once(']')
+                    .ok_or(BlockParseError::UnclosedProperties)?;
+                if !trailing.is_empty() {
+                    return Err(BlockParseError::TrailingCharacters(trailing.to_owned()));
+                }
+                (name, Some(properties))
+            }
+            None => (s, None),
+        };
+        if name.is_empty() {
+            return Err(BlockParseError::EmptyName);
+        }
+
+        let properties = match properties {
+            Some(properties) => {
+                let mut parsed: Vec<(NbtString, NbtString)> = Vec::new();
+                for property in properties.split(',').filter(|_| !properties.is_empty()) {
+                    let (key, value) = property
+                        .split_once('=')
+                        .ok_or_else(|| BlockParseError::MissingValue(property.to_owned()))?;
+                    if key.is_empty() {
+                        return Err(BlockParseError::EmptyKey);
+                    }
+                    if value.is_empty() {
+                        return Err(BlockParseError::EmptyValue(key.to_owned()));
+                    }
+                    if parsed.iter().any(|(known, _)| *known == key) {
+                        return Err(BlockParseError::DuplicateKey(key.to_owned()));
+                    }
+                    parsed.push((string(key)?, string(value)?));
+                }
+                Some(string_compound(&parsed))
+            }
+            None => None,
+        };
+        Ok(Block::new(string(name)?, properties))
+    }
+}
+
+/// Picks blocks by name and some of their properties, see
+/// [`Region::count_blocks`](crate::region::Region::count_blocks). Written
+/// like a block, `minecraft:oak_log[axis=y]` picks upright oak logs, whatever
+/// their other properties. Names without a namespace get `minecraft:`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BlockSelector {
+    name: String,
+    properties: Vec<(String, String)>,
+}
+
+impl BlockSelector {
+    /// Picks every block called `name`.
+    pub fn new(name: &str) -> Self {
+        let name = match name.contains(':') {
+            true => name.to_owned(),
+            false => format!("minecraft:{name}"),
+        };
+        Self {
+            name,
+            properties: Vec::new(),
+        }
+    }
+
+    /// Only picks blocks that also have the property with the value.
+    pub fn property(mut self, key: &str, value: &str) -> Self {
+        self.properties.push((key.to_owned(), value.to_owned()));
+        self
+    }
+
+    pub fn matches(&self, block: &Block) -> bool {
+        *block.name == *self.name
+            && (self.properties.iter()).all(|(key, value)| block.get_property(key) == Some(value))
+    }
+}
+
+/// Reads the format [`Block`]'s `Display` writes, with the properties that
+/// have to match.
+impl FromStr for BlockSelector {
+    type Err = BlockParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let block: Block = s.parse()?;
+        let selector = BlockSelector::new(&block.name);
+        Ok(block
+            .property_keys()
+            .iter()
+            .fold(selector, |selector, key| {
+                let value = block.get_property(key).unwrap_or_default();
+                selector.property(key, value)
+            }))
+    }
+}
+
+/// A compound of string tags in the given order, which building it from a
+/// `HashMap` would lose.
+pub(crate) fn string_compound(entries: &[(NbtString, NbtString)]) -> NbtCompound {
+    // a nameless root compound, as `parse_nbt` expects it
+    let mut nbt = vec![10, 0, 0];
+    for (key, value) in entries {
+        nbt.push(8);
+        key.serialize_nbt_payload(&mut nbt);
+        value.serialize_nbt_payload(&mut nbt);
+    }
+    nbt.push(0);
+    // only ever fed what was just written
+    parse_nbt(&nbt).unwrap().1
+}
+
+/// Hands out a single shared [`Block`] for every distinct block state, so
+/// equal blocks can be told apart from different ones with [`Shared::ptr_eq`].
+/// Blocks are the same if they're equal, i.e. regardless of the order of
+/// their properties.
+///
+/// Block names are shared as well, also between blocks that differ in their
+/// properties.
+#[derive(Debug, Clone, Default)]
+pub struct BlockInterner {
+    blocks: HashSet<Shared<Block>>,
+    names: HashSet<Shared<str>>,
+    // set for the interner of a single chunk parsed without one
+    names_only: bool,
+}
+
+impl BlockInterner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The shared block equal to `block`, which becomes the shared one if
+    /// there's none yet.
+    pub fn intern(&mut self, mut block: Block) -> Shared<Block> {
+        if let Some(shared) = self.blocks.get(&block) {
+            return shared.clone();
+        }
+        block.name = self.name(&block.name);
+        let shared = Shared::new(block);
+        self.blocks.insert(shared.clone());
+        shared
+    }
+
+    /// Only shares block names, which is what parsing a chunk without an
+    /// interner does.
+    pub(crate) fn names_only() -> Self {
+        Self {
+            names_only: true,
+            ..Self::default()
+        }
+    }
+
+    /// A block with the shared name equal to `name`, and the shared block if
+    /// this isn't [`BlockInterner::names_only`].
+    pub(crate) fn block(&mut self, name: &str, properties: Option<NbtCompound>) -> Shared<Block> {
+        let block = Block::with_shared_name(self.name(name), properties);
+        if self.names_only {
+            Shared::new(block)
+        } else {
+            self.intern(block)
+        }
+    }
+
+    fn name(&mut self, name: &str) -> Shared<str> {
+        if let Some(shared) = self.names.get(name) {
+            return shared.clone();
+        }
+        let shared = Shared::<str>::from(name);
+        self.names.insert(shared.clone());
+        shared
+    }
+
+    /// How many distinct blocks were interned.
+    pub fn len(&self) -> usize {
+        self.blocks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.blocks.is_empty()
+    }
 }
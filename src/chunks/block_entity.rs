@@ -0,0 +1,19 @@
+use nbt_rs::types::{NbtCompound, NbtString};
+
+/// A block with extra data, like a chest or a sign, in world coordinates.
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+pub struct BlockEntity {
+    pub id: NbtString,
+    pub x: i32,
+    pub y: i32,
+    pub z: i32,
+    /// The whole compound as stored, including the fields above.
+    pub data: NbtCompound,
+}
+
+impl BlockEntity {
+    // what block entities are sorted and looked up by
+    pub(crate) fn key(&self) -> (i32, i32, i32) {
+        (self.y, self.z, self.x)
+    }
+}
@@ -0,0 +1,224 @@
+use std::{collections::BTreeMap, ops::Range};
+
+use thiserror::Error;
+
+use crate::{
+    bounds::OutOfBounds,
+    chunks::{
+        block::{Block, LazyAir, Shared},
+        block_entity::BlockEntity,
+        chunk::Chunk,
+        section::Section,
+        status::ChunkStatus,
+    },
+    validate::SECTION_Y_LIMITS,
+};
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum BuildError {
+    #[error("the y range {0:?} is empty, not aligned to sections or too large")]
+    InvalidYRange(Range<i32>),
+
+    #[error("the section at y {0} is outside of the chunk's y range")]
+    SectionOutOfRange(i32),
+
+    #[error("the section at y {0} was added more than once")]
+    DuplicateSection(i32),
+
+    #[error("a block entity is outside of the chunk: {0}")]
+    BlockEntityOutOfBounds(OutOfBounds),
+
+    #[error("more than one block entity was added at ({}, {}, {})", .0.0, .0.1, .0.2)]
+    DuplicateBlockEntity((i32, i32, i32)),
+}
+
+/// Assembles a [`Chunk`] from scratch. Sections that aren't given are
+/// filled with air.
+///
+/// ```
+/// use std::collections::HashMap;
+///
+/// use mca_rs::{
+///     block::Block, block_entity::BlockEntity, builder::ChunkBuilder, chunk::parse_chunk,
+///     section::Section, status::ChunkStatus,
+/// };
+///
+/// let block = |name: &str| Block::new(name.to_owned().try_into().unwrap(), None);
+/// let mut bottom = Section::filled(block("minecraft:dirt"));
+/// for x in 0..16 {
+///     for z in 0..16 {
+///         bottom.set_block(x, 0, z, block("minecraft:bedrock"))?;
+///         bottom.set_block(x, 15, z, block("minecraft:grass_block"))?;
+///     }
+/// }
+/// bottom.set_block(3, 14, 5, block("minecraft:chest"))?;
+/// // the id and position are written into the compound
+/// let chest = BlockEntity {
+///     id: "minecraft:chest".to_owned().try_into().unwrap(),
+///     x: 3,
+///     y: -50,
+///     z: 5,
+///     data: HashMap::new().into(),
+/// };
+///
+/// let chunk = ChunkBuilder::new(0, 0)
+///     .y_range(-64..320)
+///     .data_version(3465)
+///     .status(ChunkStatus::Full)
+///     .section(-4, bottom)
+///     .block_entity(chest)
+///     .compute_heightmaps()
+///     .build()?;
+///
+/// let chunk = parse_chunk(&chunk.to_bytes())?;
+/// assert_eq!(chunk.get(0, -64, 0).unwrap().get_name(), "minecraft:bedrock");
+/// assert_eq!(chunk.get(0, -49, 0).unwrap().get_name(), "minecraft:grass_block");
+/// assert!(chunk.get(0, -48, 0).unwrap().is_air());
+/// let chest = chunk.get_block_entity(3, -50, 5).unwrap();
+/// assert_eq!(&*chest.id, "minecraft:chest");
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct ChunkBuilder {
+    x_pos: i32,
+    z_pos: i32,
+    y_range: Range<i32>,
+    data_version: i32,
+    status: ChunkStatus,
+    last_update: i64,
+    inhabited_time: i64,
+    sections: Vec<(i32, Section)>,
+    block_entities: Vec<BlockEntity>,
+    compute_heightmaps: bool,
+}
+
+impl ChunkBuilder {
+    /// Starts a fully generated chunk at the given chunk coordinates, with
+    /// the overworld's y range of -64..320 and no data version.
+    pub fn new(x: i32, z: i32) -> Self {
+        Self {
+            x_pos: x,
+            z_pos: z,
+            y_range: -64..320,
+            data_version: 0,
+            status: ChunkStatus::Full,
+            last_update: 0,
+            inhabited_time: 0,
+            sections: Vec::new(),
+            block_entities: Vec::new(),
+            compute_heightmaps: false,
+        }
+    }
+
+    /// The range of block y coordinates, both ends have to be multiples
+    /// of 16.
+    pub fn y_range(mut self, range: Range<i32>) -> Self {
+        self.y_range = range;
+        self
+    }
+
+    pub fn data_version(mut self, data_version: i32) -> Self {
+        self.data_version = data_version;
+        self
+    }
+
+    pub fn status(mut self, status: ChunkStatus) -> Self {
+        self.status = status;
+        self
+    }
+
+    pub fn last_update(mut self, last_update: i64) -> Self {
+        self.last_update = last_update;
+        self
+    }
+
+    pub fn inhabited_time(mut self, inhabited_time: i64) -> Self {
+        self.inhabited_time = inhabited_time;
+        self
+    }
+
+    /// Sets the section at section `y`, i.e. a block y divided by 16.
+    pub fn section(mut self, y: i32, section: Section) -> Self {
+        self.sections.push((y, section));
+        self
+    }
+
+    /// Adds a block entity, whose position is in world coordinates and has
+    /// to be within the chunk.
+    pub fn block_entity(mut self, block_entity: BlockEntity) -> Self {
+        self.block_entities.push(block_entity);
+        self
+    }
+
+    /// Computes the heightmaps of the built chunk, see
+    /// [`Chunk::compute_heightmaps`]. Without this the chunk has none.
+    pub fn compute_heightmaps(mut self) -> Self {
+        self.compute_heightmaps = true;
+        self
+    }
+
+    pub fn build(self) -> Result<Chunk, BuildError> {
+        let range = self.y_range;
+        if range.start >= range.end || range.start % 16 != 0 || range.end % 16 != 0 {
+            return Err(BuildError::InvalidYRange(range));
+        }
+        let section_range = range.start >> 4..range.end >> 4;
+        if section_range.start < SECTION_Y_LIMITS.start || section_range.end > SECTION_Y_LIMITS.end
+        {
+            return Err(BuildError::InvalidYRange(range));
+        }
+
+        let mut sections: Vec<Option<Section>> = section_range.clone().map(|_| None).collect();
+        for (y, section) in self.sections {
+            if !section_range.contains(&y) {
+                return Err(BuildError::SectionOutOfRange(y));
+            }
+            let slot = &mut sections[(y - section_range.start) as usize];
+            if slot.is_some() {
+                return Err(BuildError::DuplicateSection(y));
+            }
+            *slot = Some(section);
+        }
+
+        let air = Shared::new(Block::air());
+        let mut chunk = Chunk {
+            x_pos: self.x_pos,
+            y_pos: section_range.start,
+            z_pos: self.z_pos,
+            data_version: self.data_version,
+            status: self.status,
+            last_update: self.last_update,
+            inhabited_time: self.inhabited_time,
+            // the missing sections share one air block
+            sections: sections
+                .into_iter()
+                .map(|section| section.unwrap_or_else(|| Section::filled_shared(air.clone())))
+                .collect(),
+            heightmaps: BTreeMap::new(),
+            block_entities: Vec::new(),
+            entities: Vec::new(),
+            block_ticks: Vec::new(),
+            fluid_ticks: Vec::new(),
+            structure_references: Vec::new(),
+            structure_starts: Vec::new(),
+            below_zero_retrogen: None,
+            blending_data: None,
+            legacy_sections: None,
+            raw_nbt: None,
+            warnings: Vec::new(),
+            air: LazyAir::default(),
+        };
+        for block_entity in self.block_entities {
+            let pos = (block_entity.x, block_entity.y, block_entity.z);
+            let replaced = (chunk.set_block_entity(block_entity))
+                .map_err(BuildError::BlockEntityOutOfBounds)?;
+            if replaced.is_some() {
+                return Err(BuildError::DuplicateBlockEntity(pos));
+            }
+        }
+        if self.compute_heightmaps {
+            chunk.compute_heightmaps();
+        }
+        Ok(chunk)
+    }
+}
@@ -0,0 +1,619 @@
+//! A compact binary form of [`Chunk`], for caching parsed chunks.
+//!
+//! Every distinct block is written once to a table, which sections refer
+//! to through their palettes. The palette indices are copied as the section
+//! holds them, a byte or two per block, or packed like in the game's own
+//! format for sections read lazily whose blocks haven't been looked at.
+//! The layout starts with a format version and may change between versions
+//! of this crate, data written by another version is rejected with
+//! [`CacheError::UnsupportedVersion`].
+
+use std::collections::BTreeMap;
+
+use nbt_rs::{
+    get_field, parse_nbt,
+    traits::NbtSerialize,
+    types::{NbtCompound, NbtString, NbtTag},
+};
+use thiserror::Error;
+
+use crate::{
+    chunks::{
+        block::{Block, LazyAir, Shared},
+        block_entity::BlockEntity,
+        chunk::Chunk,
+        heightmap::{Heightmap, HeightmapKind},
+        packed,
+        section::{Decoded, Indices, Section},
+        status::ChunkStatus,
+        structure::{StructureStart, parse_start},
+        tick::ScheduledTick,
+        upgrade::BelowZeroRetrogen,
+    },
+    entity::{Entity, parse_entity},
+};
+
+const VERSION: u8 = 11;
+
+// sections keep up to twice as many palette entries as blocks before
+// dropping unused ones
+const MAX_PALETTE_LEN: usize = 8192;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum CacheError {
+    #[error("the cache format version {0} is not supported, expected {VERSION}")]
+    UnsupportedVersion(u8),
+
+    #[error("the cached data ends early")]
+    UnexpectedEnd,
+
+    #[error("the cached data is invalid: {0}")]
+    Invalid(&'static str),
+}
+
+impl Chunk {
+    /// Writes the chunk in the cache format, see [`crate::cache`].
+    pub fn to_cache_bytes(&self) -> Vec<u8> {
+        // most sections hold a byte per block and their occupancy
+        let mut out = Vec::with_capacity(self.sections.len() * 4700 + 4096);
+        out.push(VERSION);
+        for value in [self.x_pos, self.y_pos, self.z_pos, self.data_version] {
+            out.extend_from_slice(&value.to_be_bytes());
+        }
+        write_string(&mut out, &self.status.to_string());
+        for value in [self.last_update, self.inhabited_time] {
+            out.extend_from_slice(&value.to_be_bytes());
+        }
+
+        // every palette entry is kept, even unused ones, so the indices can
+        // be copied as the section holds them
+        let mut table = BlockTable::default();
+        let palettes: Vec<u32> = (self.sections.iter())
+            .flat_map(|section| &section.palette)
+            .map(|block| table.index(block))
+            .collect();
+
+        out.extend_from_slice(&table.len.to_be_bytes());
+        out.extend_from_slice(&table.encoded);
+
+        out.extend_from_slice(&(self.sections.len() as u32).to_be_bytes());
+        let mut palettes = palettes.iter();
+        for section in &self.sections {
+            out.extend_from_slice(&(section.palette.len() as u16).to_be_bytes());
+            for index in palettes.by_ref().take(section.palette.len()) {
+                out.extend_from_slice(&index.to_be_bytes());
+            }
+            write_indices(&mut out, section);
+            write_biomes(&mut out, &section.biomes);
+            for light in [&section.block_light, &section.sky_light] {
+                match light {
+                    Some(light) => {
+                        out.push(1);
+                        out.extend_from_slice(&light[..]);
+                    }
+                    None => out.push(0),
+                }
+            }
+        }
+
+        out.extend_from_slice(&(self.block_entities.len() as u32).to_be_bytes());
+        for block_entity in &self.block_entities {
+            write_compound(&mut out, &block_entity.data);
+        }
+        out.extend_from_slice(&(self.entities.len() as u32).to_be_bytes());
+        for entity in &self.entities {
+            write_compound(&mut out, &entity.data);
+        }
+        for ticks in [&self.block_ticks, &self.fluid_ticks] {
+            out.extend_from_slice(&(ticks.len() as u32).to_be_bytes());
+            for tick in ticks {
+                write_string(&mut out, &tick.target);
+                for value in [tick.x, tick.y, tick.z, tick.delay, tick.priority] {
+                    out.extend_from_slice(&value.to_be_bytes());
+                }
+            }
+        }
+        out.extend_from_slice(&(self.structure_references.len() as u32).to_be_bytes());
+        for (name, starts) in &self.structure_references {
+            write_string(&mut out, name);
+            out.extend_from_slice(&(starts.len() as u32).to_be_bytes());
+            for &(x, z) in starts {
+                out.extend_from_slice(&x.to_be_bytes());
+                out.extend_from_slice(&z.to_be_bytes());
+            }
+        }
+        out.extend_from_slice(&(self.structure_starts.len() as u32).to_be_bytes());
+        for start in &self.structure_starts {
+            write_compound(&mut out, &start.data);
+        }
+        match &self.below_zero_retrogen {
+            Some(retrogen) => {
+                out.push(1);
+                write_string(&mut out, &retrogen.target_status.to_string());
+                for word in retrogen.missing_bedrock {
+                    out.extend_from_slice(&word.to_be_bytes());
+                }
+            }
+            None => out.push(0),
+        }
+        match &self.blending_data {
+            Some(data) => write_compound(&mut out, data),
+            None => out.extend_from_slice(&0u32.to_be_bytes()),
+        }
+
+        out.push(self.heightmaps.len() as u8);
+        for (kind, heightmap) in &self.heightmaps {
+            let position = HeightmapKind::ALL.iter().position(|k| k == kind).unwrap();
+            out.push(position as u8);
+            for height in heightmap.heights() {
+                out.extend_from_slice(&height.to_be_bytes());
+            }
+        }
+        out
+    }
+
+    /// Reads a chunk written by [`Chunk::to_cache_bytes`].
+    pub fn from_cache_bytes(bytes: &[u8]) -> Result<Chunk, CacheError> {
+        let mut reader = Reader(bytes);
+        let version = reader.u8()?;
+        if version != VERSION {
+            return Err(CacheError::UnsupportedVersion(version));
+        }
+        let (x_pos, y_pos, z_pos) = (reader.i32()?, reader.i32()?, reader.i32()?);
+        let data_version = reader.i32()?;
+        let status = ChunkStatus::parse(&reader.string()?);
+        let (last_update, inhabited_time) = (reader.i64()?, reader.i64()?);
+
+        let table = (0..reader.u32()?)
+            .map(|_| reader.block().map(Shared::new))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let section_count = reader.u32()? as usize;
+        let mut sections = Vec::with_capacity(section_count.min(4096));
+        let mut biomes = Vec::new();
+        for _ in 0..section_count {
+            let palette_len = reader.u16()? as usize;
+            if !(1..=MAX_PALETTE_LEN).contains(&palette_len) {
+                return Err(CacheError::Invalid("palette length"));
+            }
+            let palette = (0..palette_len)
+                .map(|_| {
+                    let index = reader.u32()? as usize;
+                    table
+                        .get(index)
+                        .cloned()
+                        .ok_or(CacheError::Invalid("block index"))
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            let mut section = reader.section(palette)?;
+            section.biomes = reader.biomes(&mut biomes)?;
+            section.block_light = reader.light()?;
+            section.sky_light = reader.light()?;
+            sections.push(section);
+        }
+
+        let block_entities = (0..reader.u32()?)
+            .map(|_| reader.block_entity())
+            .collect::<Result<Vec<_>, _>>()?;
+        let entities = (0..reader.u32()?)
+            .map(|_| reader.entity())
+            .collect::<Result<Vec<_>, _>>()?;
+        let block_ticks = reader.ticks()?;
+        let fluid_ticks = reader.ticks()?;
+        let structure_references = (0..reader.u32()?)
+            .map(|_| {
+                let name = reader
+                    .string()?
+                    .try_into()
+                    .map_err(|_| CacheError::Invalid("structure name"))?;
+                let starts = (0..reader.u32()?)
+                    .map(|_| Ok((reader.i32()?, reader.i32()?)))
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok((name, starts))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let structure_starts = (0..reader.u32()?)
+            .map(|_| reader.structure_start())
+            .collect::<Result<Vec<_>, _>>()?;
+        let below_zero_retrogen = reader.retrogen()?;
+        let blending_data = match reader.u32()? as usize {
+            0 => None,
+            len => Some(reader.compound(len)?),
+        };
+
+        let mut heightmaps = BTreeMap::new();
+        for _ in 0..reader.u8()? {
+            let kind = *HeightmapKind::ALL
+                .get(reader.u8()? as usize)
+                .ok_or(CacheError::Invalid("heightmap kind"))?;
+            let bytes = reader.slice(512)?;
+            let heights = std::array::from_fn(|i| {
+                i16::from_be_bytes(bytes[i * 2..i * 2 + 2].try_into().unwrap())
+            });
+            heightmaps.insert(kind, Heightmap::new(heights));
+        }
+
+        if !reader.0.is_empty() {
+            return Err(CacheError::Invalid("trailing data"));
+        }
+        Ok(Chunk {
+            x_pos,
+            y_pos,
+            z_pos,
+            data_version,
+            status,
+            last_update,
+            inhabited_time,
+            sections,
+            heightmaps,
+            block_entities,
+            entities,
+            block_ticks,
+            fluid_ticks,
+            structure_references,
+            structure_starts,
+            below_zero_retrogen,
+            blending_data,
+            legacy_sections: None,
+            raw_nbt: None,
+            warnings: Vec::new(),
+            air: LazyAir::default(),
+        })
+    }
+}
+
+/// The distinct blocks of a chunk. Blocks aren't shared between sections,
+/// so they're told apart by name first and then compared, rather than
+/// hashed whole. A chunk has few names, which parsing shares, so they're
+/// looked up by address before their contents.
+#[derive(Default)]
+struct BlockTable<'a> {
+    by_name: Vec<(&'a str, Vec<(&'a Block, u32)>)>,
+    len: u32,
+    encoded: Vec<u8>,
+}
+
+impl<'a> BlockTable<'a> {
+    fn index(&mut self, block: &'a Shared<Block>) -> u32 {
+        let name: &str = &block.name;
+        let position = (self
+            .by_name
+            .iter()
+            .position(|(known, _)| std::ptr::eq(*known, name)))
+        .or_else(|| self.by_name.iter().position(|(known, _)| *known == name))
+        .unwrap_or_else(|| {
+            self.by_name.push((name, Vec::new()));
+            self.by_name.len() - 1
+        });
+        let known = &mut self.by_name[position].1;
+        if let Some(&(_, index)) = known.iter().find(|(known, _)| *known == &**block) {
+            return index;
+        }
+        write_string(&mut self.encoded, name);
+        match &block.properties {
+            Some(properties) => write_compound(&mut self.encoded, properties),
+            None => self.encoded.extend_from_slice(&0u32.to_be_bytes()),
+        }
+        known.push((block, self.len));
+        self.len += 1;
+        self.len - 1
+    }
+}
+
+/// The palette indices as the section holds them, with the occupancy of
+/// its blocks, or packed as read if they haven't been looked at yet.
+fn write_indices(out: &mut Vec<u8>, section: &Section) {
+    let Some(decoded) = section.decoded_parts() else {
+        let packed = section.packed_parts().unwrap();
+        out.extend_from_slice(&[3, packed.bits as u8, packed.spanning as u8]);
+        out.extend_from_slice(&(packed.data.len() as u32).to_be_bytes());
+        for long in &packed.data {
+            out.extend_from_slice(&long.to_be_bytes());
+        }
+        return;
+    };
+    match &decoded.indices {
+        Indices::Single => return out.push(0),
+        Indices::Bytes(indices) => {
+            out.push(1);
+            out.extend_from_slice(&indices[..]);
+        }
+        Indices::Shorts(indices) => {
+            out.push(2);
+            for index in &indices[..] {
+                out.extend_from_slice(&index.to_be_bytes());
+            }
+        }
+    }
+    let mut occupancy = [0; 512];
+    for (bytes, word) in occupancy.chunks_exact_mut(8).zip(decoded.occupancy) {
+        bytes.copy_from_slice(&word.to_be_bytes());
+    }
+    out.extend_from_slice(&occupancy);
+}
+
+/// A palette of at most 64 biomes, then an index per cell unless there's
+/// only one. No biomes are written as an empty palette.
+fn write_biomes(out: &mut Vec<u8>, biomes: &[Shared<NbtString>]) {
+    // the usual case, checked without building a palette
+    if let Some(first) = biomes.first()
+        && biomes.iter().all(|biome| Shared::ptr_eq(biome, first))
+    {
+        out.push(1);
+        return write_string(out, first);
+    }
+
+    let mut palette: Vec<&Shared<NbtString>> = Vec::new();
+    let indices: Vec<u8> = biomes
+        .iter()
+        .map(|biome| {
+            let index = palette.iter().position(|&known| known == biome);
+            index.unwrap_or_else(|| {
+                palette.push(biome);
+                palette.len() - 1
+            }) as u8
+        })
+        .collect();
+
+    out.push(palette.len() as u8);
+    for biome in &palette {
+        write_string(out, biome);
+    }
+    if palette.len() > 1 {
+        out.extend_from_slice(&indices);
+    }
+}
+
+fn write_compound(out: &mut Vec<u8>, compound: &NbtCompound) {
+    // the length goes first, so it's filled in once the compound is written
+    let start = out.len();
+    out.extend_from_slice(&[0; 4]);
+    // a nameless root compound, as `parse_nbt` expects it
+    out.extend_from_slice(&[10, 0, 0]);
+    compound.serialize_nbt_payload(out);
+    let len = (out.len() - start - 4) as u32;
+    out[start..start + 4].copy_from_slice(&len.to_be_bytes());
+}
+
+fn write_string(out: &mut Vec<u8>, value: &str) {
+    out.extend_from_slice(&(value.len() as u16).to_be_bytes());
+    out.extend_from_slice(value.as_bytes());
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl Reader<'_> {
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], CacheError> {
+        let (bytes, rest) = self
+            .0
+            .split_first_chunk()
+            .ok_or(CacheError::UnexpectedEnd)?;
+        self.0 = rest;
+        Ok(*bytes)
+    }
+
+    fn slice(&mut self, len: usize) -> Result<&[u8], CacheError> {
+        let (bytes, rest) = self
+            .0
+            .split_at_checked(len)
+            .ok_or(CacheError::UnexpectedEnd)?;
+        self.0 = rest;
+        Ok(bytes)
+    }
+
+    /// Straight from the input, as `Box::new` would build the array on the
+    /// stack first.
+    fn boxed<const N: usize>(&mut self) -> Result<Box<[u8; N]>, CacheError> {
+        Ok(self
+            .slice(N)?
+            .to_vec()
+            .into_boxed_slice()
+            .try_into()
+            .unwrap())
+    }
+
+    fn u8(&mut self) -> Result<u8, CacheError> {
+        self.take().map(u8::from_be_bytes)
+    }
+
+    fn u16(&mut self) -> Result<u16, CacheError> {
+        self.take().map(u16::from_be_bytes)
+    }
+
+    fn u32(&mut self) -> Result<u32, CacheError> {
+        self.take().map(u32::from_be_bytes)
+    }
+
+    fn i32(&mut self) -> Result<i32, CacheError> {
+        self.take().map(i32::from_be_bytes)
+    }
+
+    fn i64(&mut self) -> Result<i64, CacheError> {
+        self.take().map(i64::from_be_bytes)
+    }
+
+    fn string(&mut self) -> Result<String, CacheError> {
+        let len = self.u16()? as usize;
+        let bytes = self.slice(len)?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| CacheError::Invalid("string"))
+    }
+
+    /// The blocks of a section written by [`write_indices`].
+    fn section(&mut self, palette: Vec<Shared<Block>>) -> Result<Section, CacheError> {
+        let len = palette.len();
+        let indices = match self.u8()? {
+            0 => {
+                let occupancy = [if palette[0].is_air() { 0 } else { u64::MAX }; 64];
+                let decoded = Decoded {
+                    indices: Indices::Single,
+                    occupancy,
+                };
+                return Ok(Section::from_decoded(palette, decoded));
+            }
+            1 => {
+                let indices = self.boxed::<4096>()?;
+                // rather than `any`, which stops early and so isn't
+                // vectorized
+                if indices.iter().fold(0, |max: u8, &index| max.max(index)) as usize >= len {
+                    return Err(CacheError::Invalid("palette index"));
+                }
+                Indices::Bytes(indices)
+            }
+            2 => {
+                let bytes = self.slice(8192)?;
+                let indices: [u16; 4096] =
+                    std::array::from_fn(|i| u16::from_be_bytes([bytes[i * 2], bytes[i * 2 + 1]]));
+                if indices.iter().any(|&index| index as usize >= len) {
+                    return Err(CacheError::Invalid("palette index"));
+                }
+                Indices::Shorts(Box::new(indices))
+            }
+            3 => {
+                let (bits, spanning) = (self.u8()? as usize, self.u8()? != 0);
+                if !(1..64).contains(&bits) {
+                    return Err(CacheError::Invalid("index bits"));
+                }
+                let data = (0..self.u32()?)
+                    .map(|_| self.i64())
+                    .collect::<Result<Vec<_>, _>>()?;
+                let section = Section::from_packed(palette, &data, bits, spanning)
+                    .ok_or(CacheError::Invalid("packed indices"))?;
+                if packed::find_at_least(&data, bits, 4096, spanning, len as u64).is_some() {
+                    return Err(CacheError::Invalid("palette index"));
+                }
+                return Ok(section);
+            }
+            _ => return Err(CacheError::Invalid("index kind")),
+        };
+        let bytes = self.slice(512)?;
+        let occupancy = std::array::from_fn(|i| {
+            u64::from_be_bytes(bytes[i * 8..i * 8 + 8].try_into().unwrap())
+        });
+        Ok(Section::from_decoded(
+            palette,
+            Decoded { indices, occupancy },
+        ))
+    }
+
+    /// The biomes of a section, sharing each name with the sections read
+    /// before through `known`.
+    fn biomes(
+        &mut self,
+        known: &mut Vec<Shared<NbtString>>,
+    ) -> Result<Vec<Shared<NbtString>>, CacheError> {
+        let palette = (0..self.u8()?)
+            .map(|_| {
+                let len = self.u16()? as usize;
+                let name = std::str::from_utf8(self.slice(len)?)
+                    .map_err(|_| CacheError::Invalid("biome"))?;
+                if let Some(shared) = known.iter().find(|known| ***known == name) {
+                    return Ok(shared.clone());
+                }
+                let name = NbtString::try_from(name.to_string())
+                    .map_err(|_| CacheError::Invalid("biome"))?;
+                known.push(Shared::new(name));
+                Ok(known.last().unwrap().clone())
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        match palette.len() {
+            0 => Ok(Vec::new()),
+            1 => Ok(vec![palette[0].clone(); 64]),
+            len if len <= 64 => self
+                .slice(64)?
+                .iter()
+                .map(|&index| {
+                    palette
+                        .get(index as usize)
+                        .cloned()
+                        .ok_or(CacheError::Invalid("biome index"))
+                })
+                .collect(),
+            _ => Err(CacheError::Invalid("biome palette length")),
+        }
+    }
+
+    fn light(&mut self) -> Result<Option<Box<[u8; 2048]>>, CacheError> {
+        match self.u8()? {
+            0 => Ok(None),
+            1 => self.boxed().map(Some),
+            _ => Err(CacheError::Invalid("light flag")),
+        }
+    }
+
+    fn block(&mut self) -> Result<Block, CacheError> {
+        let len = self.u16()? as usize;
+        let name =
+            std::str::from_utf8(self.slice(len)?).map_err(|_| CacheError::Invalid("block name"))?;
+        let name = Shared::from(name);
+        let properties = match self.u32()? as usize {
+            0 => None,
+            len => Some(self.compound(len)?),
+        };
+        Ok(Block::with_shared_name(name, properties))
+    }
+
+    fn compound(&mut self, len: usize) -> Result<NbtCompound, CacheError> {
+        let (_, compound) = parse_nbt(self.slice(len)?).map_err(|_| CacheError::Invalid("nbt"))?;
+        Ok(compound)
+    }
+
+    fn block_entity(&mut self) -> Result<BlockEntity, CacheError> {
+        let len = self.u32()? as usize;
+        let data = self.compound(len)?;
+        let invalid = || CacheError::Invalid("block entity");
+        let id = get_field!(data, "id", as_string)
+            .ok_or_else(invalid)?
+            .clone();
+        let position = |field| match data.get(field) {
+            Some(NbtTag::Int(value)) => Ok(*value),
+            _ => Err(invalid()),
+        };
+        let (x, y, z) = (position("x")?, position("y")?, position("z")?);
+        Ok(BlockEntity { id, x, y, z, data })
+    }
+
+    fn entity(&mut self) -> Result<Entity, CacheError> {
+        let len = self.u32()? as usize;
+        let data = self.compound(len)?;
+        parse_entity(&data, "").map_err(|_| CacheError::Invalid("entity"))
+    }
+
+    fn structure_start(&mut self) -> Result<StructureStart, CacheError> {
+        let len = self.u32()? as usize;
+        let data = self.compound(len)?;
+        parse_start(data).ok_or(CacheError::Invalid("structure start"))
+    }
+
+    fn retrogen(&mut self) -> Result<Option<BelowZeroRetrogen>, CacheError> {
+        match self.u8()? {
+            0 => Ok(None),
+            1 => {
+                let mut retrogen = BelowZeroRetrogen::new(ChunkStatus::parse(&self.string()?));
+                for word in &mut retrogen.missing_bedrock {
+                    *word = self.take().map(u64::from_be_bytes)?;
+                }
+                Ok(Some(retrogen))
+            }
+            _ => Err(CacheError::Invalid("retrogen flag")),
+        }
+    }
+
+    fn ticks(&mut self) -> Result<Vec<ScheduledTick>, CacheError> {
+        (0..self.u32()?)
+            .map(|_| {
+                let target = self
+                    .string()?
+                    .try_into()
+                    .map_err(|_| CacheError::Invalid("tick target"))?;
+                Ok(ScheduledTick {
+                    target,
+                    x: self.i32()?,
+                    y: self.i32()?,
+                    z: self.i32()?,
+                    delay: self.i32()?,
+                    priority: self.i32()?,
+                })
+            })
+            .collect()
+    }
+}
@@ -1,46 +1,1131 @@
-use std::{cmp::max, io::Read, ops::Range, rc::Rc};
+use std::{
+    cmp::{Ordering, max},
+    collections::{BTreeMap, HashMap},
+    fmt, io,
+    iter::Enumerate,
+    ops::{Index, Range},
+    slice,
+    sync::Arc,
+};
 
-use flate2::read::{GzDecoder, ZlibDecoder};
 use nbt_rs::get_field as try_get_field;
-use nbt_rs::{error::ParseError, parse_nbt};
+use nbt_rs::{
+    error::ParseError,
+    parse_nbt,
+    types::{NbtCompound, NbtList, NbtString, NbtTag},
+};
 use thiserror::Error;
 
-use crate::chunks::{block::Block, section::Section};
+use crate::{
+    bounds::{Axis, OutOfBounds},
+    chunks::{
+        block::{Block, BlockInterner, LazyAir, Shared},
+        block_entity::BlockEntity,
+        hash::nbt_eq,
+        heightmap::{Heightmap, HeightmapKind, to_height},
+        limits::check_limits,
+        packed,
+        raw::RawChunk,
+        section::Section,
+        status::ChunkStatus,
+        structure::{StructureReferences, StructureStart, parse_structures},
+        tick::ScheduledTick,
+        upgrade::BelowZeroRetrogen,
+    },
+    coords::{BlockPos, ChunkPos},
+    entity::{Entity, parse_entities},
+    legacy::{LegacyMapping, LegacySection, parse_alpha_compound},
+    trace,
+    validate::{SECTION_Y_LIMITS, ValidationIssue, ValidationOptions},
+};
 
-const COMPRESSION_GZIP: u8 = 1;
-const COMPRESSION_ZLIB: u8 = 2;
-const COMPRESSION_RAW: u8 = 3;
-// const COMPRESSION_LZ4: u8 = 4;
-// const COMPRESSION_CUSTOM: u8 = 127;
+/// The inclusive minimum and maximum `(x, y, z)` corners of a box of blocks
+/// in a chunk.
+pub type ChunkBounds = ((u8, i32, u8), (u8, i32, u8));
 
-#[derive(Debug, Clone, PartialEq, PartialOrd)]
+#[derive(Debug, Clone)]
 pub struct Chunk {
-    y_pos: i32,
-    sections: Vec<Section>,
+    pub(crate) x_pos: i32,
+    pub(crate) y_pos: i32,
+    pub(crate) z_pos: i32,
+    pub(crate) data_version: i32,
+    pub(crate) status: ChunkStatus,
+    pub(crate) last_update: i64,
+    pub(crate) inhabited_time: i64,
+    pub(crate) sections: Vec<Section>,
+    pub(crate) heightmaps: BTreeMap<HeightmapKind, Heightmap>,
+    // sorted by position, see `BlockEntity::key`
+    pub(crate) block_entities: Vec<BlockEntity>,
+    // only stored in chunks from before 1.17
+    pub(crate) entities: Vec<Entity>,
+    pub(crate) block_ticks: Vec<ScheduledTick>,
+    pub(crate) fluid_ticks: Vec<ScheduledTick>,
+    pub(crate) structure_references: StructureReferences,
+    pub(crate) structure_starts: Vec<StructureStart>,
+    // only in chunks upgraded from before 1.18
+    pub(crate) below_zero_retrogen: Option<BelowZeroRetrogen>,
+    pub(crate) blending_data: Option<NbtCompound>,
+    // only for chunks from before 1.13, as read
+    pub(crate) legacy_sections: Option<Vec<LegacySection>>,
+    // only with `ChunkParseOptions::keep_nbt`
+    pub(crate) raw_nbt: Option<NbtCompound>,
+    // only with `ChunkParseOptions::lenient`
+    pub(crate) warnings: Vec<ParseWarning>,
+    // for `Chunk::get_or_air`
+    pub(crate) air: LazyAir,
+}
+
+/// Chunks are equal if they hold the same content. What's only kept as read,
+/// [`Chunk::raw_nbt`] and [`Chunk::legacy_sections`], and the
+/// [`Chunk::parse_warnings`] are left out, so parsing the same bytes with
+/// other [`ChunkParseOptions`] gives equal chunks. The nbt of block
+/// entities, entities and structures is compared in any key order, and its
+/// floats by their bits, so NaN equals itself.
+impl PartialEq for Chunk {
+    fn eq(&self, other: &Self) -> bool {
+        fn all<T>(a: &[T], b: &[T], eq: impl Fn(&T, &T) -> bool) -> bool {
+            a.len() == b.len() && a.iter().zip(b).all(|(a, b)| eq(a, b))
+        }
+        fn bits(entity: &Entity) -> [u64; 8] {
+            let ((x, y, z), (dx, dy, dz), (yaw, pitch)) =
+                (entity.pos, entity.motion, entity.rotation);
+            [x, y, z, dx, dy, dz, yaw.into(), pitch.into()].map(f64::to_bits)
+        }
+
+        (self.x_pos, self.y_pos, self.z_pos, self.data_version)
+            == (other.x_pos, other.y_pos, other.z_pos, other.data_version)
+            && self.status == other.status
+            && (self.last_update, self.inhabited_time) == (other.last_update, other.inhabited_time)
+            && self.sections == other.sections
+            && self.heightmaps == other.heightmaps
+            && all(&self.block_entities, &other.block_entities, |a, b| {
+                (&a.id, a.x, a.y, a.z) == (&b.id, b.x, b.y, b.z) && nbt_eq(&a.data, &b.data)
+            })
+            && all(&self.entities, &other.entities, |a, b| {
+                a.id == b.id && bits(a) == bits(b) && nbt_eq(&a.data, &b.data)
+            })
+            && self.block_ticks == other.block_ticks
+            && self.fluid_ticks == other.fluid_ticks
+            && self.structure_references == other.structure_references
+            && all(&self.structure_starts, &other.structure_starts, |a, b| {
+                (&a.id, a.bounding_box) == (&b.id, b.bounding_box) && nbt_eq(&a.data, &b.data)
+            })
+            && self.below_zero_retrogen == other.below_zero_retrogen
+            && match (&self.blending_data, &other.blending_data) {
+                (Some(a), Some(b)) => nbt_eq(a, b),
+                (a, b) => a.is_none() && b.is_none(),
+            }
+    }
+}
+
+impl Eq for Chunk {}
+
+/// Chunks are ordered by position, data version, status, timestamps,
+/// sections and heightmaps. Unequal chunks that only differ in the other
+/// fields aren't ordered.
+impl PartialOrd for Chunk {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        if self == other {
+            return Some(Ordering::Equal);
+        }
+        let key = |chunk: &Chunk| {
+            (
+                (chunk.x_pos, chunk.z_pos, chunk.y_pos, chunk.data_version),
+                chunk.status.clone(),
+                (chunk.last_update, chunk.inhabited_time),
+            )
+        };
+        match key(self).partial_cmp(&key(other))? {
+            Ordering::Equal => (&self.sections, &self.heightmaps)
+                .partial_cmp(&(&other.sections, &other.heightmaps))
+                .filter(|ordering| ordering.is_ne()),
+            ordering => Some(ordering),
+        }
+    }
 }
 
 impl Chunk {
-    pub fn get_y_range(&self) -> Range<i16> {
-        let start = self.y_pos as i16 * 16;
-        let end = start + self.sections.len() as i16 * 16;
+    pub fn get_pos(&self) -> ChunkPos {
+        ChunkPos::new(self.x_pos, self.z_pos)
+    }
+
+    /// The chunk x the chunk claims to be at, from its `xPos` field.
+    pub fn x_pos(&self) -> i32 {
+        self.x_pos
+    }
+
+    /// The chunk z the chunk claims to be at, from its `zPos` field.
+    pub fn z_pos(&self) -> i32 {
+        self.z_pos
+    }
+
+    /// The version of the game that wrote the chunk, 0 if it isn't stored.
+    pub fn data_version(&self) -> i32 {
+        self.data_version
+    }
+
+    /// The sections of a chunk from before 1.13 as stored, with numeric
+    /// block ids, or `None` for chunks storing palettes. The chunk's blocks
+    /// are these mapped with [`ChunkParseOptions::legacy_mapping`]. They're
+    /// as read, so blocks set since aren't reflected, and they're left out
+    /// when the chunk is written.
+    pub fn legacy_sections(&self) -> Option<&[LegacySection]> {
+        self.legacy_sections.as_deref()
+    }
+
+    /// The whole compound the chunk was decoded from, for fields this crate
+    /// doesn't read. Only kept with [`ChunkParseOptions::keep_nbt`], and as
+    /// read, so changes to the chunk aren't reflected.
+    pub fn raw_nbt(&self) -> Option<&NbtCompound> {
+        self.raw_nbt.as_ref()
+    }
+
+    /// What was worked around while parsing the chunk with
+    /// [`ChunkParseOptions::lenient`] or [`ChunkParseOptions::salvage`], in
+    /// the order it was found.
+    pub fn parse_warnings(&self) -> &[ParseWarning] {
+        &self.warnings
+    }
+
+    /// Whether the chunk was salvaged from nbt that breaks off partway, so
+    /// sections or fields stored after the break are missing. The
+    /// [`ParseWarning::Truncated`] in [`Chunk::parse_warnings`] says where.
+    pub fn is_partial(&self) -> bool {
+        (self.warnings.iter()).any(|warning| matches!(warning, ParseWarning::Truncated { .. }))
+    }
+
+    /// Chunks without a `Status` field are taken as fully generated.
+    pub fn status(&self) -> &ChunkStatus {
+        &self.status
+    }
+
+    /// The game tick the chunk was last saved at, 0 if it isn't stored.
+    pub fn last_update(&self) -> i64 {
+        self.last_update
+    }
+
+    /// How many ticks players have spent in or near the chunk, summed over
+    /// all players, 0 if it isn't stored.
+    pub fn inhabited_time(&self) -> i64 {
+        self.inhabited_time
+    }
+
+    pub fn get_y_range(&self) -> Range<i32> {
+        let start = self.y_pos * 16;
+        let end = start + self.sections.len() as i32 * 16;
         start..end
     }
 
-    pub fn get(&self, x: u8, y: i16, z: u8) -> Option<&Block> {
+    /// Returns `None` if `x` or `z` is 16 or above, or `y` is outside of
+    /// [`Chunk::get_y_range`].
+    pub fn get(&self, x: u8, y: i32, z: u8) -> Option<&Block> {
         if x >= 16 || !self.get_y_range().contains(&y) || z >= 16 {
             return None;
         }
 
-        let local_y = (y as i32 - self.y_pos * 16) as usize;
+        let local_y = (y - self.y_pos * 16) as usize;
         let section = local_y >> 4;
         let block = (local_y as u8) & 0xF;
 
         self.sections[section].get_block(x, block, z)
     }
 
+    /// Like [`Chunk::get`], but the blocks above and below
+    /// [`Chunk::get_y_range`] are air, like the world treats them. Panics if
+    /// `x` or `z` is 16 or above, like indexing the chunk.
+    pub fn get_or_air(&self, x: u8, y: i32, z: u8) -> &Block {
+        match self.get(x, y, z) {
+            Some(block) => block,
+            None if x < 16 && z < 16 => self.air.get(),
+            None => self.out_of_range(x, y, z),
+        }
+    }
+
+    fn out_of_range(&self, x: u8, y: i32, z: u8) -> ! {
+        let Range { start, end } = self.get_y_range();
+        panic!(
+            "block ({x}, {y}, {z}) out of range for a chunk spanning y {start}..{end}, x and z go \
+             up to 15"
+        )
+    }
+
+    /// The biome at the block, see [`Section::get_biome`]. Returns `None` if
+    /// the block is out of bounds or its section has no biomes.
+    pub fn get_biome(&self, x: u8, y: i32, z: u8) -> Option<&NbtString> {
+        if !self.get_y_range().contains(&y) {
+            return None;
+        }
+
+        let local_y = (y - self.y_pos * 16) as usize;
+        self.sections[local_y >> 4].get_biome(x, (local_y as u8) & 0xF, z)
+    }
+
+    /// The block light at the block, see [`Section::get_block_light`].
+    /// Returns `None` if the block is out of bounds or its section has no
+    /// block light.
+    pub fn get_block_light(&self, x: u8, y: i32, z: u8) -> Option<u8> {
+        if !self.get_y_range().contains(&y) {
+            return None;
+        }
+
+        let local_y = (y - self.y_pos * 16) as usize;
+        self.sections[local_y >> 4].get_block_light(x, (local_y as u8) & 0xF, z)
+    }
+
+    /// The sky light at the block, see [`Section::get_sky_light`]. Returns
+    /// `None` if the block is out of bounds or its section has no sky light.
+    pub fn get_sky_light(&self, x: u8, y: i32, z: u8) -> Option<u8> {
+        if !self.get_y_range().contains(&y) {
+            return None;
+        }
+
+        let local_y = (y - self.y_pos * 16) as usize;
+        self.sections[local_y >> 4].get_sky_light(x, (local_y as u8) & 0xF, z)
+    }
+
+    /// Sets the block light at the block, see [`Section::set_block_light`].
+    pub fn set_block_light(&mut self, x: u8, y: i32, z: u8, level: u8) -> Result<(), OutOfBounds> {
+        OutOfBounds::check(Axis::Y, y, self.get_y_range())?;
+
+        let local_y = (y - self.y_pos * 16) as usize;
+        self.sections[local_y >> 4].set_block_light(x, (local_y as u8) & 0xF, z, level)
+    }
+
+    /// Sets the sky light at the block, see [`Section::set_sky_light`].
+    pub fn set_sky_light(&mut self, x: u8, y: i32, z: u8, level: u8) -> Result<(), OutOfBounds> {
+        OutOfBounds::check(Axis::Y, y, self.get_y_range())?;
+
+        let local_y = (y - self.y_pos * 16) as usize;
+        self.sections[local_y >> 4].set_sky_light(x, (local_y as u8) & 0xF, z, level)
+    }
+
+    /// Drops the light of every section, see [`Section::clear_light`].
+    pub fn clear_light(&mut self) {
+        self.sections.iter_mut().for_each(Section::clear_light);
+    }
+
+    /// Sets the biome at the block, see [`Section::set_biome`].
+    pub fn set_biome(&mut self, x: u8, y: i32, z: u8, name: NbtString) -> Result<(), OutOfBounds> {
+        OutOfBounds::check(Axis::Y, y, self.get_y_range())?;
+
+        let local_y = (y - self.y_pos * 16) as usize;
+        self.sections[local_y >> 4].set_biome(x, (local_y as u8) & 0xF, z, name)
+    }
+
+    /// Like [`Chunk::get`], but only the in-chunk offset of `pos` is used,
+    /// it isn't checked whether `pos` actually lies in this chunk.
+    pub fn get_at(&self, pos: impl Into<BlockPos>) -> Option<&Block> {
+        let (x, y, z) = pos.into().chunk_local();
+        self.get(x, y, z)
+    }
+
+    /// Sets the block, in chunk coordinates like [`Chunk::get`]. A block
+    /// entity at the block is kept even if the new block can't have one,
+    /// see [`Chunk::remove_block_entity`].
+    pub fn set(&mut self, x: u8, y: i32, z: u8, block: Block) -> Result<(), OutOfBounds> {
+        OutOfBounds::check(Axis::X, x, 0..16)?;
+        OutOfBounds::check(Axis::Y, y, self.get_y_range())?;
+        OutOfBounds::check(Axis::Z, z, 0..16)?;
+
+        let local_y = (y - self.y_pos * 16) as usize;
+        self.sections[local_y >> 4].set_block(x, (local_y as u8) & 0xF, z, block)
+    }
+
+    /// Sets every block in the box spanned by the ranges, filling whole
+    /// sections where the box covers them. The ranges are clamped to the
+    /// chunk like in [`Section::fill_box`], so parts of the box outside of
+    /// [`Chunk::get_y_range`] are left out. All sections share one `block`.
+    pub fn fill_box(&mut self, x: Range<u8>, y: Range<i32>, z: Range<u8>, block: Block) {
+        let chunk_y = self.get_y_range();
+        let y = y.start.max(chunk_y.start)..y.end.min(chunk_y.end);
+        if x.is_empty() || y.is_empty() || z.is_empty() {
+            return;
+        }
+
+        let block = Shared::new(block);
+        for (i, section) in self.sections.iter_mut().enumerate() {
+            let bottom = chunk_y.start + i as i32 * 16;
+            let local = |y: i32| (y - bottom).clamp(0, 16) as u8;
+            section.fill_box_shared(
+                x.clone(),
+                local(y.start)..local(y.end),
+                z.clone(),
+                block.clone(),
+            );
+        }
+    }
+
+    /// Like [`Chunk::fill_box`], but fails without setting anything if the
+    /// box reaches outside of the chunk.
+    pub fn try_fill_box(
+        &mut self,
+        x: Range<u8>,
+        y: Range<i32>,
+        z: Range<u8>,
+        block: Block,
+    ) -> Result<(), OutOfBounds> {
+        // the first and last block of every non-empty range
+        let ends = |range: &Range<i32>| [range.start, range.end - 1];
+        if !x.is_empty() && !y.is_empty() && !z.is_empty() {
+            for value in ends(&(x.start as i32..x.end as i32)) {
+                OutOfBounds::check(Axis::X, value, 0..16)?;
+            }
+            for value in ends(&y) {
+                OutOfBounds::check(Axis::Y, value, self.get_y_range())?;
+            }
+            for value in ends(&(z.start as i32..z.end as i32)) {
+                OutOfBounds::check(Axis::Z, value, 0..16)?;
+            }
+        }
+        self.fill_box(x, y, z, block);
+        Ok(())
+    }
+
+    /// Like [`Chunk::set`], but if `y` is above or below the sections the
+    /// chunk has, air sections are added up to the one holding it. They take
+    /// the biomes of the section they're added next to and have no light
+    /// stored. Fails if `y` is outside of what any dimension can have.
+    pub fn set_extending(&mut self, x: u8, y: i32, z: u8, block: Block) -> Result<(), OutOfBounds> {
+        OutOfBounds::check(Axis::X, x, 0..16)?;
+        OutOfBounds::check(
+            Axis::Y,
+            y,
+            SECTION_Y_LIMITS.start * 16..SECTION_Y_LIMITS.end * 16,
+        )?;
+        OutOfBounds::check(Axis::Z, z, 0..16)?;
+
+        let section_y = y >> 4;
+        if self.sections.is_empty() {
+            let old_min_y = self.get_y_range().start;
+            self.y_pos = section_y;
+            self.move_empty_heightmaps(old_min_y);
+        }
+        if section_y < self.y_pos {
+            self.extend_down((self.y_pos - section_y) as usize)?;
+        }
+        let top = self.y_pos + self.sections.len() as i32;
+        if section_y >= top {
+            self.extend_up((section_y - top + 1) as usize)?;
+        }
+        self.set(x, y, z, block)
+    }
+
+    /// Sets the block, in chunk coordinates like [`Chunk::get`]. Unlike
+    /// [`Chunk::set_extending`], a `y` outside of the chunk's sections fails
+    /// instead of adding sections.
+    pub fn set_block(&mut self, x: u8, y: i16, z: u8, block: Block) -> Result<(), SetBlockError> {
+        OutOfBounds::check(Axis::X, x, 0..16).map_err(SetBlockError::OutOfChunk)?;
+        OutOfBounds::check(Axis::Z, z, 0..16).map_err(SetBlockError::OutOfChunk)?;
+        let sections = self.get_y_range();
+        if !sections.contains(&y.into()) {
+            return Err(SetBlockError::OutsideSections { y, sections });
+        }
+        // all three were checked, so this doesn't fail
+        self.set(x, y.into(), z, block)
+            .map_err(SetBlockError::OutOfChunk)
+    }
+
+    /// Adds `sections` air sections below the chunk, so
+    /// [`Chunk::get_y_range`] starts lower while every block keeps its y.
+    /// Like with [`Chunk::set_extending`], they take the biomes of the
+    /// lowest section and have no light stored. Fails without changing
+    /// anything if the chunk would reach below what any dimension can have.
+    pub fn extend_down(&mut self, sections: usize) -> Result<(), OutOfBounds> {
+        let bottom = self.y_pos as i64 - sections as i64;
+        OutOfBounds::check(
+            Axis::Y,
+            bottom * 16,
+            SECTION_Y_LIMITS.start * 16..SECTION_Y_LIMITS.end * 16,
+        )?;
+
+        let old_min_y = self.get_y_range().start;
+        let below = air_next_to(self.sections.first());
+        (self.sections).splice(0..0, std::iter::repeat_n(below, sections));
+        self.y_pos = bottom as i32;
+        self.move_empty_heightmaps(old_min_y);
+        Ok(())
+    }
+
+    /// Moves the heights of the columns without blocks from `old_min_y` to
+    /// the bottom of the chunk.
+    fn move_empty_heightmaps(&mut self, old_min_y: i32) {
+        let min_y = self.get_y_range().start;
+        match (to_height(old_min_y), to_height(min_y)) {
+            (Some(old_min_y), Some(min_y)) => {
+                for heightmap in self.heightmaps.values_mut() {
+                    heightmap.move_empty(old_min_y, min_y);
+                }
+            }
+            // heights this far out can't be kept
+            _ => self.heightmaps.clear(),
+        }
+    }
+
+    /// Like [`Chunk::extend_down`], but adds the sections above the chunk,
+    /// with the biomes of the highest section.
+    pub fn extend_up(&mut self, sections: usize) -> Result<(), OutOfBounds> {
+        if sections == 0 {
+            return Ok(());
+        }
+        let top = self.y_pos as i64 + (self.sections.len() + sections) as i64;
+        OutOfBounds::check(
+            Axis::Y,
+            top * 16 - 1,
+            SECTION_Y_LIMITS.start * 16..SECTION_Y_LIMITS.end * 16,
+        )?;
+
+        let above = air_next_to(self.sections.last());
+        (self.sections).extend(std::iter::repeat_n(above, sections));
+        Ok(())
+    }
+
+    /// Like [`Chunk::get`], but takes world coordinates. Returns `None` if
+    /// they don't lie in this chunk.
+    pub fn get_world(&self, x: i32, y: i32, z: i32) -> Option<&Block> {
+        let pos = BlockPos::new(x, y, z);
+        if pos.chunk() != self.get_pos() {
+            return None;
+        }
+        self.get_at(pos)
+    }
+
+    pub fn set_block_world(
+        &mut self,
+        x: i32,
+        y: i32,
+        z: i32,
+        block: Block,
+    ) -> Result<(), OutOfBounds> {
+        let min = self.get_pos().min_block(y);
+        OutOfBounds::check(Axis::X, x, min.x..min.x + 16)?;
+        OutOfBounds::check(Axis::Z, z, min.z..min.z + 16)?;
+
+        let (x, _, z) = BlockPos::new(x, y, z).chunk_local();
+        self.set(x, y, z, block)
+    }
+
+    /// The section y coordinates this chunk has sections for.
+    pub fn section_y_range(&self) -> Range<i32> {
+        self.y_pos..self.y_pos + self.sections.len() as i32
+    }
+
+    /// How many sections the chunk has, including empty ones.
+    pub fn section_count(&self) -> usize {
+        self.sections.len()
+    }
+
+    /// Takes a section y, i.e. a block y divided by 16, see
+    /// [`Chunk::get_section_at_block_y`] for block coordinates. Returns `None`
+    /// if `y` is outside of [`Chunk::section_y_range`].
     pub fn get_section(&self, y: i32) -> Option<&Section> {
+        if !self.section_y_range().contains(&y) {
+            return None;
+        }
         self.sections.get((y - self.y_pos) as usize)
     }
+
+    pub fn get_section_mut(&mut self, y: i32) -> Option<&mut Section> {
+        if !self.section_y_range().contains(&y) {
+            return None;
+        }
+        self.sections.get_mut((y - self.y_pos) as usize)
+    }
+
+    /// The section containing the block at `y`.
+    pub fn get_section_at_block_y(&self, y: i32) -> Option<&Section> {
+        self.get_section(y >> 4)
+    }
+
+    /// The block entities of the chunk, ordered by `y`, `z`, then `x`.
+    pub fn block_entities(&self) -> &[BlockEntity] {
+        &self.block_entities
+    }
+
+    /// The block entity at the block, in chunk coordinates like
+    /// [`Chunk::get`].
+    pub fn get_block_entity(&self, x: u8, y: i32, z: u8) -> Option<&BlockEntity> {
+        if x >= 16 || z >= 16 {
+            return None;
+        }
+
+        let min = self.get_pos().min_block(y);
+        let key = (y, min.z + z as i32, min.x + x as i32);
+        self.block_entities
+            .binary_search_by_key(&key, BlockEntity::key)
+            .ok()
+            .map(|index| &self.block_entities[index])
+    }
+
+    /// Puts `block_entity` at its position, which is in world coordinates
+    /// and has to be within the chunk, and returns the one it replaces.
+    pub fn set_block_entity(
+        &mut self,
+        block_entity: BlockEntity,
+    ) -> Result<Option<BlockEntity>, OutOfBounds> {
+        let min = self.get_pos().min_block(0);
+        OutOfBounds::check(Axis::X, block_entity.x, min.x..min.x + 16)?;
+        OutOfBounds::check(Axis::Y, block_entity.y, self.get_y_range())?;
+        OutOfBounds::check(Axis::Z, block_entity.z, min.z..min.z + 16)?;
+
+        match (self.block_entities).binary_search_by_key(&block_entity.key(), BlockEntity::key) {
+            Ok(index) => Ok(Some(std::mem::replace(
+                &mut self.block_entities[index],
+                block_entity,
+            ))),
+            Err(index) => {
+                self.block_entities.insert(index, block_entity);
+                Ok(None)
+            }
+        }
+    }
+
+    /// Removes the block entity at the block, in chunk coordinates like
+    /// [`Chunk::get`], and returns it.
+    pub fn remove_block_entity(&mut self, x: u8, y: i32, z: u8) -> Option<BlockEntity> {
+        if x >= 16 || z >= 16 {
+            return None;
+        }
+
+        let min = self.get_pos().min_block(y);
+        let key = (y, min.z + z as i32, min.x + x as i32);
+        let index = (self.block_entities)
+            .binary_search_by_key(&key, BlockEntity::key)
+            .ok()?;
+        Some(self.block_entities.remove(index))
+    }
+
+    /// The entities stored in the chunk's `Level.Entities`, in the order
+    /// they're stored. Since 1.17 they're in separate files, see
+    /// [`crate::EntityRegion`], and this is empty. [`crate::World::get_entities`]
+    /// looks in both places.
+    pub fn embedded_entities(&self) -> &[Entity] {
+        &self.entities
+    }
+
+    /// The scheduled block updates, in the order they're stored.
+    pub fn block_ticks(&self) -> &[ScheduledTick] {
+        &self.block_ticks
+    }
+
+    /// The scheduled fluid updates, in the order they're stored.
+    pub fn fluid_ticks(&self) -> &[ScheduledTick] {
+        &self.fluid_ticks
+    }
+
+    /// The structures reaching into this chunk by name, like
+    /// `minecraft:fortress`, with the chunk positions of their starts. Sorted
+    /// by name.
+    pub fn structure_references(&self) -> impl Iterator<Item = (&str, Vec<(i32, i32)>)> {
+        self.structure_references
+            .iter()
+            .map(|(name, starts)| (&**name, starts.clone()))
+    }
+
+    /// The structures that start in this chunk, sorted by id.
+    pub fn structure_starts(&self) -> &[StructureStart] {
+        &self.structure_starts
+    }
+
+    /// The heightmap as stored in the chunk, or as last computed by
+    /// [`Chunk::compute_heightmaps`].
+    pub fn get_heightmap(&self, kind: HeightmapKind) -> Option<&Heightmap> {
+        self.heightmaps.get(&kind)
+    }
+
+    /// Rebuilds all heightmaps from the blocks, replacing the stored ones.
+    /// See [`HeightmapKind::matches`] for how blocks are classified. A
+    /// chunk whose heights don't all fit in an `i16` is left without
+    /// heightmaps, see [`Heightmap`].
+    pub fn compute_heightmaps(&mut self) {
+        let heightmaps = HeightmapKind::ALL
+            .into_iter()
+            .map(|kind| Some((kind, self.computed_heightmap(kind)?)))
+            .collect::<Option<_>>();
+        self.heightmaps = heightmaps.unwrap_or_default();
+    }
+
+    fn computed_heightmap(&self, kind: HeightmapKind) -> Option<Heightmap> {
+        let mut heights = [0; 256];
+        for (height, computed) in heights.iter_mut().zip(self.computed_heights(kind)) {
+            *height = computed?;
+        }
+        Some(Heightmap::new(heights))
+    }
+
+    /// The heights of the columns indexed by z then x, `None` where they
+    /// don't fit in an `i16`.
+    fn computed_heights(&self, kind: HeightmapKind) -> [Option<i16>; 256] {
+        let range = self.get_y_range();
+        // a chunk reaching past an i16 may have its surface out there
+        let (Some(min_y), Some(_)) = (to_height(range.start), to_height(range.end)) else {
+            return [None; 256];
+        };
+        let surface = self.compute_surface(|block| kind.matches(block), None);
+        std::array::from_fn(|index| Some(surface[index & 0xF][index >> 4].map_or(min_y, |y| y + 1)))
+    }
+
+    /// The y of the highest block matching `predicate` in each column, at or
+    /// below `from_y`, or the top of the chunk if it's `None`, indexed by x
+    /// then z. Sections made up of a single block that doesn't match are
+    /// skipped without looking at their columns, and so are sections beyond
+    /// the range of an `i16`, further out than the game ever puts them.
+    pub fn compute_surface(
+        &self,
+        predicate: impl Fn(&Block) -> bool,
+        from_y: Option<i32>,
+    ) -> [[Option<i16>; 16]; 16] {
+        let from_y = from_y.unwrap_or(i32::MAX);
+        let mut surface = [[None; 16]; 16];
+        let mut remaining = 256;
+        for (section_y, section) in self.sections().rev() {
+            // sections line up with the ends of the i16 range, so the whole
+            // section fits if its bottom does
+            let min_y = section_y * 16;
+            let Some(section_min_y) = to_height(min_y) else {
+                continue;
+            };
+            if min_y > from_y {
+                continue;
+            }
+            if let Some(block) = section.is_uniform()
+                && !predicate(block)
+            {
+                continue;
+            }
+
+            let top = from_y.saturating_sub(min_y).min(15) as u8;
+            for (x, column) in surface.iter_mut().enumerate() {
+                for (z, height) in column.iter_mut().enumerate() {
+                    if height.is_some() {
+                        continue;
+                    }
+                    let found = (0..=top)
+                        .rev()
+                        .find(|&y| predicate(section.get_block(x as u8, y, z as u8).unwrap()));
+                    if let Some(y) = found {
+                        *height = Some(section_min_y + y as i16);
+                        remaining -= 1;
+                    }
+                }
+            }
+            if remaining == 0 {
+                break;
+            }
+        }
+        surface
+    }
+
+    /// Removes the sections holding only air from the top and bottom of the
+    /// chunk, returning how many were removed. Empty sections between others
+    /// are kept, and so is one section of a chunk that's all air. Heightmap
+    /// columns without blocks are moved up to the new bottom.
+    pub fn trim_empty_sections(&mut self) -> usize {
+        let len = self.sections.len();
+        let top = self
+            .sections
+            .iter()
+            .rposition(|section| !section.is_empty())
+            .map_or(1, |i| i + 1);
+        self.sections.truncate(top);
+        let bottom = self
+            .sections
+            .iter()
+            .position(|section| !section.is_empty())
+            .unwrap_or(0);
+        self.sections.drain(..bottom);
+        self.y_pos += bottom as i32;
+
+        match to_height(self.get_y_range().start) {
+            Some(min_y) => {
+                for heightmap in self.heightmaps.values_mut() {
+                    heightmap.raise_to(min_y);
+                }
+            }
+            None => self.heightmaps.clear(),
+        }
+        len - self.sections.len()
+    }
+
+    /// Checks the chunk for things the game wouldn't load, see
+    /// [`ValidationIssue`].
+    pub fn validate(&self) -> Vec<ValidationIssue> {
+        self.validate_with(&ValidationOptions::default())
+    }
+
+    /// Like [`Chunk::validate`], with the more expensive checks the options
+    /// turn on.
+    pub fn validate_with(&self, options: &ValidationOptions) -> Vec<ValidationIssue> {
+        let mut issues = Vec::new();
+
+        let range = self.section_y_range();
+        if range.start < SECTION_Y_LIMITS.start || range.end > SECTION_Y_LIMITS.end {
+            issues.push(ValidationIssue::SectionsOutOfRange { range });
+        }
+
+        for (section_y, section) in self {
+            let count = section
+                .blocks()
+                .filter(|block| block.name.is_empty())
+                .count();
+            if count > 0 {
+                issues.push(ValidationIssue::EmptyBlockName { section_y, count });
+            }
+        }
+
+        let min = self.get_pos().min_block(0);
+        for block_entity in &self.block_entities {
+            if !(min.x..min.x + 16).contains(&block_entity.x)
+                || !(min.z..min.z + 16).contains(&block_entity.z)
+                || !self.get_y_range().contains(&block_entity.y)
+            {
+                issues.push(ValidationIssue::BlockEntityOutsideChunk {
+                    id: block_entity.id.to_string(),
+                    pos: BlockPos::new(block_entity.x, block_entity.y, block_entity.z),
+                });
+            }
+        }
+
+        if options.heightmaps {
+            for (&kind, stored) in &self.heightmaps {
+                let computed = self.computed_heights(kind);
+                let columns = (stored.heights().iter())
+                    .zip(computed)
+                    .filter(|&(&stored, computed)| Some(stored) != computed)
+                    .count();
+                if columns > 0 {
+                    issues.push(ValidationIssue::HeightmapMismatch { kind, columns });
+                }
+            }
+        }
+
+        issues
+    }
+
+    /// Whether every section holds nothing but air, see
+    /// [`Section::is_empty`].
+    pub fn is_empty(&self) -> bool {
+        self.sections.iter().all(Section::is_empty)
+    }
+
+    /// How many of each block the chunk holds, see [`Section::block_counts`].
+    pub fn block_counts(&self) -> HashMap<&Block, u32> {
+        let mut counts = HashMap::new();
+        for section in &self.sections {
+            for (block, count) in section.block_counts() {
+                *counts.entry(block).or_insert(0) += count;
+            }
+        }
+        counts
+    }
+
+    /// The positions of the blocks `predicate` returns `true` for, section
+    /// by section from the bottom up, see [`Section::find_blocks`].
+    pub fn find_blocks(&self, predicate: impl Fn(&Block) -> bool) -> Vec<(u8, i32, u8)> {
+        self.sections()
+            .flat_map(|(section_y, section)| {
+                section
+                    .find_blocks(&predicate)
+                    .into_iter()
+                    .map(move |(x, y, z)| (x, section_y * 16 + y as i32, z))
+            })
+            .collect()
+    }
+
+    /// How many 4x4x4 cells of each biome the chunk holds, see
+    /// [`Section::biome_counts`].
+    pub fn biome_counts(&self) -> HashMap<&NbtString, u32> {
+        let mut counts = HashMap::new();
+        for section in &self.sections {
+            for (biome, count) in section.biome_counts() {
+                *counts.entry(biome).or_insert(0) += count;
+            }
+        }
+        counts
+    }
+
+    /// The biome most of the 4x4x4 cells in the column at `x`, `z` are, for
+    /// maps that tint each column by a single biome. On a tie, the biome
+    /// that shows up first going up the column wins. Returns `None` if `x`
+    /// or `z` is 16 or above, or if the chunk has no biomes.
+    pub fn dominant_biome_at(&self, x: u8, z: u8) -> Option<&NbtString> {
+        if x >= 16 || z >= 16 {
+            return None;
+        }
+
+        let mut counts: Vec<(&NbtString, u32)> = Vec::new();
+        for section in &self.sections {
+            for y in (0..16).step_by(4) {
+                let Some(biome) = section.get_biome(x, y, z) else {
+                    continue;
+                };
+                match counts.iter_mut().find(|(known, _)| *known == biome) {
+                    Some((_, count)) => *count += 1,
+                    None => counts.push((biome, 1)),
+                }
+            }
+        }
+        // the first of the most common ones
+        let most = counts.iter().map(|&(_, count)| count).max()?;
+        (counts.into_iter()).find_map(|(biome, count)| (count == most).then_some(biome))
+    }
+
+    /// The center of every 4x4x4 cell of the biome called `name`, section by
+    /// section from the bottom up, see [`Section::find_biome`].
+    pub fn find_biome(&self, name: &str) -> Vec<(u8, i32, u8)> {
+        self.sections()
+            .flat_map(|(section_y, section)| {
+                section
+                    .find_biome(name)
+                    .into_iter()
+                    .map(move |(x, y, z)| (x, section_y * 16 + y as i32, z))
+            })
+            .collect()
+    }
+
+    /// Iterates over all blocks, section by section from the bottom up.
+    pub fn iter_blocks(&self) -> impl Iterator<Item = (u8, i32, u8, &Block)> {
+        self.sections().flat_map(|(section_y, section)| {
+            section
+                .iter_blocks()
+                .map(move |(x, y, z, block)| (x, section_y * 16 + y as i32, z, block))
+        })
+    }
+
+    /// Like [`Chunk::iter_blocks`], but skips air, and sections holding only
+    /// air are skipped without looking at their blocks.
+    pub fn iter_blocks_non_air(&self) -> impl Iterator<Item = (u8, i32, u8, &Block)> {
+        self.sections()
+            .filter(|(_, section)| !section.is_empty())
+            .flat_map(|(section_y, section)| {
+                section
+                    .iter_blocks_non_air()
+                    .map(move |(x, y, z, block)| (x, section_y * 16 + y as i32, z, block))
+            })
+    }
+
+    /// The blocks of the column at `x`, `z` with their y, from the bottom
+    /// up, or from the top down with `.rev()`. Empty if `x` or `z` is 16 or
+    /// above.
+    pub fn get_column(&self, x: u8, z: u8) -> impl DoubleEndedIterator<Item = (i32, &Block)> {
+        self.sections().flat_map(move |(section_y, section)| {
+            (0..16)
+                .filter_map(move |y| Some((section_y * 16 + y as i32, section.get_block(x, y, z)?)))
+        })
+    }
+
+    /// The highest block of the column at `x`, `z` that isn't air, see
+    /// [`Chunk::highest_block_matching`].
+    pub fn highest_block(&self, x: u8, z: u8) -> Option<(i32, &Block)> {
+        self.highest_block_matching(x, z, |block| !block.is_air())
+    }
+
+    /// The highest block of the column at `x`, `z` that is neither air nor a
+    /// fluid, see [`Block::is_fluid`], so the floor under a lake rather than
+    /// its surface. This is to [`Chunk::highest_block`] what the ocean floor
+    /// heightmap is to the world surface one, except that plants and other
+    /// blocks that don't block motion still count. Waterlogged blocks count
+    /// too.
+    pub fn highest_solid_block(&self, x: u8, z: u8) -> Option<(i32, &Block)> {
+        self.highest_solid_block_with(x, z, Block::is_fluid)
+    }
+
+    /// Like [`Chunk::highest_solid_block`], with `is_fluid` deciding which
+    /// blocks are fluids, e.g. to add those of mods.
+    pub fn highest_solid_block_with(
+        &self,
+        x: u8,
+        z: u8,
+        is_fluid: impl Fn(&Block) -> bool,
+    ) -> Option<(i32, &Block)> {
+        self.highest_block_matching(x, z, |block| !block.is_air() && !is_fluid(block))
+    }
+
+    /// The highest block of the column at `x`, `z` for which `predicate`
+    /// returns `true`, with its y. Sections without such a block in their
+    /// palette, like ones holding only air, are skipped without looking at
+    /// their blocks. Returns `None` if there's no such block, or `x` or `z`
+    /// is 16 or above.
+    pub fn highest_block_matching(
+        &self,
+        x: u8,
+        z: u8,
+        predicate: impl Fn(&Block) -> bool,
+    ) -> Option<(i32, &Block)> {
+        self.sections()
+            .rev()
+            .filter(|(_, section)| section.palette.iter().any(|block| predicate(block)))
+            .find_map(|(section_y, section)| {
+                (0..16).rev().find_map(|y| {
+                    let block = section.get_block(x, y, z)?;
+                    predicate(block).then_some((section_y * 16 + y as i32, block))
+                })
+            })
+    }
+
+    /// Like [`Section::non_air_bounds`], but for the whole chunk. Sections
+    /// holding only air are skipped without looking at their blocks.
+    pub fn non_air_bounds(&self) -> Option<ChunkBounds> {
+        let mut bounds: Option<ChunkBounds> = None;
+        for (section_y, section) in self.sections() {
+            if section.is_empty() {
+                continue;
+            }
+            let Some(((x0, y0, z0), (x1, y1, z1))) = section.non_air_bounds() else {
+                continue;
+            };
+            let (y0, y1) = (section_y * 16 + y0 as i32, section_y * 16 + y1 as i32);
+            bounds = Some(match bounds {
+                None => ((x0, y0, z0), (x1, y1, z1)),
+                // sections go up, so the bottom is already known
+                Some(((min_x, min_y, min_z), (max_x, _, max_z))) => (
+                    (min_x.min(x0), min_y, min_z.min(z0)),
+                    (max_x.max(x1), y1, max_z.max(z1)),
+                ),
+            });
+        }
+        bounds
+    }
+
+    /// Iterates over the sections from the bottom up, with their section y.
+    pub fn sections(&self) -> Sections<'_> {
+        Sections {
+            y_pos: self.y_pos,
+            iter: self.sections.iter().enumerate(),
+        }
+    }
+
+    /// Like [`Chunk::sections`], but the sections can be changed.
+    pub fn sections_mut(&mut self) -> SectionsMut<'_> {
+        SectionsMut {
+            y_pos: self.y_pos,
+            iter: self.sections.iter_mut().enumerate(),
+        }
+    }
+}
+
+pub struct Sections<'a> {
+    y_pos: i32,
+    iter: Enumerate<slice::Iter<'a, Section>>,
+}
+
+impl<'a> Iterator for Sections<'a> {
+    type Item = (i32, &'a Section);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (i, section) = self.iter.next()?;
+        Some((self.y_pos + i as i32, section))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+impl DoubleEndedIterator for Sections<'_> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let (i, section) = self.iter.next_back()?;
+        Some((self.y_pos + i as i32, section))
+    }
+}
+
+impl ExactSizeIterator for Sections<'_> {}
+
+pub struct SectionsMut<'a> {
+    y_pos: i32,
+    iter: Enumerate<slice::IterMut<'a, Section>>,
+}
+
+impl<'a> Iterator for SectionsMut<'a> {
+    type Item = (i32, &'a mut Section);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (i, section) = self.iter.next()?;
+        Some((self.y_pos + i as i32, section))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+impl DoubleEndedIterator for SectionsMut<'_> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let (i, section) = self.iter.next_back()?;
+        Some((self.y_pos + i as i32, section))
+    }
+}
+
+impl ExactSizeIterator for SectionsMut<'_> {}
+
+impl<'a> IntoIterator for &'a Chunk {
+    type Item = (i32, &'a Section);
+    type IntoIter = Sections<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.sections()
+    }
+}
+
+/// The block at `(x, y, z)`, see [`Chunk::get`] for a lookup that doesn't
+/// panic, and [`Chunk::get_or_air`] for one that doesn't panic on `y`.
+impl Index<(u8, i32, u8)> for Chunk {
+    type Output = Block;
+
+    fn index(&self, (x, y, z): (u8, i32, u8)) -> &Block {
+        self.get(x, y, z)
+            .unwrap_or_else(|| self.out_of_range(x, y, z))
+    }
+}
+
+impl<'a> IntoIterator for &'a mut Chunk {
+    type Item = (i32, &'a mut Section);
+    type IntoIter = SectionsMut<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.sections_mut()
+    }
+}
+
+/// Parses chunk bytes with their 5-byte header, see [`parse_chunk`]. Never
+/// panics, whatever the input.
+impl TryFrom<&[u8]> for Chunk {
+    type Error = ChunkParseError;
+
+    fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+        parse_chunk(bytes)
+    }
+}
+
+/// Returned by [`Chunk::set_block`].
+#[derive(Error, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum SetBlockError {
+    /// `x` or `z` isn't below 16.
+    #[error(transparent)]
+    OutOfChunk(OutOfBounds),
+
+    /// `y` is outside of the chunk's sections.
+    #[error("y {y} is outside of the chunk's sections, which span y {}..{}", sections.start, sections.end)]
+    OutsideSections { y: i16, sections: Range<i32> },
+}
+
+/// Why the data of a section was rejected, see
+/// [`ChunkParseError::InvalidSectionData`].
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum SectionDataError {
+    /// Fewer longs of packed indices than the palette size calls for.
+    #[error("{len} longs of indices, expected at least {expected} for {bits} bits per index")]
+    DataTooShort {
+        len: usize,
+        expected: usize,
+        bits: usize,
+    },
+
+    #[error("palette index {index} out of range (palette has {palette_len} entries)")]
+    IndexOutOfRange { index: u64, palette_len: usize },
+
+    /// An array of a fixed size, like light or legacy block ids.
+    #[error("{len} entries, expected {expected}")]
+    WrongLength { len: usize, expected: usize },
 }
 
 #[derive(Error, Debug)]
@@ -51,136 +1136,1010 @@ pub enum ChunkParseError {
     #[error("the compression format the chunk uses is not supported")]
     UnsupportedCompression,
 
+    /// The identifier of a custom compression no codec was registered for,
+    /// see [`CustomCodecs`].
+    #[error("no codec is registered for the custom compression {0}")]
+    UnknownCodec(String),
+
     #[error("failed to decompress the data: {0}")]
-    DecompressionFailed(std::io::Error),
+    DecompressionFailed(#[source] std::io::Error),
 
     #[error("failed to parse the chunk: {0}")]
-    ParseFailed(ParseError),
+    ParseFailed(#[source] ParseError),
+
+    /// The path to the field, and the type it was expected to have.
+    #[error("the field {0} is missing or has an invalid type, expected {1}")]
+    InvalidField(String, &'static str),
+
+    #[error("the block palette at {0} is invalid")]
+    InvalidPalette(String),
+
+    /// Data of the section at `section_y` doesn't match its palette or has
+    /// the wrong size. `path` leads to the field, and `section_y` is `None`
+    /// for data kept outside of sections.
+    #[error(
+        "{}{path}: {reason}",
+        section_y.map(|y| format!("section y={y}: ")).unwrap_or_default()
+    )]
+    InvalidSectionData {
+        section_y: Option<i32>,
+        path: String,
+        reason: SectionDataError,
+    },
+
+    #[error("the heightmap at {0} is invalid")]
+    InvalidHeightmap(String),
+
+    /// The section y the chunk starts at, and how many sections it has.
+    #[error("{1} sections starting at section y {0} exceed the range of block coordinates")]
+    InvalidYRange(i32, usize),
+
+    #[error("the chunk exceeds the {0} limit")]
+    LimitExceeded(Limit),
+
+    /// The payload inflates to more than `limit` bytes, see
+    /// [`ChunkParseOptions::max_decompressed_len`].
+    #[error("the chunk decompresses to more than {limit} bytes")]
+    DecompressedTooLarge { limit: usize },
+
+    /// The length in the chunk's first 4 bytes, which counts the compression
+    /// byte, runs past the sectors the region allocates it, which hold
+    /// `available` bytes after the length. Only reported for chunks read
+    /// from a region, see
+    /// [`crate::region::RegionParseOptions::recover_lengths`].
+    #[error("the chunk is {declared} bytes long, but its sectors only hold {available}")]
+    LengthExceedsSectors { declared: usize, available: usize },
+
+    /// Only reported for chunks read from a region, see
+    /// [`crate::region::RegionParseOptions::check_slots`].
+    #[error(
+        "the chunk in slot ({}, {}) claims to be at ({}, {})",
+        slot.0,
+        slot.1,
+        pos.x,
+        pos.z
+    )]
+    SlotMismatch { slot: (usize, usize), pos: ChunkPos },
+}
+
+/// The resource limit that tripped, see [`ChunkParseOptions`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Limit {
+    Depth,
+    ListLength,
+    TagCount,
+    StringLength,
+}
+
+impl fmt::Display for Limit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Limit::Depth => "nesting depth",
+            Limit::ListLength => "list length",
+            Limit::TagCount => "tag count",
+            Limit::StringLength => "string length",
+        })
+    }
+}
+
+/// The name [`ChunkParseOptions::lenient`] gives to blocks whose palette
+/// entry has no name.
+pub const UNKNOWN_BLOCK: &str = "mca_rs:unknown";
+
+/// Something [`ChunkParseOptions::lenient`] or [`ChunkParseOptions::salvage`]
+/// worked around instead of failing the chunk, see
+/// [`Chunk::parse_warnings`]. `path` leads to the palette entry.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ParseWarning {
+    /// A palette entry without a `Name` string, read as [`UNKNOWN_BLOCK`].
+    UnnamedBlock { path: String },
+    /// A block name without a namespace, read as a `minecraft:` one like
+    /// the game does.
+    MissingNamespace { path: String, name: String },
+    /// The nbt could only be read up to byte `offset` of the decompressed
+    /// data, `error` is what the parser reported about the rest.
+    Truncated { offset: usize, error: String },
+    /// The packed block indices at `path` hold `index`, which is past the
+    /// end of the palette. It and any others like it read as the first
+    /// entry of the palette.
+    IndexOutOfRange { path: String, index: u64 },
+}
+
+impl fmt::Display for ParseWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseWarning::UnnamedBlock { path } => {
+                write!(f, "{path} has no name, read as {UNKNOWN_BLOCK}")
+            }
+            ParseWarning::MissingNamespace { path, name } => {
+                write!(
+                    f,
+                    "{path}: {name} has no namespace, read as minecraft:{name}"
+                )
+            }
+            ParseWarning::Truncated { offset, error } => {
+                write!(f, "nbt only read up to byte {offset}: {error}")
+            }
+            ParseWarning::IndexOutOfRange { path, index } => {
+                write!(
+                    f,
+                    "{path}: index {index} is past the end of the palette, read as its first \
+                     entry"
+                )
+            }
+        }
+    }
+}
+
+/// Limits for parsing untrusted chunks. The defaults are far above what the
+/// game writes, but keep hostile input from exhausting memory or the stack.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChunkParseOptions {
+    /// The maximum size of the decompressed nbt in bytes, 16 MiB by default.
+    /// Decompression stops as soon as it's exceeded, so a small payload
+    /// inflating to far more is never held in memory whole. Custom codecs
+    /// are the exception, see [`CustomCodecs`].
+    pub max_decompressed_len: usize,
+    /// How deep lists and compounds may be nested.
+    pub max_depth: usize,
+    /// The maximum length of lists and arrays.
+    pub max_list_len: usize,
+    /// The maximum number of tags, counting list elements but not array ones.
+    pub max_tags: usize,
+    /// The maximum length of strings and compound keys, in bytes.
+    pub max_string_len: usize,
+    /// Decompressors for chunks using custom compression.
+    pub custom_codecs: CustomCodecs,
+    /// Only the sections holding blocks in this range of block y are
+    /// decoded, the others are left out of the chunk as if it ended there,
+    /// see [`Chunk::get_y_range`]. Heightmaps are still read for the whole
+    /// chunk.
+    pub y_range: Option<Range<i32>>,
+    /// Keeps the block indices of sections packed as stored until their
+    /// blocks are first looked at, which saves unpacking sections that never
+    /// are. The indices are still checked against the palette when the chunk
+    /// is parsed, so an index past its end fails the chunk unless
+    /// [`ChunkParseOptions::lenient`] is set, and gets a warning if it is,
+    /// like without this. Checking them scans the packed longs without
+    /// unpacking them.
+    pub lazy_sections: bool,
+    /// How the numeric block ids of chunks from before 1.13 are turned into
+    /// blocks.
+    pub legacy_mapping: LegacyMapping,
+    /// Keeps the compound each chunk was decoded from, see
+    /// [`Chunk::raw_nbt`]. It takes about as much memory as the rest of the
+    /// chunk.
+    pub keep_nbt: bool,
+    /// Reads palette entries without a name as [`UNKNOWN_BLOCK`], adds the
+    /// `minecraft:` namespace to names without one, and reads block indices
+    /// past the end of the palette as its first entry, instead of failing
+    /// the chunk or keeping the name as is. What was worked around is kept
+    /// in [`Chunk::parse_warnings`].
+    pub lenient: bool,
+    /// Reads chunks whose nbt breaks off or turns malformed partway up to
+    /// there instead of failing them. The outermost list the break is in
+    /// loses the element it cut short, so a chunk keeps the sections before
+    /// it, and compounds keep the fields read in full. Fields stored after
+    /// the cut are lost, and the chunk still fails if it can't do without
+    /// them. Salvaged chunks are flagged by [`Chunk::is_partial`].
+    pub salvage: bool,
+}
+
+impl Default for ChunkParseOptions {
+    fn default() -> Self {
+        Self {
+            max_decompressed_len: 16 << 20,
+            max_depth: 512,
+            max_list_len: 1 << 20,
+            max_tags: 1 << 20,
+            max_string_len: u16::MAX as usize,
+            custom_codecs: CustomCodecs::default(),
+            y_range: None,
+            lazy_sections: false,
+            legacy_mapping: LegacyMapping::default(),
+            keep_nbt: false,
+            lenient: false,
+            salvage: false,
+        }
+    }
+}
+
+/// A decompressor registered in [`CustomCodecs`].
+pub type Codec = dyn Fn(&[u8]) -> io::Result<Vec<u8>> + Send + Sync;
+
+/// Decompressors for chunks with the custom compression type, keyed by the
+/// namespaced identifier such chunks start with.
+///
+/// [`ChunkParseOptions::max_decompressed_len`] doesn't bound a codec while
+/// it runs, it's only checked against the data the codec returns. A codec
+/// reading untrusted chunks has to stop inflating past the limit itself.
+///
+/// ```
+/// use mca_rs::chunk::{ChunkParseOptions, CustomCodecs};
+///
+/// let mut codecs = CustomCodecs::default();
+/// codecs.register("example:identity", |data| Ok(data.to_vec()));
+/// let options = ChunkParseOptions {
+///     custom_codecs: codecs,
+///     ..Default::default()
+/// };
+/// assert!(options.custom_codecs.get("example:identity").is_some());
+/// ```
+#[derive(Clone, Default)]
+pub struct CustomCodecs(HashMap<String, Arc<Codec>>);
+
+impl CustomCodecs {
+    /// Registers `codec` for `id`, replacing the one registered before.
+    pub fn register(
+        &mut self,
+        id: impl Into<String>,
+        codec: impl Fn(&[u8]) -> io::Result<Vec<u8>> + Send + Sync + 'static,
+    ) {
+        self.0.insert(id.into(), Arc::new(codec));
+    }
+
+    pub fn get(&self, id: &str) -> Option<&Codec> {
+        self.0.get(id).map(|codec| &**codec)
+    }
+}
 
-    #[error("the filed {0} is missing or has an invalid type")]
-    InvalidField(String),
+impl fmt::Debug for CustomCodecs {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.0.keys()).finish()
+    }
+}
 
-    #[error("the block palette is invalid")]
-    InvalidPalette,
+/// Codecs are equal if they're the same closures.
+impl PartialEq for CustomCodecs {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.len() == other.0.len()
+            && self.0.iter().all(|(id, codec)| {
+                other
+                    .0
+                    .get(id)
+                    .is_some_and(|other| Arc::ptr_eq(codec, other))
+            })
+    }
+}
+
+impl Eq for CustomCodecs {}
 
-    #[error("the section data is invalid")]
-    InvalidSectionData,
+/// An air section to add next to `next_to`, taking its biomes.
+fn air_next_to(next_to: Option<&Section>) -> Section {
+    let mut section = Section::empty();
+    if let Some(next_to) = next_to {
+        section.biomes = next_to.biomes.clone();
+    }
+    section
+}
+
+fn expected_type(accessors: &str) -> &'static str {
+    match accessors.replace(' ', "").as_str() {
+        "as_byte" => "byte",
+        "as_int" => "int",
+        "as_string" => "string",
+        "as_compound" => "compound",
+        "as_byte_array" => "byte array",
+        "as_long_array" => "long array",
+        "as_list" => "list",
+        "as_list.as_compound" => "list of compounds",
+        "as_list.as_string" => "list of strings",
+        _ => "value",
+    }
 }
 
 macro_rules! get_field {
-    ($input:ident, $field:literal $(, $($ty:ident).*)? ) => {{
-        try_get_field!($input, $field $(, $($ty).*)?)
-            .ok_or(ChunkParseError::InvalidField($field.to_owned()))?
+    ($input:ident, $field:literal $(, $($ty:ident).*)? ) => {
+        get_field!($input @ "", $field $(, $($ty).*)?)
+    };
+    // the path leads to `$input` and ends with a `.` unless it's empty
+    ($input:ident @ $path:expr, $field:literal $(, $($ty:ident).*)? ) => {{
+        try_get_field!($input, $field $(, $($ty).*)?).ok_or_else(|| {
+            ChunkParseError::InvalidField(
+                format!("{}{}", $path, $field),
+                expected_type(stringify!($($($ty).*)?)),
+            )
+        })?
     }};
 }
 
+pub(crate) fn parse_nbt_with(
+    data: &[u8],
+    options: &ChunkParseOptions,
+) -> Result<NbtCompound, ChunkParseError> {
+    check_limits(data, options).map_err(ChunkParseError::LimitExceeded)?;
+    let (_, decoded) = parse_nbt(data).map_err(ChunkParseError::ParseFailed)?;
+    Ok(decoded)
+}
+
+/// Parses chunk bytes with their 5-byte header. Malformed input of any kind
+/// is reported as an error and never panics, also through `Chunk::try_from`.
+///
+/// Chunks written by any version since McRegion are read into the same
+/// layout, with the numeric block ids from before 1.13 mapped through
+/// [`ChunkParseOptions::legacy_mapping`].
 pub fn parse_chunk(bytes: &[u8]) -> Result<Chunk, ChunkParseError> {
-    if bytes.len() < 5 {
-        return Err(ChunkParseError::InputTooShort(5, bytes.len()));
-    }
-    let (header, body) = bytes.split_at(5);
-    let len = u32::from_be_bytes(header[..4].try_into().unwrap()) as usize;
-    if body.len() < len {
-        return Err(ChunkParseError::InputTooShort(len + 5, bytes.len()));
-    }
-
-    let compression_format = header[4];
-    let raw_data = &body[..len];
-
-    let data = match compression_format {
-        COMPRESSION_GZIP => {
-            let mut decoder = GzDecoder::new(raw_data);
-            let mut decompressed = Vec::new();
-            decoder
-                .read_to_end(&mut decompressed)
-                .map_err(ChunkParseError::DecompressionFailed)?;
-            decompressed
-        }
-        COMPRESSION_ZLIB => {
-            let mut decoder = ZlibDecoder::new(raw_data);
-            let mut decompressed = Vec::new();
-            decoder
-                .read_to_end(&mut decompressed)
-                .map_err(ChunkParseError::DecompressionFailed)?;
-            decompressed
-        }
-        COMPRESSION_RAW => raw_data.to_owned(),
-        _ => return Err(ChunkParseError::UnsupportedCompression),
-    };
-
-    let (_, decoded) = parse_nbt(&data).map_err(ChunkParseError::ParseFailed)?;
-    let &y_pos = get_field!(decoded, "yPos", as_int);
-    let original_sections = get_field!(decoded, "sections", as_list.as_compound);
-
-    let mut sections: Vec<Section> = Vec::with_capacity(original_sections.len());
-    for section in original_sections.iter().cloned() {
-        let section = get_field!(section, "block_states", as_compound);
-        let original_palette = get_field!(section, "palette", as_list.as_compound);
-        let palette_len = original_palette.len();
-        if palette_len == 0 && palette_len > 4096 {
-            return Err(ChunkParseError::InvalidPalette);
-        }
-
-        let mut palette: Vec<Rc<Block>> = Vec::new();
-        for block in original_palette.iter() {
-            let name = get_field!(block, "Name", as_string).clone();
-            let properties = try_get_field!(block, "Properties", as_compound).cloned();
-            palette.push(Rc::new(Block { name, properties }));
-        }
-
-        let bits_per_index = max(
-            4,
-            (usize::BITS - (palette_len - 1).leading_zeros()) as usize,
-        );
-
-        if palette_len == 1 {
-            sections.push(Section {
-                blocks: vec![palette[0].clone(); 4096].try_into().unwrap(),
-            });
-            continue;
+    parse_chunk_with(bytes, &ChunkParseOptions::default())
+}
+
+pub fn parse_chunk_with(
+    bytes: &[u8],
+    options: &ChunkParseOptions,
+) -> Result<Chunk, ChunkParseError> {
+    RawChunk::parse(bytes)?.decode_with(options)
+}
+
+/// Like [`parse_chunk`], but decompresses into `buffer`, see
+/// [`RawChunk::decode_with_buffer`].
+pub fn parse_chunk_with_buffer(
+    bytes: &[u8],
+    buffer: &mut Vec<u8>,
+) -> Result<Chunk, ChunkParseError> {
+    RawChunk::parse(bytes)?.decode_with_buffer(&ChunkParseOptions::default(), buffer)
+}
+
+/// Decompresses and parses the chunk like [`parse_chunk`], but returns the
+/// compound as it is instead of decoding it, see [`RawChunk::decode_nbt`].
+pub fn parse_chunk_nbt(bytes: &[u8]) -> Result<NbtCompound, ChunkParseError> {
+    RawChunk::parse(bytes)?.decode_nbt(&ChunkParseOptions::default())
+}
+
+/// The fields at the root of a chunk, without its blocks or anything else,
+/// see [`parse_chunk_metadata`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ChunkMetadata {
+    pub x_pos: i32,
+    /// The section y the chunk starts at. Chunks from before 1.18 rarely
+    /// store it, it's 0 for those.
+    pub y_pos: i32,
+    pub z_pos: i32,
+    pub data_version: i32,
+    pub status: ChunkStatus,
+    pub last_update: i64,
+    pub inhabited_time: i64,
+}
+
+/// Like [`parse_chunk`], but only reads the fields of [`ChunkMetadata`], so
+/// the sections and everything else aren't decoded.
+pub fn parse_chunk_metadata(bytes: &[u8]) -> Result<ChunkMetadata, ChunkParseError> {
+    RawChunk::parse(bytes)?.decode_metadata(&ChunkParseOptions::default())
+}
+
+pub(crate) fn parse_metadata_compound(
+    decoded: &NbtCompound,
+) -> Result<ChunkMetadata, ChunkParseError> {
+    let data_version = try_get_field!(decoded, "DataVersion", as_int).map_or(0, |&v| v);
+    let status = parse_chunk_status(decoded);
+    let (root, path, y_pos) = match try_get_field!(decoded, "Level", as_compound) {
+        Some(level) => {
+            let y_pos = try_get_field!(level, "yPos", as_int).map_or(0, |&y| y);
+            (level, "Level.", y_pos)
         }
+        None => (decoded, "", *get_field!(decoded, "yPos", as_int)),
+    };
+    Ok(ChunkMetadata {
+        x_pos: *get_field!(root @ path, "xPos", as_int),
+        y_pos,
+        z_pos: *get_field!(root @ path, "zPos", as_int),
+        data_version,
+        status,
+        last_update: try_get_field!(root, "LastUpdate", as_long).map_or(0, |&v| v),
+        inhabited_time: try_get_field!(root, "InhabitedTime", as_long).map_or(0, |&v| v),
+    })
+}
+
+pub(crate) fn parse_chunk_status(decoded: &NbtCompound) -> ChunkStatus {
+    let root = try_get_field!(decoded, "Level", as_compound).unwrap_or(decoded);
+    try_get_field!(root, "Status", as_string)
+        .map_or(ChunkStatus::Full, |status| ChunkStatus::parse(status))
+}
+
+/// The data version of 17w47a, which replaced numeric block ids with
+/// palettes.
+pub(crate) const FLATTENING_VERSION: i32 = 1451;
+
+/// The data version of 20w17a, which stopped palette indices from spanning
+/// two longs.
+pub(crate) const PADDED_INDICES_VERSION: i32 = 2529;
+
+/// Blocks are shared through `interner` if there is one, otherwise only
+/// their names are shared within the chunk. Only the sections
+/// in [`ChunkParseOptions::y_range`] are decoded, except for McRegion chunks,
+/// which are only 8 sections high.
+pub(crate) fn parse_chunk_compound(
+    decoded: &NbtCompound,
+    options: &ChunkParseOptions,
+    interner: Option<&mut BlockInterner>,
+) -> Result<Chunk, ChunkParseError> {
+    let data_version = try_get_field!(decoded, "DataVersion", as_int).map_or(0, |&v| v);
+    let status = parse_chunk_status(decoded);
+    // chunks without a version are assumed to be new enough
+    let spanning = data_version != 0 && data_version < PADDED_INDICES_VERSION;
+    let mut names_only = None;
+    let interner = match interner {
+        Some(interner) => interner,
+        None => names_only.insert(BlockInterner::names_only()),
+    };
+    let decode = |section_y: i32| {
+        options.y_range.as_ref().is_none_or(|range| {
+            let min = section_y as i64 * 16;
+            (range.start as i64) < min + 16 && min < range.end as i64
+        })
+    };
 
-        let data: Vec<i64> = get_field!(section, "data", as_long_array).to_vec();
+    // McRegion chunks from before 1.2 keep their blocks in the level itself
+    if let Some(level) = try_get_field!(decoded, "Level", as_compound)
+        && level.get("Sections").is_none()
+        && level.get("Blocks").is_some()
+    {
+        return parse_alpha_compound(decoded, options.legacy_mapping.get());
+    }
 
-        if data.len() < bits_per_index * 64 {
-            return Err(ChunkParseError::InvalidSectionData);
+    // before 1.18, everything but the version is in a `Level` compound
+    let mut legacy_sections = None;
+    let mut warnings = Vec::new();
+    let (root, path, y_pos, x_pos, z_pos, sections) =
+        match try_get_field!(decoded, "Level", as_compound) {
+            Some(level) => {
+                let &x_pos = get_field!(level @ "Level.", "xPos", as_int);
+                let &z_pos = get_field!(level @ "Level.", "zPos", as_int);
+                let stored = parse_level_sections(
+                    level,
+                    data_version,
+                    spanning,
+                    options,
+                    interner,
+                    &mut warnings,
+                    decode,
+                )?;
+                legacy_sections = stored.legacy;
+                (level, "Level.", stored.y_pos, x_pos, z_pos, stored.sections)
+            }
+            None => {
+                let &y_pos = get_field!(decoded, "yPos", as_int);
+                let &x_pos = get_field!(decoded, "xPos", as_int);
+                let &z_pos = get_field!(decoded, "zPos", as_int);
+                let sections = parse_sections(
+                    decoded,
+                    y_pos,
+                    spanning,
+                    options,
+                    interner,
+                    &mut warnings,
+                    decode,
+                )?;
+                (decoded, "", y_pos, x_pos, z_pos, sections)
+            }
+        };
+
+    // called tile entities and tile and liquid ticks before 1.18
+    let [block_entities_field, block_ticks_field, fluid_ticks_field] = if path.is_empty() {
+        ["block_entities", "block_ticks", "fluid_ticks"]
+    } else {
+        ["TileEntities", "TileTicks", "LiquidTicks"]
+    };
+    let block_entities = parse_block_entities(root, path, block_entities_field)?;
+    let block_ticks = parse_ticks(root, path, block_ticks_field)?;
+    let fluid_ticks = parse_ticks(root, path, fluid_ticks_field)?;
+    let [structures_field, starts_field] = if path.is_empty() {
+        ["structures", "starts"]
+    } else {
+        ["Structures", "Starts"]
+    };
+    let (structure_references, structure_starts) =
+        parse_structures(root, path, structures_field, starts_field)?;
+    let entities = if path.is_empty() {
+        Vec::new()
+    } else {
+        parse_entities(root, path)?
+    };
+    let last_update = try_get_field!(root, "LastUpdate", as_long).map_or(0, |&v| v);
+    let inhabited_time = try_get_field!(root, "InhabitedTime", as_long).map_or(0, |&v| v);
+    let below_zero_retrogen = match root.get("below_zero_retrogen") {
+        None => None,
+        Some(tag) => {
+            let path = format!("{path}below_zero_retrogen");
+            let compound = (tag.as_compound())
+                .ok_or_else(|| ChunkParseError::InvalidField(path.clone(), "compound"))?;
+            Some(BelowZeroRetrogen::parse(compound, &path)?)
         }
+    };
+    let blending_data = match root.get("blending_data") {
+        None => None,
+        Some(tag) => Some(tag.as_compound().cloned().ok_or_else(|| {
+            ChunkParseError::InvalidField(format!("{path}blending_data"), "compound")
+        })?),
+    };
 
-        let mut blocks: Vec<Rc<Block>> = Vec::with_capacity(4096);
-        let mask: u64 = (1u64 << bits_per_index) - 1;
-        let mut long_idx = 0;
-        let mut bit_offset = 0;
-        for _ in 0..4096 {
-            if bit_offset + bits_per_index as usize > 64 {
-                long_idx += 1;
-                bit_offset = 0;
-                let index = (data[long_idx] as u64 & mask) as usize;
-                if index >= palette_len {
-                    return Err(ChunkParseError::InvalidSectionData);
-                }
-                blocks.push(palette[index].clone());
-                bit_offset += bits_per_index as usize;
+    // block y coordinates have to fit in an i32
+    let end = (y_pos as i64 + sections.len() as i64) * 16;
+    if (y_pos as i64 * 16) < i32::MIN as i64 || end > i32::MAX as i64 {
+        return Err(ChunkParseError::InvalidYRange(y_pos, sections.len()));
+    }
+
+    let mut heightmaps = BTreeMap::new();
+    if let Some(stored) = try_get_field!(root, "Heightmaps", as_compound) {
+        let (min_y, height) = (y_pos * 16, sections.len() * 16);
+        for kind in HeightmapKind::ALL {
+            let Some(data) = stored.get(kind.name()).and_then(|tag| tag.as_long_array()) else {
                 continue;
-            }
+            };
+            let heightmap = Heightmap::unpack(data, min_y, height, spanning).ok_or_else(|| {
+                ChunkParseError::InvalidHeightmap(format!("{path}Heightmaps.{}", kind.name()))
+            })?;
+            heightmaps.insert(kind, heightmap);
+        }
+    }
+
+    // the range is contiguous, so only the top and bottom sections are left out
+    let first = sections.iter().position(Option::is_some).unwrap_or(0);
+    let y_pos = y_pos + first as i32;
+    let sections = sections.into_iter().flatten().collect();
+
+    Ok(Chunk {
+        x_pos,
+        y_pos,
+        z_pos,
+        data_version,
+        status,
+        last_update,
+        inhabited_time,
+        sections,
+        heightmaps,
+        block_entities,
+        entities,
+        block_ticks,
+        fluid_ticks,
+        structure_references,
+        structure_starts,
+        below_zero_retrogen,
+        blending_data,
+        legacy_sections,
+        raw_nbt: None,
+        warnings,
+        air: LazyAir::default(),
+    })
+}
+
+/// Reads the scheduled ticks in the list `field` of `root`, `path` leads to
+/// `root`.
+fn parse_ticks(
+    root: &NbtCompound,
+    path: &str,
+    field: &str,
+) -> Result<Vec<ScheduledTick>, ChunkParseError> {
+    let path = format!("{path}{field}");
+    let stored: &[NbtCompound] = match root.get(field) {
+        None | Some(NbtTag::List(NbtList::End)) => &[],
+        Some(tag) => tag
+            .as_list()
+            .and_then(|list| list.as_compound())
+            .ok_or_else(|| {
+                ChunkParseError::InvalidField(path.clone(), expected_type("as_list.as_compound"))
+            })?,
+    };
+
+    let mut ticks = Vec::with_capacity(stored.len());
+    for (i, tick) in stored.iter().enumerate() {
+        let path = format!("{path}[{i}].");
+        ticks.push(ScheduledTick {
+            target: get_field!(tick @ path, "i", as_string).clone(),
+            x: *get_field!(tick @ path, "x", as_int),
+            y: *get_field!(tick @ path, "y", as_int),
+            z: *get_field!(tick @ path, "z", as_int),
+            delay: *get_field!(tick @ path, "t", as_int),
+            priority: *get_field!(tick @ path, "p", as_int),
+        });
+    }
+    Ok(ticks)
+}
+
+/// Reads the block entities in the list `field` of `root`, `path` leads to
+/// `root`.
+fn parse_block_entities(
+    root: &NbtCompound,
+    path: &str,
+    field: &str,
+) -> Result<Vec<BlockEntity>, ChunkParseError> {
+    let path = format!("{path}{field}");
+    let stored: &[NbtCompound] = match root.get(field) {
+        None | Some(NbtTag::List(NbtList::End)) => &[],
+        Some(tag) => tag
+            .as_list()
+            .and_then(|list| list.as_compound())
+            .ok_or_else(|| {
+                ChunkParseError::InvalidField(path.clone(), expected_type("as_list.as_compound"))
+            })?,
+    };
+
+    let mut block_entities = Vec::with_capacity(stored.len());
+    for (i, data) in stored.iter().enumerate() {
+        let path = format!("{path}[{i}].");
+        let id = get_field!(data @ path, "id", as_string).clone();
+        let &x = get_field!(data @ path, "x", as_int);
+        let &y = get_field!(data @ path, "y", as_int);
+        let &z = get_field!(data @ path, "z", as_int);
+        block_entities.push(BlockEntity {
+            id,
+            x,
+            y,
+            z,
+            data: data.clone(),
+        });
+    }
+    block_entities.sort_by_key(BlockEntity::key);
+    Ok(block_entities)
+}
+
+/// Returns `None` for the sections `decode` rejects, which it gets the y of.
+fn parse_sections(
+    decoded: &NbtCompound,
+    y_pos: i32,
+    spanning: bool,
+    options: &ChunkParseOptions,
+    interner: &mut BlockInterner,
+    warnings: &mut Vec<ParseWarning>,
+    decode: impl Fn(i32) -> bool,
+) -> Result<Vec<Option<Section>>, ChunkParseError> {
+    let sections = get_field!(decoded, "sections", as_list.as_compound);
+    let mut parsed = Vec::with_capacity(sections.len());
+    for (i, section) in sections.iter().enumerate() {
+        if !decode(y_pos.saturating_add(i as i32)) {
+            trace::debug!(
+                section_y = y_pos.saturating_add(i as i32),
+                "skipping section outside the y range"
+            );
+            parsed.push(None);
+            continue;
+        }
+        let path = format!("sections[{i}].block_states.");
+        let states = get_field!(section @ format!("sections[{i}]."), "block_states", as_compound);
+        let palette = get_field!(states @ path, "palette", as_list.as_compound);
+        let y = y_pos.saturating_add(i as i32);
+        let at = StatesAt {
+            section_y: y,
+            palette: format!("{path}palette"),
+            data: format!("{path}data"),
+        };
+        let data = || Ok(&get_field!(states @ path, "data", as_long_array)[..]);
+        let mut blocks =
+            parse_block_states(palette, &at, spanning, options, interner, warnings, data)?;
+        if let Some(biomes) = try_get_field!(section, "biomes", as_compound) {
+            blocks.biomes = parse_biomes(biomes, &format!("sections[{i}].biomes."), y)?;
+        }
+        let path = format!("sections[{i}].");
+        blocks.block_light = parse_light(section, &path, "BlockLight", y)?;
+        blocks.sky_light = parse_light(section, &path, "SkyLight", y)?;
+        parsed.push(Some(blocks));
+    }
+    Ok(parsed)
+}
+
+/// Where the block states of a section are, for errors.
+struct StatesAt {
+    section_y: i32,
+    palette: String,
+    data: String,
+}
+
+/// Decodes a section from its block palette and the packed indices `data`
+/// gives, which is only asked for if the palette has more than one entry.
+/// The indices are unpacked on first use with
+/// [`ChunkParseOptions::lazy_sections`], and what
+/// [`ChunkParseOptions::lenient`] works around is added to `warnings`.
+fn parse_block_states<'a>(
+    original_palette: &[NbtCompound],
+    at: &StatesAt,
+    spanning: bool,
+    options: &ChunkParseOptions,
+    interner: &mut BlockInterner,
+    warnings: &mut Vec<ParseWarning>,
+    data: impl FnOnce() -> Result<&'a [i64], ChunkParseError>,
+) -> Result<Section, ChunkParseError> {
+    let palette_len = original_palette.len();
+    if palette_len == 0 || palette_len > 4096 {
+        return Err(ChunkParseError::InvalidPalette(at.palette.clone()));
+    }
 
-            let long = data[long_idx] as u64;
-            let index = ((long >> bit_offset) & mask) as usize;
-            if index >= palette_len {
-                return Err(ChunkParseError::InvalidSectionData);
+    let mut palette: Vec<Shared<Block>> = Vec::new();
+    for (j, block) in original_palette.iter().enumerate() {
+        let properties = try_get_field!(block, "Properties", as_compound).cloned();
+        if !options.lenient {
+            let name = get_field!(block @ format!("{}[{j}].", at.palette), "Name", as_string);
+            palette.push(interner.block(name, properties));
+            continue;
+        }
+        let path = || format!("{}[{j}]", at.palette);
+        let block = match try_get_field!(block, "Name", as_string) {
+            None => {
+                let warning = ParseWarning::UnnamedBlock { path: path() };
+                trace::debug!(%warning, "substituting a palette entry");
+                warnings.push(warning);
+                interner.block(UNKNOWN_BLOCK, properties)
             }
-            blocks.push(palette[index].clone());
-            bit_offset += bits_per_index as usize;
+            Some(name) if !name.contains(':') => {
+                let warning = ParseWarning::MissingNamespace {
+                    path: path(),
+                    name: name.to_string(),
+                };
+                trace::debug!(%warning, "substituting a palette entry");
+                warnings.push(warning);
+                interner.block(&format!("minecraft:{name}"), properties)
+            }
+            Some(name) => interner.block(name, properties),
+        };
+        palette.push(block);
+    }
+
+    let bits_per_index = max(
+        4,
+        (usize::BITS - (palette_len - 1).leading_zeros()) as usize,
+    );
+
+    if palette_len == 1 {
+        return Ok(Section::filled_shared(palette[0].clone()));
+    }
 
-            if bit_offset == 64 {
-                long_idx += 1;
-                bit_offset = 0;
+    let data = data()?;
+    let too_short = || ChunkParseError::InvalidSectionData {
+        section_y: Some(at.section_y),
+        path: at.data.clone(),
+        reason: SectionDataError::DataTooShort {
+            len: data.len(),
+            expected: packed::packed_len(bits_per_index, 4096, spanning),
+            bits: bits_per_index,
+        },
+    };
+    if options.lazy_sections {
+        let section =
+            Section::from_packed(palette, data, bits_per_index, spanning).ok_or_else(too_short)?;
+        // with as many entries as the bits can tell apart, none can be past
+        // the end
+        if palette_len < 1 << bits_per_index {
+            let limit = palette_len as u64;
+            let found = packed::find_at_least(data, bits_per_index, 4096, spanning, limit);
+            if let Some(index) = found {
+                index_out_of_range(index, palette_len, at, options, warnings)?;
             }
         }
+        return Ok(section);
+    }
+    let indices = if spanning {
+        packed::unpack_spanning(data, bits_per_index, 4096)
+    } else {
+        packed::unpack(data, bits_per_index, 4096)
+    };
+    let mut indices = indices.ok_or_else(too_short)?;
+    let limit = palette_len as u64;
+    if let Some(&index) = indices.iter().find(|&&index| index >= limit) {
+        index_out_of_range(index, palette_len, at, options, warnings)?;
+        for index in indices.iter_mut().filter(|index| **index >= limit) {
+            *index = 0;
+        }
+    }
+
+    Ok(Section::from_palette(&palette, &indices))
+}
 
-        sections.push(Section {
-            blocks: blocks.try_into().unwrap(),
+/// Fails on `index`, the first index past the end of the palette, or with
+/// [`ChunkParseOptions::lenient`] adds a warning that it's read as the first
+/// entry.
+fn index_out_of_range(
+    index: u64,
+    palette_len: usize,
+    at: &StatesAt,
+    options: &ChunkParseOptions,
+    warnings: &mut Vec<ParseWarning>,
+) -> Result<(), ChunkParseError> {
+    if !options.lenient {
+        return Err(ChunkParseError::InvalidSectionData {
+            section_y: Some(at.section_y),
+            path: at.data.clone(),
+            reason: SectionDataError::IndexOutOfRange { index, palette_len },
         });
     }
+    let warning = ParseWarning::IndexOutOfRange {
+        path: at.data.clone(),
+        index,
+    };
+    trace::debug!(%warning, "substituting out of range block indices");
+    warnings.push(warning);
+    Ok(())
+}
+
+/// Fails on the first of `indices` past the end of the palette.
+fn check_indices(
+    indices: &[u64],
+    palette_len: usize,
+    section_y: i32,
+    path: &str,
+) -> Result<(), ChunkParseError> {
+    match indices.iter().find(|&&index| index as usize >= palette_len) {
+        None => Ok(()),
+        Some(&index) => Err(ChunkParseError::InvalidSectionData {
+            section_y: Some(section_y),
+            path: path.to_owned(),
+            reason: SectionDataError::IndexOutOfRange { index, palette_len },
+        }),
+    }
+}
+
+/// Decodes the biomes of the section at `section_y`, `path` leads to their
+/// compound.
+fn parse_biomes(
+    biomes: &NbtCompound,
+    path: &str,
+    section_y: i32,
+) -> Result<Vec<Shared<NbtString>>, ChunkParseError> {
+    let palette = get_field!(biomes @ path, "palette", as_list.as_string);
+    if palette.is_empty() || palette.len() > 64 {
+        return Err(ChunkParseError::InvalidPalette(format!("{path}palette")));
+    }
+    let palette: Vec<Shared<NbtString>> = palette.iter().cloned().map(Shared::new).collect();
+    if palette.len() == 1 {
+        return Ok(vec![palette[0].clone(); 64]);
+    }
+
+    let data = get_field!(biomes @ path, "data", as_long_array);
+    let (bits, data_path) = (packed::bits_for(palette.len() - 1), format!("{path}data"));
+    let indices =
+        packed::unpack(data, bits, 64).ok_or_else(|| ChunkParseError::InvalidSectionData {
+            section_y: Some(section_y),
+            path: data_path.clone(),
+            reason: SectionDataError::DataTooShort {
+                len: data.len(),
+                expected: packed::packed_len(bits, 64, false),
+                bits,
+            },
+        })?;
+    check_indices(&indices, palette.len(), section_y, &data_path)?;
+    Ok(indices
+        .iter()
+        .map(|&index| palette[index as usize].clone())
+        .collect())
+}
+
+/// Reads a light array of the section at `section_y`, which is optional.
+fn parse_light(
+    section: &NbtCompound,
+    path: &str,
+    field: &str,
+    section_y: i32,
+) -> Result<Option<Box<[u8; 2048]>>, ChunkParseError> {
+    let Some(tag) = section.get(field) else {
+        return Ok(None);
+    };
+    let light = tag.as_byte_array().ok_or_else(|| {
+        ChunkParseError::InvalidField(format!("{path}{field}"), expected_type("as_byte_array"))
+    })?;
+    let light: [u8; 2048] = light
+        .iter()
+        .map(|&nibbles| nibbles as u8)
+        .collect::<Vec<_>>()
+        .try_into()
+        .map_err(|_| ChunkParseError::InvalidSectionData {
+            section_y: Some(section_y),
+            path: format!("{path}{field}"),
+            reason: SectionDataError::WrongLength {
+                len: light.len(),
+                expected: 2048,
+            },
+        })?;
+    Ok(Some(Box::new(light)))
+}
+
+/// What [`parse_level_sections`] returns.
+struct LevelSections {
+    y_pos: i32,
+    sections: Vec<Option<Section>>,
+    legacy: Option<Vec<LegacySection>>,
+}
+
+/// Reads the sections of a chunk from before 1.18, where only the ones that
+/// aren't empty are stored, along with ones only holding light. Returns the
+/// y of the lowest section, and the sections filled up with air to cover at
+/// least the 0 to 256 height of those versions, `None` for the ones
+/// `decode` rejects. Sections from before 1.13 are flattened with
+/// [`ChunkParseOptions::legacy_mapping`] and also returned as they are
+/// stored, which they are for every chunk with such sections or a data
+/// version from before then.
+fn parse_level_sections(
+    level: &NbtCompound,
+    data_version: i32,
+    spanning: bool,
+    options: &ChunkParseOptions,
+    interner: &mut BlockInterner,
+    warnings: &mut Vec<ParseWarning>,
+    decode: impl Fn(i32) -> bool,
+) -> Result<LevelSections, ChunkParseError> {
+    let invalid_sections = || {
+        ChunkParseError::InvalidField(
+            "Level.Sections".to_owned(),
+            expected_type("as_list.as_compound"),
+        )
+    };
+    let stored: &[NbtCompound] = match try_get_field!(level, "Sections", as_list) {
+        None | Some(NbtList::End) => &[],
+        Some(list) => list.as_compound().ok_or_else(invalid_sections)?,
+    };
+
+    let mut legacy = (data_version != 0 && data_version < FLATTENING_VERSION).then(Vec::new);
+    let mut mapped = HashMap::new();
+    let mut by_y = BTreeMap::new();
+    for (i, section) in stored.iter().enumerate() {
+        let path = format!("Level.Sections[{i}].");
+        let Some(palette) = try_get_field!(section, "Palette", as_list.as_compound) else {
+            // numeric block ids from before 1.13
+            if section.get("Blocks").is_some() {
+                let stored = LegacySection::parse(section, &path)?;
+                if decode(stored.y()) {
+                    let mut blocks =
+                        stored.flatten(options.legacy_mapping.get(), interner, &mut mapped);
+                    blocks.block_light = parse_light(section, &path, "BlockLight", stored.y())?;
+                    blocks.sky_light = parse_light(section, &path, "SkyLight", stored.y())?;
+                    by_y.insert(stored.y(), blocks);
+                    legacy.get_or_insert_with(Vec::new).push(stored);
+                } else {
+                    trace::debug!(
+                        section_y = stored.y(),
+                        "skipping section outside the y range"
+                    );
+                }
+            } else {
+                // proto-chunks store sections with only light
+                trace::debug!(section = i, "skipping section without blocks");
+            }
+            continue;
+        };
+        let &y = get_field!(section @ path, "Y", as_byte);
+        if !decode(y as i32) {
+            trace::debug!(section_y = y, "skipping section outside the y range");
+            continue;
+        }
+        let at = StatesAt {
+            section_y: y as i32,
+            palette: format!("{path}Palette"),
+            data: format!("{path}BlockStates"),
+        };
+        let data = || Ok(&get_field!(section @ path, "BlockStates", as_long_array)[..]);
+        let mut blocks =
+            parse_block_states(palette, &at, spanning, options, interner, warnings, data)?;
+        blocks.block_light = parse_light(section, &path, "BlockLight", y as i32)?;
+        blocks.sky_light = parse_light(section, &path, "SkyLight", y as i32)?;
+        by_y.insert(y as i32, blocks);
+    }
 
-    Ok(Chunk { y_pos, sections })
+    let stored_ys = stored.iter().filter_map(|section| {
+        (section.get("Palette").or_else(|| section.get("Blocks")))?;
+        try_get_field!(section, "Y", as_byte).map(|&y| y as i32)
+    });
+    let (lowest, highest) = stored_ys.fold((0, 15), |(lowest, highest), y| {
+        (lowest.min(y), highest.max(y))
+    });
+    let y_pos = try_get_field!(level, "yPos", as_int).map_or(lowest, |&y| y);
+    let top = highest + 1;
+    // section ys are bytes, but the position isn't bounded
+    if !SECTION_Y_LIMITS.contains(&y_pos) {
+        return Err(ChunkParseError::InvalidYRange(
+            y_pos,
+            top.saturating_sub(y_pos).max(0) as usize,
+        ));
+    }
+    let air = Shared::new(Block::air());
+    let sections = (y_pos..top)
+        .map(|y| {
+            decode(y).then(|| {
+                by_y.remove(&y)
+                    .unwrap_or_else(|| Section::filled_shared(air.clone()))
+            })
+        })
+        .collect();
+    if let Some(legacy) = &mut legacy {
+        legacy.sort_by_key(LegacySection::y);
+    }
+    Ok(LevelSections {
+        y_pos,
+        sections,
+        legacy,
+    })
 }
@@ -0,0 +1,243 @@
+use std::{cmp::Ordering, collections::HashMap};
+
+use crate::{
+    chunks::{
+        block::{Block, Shared},
+        block_entity::BlockEntity,
+        chunk::Chunk,
+    },
+    region::Region,
+};
+
+/// A block that differs between two chunks, in chunk coordinates.
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+pub struct BlockChange {
+    pub x: u8,
+    pub y: i32,
+    pub z: u8,
+    pub before: Block,
+    pub after: Block,
+}
+
+/// A block entity that differs between two chunks, by its position.
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+pub enum BlockEntityChange {
+    /// Only the second chunk has a block entity at the position.
+    Added(BlockEntity),
+    /// Only the first chunk has a block entity at the position.
+    Removed(BlockEntity),
+    /// Both chunks have a block entity at the position, with a different id
+    /// or data.
+    Changed {
+        before: BlockEntity,
+        after: BlockEntity,
+    },
+}
+
+/// The blocks and block entities that differ between two chunks, see
+/// [`Chunk::diff`].
+#[derive(Debug, Clone, Default, PartialEq, PartialOrd)]
+pub struct ChunkDiff {
+    changes: Vec<BlockChange>,
+    block_entities: Vec<BlockEntityChange>,
+}
+
+impl ChunkDiff {
+    /// The changed blocks, section by section from the bottom up, and in
+    /// `y`, `z`, `x` order within a section.
+    pub fn changes(&self) -> &[BlockChange] {
+        &self.changes
+    }
+
+    /// The changed block entities, in `y`, `z`, `x` order.
+    pub fn block_entity_changes(&self) -> &[BlockEntityChange] {
+        &self.block_entities
+    }
+
+    /// Whether neither blocks nor block entities changed.
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty() && self.block_entities.is_empty()
+    }
+
+    /// The number of changed blocks.
+    pub fn len(&self) -> usize {
+        self.changes.len()
+    }
+}
+
+impl IntoIterator for ChunkDiff {
+    type Item = BlockChange;
+    type IntoIter = std::vec::IntoIter<BlockChange>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.changes.into_iter()
+    }
+}
+
+/// How a chunk differs between two regions, see [`Region::diff`].
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+pub enum ChunkChange {
+    /// Only the second region has a chunk in the slot.
+    Added,
+    /// Only the first region has a chunk in the slot.
+    Removed,
+    /// Both regions have a chunk in the slot, with different blocks or
+    /// block entities.
+    Changed(ChunkDiff),
+}
+
+/// The chunks that differ between two regions, see [`Region::diff`].
+#[derive(Debug, Clone, Default, PartialEq, PartialOrd)]
+pub struct RegionDiff {
+    chunks: Vec<(usize, usize, ChunkChange)>,
+}
+
+impl RegionDiff {
+    /// The slots whose chunk changed, `x` before `z`.
+    pub fn chunks(&self) -> &[(usize, usize, ChunkChange)] {
+        &self.chunks
+    }
+
+    /// Returns `None` if the chunk in the slot didn't change.
+    pub fn get(&self, x: usize, z: usize) -> Option<&ChunkChange> {
+        self.chunks
+            .iter()
+            .find(|(chunk_x, chunk_z, _)| (*chunk_x, *chunk_z) == (x, z))
+            .map(|(_, _, change)| change)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.chunks.is_empty()
+    }
+
+    /// The number of changed chunks.
+    pub fn len(&self) -> usize {
+        self.chunks.len()
+    }
+}
+
+impl IntoIterator for RegionDiff {
+    type Item = (usize, usize, ChunkChange);
+    type IntoIter = std::vec::IntoIter<(usize, usize, ChunkChange)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.chunks.into_iter()
+    }
+}
+
+impl Chunk {
+    /// The blocks and block entities that differ from `self` to `other`.
+    /// Nothing else is compared, not the position or any other metadata.
+    /// Where only one of the chunks has a section, its blocks are compared
+    /// against air. Block entities are paired by their position.
+    ///
+    /// Sections with the same palette and indices are skipped without
+    /// looking at their blocks, so diffing a chunk against an unchanged copy
+    /// is cheap.
+    pub fn diff(&self, other: &Chunk) -> ChunkDiff {
+        let (before, after) = (self.section_y_range(), other.section_y_range());
+        let range = before.start.min(after.start)..before.end.max(after.end);
+        let air = [Shared::new(Block::air())];
+
+        let mut changes = Vec::new();
+        for section_y in range {
+            let sections = [self, other].map(|chunk| chunk.get_section(section_y));
+            if let [Some(before), Some(after)] = sections
+                && before.same_blocks(after)
+            {
+                continue;
+            }
+            let [before, after] =
+                sections.map(|section| section.map_or(&air[..], |section| &section.palette[..]));
+
+            // equal blocks get the same id, so each block is one comparison
+            let mut ids = HashMap::new();
+            let [before_ids, after_ids] = [before, after].map(|palette| {
+                (palette.iter())
+                    .map(|block| {
+                        let next = ids.len();
+                        *ids.entry(&**block).or_insert(next)
+                    })
+                    .collect::<Vec<_>>()
+            });
+            let [a, b] = sections.map(|section| {
+                (0..4096).map(move |i| section.map_or(0, |section| section.palette_index(i)))
+            });
+            for (i, (a, b)) in a.zip(b).enumerate() {
+                if before_ids[a] == after_ids[b] {
+                    continue;
+                }
+                changes.push(BlockChange {
+                    x: (i & 0xF) as u8,
+                    y: section_y * 16 + (i >> 8) as i32,
+                    z: ((i >> 4) & 0xF) as u8,
+                    before: Block::clone(&before[a]),
+                    after: Block::clone(&after[b]),
+                });
+            }
+        }
+        ChunkDiff {
+            changes,
+            block_entities: diff_block_entities(&self.block_entities, &other.block_entities),
+        }
+    }
+}
+
+/// Both lists are sorted by [`BlockEntity::key`].
+fn diff_block_entities(before: &[BlockEntity], after: &[BlockEntity]) -> Vec<BlockEntityChange> {
+    let mut changes = Vec::new();
+    let (mut before, mut after) = (before.iter().peekable(), after.iter().peekable());
+    loop {
+        let order = match (before.peek(), after.peek()) {
+            (None, None) => break,
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (Some(a), Some(b)) => a.key().cmp(&b.key()),
+        };
+        match order {
+            Ordering::Less => {
+                changes.push(BlockEntityChange::Removed(before.next().unwrap().clone()))
+            }
+            Ordering::Greater => {
+                changes.push(BlockEntityChange::Added(after.next().unwrap().clone()))
+            }
+            Ordering::Equal => {
+                let (a, b) = (before.next().unwrap(), after.next().unwrap());
+                if a != b {
+                    changes.push(BlockEntityChange::Changed {
+                        before: a.clone(),
+                        after: b.clone(),
+                    });
+                }
+            }
+        }
+    }
+    changes
+}
+
+impl Region {
+    /// The chunks that differ from `self` to `other`, paired by slot. Chunks
+    /// that failed to decode count as missing, see [`Chunk::diff`] for how
+    /// chunks are compared.
+    pub fn diff(&self, other: &Region) -> RegionDiff {
+        let mut chunks = Vec::new();
+        for z in 0..32 {
+            for x in 0..32 {
+                let change = match (self.get_chunk(x, z), other.get_chunk(x, z)) {
+                    (None, None) => continue,
+                    (None, Some(_)) => ChunkChange::Added,
+                    (Some(_), None) => ChunkChange::Removed,
+                    (Some(before), Some(after)) => {
+                        let diff = before.diff(after);
+                        if diff.is_empty() {
+                            continue;
+                        }
+                        ChunkChange::Changed(diff)
+                    }
+                };
+                chunks.push((x, z, change));
+            }
+        }
+        RegionDiff { chunks }
+    }
+}
@@ -0,0 +1,616 @@
+//! Writing chunks back to NBT, the reverse of [`crate::chunk::parse_chunk`].
+
+use std::{collections::HashMap, io::Write};
+
+use flate2::write::{GzEncoder, ZlibEncoder};
+use nbt_rs::{
+    serialize_nbt,
+    traits::NbtSerialize,
+    types::{NbtCompound, NbtList, NbtString, NbtTag},
+};
+
+use crate::{
+    chunks::{
+        block::{Block, Shared},
+        block_entity::BlockEntity,
+        chunk::{Chunk, FLATTENING_VERSION, PADDED_INDICES_VERSION},
+        packed,
+        section::Section,
+        structure::pack_chunk_pos,
+        tick::ScheduledTick,
+        upgrade::BelowZeroRetrogen,
+    },
+    compression::{Compression, WriteOptions},
+    entity::Entity,
+    raw::RawChunk,
+};
+
+/// The data version of 21w43a, which moved the fields of the `Level`
+/// compound to the root and renamed most of them.
+const LEVEL_REMOVED_VERSION: i32 = 2844;
+
+/// How a chunk is laid out in nbt, which follows its data version.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Layout {
+    /// Since 1.18, everything at the root.
+    Root,
+    /// From 1.13 to 1.17, everything but the data version in `Level`, with
+    /// palette indices spanning two longs before 20w17a.
+    Level { spanning: bool },
+}
+
+impl Chunk {
+    /// The chunk as the game stores it at its data version. Each section's
+    /// palette is rebuilt from the blocks it holds, and since 1.18, sections
+    /// made up of a single block get a palette of one entry and no data.
+    ///
+    /// Chunks from before 1.18 are written in the `Level` compound of those
+    /// versions, with their entities, and the game upgrades them when it
+    /// loads them. Chunks from before 1.13, whose blocks are mapped to
+    /// palettes, are written like ones from 1.13, with its data version.
+    /// Chunks without a data version are written in the current layout.
+    ///
+    /// Fields this crate doesn't read, like `isLightOn`, `PostProcessing`
+    /// or data added by mods, are taken from [`Chunk::raw_nbt`] if the
+    /// chunk was read with [`crate::chunk::ChunkParseOptions::keep_nbt`]
+    /// and is written in the layout it was read in. Otherwise they're left
+    /// out. Biomes from before 1.18 are among them. If blocks were changed
+    /// since, `isLightOn` is written as false so the game lights the chunk
+    /// again. The light the sections hold is written as is, so it may not
+    /// match those blocks, see [`Chunk::recompute_sky_light`].
+    pub fn to_nbt(&self) -> NbtCompound {
+        let layout = self.layout();
+        let (root, fields) = self.unknown_fields(layout);
+        let mut fields = self.encode_without_sections(layout, fields);
+        let sections = self
+            .sections
+            .iter()
+            .zip(self.y_pos..)
+            .map(|(section, y)| encode_section(section, y, layout))
+            .collect();
+        match layout {
+            Layout::Root => {
+                insert(&mut fields, "sections", compound_list(sections));
+                fields.into()
+            }
+            Layout::Level { .. } => {
+                insert(&mut fields, "Sections", compound_list(sections));
+                let mut root = root;
+                insert(
+                    &mut root,
+                    "DataVersion",
+                    NbtTag::Int(self.written_version()),
+                );
+                insert(&mut root, "Level", NbtTag::Compound(fields.into()));
+                root.into()
+            }
+        }
+    }
+
+    /// The length of the nbt [`Chunk::to_bytes_with`] compresses, worked
+    /// out from the palettes of the sections and the bits their indices
+    /// take rather than by packing them. Chunks written in the layout from
+    /// before 1.18 are encoded to measure them.
+    pub fn nbt_size(&self) -> usize {
+        if self.layout() != Layout::Root {
+            return serialize_nbt(&string(""), &self.to_nbt()).len();
+        }
+        let (_, fields) = self.unknown_fields(Layout::Root);
+        let mut root = Vec::new();
+        NbtCompound::from(self.encode_without_sections(Layout::Root, fields))
+            .serialize_nbt_payload(&mut root);
+        let sections: usize = self.sections.iter().map(section_size).sum();
+        // the root's tag and empty name, then the list of sections before
+        // the end of the root
+        3 + root.len() + field_size("sections") + 5 + sections
+    }
+
+    /// About how long [`Chunk::to_bytes_with`] is with `compression`,
+    /// header included, from [`Chunk::nbt_size`] and a ratio typical of
+    /// chunks. Compress the chunk for its exact length.
+    pub fn estimate_serialized_size(&self, compression: Compression) -> usize {
+        self.estimate_serialized_size_with(typical_ratio(compression))
+    }
+
+    /// Like [`Chunk::estimate_serialized_size`], but with the compressed
+    /// length expected for every byte of nbt, like one measured on chunks of
+    /// the same world.
+    pub fn estimate_serialized_size_with(&self, ratio: f64) -> usize {
+        5 + (self.nbt_size() as f64 * ratio).ceil() as usize
+    }
+
+    fn layout(&self) -> Layout {
+        let version = self.written_version();
+        if version == 0 || version >= LEVEL_REMOVED_VERSION {
+            Layout::Root
+        } else {
+            Layout::Level {
+                spanning: version < PADDED_INDICES_VERSION,
+            }
+        }
+    }
+
+    /// The data version the chunk is written with, that of 1.13 for chunks
+    /// whose numeric ids were mapped to palettes.
+    fn written_version(&self) -> i32 {
+        let numeric = self.data_version != 0 && self.data_version < FLATTENING_VERSION;
+        if numeric || self.legacy_sections.is_some() {
+            FLATTENING_VERSION
+        } else {
+            self.data_version
+        }
+    }
+
+    /// The fields of [`Chunk::raw_nbt`] at the root and those the chunk's
+    /// fields are in, which [`Chunk::to_nbt`] writes over. Empty if the
+    /// chunk wasn't read in `layout`.
+    fn unknown_fields(
+        &self,
+        layout: Layout,
+    ) -> (HashMap<NbtString, NbtTag>, HashMap<NbtString, NbtTag>) {
+        let Some(raw) = &self.raw_nbt else {
+            return Default::default();
+        };
+        let mut root: HashMap<_, _> = raw.clone().into();
+        let level = root.remove("Level");
+        let mut fields = match (layout, level) {
+            (Layout::Root, None) => std::mem::take(&mut root),
+            (Layout::Level { .. }, Some(NbtTag::Compound(level))) => level.into(),
+            _ => return Default::default(),
+        };
+        // written only if the chunk has them, so they mustn't come back
+        // once removed
+        for name in ["below_zero_retrogen", "blending_data"] {
+            fields.remove(name);
+        }
+        // the stored light is stale once blocks changed, so the game has to
+        // light the chunk again
+        if self.sections.iter().any(|section| section.edited)
+            && let Some(light_on) = fields.get_mut("isLightOn")
+        {
+            *light_on = NbtTag::Byte(0);
+        }
+        (root, fields)
+    }
+
+    /// Every field [`Chunk::to_nbt`] writes where the sections go, but the
+    /// sections, over `fields`.
+    fn encode_without_sections(
+        &self,
+        layout: Layout,
+        fields: HashMap<NbtString, NbtTag>,
+    ) -> HashMap<NbtString, NbtTag> {
+        let mut root = fields;
+        let [block_entities, block_ticks, fluid_ticks, structures] = match layout {
+            Layout::Root => {
+                insert(&mut root, "DataVersion", NbtTag::Int(self.data_version));
+                insert(&mut root, "yPos", NbtTag::Int(self.y_pos));
+                ["block_entities", "block_ticks", "fluid_ticks", "structures"]
+            }
+            Layout::Level { .. } => {
+                // the game only wrote it shortly before 1.18, it goes by
+                // the sections otherwise
+                if self.y_pos != 0 || root.contains_key("yPos") {
+                    insert(&mut root, "yPos", NbtTag::Int(self.y_pos));
+                }
+                let entities = self.entities.iter().map(encode_entity).collect();
+                insert(&mut root, "Entities", compound_list(entities));
+                ["TileEntities", "TileTicks", "LiquidTicks", "Structures"]
+            }
+        };
+        insert(&mut root, "xPos", NbtTag::Int(self.x_pos));
+        insert(&mut root, "zPos", NbtTag::Int(self.z_pos));
+        insert(
+            &mut root,
+            "Status",
+            NbtTag::String(string(&self.status.to_string())),
+        );
+        insert(&mut root, "LastUpdate", NbtTag::Long(self.last_update));
+        insert(
+            &mut root,
+            "InhabitedTime",
+            NbtTag::Long(self.inhabited_time),
+        );
+
+        let entities = self
+            .block_entities
+            .iter()
+            .map(encode_block_entity)
+            .collect();
+        insert(&mut root, block_entities, compound_list(entities));
+        for (name, ticks) in [
+            (block_ticks, &self.block_ticks),
+            (fluid_ticks, &self.fluid_ticks),
+        ] {
+            let ticks = ticks.iter().map(encode_tick).collect();
+            insert(&mut root, name, compound_list(ticks));
+        }
+        insert(
+            &mut root,
+            structures,
+            NbtTag::Compound(encode_structures(self, layout)),
+        );
+        if let Some(retrogen) = &self.below_zero_retrogen {
+            insert(
+                &mut root,
+                "below_zero_retrogen",
+                NbtTag::Compound(encode_retrogen(retrogen)),
+            );
+        }
+        if let Some(data) = &self.blending_data {
+            insert(&mut root, "blending_data", NbtTag::Compound(data.clone()));
+        }
+
+        let (min_y, height) = (self.y_pos * 16, self.sections.len() * 16);
+        let bits = packed::bits_for(height).max(1);
+        let mut heightmaps = HashMap::new();
+        for (kind, heightmap) in &self.heightmaps {
+            let values = heightmap
+                .heights()
+                .iter()
+                .map(|&y| (i32::from(y) - min_y).clamp(0, height as i32) as u64);
+            let data = pack(values, bits, layout).try_into().unwrap();
+            insert(&mut heightmaps, kind.name(), NbtTag::LongArray(data));
+        }
+        insert(&mut root, "Heightmaps", NbtTag::Compound(heightmaps.into()));
+        root
+    }
+
+    /// The chunk with its header and zlib compressed, as
+    /// [`crate::chunk::parse_chunk`] reads it.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.to_bytes_with(&WriteOptions::default())
+    }
+
+    /// Like [`Chunk::to_bytes`], but compressed as the options say. The
+    /// header names the compression used.
+    pub fn to_bytes_with(&self, options: &WriteOptions) -> Vec<u8> {
+        let mut out = Vec::new();
+        self.to_raw_with(options).write_to(&mut out);
+        out
+    }
+
+    pub(crate) fn to_raw_with(&self, options: &WriteOptions) -> RawChunk {
+        let nbt = serialize_nbt(&string(""), &self.to_nbt());
+        let level = flate2::Compression::new(options.level.min(9));
+        // writing to a vec can't fail
+        let data = match options.compression {
+            Compression::Gzip => {
+                let mut encoder = GzEncoder::new(Vec::new(), level);
+                encoder.write_all(&nbt).unwrap();
+                encoder.finish().unwrap()
+            }
+            Compression::Zlib => {
+                let mut encoder = ZlibEncoder::new(Vec::new(), level);
+                encoder.write_all(&nbt).unwrap();
+                encoder.finish().unwrap()
+            }
+            Compression::None => nbt,
+            #[cfg(feature = "lz4")]
+            Compression::Lz4 => crate::lz4::compress(&nbt),
+        };
+        RawChunk {
+            timestamp: 0,
+            compression: options.compression.into(),
+            external: false,
+            data: data.into(),
+        }
+    }
+}
+
+fn encode_structures(chunk: &Chunk, layout: Layout) -> NbtCompound {
+    let mut references = HashMap::new();
+    for (name, starts) in &chunk.structure_references {
+        let packed: Vec<_> = starts.iter().map(|&(x, z)| pack_chunk_pos(x, z)).collect();
+        references.insert(name.clone(), NbtTag::LongArray(packed.try_into().unwrap()));
+    }
+    let starts: HashMap<_, _> = chunk
+        .structure_starts
+        .iter()
+        .map(|start| (start.id.clone(), NbtTag::Compound(start.data.clone())))
+        .collect();
+
+    let mut structures = HashMap::new();
+    insert(
+        &mut structures,
+        "References",
+        NbtTag::Compound(references.into()),
+    );
+    let starts_field = match layout {
+        Layout::Root => "starts",
+        Layout::Level { .. } => "Starts",
+    };
+    insert(
+        &mut structures,
+        starts_field,
+        NbtTag::Compound(starts.into()),
+    );
+    structures.into()
+}
+
+fn encode_retrogen(retrogen: &BelowZeroRetrogen) -> NbtCompound {
+    let mut fields = HashMap::new();
+    insert(
+        &mut fields,
+        "target_status",
+        NbtTag::String(string(&retrogen.target_status.to_string())),
+    );
+    // like the game, trailing words without any bits set are left out
+    let words = &retrogen.missing_bedrock;
+    let len = words
+        .iter()
+        .rposition(|&word| word != 0)
+        .map_or(0, |last| last + 1);
+    if len > 0 {
+        let words: Vec<_> = words[..len].iter().map(|&word| word as i64).collect();
+        insert(
+            &mut fields,
+            "missing_bedrock",
+            NbtTag::LongArray(words.try_into().unwrap()),
+        );
+    }
+    fields.into()
+}
+
+fn encode_tick(tick: &ScheduledTick) -> NbtCompound {
+    let mut fields = HashMap::new();
+    insert(&mut fields, "i", NbtTag::String(tick.target.clone()));
+    insert(&mut fields, "x", NbtTag::Int(tick.x));
+    insert(&mut fields, "y", NbtTag::Int(tick.y));
+    insert(&mut fields, "z", NbtTag::Int(tick.z));
+    insert(&mut fields, "t", NbtTag::Int(tick.delay));
+    insert(&mut fields, "p", NbtTag::Int(tick.priority));
+    fields.into()
+}
+
+fn encode_block_entity(block_entity: &BlockEntity) -> NbtCompound {
+    let fields = [
+        ("id", NbtTag::String(block_entity.id.clone())),
+        ("x", NbtTag::Int(block_entity.x)),
+        ("y", NbtTag::Int(block_entity.y)),
+        ("z", NbtTag::Int(block_entity.z)),
+    ];
+    // rebuilding the compound loses its order, so only do it if the fields
+    // were changed
+    if fields
+        .iter()
+        .all(|(name, tag)| block_entity.data.get(name) == Some(tag))
+    {
+        return block_entity.data.clone();
+    }
+
+    let mut data: HashMap<_, _> = block_entity.data.clone().into();
+    for (name, tag) in fields {
+        insert(&mut data, name, tag);
+    }
+    data.into()
+}
+
+fn encode_entity(entity: &Entity) -> NbtCompound {
+    let doubles = |(x, y, z): (f64, f64, f64)| {
+        NbtTag::List(NbtList::Double(vec![x, y, z].try_into().unwrap()))
+    };
+    let (yaw, pitch) = entity.rotation;
+    let mut fields = vec![
+        ("id", NbtTag::String(entity.id.clone())),
+        ("Pos", doubles(entity.pos)),
+    ];
+    // those that aren't stored read as zero
+    if entity.data.get("Motion").is_some() || entity.motion != (0.0, 0.0, 0.0) {
+        fields.push(("Motion", doubles(entity.motion)));
+    }
+    if entity.data.get("Rotation").is_some() || entity.rotation != (0.0, 0.0) {
+        let rotation = NbtList::Float(vec![yaw, pitch].try_into().unwrap());
+        fields.push(("Rotation", NbtTag::List(rotation)));
+    }
+    if fields
+        .iter()
+        .all(|(name, tag)| entity.data.get(name) == Some(tag))
+    {
+        return entity.data.clone();
+    }
+
+    let mut data: HashMap<_, _> = entity.data.clone().into();
+    for (name, tag) in fields {
+        insert(&mut data, name, tag);
+    }
+    data.into()
+}
+
+fn encode_section(section: &Section, y: i32, layout: Layout) -> NbtCompound {
+    let mut compound = HashMap::new();
+    insert(&mut compound, "Y", NbtTag::Byte(y as i8));
+
+    // entries are mapped when first used, so unused ones are left out
+    let mut palette: Vec<&Shared<Block>> = Vec::new();
+    let mut remap = vec![None; section.palette.len()];
+    let indices: Vec<u64> = section
+        .iter_palette_indices()
+        .map(|entry| {
+            *remap[entry]
+                .get_or_insert_with(|| palette_index(&mut palette, &section.palette[entry]))
+        })
+        .collect();
+
+    let mut states = HashMap::new();
+    let entries = palette
+        .iter()
+        .map(|block| {
+            let mut entry = HashMap::new();
+            insert(&mut entry, "Name", NbtTag::String(block.nbt_name()));
+            if let Some(properties) = &block.properties {
+                insert(
+                    &mut entry,
+                    "Properties",
+                    NbtTag::Compound(properties.clone()),
+                );
+            }
+            entry.into()
+        })
+        .collect();
+    let bits = packed::bits_for(palette.len() - 1).max(4);
+    match layout {
+        Layout::Root => {
+            insert(&mut states, "palette", compound_list(entries));
+            if palette.len() > 1 {
+                let data = packed::pack(indices, bits).try_into().unwrap();
+                insert(&mut states, "data", NbtTag::LongArray(data));
+            }
+            insert(
+                &mut compound,
+                "block_states",
+                NbtTag::Compound(states.into()),
+            );
+        }
+        // before 1.18, the palette and its indices are in the section and
+        // the indices are always there
+        Layout::Level { .. } => {
+            insert(&mut compound, "Palette", compound_list(entries));
+            let data = pack(indices, bits, layout).try_into().unwrap();
+            insert(&mut compound, "BlockStates", NbtTag::LongArray(data));
+        }
+    }
+
+    // biomes were kept by column in the level before 1.18
+    if !section.biomes.is_empty() && layout == Layout::Root {
+        let mut palette: Vec<&Shared<NbtString>> = Vec::new();
+        let indices: Vec<u64> = section
+            .biomes
+            .iter()
+            .map(|biome| palette_index(&mut palette, biome))
+            .collect();
+
+        let mut biomes = HashMap::new();
+        let names: Vec<NbtString> = palette
+            .iter()
+            .map(|&biome| NbtString::clone(biome))
+            .collect();
+        insert(
+            &mut biomes,
+            "palette",
+            NbtTag::List(NbtList::String(names.try_into().unwrap())),
+        );
+        if palette.len() > 1 {
+            let data = packed::pack(indices, packed::bits_for(palette.len() - 1));
+            insert(
+                &mut biomes,
+                "data",
+                NbtTag::LongArray(data.try_into().unwrap()),
+            );
+        }
+        insert(&mut compound, "biomes", NbtTag::Compound(biomes.into()));
+    }
+
+    for (name, light) in [
+        ("BlockLight", &section.block_light),
+        ("SkyLight", &section.sky_light),
+    ] {
+        if let Some(light) = light {
+            let bytes: Vec<i8> = light.iter().map(|&nibbles| nibbles as i8).collect();
+            insert(
+                &mut compound,
+                name,
+                NbtTag::ByteArray(bytes.try_into().unwrap()),
+            );
+        }
+    }
+
+    compound.into()
+}
+
+/// The length of what [`encode_section`] writes, as an entry of a list.
+fn section_size(section: &Section) -> usize {
+    let blocks = section.unique_blocks();
+    let mut states = field_size("palette") + 5;
+    for block in &blocks {
+        states += field_size("Name") + 2 + block.name.len() + 1;
+        if let Some(properties) = &block.properties {
+            let mut payload = Vec::new();
+            properties.serialize_nbt_payload(&mut payload);
+            states += field_size("Properties") + payload.len();
+        }
+    }
+    if blocks.len() > 1 {
+        let bits = packed::bits_for(blocks.len() - 1).max(4);
+        states += field_size("data") + long_array_size(bits, 4096);
+    }
+    let mut size = field_size("Y") + 1 + field_size("block_states") + states + 1;
+
+    if !section.biomes.is_empty() {
+        let mut palette: Vec<&Shared<NbtString>> = Vec::new();
+        for biome in &section.biomes {
+            palette_index(&mut palette, biome);
+        }
+        let names: usize = palette.iter().map(|biome| 2 + biome.len()).sum();
+        let mut biomes = field_size("palette") + 5 + names;
+        if palette.len() > 1 {
+            let bits = packed::bits_for(palette.len() - 1);
+            biomes += field_size("data") + long_array_size(bits, section.biomes.len());
+        }
+        size += field_size("biomes") + biomes + 1;
+    }
+
+    for (name, light) in [
+        ("BlockLight", &section.block_light),
+        ("SkyLight", &section.sky_light),
+    ] {
+        if let Some(light) = light {
+            size += field_size(name) + 4 + light.len();
+        }
+    }
+    size + 1
+}
+
+/// The tag id and name a field of a compound starts with.
+fn field_size(name: &str) -> usize {
+    3 + name.len()
+}
+
+fn long_array_size(bits: usize, count: usize) -> usize {
+    4 + packed::packed_len(bits, count, false) * 8
+}
+
+/// The compressed length of a byte of chunk nbt, measured on chunks of a
+/// generated world.
+fn typical_ratio(compression: Compression) -> f64 {
+    match compression {
+        Compression::Gzip => 0.14,
+        Compression::Zlib => 0.14,
+        Compression::None => 1.0,
+        #[cfg(feature = "lz4")]
+        Compression::Lz4 => 0.25,
+    }
+}
+
+/// Packs `values` the way they're stored in `layout`, see [`packed`].
+fn pack(values: impl IntoIterator<Item = u64>, bits: usize, layout: Layout) -> Vec<i64> {
+    match layout {
+        Layout::Level { spanning: true } => packed::pack_spanning(values, bits),
+        _ => packed::pack(values, bits),
+    }
+}
+
+/// The index of `value` in `palette`, which it's added to if it isn't there
+/// yet. Shared values are found by address before being compared.
+fn palette_index<'a, T: PartialEq>(palette: &mut Vec<&'a Shared<T>>, value: &'a Shared<T>) -> u64 {
+    let known = palette
+        .iter()
+        .position(|&known| Shared::ptr_eq(known, value))
+        .or_else(|| palette.iter().position(|&known| known == value));
+    known.unwrap_or_else(|| {
+        palette.push(value);
+        palette.len() - 1
+    }) as u64
+}
+
+fn compound_list(compounds: Vec<NbtCompound>) -> NbtTag {
+    NbtTag::List(NbtList::Compound(compounds.try_into().unwrap()))
+}
+
+fn insert(compound: &mut HashMap<NbtString, NbtTag>, name: &str, tag: NbtTag) {
+    compound.insert(string(name), tag);
+}
+
+fn string(value: &str) -> NbtString {
+    value.to_owned().try_into().unwrap()
+}
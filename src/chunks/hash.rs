@@ -0,0 +1,345 @@
+use nbt_rs::types::{NbtCompound, NbtList, NbtString, NbtTag};
+
+use crate::chunks::{block::Block, block_entity::BlockEntity, chunk::Chunk};
+
+// the seed, multiplier and rotation of each lane, the first one being the
+// 64-bit hash
+const LANES: [(u64, u64, u32); 4] = [
+    (0xcbf2_9ce4_8422_2325, 0x517c_c1b7_2722_0a95, 5),
+    (0x9e37_79b9_7f4a_7c15, 0x94d0_49bb_1331_11eb, 7),
+    (0x6a09_e667_f3bc_c908, 0xff51_afd7_ed55_8ccd, 11),
+    (0xbb67_ae85_84ca_a73b, 0xc4ce_b9fe_1a85_ec53, 13),
+];
+
+/// A fast, non-cryptographic hasher with a fixed algorithm, unlike the
+/// std ones, so hashes can be stored.
+pub(crate) struct ContentHasher {
+    state: u64,
+    multiplier: u64,
+    rotation: u32,
+}
+
+impl ContentHasher {
+    pub(crate) fn new() -> Self {
+        Self::lane(0)
+    }
+
+    fn lane(lane: usize) -> Self {
+        let (state, multiplier, rotation) = LANES[lane];
+        Self {
+            state,
+            multiplier,
+            rotation,
+        }
+    }
+
+    pub(crate) fn write_u64(&mut self, value: u64) {
+        self.state = (self.state.rotate_left(self.rotation) ^ value).wrapping_mul(self.multiplier);
+    }
+
+    pub(crate) fn finish(&self) -> u64 {
+        // fold the high bits in, the multiplication leaves the low ones weak
+        let hash = self.state ^ (self.state >> 29);
+        hash.wrapping_mul(0xbf58_476d_1ce4_e5b9) ^ (hash >> 32)
+    }
+}
+
+/// What [`Chunk::content_hash`] and [`Chunk::content_hash_256`] feed their
+/// content to.
+trait ContentWrite {
+    type Output;
+
+    fn new() -> Self;
+
+    fn write_u64(&mut self, value: u64);
+
+    fn write_hash(&mut self, hash: &Self::Output);
+
+    fn finish(&self) -> Self::Output;
+
+    fn write_bytes(&mut self, bytes: &[u8]) {
+        self.write_u64(bytes.len() as u64);
+        for chunk in bytes.chunks(8) {
+            let mut word = [0; 8];
+            word[..chunk.len()].copy_from_slice(chunk);
+            self.write_u64(u64::from_le_bytes(word));
+        }
+    }
+
+    /// Writes the tag with its type, with compound entries in the order of
+    /// their keys, as the order they were stored in doesn't matter to the
+    /// game.
+    fn write_tag(&mut self, tag: NbtTag) {
+        match tag {
+            NbtTag::End => self.write_u64(0),
+            NbtTag::Byte(v) => self.write_typed(1, v as u64),
+            NbtTag::Short(v) => self.write_typed(2, v as u64),
+            NbtTag::Int(v) => self.write_typed(3, v as u64),
+            NbtTag::Long(v) => self.write_typed(4, v as u64),
+            NbtTag::Float(v) => self.write_typed(5, float_bits(v.into())),
+            NbtTag::Double(v) => self.write_typed(6, float_bits(v)),
+            NbtTag::ByteArray(v) => self.write_array(7, v.iter().map(|&v| v as u64)),
+            NbtTag::String(v) => {
+                self.write_u64(8);
+                self.write_bytes(v.as_bytes());
+            }
+            NbtTag::List(v) => {
+                self.write_u64(9);
+                self.write_list(v);
+            }
+            NbtTag::Compound(v) => {
+                self.write_u64(10);
+                self.write_compound(v);
+            }
+            NbtTag::IntArray(v) => self.write_array(11, v.iter().map(|&v| v as u64)),
+            NbtTag::LongArray(v) => self.write_array(12, v.iter().map(|&v| v as u64)),
+        }
+    }
+
+    fn write_typed(&mut self, tag: u64, value: u64) {
+        self.write_u64(tag);
+        self.write_u64(value);
+    }
+
+    fn write_array(&mut self, tag: u64, values: impl ExactSizeIterator<Item = u64>) {
+        self.write_u64(tag);
+        self.write_u64(values.len() as u64);
+        for value in values {
+            self.write_u64(value);
+        }
+    }
+
+    fn write_list(&mut self, list: NbtList) {
+        macro_rules! write {
+            ($items:expr, $tag:expr) => {{
+                let items = Vec::from($items);
+                self.write_u64(items.len() as u64);
+                for item in items {
+                    self.write_tag($tag(item));
+                }
+            }};
+        }
+        match list {
+            NbtList::End => self.write_u64(0),
+            NbtList::Byte(v) => write!(v, NbtTag::Byte),
+            NbtList::Short(v) => write!(v, NbtTag::Short),
+            NbtList::Int(v) => write!(v, NbtTag::Int),
+            NbtList::Long(v) => write!(v, NbtTag::Long),
+            NbtList::Float(v) => write!(v, NbtTag::Float),
+            NbtList::Double(v) => write!(v, NbtTag::Double),
+            NbtList::ByteArray(v) => write!(v, NbtTag::ByteArray),
+            NbtList::String(v) => write!(v, NbtTag::String),
+            NbtList::List(v) => write!(v, NbtTag::List),
+            NbtList::Compound(v) => write!(v, NbtTag::Compound),
+            NbtList::IntArray(v) => write!(v, NbtTag::IntArray),
+            NbtList::LongArray(v) => write!(v, NbtTag::LongArray),
+        }
+    }
+
+    fn write_compound(&mut self, compound: NbtCompound) {
+        self.write_entries(compound.into());
+    }
+
+    fn write_entries(&mut self, mut entries: Vec<(NbtString, NbtTag)>) {
+        entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+        self.write_u64(entries.len() as u64);
+        for (key, tag) in entries {
+            self.write_bytes(key.as_bytes());
+            self.write_tag(tag);
+        }
+    }
+}
+
+// 0.0 and -0.0 are equal, so they have to hash the same
+fn float_bits(value: f64) -> u64 {
+    if value == 0.0 { 0 } else { value.to_bits() }
+}
+
+/// Collects the values instead of hashing them, so content can be compared
+/// the way it's hashed.
+struct Canonical(Vec<u64>);
+
+impl ContentWrite for Canonical {
+    type Output = Vec<u64>;
+
+    fn new() -> Self {
+        Self(Vec::new())
+    }
+
+    fn write_u64(&mut self, value: u64) {
+        self.0.push(value);
+    }
+
+    fn write_hash(&mut self, hash: &Vec<u64>) {
+        self.0.extend(hash);
+    }
+
+    fn finish(&self) -> Vec<u64> {
+        self.0.clone()
+    }
+}
+
+fn canonical(entries: Vec<(NbtString, NbtTag)>) -> Vec<u64> {
+    let mut canonical = Canonical::new();
+    canonical.write_entries(entries);
+    canonical.0
+}
+
+/// Whether the compounds hash the same way, i.e. hold the same entries in
+/// any order. Unlike `==`, floats compare by their bits, so NaN equals
+/// itself.
+pub(crate) fn nbt_eq(a: &NbtCompound, b: &NbtCompound) -> bool {
+    canonical(a.clone().into()) == canonical(b.clone().into())
+}
+
+impl ContentWrite for ContentHasher {
+    type Output = u64;
+
+    fn new() -> Self {
+        ContentHasher::new()
+    }
+
+    fn write_u64(&mut self, value: u64) {
+        ContentHasher::write_u64(self, value);
+    }
+
+    fn write_hash(&mut self, hash: &u64) {
+        ContentHasher::write_u64(self, *hash);
+    }
+
+    fn finish(&self) -> u64 {
+        ContentHasher::finish(self)
+    }
+}
+
+/// Four lanes of [`ContentHasher`] with their own constants, fed the same
+/// values.
+struct WideHasher([ContentHasher; 4]);
+
+impl ContentWrite for WideHasher {
+    type Output = [u64; 4];
+
+    fn new() -> Self {
+        Self(std::array::from_fn(ContentHasher::lane))
+    }
+
+    fn write_u64(&mut self, value: u64) {
+        for lane in &mut self.0 {
+            lane.write_u64(value);
+        }
+    }
+
+    fn write_hash(&mut self, hash: &[u64; 4]) {
+        for value in hash {
+            ContentWrite::write_u64(self, *value);
+        }
+    }
+
+    fn finish(&self) -> [u64; 4] {
+        self.0.each_ref().map(ContentHasher::finish)
+    }
+}
+
+fn block_hash<H: ContentWrite>(block: &Block) -> H::Output {
+    let mut hasher = H::new();
+    hasher.write_bytes(block.name.as_bytes());
+    for (key, value) in block.sorted_properties() {
+        hasher.write_bytes(key.as_bytes());
+        hasher.write_bytes(value.to_string().as_bytes());
+    }
+    hasher.finish()
+}
+
+/// The position of a block entity relative to the chunk, and its nbt without
+/// the position, so they're the same wherever the chunk is.
+fn local_block_entity(
+    chunk: &Chunk,
+    block_entity: &BlockEntity,
+) -> ([i32; 3], Vec<(NbtString, NbtTag)>) {
+    let position = [
+        block_entity.x.wrapping_sub(chunk.x_pos.wrapping_mul(16)),
+        block_entity.y,
+        block_entity.z.wrapping_sub(chunk.z_pos.wrapping_mul(16)),
+    ];
+    let mut data: Vec<_> = block_entity.data.clone().into();
+    data.retain(|(key, _)| !matches!(&**key, "x" | "y" | "z"));
+    (position, data)
+}
+
+impl Chunk {
+    /// A hash of the chunk's blocks, biomes and block entities, the same for
+    /// chunks that are equal. It doesn't depend on where the chunk is, so
+    /// copies of a chunk elsewhere in the world hash the same, nor on how it
+    /// was stored, i.e. its compression, palette order, the order of block
+    /// properties or of the keys in block entities.
+    ///
+    /// The hash is stable within a minor version of this crate, but may
+    /// change between them. It isn't cryptographic, so don't rely on it
+    /// against deliberate collisions.
+    ///
+    /// Chunks with the same hash aren't necessarily the same: it leaves out
+    /// light, ticks and the other fields, and different content may collide.
+    /// To deduplicate chunks, use it to find candidates and compare those
+    /// with [`Chunk::content_eq`], or with `==` to also compare their
+    /// position and everything else.
+    pub fn content_hash(&self) -> u64 {
+        self.hash_content::<ContentHasher>()
+    }
+
+    /// The same content as [`Chunk::content_hash`] hashed to 256 bits, for
+    /// when there are enough chunks that 64-bit hashes may collide by
+    /// chance. It's just as stable, and just as far from cryptographic.
+    pub fn content_hash_256(&self) -> [u8; 32] {
+        let mut hash = [0; 32];
+        for (bytes, lane) in hash.chunks_mut(8).zip(self.hash_content::<WideHasher>()) {
+            bytes.copy_from_slice(&lane.to_le_bytes());
+        }
+        hash
+    }
+
+    /// Whether the chunks hold what [`Chunk::content_hash`] hashes, the
+    /// same blocks and biomes in sections at the same y, and the same block
+    /// entities relative to the chunk, wherever the chunks are. Palettes are
+    /// compared by value, like with `==`.
+    pub fn content_eq(&self, other: &Chunk) -> bool {
+        self.into_iter().len() == other.into_iter().len()
+            && (self.into_iter().zip(other))
+                .all(|((a_y, a), (b_y, b))| a_y == b_y && a.same_content(b))
+            && self.block_entities.len() == other.block_entities.len()
+            && (self.block_entities.iter().zip(&other.block_entities)).all(|(a, b)| {
+                let (a_position, a_data) = local_block_entity(self, a);
+                let (b_position, b_data) = local_block_entity(other, b);
+                a.id == b.id && a_position == b_position && canonical(a_data) == canonical(b_data)
+            })
+    }
+
+    fn hash_content<H: ContentWrite>(&self) -> H::Output {
+        let mut hasher = H::new();
+        for (section_y, section) in self {
+            hasher.write_u64(section_y as u64);
+            let hashes: Vec<_> = section
+                .palette
+                .iter()
+                .map(|block| block_hash::<H>(block))
+                .collect();
+            for entry in section.iter_palette_indices() {
+                hasher.write_hash(&hashes[entry]);
+            }
+            hasher.write_u64(section.biomes.len() as u64);
+            for biome in &section.biomes {
+                hasher.write_bytes(biome.as_bytes());
+            }
+        }
+        // kept sorted by position, so equal chunks list them in one order
+        hasher.write_u64(self.block_entities.len() as u64);
+        for block_entity in &self.block_entities {
+            let (position, data) = local_block_entity(self, block_entity);
+            hasher.write_bytes(block_entity.id.as_bytes());
+            for value in position {
+                hasher.write_u64(value as u64);
+            }
+            hasher.write_entries(data);
+        }
+        hasher.finish()
+    }
+}
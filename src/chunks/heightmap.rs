@@ -0,0 +1,250 @@
+use std::fmt;
+
+use crate::chunks::{block::Block, packed};
+
+/// The heightmaps the game keeps for fully generated chunks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum HeightmapKind {
+    /// The highest block that isn't air.
+    WorldSurface,
+    /// The highest block that blocks motion, ignoring fluids.
+    OceanFloor,
+    /// The highest block that blocks motion or holds a fluid.
+    MotionBlocking,
+    /// Like [`HeightmapKind::MotionBlocking`], but ignoring leaves.
+    MotionBlockingNoLeaves,
+}
+
+// names of blocks that don't block motion, besides air and the ones
+// matching `NON_SOLID_SUFFIXES`
+const NON_SOLID_NAMES: &[&str] = &[
+    "short_grass",
+    "grass",
+    "tall_grass",
+    "fern",
+    "large_fern",
+    "dead_bush",
+    "vine",
+    "glow_lichen",
+    "sculk_vein",
+    "snow",
+    "seagrass",
+    "tall_seagrass",
+    "kelp",
+    "kelp_plant",
+    "sugar_cane",
+    "cobweb",
+    "bamboo_sapling",
+    "lever",
+    "ladder",
+    "tripwire",
+    "tripwire_hook",
+    "redstone_wire",
+    "fire",
+    "soul_fire",
+    "nether_portal",
+    "end_portal",
+    "end_gateway",
+    "light",
+    "structure_void",
+    "water",
+    "lava",
+    "bubble_column",
+    "dandelion",
+    "poppy",
+    "blue_orchid",
+    "allium",
+    "azure_bluet",
+    "oxeye_daisy",
+    "cornflower",
+    "lily_of_the_valley",
+    "wither_rose",
+    "torchflower",
+    "sunflower",
+    "lilac",
+    "rose_bush",
+    "peony",
+    "pink_petals",
+    "leaf_litter",
+    "wildflowers",
+    "firefly_bush",
+    "bush",
+    "short_dry_grass",
+    "tall_dry_grass",
+    "spore_blossom",
+    "hanging_roots",
+    "crimson_roots",
+    "warped_roots",
+    "wheat",
+    "carrots",
+    "potatoes",
+    "beetroots",
+    "sweet_berry_bush",
+    "nether_wart",
+    "nether_sprouts",
+];
+
+const NON_SOLID_SUFFIXES: &[&str] = &[
+    "_sapling",
+    "torch",
+    "_sign",
+    "_banner",
+    "_button",
+    "_pressure_plate",
+    "rail",
+    "_carpet",
+    "_tulip",
+    "_mushroom",
+    "_fungus",
+    "_coral",
+    "_coral_fan",
+    "_vines",
+    "_vines_plant",
+];
+
+// blocks that are always filled with a fluid, besides waterlogged ones
+const FLUIDS: &[&str] = &[
+    "water",
+    "lava",
+    "bubble_column",
+    "seagrass",
+    "tall_seagrass",
+    "kelp",
+    "kelp_plant",
+];
+
+impl HeightmapKind {
+    pub const ALL: [HeightmapKind; 4] = [
+        HeightmapKind::WorldSurface,
+        HeightmapKind::OceanFloor,
+        HeightmapKind::MotionBlocking,
+        HeightmapKind::MotionBlockingNoLeaves,
+    ];
+
+    /// The key of the heightmap in the chunk's `Heightmaps` compound.
+    pub fn name(&self) -> &'static str {
+        match self {
+            HeightmapKind::WorldSurface => "WORLD_SURFACE",
+            HeightmapKind::OceanFloor => "OCEAN_FLOOR",
+            HeightmapKind::MotionBlocking => "MOTION_BLOCKING",
+            HeightmapKind::MotionBlockingNoLeaves => "MOTION_BLOCKING_NO_LEAVES",
+        }
+    }
+
+    /// Whether `block` counts towards this heightmap.
+    ///
+    /// The game decides this from block properties this crate doesn't know,
+    /// so this is an approximation by name: blocks don't block motion if
+    /// they're plants, flowers, crops, torches, signs, banners, buttons,
+    /// pressure plates, rails, carpets, fluids, portals, fire or a few other
+    /// decorations, and everything else does. Water, lava, bubble columns,
+    /// seagrass, kelp and waterlogged blocks hold a fluid, and leaves are the
+    /// blocks whose name ends with `_leaves`.
+    pub fn matches(&self, block: &Block) -> bool {
+        if block.is_air() {
+            return false;
+        }
+        match self {
+            HeightmapKind::WorldSurface => true,
+            HeightmapKind::OceanFloor => blocks_motion(block),
+            HeightmapKind::MotionBlocking => blocks_motion(block) || has_fluid(block),
+            HeightmapKind::MotionBlockingNoLeaves => {
+                (blocks_motion(block) || has_fluid(block)) && !block.name.ends_with("_leaves")
+            }
+        }
+    }
+}
+
+impl fmt::Display for HeightmapKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+pub(crate) fn short_name(block: &Block) -> &str {
+    block.name.strip_prefix("minecraft:").unwrap_or(&block.name)
+}
+
+pub(crate) fn blocks_motion(block: &Block) -> bool {
+    let name = short_name(block);
+    !NON_SOLID_NAMES.contains(&name)
+        && !NON_SOLID_SUFFIXES
+            .iter()
+            .any(|suffix| name.ends_with(suffix))
+}
+
+pub(crate) fn has_fluid(block: &Block) -> bool {
+    FLUIDS.contains(&short_name(block)) || block.is_waterlogged()
+}
+
+/// The height of each column of a chunk, i.e. the y coordinate right above
+/// the highest block counting towards the heightmap, or the bottom of the
+/// chunk if there is none.
+///
+/// Heights are kept as `i16`, which covers the ±2032 blocks the game allows.
+/// Stored heights beyond that fail the chunk with
+/// [`crate::chunk::ChunkParseError::InvalidHeightmap`], and chunks reaching
+/// further than that can't have heightmaps computed.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Heightmap {
+    // indexed by z then x
+    heights: [i16; 256],
+}
+
+/// A block y coordinate as a height, `None` if it doesn't fit.
+pub(crate) fn to_height(y: i32) -> Option<i16> {
+    i16::try_from(y).ok()
+}
+
+impl Heightmap {
+    pub(crate) fn new(heights: [i16; 256]) -> Self {
+        Self { heights }
+    }
+
+    /// Unpacks a heightmap as stored, relative to `min_y`, for a chunk
+    /// that's `height` blocks high. Before 1.16 the heights can span two
+    /// longs, like palette indices. Returns `None` for heights that don't
+    /// fit in an `i16`.
+    pub(crate) fn unpack(data: &[i64], min_y: i32, height: usize, spanning: bool) -> Option<Self> {
+        let bits = packed::bits_for(height).max(1);
+        let values = if spanning {
+            packed::unpack_spanning(data, bits, 256)
+        } else {
+            packed::unpack(data, bits, 256)
+        }?;
+        let mut heights = [0; 256];
+        for (height, value) in heights.iter_mut().zip(values) {
+            *height = to_height(min_y.saturating_add(value as i32))?;
+        }
+        Some(Self { heights })
+    }
+
+    /// The heights indexed by z then x.
+    pub(crate) fn heights(&self) -> &[i16; 256] {
+        &self.heights
+    }
+
+    pub(crate) fn raise_to(&mut self, min_y: i16) {
+        for height in &mut self.heights {
+            *height = (*height).max(min_y);
+        }
+    }
+
+    /// Moves the columns without blocks, at `old_min_y`, to `min_y`.
+    pub(crate) fn move_empty(&mut self, old_min_y: i16, min_y: i16) {
+        for height in &mut self.heights {
+            if *height == old_min_y {
+                *height = min_y;
+            }
+        }
+    }
+
+    /// Returns `None` if `x` or `z` is 16 or above.
+    pub fn get(&self, x: u8, z: u8) -> Option<i16> {
+        if x >= 16 || z >= 16 {
+            return None;
+        }
+
+        Some(self.heights[((z as usize) << 4) | x as usize])
+    }
+}
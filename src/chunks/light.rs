@@ -0,0 +1,91 @@
+//! Approximating sky light after editing blocks, see
+//! [`Chunk::recompute_sky_light`].
+
+use std::collections::HashMap;
+
+use nbt_rs::types::{NbtCompound, NbtString, NbtTag};
+
+use crate::chunks::{
+    block::Block,
+    chunk::Chunk,
+    heightmap::{blocks_motion, has_fluid, short_name},
+};
+
+// blocks that block motion but let light through unchanged
+const CLEAR_NAMES: &[&str] = &["glass", "iron_bars", "chain", "barrier", "scaffolding"];
+
+const CLEAR_SUFFIXES: &[&str] = &["_glass", "_glass_pane", "_fence", "_fence_gate"];
+
+/// How much the block dims sky light passing down through it, or `None`
+/// if it stops it.
+fn dimming(block: &Block) -> Option<u8> {
+    let name = short_name(block);
+    if block.is_air() {
+        Some(0)
+    } else if has_fluid(block) || name.ends_with("_leaves") || name == "cobweb" {
+        Some(1)
+    } else if !blocks_motion(block)
+        || (name != "tinted_glass"
+            && (CLEAR_NAMES.contains(&name)
+                || CLEAR_SUFFIXES.iter().any(|suffix| name.ends_with(suffix))))
+    {
+        Some(0)
+    } else {
+        None
+    }
+}
+
+impl Chunk {
+    /// Replaces the sky light of every section with an approximation from
+    /// the blocks, for chunks whose blocks were edited. Light comes straight
+    /// down from the top of the chunk at level 15, goes one level down for
+    /// each fluid, leaves or cobweb block it passes, and stops at the first
+    /// block that isn't air, glass, a fence, a plant or the like. It doesn't
+    /// spread sideways, so overhangs and caves are darker than in the game.
+    ///
+    /// The chunk's `isLightOn`, if it keeps one in [`Chunk::raw_nbt`], is
+    /// set to false, so the game still lights the chunk properly when it
+    /// loads it. The block light is left as it is.
+    pub fn recompute_sky_light(&mut self) {
+        let mut levels = [15u8; 256];
+        for section in self.sections.iter_mut().rev() {
+            let mut light = Box::new([0; 2048]);
+            let uniform = section.is_uniform().map(dimming);
+            for y in (0..16u8).rev() {
+                for (column, level) in levels.iter_mut().enumerate() {
+                    let (x, z) = ((column & 0xF) as u8, (column >> 4) as u8);
+                    let dimming = match uniform {
+                        Some(dimming) => dimming,
+                        None => dimming(section.get_block(x, y, z).unwrap()),
+                    };
+                    *level = dimming.map_or(0, |dimming| level.saturating_sub(dimming));
+                    let index = (y as usize) << 8 | column;
+                    let shift = (index & 1) * 4;
+                    light[index >> 1] |= *level << shift;
+                }
+            }
+            section.sky_light = Some(light);
+        }
+        if let Some(raw) = &mut self.raw_nbt {
+            turn_light_off(raw);
+        }
+    }
+}
+
+/// Sets `isLightOn` to false where the chunk stores it, in the root or in
+/// its `Level`.
+fn turn_light_off(raw: &mut NbtCompound) {
+    let off = |compound: &mut HashMap<NbtString, NbtTag>| {
+        if let Some(light_on) = compound.get_mut("isLightOn") {
+            *light_on = NbtTag::Byte(0);
+        }
+    };
+    let mut root: HashMap<NbtString, NbtTag> = raw.clone().into();
+    off(&mut root);
+    if let Some(NbtTag::Compound(level)) = root.get_mut("Level") {
+        let mut fields: HashMap<NbtString, NbtTag> = level.clone().into();
+        off(&mut fields);
+        *level = fields.into();
+    }
+    *raw = root.into();
+}
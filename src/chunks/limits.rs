@@ -0,0 +1,145 @@
+use crate::chunk::{ChunkParseOptions, Limit};
+
+pub(crate) const TAG_END: u8 = 0;
+pub(crate) const TAG_BYTE_ARRAY: u8 = 7;
+pub(crate) const TAG_STRING: u8 = 8;
+pub(crate) const TAG_LIST: u8 = 9;
+pub(crate) const TAG_COMPOUND: u8 = 10;
+pub(crate) const TAG_INT_ARRAY: u8 = 11;
+pub(crate) const TAG_LONG_ARRAY: u8 = 12;
+
+enum Frame {
+    Compound,
+    List { tag: u8, remaining: usize },
+}
+
+pub(crate) struct Scanner<'a> {
+    pub(crate) data: &'a [u8],
+    pub(crate) pos: usize,
+}
+
+impl Scanner<'_> {
+    pub(crate) fn take(&mut self, len: usize) -> Option<&[u8]> {
+        let bytes = self.data.get(self.pos..self.pos.checked_add(len)?)?;
+        self.pos += len;
+        Some(bytes)
+    }
+
+    pub(crate) fn u8(&mut self) -> Option<u8> {
+        Some(self.take(1)?[0])
+    }
+
+    pub(crate) fn u16(&mut self) -> Option<usize> {
+        Some(u16::from_be_bytes(self.take(2)?.try_into().unwrap()) as usize)
+    }
+
+    pub(crate) fn len(&mut self) -> Option<usize> {
+        // negative lengths are left for the parser to reject
+        usize::try_from(i32::from_be_bytes(self.take(4)?.try_into().unwrap())).ok()
+    }
+}
+
+/// Walks the nbt without building it, so limits are checked before the
+/// recursive parser allocates anything. Malformed data that stays within the
+/// limits is let through, the parser reports it properly.
+pub(crate) fn check_limits(data: &[u8], options: &ChunkParseOptions) -> Result<(), Limit> {
+    scan(data, options).unwrap_or(Ok(()))
+}
+
+fn scan(data: &[u8], options: &ChunkParseOptions) -> Option<Result<(), Limit>> {
+    let mut scanner = Scanner { data, pos: 0 };
+    let mut stack: Vec<Frame> = Vec::new();
+    let mut tags = 0usize;
+
+    let root = scanner.u8()?;
+    let name_len = scanner.u16()?;
+    scanner.take(name_len)?;
+    let mut next = Some(root);
+
+    loop {
+        // the payload to read, if any
+        if let Some(tag) = next.take() {
+            tags += 1;
+            if tags > options.max_tags {
+                return Some(Err(Limit::TagCount));
+            }
+
+            match tag {
+                1..=6 => {
+                    scanner.take([1, 2, 4, 8, 4, 8][tag as usize - 1])?;
+                }
+                TAG_BYTE_ARRAY | TAG_INT_ARRAY | TAG_LONG_ARRAY => {
+                    let len = scanner.len()?;
+                    if len > options.max_list_len {
+                        return Some(Err(Limit::ListLength));
+                    }
+                    let size = match tag {
+                        TAG_BYTE_ARRAY => 1,
+                        TAG_INT_ARRAY => 4,
+                        _ => 8,
+                    };
+                    // only overflows where usize is 32 bits, and no array
+                    // that long could be read there anyway
+                    let Some(bytes) = len.checked_mul(size) else {
+                        return Some(Err(Limit::ListLength));
+                    };
+                    scanner.take(bytes)?;
+                }
+                TAG_STRING => {
+                    let len = scanner.u16()?;
+                    if len > options.max_string_len {
+                        return Some(Err(Limit::StringLength));
+                    }
+                    scanner.take(len)?;
+                }
+                TAG_LIST | TAG_COMPOUND => {
+                    if stack.len() >= options.max_depth {
+                        return Some(Err(Limit::Depth));
+                    }
+                    if tag == TAG_COMPOUND {
+                        stack.push(Frame::Compound);
+                    } else {
+                        let tag = scanner.u8()?;
+                        let mut remaining = scanner.len()?;
+                        if remaining > options.max_list_len {
+                            return Some(Err(Limit::ListLength));
+                        }
+                        // the parser reads a list of end tags with a length
+                        // as empty, stopping here would skip the checks on
+                        // everything after it
+                        if tag == TAG_END {
+                            remaining = 0;
+                        }
+                        stack.push(Frame::List { tag, remaining });
+                    }
+                }
+                _ => return None,
+            }
+        }
+
+        match stack.last_mut() {
+            None => return Some(Ok(())),
+            Some(Frame::Compound) => {
+                let tag = scanner.u8()?;
+                if tag == TAG_END {
+                    stack.pop();
+                    continue;
+                }
+                let name_len = scanner.u16()?;
+                if name_len > options.max_string_len {
+                    return Some(Err(Limit::StringLength));
+                }
+                scanner.take(name_len)?;
+                next = Some(tag);
+            }
+            Some(Frame::List { tag, remaining }) => {
+                if *remaining == 0 {
+                    stack.pop();
+                    continue;
+                }
+                *remaining -= 1;
+                next = Some(*tag);
+            }
+        }
+    }
+}
@@ -0,0 +1,625 @@
+//! The colors blocks get on vanilla maps, as of 1.21.5 (data version 4325).
+//!
+//! Only the block's own color is known here. The game also darkens water by
+//! its depth and tints grass and leaves by biome, which is left to the caller.
+
+use crate::chunks::block::Block;
+
+/// One of the game's 62 map colors, by id. Id 0 is the transparent color of
+/// blocks that don't show on maps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct MapColor(u8);
+
+/// The shades a map color is drawn in, ordered like the low two bits of the
+/// color bytes stored in map items.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Brightness {
+    Low,
+    /// Used for blocks at the same height as the one north of them.
+    Normal,
+    /// The color as listed, unshaded.
+    High,
+    Lowest,
+}
+
+impl Brightness {
+    /// What each channel is multiplied by, out of 255.
+    pub fn multiplier(self) -> u16 {
+        match self {
+            Brightness::Low => 180,
+            Brightness::Normal => 220,
+            Brightness::High => 255,
+            Brightness::Lowest => 135,
+        }
+    }
+}
+
+// `MapColor` ids 0 to 61, as 0xRRGGBB
+const COLORS: [u32; 62] = [
+    0x000000, 0x7FB238, 0xF7E9A3, 0xC7C7C7, 0xFF0000, 0xA0A0FF, 0xA7A7A7, 0x007C00, 0xFFFFFF,
+    0xA4A8B8, 0x976D4D, 0x707070, 0x4040FF, 0x8F7748, 0xFFFCF5, 0xD87F33, 0xB24CD8, 0x6699D8,
+    0xE5E533, 0x7FCC19, 0xF27FA5, 0x4C4C4C, 0x999999, 0x4C7F99, 0x7F3FB2, 0x334CB2, 0x664C33,
+    0x667F33, 0x993333, 0x191919, 0xFAEE4D, 0x5CDBD5, 0x4A80FF, 0x00D93A, 0x815631, 0x700200,
+    0xD1B1A1, 0x9F5224, 0x95576C, 0x706C8A, 0xBA8524, 0x677535, 0xA04D4E, 0x392923, 0x876B62,
+    0x575C5C, 0x7A4958, 0x4C3E5C, 0x4C3223, 0x4C522A, 0x8E3C2E, 0x251610, 0xBD3031, 0x943F61,
+    0x5C191D, 0x167E86, 0x3A8E8C, 0x562C3E, 0x14B485, 0x646464, 0xD8AF93, 0x7FA796,
+];
+
+impl MapColor {
+    pub const NONE: MapColor = MapColor(0);
+    pub const GRASS: MapColor = MapColor(1);
+    pub const PLANT: MapColor = MapColor(7);
+    pub const WATER: MapColor = MapColor(12);
+
+    /// `None` if `id` is 62 or above.
+    pub fn from_id(id: u8) -> Option<Self> {
+        (usize::from(id) < COLORS.len()).then_some(MapColor(id))
+    }
+
+    pub fn id(self) -> u8 {
+        self.0
+    }
+
+    pub fn rgb(self) -> [u8; 3] {
+        let [_, r, g, b] = COLORS[usize::from(self.0)].to_be_bytes();
+        [r, g, b]
+    }
+
+    /// The color in the shade, fully transparent for [`MapColor::NONE`].
+    pub fn rgba(self, brightness: Brightness) -> [u8; 4] {
+        if self == MapColor::NONE {
+            return [0; 4];
+        }
+        let [r, g, b] = self
+            .rgb()
+            .map(|channel| (u16::from(channel) * brightness.multiplier() / 255) as u8);
+        [r, g, b, 255]
+    }
+
+    /// The byte a map item stores for the color in the shade.
+    pub fn packed(self, brightness: Brightness) -> u8 {
+        self.0 * 4 + brightness as u8
+    }
+}
+
+/// The block's map color with [`Brightness::High`], `None` for blocks that
+/// don't show on maps, like air, glass and leaf litter, and for blocks not in
+/// the table.
+/// See [`map_color_of`] for the other shades.
+pub fn map_color(block: &Block) -> Option<[u8; 4]> {
+    map_color_of(block).map(|color| color.rgba(Brightness::High))
+}
+
+/// Like [`map_color`], but returns the [`MapColor`] itself.
+pub fn map_color_of(block: &Block) -> Option<MapColor> {
+    let name = block.get_name().strip_prefix("minecraft:")?;
+    let name = name.strip_prefix("waxed_").unwrap_or(name);
+
+    if let Ok(index) = PILLARS.binary_search_by_key(&name, |&(name, ..)| name) {
+        let (_, top, side) = PILLARS[index];
+        let upright = matches!(block.get_property("axis"), None | Some("y"));
+        return color(if upright { top } else { side });
+    }
+    if let Some(id) = dyed(block, name) {
+        return color(id);
+    }
+    if let Some(id) = lookup(name) {
+        return color(id);
+    }
+    // stairs, slabs, doors and the like take the color of what they're made of
+    let id = SHAPES.iter().find_map(|shape| {
+        let base = name.strip_suffix(shape)?;
+        ["_planks", "", "s", "_block"]
+            .iter()
+            .find_map(|end| lookup(&format!("{base}{end}")))
+    })?;
+    color(id)
+}
+
+fn color(id: u8) -> Option<MapColor> {
+    (id != 0).then_some(MapColor(id))
+}
+
+fn lookup(name: &str) -> Option<u8> {
+    let index = BLOCKS.binary_search_by_key(&name, |&(name, _)| name).ok()?;
+    Some(BLOCKS[index].1)
+}
+
+/// The color of blocks that come in the 16 dye colors, like `red_wool`.
+fn dyed(block: &Block, name: &str) -> Option<u8> {
+    let (rest, dye, terracotta) = DYES.iter().find_map(|&(dye, color, terracotta)| {
+        let rest = name.strip_prefix(dye)?.strip_prefix('_')?;
+        Some((rest, color, terracotta))
+    })?;
+    match rest {
+        "wool" | "carpet" | "concrete" | "concrete_powder" | "stained_glass"
+        | "stained_glass_pane" | "glazed_terracotta" | "shulker_box" | "candle" => Some(dye),
+        "terracotta" => Some(terracotta),
+        // the head of a bed shows the pillow
+        "bed" if block.get_property("part") == Some("head") => Some(WOOL),
+        "bed" => Some(dye),
+        "banner" | "wall_banner" => Some(WOOD),
+        _ => None,
+    }
+}
+
+const WOOL: u8 = 3;
+const WOOD: u8 = 13;
+
+// longer suffixes first, so `_wall_sign` isn't taken for a wall
+const SHAPES: [&str; 12] = [
+    "_wall_hanging_sign",
+    "_hanging_sign",
+    "_wall_sign",
+    "_sign",
+    "_stairs",
+    "_slab",
+    "_wall",
+    "_fence_gate",
+    "_fence",
+    "_pressure_plate",
+    "_trapdoor",
+    "_door",
+];
+
+// each dye with its color and the color of its terracotta
+const DYES: [(&str, u8, u8); 16] = [
+    ("white", 8, 36),
+    ("orange", 15, 37),
+    ("magenta", 16, 38),
+    ("light_blue", 17, 39),
+    ("yellow", 18, 40),
+    ("lime", 19, 41),
+    ("pink", 20, 42),
+    ("gray", 21, 43),
+    ("light_gray", 22, 44),
+    ("cyan", 23, 45),
+    ("purple", 24, 46),
+    ("blue", 25, 47),
+    ("brown", 26, 48),
+    ("green", 27, 49),
+    ("red", 28, 50),
+    ("black", 29, 51),
+];
+
+// sorted by name, without the namespace
+const BLOCKS: &[(&str, u8)] = &[
+    ("acacia_leaves", 7),
+    ("acacia_planks", 15),
+    ("acacia_sapling", 7),
+    ("acacia_wood", 21),
+    ("allium", 7),
+    ("amethyst_block", 24),
+    ("amethyst_cluster", 24),
+    ("ancient_debris", 29),
+    ("andesite", 11),
+    ("anvil", 6),
+    ("attached_melon_stem", 7),
+    ("attached_pumpkin_stem", 7),
+    ("azalea", 7),
+    ("azalea_leaves", 7),
+    ("azure_bluet", 7),
+    ("bamboo", 7),
+    ("bamboo_mosaic", 18),
+    ("bamboo_planks", 18),
+    ("bamboo_sapling", 7),
+    ("barrel", 13),
+    ("basalt", 29),
+    ("beacon", 31),
+    ("bedrock", 11),
+    ("bee_nest", 18),
+    ("beehive", 13),
+    ("beetroots", 7),
+    ("bell", 30),
+    ("big_dripleaf", 7),
+    ("big_dripleaf_stem", 7),
+    ("birch_leaves", 7),
+    ("birch_planks", 2),
+    ("birch_sapling", 7),
+    ("birch_wood", 2),
+    ("blackstone", 29),
+    ("blast_furnace", 11),
+    ("blue_ice", 5),
+    ("blue_orchid", 7),
+    ("bone_block", 2),
+    ("bookshelf", 13),
+    ("brewing_stand", 6),
+    ("bricks", 28),
+    ("brown_mushroom", 26),
+    ("brown_mushroom_block", 10),
+    ("bubble_column", 12),
+    ("budding_amethyst", 24),
+    ("bush", 7),
+    ("cactus", 7),
+    ("cactus_flower", 20),
+    ("calcite", 36),
+    ("calibrated_sculk_sensor", 23),
+    ("candle", 2),
+    ("carrots", 7),
+    ("cartography_table", 13),
+    ("carved_pumpkin", 15),
+    ("cauldron", 11),
+    ("cave_vines", 7),
+    ("cave_vines_plant", 7),
+    ("cherry_leaves", 20),
+    ("cherry_planks", 36),
+    ("cherry_sapling", 7),
+    ("cherry_wood", 43),
+    ("chest", 13),
+    ("chipped_anvil", 6),
+    ("chiseled_bookshelf", 13),
+    ("chiseled_copper", 15),
+    ("chiseled_deepslate", 59),
+    ("chiseled_nether_bricks", 35),
+    ("chiseled_polished_blackstone", 29),
+    ("chiseled_quartz_block", 14),
+    ("chiseled_red_sandstone", 15),
+    ("chiseled_resin_bricks", 37),
+    ("chiseled_sandstone", 2),
+    ("chiseled_stone_bricks", 11),
+    ("chiseled_tuff", 43),
+    ("chiseled_tuff_bricks", 43),
+    ("chorus_flower", 24),
+    ("chorus_plant", 24),
+    ("clay", 9),
+    ("closed_eyeblossom", 7),
+    ("coal_block", 29),
+    ("coal_ore", 11),
+    ("coarse_dirt", 10),
+    ("cobbled_deepslate", 59),
+    ("cobblestone", 11),
+    ("cobweb", 3),
+    ("composter", 13),
+    ("conduit", 31),
+    ("copper_block", 15),
+    ("copper_bulb", 15),
+    ("copper_door", 15),
+    ("copper_grate", 15),
+    ("copper_ore", 11),
+    ("copper_trapdoor", 15),
+    ("cornflower", 7),
+    ("cracked_deepslate_bricks", 59),
+    ("cracked_deepslate_tiles", 59),
+    ("cracked_nether_bricks", 35),
+    ("cracked_polished_blackstone_bricks", 29),
+    ("cracked_stone_bricks", 11),
+    ("crafter", 11),
+    ("crafting_table", 13),
+    ("creaking_heart", 15),
+    ("crimson_fungus", 35),
+    ("crimson_hyphae", 54),
+    ("crimson_nylium", 52),
+    ("crimson_planks", 53),
+    ("crimson_roots", 35),
+    ("crimson_stem", 53),
+    ("crying_obsidian", 29),
+    ("cut_copper", 15),
+    ("cut_red_sandstone", 15),
+    ("cut_sandstone", 2),
+    ("damaged_anvil", 6),
+    ("dandelion", 7),
+    ("dark_oak_leaves", 7),
+    ("dark_oak_planks", 26),
+    ("dark_oak_sapling", 7),
+    ("dark_oak_wood", 26),
+    ("dark_prismarine", 31),
+    ("dead_bush", 13),
+    ("decorated_pot", 50),
+    ("deepslate", 59),
+    ("deepslate_bricks", 59),
+    ("deepslate_coal_ore", 59),
+    ("deepslate_copper_ore", 59),
+    ("deepslate_diamond_ore", 59),
+    ("deepslate_emerald_ore", 59),
+    ("deepslate_gold_ore", 59),
+    ("deepslate_iron_ore", 59),
+    ("deepslate_lapis_ore", 59),
+    ("deepslate_redstone_ore", 59),
+    ("deepslate_tiles", 59),
+    ("diamond_block", 31),
+    ("diamond_ore", 11),
+    ("diorite", 14),
+    ("dirt", 10),
+    ("dirt_path", 10),
+    ("dispenser", 11),
+    ("dragon_egg", 29),
+    ("dripstone_block", 48),
+    ("dropper", 11),
+    ("emerald_block", 33),
+    ("emerald_ore", 11),
+    ("enchanting_table", 28),
+    ("end_gateway", 29),
+    ("end_portal", 29),
+    ("end_portal_frame", 27),
+    ("end_stone", 2),
+    ("end_stone_bricks", 2),
+    ("exposed_chiseled_copper", 44),
+    ("exposed_copper", 44),
+    ("exposed_copper_bulb", 44),
+    ("exposed_copper_door", 44),
+    ("exposed_copper_grate", 44),
+    ("exposed_copper_trapdoor", 44),
+    ("exposed_cut_copper", 44),
+    ("farmland", 10),
+    ("fern", 7),
+    ("fire", 4),
+    ("firefly_bush", 7),
+    ("fletching_table", 13),
+    ("flowering_azalea", 7),
+    ("flowering_azalea_leaves", 7),
+    ("frogspawn", 12),
+    ("frosted_ice", 5),
+    ("furnace", 11),
+    ("gilded_blackstone", 29),
+    ("glow_lichen", 61),
+    ("glowstone", 2),
+    ("gold_block", 30),
+    ("gold_ore", 11),
+    ("granite", 10),
+    ("grass_block", 1),
+    ("gravel", 11),
+    ("grindstone", 6),
+    ("hanging_roots", 10),
+    ("hay_block", 18),
+    ("heavy_core", 6),
+    ("heavy_weighted_pressure_plate", 6),
+    ("honey_block", 15),
+    ("honeycomb_block", 15),
+    ("hopper", 11),
+    ("ice", 5),
+    ("infested_cobblestone", 11),
+    ("infested_deepslate", 59),
+    ("infested_stone", 11),
+    ("infested_stone_bricks", 11),
+    ("iron_block", 6),
+    ("iron_door", 6),
+    ("iron_ore", 11),
+    ("iron_trapdoor", 6),
+    ("jack_o_lantern", 15),
+    ("jukebox", 10),
+    ("jungle_leaves", 7),
+    ("jungle_planks", 10),
+    ("jungle_sapling", 7),
+    ("jungle_wood", 10),
+    ("kelp", 12),
+    ("kelp_plant", 12),
+    ("lantern", 6),
+    ("lapis_block", 32),
+    ("lapis_ore", 11),
+    ("large_amethyst_bud", 24),
+    ("large_fern", 7),
+    ("lava", 4),
+    ("lava_cauldron", 11),
+    ("leaf_litter", 0),
+    ("lectern", 13),
+    ("light_weighted_pressure_plate", 30),
+    ("lightning_rod", 15),
+    ("lilac", 7),
+    ("lily_of_the_valley", 7),
+    ("lily_pad", 7),
+    ("lodestone", 6),
+    ("loom", 13),
+    ("magma_block", 35),
+    ("mangrove_leaves", 7),
+    ("mangrove_planks", 28),
+    ("mangrove_roots", 34),
+    ("mangrove_wood", 28),
+    ("medium_amethyst_bud", 24),
+    ("melon", 19),
+    ("melon_stem", 7),
+    ("moss_block", 27),
+    ("moss_carpet", 27),
+    ("mossy_cobblestone", 11),
+    ("mossy_stone_bricks", 11),
+    ("mud", 45),
+    ("mud_bricks", 44),
+    ("mushroom_stem", 3),
+    ("mycelium", 24),
+    ("nether_bricks", 35),
+    ("nether_gold_ore", 35),
+    ("nether_quartz_ore", 35),
+    ("nether_sprouts", 23),
+    ("nether_wart", 28),
+    ("nether_wart_block", 28),
+    ("netherite_block", 29),
+    ("netherrack", 35),
+    ("note_block", 13),
+    ("oak_leaves", 7),
+    ("oak_planks", 13),
+    ("oak_sapling", 7),
+    ("oak_wood", 13),
+    ("observer", 11),
+    ("obsidian", 29),
+    ("open_eyeblossom", 7),
+    ("orange_tulip", 7),
+    ("oxeye_daisy", 7),
+    ("oxidized_chiseled_copper", 58),
+    ("oxidized_copper", 58),
+    ("oxidized_copper_bulb", 58),
+    ("oxidized_copper_door", 58),
+    ("oxidized_copper_grate", 58),
+    ("oxidized_copper_trapdoor", 58),
+    ("oxidized_cut_copper", 58),
+    ("packed_ice", 5),
+    ("packed_mud", 10),
+    ("pale_hanging_moss", 22),
+    ("pale_moss_block", 22),
+    ("pale_moss_carpet", 22),
+    ("pale_oak_leaves", 27),
+    ("pale_oak_planks", 14),
+    ("pale_oak_sapling", 7),
+    ("pale_oak_wood", 11),
+    ("peony", 7),
+    ("petrified_oak_slab", 13),
+    ("pink_petals", 7),
+    ("pink_tulip", 7),
+    ("piston", 11),
+    ("piston_head", 11),
+    ("pitcher_crop", 7),
+    ("pitcher_plant", 7),
+    ("podzol", 34),
+    ("pointed_dripstone", 48),
+    ("polished_andesite", 11),
+    ("polished_basalt", 29),
+    ("polished_blackstone", 29),
+    ("polished_blackstone_bricks", 29),
+    ("polished_deepslate", 59),
+    ("polished_diorite", 14),
+    ("polished_granite", 10),
+    ("polished_tuff", 43),
+    ("poppy", 7),
+    ("potatoes", 7),
+    ("powder_snow", 8),
+    ("powder_snow_cauldron", 11),
+    ("prismarine", 23),
+    ("prismarine_bricks", 31),
+    ("pumpkin", 15),
+    ("pumpkin_stem", 7),
+    ("purpur_block", 16),
+    ("purpur_pillar", 16),
+    ("quartz_block", 14),
+    ("quartz_bricks", 14),
+    ("quartz_pillar", 14),
+    ("raw_copper_block", 15),
+    ("raw_gold_block", 30),
+    ("raw_iron_block", 60),
+    ("red_mushroom", 28),
+    ("red_mushroom_block", 28),
+    ("red_nether_bricks", 35),
+    ("red_sand", 15),
+    ("red_sandstone", 15),
+    ("red_tulip", 7),
+    ("redstone_ore", 11),
+    ("reinforced_deepslate", 59),
+    ("resin_block", 37),
+    ("resin_bricks", 37),
+    ("resin_clump", 37),
+    ("respawn_anchor", 29),
+    ("rooted_dirt", 10),
+    ("rose_bush", 7),
+    ("sand", 2),
+    ("sandstone", 2),
+    ("scaffolding", 2),
+    ("sculk", 29),
+    ("sculk_catalyst", 29),
+    ("sculk_sensor", 23),
+    ("sculk_shrieker", 29),
+    ("sculk_vein", 29),
+    ("sea_lantern", 14),
+    ("sea_pickle", 27),
+    ("seagrass", 12),
+    ("short_dry_grass", 18),
+    ("short_grass", 7),
+    ("shroomlight", 28),
+    ("shulker_box", 24),
+    ("slime_block", 1),
+    ("small_amethyst_bud", 24),
+    ("small_dripleaf", 7),
+    ("smithing_table", 13),
+    ("smoker", 11),
+    ("smooth_basalt", 29),
+    ("smooth_quartz", 14),
+    ("smooth_red_sandstone", 15),
+    ("smooth_sandstone", 2),
+    ("smooth_stone", 11),
+    ("sniffer_egg", 28),
+    ("snow", 8),
+    ("snow_block", 8),
+    ("soul_fire", 17),
+    ("soul_lantern", 6),
+    ("soul_sand", 26),
+    ("soul_soil", 26),
+    ("spawner", 11),
+    ("sponge", 18),
+    ("spore_blossom", 7),
+    ("spruce_leaves", 7),
+    ("spruce_planks", 34),
+    ("spruce_sapling", 7),
+    ("spruce_wood", 34),
+    ("sticky_piston", 11),
+    ("stone", 11),
+    ("stone_bricks", 11),
+    ("stonecutter", 11),
+    ("stripped_acacia_wood", 15),
+    ("stripped_birch_wood", 2),
+    ("stripped_cherry_wood", 42),
+    ("stripped_crimson_hyphae", 54),
+    ("stripped_crimson_stem", 53),
+    ("stripped_dark_oak_wood", 26),
+    ("stripped_jungle_wood", 10),
+    ("stripped_mangrove_wood", 28),
+    ("stripped_oak_wood", 13),
+    ("stripped_pale_oak_wood", 14),
+    ("stripped_spruce_wood", 34),
+    ("stripped_warped_hyphae", 57),
+    ("stripped_warped_stem", 56),
+    ("sugar_cane", 7),
+    ("sunflower", 7),
+    ("suspicious_gravel", 11),
+    ("suspicious_sand", 2),
+    ("sweet_berry_bush", 7),
+    ("tall_dry_grass", 18),
+    ("tall_grass", 7),
+    ("tall_seagrass", 12),
+    ("target", 14),
+    ("terracotta", 15),
+    ("tinted_glass", 21),
+    ("tnt", 4),
+    ("torchflower", 7),
+    ("torchflower_crop", 7),
+    ("trapped_chest", 13),
+    ("trial_spawner", 11),
+    ("tuff", 43),
+    ("tuff_bricks", 43),
+    ("twisting_vines", 23),
+    ("twisting_vines_plant", 23),
+    ("vault", 11),
+    ("vine", 7),
+    ("warped_fungus", 23),
+    ("warped_hyphae", 57),
+    ("warped_nylium", 55),
+    ("warped_planks", 56),
+    ("warped_roots", 23),
+    ("warped_stem", 56),
+    ("warped_wart_block", 58),
+    ("water", 12),
+    ("water_cauldron", 11),
+    ("weathered_chiseled_copper", 56),
+    ("weathered_copper", 56),
+    ("weathered_copper_bulb", 56),
+    ("weathered_copper_door", 56),
+    ("weathered_copper_grate", 56),
+    ("weathered_copper_trapdoor", 56),
+    ("weathered_cut_copper", 56),
+    ("weeping_vines", 35),
+    ("weeping_vines_plant", 35),
+    ("wet_sponge", 18),
+    ("wheat", 7),
+    ("white_tulip", 7),
+    ("wildflowers", 7),
+    ("wither_rose", 7),
+];
+
+// blocks colored by the end they show on maps: (top, side)
+const PILLARS: &[(&str, u8, u8)] = &[
+    ("acacia_log", 15, 11),
+    ("bamboo_block", 18, 7),
+    ("birch_log", 2, 14),
+    ("cherry_log", 36, 43),
+    ("dark_oak_log", 26, 26),
+    ("jungle_log", 10, 34),
+    ("mangrove_log", 28, 34),
+    ("oak_log", 13, 34),
+    ("pale_oak_log", 14, 11),
+    ("spruce_log", 34, 26),
+    ("stripped_acacia_log", 15, 15),
+    ("stripped_bamboo_block", 18, 18),
+    ("stripped_birch_log", 2, 2),
+    ("stripped_cherry_log", 36, 42),
+    ("stripped_dark_oak_log", 26, 26),
+    ("stripped_jungle_log", 10, 10),
+    ("stripped_mangrove_log", 28, 28),
+    ("stripped_oak_log", 13, 13),
+    ("stripped_pale_oak_log", 14, 14),
+    ("stripped_spruce_log", 34, 34),
+];
@@ -1,3 +1,25 @@
+#[cfg(feature = "ndarray")]
+pub mod arrays;
 pub mod block;
+pub mod block_entity;
+pub mod builder;
+pub mod cache;
 pub mod chunk;
+pub mod diff;
+mod encode;
+pub(crate) mod hash;
+pub mod heightmap;
+mod light;
+mod limits;
+#[cfg(feature = "map_colors")]
+pub mod map_color;
+mod packed;
+pub mod raw;
+mod relocate;
+mod salvage;
 pub mod section;
+pub mod status;
+pub mod structure;
+pub mod surface;
+pub mod tick;
+pub mod upgrade;
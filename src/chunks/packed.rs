@@ -0,0 +1,135 @@
+/// Unpacks `count` values of `bits` bits each, stored starting from the
+/// least significant end of the longs. Values never span two longs, the
+/// leftover bits of each long are padding. Returns `None` if `data` is too
+/// short.
+pub(crate) fn unpack(data: &[i64], bits: usize, count: usize) -> Option<Vec<u64>> {
+    let per_long = 64 / bits;
+    if data.len() < packed_len(bits, count, false) {
+        return None;
+    }
+
+    // a long at a time, rather than dividing to find the long of each value
+    let mask = (1u64 << bits) - 1;
+    let mut values = Vec::with_capacity(count);
+    for &long in data {
+        let mut long = long as u64;
+        for _ in 0..per_long.min(count - values.len()) {
+            values.push(long & mask);
+            long >>= bits;
+        }
+        if values.len() == count {
+            break;
+        }
+    }
+    Some(values)
+}
+
+/// Like [`unpack`], but without padding, so a value can start at the end of
+/// one long and continue at the start of the next. Used before 1.16.
+pub(crate) fn unpack_spanning(data: &[i64], bits: usize, count: usize) -> Option<Vec<u64>> {
+    if data.len() < packed_len(bits, count, true) {
+        return None;
+    }
+
+    let mask = (1u64 << bits) - 1;
+    Some(
+        (0..count)
+            .map(|i| {
+                let (long, offset) = (i * bits / 64, i * bits % 64);
+                let mut value = data[long] as u64 >> offset;
+                // the high bits are at the start of the next long
+                if offset + bits > 64 {
+                    value |= (data[long + 1] as u64) << (64 - offset);
+                }
+                value & mask
+            })
+            .collect(),
+    )
+}
+
+/// The first of `count` values packed like [`unpack`] or [`unpack_spanning`]
+/// that is `limit` or more, found without unpacking them. `data` has to be
+/// long enough.
+pub(crate) fn find_at_least(
+    data: &[i64],
+    bits: usize,
+    count: usize,
+    spanning: bool,
+    limit: u64,
+) -> Option<u64> {
+    let mask = (1u64 << bits) - 1;
+    if spanning {
+        return (0..count)
+            .map(|i| {
+                let (long, offset) = (i * bits / 64, i * bits % 64);
+                let mut value = data[long] as u64 >> offset;
+                if offset + bits > 64 {
+                    value |= (data[long + 1] as u64) << (64 - offset);
+                }
+                value & mask
+            })
+            .find(|&value| value >= limit);
+    }
+
+    let per_long = 64 / bits;
+    let mut left = count;
+    for &long in data {
+        let mut long = long as u64;
+        for _ in 0..per_long.min(left) {
+            if long & mask >= limit {
+                return Some(long & mask);
+            }
+            long >>= bits;
+        }
+        left = left.saturating_sub(per_long);
+        if left == 0 {
+            break;
+        }
+    }
+    None
+}
+
+/// How many longs `count` values of `bits` bits take, see [`unpack`] and
+/// [`unpack_spanning`].
+pub(crate) fn packed_len(bits: usize, count: usize, spanning: bool) -> usize {
+    if spanning {
+        (count * bits).div_ceil(64)
+    } else {
+        count.div_ceil(64 / bits)
+    }
+}
+
+/// The reverse of [`unpack`], `values` have to fit in `bits` bits.
+pub(crate) fn pack(values: impl IntoIterator<Item = u64>, bits: usize) -> Vec<i64> {
+    let per_long = 64 / bits;
+    let mut data = Vec::new();
+    for (i, value) in values.into_iter().enumerate() {
+        if i % per_long == 0 {
+            data.push(0);
+        }
+        *data.last_mut().unwrap() |= (value << (i % per_long * bits)) as i64;
+    }
+    data
+}
+
+/// The reverse of [`unpack_spanning`], `values` have to fit in `bits` bits.
+pub(crate) fn pack_spanning(values: impl IntoIterator<Item = u64>, bits: usize) -> Vec<i64> {
+    let mut data = Vec::new();
+    for (i, value) in values.into_iter().enumerate() {
+        let (long, offset) = (i * bits / 64, i * bits % 64);
+        if long == data.len() {
+            data.push(0);
+        }
+        data[long] |= (value << offset) as i64;
+        // the high bits go at the start of the next long
+        if offset + bits > 64 {
+            data.push((value >> (64 - offset)) as i64);
+        }
+    }
+    data
+}
+
+/// The number of bits needed to store values up to and including `max`.
+pub(crate) fn bits_for(max: usize) -> usize {
+    (usize::BITS - max.leading_zeros()) as usize
+}
@@ -0,0 +1,428 @@
+use std::{
+    cmp::Ordering,
+    fmt,
+    io::{self, Cursor, Read},
+    ops::{Deref, Range},
+};
+
+use crate::{
+    block::BlockInterner,
+    chunk::{
+        Chunk, ChunkMetadata, ChunkParseError, ChunkParseOptions, parse_chunk_compound,
+        parse_chunk_status, parse_metadata_compound, parse_nbt_with,
+    },
+    chunks::salvage::parse_nbt_salvaging,
+    compression::CompressionType,
+    status::ChunkStatus,
+};
+use flate2::read::{GzDecoder, ZlibDecoder};
+use nbt_rs::types::NbtCompound;
+
+/// A chunk exactly as stored in a region file, before its payload is
+/// decompressed or decoded.
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+pub struct RawChunk {
+    pub(crate) timestamp: u32,
+    pub(crate) compression: CompressionType,
+    pub(crate) external: bool,
+    pub(crate) data: Payload,
+}
+
+/// The payload of a [`RawChunk`], its own or, with the `bytes` feature, a
+/// slice of the region it was read from.
+#[derive(Clone)]
+pub(crate) enum Payload {
+    Owned(Vec<u8>),
+    #[cfg(feature = "bytes")]
+    Shared(bytes::Bytes),
+}
+
+impl Deref for Payload {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            Payload::Owned(data) => data,
+            #[cfg(feature = "bytes")]
+            Payload::Shared(data) => data,
+        }
+    }
+}
+
+impl From<Vec<u8>> for Payload {
+    fn from(data: Vec<u8>) -> Self {
+        Payload::Owned(data)
+    }
+}
+
+// compared and shown by content, wherever it's kept
+impl fmt::Debug for Payload {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        (**self).fmt(f)
+    }
+}
+
+impl PartialEq for Payload {
+    fn eq(&self, other: &Self) -> bool {
+        **self == **other
+    }
+}
+
+impl PartialOrd for Payload {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        (**self).partial_cmp(&**other)
+    }
+}
+
+impl RawChunk {
+    /// Reads the chunk header and keeps the payload as is, so chunks using
+    /// compression formats this crate can't decode can still be passed
+    /// through. Only unknown compression bytes are rejected. The timestamp
+    /// is only known inside a region and is 0 here.
+    pub fn parse(bytes: &[u8]) -> Result<Self, ChunkParseError> {
+        let (compression, external, payload) = locate(bytes)?;
+        Ok(Self::owned(compression, external, &bytes[payload]))
+    }
+
+    /// Like [`RawChunk::parse`], but for the sectors a region allocates the
+    /// chunk, `allocated` bytes even if `bytes` was cut short at the end of
+    /// the file. A length running past them fails with
+    /// [`ChunkParseError::LengthExceedsSectors`], unless `recover` is set,
+    /// which takes everything after the header as the payload instead, also
+    /// for a length leaving no payload. Most formats ignore what follows
+    /// their data, so this rescues chunks whose length was garbled.
+    pub fn parse_sectors(
+        bytes: &[u8],
+        allocated: usize,
+        recover: bool,
+    ) -> Result<Self, ChunkParseError> {
+        let (compression, external, payload) = locate_in_sectors(bytes, allocated, recover)?;
+        Ok(Self::owned(compression, external, &bytes[payload]))
+    }
+
+    /// Like [`RawChunk::parse_sectors`], but the payload is a slice of
+    /// `bytes` rather than a copy.
+    #[cfg(feature = "bytes")]
+    pub(crate) fn parse_shared_sectors(
+        bytes: &bytes::Bytes,
+        allocated: usize,
+        recover: bool,
+    ) -> Result<Self, ChunkParseError> {
+        let (compression, external, payload) = locate_in_sectors(bytes, allocated, recover)?;
+        Ok(Self {
+            timestamp: 0,
+            compression,
+            external,
+            data: Payload::Shared(bytes.slice(payload)),
+        })
+    }
+
+    fn owned(compression: CompressionType, external: bool, payload: &[u8]) -> Self {
+        Self {
+            timestamp: 0,
+            compression,
+            external,
+            data: payload.to_vec().into(),
+        }
+    }
+
+    /// Appends the chunk with its header, the reverse of [`RawChunk::parse`].
+    pub(crate) fn write_to(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&(self.data.len() as u32 + 1).to_be_bytes());
+        out.push(self.compression.to_header_byte(self.external));
+        out.extend_from_slice(&self.data);
+    }
+
+    /// The last modification time, in seconds since the unix epoch.
+    pub fn timestamp(&self) -> u32 {
+        self.timestamp
+    }
+
+    pub fn compression(&self) -> CompressionType {
+        self.compression
+    }
+
+    /// Whether the payload is stored in a separate `.mcc` file, in which case
+    /// the data held here is empty and can't be decompressed.
+    pub fn is_external(&self) -> bool {
+        self.external
+    }
+
+    pub fn compressed_data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Decompresses the payload, up to the default
+    /// [`ChunkParseOptions::max_decompressed_len`].
+    pub fn decompressed_data(&self) -> Result<Vec<u8>, ChunkParseError> {
+        self.decompress(&ChunkParseOptions::default())
+    }
+
+    pub(crate) fn decompress(
+        &self,
+        options: &ChunkParseOptions,
+    ) -> Result<Vec<u8>, ChunkParseError> {
+        let mut decompressed = Vec::new();
+        self.decompress_into(options, &mut decompressed)?;
+        Ok(decompressed)
+    }
+
+    /// Decompresses the payload into `out`, replacing what it held, so its
+    /// allocation can be reused.
+    pub(crate) fn decompress_into(
+        &self,
+        options: &ChunkParseOptions,
+        out: &mut Vec<u8>,
+    ) -> Result<(), ChunkParseError> {
+        out.clear();
+        if self.external {
+            return Err(ChunkParseError::UnsupportedCompression);
+        }
+
+        let data = &*self.data;
+        let reader: Box<dyn Read> = match self.compression {
+            CompressionType::Gzip => Box::new(GzDecoder::new(data)),
+            CompressionType::Zlib => Box::new(ZlibDecoder::new(data)),
+            CompressionType::None => Box::new(data),
+            #[cfg(feature = "lz4")]
+            CompressionType::Lz4 => Box::new(crate::lz4::BlockReader::new(data)),
+            #[cfg(not(feature = "lz4"))]
+            CompressionType::Lz4 => return Err(ChunkParseError::UnsupportedCompression),
+            CompressionType::Custom => {
+                let (id, data) = split_codec_id(data).ok_or_else(|| {
+                    ChunkParseError::DecompressionFailed(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "missing custom compression identifier",
+                    ))
+                })?;
+                let codec = options
+                    .custom_codecs
+                    .get(id)
+                    .ok_or_else(|| ChunkParseError::UnknownCodec(id.to_owned()))?;
+                Box::new(Cursor::new(
+                    codec(data).map_err(ChunkParseError::DecompressionFailed)?,
+                ))
+            }
+        };
+
+        out.reserve(
+            self.decompressed_len_hint()
+                .min(options.max_decompressed_len),
+        );
+        read_limited(reader, options, out)
+    }
+
+    /// A guess of the decompressed length, to allocate it up front. Gzip
+    /// stores it in its trailer, modulo 2^32, other formats get a typical
+    /// ratio for nbt.
+    fn decompressed_len_hint(&self) -> usize {
+        match self.compression {
+            // deflate can't do better than about 1:1032, anything more is a
+            // lie
+            CompressionType::Gzip => self.data.last_chunk::<4>().map_or(0, |&len| {
+                (u32::from_le_bytes(len) as usize).min(self.data.len() * 1032)
+            }),
+            CompressionType::None => self.data.len(),
+            _ => self.data.len() * 8,
+        }
+    }
+
+    /// The identifier of the codec a chunk with custom compression was
+    /// written with, `None` for other compressions or if it's malformed.
+    pub fn custom_codec_id(&self) -> Option<&str> {
+        if self.compression != CompressionType::Custom {
+            return None;
+        }
+        split_codec_id(&self.data).map(|(id, _)| id)
+    }
+
+    pub fn decode(&self) -> Result<Chunk, ChunkParseError> {
+        self.decode_with(&ChunkParseOptions::default())
+    }
+
+    pub fn decode_with(&self, options: &ChunkParseOptions) -> Result<Chunk, ChunkParseError> {
+        self.decode_with_buffer(options, &mut Vec::new())
+    }
+
+    /// Like [`RawChunk::decode_with`], but decompresses into `buffer`, so
+    /// decoding many chunks with the same buffer doesn't allocate the
+    /// decompressed data again for each of them. What it holds afterwards
+    /// is unspecified.
+    pub fn decode_with_buffer(
+        &self,
+        options: &ChunkParseOptions,
+        buffer: &mut Vec<u8>,
+    ) -> Result<Chunk, ChunkParseError> {
+        Ok(self.decode_if(options, None, buffer, |_| true)?.unwrap())
+    }
+
+    /// Like [`RawChunk::decode_with`], but only reads the fields of
+    /// [`ChunkMetadata`].
+    pub fn decode_metadata(
+        &self,
+        options: &ChunkParseOptions,
+    ) -> Result<ChunkMetadata, ChunkParseError> {
+        self.decode_metadata_with_buffer(options, &mut Vec::new())
+    }
+
+    pub(crate) fn decode_metadata_with_buffer(
+        &self,
+        options: &ChunkParseOptions,
+        buffer: &mut Vec<u8>,
+    ) -> Result<ChunkMetadata, ChunkParseError> {
+        self.decompress_into(options, buffer)?;
+        parse_metadata_compound(&parse_nbt_with(buffer, options)?)
+    }
+
+    /// Decompresses the chunk and parses its nbt, checking the limits of
+    /// `options`, without decoding the compound into a [`Chunk`].
+    pub fn decode_nbt(&self, options: &ChunkParseOptions) -> Result<NbtCompound, ChunkParseError> {
+        parse_nbt_with(&self.decompress(options)?, options)
+    }
+
+    /// Like [`RawChunk::decode_with`], but blocks are shared through
+    /// `interner`, also with chunks decoded through it before.
+    pub fn decode_interned(
+        &self,
+        options: &ChunkParseOptions,
+        interner: &mut BlockInterner,
+    ) -> Result<Chunk, ChunkParseError> {
+        Ok(self
+            .decode_if(options, Some(interner), &mut Vec::new(), |_| true)?
+            .unwrap())
+    }
+
+    /// Like [`RawChunk::decode_with`], but returns `None` without decoding
+    /// the sections if `keep` rejects the chunk's status.
+    pub(crate) fn decode_if(
+        &self,
+        options: &ChunkParseOptions,
+        interner: Option<&mut BlockInterner>,
+        buffer: &mut Vec<u8>,
+        keep: impl Fn(&ChunkStatus) -> bool,
+    ) -> Result<Option<Chunk>, ChunkParseError> {
+        #[cfg(feature = "tracing")]
+        let span = tracing::debug_span!(
+            "parse_chunk",
+            compression = u8::from(self.compression),
+            data_version = tracing::field::Empty,
+            sections = tracing::field::Empty,
+        )
+        .entered();
+
+        self.decompress_into(options, buffer)?;
+        let (decoded, truncated) = parse_nbt_salvaging(buffer, options)?;
+
+        #[cfg(feature = "tracing")]
+        {
+            if let Some(data_version) = nbt_rs::get_field!(decoded, "DataVersion", as_int) {
+                span.record("data_version", data_version);
+            }
+            if let Some(sections) = nbt_rs::get_field!(decoded, "sections", as_list.as_compound) {
+                span.record("sections", sections.len());
+            }
+        }
+
+        if !keep(&parse_chunk_status(&decoded)) {
+            return Ok(None);
+        }
+        let mut chunk = parse_chunk_compound(&decoded, options, interner)?;
+        chunk.warnings.extend(truncated);
+        if options.keep_nbt {
+            chunk.raw_nbt = Some(decoded);
+        }
+        Ok(Some(chunk))
+    }
+}
+
+/// Appends what `reader` yields to `out`, failing with
+/// [`ChunkParseError::DecompressedTooLarge`] as soon as it exceeds
+/// [`ChunkParseOptions::max_decompressed_len`].
+pub(crate) fn read_limited(
+    reader: impl Read,
+    options: &ChunkParseOptions,
+    out: &mut Vec<u8>,
+) -> Result<(), ChunkParseError> {
+    // read one byte past the limit to tell reaching it from exceeding it
+    let limit = options.max_decompressed_len as u64 + 1;
+    let start = out.len();
+    reader
+        .take(limit)
+        .read_to_end(out)
+        .map_err(ChunkParseError::DecompressionFailed)?;
+    if out.len() - start > options.max_decompressed_len {
+        return Err(ChunkParseError::DecompressedTooLarge {
+            limit: options.max_decompressed_len,
+        });
+    }
+    Ok(())
+}
+
+/// Gunzips `bytes` and parses the nbt within, with the limits of
+/// `options`, for the gzip-compressed nbt files that aren't chunks.
+pub(crate) fn parse_gzip_nbt(
+    bytes: &[u8],
+    options: &ChunkParseOptions,
+) -> Result<NbtCompound, ChunkParseError> {
+    let mut data = Vec::new();
+    read_limited(GzDecoder::new(bytes), options, &mut data)?;
+    parse_nbt_with(&data, options)
+}
+
+// custom compressed data starts with the codec identifier, prefixed by its
+// length like nbt strings
+fn split_codec_id(data: &[u8]) -> Option<(&str, &[u8])> {
+    let (len, rest) = data.split_first_chunk::<2>()?;
+    let (id, rest) = rest.split_at_checked(u16::from_be_bytes(*len) as usize)?;
+    Some((std::str::from_utf8(id).ok()?, rest))
+}
+
+/// Reads the chunk header, returning the compression, whether the payload
+/// is external and where the payload is in `bytes`, see [`RawChunk::parse`].
+fn locate(bytes: &[u8]) -> Result<(CompressionType, bool, Range<usize>), ChunkParseError> {
+    if bytes.len() < 5 {
+        return Err(ChunkParseError::InputTooShort(5, bytes.len()));
+    }
+    let (header, body) = bytes.split_at(5);
+    let len = u32::from_be_bytes(header[..4].try_into().unwrap()) as usize;
+    // the length includes the compression byte
+    let len = len.saturating_sub(1);
+    if body.len() < len {
+        return Err(ChunkParseError::InputTooShort(
+            len.saturating_add(5),
+            bytes.len(),
+        ));
+    }
+
+    let (compression, external) = CompressionType::from_header_byte(header[4])
+        .map_err(|_| ChunkParseError::UnsupportedCompression)?;
+    Ok((compression, external, 5..5 + len))
+}
+
+/// Like [`locate`], for [`RawChunk::parse_sectors`].
+fn locate_in_sectors(
+    bytes: &[u8],
+    allocated: usize,
+    recover: bool,
+) -> Result<(CompressionType, bool, Range<usize>), ChunkParseError> {
+    if bytes.len() < 5 {
+        return Err(ChunkParseError::InputTooShort(5, bytes.len()));
+    }
+    let declared = u32::from_be_bytes(bytes[..4].try_into().unwrap()) as usize;
+    let available = allocated.saturating_sub(4);
+    // a length of 0 or 1 leaves no payload, which is as bogus
+    let bogus = declared > available || declared <= 1;
+    if recover && bogus {
+        // the header's length is ignored, all of the rest is the payload
+        let (compression, external) = CompressionType::from_header_byte(bytes[4])
+            .map_err(|_| ChunkParseError::UnsupportedCompression)?;
+        Ok((compression, external, 5..bytes.len()))
+    } else if declared > available {
+        Err(ChunkParseError::LengthExceedsSectors {
+            declared,
+            available,
+        })
+    } else {
+        locate(bytes)
+    }
+}
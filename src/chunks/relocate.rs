@@ -0,0 +1,129 @@
+//! Moving chunks to other chunk coordinates, see [`Chunk::relocate`].
+
+use std::collections::HashMap;
+
+use nbt_rs::types::{NbtCompound, NbtList, NbtString, NbtTag};
+
+use crate::chunks::{
+    chunk::Chunk,
+    structure::{BoundingBox, StructureStart},
+};
+
+impl Chunk {
+    /// Moves the chunk to the chunk at `new_cx`, `new_cz`, shifting the
+    /// world `x` and `z` of everything the crate reads along with it: block
+    /// entities, scheduled ticks, entities stored in the chunk, structure
+    /// references, and the boxes and chunk positions of structure starts and
+    /// their pieces. `y` is left as is.
+    ///
+    /// Positions in fields the crate doesn't read, like the home of a mob or
+    /// the position of a jigsaw junction, aren't shifted, and neither are
+    /// [`Chunk::raw_nbt`] and [`Chunk::legacy_sections`].
+    pub fn relocate(&mut self, new_cx: i32, new_cz: i32) {
+        let (dx, dz) = (new_cx - self.x_pos, new_cz - self.z_pos);
+        let (block_dx, block_dz) = (dx * 16, dz * 16);
+        self.x_pos = new_cx;
+        self.z_pos = new_cz;
+
+        // a shift of all of them keeps them sorted
+        for block_entity in &mut self.block_entities {
+            block_entity.x += block_dx;
+            block_entity.z += block_dz;
+            block_entity.data = map_fields(&block_entity.data, |name, tag| match (name, tag) {
+                ("x", &NbtTag::Int(x)) => Some(NbtTag::Int(x + block_dx)),
+                ("z", &NbtTag::Int(z)) => Some(NbtTag::Int(z + block_dz)),
+                _ => None,
+            });
+        }
+        for tick in self.block_ticks.iter_mut().chain(&mut self.fluid_ticks) {
+            tick.x += block_dx;
+            tick.z += block_dz;
+        }
+        for entity in &mut self.entities {
+            entity.pos.0 += block_dx as f64;
+            entity.pos.2 += block_dz as f64;
+            entity.data = shift_entity(&entity.data, block_dx as f64, block_dz as f64);
+        }
+        for (_, starts) in &mut self.structure_references {
+            for (x, z) in starts {
+                *x += dx;
+                *z += dz;
+            }
+        }
+        for start in &mut self.structure_starts {
+            shift_start(start, dx, dz);
+        }
+    }
+}
+
+/// `compound` with every field `f` returns a tag for replaced by it.
+fn map_fields(compound: &NbtCompound, f: impl Fn(&str, &NbtTag) -> Option<NbtTag>) -> NbtCompound {
+    let fields: Vec<(NbtString, NbtTag)> = compound.clone().into();
+    fields
+        .into_iter()
+        .map(|(name, tag)| {
+            let tag = f(&name, &tag).unwrap_or(tag);
+            (name, tag)
+        })
+        .collect::<HashMap<_, _>>()
+        .into()
+}
+
+/// The entity with its `Pos`, and those of its passengers, shifted.
+fn shift_entity(data: &NbtCompound, dx: f64, dz: f64) -> NbtCompound {
+    map_fields(data, |name, tag| match (name, tag) {
+        ("Pos", NbtTag::List(NbtList::Double(pos))) if pos.len() == 3 => {
+            let pos = vec![pos[0] + dx, pos[1], pos[2] + dz];
+            Some(NbtTag::List(NbtList::Double(pos.try_into().unwrap())))
+        }
+        ("Passengers", NbtTag::List(NbtList::Compound(passengers))) => {
+            let passengers: Vec<_> = (passengers.iter())
+                .map(|passenger| shift_entity(passenger, dx, dz))
+                .collect();
+            Some(NbtTag::List(NbtList::Compound(
+                passengers.try_into().unwrap(),
+            )))
+        }
+        _ => None,
+    })
+}
+
+fn shift_start(start: &mut StructureStart, dx: i32, dz: i32) {
+    let (block_dx, block_dz) = (dx * 16, dz * 16);
+    if let Some(BoundingBox { min, max }) = &mut start.bounding_box {
+        min.x += block_dx;
+        min.z += block_dz;
+        max.x += block_dx;
+        max.z += block_dz;
+    }
+    start.data = map_fields(&start.data, |name, tag| match (name, tag) {
+        ("ChunkX", &NbtTag::Int(x)) => Some(NbtTag::Int(x + dx)),
+        ("ChunkZ", &NbtTag::Int(z)) => Some(NbtTag::Int(z + dz)),
+        ("BB", tag) => shift_box(tag, block_dx, block_dz),
+        ("Children", NbtTag::List(NbtList::Compound(children))) => {
+            let children: Vec<_> = (children.iter())
+                .map(|child| {
+                    map_fields(child, |name, tag| match name {
+                        "BB" => shift_box(tag, block_dx, block_dz),
+                        _ => None,
+                    })
+                })
+                .collect();
+            Some(NbtTag::List(NbtList::Compound(
+                children.try_into().unwrap(),
+            )))
+        }
+        _ => None,
+    })
+}
+
+/// A `BB` int array shifted, `None` if it isn't one.
+fn shift_box(tag: &NbtTag, dx: i32, dz: i32) -> Option<NbtTag> {
+    match tag.as_int_array().map(|values| &values[..]) {
+        Some(&[min_x, min_y, min_z, max_x, max_y, max_z]) => {
+            let values = vec![min_x + dx, min_y, min_z + dz, max_x + dx, max_y, max_z + dz];
+            Some(NbtTag::IntArray(values.try_into().unwrap()))
+        }
+        _ => None,
+    }
+}
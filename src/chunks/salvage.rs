@@ -0,0 +1,207 @@
+//! Recovery of chunks whose nbt breaks off partway, see
+//! [`ChunkParseOptions::salvage`].
+
+use std::collections::HashSet;
+
+use nbt_rs::types::NbtCompound;
+
+use crate::{
+    chunks::{
+        chunk::{ChunkParseError, ChunkParseOptions, ParseWarning, parse_nbt_with},
+        limits::{
+            Scanner, TAG_BYTE_ARRAY, TAG_COMPOUND, TAG_END, TAG_INT_ARRAY, TAG_LIST,
+            TAG_LONG_ARRAY, TAG_STRING,
+        },
+    },
+    trace,
+};
+
+enum Open<'a> {
+    Compound {
+        names: HashSet<&'a [u8]>,
+    },
+    List {
+        tag: u8,
+        remaining: usize,
+        read: usize,
+        // where its length is stored, to write the number read over it
+        len_at: usize,
+    },
+}
+
+struct Frame<'a> {
+    open: Open<'a>,
+    // the end of the last entry or element read in full
+    boundary: usize,
+}
+
+/// Parses `data` like [`parse_nbt_with`], but with
+/// [`ChunkParseOptions::salvage`], nbt that breaks off or turns malformed
+/// partway is read up to there instead of failing. The warning says where
+/// and why.
+pub(crate) fn parse_nbt_salvaging(
+    data: &[u8],
+    options: &ChunkParseOptions,
+) -> Result<(NbtCompound, Option<ParseWarning>), ChunkParseError> {
+    let error = match parse_nbt_with(data, options) {
+        Ok(decoded) => return Ok((decoded, None)),
+        Err(ChunkParseError::ParseFailed(error)) if options.salvage => error,
+        Err(error) => return Err(error),
+    };
+    let Some((repaired, offset)) = repair(data) else {
+        return Err(ChunkParseError::ParseFailed(error));
+    };
+    // if the walk let through something the parser still rejects, the
+    // original error says more about the data than a second one
+    let decoded = parse_nbt_with(&repaired, options)
+        .map_err(|_| ChunkParseError::ParseFailed(error.clone()))?;
+    let warning = ParseWarning::Truncated {
+        offset,
+        error: error.to_string(),
+    };
+    trace::debug!(%warning, "salvaging a truncated chunk");
+    Ok((decoded, Some(warning)))
+}
+
+/// Walks the nbt up to where it can't be read anymore and returns the bytes
+/// read so far with everything still open closed, along with how many bytes
+/// of `data` were kept. Compounds keep the entries read in full, the
+/// outermost list drops the element that was cut short. `None` if the root
+/// compound itself can't be read.
+fn repair(data: &[u8]) -> Option<(Vec<u8>, usize)> {
+    let mut scanner = Scanner { data, pos: 0 };
+    if scanner.u8()? != TAG_COMPOUND {
+        return None;
+    }
+    let name_len = scanner.u16()?;
+    std::str::from_utf8(scanner.take(name_len)?).ok()?;
+    let mut stack = vec![Frame {
+        open: Open::Compound {
+            names: HashSet::new(),
+        },
+        boundary: scanner.pos,
+    }];
+
+    if walk(&mut scanner, &mut stack).is_some() {
+        // the root was read in full, only what follows it is wrong
+        return Some((data[..scanner.pos].to_vec(), scanner.pos));
+    }
+
+    // an element cut short is left out of the outermost list whole, so a
+    // chunk doesn't keep a section without the fields after the cut
+    if let Some(list) = (stack.iter()).position(|frame| matches!(frame.open, Open::List { .. })) {
+        stack.truncate(list + 1);
+    }
+    let cut = stack.last()?.boundary;
+    let mut repaired = data[..cut].to_vec();
+    for frame in stack.iter().rev() {
+        match frame.open {
+            Open::Compound { .. } => repaired.push(TAG_END),
+            Open::List { read, len_at, .. } => {
+                repaired[len_at..len_at + 4].copy_from_slice(&(read as i32).to_be_bytes());
+            }
+        }
+    }
+    Some((repaired, cut))
+}
+
+/// Reads until the root compound ends, `None` if the data stops being
+/// readable first, with the compounds and lists it's inside of left on
+/// `stack`.
+fn walk<'a>(scanner: &mut Scanner<'a>, stack: &mut Vec<Frame<'a>>) -> Option<()> {
+    let data = scanner.data;
+    loop {
+        let frame = stack.last_mut()?;
+        let tag = match &mut frame.open {
+            Open::Compound { names } => {
+                let tag = scanner.u8()?;
+                if tag == TAG_END {
+                    stack.pop();
+                    if stack.is_empty() {
+                        return Some(());
+                    }
+                    read_in_full(stack, scanner.pos);
+                    continue;
+                }
+                let name_len = scanner.u16()?;
+                let start = scanner.pos;
+                scanner.take(name_len)?;
+                let name = &data[start..scanner.pos];
+                std::str::from_utf8(name).ok()?;
+                if !names.insert(name) {
+                    return None;
+                }
+                tag
+            }
+            Open::List { tag, remaining, .. } => {
+                if *remaining == 0 {
+                    stack.pop();
+                    read_in_full(stack, scanner.pos);
+                    continue;
+                }
+                *remaining -= 1;
+                *tag
+            }
+        };
+
+        match tag {
+            1..=6 => {
+                scanner.take([1, 2, 4, 8, 4, 8][tag as usize - 1])?;
+            }
+            TAG_BYTE_ARRAY | TAG_INT_ARRAY | TAG_LONG_ARRAY => {
+                let len = scanner.len()?;
+                let size = match tag {
+                    TAG_BYTE_ARRAY => 1,
+                    TAG_INT_ARRAY => 4,
+                    _ => 8,
+                };
+                scanner.take(len.checked_mul(size)?)?;
+            }
+            TAG_STRING => {
+                let len = scanner.u16()?;
+                std::str::from_utf8(scanner.take(len)?).ok()?;
+            }
+            TAG_LIST => {
+                let tag = scanner.u8()?;
+                if tag > TAG_LONG_ARRAY {
+                    return None;
+                }
+                let len_at = scanner.pos;
+                let mut remaining = scanner.len()?;
+                if tag == TAG_END {
+                    remaining = 0;
+                }
+                stack.push(Frame {
+                    open: Open::List {
+                        tag,
+                        remaining,
+                        read: 0,
+                        len_at,
+                    },
+                    boundary: scanner.pos,
+                });
+                continue;
+            }
+            TAG_COMPOUND => {
+                stack.push(Frame {
+                    open: Open::Compound {
+                        names: HashSet::new(),
+                    },
+                    boundary: scanner.pos,
+                });
+                continue;
+            }
+            _ => return None,
+        }
+        read_in_full(stack, scanner.pos);
+    }
+}
+
+fn read_in_full(stack: &mut [Frame], pos: usize) {
+    if let Some(frame) = stack.last_mut() {
+        frame.boundary = pos;
+        if let Open::List { read, .. } = &mut frame.open {
+            *read += 1;
+        }
+    }
+}
@@ -1,32 +1,787 @@
-use std::rc::Rc;
+use std::{
+    borrow::Cow,
+    cmp::Ordering,
+    collections::HashMap,
+    ops::{Index, Range},
+    sync::OnceLock,
+};
 
-use crate::chunks::block::Block;
+use nbt_rs::types::NbtString;
 
-#[derive(Debug, Clone, PartialEq, PartialOrd)]
+use crate::{
+    bounds::{Axis, OutOfBounds},
+    chunks::{
+        block::{Block, Shared},
+        packed,
+    },
+};
+
+/// The inclusive minimum and maximum `(x, y, z)` corners of a box of blocks
+/// in a section.
+pub type SectionBounds = ((u8, u8, u8), (u8, u8, u8));
+
+#[derive(Debug, Clone)]
 pub struct Section {
-    pub(crate) blocks: [Rc<Block>; 4096],
+    // may hold entries no block uses anymore, they're dropped when it fills up
+    pub(crate) palette: Vec<Shared<Block>>,
+    // only filled on first use if the section was read lazily, from `packed`
+    decoded: OnceLock<Decoded>,
+    packed: Option<Packed>,
+    // one per 4x4x4 cell in the same order as blocks, or none if not stored
+    pub(crate) biomes: Vec<Shared<NbtString>>,
+    // a nibble per block in the same order as blocks, low nibble first
+    pub(crate) block_light: Option<Box<[u8; 2048]>>,
+    pub(crate) sky_light: Option<Box<[u8; 2048]>>,
+    // set once a block was changed, so the light the chunk stores is stale
+    pub(crate) edited: bool,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct Decoded {
+    pub(crate) indices: Indices,
+    // one bit per block, set for everything but air
+    pub(crate) occupancy: [u64; 64],
+}
+
+impl Decoded {
+    /// `indices` has to hold 4096 valid indices into `palette`.
+    fn new(palette: &[Shared<Block>], indices: &[u64]) -> Self {
+        let air: Vec<bool> = palette.iter().map(|block| block.is_air()).collect();
+        let mut occupancy = [0u64; 64];
+        for (i, &index) in indices.iter().enumerate() {
+            if !air[index as usize] {
+                occupancy[i >> 6] |= 1 << (i & 63);
+            }
+        }
+        Self {
+            indices: Indices::from_fn(palette.len(), |i| indices[i] as usize),
+            occupancy,
+        }
+    }
+}
+
+/// The palette indices as stored, see [`packed::unpack`].
+#[derive(Debug, Clone)]
+pub(crate) struct Packed {
+    pub(crate) data: Box<[i64]>,
+    pub(crate) bits: usize,
+    pub(crate) spanning: bool,
+}
+
+/// The palette index of every block, as narrow as the palette allows.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Indices {
+    /// Every block is the first palette entry.
+    Single,
+    Bytes(Box<[u8; 4096]>),
+    Shorts(Box<[u16; 4096]>),
+}
+
+impl Indices {
+    fn from_fn(palette_len: usize, f: impl Fn(usize) -> usize) -> Self {
+        match palette_len {
+            0..=1 => Indices::Single,
+            2..=256 => Indices::Bytes(Box::new(std::array::from_fn(|i| f(i) as u8))),
+            _ => Indices::Shorts(Box::new(std::array::from_fn(|i| f(i) as u16))),
+        }
+    }
+
+    #[inline(always)]
+    fn get(&self, i: usize) -> usize {
+        match self {
+            Indices::Single => 0,
+            Indices::Bytes(indices) => indices[i] as usize,
+            Indices::Shorts(indices) => indices[i] as usize,
+        }
+    }
+
+    /// How many palette entries can be told apart.
+    fn capacity(&self) -> usize {
+        match self {
+            Indices::Single => 1,
+            Indices::Bytes(_) => 256,
+            // twice the blocks a section holds, so unused entries can pile
+            // up a while before they're dropped
+            Indices::Shorts(_) => 8192,
+        }
+    }
 }
 
 impl Section {
+    pub(crate) fn new(blocks: [Shared<Block>; 4096]) -> Self {
+        let mut palette: Vec<Shared<Block>> = Vec::new();
+        let mut indices = [0u16; 4096];
+        for (index, block) in indices.iter_mut().zip(&blocks) {
+            let known = palette
+                .iter()
+                .position(|known| Shared::ptr_eq(known, block))
+                .or_else(|| palette.iter().position(|known| known == block));
+            *index = known.unwrap_or_else(|| {
+                palette.push(block.clone());
+                palette.len() - 1
+            }) as u16;
+        }
+        let indices: Vec<u64> = indices.iter().map(|&index| index as u64).collect();
+        Self::from_palette(&palette, &indices)
+    }
+
+    /// Takes the section's palette and the index into it of every block.
+    /// `indices` has to hold 4096 valid indices into `palette`.
+    pub(crate) fn from_palette(palette: &[Shared<Block>], indices: &[u64]) -> Self {
+        Self::from_decoded(palette.to_vec(), Decoded::new(palette, indices))
+    }
+
+    /// Takes the blocks as another section holds them, see
+    /// [`Section::decoded_parts`]. `decoded` has to hold valid indices into
+    /// `palette`.
+    pub(crate) fn from_decoded(palette: Vec<Shared<Block>>, decoded: Decoded) -> Self {
+        Self {
+            palette,
+            decoded: OnceLock::from(decoded),
+            packed: None,
+            biomes: Vec::new(),
+            block_light: None,
+            sky_light: None,
+            edited: false,
+        }
+    }
+
+    /// Like [`Section::from_palette`], but keeps the indices packed as
+    /// stored until the blocks are first looked at. Returns `None` if `data`
+    /// is too short. Indices past the end of the palette have to be checked
+    /// beforehand, they read as the first entry.
+    pub(crate) fn from_packed(
+        palette: Vec<Shared<Block>>,
+        data: &[i64],
+        bits: usize,
+        spanning: bool,
+    ) -> Option<Self> {
+        if data.len() < packed::packed_len(bits, 4096, spanning) {
+            return None;
+        }
+        Some(Self {
+            palette,
+            decoded: OnceLock::new(),
+            packed: Some(Packed {
+                data: data.into(),
+                bits,
+                spanning,
+            }),
+            biomes: Vec::new(),
+            block_light: None,
+            sky_light: None,
+            edited: false,
+        })
+    }
+
+    fn decoded(&self) -> &Decoded {
+        self.decoded.get_or_init(|| {
+            let Packed {
+                data,
+                bits,
+                spanning,
+                ..
+            } = self.packed.as_ref().unwrap();
+            let indices = if *spanning {
+                packed::unpack_spanning(data, *bits, 4096)
+            } else {
+                packed::unpack(data, *bits, 4096)
+            };
+            // only left in by lenient parsing, which warned about them
+            let len = self.palette.len() as u64;
+            let mut indices = indices.unwrap();
+            for index in indices.iter_mut().filter(|index| **index >= len) {
+                *index = 0;
+            }
+            Decoded::new(&self.palette, &indices)
+        })
+    }
+
+    fn decoded_mut(&mut self) -> &mut Decoded {
+        self.decoded();
+        self.packed = None;
+        self.decoded.get_mut().unwrap()
+    }
+
+    /// The blocks as the section holds them, if they've been decoded.
+    pub(crate) fn decoded_parts(&self) -> Option<&Decoded> {
+        self.decoded.get()
+    }
+
+    /// The palette indices as stored, if the blocks were read lazily and
+    /// haven't been changed since.
+    pub(crate) fn packed_parts(&self) -> Option<&Packed> {
+        self.packed.as_ref()
+    }
+
+    /// Whether the blocks have been decoded, which only isn't the case for
+    /// sections read with [`crate::chunk::ChunkParseOptions::lazy_sections`]
+    /// whose blocks haven't been looked at yet.
+    pub fn is_decoded(&self) -> bool {
+        self.decoded.get().is_some()
+    }
+
+    /// A section made up of only `block`.
+    pub fn filled(block: Block) -> Self {
+        Self::filled_shared(Shared::new(block))
+    }
+
+    pub(crate) fn filled_shared(block: Shared<Block>) -> Self {
+        let occupancy = [if block.is_air() { 0 } else { u64::MAX }; 64];
+        Self {
+            palette: vec![block],
+            decoded: OnceLock::from(Decoded {
+                indices: Indices::Single,
+                occupancy,
+            }),
+            packed: None,
+            biomes: Vec::new(),
+            block_light: None,
+            sky_light: None,
+            edited: false,
+        }
+    }
+
+    /// A section with only air.
+    pub fn empty() -> Self {
+        Self::filled(Block::air())
+    }
+
+    /// The block this section is made up of, if it's only one.
+    pub fn is_uniform(&self) -> Option<&Block> {
+        let first = self.block(0);
+        self.blocks()
+            .all(|block| Shared::ptr_eq(block, first) || block == first)
+            .then_some(&**first)
+    }
+
+    /// The distinct blocks in the section, in the order of the palette.
+    /// Entries of the palette no block uses anymore are left out.
+    pub fn unique_blocks(&self) -> Vec<&Block> {
+        let mut used = vec![false; self.palette.len()];
+        for index in self.iter_palette_indices() {
+            used[index] = true;
+        }
+
+        let mut blocks: Vec<&Block> = Vec::new();
+        for (block, _) in self.palette.iter().zip(used).filter(|(_, used)| *used) {
+            if !blocks.contains(&&**block) {
+                blocks.push(block);
+            }
+        }
+        blocks
+    }
+
+    /// The blocks the section refers to by [`Section::palette_indices`].
+    /// It may hold entries no block uses anymore, and the same block more
+    /// than once.
+    pub fn palette(&self) -> &[Shared<Block>] {
+        &self.palette
+    }
+
+    /// The index into [`Section::palette`] of every block, in `y`, `z`, `x`
+    /// order, so the block at `x`, `y`, `z` is at `(y * 16 + z) * 16 + x`.
+    /// Borrowed if the section keeps its indices this wide, which it does
+    /// for palettes of more than 256 entries, and built otherwise.
+    pub fn palette_indices(&self) -> Cow<'_, [u16; 4096]> {
+        match &self.decoded().indices {
+            Indices::Shorts(indices) => Cow::Borrowed(indices),
+            indices => Cow::Owned(std::array::from_fn(|i| indices.get(i) as u16)),
+        }
+    }
+
+    /// Like [`Section::palette_indices`] and [`Section::palette`] together,
+    /// but into buffers the caller keeps between sections, so reading many
+    /// sections allocates nothing once `palette` has grown large enough.
+    /// Only the palette entries are cloned, never a block per index. In the
+    /// `iter_blocks` benchmark this reads a region's sections about 50 times
+    /// as fast as [`Section::get_block`] for every block.
+    pub fn copy_blocks_into(&self, indices: &mut [u16; 4096], palette: &mut Vec<Shared<Block>>) {
+        match &self.decoded().indices {
+            Indices::Single => indices.fill(0),
+            Indices::Bytes(bytes) => {
+                for (index, &byte) in indices.iter_mut().zip(bytes.iter()) {
+                    *index = byte as u16;
+                }
+            }
+            Indices::Shorts(shorts) => indices.copy_from_slice(&shorts[..]),
+        }
+        palette.clear();
+        palette.extend_from_slice(&self.palette);
+    }
+
+    /// The palette index of every block, in `y`, `z`, `x` order.
+    pub(crate) fn iter_palette_indices(&self) -> impl Iterator<Item = usize> + '_ {
+        let indices = &self.decoded().indices;
+        (0..4096).map(|i| indices.get(i))
+    }
+
+    #[inline(always)]
+    pub(crate) fn palette_index(&self, i: usize) -> usize {
+        self.decoded().indices.get(i)
+    }
+
+    /// The block at an index as used by [`Section::iter_palette_indices`].
+    #[inline(always)]
+    pub(crate) fn block(&self, i: usize) -> &Shared<Block> {
+        &self.palette[self.decoded().indices.get(i)]
+    }
+
+    /// Every block in `y`, `z`, `x` order, shared with the palette.
+    pub(crate) fn blocks(&self) -> impl Iterator<Item = &Shared<Block>> + '_ {
+        self.iter_palette_indices()
+            .map(|index| &self.palette[index])
+    }
+
+    /// Whether both sections have the same palette and the same indices
+    /// into it, compared without decoding if neither is decoded yet. `false`
+    /// doesn't mean the blocks differ, only that they have to be compared
+    /// one by one.
+    pub(crate) fn same_blocks(&self, other: &Section) -> bool {
+        let same_palette = self.palette.len() == other.palette.len()
+            && (self.palette.iter())
+                .zip(&other.palette)
+                .all(|(a, b)| Shared::ptr_eq(a, b) || a == b);
+        if !same_palette {
+            return false;
+        }
+        match (&self.packed, &other.packed) {
+            (Some(a), Some(b)) if !self.is_decoded() || !other.is_decoded() => {
+                (&a.data, a.bits, a.spanning) == (&b.data, b.bits, b.spanning)
+            }
+            _ => self.decoded().indices == other.decoded().indices,
+        }
+    }
+
+    /// Whether both sections have the same blocks and biomes, leaving out
+    /// light. Blocks are compared by value, however the palettes are laid
+    /// out.
+    pub(crate) fn same_content(&self, other: &Section) -> bool {
+        self.blocks()
+            .zip(other.blocks())
+            .all(|(a, b)| Shared::ptr_eq(a, b) || a == b)
+            && self.biomes == other.biomes
+    }
+
     #[inline(always)]
     fn get_block_pos(x: u8, y: u8, z: u8) -> usize {
         let (x, y, z) = (x as u32, y as u32, z as u32);
-        return ((((y << 4) | z) << 4) | x) as usize;
+        ((((y << 4) | z) << 4) | x) as usize
     }
 
+    /// Returns `None` if any of the coordinates is 16 or above.
     pub fn get_block(&self, x: u8, y: u8, z: u8) -> Option<&Block> {
         if x >= 16 || y >= 16 || z >= 16 {
             return None;
         }
 
-        return Some(&self.blocks[Section::get_block_pos(x, y, z)]);
+        Some(self.block(Section::get_block_pos(x, y, z)))
+    }
+
+    /// The biome at the block, biomes are stored for 4x4x4 cells. Returns
+    /// `None` if any of the coordinates is 16 or above, or if the section
+    /// has no biomes, like ones written before 1.18.
+    pub fn get_biome(&self, x: u8, y: u8, z: u8) -> Option<&NbtString> {
+        if x >= 16 || y >= 16 || z >= 16 {
+            return None;
+        }
+
+        let (x, y, z) = ((x >> 2) as usize, (y >> 2) as usize, (z >> 2) as usize);
+        self.biomes
+            .get((((y << 2) | z) << 2) | x)
+            .map(|biome| &**biome)
+    }
+
+    /// Sets the biome of the 4x4x4 cell holding the block. A section without
+    /// biomes, like one from [`Section::empty`], gets `name` in every cell.
+    pub fn set_biome(&mut self, x: u8, y: u8, z: u8, name: NbtString) -> Result<(), OutOfBounds> {
+        OutOfBounds::check(Axis::X, x, 0..16)?;
+        OutOfBounds::check(Axis::Y, y, 0..16)?;
+        OutOfBounds::check(Axis::Z, z, 0..16)?;
+
+        // cells of the same biome share it, like decoded ones do
+        let biome = match self.biomes.iter().find(|&biome| **biome == name) {
+            Some(known) => known.clone(),
+            None => Shared::new(name),
+        };
+        if self.biomes.is_empty() {
+            self.biomes = vec![biome; 64];
+            return Ok(());
+        }
+        let (x, y, z) = ((x >> 2) as usize, (y >> 2) as usize, (z >> 2) as usize);
+        self.biomes[(((y << 2) | z) << 2) | x] = biome;
+        Ok(())
+    }
+
+    /// Sets the biome of every cell, so the section is written with a
+    /// single biome.
+    pub fn fill_biome(&mut self, name: NbtString) {
+        self.biomes = vec![Shared::new(name); 64];
     }
 
-    pub fn set_block(&mut self, x: u8, y: u8, z: u8, block: Block) {
+    /// The block light at the block. Returns `None` if any of the coordinates
+    /// is 16 or above, or if the section has no block light stored.
+    pub fn get_block_light(&self, x: u8, y: u8, z: u8) -> Option<u8> {
+        Section::get_light(self.block_light.as_deref()?, x, y, z)
+    }
+
+    /// The sky light at the block. Returns `None` if any of the coordinates
+    /// is 16 or above, or if the section has no sky light stored.
+    pub fn get_sky_light(&self, x: u8, y: u8, z: u8) -> Option<u8> {
+        Section::get_light(self.sky_light.as_deref()?, x, y, z)
+    }
+
+    fn get_light(light: &[u8; 2048], x: u8, y: u8, z: u8) -> Option<u8> {
         if x >= 16 || y >= 16 || z >= 16 {
+            return None;
+        }
+
+        let index = Section::get_block_pos(x, y, z);
+        Some((light[index >> 1] >> ((index & 1) * 4)) & 0xF)
+    }
+
+    /// Sets the block light at the block, for tools that light chunks
+    /// themselves. Levels above 15 are clamped. A section without block light
+    /// gets an unlit one first.
+    pub fn set_block_light(&mut self, x: u8, y: u8, z: u8, level: u8) -> Result<(), OutOfBounds> {
+        Section::set_light(&mut self.block_light, x, y, z, level)
+    }
+
+    /// Like [`Section::set_block_light`], but for the sky light.
+    pub fn set_sky_light(&mut self, x: u8, y: u8, z: u8, level: u8) -> Result<(), OutOfBounds> {
+        Section::set_light(&mut self.sky_light, x, y, z, level)
+    }
+
+    fn set_light(
+        light: &mut Option<Box<[u8; 2048]>>,
+        x: u8,
+        y: u8,
+        z: u8,
+        level: u8,
+    ) -> Result<(), OutOfBounds> {
+        OutOfBounds::check(Axis::X, x, 0..16)?;
+        OutOfBounds::check(Axis::Y, y, 0..16)?;
+        OutOfBounds::check(Axis::Z, z, 0..16)?;
+
+        let index = Section::get_block_pos(x, y, z);
+        let shift = (index & 1) * 4;
+        let byte = &mut light.get_or_insert_with(|| Box::new([0; 2048]))[index >> 1];
+        *byte = (*byte & !(0xF << shift)) | (level.min(15) << shift);
+        Ok(())
+    }
+
+    /// Drops the block and sky light, which the game computes again for
+    /// chunks it loads without light, see [`Chunk::to_nbt`].
+    ///
+    /// [`Chunk::to_nbt`]: crate::chunk::Chunk::to_nbt
+    pub fn clear_light(&mut self) {
+        self.block_light = None;
+        self.sky_light = None;
+    }
+
+    /// Blocks already in the palette are reused, new ones are added to it.
+    pub fn set_block(&mut self, x: u8, y: u8, z: u8, block: Block) -> Result<(), OutOfBounds> {
+        OutOfBounds::check(Axis::X, x, 0..16)?;
+        OutOfBounds::check(Axis::Y, y, 0..16)?;
+        OutOfBounds::check(Axis::Z, z, 0..16)?;
+
+        let entry = self.palette_entry(block);
+        self.set_index(Section::get_block_pos(x, y, z), entry);
+        Ok(())
+    }
+
+    /// Sets every block in the section, keeping its biomes and light.
+    pub fn fill(&mut self, block: Block) {
+        self.fill_shared(Shared::new(block));
+    }
+
+    pub(crate) fn fill_shared(&mut self, block: Shared<Block>) {
+        *self = Section {
+            biomes: std::mem::take(&mut self.biomes),
+            block_light: self.block_light.take(),
+            sky_light: self.sky_light.take(),
+            edited: true,
+            ..Section::filled_shared(block)
+        };
+    }
+
+    /// Sets every block in the box spanned by the ranges. The ranges are
+    /// clamped to the section, so parts past 15 are left out and an empty
+    /// range sets nothing.
+    pub fn fill_box(&mut self, x: Range<u8>, y: Range<u8>, z: Range<u8>, block: Block) {
+        self.fill_box_shared(x, y, z, Shared::new(block));
+    }
+
+    /// Like [`Section::fill_box`], with `block` added to the palette as is
+    /// if it isn't in it yet, so it can be shared with other sections.
+    pub(crate) fn fill_box_shared(
+        &mut self,
+        x: Range<u8>,
+        y: Range<u8>,
+        z: Range<u8>,
+        block: Shared<Block>,
+    ) {
+        let clamp = |range: Range<u8>| range.start.min(16)..range.end.min(16);
+        let (x, y, z) = (clamp(x), clamp(y), clamp(z));
+        if x.is_empty() || y.is_empty() || z.is_empty() {
             return;
         }
+        if (x.len(), y.len(), z.len()) == (16, 16, 16) {
+            return self.fill_shared(block);
+        }
+
+        let entry = self.palette_entry_shared(block);
+        for y in y {
+            for z in z.clone() {
+                for x in x.clone() {
+                    self.set_index(Section::get_block_pos(x, y, z), entry);
+                }
+            }
+        }
+    }
+
+    /// The palette entry holding `block`, added if there is none yet.
+    fn palette_entry(&mut self, block: Block) -> usize {
+        match self.palette.iter().position(|known| **known == block) {
+            Some(entry) => entry,
+            None => self.push_entry(Shared::new(block)),
+        }
+    }
+
+    fn palette_entry_shared(&mut self, block: Shared<Block>) -> usize {
+        match self.palette.iter().position(|known| *known == block) {
+            Some(entry) => entry,
+            None => self.push_entry(block),
+        }
+    }
+
+    fn push_entry(&mut self, block: Shared<Block>) -> usize {
+        if self.palette.len() == self.decoded().indices.capacity() {
+            self.drop_unused();
+        }
+        self.palette.push(block);
+        self.palette.len() - 1
+    }
+
+    /// Stores a palette index, widening the indices if it doesn't fit.
+    fn set_index(&mut self, i: usize, entry: usize) {
+        let air = self.palette[entry].is_air();
+        self.edited = true;
+        let decoded = self.decoded_mut();
+        if air {
+            decoded.occupancy[i >> 6] &= !(1 << (i & 63));
+        } else {
+            decoded.occupancy[i >> 6] |= 1 << (i & 63);
+        }
+        if entry >= decoded.indices.capacity() {
+            let indices = &decoded.indices;
+            decoded.indices = Indices::from_fn(entry + 1, |i| indices.get(i));
+        }
+        match &mut decoded.indices {
+            Indices::Single => {}
+            Indices::Bytes(indices) => indices[i] = entry as u8,
+            Indices::Shorts(indices) => indices[i] = entry as u16,
+        }
+    }
+
+    /// Removes the palette entries no block uses.
+    fn drop_unused(&mut self) {
+        let mut remap = vec![None; self.palette.len()];
+        let mut palette = Vec::new();
+        for index in self.iter_palette_indices() {
+            remap[index].get_or_insert_with(|| {
+                palette.push(self.palette[index].clone());
+                palette.len() - 1
+            });
+        }
+
+        let decoded = self.decoded_mut();
+        let indices = &decoded.indices;
+        decoded.indices = Indices::from_fn(palette.len(), |i| remap[indices.get(i)].unwrap());
+        self.palette = palette;
+    }
+
+    /// How many of each block the section holds. Counted per palette entry
+    /// rather than per block.
+    pub fn block_counts(&self) -> HashMap<&Block, u32> {
+        let mut per_entry = vec![0u32; self.palette.len()];
+        match self.decoded().indices {
+            Indices::Single => per_entry[0] = 4096,
+            _ => {
+                for index in self.iter_palette_indices() {
+                    per_entry[index] += 1;
+                }
+            }
+        }
+
+        let mut counts = HashMap::new();
+        for (block, count) in self.palette.iter().zip(per_entry) {
+            if count > 0 {
+                *counts.entry(&**block).or_insert(0) += count;
+            }
+        }
+        counts
+    }
+
+    /// The positions of the blocks `predicate` returns `true` for, in `y`,
+    /// `z`, `x` order. It's called once per palette entry, and if none
+    /// match, the blocks aren't looked at at all.
+    pub fn find_blocks(&self, predicate: impl Fn(&Block) -> bool) -> Vec<(u8, u8, u8)> {
+        let matches: Vec<bool> = self.palette.iter().map(|block| predicate(block)).collect();
+        if !matches.contains(&true) {
+            return Vec::new();
+        }
+        self.iter_palette_indices()
+            .enumerate()
+            .filter(|&(_, index)| matches[index])
+            .map(|(i, _)| Section::get_block_coords(i))
+            .collect()
+    }
+
+    /// How many of the 64 4x4x4 cells of each biome the section holds,
+    /// empty if it has no biomes. Cells decoded from the same palette entry
+    /// are counted together, so a section of a single biome is one lookup.
+    pub fn biome_counts(&self) -> HashMap<&NbtString, u32> {
+        let mut counts = HashMap::new();
+        for run in self.biomes.chunk_by(Shared::ptr_eq) {
+            *counts.entry(&*run[0]).or_insert(0) += run.len() as u32;
+        }
+        counts
+    }
+
+    /// The center of every 4x4x4 cell of the biome called `name`, in `y`,
+    /// `z`, `x` order.
+    pub fn find_biome(&self, name: &str) -> Vec<(u8, u8, u8)> {
+        (self.biomes.iter())
+            .enumerate()
+            .filter(|(_, biome)| ***biome == *name)
+            .map(|(i, _)| {
+                let (x, y, z) = (i & 3, i >> 4, (i >> 2) & 3);
+                (x as u8 * 4 + 2, y as u8 * 4 + 2, z as u8 * 4 + 2)
+            })
+            .collect()
+    }
+
+    /// Whether the section holds nothing but air.
+    pub fn is_empty(&self) -> bool {
+        self.decoded().occupancy.iter().all(|&bits| bits == 0)
+    }
+
+    pub fn count_non_air(&self) -> u16 {
+        self.decoded()
+            .occupancy
+            .iter()
+            .map(|bits| bits.count_ones() as u16)
+            .sum()
+    }
+
+    /// The inclusive minimum and maximum corners of the smallest box holding
+    /// every non-air block, or `None` if the section is empty.
+    pub fn non_air_bounds(&self) -> Option<SectionBounds> {
+        // each word holds four rows of x along z, for a single y
+        let (mut xs, mut ys, mut zs) = (0u16, 0u16, 0u16);
+        for (word, &bits) in self.decoded().occupancy.iter().enumerate() {
+            if bits == 0 {
+                continue;
+            }
+            ys |= 1 << (word >> 2);
+            for row in 0..4 {
+                let row_bits = (bits >> (row * 16)) as u16;
+                if row_bits != 0 {
+                    xs |= row_bits;
+                    zs |= 1 << ((word & 3) * 4 + row);
+                }
+            }
+        }
+        if ys == 0 {
+            return None;
+        }
+
+        let min = |mask: u16| mask.trailing_zeros() as u8;
+        let max = |mask: u16| 15 - mask.leading_zeros() as u8;
+        Some(((min(xs), min(ys), min(zs)), (max(xs), max(ys), max(zs))))
+    }
+
+    /// Iterates over all blocks in `y`, `z`, `x` order. Blocks are borrowed
+    /// from the palette through the section's indices, so no reference
+    /// counts are touched.
+    pub fn iter_blocks(&self) -> impl Iterator<Item = (u8, u8, u8, &Block)> {
+        self.blocks().enumerate().map(|(i, block)| {
+            let (x, y, z) = Section::get_block_coords(i);
+            (x, y, z, block.as_ref())
+        })
+    }
+
+    /// Like [`Section::iter_blocks`], but skips air without looking at it.
+    pub fn iter_blocks_non_air(&self) -> impl Iterator<Item = (u8, u8, u8, &Block)> {
+        self.decoded()
+            .occupancy
+            .iter()
+            .enumerate()
+            .flat_map(|(word, &bits)| SetBits(bits).map(move |bit| (word << 6) | bit))
+            .map(|i| {
+                let (x, y, z) = Section::get_block_coords(i);
+                (x, y, z, self.block(i).as_ref())
+            })
+    }
+
+    #[inline(always)]
+    fn get_block_coords(index: usize) -> (u8, u8, u8) {
+        (
+            (index & 0xF) as u8,
+            (index >> 8) as u8,
+            ((index >> 4) & 0xF) as u8,
+        )
+    }
+}
+
+struct SetBits(u64);
+
+impl Iterator for SetBits {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        if self.0 == 0 {
+            return None;
+        }
+        let bit = self.0.trailing_zeros() as usize;
+        self.0 &= self.0 - 1;
+        Some(bit)
+    }
+}
+
+/// The block at `(x, y, z)`, see [`Section::get_block`] for a lookup that
+/// doesn't panic.
+impl Index<(u8, u8, u8)> for Section {
+    type Output = Block;
+
+    fn index(&self, (x, y, z): (u8, u8, u8)) -> &Block {
+        self.get_block(x, y, z).unwrap_or_else(|| {
+            panic!("block ({x}, {y}, {z}) out of range for a section, coordinates go up to 15")
+        })
+    }
+}
+
+impl PartialEq for Section {
+    fn eq(&self, other: &Self) -> bool {
+        self.same_content(other)
+            && self.block_light == other.block_light
+            && self.sky_light == other.sky_light
+    }
+}
+
+// blocks, biomes and light compare by value, without floats
+impl Eq for Section {}
 
-        self.blocks[Section::get_block_pos(x, y, z)] = Rc::new(block);
+impl PartialOrd for Section {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        match self.blocks().partial_cmp(other.blocks())? {
+            Ordering::Equal => (&self.biomes, &self.block_light, &self.sky_light).partial_cmp(&(
+                &other.biomes,
+                &other.block_light,
+                &other.sky_light,
+            )),
+            ordering => Some(ordering),
+        }
     }
 }
@@ -0,0 +1,65 @@
+use std::fmt;
+
+/// How far the game got generating a chunk, stored in its `Status` field.
+/// The variants are ordered by generation step, so statuses can be
+/// compared to check whether a chunk got at least to a certain step.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ChunkStatus {
+    Empty,
+    StructureStarts,
+    StructureReferences,
+    Biomes,
+    Noise,
+    Surface,
+    Carvers,
+    Features,
+    InitializeLight,
+    Light,
+    Spawn,
+    Full,
+    /// A status this crate doesn't know, e.g. from older versions, as stored.
+    Other(String),
+}
+
+impl ChunkStatus {
+    const KNOWN: [(ChunkStatus, &'static str); 12] = [
+        (ChunkStatus::Empty, "empty"),
+        (ChunkStatus::StructureStarts, "structure_starts"),
+        (ChunkStatus::StructureReferences, "structure_references"),
+        (ChunkStatus::Biomes, "biomes"),
+        (ChunkStatus::Noise, "noise"),
+        (ChunkStatus::Surface, "surface"),
+        (ChunkStatus::Carvers, "carvers"),
+        (ChunkStatus::Features, "features"),
+        (ChunkStatus::InitializeLight, "initialize_light"),
+        (ChunkStatus::Light, "light"),
+        (ChunkStatus::Spawn, "spawn"),
+        (ChunkStatus::Full, "full"),
+    ];
+
+    /// Parses a status as stored, with or without the `minecraft:` namespace.
+    pub fn parse(status: &str) -> Self {
+        let name = status.strip_prefix("minecraft:").unwrap_or(status);
+        Self::KNOWN
+            .into_iter()
+            .find(|(_, known)| *known == name)
+            .map(|(status, _)| status)
+            .unwrap_or_else(|| ChunkStatus::Other(status.to_owned()))
+    }
+}
+
+/// Writes the status as the game does, with the namespace.
+impl fmt::Display for ChunkStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChunkStatus::Other(status) => f.write_str(status),
+            status => {
+                let (_, name) = Self::KNOWN
+                    .iter()
+                    .find(|(known, _)| known == status)
+                    .unwrap();
+                write!(f, "minecraft:{name}")
+            }
+        }
+    }
+}
@@ -0,0 +1,191 @@
+use nbt_rs::get_field as try_get_field;
+use nbt_rs::types::{NbtCompound, NbtList, NbtString, NbtTag};
+
+use crate::{chunk::ChunkParseError, coords::BlockPos};
+
+/// A box of blocks in world coordinates, given by its minimum and maximum
+/// corners, both of which are in the box.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct BoundingBox {
+    pub min: BlockPos,
+    pub max: BlockPos,
+}
+
+impl BoundingBox {
+    pub const fn new(min: BlockPos, max: BlockPos) -> Self {
+        Self { min, max }
+    }
+
+    /// Whether the boxes share a block.
+    pub fn intersects(&self, other: &BoundingBox) -> bool {
+        self.min.x <= other.max.x
+            && other.min.x <= self.max.x
+            && self.min.y <= other.max.y
+            && other.min.y <= self.max.y
+            && self.min.z <= other.max.z
+            && other.min.z <= self.max.z
+    }
+
+    pub fn contains_point(&self, pos: impl Into<BlockPos>) -> bool {
+        let pos = pos.into();
+        (self.min.x..=self.max.x).contains(&pos.x)
+            && (self.min.y..=self.max.y).contains(&pos.y)
+            && (self.min.z..=self.max.z).contains(&pos.z)
+    }
+
+    /// The block in the middle of the box, rounded down.
+    pub fn center(&self) -> BlockPos {
+        let middle = |min: i32, max: i32| ((min as i64 + max as i64).div_euclid(2)) as i32;
+        BlockPos::new(
+            middle(self.min.x, self.max.x),
+            middle(self.min.y, self.max.y),
+            middle(self.min.z, self.max.z),
+        )
+    }
+
+    /// The smallest box around both.
+    pub(crate) fn union(self, other: BoundingBox) -> BoundingBox {
+        let min = BlockPos::new(
+            self.min.x.min(other.min.x),
+            self.min.y.min(other.min.y),
+            self.min.z.min(other.min.z),
+        );
+        let max = BlockPos::new(
+            self.max.x.max(other.max.x),
+            self.max.y.max(other.max.y),
+            self.max.z.max(other.max.z),
+        );
+        BoundingBox { min, max }
+    }
+}
+
+/// The `BB` int array structures store, minimum `x`, `y`, `z` then maximum.
+impl From<[i32; 6]> for BoundingBox {
+    fn from([min_x, min_y, min_z, max_x, max_y, max_z]: [i32; 6]) -> Self {
+        BoundingBox::new(
+            BlockPos::new(min_x, min_y, min_z),
+            BlockPos::new(max_x, max_y, max_z),
+        )
+    }
+}
+
+impl From<BoundingBox> for [i32; 6] {
+    fn from(BoundingBox { min, max }: BoundingBox) -> Self {
+        [min.x, min.y, min.z, max.x, max.y, max.z]
+    }
+}
+
+/// A structure that starts in a chunk, like a village or a fortress, see
+/// [`crate::chunk::Chunk::structure_starts`].
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+pub struct StructureStart {
+    pub id: NbtString,
+    /// The box around all of its pieces, `None` if it has none.
+    pub bounding_box: Option<BoundingBox>,
+    /// The whole compound as stored, including the pieces.
+    pub data: NbtCompound,
+}
+
+/// The structure references sorted by structure name, each with the chunk
+/// positions of the starts.
+pub(crate) type StructureReferences = Vec<(NbtString, Vec<(i32, i32)>)>;
+
+/// Turns a chunk position packed into a long, as structure references store
+/// them, into its `x` and `z`, which take the low and high 32 bits.
+pub(crate) fn unpack_chunk_pos(packed: i64) -> (i32, i32) {
+    (packed as i32, (packed >> 32) as i32)
+}
+
+pub(crate) fn pack_chunk_pos(x: i32, z: i32) -> i64 {
+    (x as u32 as i64) | ((z as i64) << 32)
+}
+
+/// Reads the references and starts in the compound `field` of `root`,
+/// `path` leads to `root`. The game writes a start with the id `INVALID`
+/// for structures it decided against, those are left out.
+pub(crate) fn parse_structures(
+    root: &NbtCompound,
+    path: &str,
+    field: &str,
+    starts_field: &str,
+) -> Result<(StructureReferences, Vec<StructureStart>), ChunkParseError> {
+    let path = format!("{path}{field}.");
+    let Some(structures) = root.get(field) else {
+        return Ok(Default::default());
+    };
+    let invalid =
+        |field: &str, expected| ChunkParseError::InvalidField(format!("{path}{field}"), expected);
+    let structures = structures.as_compound().ok_or_else(|| {
+        ChunkParseError::InvalidField(path.trim_end_matches('.').to_owned(), "compound")
+    })?;
+
+    let mut references = Vec::new();
+    if let Some(stored) = structures.get("References") {
+        let stored: Vec<(NbtString, NbtTag)> = stored
+            .as_compound()
+            .ok_or_else(|| invalid("References", "compound"))?
+            .clone()
+            .into();
+        for (name, tag) in stored {
+            let NbtTag::LongArray(packed) = tag else {
+                return Err(invalid(&format!("References.{name}"), "long array"));
+            };
+            references.push((
+                name,
+                packed
+                    .iter()
+                    .map(|&packed| unpack_chunk_pos(packed))
+                    .collect(),
+            ));
+        }
+    }
+
+    let mut starts = Vec::new();
+    if let Some(stored) = structures.get(starts_field) {
+        let stored: Vec<(NbtString, NbtTag)> = stored
+            .as_compound()
+            .ok_or_else(|| invalid(starts_field, "compound"))?
+            .clone()
+            .into();
+        for (name, tag) in stored {
+            let NbtTag::Compound(data) = tag else {
+                return Err(invalid(&format!("{starts_field}.{name}"), "compound"));
+            };
+            let start = parse_start(data)
+                .ok_or_else(|| invalid(&format!("{starts_field}.{name}.id"), "string"))?;
+            if &*start.id != "INVALID" {
+                starts.push(start);
+            }
+        }
+    }
+    // compounds don't keep their order when written
+    references.sort_by(|(a, _), (b, _)| a.cmp(b));
+    starts.sort_by(|a, b| a.id.cmp(&b.id));
+    Ok((references, starts))
+}
+
+/// Returns `None` if the start has no id.
+pub(crate) fn parse_start(data: NbtCompound) -> Option<StructureStart> {
+    let id = try_get_field!(data, "id", as_string)?.clone();
+    // before 1.18 the start stored the box around its pieces itself
+    let bounding_box = match data.get("BB") {
+        Some(tag) => bounding_box(tag),
+        None => match try_get_field!(data, "Children", as_list) {
+            Some(NbtList::Compound(children)) => children
+                .iter()
+                .filter_map(|child| bounding_box(child.get("BB")?))
+                .reduce(BoundingBox::union),
+            _ => None,
+        },
+    };
+    Some(StructureStart {
+        id,
+        bounding_box,
+        data,
+    })
+}
+
+fn bounding_box(tag: &NbtTag) -> Option<BoundingBox> {
+    let values: [i32; 6] = tag.as_int_array()?[..].try_into().ok()?;
+    Some(values.into())
+}
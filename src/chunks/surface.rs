@@ -0,0 +1,82 @@
+//! The top block of each column, as map renderers draw them.
+
+use crate::{
+    chunks::{block::Block, chunk::Chunk, heightmap::HeightmapKind},
+    region::Region,
+};
+
+/// The top of a column, see [`Chunk::surface_map`].
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub struct SurfaceSample<'a> {
+    pub y: i32,
+    pub block: &'a Block,
+    /// The block right below, `None` at the bottom of the chunk. Renderers
+    /// use it to shade water by depth.
+    pub below: Option<&'a Block>,
+}
+
+impl Chunk {
+    /// The highest block of each column for which `predicate` returns
+    /// `true`, indexed by x then z, or `None` for columns without one. Pass
+    /// `|block| !block.is_air()` for the topmost block, or also leave out
+    /// blocks like snow layers or grass to look through them.
+    ///
+    /// The stored `WORLD_SURFACE` heightmap is trusted to be the top of each
+    /// column, so call [`Chunk::compute_heightmaps`] after editing blocks.
+    /// Without it, the columns are scanned from the top of the chunk.
+    /// Either way, sections without a matching block in their palette are
+    /// skipped without looking at their blocks.
+    pub fn surface_map(
+        &self,
+        predicate: impl Fn(&Block) -> bool,
+    ) -> [[Option<SurfaceSample<'_>>; 16]; 16] {
+        let top = self.get_heightmap(HeightmapKind::WorldSurface);
+        let sections: Vec<_> = (self.sections().rev())
+            .filter(|(_, section)| section.palette.iter().any(|block| predicate(block)))
+            .collect();
+
+        std::array::from_fn(|x| {
+            std::array::from_fn(|z| {
+                let (x, z) = (x as u8, z as u8);
+                let from_y = top
+                    .and_then(|top| top.get(x, z))
+                    .map_or(i32::MAX, |y| i32::from(y) - 1);
+                let (y, block) = sections
+                    .iter()
+                    .filter(|(section_y, _)| section_y * 16 <= from_y)
+                    .find_map(|&(section_y, section)| {
+                        let top = from_y.saturating_sub(section_y * 16).min(15) as u8;
+                        (0..=top).rev().find_map(|y| {
+                            let block = section.get_block(x, y, z)?;
+                            predicate(block).then_some((section_y * 16 + y as i32, block))
+                        })
+                    })?;
+                Some(SurfaceSample {
+                    y,
+                    block,
+                    below: self.get(x, y - 1, z),
+                })
+            })
+        })
+    }
+}
+
+impl Region {
+    /// [`Chunk::surface_map`] for every chunk of the region, indexed by
+    /// `x + z * 512`, with `x` and `z` the block's offset in the region.
+    /// Columns of missing chunks are `None`.
+    pub fn surface_map(
+        &self,
+        predicate: impl Fn(&Block) -> bool,
+    ) -> Vec<Option<SurfaceSample<'_>>> {
+        let mut map = vec![None; 512 * 512];
+        for (chunk_x, chunk_z, chunk) in self.iter_chunks() {
+            for (x, column) in chunk.surface_map(&predicate).into_iter().enumerate() {
+                for (z, sample) in column.into_iter().enumerate() {
+                    map[chunk_x * 16 + x + (chunk_z * 16 + z) * 512] = sample;
+                }
+            }
+        }
+        map
+    }
+}
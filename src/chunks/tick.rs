@@ -0,0 +1,17 @@
+use nbt_rs::types::NbtString;
+
+/// A block or fluid update the game scheduled for a later tick, like a
+/// repeater switching or water spreading, in world coordinates.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ScheduledTick {
+    /// The block or fluid the tick is for, the update is dropped if the
+    /// position holds something else by then.
+    pub target: NbtString,
+    pub x: i32,
+    pub y: i32,
+    pub z: i32,
+    /// How many ticks are left until the update, negative if it's overdue.
+    pub delay: i32,
+    /// Updates due in the same tick run from the lowest priority on.
+    pub priority: i32,
+}
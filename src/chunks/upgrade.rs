@@ -0,0 +1,111 @@
+//! What chunks upgraded from before 1.18 store about the parts of the world
+//! the game still has to fill in, see [`Chunk::below_zero_retrogen`] and
+//! [`Chunk::blending_data`].
+
+use nbt_rs::get_field as try_get_field;
+use nbt_rs::types::NbtCompound;
+
+use crate::chunks::{
+    chunk::{Chunk, ChunkParseError},
+    status::ChunkStatus,
+};
+
+/// The generation still owed to a chunk written before 1.18, whose world
+/// ended at y 0. Until it reaches [`BelowZeroRetrogen::target_status`], the
+/// sections below y 0 are generated again while the ones above stay.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Hash)]
+pub struct BelowZeroRetrogen {
+    pub target_status: ChunkStatus,
+    // a bit per column, `x + z * 16` counting from the lowest bit of the
+    // first word, like the game's `BitSet`
+    pub(crate) missing_bedrock: [u64; 4],
+}
+
+impl BelowZeroRetrogen {
+    pub fn new(target_status: ChunkStatus) -> Self {
+        Self {
+            target_status,
+            missing_bedrock: [0; 4],
+        }
+    }
+
+    /// Whether the column had no bedrock at y 0 when the chunk was upgraded,
+    /// so the game leaves a hole in the new bedrock floor below it. `false`
+    /// if `x` or `z` is 16 or above.
+    pub fn is_missing_bedrock(&self, x: u8, z: u8) -> bool {
+        if x >= 16 || z >= 16 {
+            return false;
+        }
+        let bit = x as usize + z as usize * 16;
+        self.missing_bedrock[bit / 64] >> (bit % 64) & 1 == 1
+    }
+
+    /// Does nothing if `x` or `z` is 16 or above.
+    pub fn set_missing_bedrock(&mut self, x: u8, z: u8, missing: bool) {
+        if x >= 16 || z >= 16 {
+            return;
+        }
+        let bit = x as usize + z as usize * 16;
+        let word = &mut self.missing_bedrock[bit / 64];
+        *word = (*word & !(1 << (bit % 64))) | ((missing as u64) << (bit % 64));
+    }
+
+    /// The columns missing bedrock, `x` before `z`.
+    pub fn missing_bedrock(&self) -> impl Iterator<Item = (u8, u8)> + '_ {
+        (0..256u16)
+            .map(|bit| (bit as u8 & 0xF, (bit >> 4) as u8))
+            .filter(|&(x, z)| self.is_missing_bedrock(x, z))
+    }
+
+    /// Reads the compound at `path`.
+    pub(crate) fn parse(compound: &NbtCompound, path: &str) -> Result<Self, ChunkParseError> {
+        let invalid = |field: &str, expected| {
+            ChunkParseError::InvalidField(format!("{path}.{field}"), expected)
+        };
+        let target_status = try_get_field!(compound, "target_status", as_string)
+            .ok_or_else(|| invalid("target_status", "string"))?;
+        let mut missing_bedrock = [0; 4];
+        if let Some(tag) = compound.get("missing_bedrock") {
+            // the game leaves out trailing words without any bits set
+            let words = (tag.as_long_array())
+                .filter(|words| words.len() <= 4)
+                .ok_or_else(|| invalid("missing_bedrock", "long array of up to 4 longs"))?;
+            for (word, &stored) in missing_bedrock.iter_mut().zip(words.iter()) {
+                *word = stored as u64;
+            }
+        }
+        Ok(Self {
+            target_status: ChunkStatus::parse(target_status),
+            missing_bedrock,
+        })
+    }
+}
+
+impl Chunk {
+    /// The generation still owed to the chunk below y 0, if it was written
+    /// before 1.18 and the game hasn't finished it yet.
+    pub fn below_zero_retrogen(&self) -> Option<&BelowZeroRetrogen> {
+        self.below_zero_retrogen.as_ref()
+    }
+
+    pub fn set_below_zero_retrogen(&mut self, retrogen: Option<BelowZeroRetrogen>) {
+        self.below_zero_retrogen = retrogen;
+    }
+
+    /// The section ys the chunk spanned before it was upgraded to 1.18, the
+    /// lowest included and the highest not, which new terrain next to it is
+    /// blended with. `None` if the chunk has no blending data, or if it was
+    /// written by 1.18 itself, which didn't store the sections.
+    pub fn blending_data(&self) -> Option<(i32, i32)> {
+        let data = self.blending_data.as_ref()?;
+        let &min = try_get_field!(data, "min_section", as_int)?;
+        let &max = try_get_field!(data, "max_section", as_int)?;
+        Some((min, max))
+    }
+
+    /// The `blending_data` compound as stored, with the heights of the old
+    /// terrain next to the sections.
+    pub fn blending_data_nbt(&self) -> Option<&NbtCompound> {
+        self.blending_data.as_ref()
+    }
+}
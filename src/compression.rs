@@ -0,0 +1,99 @@
+/// The compression of a chunk payload, as stored in the chunk header.
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum CompressionType {
+    Gzip = 1,
+    Zlib = 2,
+    None = 3,
+    Lz4 = 4,
+    /// A named, third-party compression method (24w04a+).
+    Custom = 127,
+}
+
+impl CompressionType {
+    /// Set in the header byte when the chunk is stored in a separate
+    /// `c.<x>.<z>.mcc` file because it didn't fit into the region.
+    pub const EXTERNAL_FLAG: u8 = 0x80;
+
+    /// Splits a header byte into the compression type and whether the
+    /// chunk is stored externally.
+    pub fn from_header_byte(byte: u8) -> Result<(Self, bool), u8> {
+        let compression = Self::try_from(byte & !Self::EXTERNAL_FLAG).map_err(|_| byte)?;
+        Ok((compression, byte & Self::EXTERNAL_FLAG != 0))
+    }
+
+    pub fn to_header_byte(self, external: bool) -> u8 {
+        u8::from(self) | if external { Self::EXTERNAL_FLAG } else { 0 }
+    }
+}
+
+impl TryFrom<u8> for CompressionType {
+    type Error = u8;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            1 => Ok(CompressionType::Gzip),
+            2 => Ok(CompressionType::Zlib),
+            3 => Ok(CompressionType::None),
+            4 => Ok(CompressionType::Lz4),
+            127 => Ok(CompressionType::Custom),
+            _ => Err(value),
+        }
+    }
+}
+
+impl From<CompressionType> for u8 {
+    fn from(value: CompressionType) -> Self {
+        value as u8
+    }
+}
+
+/// A compression chunks can be written with, see [`WriteOptions`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Compression {
+    Gzip,
+    /// What the game writes, unless configured otherwise since 24w04a.
+    #[default]
+    Zlib,
+    None,
+    /// In lz4-java's block format, like the game writes it.
+    #[cfg(feature = "lz4")]
+    Lz4,
+}
+
+impl From<Compression> for CompressionType {
+    fn from(value: Compression) -> Self {
+        match value {
+            Compression::Gzip => CompressionType::Gzip,
+            Compression::Zlib => CompressionType::Zlib,
+            Compression::None => CompressionType::None,
+            #[cfg(feature = "lz4")]
+            Compression::Lz4 => CompressionType::Lz4,
+        }
+    }
+}
+
+/// How chunks are compressed when written, see
+/// [`Chunk::to_bytes_with`](crate::chunk::Chunk::to_bytes_with).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct WriteOptions {
+    pub compression: Compression,
+    /// From 0, storing the data as is, to 9, the smallest but slowest. Only
+    /// used by gzip and zlib, levels above 9 are taken as 9.
+    pub level: u32,
+    /// Recompute the heightmaps of fully generated chunks that
+    /// [`Region::to_bytes_with`](crate::Region::to_bytes_with) encodes
+    /// again, see [`Chunk::compute_heightmaps`](crate::Chunk::compute_heightmaps).
+    /// Chunks written on their own keep the heightmaps they hold.
+    pub compute_heightmaps: bool,
+}
+
+impl Default for WriteOptions {
+    fn default() -> Self {
+        Self {
+            compression: Compression::Zlib,
+            level: 6,
+            compute_heightmaps: true,
+        }
+    }
+}
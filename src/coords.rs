@@ -0,0 +1,179 @@
+/// A block in world coordinates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+pub struct BlockPos {
+    pub x: i32,
+    pub y: i32,
+    pub z: i32,
+}
+
+/// A chunk column in chunk coordinates, i.e. block coordinates divided by 16.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+pub struct ChunkPos {
+    pub x: i32,
+    pub z: i32,
+}
+
+/// A region file in region coordinates, i.e. chunk coordinates divided by
+/// 32, as in `r.<x>.<z>.mca`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+pub struct RegionPos {
+    pub x: i32,
+    pub z: i32,
+}
+
+/// A 16x16x16 section in section coordinates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+pub struct SectionPos {
+    pub x: i32,
+    pub y: i32,
+    pub z: i32,
+}
+
+impl BlockPos {
+    pub const fn new(x: i32, y: i32, z: i32) -> Self {
+        Self { x, y, z }
+    }
+
+    pub const fn chunk(self) -> ChunkPos {
+        ChunkPos::new(self.x >> 4, self.z >> 4)
+    }
+
+    pub const fn section(self) -> SectionPos {
+        SectionPos::new(self.x >> 4, self.y >> 4, self.z >> 4)
+    }
+
+    pub const fn region(self) -> RegionPos {
+        RegionPos::new(self.x >> 9, self.z >> 9)
+    }
+
+    /// The position inside of the containing chunk, `y` is left as is.
+    pub const fn chunk_local(self) -> (u8, i32, u8) {
+        ((self.x & 0xF) as u8, self.y, (self.z & 0xF) as u8)
+    }
+
+    /// The position inside of the containing section.
+    pub const fn section_local(self) -> (u8, u8, u8) {
+        (
+            (self.x & 0xF) as u8,
+            (self.y & 0xF) as u8,
+            (self.z & 0xF) as u8,
+        )
+    }
+}
+
+impl ChunkPos {
+    pub const fn new(x: i32, z: i32) -> Self {
+        Self { x, z }
+    }
+
+    /// The containing region, as in `r.<x>.<z>.mca`.
+    pub const fn region(self) -> RegionPos {
+        RegionPos::new(self.x >> 5, self.z >> 5)
+    }
+
+    /// The position inside of the containing region.
+    pub const fn region_local(self) -> (usize, usize) {
+        ((self.x & 0x1F) as usize, (self.z & 0x1F) as usize)
+    }
+
+    /// The block with the lowest x and z in this chunk, at `y`.
+    pub const fn min_block(self, y: i32) -> BlockPos {
+        BlockPos::new(self.x << 4, y, self.z << 4)
+    }
+}
+
+impl RegionPos {
+    pub const fn new(x: i32, z: i32) -> Self {
+        Self { x, z }
+    }
+
+    /// The chunk in slot `x`, `z` of this region, the inverse of
+    /// [`ChunkPos::region_local`]. Only the low 5 bits of the slot are used,
+    /// so it stays in the region, and regions beyond ±2^26, which no chunk
+    /// coordinates reach, wrap around.
+    pub const fn chunk(self, x: u8, z: u8) -> ChunkPos {
+        ChunkPos::new(
+            self.x.wrapping_shl(5) | (x & 0x1F) as i32,
+            self.z.wrapping_shl(5) | (z & 0x1F) as i32,
+        )
+    }
+
+    /// The chunk with the lowest x and z in this region.
+    pub const fn min_chunk(self) -> ChunkPos {
+        self.chunk(0, 0)
+    }
+}
+
+impl SectionPos {
+    pub const fn new(x: i32, y: i32, z: i32) -> Self {
+        Self { x, y, z }
+    }
+
+    pub const fn chunk(self) -> ChunkPos {
+        ChunkPos::new(self.x, self.z)
+    }
+
+    pub const fn min_block(self) -> BlockPos {
+        BlockPos::new(self.x << 4, self.y << 4, self.z << 4)
+    }
+}
+
+impl From<(i32, i32, i32)> for BlockPos {
+    fn from((x, y, z): (i32, i32, i32)) -> Self {
+        Self::new(x, y, z)
+    }
+}
+
+impl From<(i32, i32)> for ChunkPos {
+    fn from((x, z): (i32, i32)) -> Self {
+        Self::new(x, z)
+    }
+}
+
+impl From<(i32, i32)> for RegionPos {
+    fn from((x, z): (i32, i32)) -> Self {
+        Self::new(x, z)
+    }
+}
+
+impl From<RegionPos> for (i32, i32) {
+    fn from(pos: RegionPos) -> Self {
+        (pos.x, pos.z)
+    }
+}
+
+impl From<(i32, i32, i32)> for SectionPos {
+    fn from((x, y, z): (i32, i32, i32)) -> Self {
+        Self::new(x, y, z)
+    }
+}
+
+impl From<BlockPos> for ChunkPos {
+    fn from(pos: BlockPos) -> Self {
+        pos.chunk()
+    }
+}
+
+impl From<BlockPos> for SectionPos {
+    fn from(pos: BlockPos) -> Self {
+        pos.section()
+    }
+}
+
+impl From<SectionPos> for ChunkPos {
+    fn from(pos: SectionPos) -> Self {
+        pos.chunk()
+    }
+}
+
+impl From<ChunkPos> for RegionPos {
+    fn from(pos: ChunkPos) -> Self {
+        pos.region()
+    }
+}
+
+impl From<BlockPos> for RegionPos {
+    fn from(pos: BlockPos) -> Self {
+        pos.region()
+    }
+}
@@ -0,0 +1,363 @@
+//! Entity region files, which hold the entities of each chunk in
+//! `entities/r.<x>.<z>.mca` since 1.17.
+
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    path::Path,
+};
+
+use nbt_rs::get_field as try_get_field;
+use nbt_rs::types::{NbtCompound, NbtList, NbtString, NbtTag};
+
+use crate::{
+    chunk::{ChunkParseError, ChunkParseOptions, parse_nbt_with},
+    coords::ChunkPos,
+    raw::RawChunk,
+    region::{RegionOpenError, RegionParseError, RegionParseOptions, boxed_slots, chunk_range},
+    region_file::RegionFile,
+    trace,
+};
+
+macro_rules! get_field {
+    ($input:ident @ $path:expr, $field:literal, $expected:literal, $($ty:ident).*) => {{
+        try_get_field!($input, $field, $($ty).*).ok_or_else(|| {
+            ChunkParseError::InvalidField(format!("{}{}", $path, $field), $expected)
+        })?
+    }};
+}
+
+/// An entity, like a mob, an item or a minecart.
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+pub struct Entity {
+    pub id: NbtString,
+    /// In world coordinates.
+    pub pos: (f64, f64, f64),
+    /// In blocks per tick, zero if it isn't stored.
+    pub motion: (f64, f64, f64),
+    /// The yaw and pitch in degrees, zero if it isn't stored.
+    pub rotation: (f32, f32),
+    /// The whole compound as stored, including the fields above.
+    pub data: NbtCompound,
+}
+
+impl Entity {
+    /// Whether the entity has the id, an id without a namespace is in
+    /// `minecraft:`, so `zombie` and `minecraft:zombie` are the same.
+    pub fn is(&self, id: &str) -> bool {
+        same_id(&self.id, id)
+    }
+
+    /// The item the entity shows or holds: the `Item` of dropped items and
+    /// item frames, or the main hand of mobs and armor stands. `None` if
+    /// there is none, or it's air.
+    pub fn item(&self) -> Option<Item> {
+        let data = &self.data;
+        let stored = match data.get("Item") {
+            Some(tag) => tag.as_compound()?,
+            // 1.21.5 and later
+            None => match try_get_field!(data, "equipment", as_compound) {
+                Some(equipment) => try_get_field!(equipment, "mainhand", as_compound)?,
+                None => try_get_field!(data, "HandItems", as_list.as_compound)?.first()?,
+            },
+        };
+        Item::parse(stored)
+    }
+}
+
+/// An item stack, as held by an [`Entity`].
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+pub struct Item {
+    pub id: NbtString,
+    /// One if it isn't stored.
+    pub count: i32,
+    /// The whole compound as stored, including the fields above.
+    pub data: NbtCompound,
+}
+
+impl Item {
+    fn parse(data: &NbtCompound) -> Option<Item> {
+        let id = try_get_field!(data, "id", as_string)?;
+        if same_id(id, "air") {
+            return None;
+        }
+        // `Count` was a byte before 1.20.5
+        let count = match (data.get("count"), data.get("Count")) {
+            (Some(NbtTag::Int(count)), _) => *count,
+            (_, Some(NbtTag::Byte(count))) => *count as i32,
+            _ => 1,
+        };
+        Some(Item {
+            id: id.clone(),
+            count,
+            data: data.clone(),
+        })
+    }
+}
+
+/// The entities of one chunk in an entity region.
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+pub struct EntityChunk {
+    pub(crate) x_pos: i32,
+    pub(crate) z_pos: i32,
+    pub(crate) data_version: i32,
+    pub(crate) entities: Vec<Entity>,
+}
+
+impl EntityChunk {
+    pub fn get_pos(&self) -> ChunkPos {
+        ChunkPos::new(self.x_pos, self.z_pos)
+    }
+
+    pub fn data_version(&self) -> i32 {
+        self.data_version
+    }
+
+    /// In the order they're stored. Passengers aren't listed separately,
+    /// they're in the `Passengers` field of the entity they ride.
+    pub fn entities(&self) -> &[Entity] {
+        &self.entities
+    }
+
+    /// The entities with the id, see [`Entity::is`].
+    pub fn entities_by_id<'a>(&'a self, id: &'a str) -> impl Iterator<Item = &'a Entity> {
+        self.entities.iter().filter(move |entity| entity.is(id))
+    }
+}
+
+/// Like [`crate::Region`], but for entity region files.
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+pub struct EntityRegion {
+    chunks: Box<[Option<EntityChunk>; 1024]>,
+}
+
+impl EntityRegion {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, RegionOpenError> {
+        Self::open_with(path, &RegionParseOptions::default())
+    }
+
+    pub fn open_with(
+        path: impl AsRef<Path>,
+        options: &RegionParseOptions,
+    ) -> Result<Self, RegionOpenError> {
+        let bytes = fs::read(path)?;
+        Ok(Self::parse_bytes_with(&bytes, options)?)
+    }
+
+    pub fn parse_bytes(bytes: &[u8]) -> Result<Self, RegionParseError> {
+        Self::parse_bytes_with(bytes, &RegionParseOptions::default())
+    }
+
+    /// Chunks that fail to decode are left out, or fail the whole region if
+    /// [`RegionParseOptions::strict`] is set. The status filter and block
+    /// interning don't apply to entities.
+    pub fn parse_bytes_with(
+        bytes: &[u8],
+        options: &RegionParseOptions,
+    ) -> Result<Self, RegionParseError> {
+        let mut chunks = boxed_slots();
+        let mut buffer = Vec::new();
+        for (index, entry) in options.header_entries(bytes)?.into_iter().enumerate() {
+            let Some((range, _)) = entry else {
+                continue;
+            };
+            let (x, z) = (index % 32, index / 32);
+            let range = chunk_range(bytes, range);
+            let end = range.end.min(bytes.len());
+            let chunk = (options.parse_raw(&bytes[range.start.min(end)..end], range.len()))
+                .and_then(|raw| decode_entity_chunk(&raw, &options.chunk, &mut buffer))
+                .and_then(|chunk| {
+                    let pos = chunk.get_pos();
+                    if options.check_slots && pos.region_local() != (x, z) {
+                        return Err(ChunkParseError::SlotMismatch { slot: (x, z), pos });
+                    }
+                    Ok(chunk)
+                });
+            match chunk {
+                Ok(chunk) => chunks[index] = Some(chunk),
+                Err(source) => {
+                    trace::debug!(x, z, error = %source, "skipping entity chunk");
+                    if options.strict {
+                        return Err(RegionParseError::ChunkError { x, z, source });
+                    }
+                }
+            }
+        }
+        Ok(Self { chunks })
+    }
+
+    /// Returns `None` if the slot is empty, its chunk failed to decode, or
+    /// `x` or `z` is 32 or above.
+    pub fn get_chunk(&self, x: usize, z: usize) -> Option<&EntityChunk> {
+        if x >= 32 || z >= 32 {
+            return None;
+        }
+        self.chunks[x + z * 32].as_ref()
+    }
+
+    /// The entities of the chunk in the slot, empty if there is no chunk, see
+    /// [`EntityRegion::get_chunk`].
+    pub fn get_entities(&self, x: usize, z: usize) -> &[Entity] {
+        self.get_chunk(x, z).map_or(&[], EntityChunk::entities)
+    }
+
+    /// Like [`EntityRegion::get_entities`], but takes the chunk's world
+    /// position. Only its offset in the region is used.
+    pub fn get_entities_at(&self, pos: impl Into<ChunkPos>) -> &[Entity] {
+        let (x, z) = pos.into().region_local();
+        self.get_entities(x, z)
+    }
+
+    pub fn count_chunks(&self) -> u16 {
+        self.iter_chunks().count() as u16
+    }
+
+    /// The chunks in the region with their slot, `x` before `z`.
+    pub fn iter_chunks(&self) -> impl Iterator<Item = (usize, usize, &EntityChunk)> {
+        self.chunks
+            .iter()
+            .enumerate()
+            .filter_map(|(index, chunk)| Some((index % 32, index / 32, chunk.as_ref()?)))
+    }
+
+    /// Every entity in the region with the slot of its chunk.
+    pub fn iter_entities(&self) -> impl Iterator<Item = (usize, usize, &Entity)> {
+        self.iter_chunks()
+            .flat_map(|(x, z, chunk)| chunk.entities.iter().map(move |entity| (x, z, entity)))
+    }
+
+    /// Like [`EntityChunk::entities_by_id`], but for all chunks.
+    pub fn entities_by_id<'a>(
+        &'a self,
+        id: &'a str,
+    ) -> impl Iterator<Item = (usize, usize, &'a Entity)> {
+        self.iter_entities()
+            .filter(move |(_, _, entity)| entity.is(id))
+    }
+
+    /// How many of each entity all chunks hold together, by the id as
+    /// stored.
+    pub fn count_entities_by_id(&self) -> HashMap<&NbtString, u32> {
+        let mut counts = HashMap::new();
+        for (_, _, entity) in self.iter_entities() {
+            *counts.entry(&entity.id).or_insert(0) += 1;
+        }
+        counts
+    }
+
+    /// The entities whose position is between `min` and `max`, both
+    /// included, in world coordinates.
+    pub fn entities_in_box(
+        &self,
+        min: (f64, f64, f64),
+        max: (f64, f64, f64),
+    ) -> impl Iterator<Item = (usize, usize, &Entity)> {
+        self.iter_entities().filter(move |(_, _, entity)| {
+            let (x, y, z) = entity.pos;
+            (min.0..=max.0).contains(&x)
+                && (min.1..=max.1).contains(&y)
+                && (min.2..=max.2).contains(&z)
+        })
+    }
+}
+
+pub(crate) fn same_id(stored: &str, id: &str) -> bool {
+    fn split(id: &str) -> (&str, &str) {
+        id.split_once(':').unwrap_or(("minecraft", id))
+    }
+    split(stored) == split(id)
+}
+
+/// Reads only the chunk in the slot of the entity region file at `path`,
+/// `None` if the slot is empty.
+pub(crate) fn read_entity_chunk(
+    path: &Path,
+    x: usize,
+    z: usize,
+    options: &RegionParseOptions,
+) -> Result<Option<EntityChunk>, RegionOpenError> {
+    let mut file = RegionFile::with_options(File::open(path)?, options.clone())?;
+    let Some(raw) = file.read_raw_chunk(x, z)? else {
+        return Ok(None);
+    };
+    let chunk = decode_entity_chunk(&raw, &options.chunk, &mut Vec::new())
+        .map_err(|source| RegionParseError::ChunkError { x, z, source })?;
+    Ok(Some(chunk))
+}
+
+fn decode_entity_chunk(
+    raw: &RawChunk,
+    options: &ChunkParseOptions,
+    buffer: &mut Vec<u8>,
+) -> Result<EntityChunk, ChunkParseError> {
+    raw.decompress_into(options, buffer)?;
+    let decoded = parse_nbt_with(buffer, options)?;
+    parse_entity_chunk(&decoded)
+}
+
+fn parse_entity_chunk(decoded: &NbtCompound) -> Result<EntityChunk, ChunkParseError> {
+    let data_version = try_get_field!(decoded, "DataVersion", as_int).map_or(0, |&v| v);
+    let position = get_field!(decoded @ "", "Position", "int array", as_int_array);
+    let &[x_pos, z_pos] = &position[..] else {
+        return Err(ChunkParseError::InvalidField(
+            "Position".to_owned(),
+            "int array of 2",
+        ));
+    };
+
+    Ok(EntityChunk {
+        x_pos,
+        z_pos,
+        data_version,
+        entities: parse_entities(decoded, "")?,
+    })
+}
+
+/// Reads the entities in the `Entities` list of `root`, `path` leads to
+/// `root`.
+pub(crate) fn parse_entities(
+    root: &NbtCompound,
+    path: &str,
+) -> Result<Vec<Entity>, ChunkParseError> {
+    let stored: &[NbtCompound] = match root.get("Entities") {
+        None | Some(NbtTag::List(NbtList::End)) => &[],
+        Some(tag) => tag
+            .as_list()
+            .and_then(|list| list.as_compound())
+            .ok_or_else(|| {
+                ChunkParseError::InvalidField(format!("{path}Entities"), "list of compounds")
+            })?,
+    };
+    stored
+        .iter()
+        .enumerate()
+        .map(|(i, data)| parse_entity(data, &format!("{path}Entities[{i}].")))
+        .collect()
+}
+
+/// `path` leads to `data` and ends with a `.`.
+pub(crate) fn parse_entity(data: &NbtCompound, path: &str) -> Result<Entity, ChunkParseError> {
+    let id = get_field!(data @ path, "id", "string", as_string).clone();
+    let pos = get_field!(data @ path, "Pos", "list of 3 doubles", as_list.as_double);
+    let &[x, y, z] = &pos[..] else {
+        return Err(ChunkParseError::InvalidField(
+            format!("{path}Pos"),
+            "list of 3 doubles",
+        ));
+    };
+    let motion = match try_get_field!(data, "Motion", as_list.as_double).map(|m| &m[..]) {
+        Some(&[x, y, z]) => (x, y, z),
+        _ => (0.0, 0.0, 0.0),
+    };
+    let rotation = match try_get_field!(data, "Rotation", as_list.as_float).map(|r| &r[..]) {
+        Some(&[yaw, pitch]) => (yaw, pitch),
+        _ => (0.0, 0.0),
+    };
+    Ok(Entity {
+        id,
+        pos: (x, y, z),
+        motion,
+        rotation,
+        data: data.clone(),
+    })
+}
@@ -0,0 +1,269 @@
+#![allow(clippy::missing_safety_doc)]
+
+use std::{
+    ffi::{CStr, c_char},
+    panic::{AssertUnwindSafe, catch_unwind},
+    slice,
+};
+
+use crate::{
+    chunk::{Chunk, ChunkParseError, parse_chunk},
+    region::{Region, RegionParseError},
+};
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+// the values are part of the C ABI, new errors go at the end
+pub enum McaError {
+    Ok = 0,
+    NullPointer = 1,
+    OutOfBounds = 2,
+    ChunkNotFound = 3,
+    InputTooShort = 4,
+    InputInvalidSize = 5,
+    UnsupportedCompression = 6,
+    DecompressionFailed = 7,
+    ParseFailed = 8,
+    InvalidField = 9,
+    InvalidPalette = 10,
+    InvalidSectionData = 11,
+    Panic = 12,
+    LimitExceeded = 13,
+    InvalidHeightmap = 14,
+    SectorOverlap = 15,
+}
+
+impl McaError {
+    // in the order of their values, which count up from 0
+    const ALL: [McaError; 16] = [
+        McaError::Ok,
+        McaError::NullPointer,
+        McaError::OutOfBounds,
+        McaError::ChunkNotFound,
+        McaError::InputTooShort,
+        McaError::InputInvalidSize,
+        McaError::UnsupportedCompression,
+        McaError::DecompressionFailed,
+        McaError::ParseFailed,
+        McaError::InvalidField,
+        McaError::InvalidPalette,
+        McaError::InvalidSectionData,
+        McaError::Panic,
+        McaError::LimitExceeded,
+        McaError::InvalidHeightmap,
+        McaError::SectorOverlap,
+    ];
+
+    fn message(self) -> &'static CStr {
+        match self {
+            McaError::Ok => c"ok",
+            McaError::NullPointer => c"null pointer argument",
+            McaError::OutOfBounds => c"coordinates out of bounds",
+            McaError::ChunkNotFound => c"chunk not present in region",
+            McaError::InputTooShort => c"input too short",
+            McaError::InputInvalidSize => c"input size is not a multiple of 4096",
+            McaError::UnsupportedCompression => c"unsupported chunk compression",
+            McaError::DecompressionFailed => c"failed to decompress chunk data",
+            McaError::ParseFailed => c"failed to parse chunk nbt",
+            McaError::InvalidField => c"missing or invalid chunk field",
+            McaError::InvalidPalette => c"invalid block palette",
+            McaError::InvalidSectionData => c"invalid section data",
+            McaError::Panic => c"internal panic",
+            McaError::LimitExceeded => c"chunk exceeds a resource limit",
+            McaError::InvalidHeightmap => c"invalid heightmap",
+            McaError::SectorOverlap => c"chunks in the region share sectors",
+        }
+    }
+}
+
+impl From<&RegionParseError> for McaError {
+    fn from(err: &RegionParseError) -> Self {
+        match err {
+            RegionParseError::InputTooShort(_) | RegionParseError::LocationsTooShort(_) => {
+                McaError::InputTooShort
+            }
+            RegionParseError::InputInvalidSize(_) => McaError::InputInvalidSize,
+            RegionParseError::GzipFailed(_) => McaError::DecompressionFailed,
+            RegionParseError::GzipTooLarge => McaError::LimitExceeded,
+            RegionParseError::SectorOverlap { .. } => McaError::SectorOverlap,
+            RegionParseError::ChunkError { source, .. } => source.into(),
+        }
+    }
+}
+
+impl From<&ChunkParseError> for McaError {
+    fn from(err: &ChunkParseError) -> Self {
+        match err {
+            ChunkParseError::InputTooShort(..) | ChunkParseError::LengthExceedsSectors { .. } => {
+                McaError::InputTooShort
+            }
+            ChunkParseError::UnsupportedCompression | ChunkParseError::UnknownCodec(_) => {
+                McaError::UnsupportedCompression
+            }
+            ChunkParseError::DecompressionFailed(_) => McaError::DecompressionFailed,
+            ChunkParseError::ParseFailed(_) => McaError::ParseFailed,
+            ChunkParseError::InvalidField(..)
+            | ChunkParseError::InvalidYRange(..)
+            | ChunkParseError::SlotMismatch { .. } => McaError::InvalidField,
+            ChunkParseError::InvalidPalette(_) => McaError::InvalidPalette,
+            ChunkParseError::InvalidSectionData { .. } => McaError::InvalidSectionData,
+            ChunkParseError::LimitExceeded(_) | ChunkParseError::DecompressedTooLarge { .. } => {
+                McaError::LimitExceeded
+            }
+            ChunkParseError::InvalidHeightmap(_) => McaError::InvalidHeightmap,
+        }
+    }
+}
+
+/// Borrowed view of a block. `name` is not NUL-terminated and stays valid
+/// for as long as the chunk the block was read from.
+#[repr(C)]
+pub struct McaBlockInfo {
+    pub name: *const u8,
+    pub name_len: usize,
+    pub property_count: usize,
+}
+
+fn guard(f: impl FnOnce() -> McaError) -> McaError {
+    catch_unwind(AssertUnwindSafe(f)).unwrap_or(McaError::Panic)
+}
+
+/// Takes the error as an integer, since C may pass values that aren't an
+/// `mca_error`. Those get a message saying so.
+#[unsafe(no_mangle)]
+pub extern "C" fn mca_error_message(err: u32) -> *const c_char {
+    let error = usize::try_from(err)
+        .ok()
+        .and_then(|err| McaError::ALL.get(err));
+    error
+        .map_or(c"unknown error", |error| error.message())
+        .as_ptr()
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn mca_region_parse(
+    data: *const u8,
+    len: usize,
+    out: *mut *mut Region,
+) -> McaError {
+    if data.is_null() || out.is_null() {
+        return McaError::NullPointer;
+    }
+
+    guard(|| {
+        let bytes = unsafe { slice::from_raw_parts(data, len) };
+        match Region::parse_bytes(bytes) {
+            Ok(region) => {
+                unsafe { *out = Box::into_raw(Box::new(region)) };
+                McaError::Ok
+            }
+            Err(err) => McaError::from(&err),
+        }
+    })
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn mca_region_free(region: *mut Region) {
+    if !region.is_null() {
+        drop(unsafe { Box::from_raw(region) });
+    }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn mca_region_count_chunks(region: *const Region) -> u16 {
+    match unsafe { region.as_ref() } {
+        Some(region) => region.count_chunks(),
+        None => 0,
+    }
+}
+
+/// The returned chunk is borrowed from the region and must not be freed.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn mca_region_get_chunk(
+    region: *const Region,
+    x: usize,
+    z: usize,
+    out: *mut *const Chunk,
+) -> McaError {
+    let Some(region) = (unsafe { region.as_ref() }) else {
+        return McaError::NullPointer;
+    };
+    if out.is_null() {
+        return McaError::NullPointer;
+    }
+    if x >= 32 || z >= 32 {
+        return McaError::OutOfBounds;
+    }
+
+    match region.get_chunk(x, z) {
+        Some(chunk) => {
+            unsafe { *out = chunk };
+            McaError::Ok
+        }
+        None => McaError::ChunkNotFound,
+    }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn mca_chunk_parse(
+    data: *const u8,
+    len: usize,
+    out: *mut *mut Chunk,
+) -> McaError {
+    if data.is_null() || out.is_null() {
+        return McaError::NullPointer;
+    }
+
+    guard(|| {
+        let bytes = unsafe { slice::from_raw_parts(data, len) };
+        match parse_chunk(bytes) {
+            Ok(chunk) => {
+                unsafe { *out = Box::into_raw(Box::new(chunk)) };
+                McaError::Ok
+            }
+            Err(err) => McaError::from(&err),
+        }
+    })
+}
+
+/// Only chunks returned by `mca_chunk_parse` may be freed.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn mca_chunk_free(chunk: *mut Chunk) {
+    if !chunk.is_null() {
+        drop(unsafe { Box::from_raw(chunk) });
+    }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn mca_chunk_get_block(
+    chunk: *const Chunk,
+    x: u8,
+    y: i32,
+    z: u8,
+    out: *mut McaBlockInfo,
+) -> McaError {
+    let Some(chunk) = (unsafe { chunk.as_ref() }) else {
+        return McaError::NullPointer;
+    };
+    if out.is_null() {
+        return McaError::NullPointer;
+    }
+
+    guard(|| {
+        let Some(block) = chunk.get(x, y, z) else {
+            return McaError::OutOfBounds;
+        };
+
+        let name = block.get_name();
+        let property_count = block.property_keys().len();
+
+        unsafe {
+            *out = McaBlockInfo {
+                name: name.as_ptr(),
+                name_len: name.len(),
+                property_count,
+            }
+        };
+        McaError::Ok
+    })
+}
@@ -0,0 +1,93 @@
+use nbt_rs::types::{NbtCompound, NbtList, NbtTag};
+use serde_json::{Map, Value};
+
+use crate::chunk::Chunk;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct JsonOptions {
+    /// Emit longs as strings, so consumers parsing numbers as doubles (like
+    /// JavaScript) don't lose precision.
+    pub longs_as_strings: bool,
+}
+
+pub fn nbt_to_json(compound: &NbtCompound) -> Value {
+    nbt_to_json_with(compound, JsonOptions::default())
+}
+
+pub fn nbt_to_json_with(compound: &NbtCompound, options: JsonOptions) -> Value {
+    // compounds can only be walked by value, so clone once and consume
+    compound_to_json(compound.clone(), &options)
+}
+
+impl Chunk {
+    /// The chunk's compound as it was read, as JSON, or `None` if it wasn't
+    /// kept, see [`Chunk::raw_nbt`].
+    pub fn raw_nbt_json(&self) -> Option<Value> {
+        self.raw_nbt_json_with(JsonOptions::default())
+    }
+
+    /// Like [`Chunk::raw_nbt_json`], but converted like
+    /// [`nbt_to_json_with`] does.
+    pub fn raw_nbt_json_with(&self, options: JsonOptions) -> Option<Value> {
+        Some(nbt_to_json_with(self.raw_nbt()?, options))
+    }
+}
+
+fn compound_to_json(compound: NbtCompound, options: &JsonOptions) -> Value {
+    let entries: Vec<_> = compound.into();
+    let mut map = Map::with_capacity(entries.len());
+    for (key, tag) in entries {
+        map.insert(key.to_string(), tag_to_json(tag, options));
+    }
+    Value::Object(map)
+}
+
+fn long_to_json(value: i64, options: &JsonOptions) -> Value {
+    if options.longs_as_strings {
+        Value::String(value.to_string())
+    } else {
+        Value::from(value)
+    }
+}
+
+fn tag_to_json(tag: NbtTag, options: &JsonOptions) -> Value {
+    match tag {
+        NbtTag::End => Value::Null,
+        NbtTag::Byte(v) => Value::from(v),
+        NbtTag::Short(v) => Value::from(v),
+        NbtTag::Int(v) => Value::from(v),
+        NbtTag::Long(v) => long_to_json(v, options),
+        NbtTag::Float(v) => Value::from(v),
+        NbtTag::Double(v) => Value::from(v),
+        NbtTag::ByteArray(v) => v.iter().map(|&v| Value::from(v)).collect(),
+        NbtTag::String(v) => Value::String(v.to_string()),
+        NbtTag::List(v) => list_to_json(v, options),
+        NbtTag::Compound(v) => compound_to_json(v, options),
+        NbtTag::IntArray(v) => v.iter().map(|&v| Value::from(v)).collect(),
+        NbtTag::LongArray(v) => v.iter().map(|&v| long_to_json(v, options)).collect(),
+    }
+}
+
+fn list_to_json(list: NbtList, options: &JsonOptions) -> Value {
+    macro_rules! convert {
+        ($items:expr, $convert:expr) => {
+            Vec::from($items).into_iter().map($convert).collect()
+        };
+    }
+
+    match list {
+        NbtList::End => Value::Array(Vec::new()),
+        NbtList::Byte(v) => convert!(v, Value::from),
+        NbtList::Short(v) => convert!(v, Value::from),
+        NbtList::Int(v) => convert!(v, Value::from),
+        NbtList::Long(v) => convert!(v, |v| long_to_json(v, options)),
+        NbtList::Float(v) => convert!(v, Value::from),
+        NbtList::Double(v) => convert!(v, Value::from),
+        NbtList::ByteArray(v) => convert!(v, |v| tag_to_json(NbtTag::ByteArray(v), options)),
+        NbtList::String(v) => convert!(v, |v| Value::String(v.to_string())),
+        NbtList::List(v) => convert!(v, |v| list_to_json(v, options)),
+        NbtList::Compound(v) => convert!(v, |v| compound_to_json(v, options)),
+        NbtList::IntArray(v) => convert!(v, |v| tag_to_json(NbtTag::IntArray(v), options)),
+        NbtList::LongArray(v) => convert!(v, |v| tag_to_json(NbtTag::LongArray(v), options)),
+    }
+}
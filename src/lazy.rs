@@ -0,0 +1,251 @@
+//! A region that only decodes chunks when they're first asked for.
+
+use std::cell::{OnceCell, RefCell};
+use std::ops::{Deref, Range};
+
+use crate::{
+    chunk::{Chunk, ChunkParseError},
+    coords::ChunkPos,
+    raw::RawChunk,
+    region::{RegionParseError, RegionParseOptions, Scratch, boxed_slots, chunk_range},
+    trace,
+};
+
+/// Like [`crate::Region`], but parsing only reads the header, and each chunk
+/// is decompressed and decoded the first time it's asked for, then kept.
+/// It borrows the bytes of the region file instead of copying the chunks,
+/// or with the `bytes` feature, shares them, see `LazyRegion::parse_shared`.
+///
+/// With the `sync` feature it can be sent to another thread, but not shared
+/// between threads, since chunks are decoded behind a shared reference.
+#[derive(Debug, Clone)]
+pub struct LazyRegion<'a> {
+    bytes: Source<'a>,
+    entries: [Option<(Range<usize>, u32)>; 1024],
+    chunks: Box<[OnceCell<Option<Chunk>>; 1024]>,
+    options: RegionParseOptions,
+    scratch: RefCell<Scratch>,
+}
+
+impl<'a> LazyRegion<'a> {
+    pub fn parse_bytes(bytes: &'a [u8]) -> Result<Self, RegionParseError> {
+        Self::parse_bytes_with(bytes, RegionParseOptions::default())
+    }
+
+    /// The options are kept and used for every chunk that gets decoded.
+    pub fn parse_bytes_with(
+        bytes: &'a [u8],
+        options: RegionParseOptions,
+    ) -> Result<Self, RegionParseError> {
+        Self::parse_source(Source::Borrowed(bytes), options)
+    }
+
+    fn parse_source(
+        bytes: Source<'a>,
+        options: RegionParseOptions,
+    ) -> Result<Self, RegionParseError> {
+        let mut entries = [const { None }; 1024];
+        for (entry, header) in entries.iter_mut().zip(options.header_entries(&bytes)?) {
+            *entry = header.map(|(range, timestamp)| (chunk_range(&bytes, range), timestamp));
+        }
+
+        Ok(Self {
+            bytes,
+            entries,
+            chunks: boxed_slots(),
+            scratch: RefCell::new(Scratch::new(&options)),
+            options,
+        })
+    }
+
+    /// Like [`LazyRegion::parse_bytes`], but keeps `bytes` rather than
+    /// borrowing them, so the region can outlive the buffer it was read
+    /// from.
+    #[cfg(feature = "bytes")]
+    pub fn parse_shared(bytes: bytes::Bytes) -> Result<LazyRegion<'static>, RegionParseError> {
+        LazyRegion::parse_shared_with(bytes, RegionParseOptions::default())
+    }
+
+    #[cfg(feature = "bytes")]
+    pub fn parse_shared_with(
+        bytes: bytes::Bytes,
+        options: RegionParseOptions,
+    ) -> Result<LazyRegion<'static>, RegionParseError> {
+        LazyRegion::parse_source(Source::Shared(bytes), options)
+    }
+
+    /// The number of chunks in the header. Unlike [`crate::Region::count_chunks`],
+    /// this includes chunks that fail to decode or are filtered out.
+    pub fn count_chunks(&self) -> u16 {
+        self.entries.iter().flatten().count() as u16
+    }
+
+    /// How many chunks have been decoded so far, including ones that failed.
+    pub fn count_decoded(&self) -> u16 {
+        self.chunks
+            .iter()
+            .filter(|chunk| chunk.get().is_some())
+            .count() as u16
+    }
+
+    /// Decodes the chunk if that hasn't been done yet. Returns `None` if the
+    /// chunk is missing, failed to decode, was left out by the status
+    /// filter, or `x` or `z` is 32 or above.
+    pub fn get_chunk(&self, x: usize, z: usize) -> Option<&Chunk> {
+        if x >= 32 || z >= 32 {
+            return None;
+        }
+
+        let index = x + z * 32;
+        self.chunks[index]
+            .get_or_init(|| self.decode(index))
+            .as_ref()
+    }
+
+    /// Like [`LazyRegion::get_chunk`], but takes the chunk's world position.
+    /// Only its offset in the region is used.
+    pub fn get_chunk_at(&self, pos: impl Into<ChunkPos>) -> Option<&Chunk> {
+        let (x, z) = pos.into().region_local();
+        self.get_chunk(x, z)
+    }
+
+    /// The last modification time of the chunk, from the header. Returns
+    /// `None` for empty slots, or if `x` or `z` is 32 or above.
+    pub fn get_timestamp(&self, x: usize, z: usize) -> Option<u32> {
+        if x >= 32 || z >= 32 {
+            return None;
+        }
+
+        self.entries[x + z * 32]
+            .as_ref()
+            .map(|(_, timestamp)| *timestamp)
+    }
+
+    /// The slots holding a chunk in both regions where the chunks are stored
+    /// differently, `x` before `z`. Only the stored bytes are compared, so
+    /// nothing is decoded until [`LazySlot::chunk`] is called.
+    pub fn conflicts<'r>(
+        &'r self,
+        other: &'r LazyRegion<'_>,
+    ) -> impl Iterator<Item = Conflict<'r>> {
+        (0..1024).filter_map(move |index| {
+            let (ours, theirs) = (self.slot(index)?, other.slot(index)?);
+            (ours.stored() != theirs.stored()).then_some(Conflict {
+                x: index % 32,
+                z: index / 32,
+                ours,
+                theirs,
+            })
+        })
+    }
+
+    fn slot(&self, index: usize) -> Option<LazySlot<'_>> {
+        let (range, timestamp) = self.entries[index].clone()?;
+        // cut short at the end of the file, like in the eager parser
+        let end = range.end.min(self.bytes.len());
+        Some(LazySlot {
+            region: self,
+            index,
+            timestamp,
+            allocated: range.len(),
+            bytes: &self.bytes[range.start.min(end)..end],
+        })
+    }
+
+    // the error is only used for tracing
+    #[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+    fn decode(&self, index: usize) -> Option<Chunk> {
+        (self.slot(index)?.raw())
+            .and_then(|raw| {
+                let mut scratch = self.scratch.borrow_mut();
+                self.options.decode(&raw, index, &mut scratch)
+            })
+            .inspect_err(|err| {
+                trace::debug!(x = index % 32, z = index / 32, error = %err, "skipping chunk");
+            })
+            .ok()
+            .flatten()
+    }
+}
+
+/// A slot holding a chunk in two regions, see [`LazyRegion::conflicts`].
+#[derive(Debug, Clone, Copy)]
+pub struct Conflict<'r> {
+    pub x: usize,
+    pub z: usize,
+    pub ours: LazySlot<'r>,
+    pub theirs: LazySlot<'r>,
+}
+
+/// A chunk of a [`LazyRegion`] that is only decoded when asked for.
+#[derive(Debug, Clone, Copy)]
+pub struct LazySlot<'r> {
+    region: &'r LazyRegion<'r>,
+    index: usize,
+    timestamp: u32,
+    // the sectors of the slot, `allocated` bytes in the header but cut
+    // short at the end of the file
+    allocated: usize,
+    bytes: &'r [u8],
+}
+
+impl<'r> LazySlot<'r> {
+    /// The last modification time of the chunk, from the header.
+    pub fn timestamp(&self) -> u32 {
+        self.timestamp
+    }
+
+    /// The length of the compressed chunk, without its header or the padding
+    /// of its last sector.
+    pub fn compressed_len(&self) -> usize {
+        self.stored().len().saturating_sub(5)
+    }
+
+    /// The chunk as stored, without decompressing it. Its payload is a slice
+    /// of the region's bytes if they're shared.
+    pub fn raw(&self) -> Result<RawChunk, ChunkParseError> {
+        let options = &self.region.options;
+        let mut raw = match &self.region.bytes {
+            Source::Borrowed(_) => options.parse_raw(self.bytes, self.allocated)?,
+            #[cfg(feature = "bytes")]
+            Source::Shared(shared) => {
+                options.parse_raw_shared(&shared.slice_ref(self.bytes), self.allocated)?
+            }
+        };
+        raw.timestamp = self.timestamp;
+        Ok(raw)
+    }
+
+    /// Decodes the chunk like [`LazyRegion::get_chunk`], which keeps it.
+    pub fn chunk(&self) -> Option<&'r Chunk> {
+        self.region.get_chunk(self.index % 32, self.index / 32)
+    }
+
+    /// The chunk with its header, as far as its length says it goes.
+    fn stored(&self) -> &'r [u8] {
+        let len = (self.bytes.get(..4)).map_or(0, |len| {
+            u32::from_be_bytes(len.try_into().unwrap()) as usize
+        });
+        &self.bytes[..len.saturating_add(4).min(self.bytes.len())]
+    }
+}
+
+/// The bytes of a [`LazyRegion`], borrowed or shared.
+#[derive(Debug, Clone)]
+enum Source<'a> {
+    Borrowed(&'a [u8]),
+    #[cfg(feature = "bytes")]
+    Shared(bytes::Bytes),
+}
+
+impl Deref for Source<'_> {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            Source::Borrowed(bytes) => bytes,
+            #[cfg(feature = "bytes")]
+            Source::Shared(bytes) => bytes,
+        }
+    }
+}
@@ -0,0 +1,146 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::PathBuf,
+};
+
+use nbt_rs::{get_field, types::NbtCompound};
+
+use crate::{
+    chunk::{Chunk, ChunkParseError, ChunkParseOptions, SectionDataError},
+    chunks::{
+        block::{Block, LazyAir, Shared},
+        raw::parse_gzip_nbt,
+        section::Section,
+        status::ChunkStatus,
+    },
+    legacy::LegacyBlockMapping,
+};
+
+const ALPHA_HEIGHT: usize = 128;
+
+/// Parses a standalone gzip-compressed `c.<x>.<z>.dat` chunk file as written
+/// by Alpha and Beta versions before region files existed. Those chunks are
+/// always 128 blocks high, so the result has 8 sections starting at y = 0.
+pub fn parse_alpha_chunk(bytes: &[u8]) -> Result<Chunk, ChunkParseError> {
+    parse_alpha_chunk_with(bytes, &ChunkParseOptions::default())
+}
+
+/// Like [`parse_alpha_chunk`], with the size and nbt limits of `options`,
+/// mapping the numeric block ids through
+/// [`ChunkParseOptions::legacy_mapping`].
+pub fn parse_alpha_chunk_with(
+    bytes: &[u8],
+    options: &ChunkParseOptions,
+) -> Result<Chunk, ChunkParseError> {
+    let root = parse_gzip_nbt(bytes, options)?;
+    parse_alpha_compound(&root, options.legacy_mapping.get())
+}
+
+/// Decodes the root compound of a chunk that keeps its 128 blocks high
+/// columns in `Level.Blocks`, which McRegion files from Beta 1.3 to 1.1 do
+/// too, mapping the numeric ids with `mapping`.
+pub(crate) fn parse_alpha_compound(
+    root: &NbtCompound,
+    mapping: &dyn LegacyBlockMapping,
+) -> Result<Chunk, ChunkParseError> {
+    let level = get_field!(root, "Level", as_compound)
+        .ok_or_else(|| ChunkParseError::InvalidField("Level".to_owned(), "compound"))?;
+    let blocks = get_field!(level, "Blocks", as_byte_array)
+        .ok_or_else(|| ChunkParseError::InvalidField("Level.Blocks".to_owned(), "byte array"))?;
+    let block_data = get_field!(level, "Data", as_byte_array)
+        .ok_or_else(|| ChunkParseError::InvalidField("Level.Data".to_owned(), "byte array"))?;
+    let &x_pos = get_field!(level, "xPos", as_int)
+        .ok_or_else(|| ChunkParseError::InvalidField("Level.xPos".to_owned(), "int"))?;
+    let &z_pos = get_field!(level, "zPos", as_int)
+        .ok_or_else(|| ChunkParseError::InvalidField("Level.zPos".to_owned(), "int"))?;
+    let last_update = get_field!(level, "LastUpdate", as_long).map_or(0, |&v| v);
+
+    for (field, len, expected) in [
+        ("Level.Blocks", blocks.len(), 16 * 16 * ALPHA_HEIGHT),
+        ("Level.Data", block_data.len(), 16 * 16 * ALPHA_HEIGHT / 2),
+    ] {
+        if len != expected {
+            return Err(ChunkParseError::InvalidSectionData {
+                section_y: None,
+                path: field.to_owned(),
+                reason: SectionDataError::WrongLength { len, expected },
+            });
+        }
+    }
+
+    let mut palette: HashMap<(u8, u8), Shared<Block>> = HashMap::new();
+    let mut sections = Vec::with_capacity(ALPHA_HEIGHT / 16);
+    for section_y in 0..ALPHA_HEIGHT / 16 {
+        let mut section_blocks = Vec::with_capacity(4096);
+        // sections are indexed y, z, x from the most significant end
+        for index in 0..4096 {
+            let (x, y, z) = (
+                index & 0xF,
+                section_y * 16 + (index >> 8),
+                (index >> 4) & 0xF,
+            );
+            // while alpha chunks are indexed x, z, y
+            let alpha_index = y + z * ALPHA_HEIGHT + x * ALPHA_HEIGHT * 16;
+            let id = blocks[alpha_index] as u8;
+            let nibble = (block_data[alpha_index >> 1] as u8 >> ((alpha_index & 1) * 4)) & 0xF;
+
+            let block = palette
+                .entry((id, nibble))
+                .or_insert_with(|| Shared::new(mapping.block(id as u16, nibble)));
+            section_blocks.push(block.clone());
+        }
+
+        sections.push(Section::new(section_blocks.try_into().unwrap()));
+    }
+
+    Ok(Chunk {
+        x_pos,
+        y_pos: 0,
+        z_pos,
+        // alpha predates data versions, and only wrote finished chunks
+        data_version: 0,
+        status: ChunkStatus::Full,
+        last_update,
+        inhabited_time: 0,
+        sections,
+        heightmaps: BTreeMap::new(),
+        block_entities: Vec::new(),
+        entities: Vec::new(),
+        block_ticks: Vec::new(),
+        fluid_ticks: Vec::new(),
+        structure_references: Vec::new(),
+        structure_starts: Vec::new(),
+        below_zero_retrogen: None,
+        blending_data: None,
+        legacy_sections: None,
+        raw_nbt: None,
+        warnings: Vec::new(),
+        air: LazyAir::default(),
+    })
+}
+
+fn base36(value: i32) -> String {
+    let digits = b"0123456789abcdefghijklmnopqrstuvwxyz";
+    let mut magnitude = value.unsigned_abs();
+    let mut out = Vec::new();
+    loop {
+        out.push(digits[(magnitude % 36) as usize]);
+        magnitude /= 36;
+        if magnitude == 0 {
+            break;
+        }
+    }
+    if value < 0 {
+        out.push(b'-');
+    }
+    out.reverse();
+    String::from_utf8(out).unwrap()
+}
+
+/// The path of a chunk file relative to the world directory, e.g.
+/// `1r/1/c.-1.1.dat` for chunk (-1, 1).
+pub fn alpha_chunk_path(cx: i32, cz: i32) -> PathBuf {
+    PathBuf::from(base36(cx.rem_euclid(64)))
+        .join(base36(cz.rem_euclid(64)))
+        .join(format!("c.{}.{}.dat", base36(cx), base36(cz)))
+}
@@ -0,0 +1,628 @@
+use std::{collections::HashMap, fmt, sync::Arc};
+
+use nbt_rs::types::{NbtCompound, NbtString, NbtTag};
+
+use crate::chunks::block::Block;
+
+pub const UNKNOWN_LEGACY_BLOCK: &str = "mca_rs:unknown";
+
+const COLORS: [&str; 16] = [
+    "white",
+    "orange",
+    "magenta",
+    "light_blue",
+    "yellow",
+    "lime",
+    "pink",
+    "gray",
+    "light_gray",
+    "cyan",
+    "purple",
+    "blue",
+    "brown",
+    "green",
+    "red",
+    "black",
+];
+
+const WOODS: [&str; 6] = ["oak", "spruce", "birch", "jungle", "acacia", "dark_oak"];
+
+const HORIZONTAL_FACING: [&str; 6] = ["north", "north", "north", "south", "west", "east"];
+const FACING: [&str; 6] = ["down", "up", "north", "south", "west", "east"];
+
+fn nbt_string(value: &str) -> NbtString {
+    // every string in the table is a short literal
+    value.to_owned().try_into().unwrap()
+}
+
+fn block(name: &str, properties: &[(&str, &str)]) -> Block {
+    let properties = (!properties.is_empty()).then(|| {
+        NbtCompound::from(
+            properties
+                .iter()
+                .map(|&(key, value)| (nbt_string(key), NbtTag::String(nbt_string(value))))
+                .collect::<HashMap<_, _>>(),
+        )
+    });
+
+    Block::new(nbt_string(&format!("minecraft:{name}")), properties)
+}
+
+fn unknown(id: u16, data: u8) -> Block {
+    let properties = NbtCompound::from(HashMap::from([
+        (
+            nbt_string("id"),
+            NbtTag::String(nbt_string(&id.to_string())),
+        ),
+        (
+            nbt_string("data"),
+            NbtTag::String(nbt_string(&data.to_string())),
+        ),
+    ]));
+
+    Block::new(nbt_string(UNKNOWN_LEGACY_BLOCK), Some(properties))
+}
+
+fn color(data: u8) -> &'static str {
+    COLORS[(data & 0xF) as usize]
+}
+
+fn wood(data: u8) -> &'static str {
+    WOODS.get((data & 0x7) as usize).copied().unwrap_or("oak")
+}
+
+fn variant<'a>(names: &[&'a str], data: u8) -> &'a str {
+    names.get(data as usize).copied().unwrap_or(names[0])
+}
+
+fn axis(data: u8) -> &'static str {
+    match (data >> 2) & 0x3 {
+        1 => "x",
+        2 => "z",
+        _ => "y",
+    }
+}
+
+fn half(data: u8) -> &'static str {
+    if data & 0x8 != 0 { "top" } else { "bottom" }
+}
+
+fn stairs(name: &str, data: u8) -> Block {
+    let facing = ["east", "west", "south", "north"][(data & 0x3) as usize];
+    let half = if data & 0x4 != 0 { "top" } else { "bottom" };
+    block(
+        name,
+        &[("facing", facing), ("half", half), ("shape", "straight")],
+    )
+}
+
+fn horizontal(name: &str, data: u8) -> Block {
+    block(
+        name,
+        &[("facing", HORIZONTAL_FACING[(data as usize).min(5)])],
+    )
+}
+
+fn directional(name: &str, data: u8) -> Block {
+    block(name, &[("facing", FACING[((data & 0x7) as usize).min(5)])])
+}
+
+fn slab(name: &str, data: u8, double: bool) -> Block {
+    let kind = if double { "double" } else { half(data) };
+    block(name, &[("type", kind)])
+}
+
+fn age(name: &str, data: u8) -> Block {
+    block(name, &[("age", &data.to_string())])
+}
+
+fn log(name: &str, data: u8) -> Block {
+    if (data >> 2) & 0x3 == 3 {
+        // all-bark logs became their own blocks
+        return block(&name.replace("_log", "_wood"), &[("axis", "y")]);
+    }
+    block(name, &[("axis", axis(data))])
+}
+
+fn door(name: &str, data: u8) -> Block {
+    if data & 0x8 != 0 {
+        return block(name, &[("half", "upper")]);
+    }
+    let facing = ["east", "south", "west", "north"][(data & 0x3) as usize];
+    let open = if data & 0x4 != 0 { "true" } else { "false" };
+    block(
+        name,
+        &[("facing", facing), ("half", "lower"), ("open", open)],
+    )
+}
+
+fn torch(name: &str, wall_name: &str, lit: Option<bool>, data: u8) -> Block {
+    let lit = lit.map(|lit| if lit { "true" } else { "false" });
+    let facing = match data {
+        1 => "east",
+        2 => "west",
+        3 => "south",
+        4 => "north",
+        _ => {
+            return match lit {
+                Some(lit) => block(name, &[("lit", lit)]),
+                None => block(name, &[]),
+            };
+        }
+    };
+    match lit {
+        Some(lit) => block(wall_name, &[("facing", facing), ("lit", lit)]),
+        None => block(wall_name, &[("facing", facing)]),
+    }
+}
+
+/// Maps the numeric block ids and data values of chunks from before 1.13 to
+/// blocks, see [`LegacyMapping`]. Implemented for closures taking the id and
+/// data value.
+pub trait LegacyBlockMapping: Send + Sync {
+    fn block(&self, id: u16, data: u8) -> Block;
+}
+
+impl<F: Fn(u16, u8) -> Block + Send + Sync> LegacyBlockMapping for F {
+    fn block(&self, id: u16, data: u8) -> Block {
+        self(id, data)
+    }
+}
+
+/// The [`LegacyBlockMapping`] the sections of chunks from before 1.13 are
+/// flattened with when parsing, [`flatten_block`] by default.
+///
+/// ```
+/// use mca_rs::{
+///     block::Block,
+///     chunk::ChunkParseOptions,
+///     legacy::{LegacyMapping, flatten_block},
+/// };
+///
+/// // a modded block the built-in table doesn't know
+/// let mapping = LegacyMapping::new(|id, data| match id {
+///     3000 => Block::new("mymod:ore".to_owned().try_into().unwrap(), None),
+///     _ => flatten_block(id, data),
+/// });
+/// assert_eq!(mapping.get().block(3000, 0).get_name(), "mymod:ore");
+/// let options = ChunkParseOptions {
+///     legacy_mapping: mapping,
+///     ..Default::default()
+/// };
+/// # let _ = options;
+/// ```
+#[derive(Clone, Default)]
+pub struct LegacyMapping(Option<Arc<dyn LegacyBlockMapping>>);
+
+impl LegacyMapping {
+    pub fn new(mapping: impl LegacyBlockMapping + 'static) -> Self {
+        Self(Some(Arc::new(mapping)))
+    }
+
+    pub fn get(&self) -> &dyn LegacyBlockMapping {
+        self.0.as_deref().unwrap_or(&flatten_block)
+    }
+}
+
+impl fmt::Debug for LegacyMapping {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Some(_) => f.write_str("LegacyMapping(custom)"),
+            None => f.write_str("LegacyMapping(flatten_block)"),
+        }
+    }
+}
+
+/// Mappings are equal if they're the same one.
+impl PartialEq for LegacyMapping {
+    fn eq(&self, other: &Self) -> bool {
+        match (&self.0, &other.0) {
+            (Some(a), Some(b)) => Arc::ptr_eq(a, b),
+            (a, b) => a.is_none() && b.is_none(),
+        }
+    }
+}
+
+impl Eq for LegacyMapping {}
+
+/// Maps a pre-1.13 numeric block id and data value to the block it became
+/// after the flattening, using current block names. Properties are only
+/// translated for the common families (stairs, slabs, logs, doors, crops,
+/// fluids, ...); everything else maps to the block's default state. Ids the
+/// table doesn't know produce a `mca_rs:unknown` block carrying the original
+/// `id` and `data` as properties.
+pub fn flatten_block(id: u16, data: u8) -> Block {
+    let data = data & 0xF;
+    match id {
+        0 => block("air", &[]),
+        1 => block(
+            variant(
+                &[
+                    "stone",
+                    "granite",
+                    "polished_granite",
+                    "diorite",
+                    "polished_diorite",
+                    "andesite",
+                    "polished_andesite",
+                ],
+                data,
+            ),
+            &[],
+        ),
+        2 => block("grass_block", &[("snowy", "false")]),
+        3 => block(variant(&["dirt", "coarse_dirt", "podzol"], data), &[]),
+        4 => block("cobblestone", &[]),
+        5 => block(&format!("{}_planks", wood(data)), &[]),
+        6 => block(
+            &format!("{}_sapling", wood(data)),
+            &[("stage", if data & 0x8 != 0 { "1" } else { "0" })],
+        ),
+        7 => block("bedrock", &[]),
+        8 | 9 => block("water", &[("level", &data.to_string())]),
+        10 | 11 => block("lava", &[("level", &data.to_string())]),
+        12 => block(variant(&["sand", "red_sand"], data), &[]),
+        13 => block("gravel", &[]),
+        14 => block("gold_ore", &[]),
+        15 => block("iron_ore", &[]),
+        16 => block("coal_ore", &[]),
+        17 => log(&format!("{}_log", WOODS[(data & 0x3) as usize]), data),
+        18 => block(
+            &format!("{}_leaves", WOODS[(data & 0x3) as usize]),
+            &[("persistent", if data & 0x4 != 0 { "true" } else { "false" })],
+        ),
+        19 => block(variant(&["sponge", "wet_sponge"], data), &[]),
+        20 => block("glass", &[]),
+        21 => block("lapis_ore", &[]),
+        22 => block("lapis_block", &[]),
+        23 => directional("dispenser", data),
+        24 => block(
+            variant(&["sandstone", "chiseled_sandstone", "cut_sandstone"], data),
+            &[],
+        ),
+        25 => block("note_block", &[]),
+        26 => block(
+            "red_bed",
+            &[
+                (
+                    "facing",
+                    ["south", "west", "north", "east"][(data & 0x3) as usize],
+                ),
+                ("part", if data & 0x8 != 0 { "head" } else { "foot" }),
+            ],
+        ),
+        27 => block("powered_rail", &[]),
+        28 => block("detector_rail", &[]),
+        29 => directional("sticky_piston", data),
+        30 => block("cobweb", &[]),
+        31 => block(variant(&["dead_bush", "short_grass", "fern"], data), &[]),
+        32 => block("dead_bush", &[]),
+        33 => directional("piston", data),
+        34 => directional("piston_head", data),
+        35 => block(&format!("{}_wool", color(data)), &[]),
+        36 => block("moving_piston", &[]),
+        37 => block("dandelion", &[]),
+        38 => block(
+            variant(
+                &[
+                    "poppy",
+                    "blue_orchid",
+                    "allium",
+                    "azure_bluet",
+                    "red_tulip",
+                    "orange_tulip",
+                    "white_tulip",
+                    "pink_tulip",
+                    "oxeye_daisy",
+                ],
+                data,
+            ),
+            &[],
+        ),
+        39 => block("brown_mushroom", &[]),
+        40 => block("red_mushroom", &[]),
+        41 => block("gold_block", &[]),
+        42 => block("iron_block", &[]),
+        43 | 44 => slab(
+            variant(
+                &[
+                    "smooth_stone_slab",
+                    "sandstone_slab",
+                    "petrified_oak_slab",
+                    "cobblestone_slab",
+                    "brick_slab",
+                    "stone_brick_slab",
+                    "nether_brick_slab",
+                    "quartz_slab",
+                ],
+                data & 0x7,
+            ),
+            data,
+            id == 43,
+        ),
+        45 => block("bricks", &[]),
+        46 => block("tnt", &[]),
+        47 => block("bookshelf", &[]),
+        48 => block("mossy_cobblestone", &[]),
+        49 => block("obsidian", &[]),
+        50 => torch("torch", "wall_torch", None, data),
+        51 => block("fire", &[]),
+        52 => block("spawner", &[]),
+        53 => stairs("oak_stairs", data),
+        54 => horizontal("chest", data),
+        55 => block("redstone_wire", &[("power", &data.to_string())]),
+        56 => block("diamond_ore", &[]),
+        57 => block("diamond_block", &[]),
+        58 => block("crafting_table", &[]),
+        59 => age("wheat", data),
+        60 => block("farmland", &[("moisture", &data.min(7).to_string())]),
+        61 | 62 => block(
+            "furnace",
+            &[
+                ("facing", HORIZONTAL_FACING[(data as usize).min(5)]),
+                ("lit", if id == 62 { "true" } else { "false" }),
+            ],
+        ),
+        63 => block("oak_sign", &[("rotation", &data.to_string())]),
+        64 => door("oak_door", data),
+        65 => horizontal("ladder", data),
+        66 => block("rail", &[]),
+        67 => stairs("cobblestone_stairs", data),
+        68 => horizontal("oak_wall_sign", data),
+        69 => block("lever", &[]),
+        70 => block("stone_pressure_plate", &[]),
+        71 => door("iron_door", data),
+        72 => block("oak_pressure_plate", &[]),
+        73 | 74 => block(
+            "redstone_ore",
+            &[("lit", if id == 74 { "true" } else { "false" })],
+        ),
+        75 | 76 => torch(
+            "redstone_torch",
+            "redstone_wall_torch",
+            Some(id == 76),
+            data,
+        ),
+        77 => block("stone_button", &[]),
+        78 => block("snow", &[("layers", &((data & 0x7) + 1).to_string())]),
+        79 => block("ice", &[]),
+        80 => block("snow_block", &[]),
+        81 => age("cactus", data),
+        82 => block("clay", &[]),
+        83 => age("sugar_cane", data),
+        84 => block("jukebox", &[]),
+        85 => block("oak_fence", &[]),
+        86 | 91 => block(
+            if id == 86 {
+                "carved_pumpkin"
+            } else {
+                "jack_o_lantern"
+            },
+            &[(
+                "facing",
+                ["south", "west", "north", "east"][(data & 0x3) as usize],
+            )],
+        ),
+        87 => block("netherrack", &[]),
+        88 => block("soul_sand", &[]),
+        89 => block("glowstone", &[]),
+        90 => block("nether_portal", &[]),
+        92 => block("cake", &[("bites", &data.min(6).to_string())]),
+        93 | 94 => block(
+            "repeater",
+            &[("powered", if id == 94 { "true" } else { "false" })],
+        ),
+        95 => block(&format!("{}_stained_glass", color(data)), &[]),
+        96 => block("oak_trapdoor", &[("half", half(data))]),
+        97 => block(
+            variant(
+                &[
+                    "infested_stone",
+                    "infested_cobblestone",
+                    "infested_stone_bricks",
+                    "infested_mossy_stone_bricks",
+                    "infested_cracked_stone_bricks",
+                    "infested_chiseled_stone_bricks",
+                ],
+                data,
+            ),
+            &[],
+        ),
+        98 => block(
+            variant(
+                &[
+                    "stone_bricks",
+                    "mossy_stone_bricks",
+                    "cracked_stone_bricks",
+                    "chiseled_stone_bricks",
+                ],
+                data,
+            ),
+            &[],
+        ),
+        99 | 100 if data == 10 || data == 15 => block("mushroom_stem", &[]),
+        99 => block("brown_mushroom_block", &[]),
+        100 => block("red_mushroom_block", &[]),
+        101 => block("iron_bars", &[]),
+        102 => block("glass_pane", &[]),
+        103 => block("melon", &[]),
+        104 => age("pumpkin_stem", data & 0x7),
+        105 => age("melon_stem", data & 0x7),
+        106 => block("vine", &[]),
+        107 => block("oak_fence_gate", &[]),
+        108 => stairs("brick_stairs", data),
+        109 => stairs("stone_brick_stairs", data),
+        110 => block("mycelium", &[("snowy", "false")]),
+        111 => block("lily_pad", &[]),
+        112 => block("nether_bricks", &[]),
+        113 => block("nether_brick_fence", &[]),
+        114 => stairs("nether_brick_stairs", data),
+        115 => age("nether_wart", data & 0x3),
+        116 => block("enchanting_table", &[]),
+        117 => block("brewing_stand", &[]),
+        118 if data == 0 => block("cauldron", &[]),
+        118 => block("water_cauldron", &[("level", &data.min(3).to_string())]),
+        119 => block("end_portal", &[]),
+        120 => block(
+            "end_portal_frame",
+            &[("eye", if data & 0x4 != 0 { "true" } else { "false" })],
+        ),
+        121 => block("end_stone", &[]),
+        122 => block("dragon_egg", &[]),
+        123 | 124 => block(
+            "redstone_lamp",
+            &[("lit", if id == 124 { "true" } else { "false" })],
+        ),
+        125 | 126 => slab(&format!("{}_slab", wood(data)), data, id == 125),
+        127 => age("cocoa", (data >> 2) & 0x3),
+        128 => stairs("sandstone_stairs", data),
+        129 => block("emerald_ore", &[]),
+        130 => horizontal("ender_chest", data),
+        131 => block("tripwire_hook", &[]),
+        132 => block("tripwire", &[]),
+        133 => block("emerald_block", &[]),
+        134 => stairs("spruce_stairs", data),
+        135 => stairs("birch_stairs", data),
+        136 => stairs("jungle_stairs", data),
+        137 => block("command_block", &[]),
+        138 => block("beacon", &[]),
+        139 => block(
+            variant(&["cobblestone_wall", "mossy_cobblestone_wall"], data),
+            &[],
+        ),
+        140 => block("flower_pot", &[]),
+        141 => age("carrots", data & 0x7),
+        142 => age("potatoes", data & 0x7),
+        143 => block("oak_button", &[]),
+        144 => block("skeleton_skull", &[]),
+        145 => block(
+            variant(&["anvil", "chipped_anvil", "damaged_anvil"], data >> 2),
+            &[],
+        ),
+        146 => horizontal("trapped_chest", data),
+        147 => block("light_weighted_pressure_plate", &[]),
+        148 => block("heavy_weighted_pressure_plate", &[]),
+        149 | 150 => block(
+            "comparator",
+            &[("powered", if id == 150 { "true" } else { "false" })],
+        ),
+        151 | 178 => block(
+            "daylight_detector",
+            &[("inverted", if id == 178 { "true" } else { "false" })],
+        ),
+        152 => block("redstone_block", &[]),
+        153 => block("nether_quartz_ore", &[]),
+        154 => block("hopper", &[]),
+        155 => match data {
+            0 => block("quartz_block", &[]),
+            1 => block("chiseled_quartz_block", &[]),
+            3 => block("quartz_pillar", &[("axis", "x")]),
+            4 => block("quartz_pillar", &[("axis", "z")]),
+            _ => block("quartz_pillar", &[("axis", "y")]),
+        },
+        156 => stairs("quartz_stairs", data),
+        157 => block("activator_rail", &[]),
+        158 => directional("dropper", data),
+        159 => block(&format!("{}_terracotta", color(data)), &[]),
+        160 => block(&format!("{}_stained_glass_pane", color(data)), &[]),
+        161 => block(
+            variant(&["acacia_leaves", "dark_oak_leaves"], data & 0x1),
+            &[("persistent", if data & 0x4 != 0 { "true" } else { "false" })],
+        ),
+        162 => log(variant(&["acacia_log", "dark_oak_log"], data & 0x1), data),
+        163 => stairs("acacia_stairs", data),
+        164 => stairs("dark_oak_stairs", data),
+        165 => block("slime_block", &[]),
+        166 => block("barrier", &[]),
+        167 => block("iron_trapdoor", &[("half", half(data))]),
+        168 => block(
+            variant(
+                &["prismarine", "prismarine_bricks", "dark_prismarine"],
+                data,
+            ),
+            &[],
+        ),
+        169 => block("sea_lantern", &[]),
+        170 => block("hay_block", &[("axis", axis(data))]),
+        171 => block(&format!("{}_carpet", color(data)), &[]),
+        172 => block("terracotta", &[]),
+        173 => block("coal_block", &[]),
+        174 => block("packed_ice", &[]),
+        175 => block(
+            variant(
+                &[
+                    "sunflower",
+                    "lilac",
+                    "tall_grass",
+                    "large_fern",
+                    "rose_bush",
+                    "peony",
+                ],
+                data & 0x7,
+            ),
+            &[("half", if data & 0x8 != 0 { "upper" } else { "lower" })],
+        ),
+        176 => block("white_banner", &[("rotation", &data.to_string())]),
+        177 => horizontal("white_wall_banner", data),
+        179 => block(
+            variant(
+                &[
+                    "red_sandstone",
+                    "chiseled_red_sandstone",
+                    "cut_red_sandstone",
+                ],
+                data,
+            ),
+            &[],
+        ),
+        180 => stairs("red_sandstone_stairs", data),
+        181 | 182 => slab("red_sandstone_slab", data, id == 181),
+        183 => block("spruce_fence_gate", &[]),
+        184 => block("birch_fence_gate", &[]),
+        185 => block("jungle_fence_gate", &[]),
+        186 => block("dark_oak_fence_gate", &[]),
+        187 => block("acacia_fence_gate", &[]),
+        188 => block("spruce_fence", &[]),
+        189 => block("birch_fence", &[]),
+        190 => block("jungle_fence", &[]),
+        191 => block("dark_oak_fence", &[]),
+        192 => block("acacia_fence", &[]),
+        193 => door("spruce_door", data),
+        194 => door("birch_door", data),
+        195 => door("jungle_door", data),
+        196 => door("acacia_door", data),
+        197 => door("dark_oak_door", data),
+        198 => directional("end_rod", data),
+        199 => block("chorus_plant", &[]),
+        200 => age("chorus_flower", data.min(5)),
+        201 => block("purpur_block", &[]),
+        202 => block("purpur_pillar", &[("axis", axis(data))]),
+        203 => stairs("purpur_stairs", data),
+        204 | 205 => slab("purpur_slab", data, id == 204),
+        206 => block("end_stone_bricks", &[]),
+        207 => age("beetroots", data & 0x3),
+        208 => block("dirt_path", &[]),
+        209 => block("end_gateway", &[]),
+        210 => directional("repeating_command_block", data),
+        211 => directional("chain_command_block", data),
+        212 => age("frosted_ice", data & 0x3),
+        213 => block("magma_block", &[]),
+        214 => block("nether_wart_block", &[]),
+        215 => block("red_nether_bricks", &[]),
+        216 => block("bone_block", &[("axis", axis(data))]),
+        217 => block("structure_void", &[]),
+        218 => directional("observer", data),
+        219..=234 => directional(&format!("{}_shulker_box", color((id - 219) as u8)), data),
+        235..=250 => block(
+            &format!("{}_glazed_terracotta", color((id - 235) as u8)),
+            &[(
+                "facing",
+                ["south", "west", "north", "east"][(data & 0x3) as usize],
+            )],
+        ),
+        251 => block(&format!("{}_concrete", color(data)), &[]),
+        252 => block(&format!("{}_concrete_powder", color(data)), &[]),
+        255 => block("structure_block", &[]),
+        _ => unknown(id, data),
+    }
+}
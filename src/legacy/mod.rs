@@ -0,0 +1,10 @@
+mod alpha;
+mod flatten;
+mod section;
+mod upgrade;
+
+pub(crate) use alpha::parse_alpha_compound;
+pub use alpha::{alpha_chunk_path, parse_alpha_chunk, parse_alpha_chunk_with};
+pub use flatten::{LegacyBlockMapping, LegacyMapping, UNKNOWN_LEGACY_BLOCK, flatten_block};
+pub use section::LegacySection;
+pub use upgrade::{NEWEST_DATA_VERSION, UpgradeError, UpgradeOptions, parse_chunk_upgraded};
@@ -0,0 +1,116 @@
+use std::collections::HashMap;
+
+use nbt_rs::{get_field, types::NbtCompound};
+
+use crate::{
+    chunk::{ChunkParseError, SectionDataError},
+    chunks::{
+        block::{Block, BlockInterner, Shared},
+        section::Section,
+    },
+    legacy::LegacyBlockMapping,
+};
+
+/// A section of an anvil chunk from before 1.13, which stores numeric block
+/// ids and data values instead of a palette, see
+/// [`crate::chunk::Chunk::legacy_sections`].
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Hash)]
+pub struct LegacySection {
+    y: i32,
+    // the low 8 bits of every id, in `y`, `z`, `x` order like modern sections
+    blocks: Box<[u8; 4096]>,
+    // a nibble per block in the same order, low nibble first
+    data: Box<[u8; 2048]>,
+    // the high 4 bits of every id, only stored once ids went past 255
+    add: Option<Box<[u8; 2048]>>,
+}
+
+impl LegacySection {
+    /// Reads the `Blocks`, `Data` and `Add` arrays of the section at `path`.
+    pub(crate) fn parse(section: &NbtCompound, path: &str) -> Result<Self, ChunkParseError> {
+        let field = |field: &str| {
+            (section.get(field))
+                .and_then(|tag| tag.as_byte_array())
+                .ok_or_else(|| {
+                    ChunkParseError::InvalidField(format!("{path}{field}"), "byte array")
+                })
+        };
+        let bytes = |array: &[i8]| array.iter().map(|&byte| byte as u8).collect::<Vec<_>>();
+
+        let &y = get_field!(section, "Y", as_byte)
+            .ok_or_else(|| ChunkParseError::InvalidField(format!("{path}Y"), "byte"))?;
+        let invalid =
+            |field: &str, len: usize, expected: usize| ChunkParseError::InvalidSectionData {
+                section_y: Some(y as i32),
+                path: format!("{path}{field}"),
+                reason: SectionDataError::WrongLength { len, expected },
+            };
+        let blocks = bytes(field("Blocks")?)
+            .try_into()
+            .map_err(|blocks: Vec<u8>| invalid("Blocks", blocks.len(), 4096))?;
+        let data = bytes(field("Data")?)
+            .try_into()
+            .map_err(|data: Vec<u8>| invalid("Data", data.len(), 2048))?;
+        let add = match section.get("Add") {
+            None => None,
+            Some(_) => Some(
+                bytes(field("Add")?)
+                    .try_into()
+                    .map_err(|add: Vec<u8>| invalid("Add", add.len(), 2048))?,
+            ),
+        };
+        Ok(Self {
+            y: y as i32,
+            blocks,
+            data,
+            add,
+        })
+    }
+
+    /// The section y, i.e. the block y of its bottom divided by 16.
+    pub fn y(&self) -> i32 {
+        self.y
+    }
+
+    /// The numeric id and data value of the block. Returns `None` if any of
+    /// the coordinates is 16 or above.
+    pub fn get_block_id(&self, x: u8, y: u8, z: u8) -> Option<(u16, u8)> {
+        if x >= 16 || y >= 16 || z >= 16 {
+            return None;
+        }
+        Some(self.block_id(((y as usize) << 8) | ((z as usize) << 4) | x as usize))
+    }
+
+    fn block_id(&self, i: usize) -> (u16, u8) {
+        let nibble = |array: &[u8; 2048]| (array[i >> 1] >> ((i & 1) * 4)) & 0xF;
+        let high = self.add.as_deref().map_or(0, nibble) as u16;
+        ((high << 8) | self.blocks[i] as u16, nibble(&self.data))
+    }
+
+    /// The section with every block mapped by `mapping`, which is called once
+    /// per distinct id and data value.
+    pub fn to_section(&self, mapping: &dyn LegacyBlockMapping) -> Section {
+        self.flatten(mapping, &mut BlockInterner::new(), &mut HashMap::new())
+    }
+
+    /// Like [`LegacySection::to_section`], with the blocks mapped so far
+    /// reused from `mapped` and new ones shared through `interner`.
+    pub(crate) fn flatten(
+        &self,
+        mapping: &dyn LegacyBlockMapping,
+        interner: &mut BlockInterner,
+        mapped: &mut HashMap<(u16, u8), Shared<Block>>,
+    ) -> Section {
+        let blocks = std::array::from_fn(|i| {
+            let (id, data) = self.block_id(i);
+            mapped
+                .entry((id, data))
+                .or_insert_with(|| {
+                    let block = mapping.block(id, data);
+                    interner.block(block.get_name(), block.get_properties().clone())
+                })
+                .clone()
+        });
+        Section::new(blocks)
+    }
+}
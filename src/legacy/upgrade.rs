@@ -0,0 +1,73 @@
+use nbt_rs::get_field as try_get_field;
+use thiserror::Error;
+
+use crate::{
+    chunk::{Chunk, ChunkParseError, ChunkParseOptions, parse_chunk_compound},
+    chunks::raw::RawChunk,
+};
+
+/// The data version of 15w32a, the first version to store one. Chunks from
+/// before it have none, which reads as 0.
+const FIRST_DATA_VERSION: i32 = 100;
+
+/// The data version of 1.21.5, the newest version whose chunks this crate
+/// knows the layout of.
+pub const NEWEST_DATA_VERSION: i32 = 4325;
+
+/// Options for [`parse_chunk_upgraded`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UpgradeOptions {
+    /// How the chunk is parsed. The numeric block ids of chunks from before
+    /// 1.13 are mapped with [`ChunkParseOptions::legacy_mapping`].
+    pub parse: ChunkParseOptions,
+    /// The newest data version that is read. Chunks from later versions may
+    /// store their blocks in a way this crate doesn't know, so they fail
+    /// instead of being read partly.
+    pub newest_data_version: i32,
+}
+
+impl Default for UpgradeOptions {
+    fn default() -> Self {
+        Self {
+            parse: ChunkParseOptions::default(),
+            newest_data_version: NEWEST_DATA_VERSION,
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum UpgradeError {
+    /// The chunk's `DataVersion` is neither missing, as in chunks from
+    /// before 15w32a, nor between 15w32a and
+    /// [`UpgradeOptions::newest_data_version`].
+    #[error("chunks with data version {data_version} can't be upgraded")]
+    Unsupported { data_version: i32 },
+
+    #[error(transparent)]
+    Parse(#[from] ChunkParseError),
+}
+
+/// Parses chunk bytes with their 5-byte header into the layout of the
+/// newest version, whichever version wrote them. McRegion chunks, Anvil
+/// chunks with numeric block ids, the `Level` compound of 1.13 to 1.17 and
+/// the layout since 1.18 are all read into the same [`Chunk`], like with
+/// [`crate::chunk::parse_chunk_with`].
+///
+/// Unlike that, the `DataVersion` is checked before anything else is read,
+/// and versions that can't be upgraded fail with
+/// [`UpgradeError::Unsupported`].
+pub fn parse_chunk_upgraded(bytes: &[u8], options: &UpgradeOptions) -> Result<Chunk, UpgradeError> {
+    let decoded = RawChunk::parse(bytes)?.decode_nbt(&options.parse)?;
+    let data_version = try_get_field!(decoded, "DataVersion", as_int).map_or(0, |&v| v);
+    if data_version != 0
+        && !(FIRST_DATA_VERSION..=options.newest_data_version).contains(&data_version)
+    {
+        return Err(UpgradeError::Unsupported { data_version });
+    }
+
+    let mut chunk = parse_chunk_compound(&decoded, &options.parse, None)?;
+    if options.parse.keep_nbt {
+        chunk.raw_nbt = Some(decoded);
+    }
+    Ok(chunk)
+}
@@ -1,5 +1,39 @@
+pub mod bounds;
 pub mod chunks;
+pub mod compression;
+pub mod coords;
+pub mod entity;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "serde")]
+pub mod json;
+pub mod lazy;
+pub mod legacy;
+#[cfg(feature = "lz4")]
+mod lz4;
+pub mod poi;
 pub mod region;
+pub mod region_file;
+pub mod schematic;
+#[cfg(feature = "serde")]
+mod serde_impls;
+pub mod sniff;
+mod trace;
+pub mod validate;
+pub mod world;
 
+pub use bounds::{Axis, OutOfBounds};
 pub use chunks::*;
-pub use region::{Region, RegionParseError};
+pub use chunks::{chunk::Chunk, raw::RawChunk};
+pub use compression::{Compression, CompressionType, WriteOptions};
+pub use coords::{BlockPos, ChunkPos, RegionPos, SectionPos};
+pub use entity::{Entity, EntityRegion, Item};
+pub use lazy::LazyRegion;
+pub use poi::{PoiRecord, PoiRegion};
+pub use region::{ChunkTooLarge, Region, RegionOpenError, RegionParseError, RegionParseOptions};
+pub use region_file::RegionFile;
+pub use sniff::{AnvilKind, sniff};
+pub use validate::{HeaderIssue, ValidationIssue};
+pub use world::{
+    CacheStats, ChunkRef, Dimension, DimensionDir, ScanOptions, World, WorldError, scan_world,
+};
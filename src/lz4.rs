@@ -0,0 +1,129 @@
+//! Reading and writing the LZ4 streams the game writes through lz4-java's
+//! `LZ4BlockOutputStream`: a series of blocks, each with a header giving
+//! its compression, both lengths and a checksum of the decompressed data,
+//! ending with an empty block.
+
+use std::io::{self, Read};
+
+use twox_hash::XxHash32;
+
+const MAGIC: &[u8; 8] = b"LZ4Block";
+const HEADER_LEN: usize = MAGIC.len() + 13;
+const METHOD_RAW: u8 = 0x10;
+const METHOD_LZ4: u8 = 0x20;
+// lz4-java doesn't write blocks larger than this
+const MAX_BLOCK_LEN: usize = 1 << 25;
+// what lz4-java splits the data into by default
+const WRITE_BLOCK_LEN: usize = 1 << 16;
+const CHECKSUM_SEED: u32 = 0x9747_b28c;
+
+pub(crate) struct BlockReader<'a> {
+    input: &'a [u8],
+    block: Vec<u8>,
+    pos: usize,
+    done: bool,
+}
+
+impl<'a> BlockReader<'a> {
+    pub(crate) fn new(input: &'a [u8]) -> Self {
+        Self {
+            input,
+            block: Vec::new(),
+            pos: 0,
+            done: false,
+        }
+    }
+
+    fn next_block(&mut self) -> io::Result<()> {
+        let (header, rest) = self
+            .input
+            .split_at_checked(HEADER_LEN)
+            .ok_or_else(|| invalid("truncated block header"))?;
+        if &header[..8] != MAGIC {
+            return Err(invalid("missing block magic"));
+        }
+        let int = |at: usize| u32::from_le_bytes(header[at..at + 4].try_into().unwrap());
+        let (compressed_len, len) = (int(9) as usize, int(13) as usize);
+        let checksum = int(17);
+        if compressed_len > MAX_BLOCK_LEN || len > MAX_BLOCK_LEN {
+            return Err(invalid("block too large"));
+        }
+        let (data, rest) = rest
+            .split_at_checked(compressed_len)
+            .ok_or_else(|| invalid("truncated block"))?;
+        self.input = rest;
+
+        self.block.clear();
+        self.pos = 0;
+        match header[8] & 0xF0 {
+            METHOD_RAW if compressed_len == len => self.block.extend_from_slice(data),
+            METHOD_LZ4 => decompress_block(data, &mut self.block, len)?,
+            _ => return Err(invalid("unknown block compression")),
+        }
+        if len == 0 {
+            self.done = true;
+        } else if XxHash32::oneshot(CHECKSUM_SEED, &self.block) & 0x0FFF_FFFF != checksum {
+            return Err(invalid("checksum mismatch"));
+        }
+        Ok(())
+    }
+}
+
+impl Read for BlockReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.pos == self.block.len() {
+            if self.done || buf.is_empty() {
+                return Ok(0);
+            }
+            self.next_block()?;
+        }
+        let len = buf.len().min(self.block.len() - self.pos);
+        buf[..len].copy_from_slice(&self.block[self.pos..self.pos + len]);
+        self.pos += len;
+        Ok(len)
+    }
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("lz4: {message}"))
+}
+
+/// Decompresses a raw LZ4 block, which has to decompress to exactly `len`
+/// bytes, appending them to `out`.
+fn decompress_block(input: &[u8], out: &mut Vec<u8>, len: usize) -> io::Result<()> {
+    let start = out.len();
+    out.resize(start + len, 0);
+    let written = lz4_flex::block::decompress_into(input, &mut out[start..])
+        .map_err(|err| invalid(&err.to_string()))?;
+    if written != len {
+        return Err(invalid("block shorter than declared"));
+    }
+    Ok(())
+}
+
+/// Compresses `data` into a stream [`BlockReader`] reads back.
+pub(crate) fn compress(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::new();
+    for input in data.chunks(WRITE_BLOCK_LEN).chain([&[][..]]) {
+        let block = lz4_flex::block::compress(input);
+        // blocks that don't get smaller are stored as they are, the low
+        // nibble of the method is the log2 of the block length minus 10
+        let (method, stored) = if block.len() < input.len() {
+            (METHOD_LZ4, &block[..])
+        } else {
+            (METHOD_RAW, input)
+        };
+        let checksum = if input.is_empty() {
+            0
+        } else {
+            XxHash32::oneshot(CHECKSUM_SEED, input) & 0x0FFF_FFFF
+        };
+        out.extend_from_slice(MAGIC);
+        out.push(method | 6);
+        for int in [stored.len() as u32, input.len() as u32, checksum] {
+            out.extend_from_slice(&int.to_le_bytes());
+        }
+        out.extend_from_slice(stored);
+    }
+    out
+}
@@ -0,0 +1,261 @@
+//! Point of interest region files, `poi/r.<x>.<z>.mca`, which hold the beds,
+//! workstations, bells and portals mobs look for.
+
+use std::{fs, path::Path};
+
+use nbt_rs::get_field as try_get_field;
+use nbt_rs::types::{NbtCompound, NbtList, NbtString, NbtTag};
+
+use crate::{
+    chunk::{ChunkParseError, ChunkParseOptions, parse_nbt_with},
+    entity::same_id,
+    raw::RawChunk,
+    region::{RegionOpenError, RegionParseError, RegionParseOptions, boxed_slots, chunk_range},
+    trace,
+};
+
+macro_rules! get_field {
+    ($input:ident @ $path:expr, $field:literal, $expected:literal, $($ty:ident).*) => {{
+        try_get_field!($input, $field, $($ty).*).ok_or_else(|| {
+            ChunkParseError::InvalidField(format!("{}{}", $path, $field), $expected)
+        })?
+    }};
+}
+
+/// A point of interest, like a bed or a composter.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct PoiRecord {
+    /// In world coordinates.
+    pub x: i32,
+    pub y: i32,
+    pub z: i32,
+    /// Like `minecraft:home` for beds or `minecraft:farmer` for composters.
+    pub ty: NbtString,
+    /// How many more mobs can claim it, a bed has one and a bell 32.
+    pub free_tickets: i32,
+}
+
+impl PoiRecord {
+    /// Whether the record has the type, a type without a namespace is in
+    /// `minecraft:`, like with [`crate::Entity::is`].
+    pub fn is(&self, ty: &str) -> bool {
+        same_id(&self.ty, ty)
+    }
+
+    /// Whether a mob can still claim it, like a job site without a villager
+    /// working there or a bed nobody sleeps in.
+    pub fn has_free_tickets(&self) -> bool {
+        self.free_tickets > 0
+    }
+}
+
+/// The records of one 16×16×16 section of a [`PoiChunk`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct PoiSection {
+    pub y: i32,
+    /// The section's `Valid` flag. The game clears it when it can't trust
+    /// the records anymore, and rebuilds them from the blocks the next time
+    /// the chunk loads. `false` if it isn't stored.
+    pub valid: bool,
+    /// In the order they're stored.
+    pub records: Vec<PoiRecord>,
+}
+
+/// The points of interest of one chunk in a POI region. Unlike terrain and
+/// entity chunks, these don't store their own position.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct PoiChunk {
+    pub(crate) data_version: i32,
+    // sorted by y
+    pub(crate) sections: Vec<PoiSection>,
+}
+
+impl PoiChunk {
+    pub fn data_version(&self) -> i32 {
+        self.data_version
+    }
+
+    /// The stored sections, ordered by `y`. Sections without any points of
+    /// interest are usually left out.
+    pub fn sections(&self) -> &[PoiSection] {
+        &self.sections
+    }
+
+    /// Takes a section y, i.e. a block y divided by 16.
+    pub fn get_section(&self, y: i32) -> Option<&PoiSection> {
+        let index = self.sections.binary_search_by_key(&y, |section| section.y);
+        Some(&self.sections[index.ok()?])
+    }
+
+    /// Every record, ordered by section.
+    pub fn records(&self) -> impl Iterator<Item = &PoiRecord> {
+        self.sections.iter().flat_map(|section| &section.records)
+    }
+
+    /// The records with the type, see [`PoiRecord::is`].
+    pub fn records_of_type<'a>(&'a self, ty: &'a str) -> impl Iterator<Item = &'a PoiRecord> {
+        self.records().filter(move |record| record.is(ty))
+    }
+
+    /// The sections whose `Valid` flag isn't set, see [`PoiSection::valid`].
+    pub fn stale_sections(&self) -> impl Iterator<Item = &PoiSection> {
+        self.sections.iter().filter(|section| !section.valid)
+    }
+}
+
+/// Like [`crate::Region`], but for POI region files.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct PoiRegion {
+    chunks: Box<[Option<PoiChunk>; 1024]>,
+}
+
+impl PoiRegion {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, RegionOpenError> {
+        Self::open_with(path, &RegionParseOptions::default())
+    }
+
+    pub fn open_with(
+        path: impl AsRef<Path>,
+        options: &RegionParseOptions,
+    ) -> Result<Self, RegionOpenError> {
+        let bytes = fs::read(path)?;
+        Ok(Self::parse_bytes_with(&bytes, options)?)
+    }
+
+    pub fn parse_bytes(bytes: &[u8]) -> Result<Self, RegionParseError> {
+        Self::parse_bytes_with(bytes, &RegionParseOptions::default())
+    }
+
+    /// Chunks that fail to decode are left out, or fail the whole region if
+    /// [`RegionParseOptions::strict`] is set. POI chunks don't store their
+    /// position, so [`RegionParseOptions::check_slots`] doesn't apply, and
+    /// neither do the status filter and block interning.
+    pub fn parse_bytes_with(
+        bytes: &[u8],
+        options: &RegionParseOptions,
+    ) -> Result<Self, RegionParseError> {
+        let mut chunks = boxed_slots();
+        let mut buffer = Vec::new();
+        for (index, entry) in options.header_entries(bytes)?.into_iter().enumerate() {
+            let Some((range, _)) = entry else {
+                continue;
+            };
+            let (x, z) = (index % 32, index / 32);
+            let range = chunk_range(bytes, range);
+            let end = range.end.min(bytes.len());
+            let chunk = (options.parse_raw(&bytes[range.start.min(end)..end], range.len()))
+                .and_then(|raw| decode_poi_chunk(&raw, &options.chunk, &mut buffer));
+            match chunk {
+                Ok(chunk) => chunks[index] = Some(chunk),
+                Err(source) => {
+                    trace::debug!(x, z, error = %source, "skipping poi chunk");
+                    if options.strict {
+                        return Err(RegionParseError::ChunkError { x, z, source });
+                    }
+                }
+            }
+        }
+        Ok(Self { chunks })
+    }
+
+    /// Returns `None` if the slot is empty, its chunk failed to decode, or
+    /// `x` or `z` is 32 or above.
+    pub fn get_chunk(&self, x: usize, z: usize) -> Option<&PoiChunk> {
+        if x >= 32 || z >= 32 {
+            return None;
+        }
+        self.chunks[x + z * 32].as_ref()
+    }
+
+    pub fn count_chunks(&self) -> u16 {
+        self.iter_chunks().count() as u16
+    }
+
+    /// The chunks in the region with their slot, `x` before `z`.
+    pub fn iter_chunks(&self) -> impl Iterator<Item = (usize, usize, &PoiChunk)> {
+        self.chunks
+            .iter()
+            .enumerate()
+            .filter_map(|(index, chunk)| Some((index % 32, index / 32, chunk.as_ref()?)))
+    }
+
+    /// Every record with the type in the region, with its world coordinates,
+    /// see [`PoiChunk::records_of_type`]. Chain
+    /// [`PoiRecord::has_free_tickets`] to only get unclaimed ones.
+    pub fn find<'a>(&'a self, ty: &'a str) -> impl Iterator<Item = (i32, i32, i32, &'a PoiRecord)> {
+        self.iter_chunks()
+            .flat_map(move |(_, _, chunk)| chunk.records_of_type(ty))
+            .map(|record| (record.x, record.y, record.z, record))
+    }
+
+    /// The sections marked invalid in the region with the slot of their
+    /// chunk, see [`PoiSection::valid`]. The game rebuilds them, but tools
+    /// reading the records should not trust them.
+    pub fn stale_sections(&self) -> impl Iterator<Item = (usize, usize, &PoiSection)> {
+        self.iter_chunks()
+            .flat_map(|(x, z, chunk)| chunk.stale_sections().map(move |section| (x, z, section)))
+    }
+}
+
+fn decode_poi_chunk(
+    raw: &RawChunk,
+    options: &ChunkParseOptions,
+    buffer: &mut Vec<u8>,
+) -> Result<PoiChunk, ChunkParseError> {
+    raw.decompress_into(options, buffer)?;
+    let decoded = parse_nbt_with(buffer, options)?;
+    parse_poi_chunk(&decoded)
+}
+
+fn parse_poi_chunk(decoded: &NbtCompound) -> Result<PoiChunk, ChunkParseError> {
+    let data_version = try_get_field!(decoded, "DataVersion", as_int).map_or(0, |&v| v);
+    let stored = get_field!(decoded @ "", "Sections", "compound", as_compound);
+
+    let stored: Vec<(NbtString, NbtTag)> = stored.clone().into();
+    let mut sections = Vec::new();
+    for (key, section) in stored {
+        let invalid = |expected| ChunkParseError::InvalidField(format!("Sections.{key}"), expected);
+        let y = key.parse().map_err(|_| invalid("section y"))?;
+        let section = section.as_compound().ok_or_else(|| invalid("compound"))?;
+        let valid = try_get_field!(section, "Valid", as_byte).is_some_and(|&valid| valid != 0);
+        let path = format!("Sections.{key}.");
+        let records: &[NbtCompound] = match section.get("Records") {
+            None | Some(NbtTag::List(NbtList::End)) => &[],
+            Some(tag) => tag
+                .as_list()
+                .and_then(|list| list.as_compound())
+                .ok_or_else(|| {
+                    ChunkParseError::InvalidField(format!("{path}Records"), "list of compounds")
+                })?,
+        };
+        let records = (records.iter().enumerate())
+            .map(|(i, record)| parse_record(record, &format!("{path}Records[{i}].")))
+            .collect::<Result<_, _>>()?;
+        sections.push(PoiSection { y, valid, records });
+    }
+    sections.sort_by_key(|section| section.y);
+
+    Ok(PoiChunk {
+        data_version,
+        sections,
+    })
+}
+
+fn parse_record(data: &NbtCompound, path: &str) -> Result<PoiRecord, ChunkParseError> {
+    let pos = get_field!(data @ path, "pos", "int array of 3", as_int_array);
+    let &[x, y, z] = &pos[..] else {
+        return Err(ChunkParseError::InvalidField(
+            format!("{path}pos"),
+            "int array of 3",
+        ));
+    };
+    let ty = get_field!(data @ path, "type", "string", as_string).clone();
+    let free_tickets = try_get_field!(data, "free_tickets", as_int).map_or(0, |&v| v);
+    Ok(PoiRecord {
+        x,
+        y,
+        z,
+        ty,
+        free_tickets,
+    })
+}
@@ -1,6 +1,37 @@
+use std::{
+    borrow::Cow,
+    cmp::Reverse,
+    collections::{BTreeMap, HashMap, btree_map},
+    ffi::OsString,
+    fs::{self, File},
+    io::{self, Read, Seek, SeekFrom, Write},
+    mem,
+    ops::Range,
+    path::Path,
+    process,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use flate2::read::GzDecoder;
+use nbt_rs::types::NbtString;
 use thiserror::Error;
 
-use crate::chunk::{Chunk, parse_chunk};
+use crate::{
+    block::{Block, BlockInterner, BlockSelector},
+    bounds::{Axis, OutOfBounds},
+    chunk::{Chunk, ChunkMetadata, ChunkParseError, ChunkParseOptions},
+    chunks::{
+        hash::ContentHasher,
+        section::Section,
+        structure::{BoundingBox, StructureStart},
+    },
+    compression::{CompressionType, WriteOptions},
+    coords::ChunkPos,
+    raw::RawChunk,
+    status::ChunkStatus,
+    trace,
+    validate::{HeaderIssue, ValidationFailed, ValidationIssue, ValidationOptions},
+};
 
 #[derive(Error, Debug)]
 pub enum RegionParseError {
@@ -9,67 +40,2172 @@ pub enum RegionParseError {
 
     #[error("input size ({0}) is not multiple of 4096")]
     InputInvalidSize(usize),
+
+    #[error("location table too short, expected 4096 bytes but got {0}")]
+    LocationsTooShort(usize),
+
+    /// The input starts like gzip, but doesn't decompress.
+    #[error("failed to decompress the gzip-wrapped region: {0}")]
+    GzipFailed(io::Error),
+
+    /// The input starts like gzip, but inflates to more than
+    /// [`MAX_REGION_LEN`], which no region can take.
+    #[error("the gzip-wrapped region decompresses to more than {MAX_REGION_LEN} bytes")]
+    GzipTooLarge,
+
+    #[error(
+        "chunks in slots ({}, {}) and ({}, {}) share sectors",
+        chunks.0.0,
+        chunks.0.1,
+        chunks.1.0,
+        chunks.1.1
+    )]
+    SectorOverlap {
+        chunks: ((usize, usize), (usize, usize)),
+    },
+
+    #[error("chunk ({x}, {z}): {source}")]
+    ChunkError {
+        x: usize,
+        z: usize,
+        source: ChunkParseError,
+    },
+}
+
+/// A chunk that doesn't fit in the 255 sectors the header of a region file
+/// can give it, see [`Region::to_bytes`].
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("chunk in slot ({x}, {z}) takes {sectors} sectors, more than the 255 a region allows")]
+pub struct ChunkTooLarge {
+    pub x: usize,
+    pub z: usize,
+    pub sectors: usize,
+}
+
+/// Failing to read a region from a file or reader, or to parse it.
+#[derive(Error, Debug)]
+pub enum RegionOpenError {
+    #[error("failed to read the region file: {0}")]
+    Io(#[from] io::Error),
+
+    #[error(transparent)]
+    Parse(#[from] RegionParseError),
+}
+
+/// The slot and error of every chunk that failed to decode, see
+/// [`Region::parse_bytes_lossy`].
+pub type ChunkErrors = Vec<(usize, usize, ChunkParseError)>;
+
+/// What became of a slot when parsing a region, see [`RegionReport`].
+#[derive(Debug)]
+pub enum SlotOutcome {
+    Parsed,
+    /// The header has no chunk for the slot.
+    Empty,
+    /// Left out by [`RegionParseOptions::status_filter`].
+    Skipped,
+    /// Left out for sharing sectors with another slot, see
+    /// [`DuplicateSectors`].
+    Overlapping,
+    /// Outside [`RegionParseOptions::chunk_range`].
+    OutsideRange,
+    Failed(ChunkParseError),
+}
+
+/// What became of every slot when parsing a region, see
+/// [`Region::parse_bytes_reported`].
+#[derive(Debug)]
+pub struct RegionReport {
+    // indexed by `x + z * 32`
+    slots: Vec<SlotOutcome>,
+}
+
+impl RegionReport {
+    /// Returns `None` if `x` or `z` is 32 or above.
+    pub fn get(&self, x: usize, z: usize) -> Option<&SlotOutcome> {
+        if x >= 32 || z >= 32 {
+            return None;
+        }
+        Some(&self.slots[x + z * 32])
+    }
+
+    /// Every slot with what became of it, `x` before `z`.
+    pub fn iter(&self) -> impl Iterator<Item = (usize, usize, &SlotOutcome)> {
+        (self.slots.iter().enumerate()).map(|(index, outcome)| (index % 32, index / 32, outcome))
+    }
+
+    /// The slots whose chunk failed to parse, with the error.
+    pub fn failed(&self) -> impl Iterator<Item = (usize, usize, &ChunkParseError)> {
+        self.iter().filter_map(|(x, z, outcome)| match outcome {
+            SlotOutcome::Failed(err) => Some((x, z, err)),
+            _ => None,
+        })
+    }
+
+    /// Whether no chunk failed to parse.
+    pub fn is_clean(&self) -> bool {
+        self.failed().next().is_none()
+    }
+
+    pub fn count_parsed(&self) -> usize {
+        self.count(|outcome| matches!(outcome, SlotOutcome::Parsed))
+    }
+
+    pub fn count_empty(&self) -> usize {
+        self.count(|outcome| matches!(outcome, SlotOutcome::Empty))
+    }
+
+    pub fn count_skipped(&self) -> usize {
+        self.count(|outcome| matches!(outcome, SlotOutcome::Skipped))
+    }
+
+    pub fn count_failed(&self) -> usize {
+        self.failed().count()
+    }
+
+    pub fn count_overlapping(&self) -> usize {
+        self.count(|outcome| matches!(outcome, SlotOutcome::Overlapping))
+    }
+
+    pub fn count_outside_range(&self) -> usize {
+        self.count(|outcome| matches!(outcome, SlotOutcome::OutsideRange))
+    }
+
+    fn count(&self, f: impl Fn(&SlotOutcome) -> bool) -> usize {
+        self.slots.iter().filter(|outcome| f(outcome)).count()
+    }
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum RegionBuildError {
+    #[error("more than one chunk was given for the slot ({0}, {1})")]
+    DuplicateSlot(u8, u8),
+
+    #[error("the slot is out of bounds: {0}")]
+    OutOfBounds(#[from] OutOfBounds),
+
+    #[error("the chunk at {pos:?} doesn't belong in the slot {slot:?}")]
+    SlotMismatch { slot: (u8, u8), pos: ChunkPos },
+}
+
+/// Which chunks to keep when parsing a region, by their generation status.
+/// Chunks without a `Status` field count as fully generated.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum StatusFilter {
+    #[default]
+    All,
+    OnlyFull,
+    /// Chunks that got at least to the given step. Statuses this crate
+    /// doesn't know are left out.
+    MinimumStatus(ChunkStatus),
+}
+
+impl StatusFilter {
+    pub fn matches(&self, status: &ChunkStatus) -> bool {
+        match self {
+            StatusFilter::All => true,
+            StatusFilter::OnlyFull => *status == ChunkStatus::Full,
+            StatusFilter::MinimumStatus(min) => {
+                !matches!(status, ChunkStatus::Other(_)) && status >= min
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RegionParseOptions {
+    /// The limits every chunk is parsed with.
+    pub chunk: ChunkParseOptions,
+    /// Chunks it rejects aren't decoded into the region. Their status is
+    /// checked before their sections are decoded. They keep their raw chunk
+    /// and timestamp, so writing the region back doesn't drop them.
+    pub status_filter: StatusFilter,
+    /// Fail with [`RegionParseError::ChunkError`] on the first chunk that
+    /// can't be decoded, instead of leaving it out.
+    pub strict: bool,
+    /// Treat chunks whose `xPos` and `zPos` don't match the slot they were
+    /// read from as failing with [`ChunkParseError::SlotMismatch`]. Only the
+    /// offset within the region is compared, since the region's position isn't
+    /// known while parsing. [`Region::validate`] reports the same without
+    /// leaving the chunks out.
+    pub check_slots: bool,
+    /// Share a single [`Shared`](crate::block::Shared) between all equal
+    /// blocks of the region, see [`BlockInterner`]. Otherwise blocks are
+    /// only shared within a section.
+    pub intern_blocks: bool,
+    /// Take all sectors of a chunk as its payload when the length it starts
+    /// with runs past them or leaves no payload, instead of failing with
+    /// [`ChunkParseError::LengthExceedsSectors`], see
+    /// [`RawChunk::parse_sectors`].
+    pub recover_lengths: bool,
+    /// What to do with slots sharing sectors. [`RegionFile`](crate::RegionFile)
+    /// reads every slot on its own, so it keeps them all.
+    pub duplicate_sectors: DuplicateSectors,
+    /// Only the slots with `x` in the first range and `z` in the second are
+    /// read, the others are left out as if the header had no chunk for them,
+    /// without decompressing them or reading their sectors from a seekable
+    /// reader. [`Region::count_present_in_header`] counts all of them.
+    /// Nothing is kept of the slots left out, so writing the region back
+    /// drops their chunks.
+    pub chunk_range: Option<(Range<usize>, Range<usize>)>,
+}
+
+impl RegionParseOptions {
+    /// The entries of the header, without the ones
+    /// [`RegionParseOptions::duplicate_sectors`] and
+    /// [`RegionParseOptions::chunk_range`] leave out.
+    pub(crate) fn header_entries(
+        &self,
+        bytes: &[u8],
+    ) -> Result<Vec<HeaderEntry>, RegionParseError> {
+        let mut entries: Vec<_> = header_entries(bytes)?.collect();
+        // sectors are claimed before the range leaves slots out, so which
+        // slot keeps them doesn't depend on it
+        self.drop_duplicates(&mut entries)?;
+        for (index, entry) in entries.iter_mut().enumerate() {
+            if !self.in_chunk_range(index) {
+                *entry = None;
+            }
+        }
+        Ok(entries)
+    }
+
+    /// Whether the slot at `index` is inside
+    /// [`RegionParseOptions::chunk_range`].
+    pub(crate) fn in_chunk_range(&self, index: usize) -> bool {
+        (self.chunk_range.as_ref())
+            .is_none_or(|(x, z)| x.contains(&(index % 32)) && z.contains(&(index / 32)))
+    }
+
+    fn drop_duplicates(&self, entries: &mut [HeaderEntry]) -> Result<(), RegionParseError> {
+        let order: Box<dyn Iterator<Item = usize>> = match self.duplicate_sectors {
+            DuplicateSectors::KeepAll => return Ok(()),
+            DuplicateSectors::FirstWins | DuplicateSectors::Error => Box::new(0..1024),
+            DuplicateSectors::LastWins => Box::new((0..1024).rev()),
+        };
+        let mut claimed: Vec<(Range<usize>, usize)> = Vec::new();
+        for index in order {
+            let Some((range, _)) = &entries[index] else {
+                continue;
+            };
+            let overlapping = (claimed.iter())
+                .find(|(other, _)| other.start < range.end && range.start < other.end);
+            match overlapping {
+                Some(&(_, other)) if self.duplicate_sectors == DuplicateSectors::Error => {
+                    let slot = |index: usize| (index % 32, index / 32);
+                    return Err(RegionParseError::SectorOverlap {
+                        chunks: (slot(other), slot(index)),
+                    });
+                }
+                Some(_) => entries[index] = None,
+                None => claimed.push((range.clone(), index)),
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads the raw chunk from the sectors of a slot, `allocated` bytes as
+    /// the header gives them.
+    pub(crate) fn parse_raw(
+        &self,
+        bytes: &[u8],
+        allocated: usize,
+    ) -> Result<RawChunk, ChunkParseError> {
+        RawChunk::parse_sectors(bytes, allocated, self.recover_lengths)
+    }
+
+    /// Like [`RegionParseOptions::parse_raw`], but the payload is a slice of
+    /// `bytes`.
+    #[cfg(feature = "bytes")]
+    pub(crate) fn parse_raw_shared(
+        &self,
+        bytes: &bytes::Bytes,
+        allocated: usize,
+    ) -> Result<RawChunk, ChunkParseError> {
+        RawChunk::parse_shared_sectors(bytes, allocated, self.recover_lengths)
+    }
+
+    /// Decodes the raw chunk read from the slot at `index`, or returns `None`
+    /// if the status filter leaves it out.
+    pub(crate) fn decode(
+        &self,
+        raw: &RawChunk,
+        index: usize,
+        scratch: &mut Scratch,
+    ) -> Result<Option<Chunk>, ChunkParseError> {
+        let keep = |status: &ChunkStatus| self.status_filter.matches(status);
+        let interner = scratch.interner.as_mut();
+        let Some(chunk) = raw.decode_if(&self.chunk, interner, &mut scratch.buffer, keep)? else {
+            trace::debug!(
+                x = index % 32,
+                z = index / 32,
+                "skipping chunk left out by the status filter"
+            );
+            return Ok(None);
+        };
+        let (slot, pos) = ((index % 32, index / 32), chunk.get_pos());
+        if self.check_slots && pos.region_local() != slot {
+            return Err(ChunkParseError::SlotMismatch { slot, pos });
+        }
+        Ok(Some(chunk))
+    }
+}
+
+/// What to do with slots whose header entries share sectors, as botched
+/// copies leave behind, see [`HeaderIssue::SectorOverlap`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum DuplicateSectors {
+    /// Every slot reads the shared sectors, like the game does.
+    #[default]
+    KeepAll,
+    /// The slot that comes first in the header keeps the sectors, the others
+    /// are empty.
+    FirstWins,
+    /// Like [`DuplicateSectors::FirstWins`], but for the slot that comes
+    /// last.
+    LastWins,
+    /// Fail with [`RegionParseError::SectorOverlap`].
+    Error,
+}
+
+/// How [`Region::count_blocks_with`] counts blocks. The counts are plain
+/// maps, which serialize with the `serde` feature, though formats like JSON
+/// that only take strings as keys need the entries as a list.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct BlockCountOptions {
+    /// Counts blocks of the same name together, under a block without
+    /// properties.
+    pub by_name: bool,
+}
+
+impl BlockCountOptions {
+    /// Turns the counts per block of a region into the ones asked for.
+    fn collect(&self, counts: HashMap<&Block, u64>) -> HashMap<Block, u64> {
+        let mut collected = HashMap::new();
+        for (block, count) in counts {
+            let block = match self.by_name {
+                true => Block::with_shared_name(block.name.clone(), None),
+                false => block.clone(),
+            };
+            *collected.entry(block).or_insert(0) += count;
+        }
+        collected
+    }
+}
+
+/// Which chunk a slot ends up with when merging regions, see
+/// [`MergePolicy::Custom`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MergeDecision {
+    KeepSelf,
+    TakeOther,
+}
+
+/// What one region holds in a slot being merged, see
+/// [`MergePolicy::Custom`].
+#[derive(Debug, Clone, Copy)]
+pub struct MergeSlot<'a> {
+    /// The decoded chunk, `None` for a chunk that's only kept raw.
+    pub chunk: Option<&'a Chunk>,
+    /// The raw chunk the slot was read from, `None` once it's dirty.
+    pub raw: Option<&'a RawChunk>,
+    /// The slot's timestamp, see [`Region::get_timestamp`].
+    pub timestamp: Option<u32>,
+}
+
+/// Picks the chunk of a slot from the slot and what both regions hold in it,
+/// see [`MergePolicy::Custom`].
+pub type MergeFn<'a> = dyn Fn(u8, u8, Option<MergeSlot>, Option<MergeSlot>) -> MergeDecision + 'a;
+
+/// How [`Region::merge`] picks between the chunks of a slot. Chunks that are
+/// only kept raw count like decoded ones. Except for [`MergePolicy::Custom`],
+/// only slots where both regions have a chunk are up to the policy, the
+/// other slots keep whichever chunk there is.
+pub enum MergePolicy<'a> {
+    /// The other region's chunks replace this one's.
+    TakeOther,
+    /// This region's chunks stay, the other region only fills empty slots.
+    KeepSelf,
+    /// The chunk with the later timestamp, this region's on a tie.
+    TakeNewer,
+    /// Called for every slot where either region has a chunk. Taking an
+    /// empty slot empties it.
+    Custom(Box<MergeFn<'a>>),
+}
+
+impl MergePolicy<'_> {
+    fn decide(&self, this: &Region, other: &Region, index: usize) -> MergeDecision {
+        let (x, z) = ((index % 32) as u8, (index / 32) as u8);
+        match (self, this.merge_slot(index), other.merge_slot(index)) {
+            (_, None, None) => MergeDecision::KeepSelf,
+            (MergePolicy::Custom(f), ours, theirs) => f(x, z, ours, theirs),
+            (_, _, None) => MergeDecision::KeepSelf,
+            (_, None, Some(_)) | (MergePolicy::TakeOther, ..) => MergeDecision::TakeOther,
+            (MergePolicy::KeepSelf, ..) => MergeDecision::KeepSelf,
+            (MergePolicy::TakeNewer, Some(ours), Some(theirs))
+                if theirs.timestamp > ours.timestamp =>
+            {
+                MergeDecision::TakeOther
+            }
+            (MergePolicy::TakeNewer, ..) => MergeDecision::KeepSelf,
+        }
+    }
+}
+
+/// How [`Region::write_to_file_with`] writes a file.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FileWriteOptions {
+    /// How the chunks that get encoded are compressed.
+    pub chunks: WriteOptions,
+    /// Replace a file that's already at the path. Otherwise writing fails
+    /// with [`io::ErrorKind::AlreadyExists`].
+    pub overwrite: bool,
+    pub modified: ModifiedTime,
+    /// Validate the region with [`Region::validate_with`] first, and fail
+    /// with [`io::ErrorKind::InvalidData`] holding a [`ValidationFailed`]
+    /// instead of writing if there are any issues.
+    pub validate: Option<ValidationOptions>,
+}
+
+/// What the modification time of a written region file is set to.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum ModifiedTime {
+    /// The time it's written at.
+    #[default]
+    Now,
+    /// That of the file it replaces, if there is one.
+    KeepOriginal,
+    /// The newest timestamp of any chunk, if there is one.
+    NewestChunk,
+}
+
+/// Which chunks come first in [`Region::iter_chunks_by_timestamp`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum TimestampOrder {
+    #[default]
+    NewestFirst,
+    OldestFirst,
+}
+
+/// What's reused from one chunk to the next when decoding a region.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Scratch {
+    // only if the options ask for it
+    pub(crate) interner: Option<BlockInterner>,
+    pub(crate) buffer: Vec<u8>,
+}
+
+impl Scratch {
+    pub(crate) fn new(options: &RegionParseOptions) -> Self {
+        Self {
+            interner: options.intern_blocks.then(BlockInterner::new),
+            buffer: Vec::new(),
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, PartialOrd)]
 pub struct Region {
-    chunks: [Option<Chunk>; 1024],
+    // keyed by `x + z * 32`, so a region with a few chunks takes little
+    // memory, and moving one copies no slots
+    pub(crate) raw_chunks: BTreeMap<usize, RawChunk>,
+    pub(crate) chunks: BTreeMap<usize, Chunk>,
+    pub(crate) timestamps: BTreeMap<usize, u32>,
+    pub(crate) skipped: u16,
+    pub(crate) position: Option<(i32, i32)>,
 }
 
 impl Region {
+    /// Reads the whole file at `path` and parses it like
+    /// [`Region::parse_maybe_compressed`], so `.mca.gz` backups open too.
+    ///
+    /// With the `mmap` feature the file is memory-mapped instead of read
+    /// into memory first. The file mustn't be truncated while it's being
+    /// parsed then, which would crash the process instead of failing.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, RegionOpenError> {
+        Self::open_with(path, &RegionParseOptions::default())
+    }
+
+    pub fn open_with(
+        path: impl AsRef<Path>,
+        options: &RegionParseOptions,
+    ) -> Result<Self, RegionOpenError> {
+        let bytes = read_file(path.as_ref())?;
+        Ok(Self::parse_bytes_with(
+            &gunzip_if_wrapped(&bytes)?,
+            options,
+        )?)
+    }
+
+    /// Like [`Region::open`], but reads the file with [`tokio::fs`] and
+    /// parses it on tokio's blocking thread pool, keeping the runtime's
+    /// worker threads free.
+    #[cfg(feature = "tokio")]
+    pub async fn open_async(path: impl AsRef<Path>) -> Result<Self, RegionOpenError> {
+        Self::open_async_with(path, &RegionParseOptions::default()).await
+    }
+
+    #[cfg(feature = "tokio")]
+    pub async fn open_async_with(
+        path: impl AsRef<Path>,
+        options: &RegionParseOptions,
+    ) -> Result<Self, RegionOpenError> {
+        let bytes = tokio::fs::read(path).await?;
+        let options = options.clone();
+        let parse = move || Self::parse_bytes_with(&gunzip_if_wrapped(&bytes)?, &options);
+        Ok(spawn_parse(parse).await?)
+    }
+
+    /// Reads everything left in `reader` and parses it like
+    /// [`Region::parse_maybe_compressed`].
+    pub fn from_reader(reader: impl Read) -> Result<Self, RegionOpenError> {
+        Self::from_reader_with(reader, &RegionParseOptions::default())
+    }
+
+    pub fn from_reader_with(
+        mut reader: impl Read,
+        options: &RegionParseOptions,
+    ) -> Result<Self, RegionOpenError> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+        Ok(Self::parse_bytes_with(
+            &gunzip_if_wrapped(&bytes)?,
+            options,
+        )?)
+    }
+
+    /// Like [`Region::from_reader`], but only reads the header and the
+    /// sectors of the chunks in it, seeking over everything else. The
+    /// reader's length is checked like in [`Region::parse_bytes`]. Gzip
+    /// can't be seeked in, so the reader must hold the region as is.
+    pub fn from_seekable(reader: impl Read + Seek) -> Result<Self, RegionOpenError> {
+        Self::from_seekable_with(reader, &RegionParseOptions::default())
+    }
+
+    pub fn from_seekable_with(
+        mut reader: impl Read + Seek,
+        options: &RegionParseOptions,
+    ) -> Result<Self, RegionOpenError> {
+        let len = reader.seek(SeekFrom::End(0))? as usize;
+        check_len(len)?;
+        let mut header = [0; 8192];
+        reader.seek(SeekFrom::Start(0))?;
+        reader.read_exact(&mut header)?;
+
+        let mut region = Region::empty();
+        let mut scratch = Scratch::new(options);
+        for (index, entry) in options.header_entries(&header)?.into_iter().enumerate() {
+            let Some((range, timestamp)) = entry else {
+                continue;
+            };
+            let range = read_chunk_range(&mut reader, range)?;
+            let bytes = read_range(&mut reader, range.clone())?;
+            region.insert_parsed(
+                index,
+                options.parse_raw(&bytes, range.len()),
+                timestamp,
+                options,
+                &mut scratch,
+                &mut |x, z, source| {
+                    if options.strict {
+                        return Err(RegionParseError::ChunkError { x, z, source });
+                    }
+                    Ok(())
+                },
+            )?;
+        }
+        Ok(region)
+    }
+
+    /// Parses a whole region file. Malformed input of any kind, from bad
+    /// header offsets to corrupt chunk nbt, is reported as an error and
+    /// never panics, also through `Region::try_from`.
     pub fn parse_bytes(bytes: &[u8]) -> Result<Self, RegionParseError> {
-        let len = bytes.len();
-        if len < 8192 {
-            return Err(RegionParseError::InputTooShort(len));
+        Self::parse_bytes_with(bytes, &RegionParseOptions::default())
+    }
+
+    /// Like [`Region::parse_bytes`], but first decompresses `bytes` if they
+    /// start with the gzip magic, as whole regions backed up as `.mca.gz`
+    /// do. The size checks apply to the decompressed region.
+    pub fn parse_maybe_compressed(bytes: &[u8]) -> Result<Self, RegionParseError> {
+        Self::parse_bytes(&gunzip_if_wrapped(bytes)?)
+    }
+
+    /// Like [`Region::parse_bytes`], but sets the region's position, see
+    /// [`Region::set_position`].
+    pub fn parse_bytes_at(bytes: &[u8], x: i32, z: i32) -> Result<Self, RegionParseError> {
+        let mut region = Self::parse_bytes(bytes)?;
+        region.set_position(x, z);
+        Ok(region)
+    }
+
+    /// Chunks that fail to decode are left out, or fail the whole region if
+    /// [`RegionParseOptions::strict`] is set.
+    pub fn parse_bytes_with(
+        bytes: &[u8],
+        options: &RegionParseOptions,
+    ) -> Result<Self, RegionParseError> {
+        let read_raw =
+            |sectors: Range<usize>, allocated| options.parse_raw(&bytes[sectors], allocated);
+        Self::parse_reporting(bytes, options, read_raw, |x, z, source| {
+            if options.strict {
+                return Err(RegionParseError::ChunkError { x, z, source });
+            }
+            Ok(())
+        })
+    }
+
+    /// Like [`Region::parse_bytes`], but the raw chunks kept for writing the
+    /// region back are slices of `bytes` rather than copies. They keep all
+    /// of `bytes` alive for as long as they're there.
+    #[cfg(feature = "bytes")]
+    pub fn parse_shared(bytes: bytes::Bytes) -> Result<Self, RegionParseError> {
+        Self::parse_shared_with(bytes, &RegionParseOptions::default())
+    }
+
+    /// Like [`Region::parse_bytes_with`], but keeps slices of `bytes` like
+    /// [`Region::parse_shared`].
+    #[cfg(feature = "bytes")]
+    pub fn parse_shared_with(
+        bytes: bytes::Bytes,
+        options: &RegionParseOptions,
+    ) -> Result<Self, RegionParseError> {
+        let read_raw = |sectors: Range<usize>, allocated| {
+            options.parse_raw_shared(&bytes.slice(sectors), allocated)
+        };
+        Self::parse_reporting(&bytes, options, read_raw, |x, z, source| {
+            if options.strict {
+                return Err(RegionParseError::ChunkError { x, z, source });
+            }
+            Ok(())
+        })
+    }
+
+    /// Like [`Region::parse_bytes_with`], but never fails because of a chunk,
+    /// instead returning the slot and error of every chunk that failed to
+    /// decode. [`RegionParseOptions::strict`] is ignored.
+    pub fn parse_bytes_lossy(
+        bytes: &[u8],
+        options: &RegionParseOptions,
+    ) -> Result<(Self, ChunkErrors), RegionParseError> {
+        let mut errors = Vec::new();
+        let read_raw =
+            |sectors: Range<usize>, allocated| options.parse_raw(&bytes[sectors], allocated);
+        let region = Self::parse_reporting(bytes, options, read_raw, |x, z, err| {
+            errors.push((x, z, err));
+            Ok(())
+        })?;
+        Ok((region, errors))
+    }
+
+    /// Like [`Region::parse_bytes_lossy`], but with what became of every
+    /// slot rather than only the errors.
+    pub fn parse_bytes_reported(
+        bytes: &[u8],
+        options: &RegionParseOptions,
+    ) -> Result<(Self, RegionReport), RegionParseError> {
+        let (region, errors) = Self::parse_bytes_lossy(bytes, options)?;
+        let claimed = options.header_entries(bytes)?;
+        let mut slots: Vec<_> = (header_entries(bytes)?.zip(claimed).enumerate())
+            .map(|(index, entry)| match entry {
+                (None, _) => SlotOutcome::Empty,
+                _ if !options.in_chunk_range(index) => SlotOutcome::OutsideRange,
+                (Some(_), None) => SlotOutcome::Overlapping,
+                _ if region.chunks.contains_key(&index) => SlotOutcome::Parsed,
+                // failed ones are filled in below
+                _ => SlotOutcome::Skipped,
+            })
+            .collect();
+        for (x, z, err) in errors {
+            slots[x + z * 32] = SlotOutcome::Failed(err);
         }
-        if !len.is_multiple_of(4096) {
-            return Err(RegionParseError::InputInvalidSize(len));
+        Ok((region, RegionReport { slots }))
+    }
+
+    /// Parses the region in `bytes`, reading the raw chunk of each slot with
+    /// `read_raw` from the sectors it's in and how many bytes the header
+    /// gives them.
+    fn parse_reporting(
+        bytes: &[u8],
+        options: &RegionParseOptions,
+        read_raw: impl Fn(Range<usize>, usize) -> Result<RawChunk, ChunkParseError>,
+        mut report: impl FnMut(usize, usize, ChunkParseError) -> Result<(), RegionParseError>,
+    ) -> Result<Self, RegionParseError> {
+        #[cfg(feature = "tracing")]
+        let (span, start) = (
+            tracing::info_span!(
+                "parse_region",
+                len = bytes.len(),
+                chunks = tracing::field::Empty,
+                failed = tracing::field::Empty,
+                skipped = tracing::field::Empty,
+                elapsed_us = tracing::field::Empty,
+            )
+            .entered(),
+            std::time::Instant::now(),
+        );
+
+        let mut region = Region::empty();
+        let mut scratch = Scratch::new(options);
+        for (index, entry) in options.header_entries(bytes)?.into_iter().enumerate() {
+            let Some((range, timestamp)) = entry else {
+                continue;
+            };
+            let range = chunk_range(bytes, range);
+            // a chunk running past the end of the file is cut short there,
+            // which only fails if its data doesn't fit
+            let end = range.end.min(bytes.len());
+            let raw = read_raw(range.start.min(end)..end, range.len());
+            region.insert_parsed(index, raw, timestamp, options, &mut scratch, &mut report)?;
         }
 
-        let locations = &bytes[0..4096];
-        let timestamps = &bytes[4096..8192];
+        #[cfg(feature = "tracing")]
+        {
+            let present = options.header_entries(bytes)?.into_iter().flatten().count();
+            let decoded = region.count_chunks();
+            span.record("chunks", decoded);
+            span.record("failed", present - (decoded + region.skipped) as usize);
+            span.record("skipped", region.skipped);
+            span.record("elapsed_us", start.elapsed().as_micros() as u64);
+        }
 
-        // the alignment is the same, only the structure changes
-        let locations = unsafe { &*(locations.as_ptr() as *const [[u8; 4]; 1024]) };
-        let timestamps = unsafe { &*(timestamps.as_ptr() as *const [[u8; 4]; 1024]) };
+        Ok(region)
+    }
 
-        let chunks: Vec<Option<Chunk>> = locations
-            .iter()
-            .zip(timestamps.iter())
-            .map(|(&location, &timestamp)| {
-                let timestamp = u32::from_be_bytes(timestamp);
-                let sector_count: u8 = location[3];
-                let offset = ((location[0] as u32) << 16)
-                    | ((location[1] as u32) << 8)
-                    | (location[2] as u32);
-
-                if offset == 0 && sector_count == 0 && timestamp == 0 {
-                    return None;
+    /// Decodes the chunk read from slot `index` and puts it there, or counts
+    /// it as skipped. `raw` is what was read from the slot's sectors.
+    fn insert_parsed(
+        &mut self,
+        index: usize,
+        raw: Result<RawChunk, ChunkParseError>,
+        timestamp: u32,
+        options: &RegionParseOptions,
+        scratch: &mut Scratch,
+        report: &mut impl FnMut(usize, usize, ChunkParseError) -> Result<(), RegionParseError>,
+    ) -> Result<(), RegionParseError> {
+        let (x, z) = (index % 32, index / 32);
+        if timestamp == 0 {
+            trace::debug!(x, z, "chunk has a zero timestamp");
+        }
+
+        let mut raw = match raw {
+            Ok(raw) => raw,
+            Err(err) => {
+                trace::debug!(x, z, error = %err, "skipping chunk");
+                return report(x, z, err);
+            }
+        };
+        raw.timestamp = timestamp;
+        match options.decode(&raw, index, scratch) {
+            Ok(Some(chunk)) => {
+                self.chunks.insert(index, chunk);
+            }
+            Ok(None) => self.skipped += 1,
+            Err(err) => {
+                trace::debug!(x, z, error = %err, "skipping chunk");
+                report(x, z, err)?;
+            }
+        }
+        // chunks that were skipped or failed to decode keep their raw chunk,
+        // so writing the region back doesn't drop them
+        self.raw_chunks.insert(index, raw);
+        self.timestamps.insert(index, timestamp);
+        Ok(())
+    }
+
+    /// Reads only the [`ChunkMetadata`] of every chunk, indexed by `x + z *
+    /// 32`. Slots that are empty or whose chunk fails to decode are `None`.
+    pub fn parse_metadata(
+        bytes: &[u8],
+    ) -> Result<Box<[Option<ChunkMetadata>; 1024]>, RegionParseError> {
+        let options = ChunkParseOptions::default();
+        let mut table = boxed_slots();
+        let mut buffer = Vec::new();
+        for (index, entry) in header_entries(bytes)?.enumerate() {
+            let Some((range, _)) = entry else {
+                continue;
+            };
+            let range = chunk_range(bytes, range);
+            let end = range.end.min(bytes.len());
+            table[index] =
+                RawChunk::parse_sectors(&bytes[range.start.min(end)..end], range.len(), false)
+                    .and_then(|raw| raw.decode_metadata_with_buffer(&options, &mut buffer))
+                    .ok();
+        }
+        Ok(table)
+    }
+
+    /// Which slots of the header hold a chunk, as bit `x + z * 32` counting
+    /// from the lowest bit of the first word. Only the first 8192 bytes are
+    /// read, so `header` can be just those, for example from a ranged read
+    /// of a remote file. Nothing is checked against the rest of the file.
+    pub fn presence_map(header: &[u8]) -> Result<[u64; 16], RegionParseError> {
+        let header = header
+            .get(..8192)
+            .ok_or(RegionParseError::InputTooShort(header.len()))?;
+        let mut map = [0; 16];
+        for (index, entry) in header_entries(header)?.enumerate() {
+            if entry.is_some() {
+                map[index / 64] |= 1 << (index % 64);
+            }
+        }
+        Ok(map)
+    }
+
+    /// How many slots of the header hold a chunk, also those a
+    /// [`RegionParseOptions::chunk_range`] leaves out of the parsed region.
+    /// Only the first 8192 bytes are read, like in [`Region::presence_map`].
+    pub fn count_present_in_header(header: &[u8]) -> Result<usize, RegionParseError> {
+        let map = Self::presence_map(header)?;
+        Ok(map.iter().map(|word| word.count_ones() as usize).sum())
+    }
+
+    /// The slots holding chunks from the location table alone, the first
+    /// 4096 bytes of a region file, as `(min_x, min_z, max_x, max_z)` with
+    /// the maxima included. `None` if no slot has a location.
+    pub fn bounding_box_from_header(
+        header: &[u8],
+    ) -> Result<Option<(usize, usize, usize, usize)>, RegionParseError> {
+        let locations = header
+            .get(..4096)
+            .ok_or(RegionParseError::LocationsTooShort(header.len()))?;
+        let present = (locations.chunks_exact(4).enumerate())
+            .filter(|(_, location)| location.iter().any(|&byte| byte != 0))
+            .map(|(index, _)| index);
+        Ok(bounding_box(present))
+    }
+
+    /// A region without any chunks, to fill with [`Region::set_chunk`] and
+    /// write with [`Region::to_bytes`]. See [`RegionBuilder`] to build one
+    /// from chunks in one go.
+    pub fn empty() -> Self {
+        Self {
+            raw_chunks: BTreeMap::new(),
+            chunks: BTreeMap::new(),
+            timestamps: BTreeMap::new(),
+            skipped: 0,
+            position: None,
+        }
+    }
+
+    /// Builds a region out of chunks and the slots they go in, see
+    /// [`RegionBuilder`] for more control.
+    pub fn from_chunks(
+        chunks: impl IntoIterator<Item = ((u8, u8), Chunk)>,
+    ) -> Result<Region, RegionBuildError> {
+        chunks
+            .into_iter()
+            .fold(RegionBuilder::new(), |builder, ((x, z), chunk)| {
+                builder.chunk(x, z, chunk)
+            })
+            .build()
+    }
+
+    /// Writes the region in the Anvil format, with the chunks one after
+    /// another from the third sector on, each padded to whole sectors.
+    ///
+    /// The raw chunks are written as they are. Chunks without a raw chunk,
+    /// like the ones from [`RegionBuilder`] or the ones [`Region::map_chunks`]
+    /// changed, are encoded with [`Chunk::to_bytes`], after their heightmaps
+    /// are computed again if they're fully generated, see
+    /// [`WriteOptions::compute_heightmaps`]. Fails on the first chunk that
+    /// doesn't fit in 255 sectors, which the game would move to a separate
+    /// file.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, ChunkTooLarge> {
+        self.to_bytes_with(&WriteOptions::default())
+    }
+
+    /// Like [`Region::to_bytes`], but the chunks that are encoded are
+    /// compressed as the options say. Raw chunks are still written as they
+    /// are.
+    pub fn to_bytes_with(&self, options: &WriteOptions) -> Result<Vec<u8>, ChunkTooLarge> {
+        let mut bytes = vec![0u8; 8192];
+        for index in 0..1024 {
+            let encoded;
+            let raw = match (self.raw_chunks.get(&index), self.chunks.get(&index)) {
+                (Some(raw), _) => raw,
+                (None, Some(chunk))
+                    if options.compute_heightmaps && *chunk.status() == ChunkStatus::Full =>
+                {
+                    let mut chunk = chunk.clone();
+                    chunk.compute_heightmaps();
+                    encoded = chunk.to_raw_with(options);
+                    &encoded
+                }
+                (None, Some(chunk)) => {
+                    encoded = chunk.to_raw_with(options);
+                    &encoded
                 }
+                (None, None) => continue,
+            };
+            let offset = bytes.len() >> 12;
+            raw.write_to(&mut bytes);
+            bytes.resize(bytes.len().next_multiple_of(4096), 0);
+            let sectors = (bytes.len() >> 12) - offset;
+            if sectors > 255 {
+                return Err(ChunkTooLarge {
+                    x: index % 32,
+                    z: index / 32,
+                    sectors,
+                });
+            }
 
-                let offset = (offset as usize) << 12;
-                parse_chunk(&bytes[offset..offset + ((sector_count as usize) << 12)])
-                    // TODO: proper error handling
-                    .ok()
+            let location = (offset as u32) << 8 | sectors as u32;
+            bytes[index * 4..index * 4 + 4].copy_from_slice(&location.to_be_bytes());
+            let timestamp = self
+                .timestamps
+                .get(&index)
+                .copied()
+                .unwrap_or(raw.timestamp);
+            bytes[4096 + index * 4..4096 + index * 4 + 4].copy_from_slice(&timestamp.to_be_bytes());
+        }
+        Ok(bytes)
+    }
+
+    /// About how long [`Region::to_bytes`] is, without compressing any
+    /// chunks, see [`Chunk::estimate_serialized_size`].
+    pub fn estimate_file_size(&self) -> usize {
+        self.estimate_file_size_with(&WriteOptions::default())
+    }
+
+    /// Like [`Region::estimate_file_size`], but for
+    /// [`Region::to_bytes_with`]. Chunks kept as read are counted exactly.
+    pub fn estimate_file_size_with(&self, options: &WriteOptions) -> usize {
+        let mut size = 8192;
+        for index in 0..1024 {
+            let len = match (self.raw_chunks.get(&index), self.chunks.get(&index)) {
+                (Some(raw), _) => raw.data.len() + 5,
+                (None, Some(chunk)) => chunk.estimate_serialized_size(options.compression),
+                (None, None) => continue,
+            };
+            size += len.next_multiple_of(4096);
+        }
+        size
+    }
+
+    /// Writes the region to the file at `path` like [`Region::to_bytes`],
+    /// without replacing a file that's already there, see
+    /// [`Region::write_to_file_with`].
+    pub fn write_to_file(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        self.write_to_file_with(path, &FileWriteOptions::default())
+    }
+
+    /// Writes the region to a temporary file next to `path`, syncs it to
+    /// disk, and renames it to `path`, so a crash leaves either the old file
+    /// or the new one, never a partly written one. The rename replaces an
+    /// existing file on Windows as well. Whether a file is already there is
+    /// checked before writing, so one created meanwhile may still be replaced
+    /// without [`FileWriteOptions::overwrite`]. A chunk too large for the
+    /// region fails with [`io::ErrorKind::InvalidData`] holding a
+    /// [`ChunkTooLarge`], before anything is written.
+    pub fn write_to_file_with(
+        &self,
+        path: impl AsRef<Path>,
+        options: &FileWriteOptions,
+    ) -> io::Result<()> {
+        let path = path.as_ref();
+        let original = match fs::metadata(path) {
+            Ok(_) if !options.overwrite => {
+                return Err(io::Error::new(
+                    io::ErrorKind::AlreadyExists,
+                    format!("{} already exists", path.display()),
+                ));
+            }
+            Ok(metadata) => Some(metadata),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => None,
+            Err(error) => return Err(error),
+        };
+        let modified = match options.modified {
+            ModifiedTime::Now => None,
+            ModifiedTime::KeepOriginal => {
+                original.map(|metadata| metadata.modified()).transpose()?
+            }
+            ModifiedTime::NewestChunk => (self.newest_timestamp())
+                .map(|timestamp| UNIX_EPOCH + Duration::from_secs(timestamp as u64)),
+        };
+
+        if let Some(validation) = &options.validate {
+            let issues = self.validate_with(validation);
+            if !issues.is_empty() {
+                let error = ValidationFailed { issues };
+                return Err(io::Error::new(io::ErrorKind::InvalidData, error));
+            }
+        }
+
+        let name = path.file_name().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "the path has no file name")
+        })?;
+        let mut temp_name = OsString::from(".");
+        temp_name.push(name);
+        temp_name.push(format!(".{}.tmp", process::id()));
+        let temp = path.with_file_name(temp_name);
+
+        let bytes = (self.to_bytes_with(&options.chunks))
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+        let written = File::create_new(&temp).and_then(|mut file| {
+            file.write_all(&bytes)?;
+            if let Some(modified) = modified {
+                file.set_modified(modified)?;
+            }
+            file.sync_all()
+        });
+        if let Err(error) = written.and_then(|()| fs::rename(&temp, path)) {
+            let _ = fs::remove_file(&temp);
+            return Err(error);
+        }
+        // the rename itself is only durable once the directory is synced,
+        // which windows doesn't allow
+        #[cfg(unix)]
+        {
+            let dir = path.parent().filter(|dir| !dir.as_os_str().is_empty());
+            File::open(dir.unwrap_or(Path::new(".")))?.sync_all()?;
+        }
+        Ok(())
+    }
+
+    /// Validates every chunk, and checks that chunks sit in the slot matching
+    /// their position, and in this region if its position is set. Issues are
+    /// paired with the slot of their chunk.
+    pub fn validate(&self) -> Vec<((usize, usize), ValidationIssue)> {
+        self.validate_with(&ValidationOptions::default())
+    }
+
+    /// Like [`Region::validate`], but validates the chunks with
+    /// [`Chunk::validate_with`].
+    pub fn validate_with(
+        &self,
+        options: &ValidationOptions,
+    ) -> Vec<((usize, usize), ValidationIssue)> {
+        let mut issues = Vec::new();
+        for (&index, chunk) in &self.chunks {
+            let slot = (index % 32, index / 32);
+
+            let pos = chunk.get_pos();
+            let elsewhere = self
+                .position
+                .is_some_and(|position| pos.region() != position.into());
+            if pos.region_local() != slot || elsewhere {
+                issues.push((slot, ValidationIssue::SlotMismatch { slot, pos }));
+            }
+            let chunk_issues = chunk.validate_with(options);
+            issues.extend(chunk_issues.into_iter().map(|issue| (slot, issue)));
+        }
+        issues
+    }
+
+    /// Replaces every chunk with the result of `f`, which gets the chunk's
+    /// slot in the region. Timestamps are kept. Chunks that come back
+    /// changed lose their raw chunk, so [`Region::to_bytes`] encodes them
+    /// again, while unchanged ones keep it and are written back byte for
+    /// byte. To tell them apart, chunks that have a raw chunk are cloned
+    /// before `f` gets them.
+    pub fn map_chunks(self, mut f: impl FnMut(u8, u8, Chunk) -> Chunk) -> Region {
+        self.filter_map_chunks(|x, z, chunk| Some(f(x, z, chunk)))
+    }
+
+    /// Like [`Region::map_chunks`], but slots for which `f` returns `None` are
+    /// emptied, including their raw chunk and timestamp.
+    pub fn filter_map_chunks(
+        mut self,
+        mut f: impl FnMut(u8, u8, Chunk) -> Option<Chunk>,
+    ) -> Region {
+        for (index, chunk) in mem::take(&mut self.chunks) {
+            let before = self.raw_chunks.contains_key(&index).then(|| chunk.clone());
+            let mapped = f((index % 32) as u8, (index / 32) as u8, chunk);
+            self.put_mapped(index, before, mapped);
+        }
+        self
+    }
+
+    /// Like [`Region::map_chunks`], but runs `f` on rayon's thread pool, in
+    /// no particular order.
+    #[cfg(feature = "rayon")]
+    pub fn par_map_chunks(self, f: impl Fn(u8, u8, Chunk) -> Chunk + Sync) -> Region {
+        self.par_filter_map_chunks(|x, z, chunk| Some(f(x, z, chunk)))
+    }
+
+    /// Like [`Region::filter_map_chunks`], but runs `f` on rayon's thread
+    /// pool, in no particular order.
+    #[cfg(feature = "rayon")]
+    pub fn par_filter_map_chunks(
+        mut self,
+        f: impl Fn(u8, u8, Chunk) -> Option<Chunk> + Sync,
+    ) -> Region {
+        use rayon::prelude::*;
+
+        let mapped: Vec<_> = (mem::take(&mut self.chunks).into_par_iter())
+            .map(|(index, chunk)| {
+                let before = self.raw_chunks.contains_key(&index).then(|| chunk.clone());
+                let mapped = f((index % 32) as u8, (index / 32) as u8, chunk);
+                (index, before, mapped)
             })
             .collect();
+        for (index, before, chunk) in mapped {
+            self.put_mapped(index, before, chunk);
+        }
+        self
+    }
 
-        Ok(Self {
-            // chunks is always 1024 long, since both of the iters are 1024
-            chunks: unsafe { chunks.try_into().unwrap_unchecked() },
+    /// Puts a chunk returned by the closure of [`Region::filter_map_chunks`]
+    /// back in its slot, dropping its raw chunk unless it's equal to the
+    /// chunk `before` it was mapped, or empties the slot for `None`.
+    fn put_mapped(&mut self, index: usize, before: Option<Chunk>, chunk: Option<Chunk>) {
+        match chunk {
+            Some(chunk) => {
+                if before.as_ref() != Some(&chunk) {
+                    self.raw_chunks.remove(&index);
+                }
+                self.chunks.insert(index, chunk);
+            }
+            None => {
+                self.raw_chunks.remove(&index);
+                self.timestamps.remove(&index);
+            }
+        }
+    }
+
+    /// Keeps only the chunks for which `f` returns `true`, like
+    /// [`Vec::retain`]. The other slots are emptied, including their raw
+    /// chunk and timestamp.
+    pub fn retain_chunks(&mut self, mut f: impl FnMut(u8, u8, &Chunk) -> bool) {
+        self.chunks.retain(|&index, chunk| {
+            let keep = f((index % 32) as u8, (index / 32) as u8, chunk);
+            if !keep {
+                self.raw_chunks.remove(&index);
+                self.timestamps.remove(&index);
+            }
+            keep
+        });
+    }
+
+    /// What the slot holds for [`Region::merge`], `None` if it's empty.
+    fn merge_slot(&self, index: usize) -> Option<MergeSlot<'_>> {
+        let chunk = self.chunks.get(&index);
+        let raw = self.raw_chunks.get(&index);
+        (chunk.is_some() || raw.is_some()).then(|| MergeSlot {
+            chunk,
+            raw,
+            timestamp: self.timestamps.get(&index).copied(),
         })
     }
 
+    /// Overlays `other` on the region, slot by slot, with `policy` picking
+    /// the chunk each slot keeps. A slot that takes the other region's chunk
+    /// also takes its raw chunk and timestamp. Chunks keep their own
+    /// position, see [`Chunk::relocate`] to move them.
+    pub fn merge(&mut self, other: &Region, policy: MergePolicy) {
+        for index in 0..1024 {
+            if policy.decide(self, other, index) == MergeDecision::TakeOther {
+                replace_slot(&mut self.chunks, index, other.chunks.get(&index).cloned());
+                replace_slot(
+                    &mut self.raw_chunks,
+                    index,
+                    other.raw_chunks.get(&index).cloned(),
+                );
+                replace_slot(
+                    &mut self.timestamps,
+                    index,
+                    other.timestamps.get(&index).copied(),
+                );
+            }
+        }
+    }
+
+    /// Like [`Region::merge`], but moves the chunks out of `other` instead of
+    /// cloning them.
+    pub fn merge_from(&mut self, mut other: Region, policy: MergePolicy) {
+        for index in 0..1024 {
+            if policy.decide(self, &other, index) == MergeDecision::TakeOther {
+                replace_slot(&mut self.chunks, index, other.chunks.remove(&index));
+                replace_slot(&mut self.raw_chunks, index, other.raw_chunks.remove(&index));
+                replace_slot(&mut self.timestamps, index, other.timestamps.remove(&index));
+            }
+        }
+    }
+
+    /// How many of each block all chunks hold together, see
+    /// [`Chunk::block_counts`].
+    pub fn block_counts(&self) -> HashMap<&Block, u32> {
+        let mut counts = HashMap::new();
+        for (_, _, chunk) in self.iter_chunks() {
+            for (block, count) in chunk.block_counts() {
+                *counts.entry(block).or_insert(0) += count;
+            }
+        }
+        counts
+    }
+
+    /// How many blocks `filter` picks all chunks hold together, or of every
+    /// block without one, keyed by the whole block state. Counted per palette
+    /// entry, and sections whose palette has nothing `filter` picks are
+    /// skipped. See [`Region::count_blocks_with`] to count by name.
+    pub fn count_blocks(&self, filter: Option<&BlockSelector>) -> HashMap<Block, u64> {
+        self.count_blocks_with(filter, &BlockCountOptions::default())
+    }
+
+    /// Like [`Region::count_blocks`], but counted as the options say.
+    pub fn count_blocks_with(
+        &self,
+        filter: Option<&BlockSelector>,
+        options: &BlockCountOptions,
+    ) -> HashMap<Block, u64> {
+        let mut counts = HashMap::new();
+        for (_, _, chunk) in self.iter_chunks() {
+            count_chunk_blocks(chunk, filter, &mut counts);
+        }
+        options.collect(counts)
+    }
+
+    /// Like [`Region::count_blocks`], but counts the chunks on rayon's
+    /// thread pool.
+    #[cfg(feature = "rayon")]
+    pub fn par_count_blocks(&self, filter: Option<&BlockSelector>) -> HashMap<Block, u64> {
+        self.par_count_blocks_with(filter, &BlockCountOptions::default())
+    }
+
+    /// Like [`Region::count_blocks_with`], but counts the chunks on rayon's
+    /// thread pool.
+    #[cfg(feature = "rayon")]
+    pub fn par_count_blocks_with(
+        &self,
+        filter: Option<&BlockSelector>,
+        options: &BlockCountOptions,
+    ) -> HashMap<Block, u64> {
+        use rayon::prelude::*;
+
+        let counts = (self.chunks.par_iter())
+            .fold(HashMap::new, |mut counts, (_, chunk)| {
+                count_chunk_blocks(chunk, filter, &mut counts);
+                counts
+            })
+            .reduce(HashMap::new, |mut counts, other| {
+                for (block, count) in other {
+                    *counts.entry(block).or_insert(0) += count;
+                }
+                counts
+            });
+        options.collect(counts)
+    }
+
+    /// Like [`Chunk::find_blocks`], but for all chunks, in world coordinates
+    /// taken from [`Region::iter_chunks_world`].
+    pub fn find_blocks(&self, predicate: impl Fn(&Block) -> bool) -> Vec<(i32, i32, i32)> {
+        self.iter_chunks_world()
+            .flat_map(|(pos, chunk)| {
+                let min = pos.min_block(0);
+                chunk
+                    .find_blocks(&predicate)
+                    .into_iter()
+                    .map(move |(x, y, z)| (min.x + x as i32, y, min.z + z as i32))
+            })
+            .collect()
+    }
+
+    /// How many 4x4x4 cells of each biome all chunks hold together, see
+    /// [`Chunk::biome_counts`].
+    pub fn biome_counts(&self) -> HashMap<&NbtString, u32> {
+        let mut counts = HashMap::new();
+        for (_, _, chunk) in self.iter_chunks() {
+            for (biome, count) in chunk.biome_counts() {
+                *counts.entry(biome).or_insert(0) += count;
+            }
+        }
+        counts
+    }
+
+    /// Like [`Chunk::find_biome`], but for all chunks, in world coordinates
+    /// taken from [`Region::iter_chunks_world`].
+    pub fn find_biome(&self, name: &str) -> Vec<(i32, i32, i32)> {
+        self.iter_chunks_world()
+            .flat_map(|(pos, chunk)| {
+                let min = pos.min_block(0);
+                chunk
+                    .find_biome(name)
+                    .into_iter()
+                    .map(move |(x, y, z)| (min.x + x as i32, y, min.z + z as i32))
+            })
+            .collect()
+    }
+
+    /// Iterates over all blocks of all chunks, in world coordinates taken
+    /// from [`Region::iter_chunks_world`]. Set the region's position with
+    /// [`Region::set_position`] to have them follow from its slots.
+    pub fn iter_blocks(&self) -> impl Iterator<Item = (i32, i32, i32, &Block)> {
+        self.iter_blocks_in_sections(|_, _| true)
+    }
+
+    /// Like [`Region::iter_blocks`], but only in the sections `keep` returns
+    /// `true` for, given the section y and the section. The others are
+    /// skipped without looking at their blocks.
+    pub fn iter_blocks_in_sections(
+        &self,
+        keep: impl Fn(i32, &Section) -> bool,
+    ) -> impl Iterator<Item = (i32, i32, i32, &Block)> {
+        self.iter_chunks_world().flat_map(move |(pos, chunk)| {
+            let min = pos.min_block(0);
+            let sections: Vec<_> = (chunk.sections())
+                .filter(|&(section_y, section)| keep(section_y, section))
+                .collect();
+            sections.into_iter().flat_map(move |(section_y, section)| {
+                section.iter_blocks().map(move |(x, y, z, block)| {
+                    let y = section_y * 16 + y as i32;
+                    (min.x + x as i32, y, min.z + z as i32, block)
+                })
+            })
+        })
+    }
+
+    /// Iterates over the non-air blocks of all chunks, in world coordinates
+    /// taken from [`Region::iter_chunks_world`].
+    pub fn iter_blocks_non_air(&self) -> impl Iterator<Item = (i32, i32, i32, &Block)> {
+        self.iter_chunks_world().flat_map(|(pos, chunk)| {
+            let min = pos.min_block(0);
+            chunk
+                .iter_blocks_non_air()
+                .map(move |(x, y, z, block)| (min.x + x as i32, y, min.z + z as i32, block))
+        })
+    }
+
+    /// Combines the [`Chunk::content_hash`] of every chunk with its slot,
+    /// and is just as stable.
+    pub fn content_hash(&self) -> u64 {
+        let mut hasher = ContentHasher::new();
+        for (&index, chunk) in &self.chunks {
+            hasher.write_u64(index as u64);
+            hasher.write_u64(chunk.content_hash());
+        }
+        hasher.finish()
+    }
+
+    /// How many chunks were left out by [`RegionParseOptions::status_filter`].
+    pub fn count_skipped(&self) -> u16 {
+        self.skipped
+    }
+
+    /// The chunks decoded into the region, without those left out while
+    /// parsing, see [`Region::count_present_in_header`].
     pub fn count_chunks(&self) -> u16 {
-        1024 - self.chunks.iter().filter(|&chunk| chunk.is_none()).count() as u16
+        self.chunks.len() as u16
+    }
+
+    /// The chunks in the region with their slot, `x` before `z`, as they're
+    /// laid out in the header.
+    pub fn iter_chunks(&self) -> impl Iterator<Item = (usize, usize, &Chunk)> {
+        (self.chunks.iter()).map(|(&index, chunk)| (index % 32, index / 32, chunk))
+    }
+
+    /// Like [`Region::iter_chunks`], but with the world position of each
+    /// chunk. It's taken from the region's position if that's set, and from
+    /// the chunk's own `xPos` and `zPos` otherwise.
+    pub fn iter_chunks_world(&self) -> impl Iterator<Item = (ChunkPos, &Chunk)> {
+        self.iter_chunks().map(|(x, z, chunk)| {
+            let pos = self.chunk_pos(x, z).unwrap_or_else(|| chunk.get_pos());
+            (pos, chunk)
+        })
+    }
+
+    /// The structures starting in the region's chunks, with the slot of the
+    /// chunk each starts in, see [`Chunk::structure_starts`].
+    pub fn structures(&self) -> impl Iterator<Item = (usize, usize, &StructureStart)> {
+        self.iter_chunks().flat_map(|(x, z, chunk)| {
+            (chunk.structure_starts().iter()).map(move |start| (x, z, start))
+        })
+    }
+
+    /// Like [`Region::structures`], but only those whose box shares a block
+    /// with `bounding_box`. Starts without a box are left out.
+    pub fn structures_intersecting(
+        &self,
+        bounding_box: BoundingBox,
+    ) -> impl Iterator<Item = (usize, usize, &StructureStart)> {
+        self.structures().filter(move |(_, _, start)| {
+            (start.bounding_box).is_some_and(|start| start.intersects(&bounding_box))
+        })
+    }
+
+    /// The position of the region, as in its `r.<x>.<z>.mca` file name, see
+    /// [`Region::set_position`].
+    pub fn position(&self) -> Option<(i32, i32)> {
+        self.position
+    }
+
+    /// Sets where the region is in the world, so its slots can be turned
+    /// into world positions. [`Region::validate`] then also reports chunks
+    /// that claim to be in another region.
+    pub fn set_position(&mut self, x: i32, z: i32) {
+        self.position = Some((x, z));
+    }
+
+    /// The world position of the slot, or `None` if the region's position
+    /// isn't set, `x` or `z` is 32 or above, or the region is too far out
+    /// for its chunks to have an i32 position.
+    pub fn chunk_pos(&self, x: usize, z: usize) -> Option<ChunkPos> {
+        let (region_x, region_z) = self.position?;
+        if x >= 32 || z >= 32 {
+            return None;
+        }
+        // a multiple of 32 plus less than 32 stays in range
+        Some(ChunkPos::new(
+            region_x.checked_mul(32)? + x as i32,
+            region_z.checked_mul(32)? + z as i32,
+        ))
+    }
+
+    /// Like [`Region::iter_chunks`], but moves the chunks out of the region,
+    /// like iterating over the region itself. See [`Region::remove_chunk`]
+    /// to move out a single chunk.
+    pub fn into_chunks(self) -> IntoChunks {
+        IntoChunks(self.chunks.into_iter())
     }
 
+    /// Returns `None` if the chunk is missing, failed to decode, or `x` or
+    /// `z` is 32 or above.
     pub fn get_chunk(&self, x: usize, z: usize) -> Option<&Chunk> {
         if x >= 32 || z >= 32 {
             return None;
         }
 
         let index = x + z * 32;
-        self.chunks[index].as_ref()
+        self.chunks.get(&index)
+    }
+
+    /// The slots holding chunks, counted like [`Region::chunk_exists`], as
+    /// `(min_x, min_z, max_x, max_z)` with the maxima included. `None` if
+    /// the region is empty.
+    pub fn bounding_box(&self) -> Option<(usize, usize, usize, usize)> {
+        bounding_box(self.chunks.keys().chain(self.raw_chunks.keys()).copied())
+    }
+
+    /// Whether the slot holds a chunk, including one that failed to decode
+    /// but was kept raw. Returns `false` if `x` or `z` is 32 or above.
+    pub fn chunk_exists(&self, x: usize, z: usize) -> bool {
+        if x >= 32 || z >= 32 {
+            return false;
+        }
+
+        let index = x + z * 32;
+        self.chunks.contains_key(&index) || self.raw_chunks.contains_key(&index)
+    }
+
+    /// The slots holding a chunk without the raw chunk it was read from, `x`
+    /// before `z`, which [`Region::to_bytes`] has to encode again. Slots
+    /// become dirty through [`Region::get_chunk_mut`], [`Region::set_chunk`]
+    /// and chunks [`Region::map_chunks`] changed, and all of a region built
+    /// from chunks are. The others are written byte for byte as they
+    /// were read.
+    pub fn dirty_chunks(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        (self.chunks.keys())
+            .filter(|index| !self.raw_chunks.contains_key(index))
+            .map(|&index| (index % 32, index / 32))
     }
+
+    /// Whether the slot is one of [`Region::dirty_chunks`]. Returns `false`
+    /// if `x` or `z` is 32 or above.
+    pub fn is_dirty(&self, x: usize, z: usize) -> bool {
+        if x >= 32 || z >= 32 {
+            return false;
+        }
+
+        let index = x + z * 32;
+        self.chunks.contains_key(&index) && !self.raw_chunks.contains_key(&index)
+    }
+
+    /// Like [`Region::get_chunk`], but the raw chunk of the slot is dropped,
+    /// since it may no longer match. [`Region::to_bytes`] then encodes the
+    /// chunk instead.
+    pub fn get_chunk_mut(&mut self, x: usize, z: usize) -> Option<&mut Chunk> {
+        if x >= 32 || z >= 32 {
+            return None;
+        }
+
+        let index = x + z * 32;
+        let chunk = self.chunks.get_mut(&index)?;
+        self.raw_chunks.remove(&index);
+        Some(chunk)
+    }
+
+    /// Puts `chunk` in the slot and returns the chunk that was there. The raw
+    /// chunk of the slot is dropped, and its timestamp is kept or set to 0
+    /// for a slot that was empty. The chunk's own position isn't checked.
+    pub fn set_chunk(
+        &mut self,
+        x: usize,
+        z: usize,
+        chunk: Chunk,
+    ) -> Result<Option<Chunk>, OutOfBounds> {
+        OutOfBounds::check(Axis::X, x as i64, 0..32)?;
+        OutOfBounds::check(Axis::Z, z as i64, 0..32)?;
+
+        let index = x + z * 32;
+        self.raw_chunks.remove(&index);
+        self.timestamps.entry(index).or_insert(0);
+        Ok(self.chunks.insert(index, chunk))
+    }
+
+    /// Puts the raw chunk in the slot as it is, to be written by
+    /// [`Region::to_bytes`] without being decoded or compressed again, and
+    /// returns the raw chunk that was there. The slot's decoded chunk is
+    /// dropped, and its timestamp is set to the raw chunk's.
+    pub fn set_chunk_raw(
+        &mut self,
+        x: usize,
+        z: usize,
+        raw: RawChunk,
+    ) -> Result<Option<RawChunk>, OutOfBounds> {
+        OutOfBounds::check(Axis::X, x as i64, 0..32)?;
+        OutOfBounds::check(Axis::Z, z as i64, 0..32)?;
+
+        let index = x + z * 32;
+        self.chunks.remove(&index);
+        self.timestamps.insert(index, raw.timestamp);
+        Ok(self.raw_chunks.insert(index, raw))
+    }
+
+    /// Empties the slot, including its raw chunk and timestamp, and returns
+    /// the chunk that was there. Returns `None` if there was none, or if `x`
+    /// or `z` is 32 or above.
+    pub fn remove_chunk(&mut self, x: usize, z: usize) -> Option<Chunk> {
+        if x >= 32 || z >= 32 {
+            return None;
+        }
+
+        let index = x + z * 32;
+        self.raw_chunks.remove(&index);
+        self.timestamps.remove(&index);
+        self.chunks.remove(&index)
+    }
+
+    /// Like [`Region::get_chunk`], but takes the chunk's world position. Only
+    /// its offset in the region is used, it isn't checked whether `pos`
+    /// actually lies in this region.
+    pub fn get_chunk_at(&self, pos: impl Into<ChunkPos>) -> Option<&Chunk> {
+        let (x, z) = pos.into().region_local();
+        self.get_chunk(x, z)
+    }
+
+    /// The last modification time of the chunk, in seconds since the unix
+    /// epoch. Returns `None` for empty slots, or if `x` or `z` is 32 or above.
+    pub fn get_timestamp(&self, x: usize, z: usize) -> Option<u32> {
+        if x >= 32 || z >= 32 {
+            return None;
+        }
+
+        self.timestamps.get(&(x + z * 32)).copied()
+    }
+
+    /// The timestamp of every slot that has one, `x` before `z`, including
+    /// chunks that failed to decode.
+    pub fn iter_timestamps(&self) -> impl Iterator<Item = (usize, usize, u32)> + '_ {
+        (self.timestamps.iter()).map(|(&index, &timestamp)| (index % 32, index / 32, timestamp))
+    }
+
+    /// Like [`Region::get_timestamp`], but as a [`SystemTime`].
+    pub fn get_timestamp_time(&self, x: usize, z: usize) -> Option<SystemTime> {
+        let timestamp = self.get_timestamp(x, z)?;
+        Some(UNIX_EPOCH + Duration::from_secs(timestamp as u64))
+    }
+
+    /// Sets the last modification time of the chunk, as written by
+    /// [`Region::to_bytes`]. It's cut to whole seconds, and times before
+    /// 1970 or after 2106, which the header can't hold, are clamped.
+    /// Returns `false` without setting anything for empty slots, which have
+    /// no timestamp, or if `x` or `z` is 32 or above.
+    pub fn set_timestamp(&mut self, x: usize, z: usize, time: SystemTime) -> bool {
+        if x >= 32 || z >= 32 {
+            return false;
+        }
+
+        let Some(timestamp) = self.timestamps.get_mut(&(x + z * 32)) else {
+            return false;
+        };
+        *timestamp = epoch_seconds(time).min(u32::MAX as u64) as u32;
+        true
+    }
+
+    /// The chunks whose timestamp is at or after `time`, as
+    /// [`Region::iter_chunks`] lists them. Timestamps only have whole
+    /// seconds, so a chunk written in the same second as `time` counts,
+    /// even if it was before.
+    pub fn chunks_modified_since(
+        &self,
+        time: SystemTime,
+    ) -> impl Iterator<Item = (usize, usize, &Chunk)> {
+        let since = epoch_seconds(time);
+        self.iter_chunks().filter(move |&(x, z, _)| {
+            self.get_timestamp(x, z)
+                .is_some_and(|timestamp| timestamp as u64 >= since)
+        })
+    }
+
+    /// The chunks with their timestamp, sorted by it in `order`. Chunks with
+    /// the same timestamp are listed as [`Region::iter_chunks`] lists them.
+    pub fn iter_chunks_by_timestamp(
+        &self,
+        order: TimestampOrder,
+    ) -> impl Iterator<Item = (usize, usize, u32, &Chunk)> {
+        let mut chunks: Vec<_> = (self.iter_chunks())
+            .map(|(x, z, chunk)| (x, z, self.get_timestamp(x, z).unwrap_or(0), chunk))
+            .collect();
+        // the sort is stable, so ties keep their slot order
+        match order {
+            TimestampOrder::NewestFirst => {
+                chunks.sort_by_key(|&(.., timestamp, _)| Reverse(timestamp))
+            }
+            TimestampOrder::OldestFirst => chunks.sort_by_key(|&(.., timestamp, _)| timestamp),
+        }
+        chunks.into_iter()
+    }
+
+    /// The latest timestamp of any slot, including chunks that failed to
+    /// decode, or `None` if the region is empty.
+    pub fn newest_timestamp(&self) -> Option<u32> {
+        self.timestamps.values().copied().max()
+    }
+
+    /// Like [`Region::newest_timestamp`], but the earliest.
+    pub fn oldest_timestamp(&self) -> Option<u32> {
+        self.timestamps.values().copied().min()
+    }
+
+    /// How many chunks are stored with each compression, counting those that
+    /// failed to decode. The compression and compressed size of single
+    /// chunks are on their [`RawChunk`], see [`Region::get_chunk_raw`].
+    /// Chunks changed since they were read have no raw chunk until they're
+    /// written, and aren't counted.
+    pub fn compression_summary(&self) -> BTreeMap<CompressionType, usize> {
+        let mut counts = BTreeMap::new();
+        for raw in self.raw_chunks.values() {
+            *counts.entry(raw.compression()).or_insert(0) += 1;
+        }
+        counts
+    }
+
+    /// The chunk as stored in the region file, also available when it
+    /// failed to decode.
+    pub fn get_chunk_raw(&self, x: usize, z: usize) -> Option<&RawChunk> {
+        if x >= 32 || z >= 32 {
+            return None;
+        }
+
+        let index = x + z * 32;
+        self.raw_chunks.get(&index)
+    }
+}
+
+/// Parses a whole region file, see [`Region::parse_bytes`]. Never panics,
+/// whatever the input.
+impl TryFrom<&[u8]> for Region {
+    type Error = RegionParseError;
+
+    fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+        Self::parse_bytes(bytes)
+    }
+}
+
+impl IntoIterator for Region {
+    type Item = (usize, usize, Chunk);
+    type IntoIter = IntoChunks;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.into_chunks()
+    }
+}
+
+/// The chunks of a region with their slot, moved out of it, see
+/// [`Region::into_chunks`].
+#[derive(Debug)]
+pub struct IntoChunks(btree_map::IntoIter<usize, Chunk>);
+
+impl Iterator for IntoChunks {
+    type Item = (usize, usize, Chunk);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (index, chunk) = self.0.next()?;
+        Some((index % 32, index / 32, chunk))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+/// Whole seconds since the unix epoch, 0 for times before it.
+pub(crate) fn epoch_seconds(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs())
+}
+
+/// Assembles a [`Region`] from decoded chunks. Such a region has no raw
+/// chunks, and chunks without a timestamp get 0.
+#[derive(Debug, Clone, Default)]
+pub struct RegionBuilder {
+    chunks: Vec<(u8, u8, Chunk)>,
+    timestamps: Vec<(u8, u8, u32)>,
+    position: Option<(i32, i32)>,
+}
+
+impl RegionBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn chunk(mut self, x: u8, z: u8, chunk: Chunk) -> Self {
+        self.chunks.push((x, z, chunk));
+        self
+    }
+
+    /// Sets the timestamp of the chunk in the slot, it's dropped if the slot
+    /// stays empty.
+    pub fn timestamp(mut self, x: u8, z: u8, timestamp: u32) -> Self {
+        self.timestamps.push((x, z, timestamp));
+        self
+    }
+
+    /// The position of the region, as in its `r.<x>.<z>.mca` file name. If
+    /// set, each chunk's position has to match its slot in this region.
+    pub fn position(mut self, x: i32, z: i32) -> Self {
+        self.position = Some((x, z));
+        self
+    }
+
+    pub fn build(self) -> Result<Region, RegionBuildError> {
+        let mut region = Region::empty();
+        region.position = self.position;
+
+        for (x, z, chunk) in self.chunks {
+            let index = slot_index(x, z)?;
+            if let Some(position) = self.position {
+                let pos = chunk.get_pos();
+                if pos.region() != position.into() || pos.region_local() != (x as usize, z as usize)
+                {
+                    return Err(RegionBuildError::SlotMismatch { slot: (x, z), pos });
+                }
+            }
+            if region.chunks.contains_key(&index) {
+                return Err(RegionBuildError::DuplicateSlot(x, z));
+            }
+            region.chunks.insert(index, chunk);
+            region.timestamps.insert(index, 0);
+        }
+
+        for (x, z, timestamp) in self.timestamps {
+            let index = slot_index(x, z)?;
+            if region.chunks.contains_key(&index) {
+                region.timestamps.insert(index, timestamp);
+            }
+        }
+
+        Ok(region)
+    }
+}
+
+/// Parses the position of a region from its file name, like `(-3, 7)` from
+/// `r.-3.7.mca`. Returns `None` for anything else, including whole paths.
+pub fn region_coords_from_filename(name: &str) -> Option<(i32, i32)> {
+    let coords = name.strip_prefix("r.")?.strip_suffix(".mca")?;
+    let (x, z) = coords.split_once('.')?;
+    Some((parse_coord(x)?, parse_coord(z)?))
+}
+
+fn parse_coord(coord: &str) -> Option<i32> {
+    // `parse` would also take a leading `+`
+    let digits = coord.strip_prefix('-').unwrap_or(coord);
+    if digits.is_empty() || !digits.bytes().all(|byte| byte.is_ascii_digit()) {
+        return None;
+    }
+    coord.parse().ok()
+}
+
+/// How much of a region file holds data, see [`fragmentation_stats`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct FragmentationStats {
+    /// The sectors of 4096 bytes in the file, including the two of the
+    /// header.
+    pub total_sectors: usize,
+    /// The sectors the header and the chunks need, each chunk taking as many
+    /// as its length requires.
+    pub used_sectors: usize,
+    /// Sectors no chunk needs: gaps between chunks, sectors allocated past
+    /// the end of a chunk's data, and chunks that can't be read.
+    pub wasted_sectors: usize,
+}
+
+/// Compares the sectors allocated in a region file's header with the length
+/// each chunk gives itself, without decoding any chunk. [`Region::to_bytes`]
+/// writes files that waste no sectors.
+pub fn fragmentation_stats(bytes: &[u8]) -> Result<FragmentationStats, RegionParseError> {
+    let mut used_sectors = 2;
+    for (range, _) in header_entries(bytes)?.flatten() {
+        let Some(header) = bytes.get(range.start..).and_then(|rest| rest.first_chunk()) else {
+            continue;
+        };
+        let needed = (u32::from_be_bytes(*header) as usize)
+            .saturating_add(4)
+            .div_ceil(4096);
+        used_sectors += needed.min(range.len() >> 12);
+    }
+
+    let total_sectors = bytes.len() >> 12;
+    Ok(FragmentationStats {
+        total_sectors,
+        used_sectors,
+        wasted_sectors: total_sectors.saturating_sub(used_sectors),
+    })
+}
+
+/// Where a chunk is stored in a region file, see [`storage_stats`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ChunkStorage {
+    /// The slot, `(x, z)` in the region.
+    pub slot: (usize, usize),
+    /// The first sector of the chunk, counting the two of the header.
+    pub sector_offset: usize,
+    pub sector_count: usize,
+    /// The length the chunk gives itself in its first 4 bytes, which counts
+    /// the compression byte and the compressed data. `None` if the chunk has
+    /// no sectors, or they're in the header or past the end of the file.
+    pub length: Option<u32>,
+    /// The compression byte as stored, with `0x80` set for chunks kept in
+    /// an external file. `None` when `length` is.
+    pub compression: Option<u8>,
+}
+
+impl ChunkStorage {
+    /// The bytes of the chunk's sectors past the end of its data, 0 if the
+    /// data doesn't fit. `None` if the length isn't known.
+    pub fn padding(&self) -> Option<usize> {
+        let length = self.length? as usize;
+        Some((self.sector_count << 12).saturating_sub(length.saturating_add(4)))
+    }
+}
+
+/// How the space of a region file is spent, see [`storage_stats`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct RegionStats {
+    /// Every slot with an offset in the header, in slot order. Slots with
+    /// only a timestamp or a sector count are left out.
+    pub chunks: Vec<ChunkStorage>,
+    /// The sectors of all chunks together.
+    pub allocated_sectors: usize,
+    /// The lengths of all chunks together.
+    pub data_bytes: u64,
+    /// The padding of all chunks together.
+    pub padding_bytes: u64,
+    /// The size the header implies the file has, up to the end of the last
+    /// sector of any chunk, and at least the 8192 bytes of the header.
+    pub implied_size: usize,
+    /// The sectors of the file past the header that no chunk is allocated,
+    /// in order.
+    pub orphan_sectors: Vec<usize>,
+}
+
+/// Reads where every chunk of a region file is stored from the header and
+/// the first 5 bytes of each chunk, without decompressing any.
+/// [`crate::RegionFile::storage_stats`] does the same reading only those
+/// parts of a file.
+pub fn storage_stats(bytes: &[u8]) -> Result<RegionStats, RegionParseError> {
+    region_stats(&bytes[..bytes.len().min(8192)], bytes.len(), |start| {
+        bytes.get(start..)?.first_chunk().copied()
+    })
+}
+
+/// The stats of a region file of `len` bytes with the given header, with
+/// `chunk_header` reading the first 5 bytes of a chunk from its byte offset.
+pub(crate) fn region_stats(
+    header: &[u8],
+    len: usize,
+    mut chunk_header: impl FnMut(usize) -> Option<[u8; 5]>,
+) -> Result<RegionStats, RegionParseError> {
+    check_len(len)?;
+    let file_sectors = len >> 12;
+    let mut referenced = vec![false; file_sectors];
+    let mut stats = RegionStats {
+        implied_size: 8192,
+        ..RegionStats::default()
+    };
+    for (index, entry) in header_entries(header)?.enumerate() {
+        let Some((range, _)) = entry else {
+            continue;
+        };
+        let (offset, count) = (range.start >> 12, range.len() >> 12);
+        let end = (offset + count).min(file_sectors);
+        if let Some(sectors) = referenced.get_mut(offset.max(2)..end) {
+            sectors.fill(true);
+        }
+        let head = (offset >= 2 && count > 0)
+            .then(|| chunk_header(range.start))
+            .flatten();
+        let chunk = ChunkStorage {
+            slot: (index % 32, index / 32),
+            sector_offset: offset,
+            sector_count: count,
+            length: head.map(|head| u32::from_be_bytes([head[0], head[1], head[2], head[3]])),
+            compression: head.map(|head| head[4]),
+        };
+
+        stats.allocated_sectors += count;
+        stats.data_bytes += chunk.length.unwrap_or(0) as u64;
+        stats.padding_bytes += chunk.padding().unwrap_or(0) as u64;
+        stats.implied_size = stats.implied_size.max(range.end);
+        stats.chunks.push(chunk);
+    }
+    stats.orphan_sectors = (2..file_sectors)
+        .filter(|&sector| !referenced[sector])
+        .collect();
+    Ok(stats)
+}
+
+/// Checks the location of every chunk in a region file's header against the
+/// file and each other, without decoding any chunk. Issues of single chunks
+/// come in slot order, followed by the overlapping pairs. Parsing the region
+/// doesn't fail on any of these, only the affected chunks do.
+pub fn validate_header(bytes: &[u8]) -> Result<Vec<HeaderIssue>, RegionParseError> {
+    let mut issues = Vec::new();
+    let mut allocated = Vec::new();
+    for (index, entry) in header_entries(bytes)?.enumerate() {
+        let slot = (index % 32, index / 32);
+        let Some((range, _)) = entry else {
+            let at = index * 4;
+            if bytes[at + 3] != 0 || bytes[4096 + at..4096 + at + 4] != [0; 4] {
+                issues.push(HeaderIssue::MissingOffset { slot });
+            }
+            continue;
+        };
+        if range.start < 8192 {
+            issues.push(HeaderIssue::OffsetIntoHeader { slot });
+            continue;
+        }
+        if range.start >= bytes.len() {
+            issues.push(HeaderIssue::OffsetPastEof { slot });
+            continue;
+        }
+        if range.end > bytes.len() {
+            issues.push(HeaderIssue::SectorsPastEof { slot });
+        }
+        if let Some(header) = bytes[range.start..].first_chunk() {
+            let length = (u32::from_be_bytes(*header) as usize).saturating_add(4);
+            let sectors = range.len() >> 12;
+            if length > range.len() {
+                issues.push(HeaderIssue::DeclaredLengthExceedsSectors {
+                    slot,
+                    length,
+                    sectors,
+                });
+            }
+        }
+        if !range.is_empty() {
+            allocated.push((range, slot));
+        }
+    }
+
+    allocated.sort_by_key(|(range, _)| range.start);
+    for (i, (range, slot)) in allocated.iter().enumerate() {
+        for (_, other) in allocated[i + 1..]
+            .iter()
+            .take_while(|(other, _)| other.start < range.end)
+        {
+            issues.push(HeaderIssue::SectorOverlap {
+                chunks: (*slot, *other),
+            });
+        }
+    }
+    Ok(issues)
+}
+
+/// The byte range and timestamp of a slot's chunk, `None` if it has none.
+pub(crate) type HeaderEntry = Option<(Range<usize>, u32)>;
+
+/// Checks the size of a region file and reads its header, giving the byte
+/// range and timestamp of every chunk in it, or `None` for empty slots. The
+/// ranges aren't checked against the length of `bytes`.
+pub(crate) fn header_entries(
+    bytes: &[u8],
+) -> Result<impl Iterator<Item = HeaderEntry> + '_, RegionParseError> {
+    check_len(bytes.len())?;
+    let (locations, timestamps) = bytes[..8192].split_at(4096);
+    Ok(locations
+        .chunks_exact(4)
+        .zip(timestamps.chunks_exact(4))
+        .map(|(location, timestamp)| {
+            header_entry(location.try_into().unwrap(), timestamp.try_into().unwrap())
+        }))
+}
+
+/// The byte range and timestamp of a chunk from its location and timestamp
+/// in the header, or `None` if the slot is empty.
+pub(crate) fn header_entry(location: [u8; 4], timestamp: [u8; 4]) -> Option<(Range<usize>, u32)> {
+    let timestamp = u32::from_be_bytes(timestamp);
+    let sector_count = location[3] as usize;
+    let offset = u32::from_be_bytes([0, location[0], location[1], location[2]]) as usize;
+
+    // no chunk can start in the header, so the slot is empty even with a
+    // timestamp or sector count left in it
+    if offset == 0 {
+        return None;
+    }
+    let offset = offset << 12;
+    Some((offset..offset + (sector_count << 12), timestamp))
+}
+
+pub(crate) fn check_len(len: usize) -> Result<(), RegionParseError> {
+    if len < 8192 {
+        return Err(RegionParseError::InputTooShort(len));
+    }
+    if !len.is_multiple_of(4096) {
+        return Err(RegionParseError::InputInvalidSize(len));
+    }
+    Ok(())
+}
+
+/// The largest region file there can be, the header and 255 sectors for
+/// each of the 1024 chunks.
+pub const MAX_REGION_LEN: usize = 8192 + 1024 * 255 * 4096;
+
+/// `bytes`, decompressed if they start with the gzip magic. A region can't
+/// start like that, as its first header entry would point past any file.
+fn gunzip_if_wrapped(bytes: &[u8]) -> Result<Cow<'_, [u8]>, RegionParseError> {
+    if !bytes.starts_with(&[0x1F, 0x8B]) {
+        return Ok(Cow::Borrowed(bytes));
+    }
+    let mut region = Vec::new();
+    // read one byte past the limit to tell reaching it from exceeding it
+    (GzDecoder::new(bytes).take(MAX_REGION_LEN as u64 + 1))
+        .read_to_end(&mut region)
+        .map_err(RegionParseError::GzipFailed)?;
+    if region.len() > MAX_REGION_LEN {
+        return Err(RegionParseError::GzipTooLarge);
+    }
+    Ok(Cow::Owned(region))
+}
+
+/// The bytes of a chunk's sectors from its header entry. Some old tools
+/// wrote entries with an offset but no sectors, which get the sectors the
+/// length the chunk starts with needs, as far as the file goes.
+pub(crate) fn chunk_range(bytes: &[u8], range: Range<usize>) -> Range<usize> {
+    match bytes.get(range.start..range.start.saturating_add(4)) {
+        Some(length) if lacks_sectors(&range) => {
+            implied_sectors(range.start, length.try_into().unwrap(), bytes.len())
+        }
+        _ => range,
+    }
+}
+
+/// Like [`chunk_range`], but reads the length from `reader`.
+pub(crate) fn read_chunk_range(
+    reader: &mut (impl Read + Seek),
+    range: Range<usize>,
+) -> io::Result<Range<usize>> {
+    if !lacks_sectors(&range) {
+        return Ok(range);
+    }
+    let len = reader.seek(SeekFrom::End(0))? as usize;
+    reader.seek(SeekFrom::Start(range.start as u64))?;
+    let mut length = [0; 4];
+    match reader.read_exact(&mut length) {
+        Ok(()) => Ok(implied_sectors(range.start, length, len)),
+        Err(error) if error.kind() == io::ErrorKind::UnexpectedEof => Ok(range),
+        Err(error) => Err(error),
+    }
+}
+
+pub(crate) fn lacks_sectors(range: &Range<usize>) -> bool {
+    range.is_empty() && range.start != 0
+}
+
+/// The sectors from `start` on that a chunk starting with `length` takes,
+/// up to the end of a file of `file_len` bytes. The length is whatever the
+/// file says, so it can't be trusted any further.
+pub(crate) fn implied_sectors(start: usize, length: [u8; 4], file_len: usize) -> Range<usize> {
+    let len = (u32::from_be_bytes(length) as usize).saturating_add(4);
+    let end = start.saturating_add(len.div_ceil(4096) << 12);
+    start..end.min(file_len.max(start))
+}
+
+/// The bytes of `range`, cut short at the end of the reader. Nothing is
+/// allocated up front, since the range may come from an untrusted length.
+pub(crate) fn read_range(
+    reader: &mut (impl Read + Seek),
+    range: Range<usize>,
+) -> io::Result<Vec<u8>> {
+    reader.seek(SeekFrom::Start(range.start as u64))?;
+    let mut bytes = Vec::new();
+    reader.take(range.len() as u64).read_to_end(&mut bytes)?;
+    Ok(bytes)
+}
+
+/// The contents of the file, see [`Region::open`].
+#[cfg(not(feature = "mmap"))]
+fn read_file(path: &Path) -> io::Result<Vec<u8>> {
+    fs::read(path)
+}
+
+#[cfg(feature = "mmap")]
+fn read_file(path: &Path) -> io::Result<memmap2::Mmap> {
+    let file = fs::File::open(path)?;
+    // SAFETY: the map is only read while parsing, which copies whatever it
+    // keeps, and dropped right after. Truncating the file meanwhile is
+    // documented on `Region::open`, other changes can only tear the data
+    // like reading the file part way through a write would.
+    unsafe { memmap2::Mmap::map(&file) }
+}
+
+/// Adds the blocks `filter` picks in `chunk` to `counts`, skipping the
+/// sections whose palette has none of them.
+fn count_chunk_blocks<'a>(
+    chunk: &'a Chunk,
+    filter: Option<&BlockSelector>,
+    counts: &mut HashMap<&'a Block, u64>,
+) {
+    let picked = |block: &Block| filter.is_none_or(|filter| filter.matches(block));
+    for (_, section) in chunk.sections() {
+        if !section.palette().iter().any(|block| picked(block)) {
+            continue;
+        }
+        for (block, count) in section.block_counts() {
+            if picked(block) {
+                *counts.entry(block).or_insert(0) += count as u64;
+            }
+        }
+    }
+}
+
+/// Runs `parse` on tokio's blocking thread pool, passing its panics on to
+/// the caller.
+#[cfg(feature = "tokio")]
+pub(crate) async fn spawn_parse<T: Send + 'static>(
+    parse: impl FnOnce() -> T + Send + 'static,
+) -> T {
+    match tokio::task::spawn_blocking(parse).await {
+        Ok(value) => value,
+        Err(error) => std::panic::resume_unwind(error.into_panic()),
+    }
+}
+
+/// The smallest box around the slots at `indices`.
+fn bounding_box(indices: impl Iterator<Item = usize>) -> Option<(usize, usize, usize, usize)> {
+    indices.fold(None, |bounds, index| {
+        let (x, z) = (index % 32, index / 32);
+        let (min_x, min_z, max_x, max_z) = bounds.unwrap_or((x, z, x, z));
+        Some((min_x.min(x), min_z.min(z), max_x.max(x), max_z.max(z)))
+    })
+}
+
+/// Puts `value` in the slot at `index`, or empties it for `None`.
+fn replace_slot<T>(slots: &mut BTreeMap<usize, T>, index: usize, value: Option<T>) {
+    match value {
+        Some(value) => slots.insert(index, value),
+        None => slots.remove(&index),
+    };
+}
+
+/// 1024 empty slots, allocated without going through the stack.
+pub(crate) fn boxed_slots<T: Default>() -> Box<[T; 1024]> {
+    let slots: Box<[T]> = (0..1024).map(|_| T::default()).collect();
+    slots.try_into().unwrap_or_else(|_| unreachable!())
+}
+
+fn slot_index(x: u8, z: u8) -> Result<usize, OutOfBounds> {
+    OutOfBounds::check(Axis::X, x, 0..32)?;
+    OutOfBounds::check(Axis::Z, z, 0..32)?;
+    Ok(x as usize + z as usize * 32)
 }
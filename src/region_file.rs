@@ -0,0 +1,340 @@
+//! Reading single chunks out of a region file without reading the rest.
+
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::{self, Read, Seek, SeekFrom},
+    mem,
+    path::{Path, PathBuf},
+};
+
+#[cfg(feature = "tokio")]
+use std::ops::Range;
+#[cfg(feature = "tokio")]
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeek, AsyncSeekExt};
+
+#[cfg(feature = "tokio")]
+use crate::region::{implied_sectors, lacks_sectors};
+use crate::{
+    chunk::{Chunk, ChunkParseError},
+    raw::RawChunk,
+    region::{
+        FileWriteOptions, ModifiedTime, Region, RegionOpenError, RegionParseError,
+        RegionParseOptions, RegionStats, Scratch, check_len, header_entries, header_entry,
+        read_chunk_range, read_range, region_stats,
+    },
+};
+
+/// A region file that chunks are read from one at a time. Each read looks
+/// up the chunk's entry in the header and only reads its sectors, and
+/// nothing is kept between reads. Use [`crate::Region`] or
+/// [`crate::LazyRegion`] to read many chunks of the same file.
+#[derive(Debug)]
+pub struct RegionFile<R = File> {
+    reader: R,
+    options: RegionParseOptions,
+    scratch: Scratch,
+    // where the file was opened from, for RegionFile::defragment
+    path: Option<PathBuf>,
+}
+
+// stands in for the file while RegionFile::defragment replaces it
+#[cfg(windows)]
+const NULL_DEVICE: &str = "NUL";
+#[cfg(not(windows))]
+const NULL_DEVICE: &str = "/dev/null";
+
+/// What [`RegionFile::defragment`] did.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct DefragReport {
+    /// How many chunks were moved.
+    pub chunks: usize,
+    /// The length of the file before, in bytes.
+    pub old_len: u64,
+    /// The length of the file after, in bytes.
+    pub new_len: u64,
+}
+
+impl DefragReport {
+    /// How many bytes the file shrank by, zero if it didn't.
+    pub fn reclaimed(&self) -> u64 {
+        self.old_len.saturating_sub(self.new_len)
+    }
+}
+
+impl RegionFile {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, RegionOpenError> {
+        let mut file = Self::new(File::open(&path)?)?;
+        file.path = Some(path.as_ref().to_owned());
+        Ok(file)
+    }
+
+    /// Rewrites the file with its chunks one after another from the third
+    /// sector on, dropping the sectors no chunk uses anymore, and reopens it.
+    /// The chunks are copied as they are, without being decompressed, and
+    /// keep their timestamps.
+    ///
+    /// The new file is written next to the old one and renamed over it, like
+    /// in [`Region::write_to_file_with`], so a crash leaves either the old
+    /// file or the new one. A chunk that can't be read fails the whole
+    /// rewrite with [`io::ErrorKind::InvalidData`] instead of being dropped,
+    /// and so does one too large for the new file, see
+    /// [`Region::to_bytes`](crate::Region::to_bytes).
+    /// Only works on files opened with [`RegionFile::open`], others fail with
+    /// [`io::ErrorKind::Unsupported`].
+    pub fn defragment(&mut self) -> io::Result<DefragReport> {
+        let path = self.path.clone().ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::Unsupported,
+                "the region file wasn't opened from a path",
+            )
+        })?;
+        let old_len = self.reader.seek(SeekFrom::End(0))?;
+
+        let mut region = Region::empty();
+        let mut chunks = 0;
+        for (x, z) in (0..32).flat_map(|z| (0..32).map(move |x| (x, z))) {
+            let raw = self.read_raw_chunk(x, z).map_err(|error| match error {
+                RegionOpenError::Io(error) => error,
+                RegionOpenError::Parse(error) => io::Error::new(io::ErrorKind::InvalidData, error),
+            })?;
+            if let Some(raw) = raw {
+                region
+                    .set_chunk_raw(x, z, raw)
+                    .expect("slot is in the region");
+                chunks += 1;
+            }
+        }
+
+        let options = FileWriteOptions {
+            overwrite: true,
+            modified: ModifiedTime::KeepOriginal,
+            ..Default::default()
+        };
+        // windows doesn't rename over a file that's still open, so the old
+        // handle is closed first, then the file is opened again, written or
+        // not
+        drop(mem::replace(&mut self.reader, File::open(NULL_DEVICE)?));
+        let written = region.write_to_file_with(&path, &options);
+        self.reader = File::open(&path)?;
+        written?;
+        Ok(DefragReport {
+            chunks,
+            old_len,
+            new_len: fs::metadata(&path)?.len(),
+        })
+    }
+}
+
+impl<R: Read + Seek> RegionFile<R> {
+    /// Only the reader's length is checked, like in
+    /// [`crate::Region::parse_bytes`].
+    pub fn new(reader: R) -> Result<Self, RegionOpenError> {
+        Self::with_options(reader, RegionParseOptions::default())
+    }
+
+    /// The options are used for every chunk that gets decoded, except
+    /// [`RegionParseOptions::strict`], which doesn't apply to single chunks.
+    /// With [`RegionParseOptions::intern_blocks`], blocks are shared with
+    /// all chunks read before.
+    pub fn with_options(
+        mut reader: R,
+        options: RegionParseOptions,
+    ) -> Result<Self, RegionOpenError> {
+        check_len(reader.seek(SeekFrom::End(0))? as usize)?;
+        Ok(Self {
+            reader,
+            scratch: Scratch::new(&options),
+            options,
+            path: None,
+        })
+    }
+
+    /// Reads and decodes the chunk in the slot. Returns `None` if the slot
+    /// is empty, the status filter leaves the chunk out, or `x` or `z` is 32
+    /// or above.
+    pub fn read_chunk(&mut self, x: usize, z: usize) -> Result<Option<Chunk>, RegionOpenError> {
+        let Some(raw) = self.read_raw_chunk(x, z)? else {
+            return Ok(None);
+        };
+        let chunk = (self.decode(&raw, x, z)).map_err(|source| RegionParseError::ChunkError {
+            x,
+            z,
+            source,
+        })?;
+        Ok(chunk)
+    }
+
+    /// Decodes a raw chunk read from the slot, like [`RegionFile::read_chunk`].
+    pub(crate) fn decode(
+        &mut self,
+        raw: &RawChunk,
+        x: usize,
+        z: usize,
+    ) -> Result<Option<Chunk>, ChunkParseError> {
+        self.options.decode(raw, x + z * 32, &mut self.scratch)
+    }
+
+    /// Reads the chunk in the slot without decompressing it, like
+    /// [`crate::Region::get_chunk_raw`]. Returns `None` if the slot is
+    /// empty, or `x` or `z` is 32 or above.
+    pub fn read_raw_chunk(
+        &mut self,
+        x: usize,
+        z: usize,
+    ) -> Result<Option<RawChunk>, RegionOpenError> {
+        if x >= 32 || z >= 32 {
+            return Ok(None);
+        }
+
+        let at = (x + z * 32) as u64 * 4;
+        let (mut location, mut timestamp) = ([0; 4], [0; 4]);
+        self.reader.seek(SeekFrom::Start(at))?;
+        self.reader.read_exact(&mut location)?;
+        self.reader.seek(SeekFrom::Start(4096 + at))?;
+        self.reader.read_exact(&mut timestamp)?;
+        let Some((range, timestamp)) = header_entry(location, timestamp) else {
+            return Ok(None);
+        };
+
+        let range = read_chunk_range(&mut self.reader, range)?;
+        let allocated = range.len();
+        // cut short at the end of the file, like in the eager parser
+        let bytes = read_range(&mut self.reader, range)?;
+        let mut raw = (self.options.parse_raw(&bytes, allocated))
+            .map_err(|source| RegionParseError::ChunkError { x, z, source })?;
+        raw.timestamp = timestamp;
+        Ok(Some(raw))
+    }
+
+    /// Like [`crate::region::storage_stats`], reading only the header and
+    /// the first 5 bytes of each chunk.
+    pub fn storage_stats(&mut self) -> Result<RegionStats, RegionOpenError> {
+        let len = self.reader.seek(SeekFrom::End(0))? as usize;
+        let header = read_range(&mut self.reader, 0..8192)?;
+        let mut heads = HashMap::new();
+        for (range, _) in header_entries(&header)?.flatten() {
+            if range.start >= 8192 && !range.is_empty() {
+                let head = read_range(&mut self.reader, range.start..range.start + 5)?;
+                if let Ok(head) = head.try_into() {
+                    heads.insert(range.start, head);
+                }
+            }
+        }
+        Ok(region_stats(&header, len, |start| {
+            heads.get(&start).copied()
+        })?)
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl RegionFile<tokio::fs::File> {
+    pub async fn open_async(path: impl AsRef<Path>) -> Result<Self, RegionOpenError> {
+        Self::new_async(tokio::fs::File::open(path).await?).await
+    }
+}
+
+/// The same reads for async readers. Only the reading is async, chunks are
+/// decoded on the calling task, which for a single chunk is quick.
+#[cfg(feature = "tokio")]
+impl<R: AsyncRead + AsyncSeek + Unpin> RegionFile<R> {
+    pub async fn new_async(reader: R) -> Result<Self, RegionOpenError> {
+        Self::with_options_async(reader, RegionParseOptions::default()).await
+    }
+
+    pub async fn with_options_async(
+        mut reader: R,
+        options: RegionParseOptions,
+    ) -> Result<Self, RegionOpenError> {
+        check_len(reader.seek(SeekFrom::End(0)).await? as usize)?;
+        Ok(Self {
+            reader,
+            scratch: Scratch::new(&options),
+            options,
+            path: None,
+        })
+    }
+
+    /// Like [`RegionFile::read_chunk`].
+    pub async fn read_chunk_async(
+        &mut self,
+        x: usize,
+        z: usize,
+    ) -> Result<Option<Chunk>, RegionOpenError> {
+        let Some(raw) = self.read_raw_chunk_async(x, z).await? else {
+            return Ok(None);
+        };
+        let chunk = self
+            .options
+            .decode(&raw, x + z * 32, &mut self.scratch)
+            .map_err(|source| RegionParseError::ChunkError { x, z, source })?;
+        Ok(chunk)
+    }
+
+    /// Like [`RegionFile::read_raw_chunk`].
+    pub async fn read_raw_chunk_async(
+        &mut self,
+        x: usize,
+        z: usize,
+    ) -> Result<Option<RawChunk>, RegionOpenError> {
+        if x >= 32 || z >= 32 {
+            return Ok(None);
+        }
+
+        let at = (x + z * 32) as u64 * 4;
+        let (mut location, mut timestamp) = ([0; 4], [0; 4]);
+        self.reader.seek(SeekFrom::Start(at)).await?;
+        self.reader.read_exact(&mut location).await?;
+        self.reader.seek(SeekFrom::Start(4096 + at)).await?;
+        self.reader.read_exact(&mut timestamp).await?;
+        let Some((range, timestamp)) = header_entry(location, timestamp) else {
+            return Ok(None);
+        };
+
+        let range = read_chunk_range_async(&mut self.reader, range).await?;
+        let allocated = range.len();
+        let bytes = read_range_async(&mut self.reader, range).await?;
+        let mut raw = (self.options.parse_raw(&bytes, allocated))
+            .map_err(|source| RegionParseError::ChunkError { x, z, source })?;
+        raw.timestamp = timestamp;
+        Ok(Some(raw))
+    }
+}
+
+/// Like [`read_chunk_range`], for async readers.
+#[cfg(feature = "tokio")]
+async fn read_chunk_range_async(
+    reader: &mut (impl AsyncRead + AsyncSeek + Unpin),
+    range: Range<usize>,
+) -> io::Result<Range<usize>> {
+    if !lacks_sectors(&range) {
+        return Ok(range);
+    }
+    let len = reader.seek(SeekFrom::End(0)).await? as usize;
+    reader.seek(SeekFrom::Start(range.start as u64)).await?;
+    let mut length = [0; 4];
+    match reader.read_exact(&mut length).await {
+        Ok(_) => Ok(implied_sectors(range.start, length, len)),
+        Err(error) if error.kind() == io::ErrorKind::UnexpectedEof => Ok(range),
+        Err(error) => Err(error),
+    }
+}
+
+/// Like [`read_range`], for async readers.
+#[cfg(feature = "tokio")]
+async fn read_range_async(
+    reader: &mut (impl AsyncRead + AsyncSeek + Unpin),
+    range: Range<usize>,
+) -> io::Result<Vec<u8>> {
+    reader.seek(SeekFrom::Start(range.start as u64)).await?;
+    let mut bytes = Vec::new();
+    reader
+        .take(range.len() as u64)
+        .read_to_end(&mut bytes)
+        .await?;
+    Ok(bytes)
+}
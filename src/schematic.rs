@@ -0,0 +1,732 @@
+use std::{collections::HashMap, io::Write, ops::Range};
+
+use flate2::{Compression, write::GzEncoder};
+use nbt_rs::get_field as try_get_field;
+use nbt_rs::{
+    error::ParseError,
+    serialize_nbt,
+    types::{NbtCompound, NbtList, NbtString, NbtTag},
+};
+use thiserror::Error;
+
+use crate::{
+    bounds::{Axis, OutOfBounds},
+    chunks::{
+        block::Block,
+        block_entity::BlockEntity,
+        chunk::{Chunk, ChunkParseError, ChunkParseOptions, Limit},
+        raw::parse_gzip_nbt,
+    },
+    coords::{BlockPos, ChunkPos},
+    legacy::flatten_block,
+    region::Region,
+};
+
+/// A box of blocks detached from any world, stored with its own palette.
+/// Blocks are indexed `(y * length + z) * width + x`, like in schematic files.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Schematic {
+    pub(crate) width: u16,
+    pub(crate) height: u16,
+    pub(crate) length: u16,
+    pub(crate) data_version: i32,
+    pub(crate) palette: Vec<Block>,
+    pub(crate) blocks: Vec<u32>,
+    pub(crate) block_entities: HashMap<(u16, u16, u16), NbtCompound>,
+}
+
+impl Schematic {
+    /// Width, height and length, i.e. the size along x, y and z.
+    pub fn get_size(&self) -> (u16, u16, u16) {
+        (self.width, self.height, self.length)
+    }
+
+    /// The data version the blocks are from, 0 if it isn't known.
+    pub fn data_version(&self) -> i32 {
+        self.data_version
+    }
+
+    pub fn get_palette(&self) -> &[Block] {
+        &self.palette
+    }
+
+    pub fn get_block(&self, x: u16, y: u16, z: u16) -> Option<&Block> {
+        let index = self.get_index(x, y, z)?;
+        Some(&self.palette[self.blocks[index] as usize])
+    }
+
+    pub fn get_block_entity(&self, x: u16, y: u16, z: u16) -> Option<&NbtCompound> {
+        self.block_entities.get(&(x, y, z))
+    }
+
+    pub fn get_block_entities(&self) -> &HashMap<(u16, u16, u16), NbtCompound> {
+        &self.block_entities
+    }
+
+    fn get_index(&self, x: u16, y: u16, z: u16) -> Option<usize> {
+        if x >= self.width || y >= self.height || z >= self.length {
+            return None;
+        }
+        let (x, y, z) = (x as usize, y as usize, z as usize);
+        Some((y * self.length as usize + z) * self.width as usize + x)
+    }
+
+    /// The schematic in the Sponge schematic format, version 3, which
+    /// WorldEdit reads since 7.3. Blocks that are in the palette more than
+    /// once are written as one entry.
+    pub fn to_nbt(&self) -> NbtCompound {
+        let mut names = HashMap::new();
+        let ids: Vec<u32> = (self.palette.iter())
+            .map(|block| {
+                let next = names.len() as u32;
+                *names.entry(block.to_string()).or_insert(next)
+            })
+            .collect();
+        let palette: HashMap<_, _> = (names.into_iter())
+            .map(|(name, id)| (string(&name), NbtTag::Int(id as i32)))
+            .collect();
+
+        // the indices are written as unsigned varints, 7 bits per byte
+        let mut data = Vec::with_capacity(self.blocks.len());
+        for &index in &self.blocks {
+            let mut id = ids[index as usize];
+            while id >= 0x80 {
+                data.push((id as u8 & 0x7F | 0x80) as i8);
+                id >>= 7;
+            }
+            data.push(id as i8);
+        }
+
+        let mut positions: Vec<_> = self.block_entities.keys().collect();
+        positions.sort_by_key(|&&(x, y, z)| (y, z, x));
+        let block_entities = positions
+            .into_iter()
+            .map(|&(x, y, z)| {
+                let mut fields = HashMap::new();
+                let pos = vec![x as i32, y as i32, z as i32];
+                insert(
+                    &mut fields,
+                    "Pos",
+                    NbtTag::IntArray(pos.try_into().unwrap()),
+                );
+                let mut rest = HashMap::new();
+                let stored: Vec<_> = self.block_entities[&(x, y, z)].clone().into();
+                for (name, tag) in stored {
+                    match &*name {
+                        "id" => insert(&mut fields, "Id", tag),
+                        "x" | "y" | "z" => {}
+                        _ => {
+                            rest.insert(name, tag);
+                        }
+                    }
+                }
+                insert(&mut fields, "Data", NbtTag::Compound(rest.into()));
+                fields.into()
+            })
+            .collect::<Vec<NbtCompound>>();
+
+        let mut blocks = HashMap::new();
+        insert(&mut blocks, "Palette", NbtTag::Compound(palette.into()));
+        insert(
+            &mut blocks,
+            "Data",
+            NbtTag::ByteArray(data.try_into().unwrap()),
+        );
+        insert(
+            &mut blocks,
+            "BlockEntities",
+            NbtTag::List(NbtList::Compound(block_entities.try_into().unwrap())),
+        );
+
+        let mut schematic = HashMap::new();
+        insert(&mut schematic, "Version", NbtTag::Int(3));
+        insert(
+            &mut schematic,
+            "DataVersion",
+            NbtTag::Int(self.data_version),
+        );
+        // the sizes are unsigned, stored in a signed short
+        for (name, size) in [
+            ("Width", self.width),
+            ("Height", self.height),
+            ("Length", self.length),
+        ] {
+            insert(&mut schematic, name, NbtTag::Short(size as i16));
+        }
+        let offset = vec![0; 3].try_into().unwrap();
+        insert(&mut schematic, "Offset", NbtTag::IntArray(offset));
+        insert(&mut schematic, "Blocks", NbtTag::Compound(blocks.into()));
+
+        let mut root = HashMap::new();
+        insert(&mut root, "Schematic", NbtTag::Compound(schematic.into()));
+        root.into()
+    }
+
+    /// [`Schematic::to_nbt`] gzipped, as written to `.schem` files.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let nbt = serialize_nbt(&string(""), &self.to_nbt());
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        // writing to a vec can't fail
+        encoder.write_all(&nbt).unwrap();
+        encoder.finish().unwrap()
+    }
+}
+
+impl Chunk {
+    /// Copies the blocks in the box into a schematic, in chunk coordinates
+    /// like [`Chunk::get`]. The block entities in the box are copied too,
+    /// with their position made relative to its lowest corner.
+    pub fn extract(
+        &self,
+        x: Range<u8>,
+        y: Range<i32>,
+        z: Range<u8>,
+    ) -> Result<Schematic, SchemError> {
+        check_range(Axis::X, &x, 0..16)?;
+        check_range(Axis::Z, &z, 0..16)?;
+        let min = self.get_pos().min_block(0);
+        let x = min.x + x.start as i32..min.x + x.end as i32;
+        let z = min.z + z.start as i32..min.z + z.end as i32;
+        extract(x, y, z, |_| Some(self))
+    }
+}
+
+impl Region {
+    /// Like [`Chunk::extract`], but takes world coordinates, so the box can
+    /// span chunks. Chunks are looked up by their offset in the region, like
+    /// in [`Region::get_chunk_at`], and fails if one the box covers is
+    /// missing or isn't at the expected position.
+    pub fn extract(
+        &self,
+        x: Range<i32>,
+        y: Range<i32>,
+        z: Range<i32>,
+    ) -> Result<Schematic, SchemError> {
+        extract(x, y, z, |pos| {
+            self.get_chunk_at(pos)
+                .filter(|chunk| chunk.get_pos() == pos)
+        })
+    }
+}
+
+/// How a schematic is pasted, see [`Region::paste_with`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PasteOptions {
+    /// Leave the blocks where the schematic has air as they are, instead of
+    /// replacing them with air.
+    pub skip_air: bool,
+}
+
+impl Chunk {
+    /// Pastes the schematic with its lowest corner at `origin`, in chunk
+    /// coordinates like [`Chunk::set`], see [`Region::paste_with`].
+    pub fn paste(
+        &mut self,
+        schematic: &Schematic,
+        origin: (u8, i32, u8),
+    ) -> Result<(), SchemError> {
+        self.paste_with(schematic, origin, &PasteOptions::default())
+    }
+
+    /// Fails without changing anything if the schematic doesn't fit in the
+    /// chunk.
+    pub fn paste_with(
+        &mut self,
+        schematic: &Schematic,
+        origin: (u8, i32, u8),
+        options: &PasteOptions,
+    ) -> Result<(), SchemError> {
+        let (x, z) = (origin.0 as i32, origin.2 as i32);
+        check_range(Axis::X, &(x..x + schematic.width as i32), 0..16)?;
+        check_range(Axis::Z, &(z..z + schematic.length as i32), 0..16)?;
+        check_y(self, schematic, origin.1)?;
+
+        let min = self.get_pos().min_block(origin.1);
+        let origin = BlockPos::new(min.x + x, min.y, min.z + z);
+        paste_into(self, schematic, origin, options);
+        Ok(())
+    }
+}
+
+impl Region {
+    /// Pastes the schematic with its lowest corner at `origin`, in world
+    /// coordinates, see [`Region::paste_with`].
+    pub fn paste(
+        &mut self,
+        schematic: &Schematic,
+        origin: (i32, i32, i32),
+    ) -> Result<(), SchemError> {
+        self.paste_with(schematic, origin, &PasteOptions::default())
+    }
+
+    /// Chunks are looked up like in [`Region::extract`]. Fails without
+    /// changing anything if a chunk the schematic covers is missing or it
+    /// leaves a chunk's y range. The block entities of the replaced blocks
+    /// are dropped, and those of the schematic are added.
+    pub fn paste_with(
+        &mut self,
+        schematic: &Schematic,
+        origin: (i32, i32, i32),
+        options: &PasteOptions,
+    ) -> Result<(), SchemError> {
+        let origin = BlockPos::from(origin);
+        let chunks = covered_chunks(schematic, origin);
+        for &pos in &chunks {
+            let chunk = (self.get_chunk_at(pos))
+                .filter(|chunk| chunk.get_pos() == pos)
+                .ok_or(SchemError::MissingChunk(pos))?;
+            check_y(chunk, schematic, origin.y)?;
+        }
+        for pos in chunks {
+            let (x, z) = pos.region_local();
+            paste_into(
+                self.get_chunk_mut(x, z).unwrap(),
+                schematic,
+                origin,
+                options,
+            );
+        }
+        Ok(())
+    }
+}
+
+/// The chunks a schematic covers when pasted at `origin`.
+fn covered_chunks(schematic: &Schematic, origin: BlockPos) -> Vec<ChunkPos> {
+    let (width, length) = (schematic.width as i32, schematic.length as i32);
+    if width == 0 || length == 0 || schematic.height == 0 {
+        return Vec::new();
+    }
+    let min = origin.chunk();
+    let max = BlockPos::new(origin.x + width - 1, 0, origin.z + length - 1).chunk();
+    (min.z..=max.z)
+        .flat_map(|z| (min.x..=max.x).map(move |x| ChunkPos::new(x, z)))
+        .collect()
+}
+
+fn check_y(chunk: &Chunk, schematic: &Schematic, y: i32) -> Result<(), OutOfBounds> {
+    check_range(
+        Axis::Y,
+        &(y..y + schematic.height as i32),
+        chunk.get_y_range(),
+    )
+}
+
+/// Pastes the part of the schematic at `origin`, in world coordinates, that
+/// lies in the chunk, which has to hold its whole height.
+fn paste_into(chunk: &mut Chunk, schematic: &Schematic, origin: BlockPos, options: &PasteOptions) {
+    let min = chunk.get_pos().min_block(origin.y);
+    let (width, length) = (schematic.width as i32, schematic.length as i32);
+    // the part in the chunk, in schematic coordinates
+    let x = (min.x - origin.x).max(0)..(min.x + 16 - origin.x).min(width);
+    let z = (min.z - origin.z).max(0)..(min.z + 16 - origin.z).min(length);
+
+    let air: Vec<bool> = schematic.palette.iter().map(Block::is_air).collect();
+    let mut pasted = Vec::new();
+    for y in 0..schematic.height as i32 {
+        for z in z.clone() {
+            for x in x.clone() {
+                let index = schematic.blocks[((y * length + z) * width + x) as usize] as usize;
+                if options.skip_air && air[index] {
+                    continue;
+                }
+                let pos = BlockPos::new(origin.x + x, origin.y + y, origin.z + z);
+                let block = schematic.palette[index].clone();
+                // the bounds were checked before
+                chunk
+                    .set((pos.x - min.x) as u8, pos.y, (pos.z - min.z) as u8, block)
+                    .unwrap();
+                pasted.push((pos.y, pos.z, pos.x));
+            }
+        }
+    }
+
+    // pasted is in the same order block entities are kept in
+    chunk
+        .block_entities
+        .retain(|block_entity| pasted.binary_search(&block_entity.key()).is_err());
+    for (&(x, y, z), data) in &schematic.block_entities {
+        let pos = BlockPos::new(
+            origin.x + x as i32,
+            origin.y + y as i32,
+            origin.z + z as i32,
+        );
+        // also leaves out those in other chunks
+        if pasted.binary_search(&(pos.y, pos.z, pos.x)).is_err() {
+            continue;
+        }
+        let Some(id) = try_get_field!(data, "id", as_string) else {
+            continue;
+        };
+        let mut fields: HashMap<_, _> = data.clone().into();
+        insert(&mut fields, "x", NbtTag::Int(pos.x));
+        insert(&mut fields, "y", NbtTag::Int(pos.y));
+        insert(&mut fields, "z", NbtTag::Int(pos.z));
+        chunk.block_entities.push(BlockEntity {
+            id: id.clone(),
+            x: pos.x,
+            y: pos.y,
+            z: pos.z,
+            data: fields.into(),
+        });
+    }
+    chunk.block_entities.sort_by_key(BlockEntity::key);
+}
+
+/// Copies the box, in world coordinates, out of the chunks `chunk_at` finds
+/// by their position.
+fn extract<'a>(
+    x: Range<i32>,
+    y: Range<i32>,
+    z: Range<i32>,
+    chunk_at: impl Fn(ChunkPos) -> Option<&'a Chunk>,
+) -> Result<Schematic, SchemError> {
+    for (axis, range) in [(Axis::X, &x), (Axis::Y, &y), (Axis::Z, &z)] {
+        if range.len() > u16::MAX as usize {
+            let max = range.start as i64 + u16::MAX as i64;
+            OutOfBounds::check(axis, range.end as i64 - 1, range.start as i64..max)?;
+        }
+    }
+    let size = [&x, &y, &z].map(|range| range.len());
+    let [width, height, length] = size.map(|len| len as u16);
+    if size.contains(&0) {
+        return Ok(Schematic {
+            width,
+            height,
+            length,
+            data_version: 0,
+            palette: Vec::new(),
+            blocks: Vec::new(),
+            block_entities: HashMap::new(),
+        });
+    }
+
+    let (min, max) = (
+        ChunkPos::new(x.start >> 4, z.start >> 4),
+        ChunkPos::new((x.end - 1) >> 4, (z.end - 1) >> 4),
+    );
+    let columns = (max.x - min.x + 1) as usize;
+    let mut chunks = Vec::new();
+    let mut block_entities = HashMap::new();
+    for chunk_z in min.z..=max.z {
+        for chunk_x in min.x..=max.x {
+            let pos = ChunkPos::new(chunk_x, chunk_z);
+            let chunk = chunk_at(pos).ok_or(SchemError::MissingChunk(pos))?;
+            check_range(Axis::Y, &y, chunk.get_y_range())?;
+            chunks.push(chunk);
+
+            for block_entity in chunk.block_entities() {
+                let pos = (block_entity.x, block_entity.y, block_entity.z);
+                if !(x.contains(&pos.0) && y.contains(&pos.1) && z.contains(&pos.2)) {
+                    continue;
+                }
+                let pos = (pos.0 - x.start, pos.1 - y.start, pos.2 - z.start);
+                let mut fields: HashMap<_, _> = block_entity.data.clone().into();
+                insert(&mut fields, "x", NbtTag::Int(pos.0));
+                insert(&mut fields, "y", NbtTag::Int(pos.1));
+                insert(&mut fields, "z", NbtTag::Int(pos.2));
+                let pos = (pos.0 as u16, pos.1 as u16, pos.2 as u16);
+                block_entities.insert(pos, fields.into());
+            }
+        }
+    }
+
+    let mut palette = Vec::new();
+    let mut indices: HashMap<&Block, u32> = HashMap::new();
+    let mut blocks = Vec::with_capacity(size.iter().product());
+    for block_y in y.clone() {
+        for block_z in z.clone() {
+            for block_x in x.clone() {
+                let column =
+                    ((block_z >> 4) - min.z) as usize * columns + ((block_x >> 4) - min.x) as usize;
+                let block = chunks[column]
+                    .get((block_x & 0xF) as u8, block_y, (block_z & 0xF) as u8)
+                    .unwrap();
+                blocks.push(*indices.entry(block).or_insert_with(|| {
+                    palette.push(block.clone());
+                    palette.len() as u32 - 1
+                }));
+            }
+        }
+    }
+
+    Ok(Schematic {
+        width,
+        height,
+        length,
+        data_version: chunks[0].data_version(),
+        palette,
+        blocks,
+        block_entities,
+    })
+}
+
+/// Fails if `range` isn't empty and doesn't lie in `bounds`.
+fn check_range<T: Copy + Into<i64> + PartialOrd>(
+    axis: Axis,
+    range: &Range<T>,
+    bounds: Range<T>,
+) -> Result<(), OutOfBounds> {
+    if range.start < range.end {
+        OutOfBounds::check(axis, range.start, bounds.clone())?;
+        OutOfBounds::check(axis, range.end.into() - 1, bounds)?;
+    }
+    Ok(())
+}
+
+#[derive(Error, Debug)]
+pub enum SchemError {
+    #[error("failed to decompress the data: {0}")]
+    DecompressionFailed(std::io::Error),
+
+    #[error("failed to parse the schematic: {0}")]
+    ParseFailed(ParseError),
+
+    /// The file inflates to more than `limit` bytes, the default
+    /// [`ChunkParseOptions::max_decompressed_len`] of chunks.
+    #[error("the schematic decompresses to more than {limit} bytes")]
+    DecompressedTooLarge { limit: usize },
+
+    /// The nbt exceeds one of the default limits of [`ChunkParseOptions`].
+    #[error("the schematic exceeds the {0} limit")]
+    LimitExceeded(Limit),
+
+    #[error("the field {0} is missing or has an invalid type")]
+    InvalidField(String),
+
+    #[error("the block data does not match the schematic size")]
+    InvalidBlockData,
+
+    #[error("the chunk at ({}, {}) is missing", .0.x, .0.z)]
+    MissingChunk(ChunkPos),
+
+    #[error(transparent)]
+    OutOfBounds(#[from] OutOfBounds),
+}
+
+macro_rules! get_field {
+    ($input:ident, $field:literal $(, $($ty:ident).*)? ) => {{
+        try_get_field!($input, $field $(, $($ty).*)?)
+            .ok_or(SchemError::InvalidField($field.to_owned()))?
+    }};
+}
+
+/// Gunzips and parses a schematic file with the limits chunks are read
+/// with, as they come from untrusted users just as often.
+fn read_nbt(bytes: &[u8]) -> Result<NbtCompound, SchemError> {
+    parse_gzip_nbt(bytes, &ChunkParseOptions::default()).map_err(|error| match error {
+        ChunkParseError::DecompressionFailed(error) => SchemError::DecompressionFailed(error),
+        ChunkParseError::ParseFailed(error) => SchemError::ParseFailed(error),
+        ChunkParseError::DecompressedTooLarge { limit } => {
+            SchemError::DecompressedTooLarge { limit }
+        }
+        ChunkParseError::LimitExceeded(limit) => SchemError::LimitExceeded(limit),
+        error => unreachable!("reading nbt failed with {error}"),
+    })
+}
+
+/// Imports an MCEdit-era `.schematic` file, flattening the numeric block ids
+/// the same way legacy chunks are.
+///
+/// The high bits of ids above 255 in `AddBlocks` are read the way WorldEdit
+/// writes them, with the block at an even index in the low nibble of its
+/// byte. MCEdit itself puts that block in the high nibble, so ids above 255
+/// in files MCEdit wrote come out wrong. WorldEdit wrote most of the files
+/// that have such ids, and the files don't say which layout they use.
+pub fn import_legacy(bytes: &[u8]) -> Result<Schematic, SchemError> {
+    let root = read_nbt(bytes)?;
+
+    if let Some(materials) = try_get_field!(root, "Materials", as_string) {
+        // pocket edition schematics use a different id table
+        if *materials != "Alpha" {
+            return Err(SchemError::InvalidField("Materials".to_owned()));
+        }
+    }
+
+    let width = get_dimension(*get_field!(root, "Width", as_short), "Width")?;
+    let height = get_dimension(*get_field!(root, "Height", as_short), "Height")?;
+    let length = get_dimension(*get_field!(root, "Length", as_short), "Length")?;
+    let volume = width as usize * height as usize * length as usize;
+
+    let ids = get_field!(root, "Blocks", as_byte_array);
+    let block_data = get_field!(root, "Data", as_byte_array);
+    let add_blocks = try_get_field!(root, "AddBlocks", as_byte_array);
+    if ids.len() != volume || block_data.len() != volume {
+        return Err(SchemError::InvalidBlockData);
+    }
+    if add_blocks.is_some_and(|add_blocks| add_blocks.len() < volume.div_ceil(2)) {
+        return Err(SchemError::InvalidBlockData);
+    }
+
+    let mut palette = Vec::new();
+    let mut palette_indices: HashMap<(u16, u8), u32> = HashMap::new();
+    let mut blocks = Vec::with_capacity(volume);
+    for index in 0..volume {
+        let mut id = ids[index] as u8 as u16;
+        if let Some(add_blocks) = add_blocks {
+            // WorldEdit's layout, even indices use the low nibble like in
+            // anvil, where MCEdit uses the high one
+            let add = add_blocks[index >> 1] as u8;
+            let add = if index & 1 == 0 { add & 0xF } else { add >> 4 };
+            id |= (add as u16) << 8;
+        }
+        let data = block_data[index] as u8 & 0xF;
+
+        let palette_index = *palette_indices.entry((id, data)).or_insert_with(|| {
+            palette.push(flatten_block(id, data));
+            palette.len() as u32 - 1
+        });
+        blocks.push(palette_index);
+    }
+
+    let mut block_entities = HashMap::new();
+    if let Some(tile_entities) = try_get_field!(root, "TileEntities", as_list.as_compound) {
+        for entity in tile_entities.iter() {
+            let &x = get_field!(entity, "x", as_int);
+            let &y = get_field!(entity, "y", as_int);
+            let &z = get_field!(entity, "z", as_int);
+            let position = match (u16::try_from(x), u16::try_from(y), u16::try_from(z)) {
+                (Ok(x), Ok(y), Ok(z)) if x < width && y < height && z < length => (x, y, z),
+                _ => return Err(SchemError::InvalidField("TileEntities".to_owned())),
+            };
+            block_entities.insert(position, entity.clone());
+        }
+    }
+
+    Ok(Schematic {
+        width,
+        height,
+        length,
+        data_version: 0,
+        palette,
+        blocks,
+        block_entities,
+    })
+}
+
+/// Imports a Sponge `.schem` file, as written by WorldEdit since 1.13, in any
+/// version of the format up to 3. See [`Schematic::to_nbt`] for the other
+/// way around.
+pub fn import_sponge(bytes: &[u8]) -> Result<Schematic, SchemError> {
+    let root = read_nbt(bytes)?;
+    // since version 3 the fields are in a compound of the root
+    let root = try_get_field!(root, "Schematic", as_compound).unwrap_or(&root);
+
+    let &version = get_field!(root, "Version", as_int);
+    if !(1..=3).contains(&version) {
+        return Err(SchemError::InvalidField("Version".to_owned()));
+    }
+    let data_version = try_get_field!(root, "DataVersion", as_int).map_or(0, |&v| v);
+    // the sizes are unsigned, stored in a signed short
+    let width = *get_field!(root, "Width", as_short) as u16;
+    let height = *get_field!(root, "Height", as_short) as u16;
+    let length = *get_field!(root, "Length", as_short) as u16;
+    let volume = width as usize * height as usize * length as usize;
+
+    let (container, data_field, block_entities_field) = match version {
+        3 => (
+            get_field!(root, "Blocks", as_compound),
+            "Data",
+            "BlockEntities",
+        ),
+        2 => (root, "BlockData", "BlockEntities"),
+        _ => (root, "BlockData", "TileEntities"),
+    };
+
+    let stored: Vec<_> = get_field!(container, "Palette", as_compound).clone().into();
+    let invalid_palette = || SchemError::InvalidField("Palette".to_owned());
+    let mut palette = vec![None; stored.len()];
+    for (name, id) in stored {
+        let id = id.as_int().and_then(|&id| usize::try_from(id).ok());
+        let entry = id
+            .and_then(|id| palette.get_mut(id))
+            .filter(|entry| entry.is_none())
+            .ok_or_else(invalid_palette)?;
+        *entry = Some(name.parse::<Block>().map_err(|_| invalid_palette())?);
+    }
+    // the ids are unique and below the palette's length, so all are set
+    let palette: Vec<Block> = palette.into_iter().flatten().collect();
+
+    let data = (container.get(data_field))
+        .and_then(NbtTag::as_byte_array)
+        .ok_or_else(|| SchemError::InvalidField(data_field.to_owned()))?;
+    // each block takes at least a byte, and the sizes are untrusted
+    let mut blocks = Vec::with_capacity(volume.min(data.len()));
+    let mut data = data.iter().map(|&byte| byte as u8);
+    while data.len() > 0 {
+        let mut index = 0u64;
+        for shift in (0..).step_by(7) {
+            let byte = data.next().ok_or(SchemError::InvalidBlockData)?;
+            if shift > 28 {
+                return Err(SchemError::InvalidBlockData);
+            }
+            index |= (byte as u64 & 0x7F) << shift;
+            if byte < 0x80 {
+                break;
+            }
+        }
+        if index as usize >= palette.len() {
+            return Err(SchemError::InvalidBlockData);
+        }
+        blocks.push(index as u32);
+    }
+    if blocks.len() != volume {
+        return Err(SchemError::InvalidBlockData);
+    }
+
+    let mut block_entities = HashMap::new();
+    let invalid_block_entities = || SchemError::InvalidField(block_entities_field.to_owned());
+    let stored = match container.get(block_entities_field) {
+        None | Some(NbtTag::List(NbtList::End)) => &[][..],
+        Some(tag) => tag
+            .as_list()
+            .and_then(|list| list.as_compound())
+            .ok_or_else(invalid_block_entities)?,
+    };
+    for stored in stored.iter() {
+        let position = match try_get_field!(stored, "Pos", as_int_array).map(|pos| &pos[..]) {
+            Some(&[x, y, z]) => (u16::try_from(x), u16::try_from(y), u16::try_from(z)),
+            _ => return Err(invalid_block_entities()),
+        };
+        let position = match position {
+            (Ok(x), Ok(y), Ok(z)) if x < width && y < height && z < length => (x, y, z),
+            _ => return Err(invalid_block_entities()),
+        };
+        let id = try_get_field!(stored, "Id", as_string).ok_or_else(invalid_block_entities)?;
+
+        // kept like in chunks, with the id and the position in the compound
+        let mut fields: HashMap<_, _> = match stored.get("Data") {
+            Some(NbtTag::Compound(data)) if version == 3 => data.clone().into(),
+            _ => Vec::from(stored.clone())
+                .into_iter()
+                .filter(|(name, _)| !matches!(&**name, "Pos" | "Id"))
+                .collect(),
+        };
+        insert(&mut fields, "id", NbtTag::String(id.clone()));
+        insert(&mut fields, "x", NbtTag::Int(position.0 as i32));
+        insert(&mut fields, "y", NbtTag::Int(position.1 as i32));
+        insert(&mut fields, "z", NbtTag::Int(position.2 as i32));
+        block_entities.insert(position, fields.into());
+    }
+
+    Ok(Schematic {
+        width,
+        height,
+        length,
+        data_version,
+        palette,
+        blocks,
+        block_entities,
+    })
+}
+
+fn get_dimension(value: i16, field: &str) -> Result<u16, SchemError> {
+    u16::try_from(value).map_err(|_| SchemError::InvalidField(field.to_owned()))
+}
+
+fn insert(compound: &mut HashMap<NbtString, NbtTag>, name: &str, tag: NbtTag) {
+    compound.insert(string(name), tag);
+}
+
+fn string(value: &str) -> NbtString {
+    value.to_owned().try_into().unwrap()
+}
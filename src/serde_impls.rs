@@ -0,0 +1,568 @@
+//! `Serialize` and `Deserialize` for blocks, sections, chunks and regions.
+//!
+//! Block properties are written as a map of strings. Other nbt, like the
+//! data of block entities, has no serde equivalent for its tag types and is
+//! written as its nbt payload bytes. Sections keep their palette, so blocks
+//! are shared within a section again after deserializing. Regions only keep
+//! their decoded chunks and timestamps, not the raw chunks.
+
+use std::{collections::BTreeMap, fmt};
+
+use nbt_rs::{
+    parse_nbt,
+    traits::NbtSerialize,
+    types::{NbtCompound, NbtString, NbtTag},
+};
+use serde::{
+    Deserialize, Deserializer, Serialize, Serializer,
+    de::{self, MapAccess, Visitor},
+    ser::SerializeMap,
+};
+
+use crate::{
+    block::{Block, LazyAir, Shared, string_compound},
+    block_entity::BlockEntity,
+    chunk::Chunk,
+    entity::parse_entity,
+    heightmap::{Heightmap, HeightmapKind},
+    region::Region,
+    section::Section,
+    status::ChunkStatus,
+    structure::parse_start,
+    tick::ScheduledTick,
+    upgrade::BelowZeroRetrogen,
+};
+
+fn nbt_string<E: de::Error>(value: String) -> Result<NbtString, E> {
+    NbtString::try_from(value).map_err(|_| E::custom("string too long for nbt"))
+}
+
+fn compound_bytes(compound: &NbtCompound) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    compound.serialize_nbt_payload(&mut bytes);
+    bytes
+}
+
+fn compound_from_bytes<E: de::Error>(bytes: &[u8]) -> Result<NbtCompound, E> {
+    // a nameless root compound, as `parse_nbt` expects it
+    let nbt = [&[10, 0, 0], bytes].concat();
+    let (_, compound) = parse_nbt(&nbt).map_err(|_| E::custom("invalid nbt payload"))?;
+    Ok(compound)
+}
+
+struct PropertiesRef<'a>(&'a NbtCompound);
+
+impl Serialize for PropertiesRef<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let properties: Vec<(NbtString, NbtTag)> = self.0.clone().into();
+        let mut map = serializer.serialize_map(Some(properties.len()))?;
+        for (key, value) in &properties {
+            match value {
+                NbtTag::String(value) => map.serialize_entry(&**key, &**value)?,
+                // the game only stores strings
+                value => map.serialize_entry(&**key, &value.to_string())?,
+            }
+        }
+        map.end()
+    }
+}
+
+/// The properties in the order they're read, which a map would lose.
+struct Properties(Vec<(String, String)>);
+
+impl<'de> Deserialize<'de> for Properties {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct PropertiesVisitor;
+
+        impl<'de> Visitor<'de> for PropertiesVisitor {
+            type Value = Properties;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a map of block properties")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Properties, A::Error> {
+                let mut properties = Vec::new();
+                while let Some(entry) = map.next_entry()? {
+                    properties.push(entry);
+                }
+                Ok(Properties(properties))
+            }
+        }
+
+        deserializer.deserialize_map(PropertiesVisitor)
+    }
+}
+
+#[derive(Serialize)]
+struct BlockRef<'a> {
+    name: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    properties: Option<PropertiesRef<'a>>,
+}
+
+#[derive(Deserialize)]
+struct BlockOwned {
+    name: String,
+    #[serde(default)]
+    properties: Option<Properties>,
+}
+
+impl Serialize for Block {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        BlockRef {
+            name: &self.name,
+            properties: self.properties.as_ref().map(PropertiesRef),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Block {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let block = BlockOwned::deserialize(deserializer)?;
+        let properties = match block.properties {
+            Some(Properties(properties)) => {
+                let properties = properties
+                    .into_iter()
+                    .map(|(key, value)| Ok((nbt_string(key)?, nbt_string(value)?)))
+                    .collect::<Result<Vec<_>, D::Error>>()?;
+                Some(string_compound(&properties))
+            }
+            None => None,
+        };
+        Ok(Block::new(nbt_string(block.name)?, properties))
+    }
+}
+
+#[derive(Serialize)]
+struct SectionRef<'a> {
+    palette: Vec<&'a Block>,
+    /// One per block in `y`, `z`, `x` order, empty if the palette has a
+    /// single entry.
+    indices: Vec<u16>,
+    /// One per 4x4x4 cell, empty if the section has no biomes.
+    biomes: Vec<&'a str>,
+    block_light: Option<&'a [u8]>,
+    sky_light: Option<&'a [u8]>,
+}
+
+#[derive(Deserialize)]
+struct SectionOwned {
+    palette: Vec<Block>,
+    indices: Vec<u16>,
+    biomes: Vec<String>,
+    block_light: Option<Vec<u8>>,
+    sky_light: Option<Vec<u8>>,
+}
+
+impl Serialize for Section {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let indices = match self.palette.len() {
+            1 => Vec::new(),
+            _ => self
+                .iter_palette_indices()
+                .map(|index| index as u16)
+                .collect(),
+        };
+        SectionRef {
+            palette: self.palette.iter().map(|block| &**block).collect(),
+            indices,
+            biomes: self.biomes.iter().map(|biome| &***biome).collect(),
+            block_light: self.block_light.as_deref().map(|light| &light[..]),
+            sky_light: self.sky_light.as_deref().map(|light| &light[..]),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Section {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let section = SectionOwned::deserialize(deserializer)?;
+        let palette: Vec<Shared<Block>> = section.palette.into_iter().map(Shared::new).collect();
+        let indices: Vec<u64> = match (palette.len(), section.indices.len()) {
+            (0, _) => return Err(de::Error::custom("empty section palette")),
+            (1, 0) => vec![0; 4096],
+            (_, 4096) => section.indices.iter().map(|&index| index as u64).collect(),
+            (_, len) => return Err(de::Error::invalid_length(len, &"4096 indices")),
+        };
+        if indices.iter().any(|&index| index as usize >= palette.len()) {
+            return Err(de::Error::custom("palette index out of range"));
+        }
+        let mut parsed = Section::from_palette(&palette, &indices);
+
+        if !matches!(section.biomes.len(), 0 | 64) {
+            return Err(de::Error::invalid_length(
+                section.biomes.len(),
+                &"64 biomes",
+            ));
+        }
+        let mut biome_palette: Vec<Shared<NbtString>> = Vec::new();
+        for biome in section.biomes {
+            let known = biome_palette.iter().find(|known| ***known == *biome);
+            let biome = match known {
+                Some(known) => known.clone(),
+                None => {
+                    let biome = Shared::new(nbt_string(biome)?);
+                    biome_palette.push(biome.clone());
+                    biome
+                }
+            };
+            parsed.biomes.push(biome);
+        }
+
+        let light = |light: Option<Vec<u8>>| {
+            light
+                .map(|light| {
+                    let len = light.len();
+                    Box::<[u8; 2048]>::try_from(light.into_boxed_slice())
+                        .map_err(|_| de::Error::invalid_length(len, &"2048 light bytes"))
+                })
+                .transpose()
+        };
+        parsed.block_light = light(section.block_light)?;
+        parsed.sky_light = light(section.sky_light)?;
+        Ok(parsed)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct BlockEntityRepr {
+    id: String,
+    x: i32,
+    y: i32,
+    z: i32,
+    /// The nbt payload of the whole compound.
+    data: Vec<u8>,
+}
+
+/// Only `data` is read back, the other fields are there for readers of the
+/// serialized form.
+#[derive(Serialize, Deserialize)]
+struct EntityRepr {
+    id: String,
+    pos: (f64, f64, f64),
+    /// The nbt payload of the whole compound.
+    data: Vec<u8>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct TickRepr {
+    target: String,
+    x: i32,
+    y: i32,
+    z: i32,
+    delay: i32,
+    priority: i32,
+}
+
+impl From<&ScheduledTick> for TickRepr {
+    fn from(tick: &ScheduledTick) -> Self {
+        Self {
+            target: tick.target.to_string(),
+            x: tick.x,
+            y: tick.y,
+            z: tick.z,
+            delay: tick.delay,
+            priority: tick.priority,
+        }
+    }
+}
+
+fn tick_from_repr<E: de::Error>(tick: TickRepr) -> Result<ScheduledTick, E> {
+    Ok(ScheduledTick {
+        target: nbt_string(tick.target)?,
+        x: tick.x,
+        y: tick.y,
+        z: tick.z,
+        delay: tick.delay,
+        priority: tick.priority,
+    })
+}
+
+#[derive(Serialize, Deserialize)]
+struct RetrogenRepr {
+    target_status: String,
+    missing_bedrock: [u64; 4],
+}
+
+#[derive(Serialize)]
+struct ChunkRef<'a> {
+    x_pos: i32,
+    y_pos: i32,
+    z_pos: i32,
+    data_version: i32,
+    status: String,
+    last_update: i64,
+    inhabited_time: i64,
+    sections: &'a [Section],
+    heightmaps: BTreeMap<&'static str, &'a [i16]>,
+    block_entities: Vec<BlockEntityRepr>,
+    entities: Vec<EntityRepr>,
+    block_ticks: Vec<TickRepr>,
+    fluid_ticks: Vec<TickRepr>,
+    structure_references: Vec<(&'a str, &'a [(i32, i32)])>,
+    /// The nbt payload of each start.
+    structure_starts: Vec<Vec<u8>>,
+    below_zero_retrogen: Option<RetrogenRepr>,
+    blending_data: Option<Vec<u8>>,
+}
+
+#[derive(Deserialize)]
+struct ChunkOwned {
+    x_pos: i32,
+    y_pos: i32,
+    z_pos: i32,
+    data_version: i32,
+    status: String,
+    last_update: i64,
+    inhabited_time: i64,
+    sections: Vec<Section>,
+    heightmaps: BTreeMap<String, Vec<i16>>,
+    block_entities: Vec<BlockEntityRepr>,
+    #[serde(default)]
+    entities: Vec<EntityRepr>,
+    #[serde(default)]
+    block_ticks: Vec<TickRepr>,
+    #[serde(default)]
+    fluid_ticks: Vec<TickRepr>,
+    #[serde(default)]
+    structure_references: Vec<(String, Vec<(i32, i32)>)>,
+    #[serde(default)]
+    structure_starts: Vec<Vec<u8>>,
+    #[serde(default)]
+    below_zero_retrogen: Option<RetrogenRepr>,
+    #[serde(default)]
+    blending_data: Option<Vec<u8>>,
+}
+
+impl Serialize for Chunk {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        ChunkRef {
+            x_pos: self.x_pos,
+            y_pos: self.y_pos,
+            z_pos: self.z_pos,
+            data_version: self.data_version,
+            status: self.status.to_string(),
+            last_update: self.last_update,
+            inhabited_time: self.inhabited_time,
+            sections: &self.sections,
+            heightmaps: self
+                .heightmaps
+                .iter()
+                .map(|(kind, heightmap)| (kind.name(), &heightmap.heights()[..]))
+                .collect(),
+            block_entities: self
+                .block_entities
+                .iter()
+                .map(|block_entity| BlockEntityRepr {
+                    id: block_entity.id.to_string(),
+                    x: block_entity.x,
+                    y: block_entity.y,
+                    z: block_entity.z,
+                    data: compound_bytes(&block_entity.data),
+                })
+                .collect(),
+            entities: self
+                .entities
+                .iter()
+                .map(|entity| EntityRepr {
+                    id: entity.id.to_string(),
+                    pos: entity.pos,
+                    data: compound_bytes(&entity.data),
+                })
+                .collect(),
+            block_ticks: self.block_ticks.iter().map(TickRepr::from).collect(),
+            fluid_ticks: self.fluid_ticks.iter().map(TickRepr::from).collect(),
+            structure_references: self
+                .structure_references
+                .iter()
+                .map(|(name, starts)| (&**name, &starts[..]))
+                .collect(),
+            structure_starts: self
+                .structure_starts
+                .iter()
+                .map(|start| compound_bytes(&start.data))
+                .collect(),
+            below_zero_retrogen: self
+                .below_zero_retrogen
+                .as_ref()
+                .map(|retrogen| RetrogenRepr {
+                    target_status: retrogen.target_status.to_string(),
+                    missing_bedrock: retrogen.missing_bedrock,
+                }),
+            blending_data: self.blending_data.as_ref().map(compound_bytes),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Chunk {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let chunk = ChunkOwned::deserialize(deserializer)?;
+
+        let mut heightmaps = BTreeMap::new();
+        for (name, heights) in chunk.heightmaps {
+            let kind = HeightmapKind::ALL
+                .into_iter()
+                .find(|kind| kind.name() == name)
+                .ok_or_else(|| de::Error::unknown_variant(&name, &[]))?;
+            let len = heights.len();
+            let heights: [i16; 256] = heights
+                .try_into()
+                .map_err(|_| de::Error::invalid_length(len, &"256 heights"))?;
+            heightmaps.insert(kind, Heightmap::new(heights));
+        }
+
+        let mut block_entities = chunk
+            .block_entities
+            .into_iter()
+            .map(|block_entity| {
+                Ok(BlockEntity {
+                    id: nbt_string(block_entity.id)?,
+                    x: block_entity.x,
+                    y: block_entity.y,
+                    z: block_entity.z,
+                    data: compound_from_bytes(&block_entity.data)?,
+                })
+            })
+            .collect::<Result<Vec<_>, D::Error>>()?;
+        block_entities.sort_by_key(BlockEntity::key);
+
+        let entities = chunk
+            .entities
+            .into_iter()
+            .map(|entity| {
+                let data = compound_from_bytes(&entity.data)?;
+                parse_entity(&data, "").map_err(de::Error::custom)
+            })
+            .collect::<Result<Vec<_>, D::Error>>()?;
+        let structure_references = chunk
+            .structure_references
+            .into_iter()
+            .map(|(name, starts)| Ok((nbt_string(name)?, starts)))
+            .collect::<Result<Vec<_>, D::Error>>()?;
+        let structure_starts = chunk
+            .structure_starts
+            .iter()
+            .map(|data| {
+                parse_start(compound_from_bytes(data)?)
+                    .ok_or_else(|| de::Error::custom("structure start without an id"))
+            })
+            .collect::<Result<Vec<_>, D::Error>>()?;
+        let ticks = |ticks: Vec<TickRepr>| {
+            ticks
+                .into_iter()
+                .map(tick_from_repr)
+                .collect::<Result<Vec<_>, D::Error>>()
+        };
+
+        Ok(Chunk {
+            x_pos: chunk.x_pos,
+            y_pos: chunk.y_pos,
+            z_pos: chunk.z_pos,
+            data_version: chunk.data_version,
+            status: ChunkStatus::parse(&chunk.status),
+            last_update: chunk.last_update,
+            inhabited_time: chunk.inhabited_time,
+            sections: chunk.sections,
+            heightmaps,
+            block_entities,
+            entities,
+            block_ticks: ticks(chunk.block_ticks)?,
+            fluid_ticks: ticks(chunk.fluid_ticks)?,
+            structure_references,
+            structure_starts,
+            below_zero_retrogen: chunk.below_zero_retrogen.map(|retrogen| BelowZeroRetrogen {
+                target_status: ChunkStatus::parse(&retrogen.target_status),
+                missing_bedrock: retrogen.missing_bedrock,
+            }),
+            blending_data: chunk
+                .blending_data
+                .map(|data| compound_from_bytes(&data))
+                .transpose()?,
+            legacy_sections: None,
+            raw_nbt: None,
+            warnings: Vec::new(),
+            air: LazyAir::default(),
+        })
+    }
+}
+
+#[derive(Serialize)]
+struct SlotRef<'a> {
+    x: u8,
+    z: u8,
+    timestamp: u32,
+    chunk: &'a Chunk,
+}
+
+#[derive(Deserialize)]
+struct SlotOwned {
+    x: u8,
+    z: u8,
+    timestamp: u32,
+    chunk: Chunk,
+}
+
+#[derive(Serialize)]
+struct RegionRef<'a> {
+    chunks: Vec<SlotRef<'a>>,
+    skipped: u16,
+    position: Option<(i32, i32)>,
+}
+
+#[derive(Deserialize)]
+struct RegionOwned {
+    chunks: Vec<SlotOwned>,
+    skipped: u16,
+    #[serde(default)]
+    position: Option<(i32, i32)>,
+}
+
+impl Serialize for Region {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let chunks = self
+            .iter_chunks()
+            .map(|(x, z, chunk)| SlotRef {
+                x: x as u8,
+                z: z as u8,
+                timestamp: self.get_timestamp(x, z).unwrap_or(0),
+                chunk,
+            })
+            .collect();
+        RegionRef {
+            chunks,
+            skipped: self.count_skipped(),
+            position: self.position,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Region {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let stored = RegionOwned::deserialize(deserializer)?;
+        let mut region = Region::empty();
+        region.skipped = stored.skipped;
+        region.position = stored.position;
+        for slot in stored.chunks {
+            if slot.x >= 32 || slot.z >= 32 {
+                return Err(de::Error::custom(format!(
+                    "the slot ({}, {}) is out of bounds",
+                    slot.x, slot.z
+                )));
+            }
+            let index = slot.x as usize + slot.z as usize * 32;
+            if region.chunks.contains_key(&index) {
+                return Err(de::Error::custom(format!(
+                    "more than one chunk for the slot ({}, {})",
+                    slot.x, slot.z
+                )));
+            }
+            region.chunks.insert(index, slot.chunk);
+            region.timestamps.insert(index, slot.timestamp);
+        }
+        Ok(region)
+    }
+}
@@ -0,0 +1,77 @@
+use nbt_rs::get_field;
+use nbt_rs::types::NbtCompound;
+
+use crate::chunk::parse_chunk_nbt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AnvilKind {
+    /// Terrain chunks from `region/`, both the 1.18+ layout and the older
+    /// `Level`-wrapped Anvil layout.
+    BlockRegion,
+    /// Entity chunks from `entities/` (1.17+).
+    EntityRegion,
+    /// Point of interest chunks from `poi/`.
+    PoiRegion,
+    /// Pre-Anvil `.mcr` files with flat 128-block-high `Blocks` arrays.
+    McRegion,
+    /// Nothing recognizable, including files without any chunk to inspect.
+    Unknown,
+}
+
+/// Guesses what kind of region-style file `bytes` holds by inspecting the
+/// first chunk that decodes successfully.
+pub fn sniff(bytes: &[u8]) -> AnvilKind {
+    if bytes.len() < 8192 {
+        return AnvilKind::Unknown;
+    }
+
+    for location in bytes[..4096].chunks_exact(4) {
+        let offset = u32::from_be_bytes([0, location[0], location[1], location[2]]) as usize;
+        let sector_count = location[3] as usize;
+        if offset < 2 || sector_count == 0 {
+            continue;
+        }
+
+        let Some(data) = bytes.get(offset << 12..(offset + sector_count) << 12) else {
+            continue;
+        };
+        let Ok(root) = parse_chunk_nbt(data) else {
+            continue;
+        };
+
+        let kind = classify(&root);
+        if kind != AnvilKind::Unknown {
+            return kind;
+        }
+    }
+
+    AnvilKind::Unknown
+}
+
+fn classify(root: &NbtCompound) -> AnvilKind {
+    if get_field!(root, "sections", as_list).is_some() {
+        return AnvilKind::BlockRegion;
+    }
+
+    if let Some(level) = get_field!(root, "Level", as_compound) {
+        if get_field!(level, "Sections", as_list).is_some() {
+            return AnvilKind::BlockRegion;
+        }
+        if get_field!(level, "Blocks", as_byte_array).is_some() {
+            return AnvilKind::McRegion;
+        }
+        return AnvilKind::Unknown;
+    }
+
+    if get_field!(root, "Entities", as_list).is_some() {
+        return AnvilKind::EntityRegion;
+    }
+
+    // poi chunks map section coordinates to compounds holding `Records`,
+    // but a chunk without any points of interest has an empty compound
+    if get_field!(root, "Sections", as_compound).is_some() && root.get("DataVersion").is_some() {
+        return AnvilKind::PoiRegion;
+    }
+
+    AnvilKind::Unknown
+}
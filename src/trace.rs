@@ -0,0 +1,11 @@
+// thin wrappers, so call sites don't need a `cfg` each and nothing is
+// evaluated when the feature is off
+
+macro_rules! debug {
+    ($($arg:tt)*) => {
+        #[cfg(feature = "tracing")]
+        tracing::debug!($($arg)*);
+    };
+}
+
+pub(crate) use debug;
@@ -0,0 +1,128 @@
+use std::{fmt, ops::Range};
+
+use thiserror::Error;
+
+use crate::{
+    chunks::heightmap::HeightmapKind,
+    coords::{BlockPos, ChunkPos},
+};
+
+/// Something that would make a chunk or region invalid for the game, as
+/// found by `Chunk::validate` and `Region::validate`.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum ValidationIssue {
+    #[error(
+        "sections {}..{} exceed the supported world height",
+        range.start,
+        range.end
+    )]
+    SectionsOutOfRange { range: Range<i32> },
+
+    #[error("section {section_y} has {count} blocks without a name")]
+    EmptyBlockName { section_y: i32, count: usize },
+
+    #[error(
+        "chunk in slot ({}, {}) claims to be at ({}, {})",
+        slot.0,
+        slot.1,
+        pos.x,
+        pos.z
+    )]
+    SlotMismatch { slot: (usize, usize), pos: ChunkPos },
+
+    #[error(
+        "block entity {id} at ({}, {}, {}) is outside the chunk",
+        pos.x,
+        pos.y,
+        pos.z
+    )]
+    BlockEntityOutsideChunk { id: String, pos: BlockPos },
+
+    /// Only checked with [`ValidationOptions::heightmaps`].
+    #[error("the {kind} heightmap doesn't match the blocks in {columns} columns")]
+    HeightmapMismatch { kind: HeightmapKind, columns: usize },
+}
+
+/// What `Chunk::validate_with` and `Region::validate_with` check besides
+/// the cheap checks that always run.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct ValidationOptions {
+    /// Compare the stored heightmaps with ones computed from the blocks,
+    /// which means going through every column. They're computed like
+    /// `Chunk::compute_heightmaps` does, by block name, so heightmaps the
+    /// game wrote may differ in a few columns, like ones with cocoa.
+    pub heightmaps: bool,
+}
+
+/// The issues that kept `Region::write_to_file_with` from writing, see
+/// `FileWriteOptions::validate`. It's returned inside an [`std::io::Error`]
+/// of kind `InvalidData`.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub struct ValidationFailed {
+    /// The issues with the slots they're in, as `Region::validate` returns
+    /// them. Never empty when writing fails.
+    pub issues: Vec<((usize, usize), ValidationIssue)>,
+}
+
+impl fmt::Display for ValidationFailed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let count = self.issues.len();
+        match self.issues.first() {
+            Some(((x, z), issue)) => write!(
+                f,
+                "the region has {count} validation issues, the first in slot ({x}, {z}): {issue}"
+            ),
+            None => f.write_str("the region has no validation issues"),
+        }
+    }
+}
+
+/// Something wrong with the header of a region file, as found by
+/// `region::validate_header`. Slots are `(x, z)` in the region.
+#[derive(Error, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum HeaderIssue {
+    #[error("chunk in slot ({}, {}) starts inside the header", slot.0, slot.1)]
+    OffsetIntoHeader { slot: (usize, usize) },
+
+    #[error("chunk in slot ({}, {}) starts past the end of the file", slot.0, slot.1)]
+    OffsetPastEof { slot: (usize, usize) },
+
+    #[error("chunk in slot ({}, {}) runs past the end of the file", slot.0, slot.1)]
+    SectorsPastEof { slot: (usize, usize) },
+
+    #[error(
+        "chunk in slot ({}, {}) is {length} bytes long, but only has {sectors} sectors",
+        slot.0,
+        slot.1
+    )]
+    DeclaredLengthExceedsSectors {
+        slot: (usize, usize),
+        /// The length of the chunk including its length field.
+        length: usize,
+        sectors: usize,
+    },
+
+    /// The slot is read as empty, like the game does.
+    #[error(
+        "slot ({}, {}) has a timestamp or sector count, but no offset",
+        slot.0,
+        slot.1
+    )]
+    MissingOffset { slot: (usize, usize) },
+
+    /// What parsing does with the slots is up to
+    /// [`DuplicateSectors`](crate::region::DuplicateSectors).
+    #[error(
+        "chunks in slots ({}, {}) and ({}, {}) share sectors",
+        chunks.0.0,
+        chunks.0.1,
+        chunks.1.0,
+        chunks.1.1
+    )]
+    SectorOverlap {
+        chunks: ((usize, usize), (usize, usize)),
+    },
+}
+
+/// The limits of a dimension's `min_y` and `height` in section coordinates.
+pub(crate) const SECTION_Y_LIMITS: Range<i32> = -2032 / 16..2032 / 16;
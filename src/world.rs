@@ -0,0 +1,685 @@
+//! Reading the region files of a whole world directory.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs::{self, File},
+    io,
+    ops::ControlFlow,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use thiserror::Error;
+
+use crate::{
+    block::Block,
+    chunk::Chunk,
+    coords::{BlockPos, ChunkPos, RegionPos},
+    entity::{Entity, read_entity_chunk},
+    region::{
+        Region, RegionOpenError, RegionParseError, RegionParseOptions, epoch_seconds,
+        region_coords_from_filename,
+    },
+    region_file::RegionFile,
+};
+
+#[derive(Error, Debug)]
+pub enum WorldError {
+    #[error("failed to read {path}: {source}")]
+    Io { path: PathBuf, source: io::Error },
+
+    #[error("{0} isn't named like a region file, r.<x>.<z>.mca")]
+    InvalidFileName(PathBuf),
+
+    #[error("failed to parse {path}: {source}")]
+    Parse {
+        path: PathBuf,
+        source: RegionParseError,
+    },
+}
+
+/// How often [`World`] found a region already parsed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct CacheStats {
+    pub hits: u64,
+    /// Lookups that had to parse the region file, whether for the first time
+    /// or after it was evicted.
+    pub misses: u64,
+    pub evictions: u64,
+}
+
+/// The regions in the `region` directory of a world. Regions are parsed the
+/// first time one of their chunks is asked for, and kept after that, up to
+/// the cache size given with [`World::with_cache_size`].
+#[derive(Debug)]
+pub struct World {
+    files: BTreeMap<(i32, i32), PathBuf>,
+    // each with the tick it was last used at
+    regions: HashMap<(i32, i32), (Region, u64)>,
+    tick: u64,
+    cache_size: Option<usize>,
+    stats: CacheStats,
+    options: RegionParseOptions,
+    // the `entities` directory next to `region`, where 1.17+ keeps entities
+    entities_dir: Option<PathBuf>,
+}
+
+impl World {
+    /// Lists the region files in `dir/region`, where `dir` is the directory
+    /// holding the world's `level.dat`. Files not ending in `.mca` are
+    /// ignored.
+    pub fn open(dir: impl AsRef<Path>) -> Result<Self, WorldError> {
+        Self::open_with(dir, RegionParseOptions::default())
+    }
+
+    pub fn open_with(
+        dir: impl AsRef<Path>,
+        options: RegionParseOptions,
+    ) -> Result<Self, WorldError> {
+        let region_dir = dir.as_ref().join("region");
+        let io_error = |source| WorldError::Io {
+            path: region_dir.clone(),
+            source,
+        };
+
+        let mut paths = Vec::new();
+        for entry in fs::read_dir(&region_dir).map_err(io_error)? {
+            paths.push(entry.map_err(io_error)?.path());
+        }
+        let world = Self::from_paths(paths, options)?;
+        Ok(world.with_entities_dir(dir.as_ref().join("entities")))
+    }
+
+    /// Lists the region files of a dimension of the world at `dir`, found
+    /// like [`Dimension::locate`] does.
+    pub fn open_dimension(
+        dir: impl AsRef<Path>,
+        dimension: &Dimension,
+    ) -> Result<Self, WorldError> {
+        Self::open_dimension_with(dir, dimension, RegionParseOptions::default())
+    }
+
+    pub fn open_dimension_with(
+        dir: impl AsRef<Path>,
+        dimension: &Dimension,
+        options: RegionParseOptions,
+    ) -> Result<Self, WorldError> {
+        let found = dimension.locate(&dir);
+        // failing like the world's own directory would if it's nowhere
+        let dir = found.map_or_else(|| dir.as_ref().join(dimension.path()), |found| found.dir);
+        Self::open_with(dir, options)
+    }
+
+    /// The dimensions of the world at `dir` that have any files yet, the
+    /// overworld, nether and end first, then the custom ones sorted by id.
+    pub fn dimensions(dir: impl AsRef<Path>) -> Result<Vec<DimensionDir>, WorldError> {
+        let dir = dir.as_ref();
+        let mut found: Vec<_> = [Dimension::Overworld, Dimension::Nether, Dimension::End]
+            .iter()
+            .filter_map(|dimension| dimension.locate(dir))
+            .collect();
+        let mut custom = Vec::new();
+        find_custom_dimensions(&dir.join("dimensions"), None, &mut custom)?;
+        custom.sort_by(|a: &DimensionDir, b| a.dimension.cmp(&b.dimension));
+        found.extend(custom);
+        Ok(found)
+    }
+
+    /// Like [`World::open`], but lists the directory with [`tokio::fs`].
+    /// Regions are still parsed when first asked for, use
+    /// [`World::get_region_async`] to do that without blocking.
+    #[cfg(feature = "tokio")]
+    pub async fn open_async(dir: impl AsRef<Path>) -> Result<Self, WorldError> {
+        Self::open_async_with(dir, RegionParseOptions::default()).await
+    }
+
+    #[cfg(feature = "tokio")]
+    pub async fn open_async_with(
+        dir: impl AsRef<Path>,
+        options: RegionParseOptions,
+    ) -> Result<Self, WorldError> {
+        let region_dir = dir.as_ref().join("region");
+        let io_error = |source| WorldError::Io {
+            path: region_dir.clone(),
+            source,
+        };
+
+        let mut paths = Vec::new();
+        let mut entries = tokio::fs::read_dir(&region_dir).await.map_err(io_error)?;
+        while let Some(entry) = entries.next_entry().await.map_err(io_error)? {
+            paths.push(entry.path());
+        }
+        let world = Self::from_paths(paths, options)?;
+        Ok(world.with_entities_dir(dir.as_ref().join("entities")))
+    }
+
+    fn from_paths(paths: Vec<PathBuf>, options: RegionParseOptions) -> Result<Self, WorldError> {
+        let mut files = BTreeMap::new();
+        for path in paths {
+            if path.extension().is_none_or(|extension| extension != "mca") {
+                continue;
+            }
+            let coords = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .and_then(region_coords_from_filename);
+            match coords {
+                Some(coords) => files.insert(coords, path),
+                None => return Err(WorldError::InvalidFileName(path)),
+            };
+        }
+
+        Ok(Self {
+            files,
+            regions: HashMap::new(),
+            tick: 0,
+            cache_size: None,
+            stats: CacheStats::default(),
+            options,
+            entities_dir: None,
+        })
+    }
+
+    fn with_entities_dir(mut self, dir: PathBuf) -> Self {
+        self.entities_dir = Some(dir);
+        self
+    }
+
+    /// Only keeps the `regions` most recently used regions parsed, the least
+    /// recently used one is dropped when another has to be parsed. At least
+    /// one region is kept.
+    pub fn with_cache_size(mut self, regions: usize) -> Self {
+        let regions = regions.max(1);
+        self.cache_size = Some(regions);
+        while self.regions.len() > regions {
+            self.evict();
+        }
+        self
+    }
+
+    pub fn cache_stats(&self) -> CacheStats {
+        self.stats
+    }
+
+    /// The coordinates of every region file, as in `r.<x>.<z>.mca`, sorted.
+    pub fn region_coords(&self) -> impl Iterator<Item = (i32, i32)> {
+        self.files.keys().copied()
+    }
+
+    /// Parses the region file if it isn't in the cache. Returns `None` if
+    /// the world has no such file.
+    pub fn get_region(&mut self, x: i32, z: i32) -> Result<Option<&Region>, WorldError> {
+        if !self.files.contains_key(&(x, z)) {
+            return Ok(None);
+        }
+        if !self.touch(x, z) {
+            let region = load_region(&self.files[&(x, z)], &self.options)?;
+            self.insert(x, z, region);
+        }
+        Ok(self.regions.get(&(x, z)).map(|(region, _)| region))
+    }
+
+    /// Like [`World::get_region`], but takes a [`RegionPos`] or anything
+    /// that converts into one, such as the [`ChunkPos`] of a chunk in it.
+    pub fn get_region_at(
+        &mut self,
+        pos: impl Into<RegionPos>,
+    ) -> Result<Option<&Region>, WorldError> {
+        let RegionPos { x, z } = pos.into();
+        self.get_region(x, z)
+    }
+
+    /// Like [`World::get_region`], but reads the file with [`tokio::fs`] and
+    /// parses it on tokio's blocking thread pool.
+    #[cfg(feature = "tokio")]
+    pub async fn get_region_async(
+        &mut self,
+        x: i32,
+        z: i32,
+    ) -> Result<Option<&Region>, WorldError> {
+        if !self.files.contains_key(&(x, z)) {
+            return Ok(None);
+        }
+        if !self.touch(x, z) {
+            let region =
+                load_region_async(self.files[&(x, z)].clone(), self.options.clone()).await?;
+            self.insert(x, z, region);
+        }
+        Ok(self.regions.get(&(x, z)).map(|(region, _)| region))
+    }
+
+    /// Marks the region as used if it's in the cache, returning whether it
+    /// is.
+    fn touch(&mut self, x: i32, z: i32) -> bool {
+        self.tick += 1;
+        let Some((_, last_used)) = self.regions.get_mut(&(x, z)) else {
+            return false;
+        };
+        *last_used = self.tick;
+        self.stats.hits += 1;
+        true
+    }
+
+    /// Caches a region that was just parsed, evicting one if it's full.
+    fn insert(&mut self, x: i32, z: i32, mut region: Region) {
+        region.set_position(x, z);
+        self.stats.misses += 1;
+        if self
+            .cache_size
+            .is_some_and(|size| self.regions.len() >= size)
+        {
+            self.evict();
+        }
+        self.regions.insert((x, z), (region, self.tick));
+    }
+
+    fn evict(&mut self) {
+        let oldest = self
+            .regions
+            .iter()
+            .min_by_key(|(_, (_, last_used))| *last_used)
+            .map(|(coords, _)| *coords);
+        if let Some(coords) = oldest {
+            self.regions.remove(&coords);
+            self.stats.evictions += 1;
+        }
+    }
+
+    /// Takes chunk coordinates, i.e. block coordinates divided by 16.
+    /// Returns `None` if the chunk or its region doesn't exist.
+    pub fn get_chunk(&mut self, x: i32, z: i32) -> Result<Option<&Chunk>, WorldError> {
+        self.get_chunk_at(ChunkPos::new(x, z))
+    }
+
+    /// Like [`World::get_chunk`], but takes a [`ChunkPos`] or anything that
+    /// converts into one, such as a [`BlockPos`] in the chunk.
+    pub fn get_chunk_at(&mut self, pos: impl Into<ChunkPos>) -> Result<Option<&Chunk>, WorldError> {
+        let pos = pos.into();
+        let region = self.get_region_at(pos)?;
+        Ok(region.and_then(|region| region.get_chunk_at(pos)))
+    }
+
+    /// Like [`World::get_chunk`], see [`World::get_region_async`].
+    #[cfg(feature = "tokio")]
+    pub async fn get_chunk_async(&mut self, x: i32, z: i32) -> Result<Option<&Chunk>, WorldError> {
+        let pos = ChunkPos::new(x, z);
+        let (region_x, region_z) = pos.region().into();
+        let region = self.get_region_async(region_x, region_z).await?;
+        Ok(region.and_then(|region| region.get_chunk_at(pos)))
+    }
+
+    /// Takes world coordinates. Returns `None` if the chunk doesn't exist or
+    /// `y` is outside of it.
+    pub fn get_block(&mut self, x: i32, y: i32, z: i32) -> Result<Option<&Block>, WorldError> {
+        self.get_block_at(BlockPos::new(x, y, z))
+    }
+
+    /// Like [`World::get_block`], but takes a [`BlockPos`] or anything that
+    /// converts into one.
+    pub fn get_block_at(&mut self, pos: impl Into<BlockPos>) -> Result<Option<&Block>, WorldError> {
+        let pos = pos.into();
+        let chunk = self.get_chunk_at(pos)?;
+        Ok(chunk.and_then(|chunk| chunk.get_at(pos)))
+    }
+
+    /// The entities in the chunk, from the world's `entities` directory
+    /// where 1.17 and later keep them. If that has no data for the chunk,
+    /// like in worlds from before 1.17, they're taken from the chunk itself,
+    /// see [`Chunk::embedded_entities`]. Empty if the chunk doesn't exist.
+    pub fn get_entities(&mut self, x: i32, z: i32) -> Result<Vec<Entity>, WorldError> {
+        self.get_entities_at(ChunkPos::new(x, z))
+    }
+
+    /// Like [`World::get_entities`], but takes a [`ChunkPos`] or anything
+    /// that converts into one.
+    pub fn get_entities_at(&mut self, pos: impl Into<ChunkPos>) -> Result<Vec<Entity>, WorldError> {
+        let pos = pos.into();
+        if let Some(dir) = &self.entities_dir {
+            let (region_x, region_z) = pos.region().into();
+            let path = dir.join(format!("r.{region_x}.{region_z}.mca"));
+            // missing and empty files both mean there's nothing stored yet
+            if fs::metadata(&path).is_ok_and(|metadata| metadata.len() > 0) {
+                let (x, z) = pos.region_local();
+                let stored = read_entity_chunk(&path, x, z, &self.options)
+                    .map_err(|error| open_error(&path, error))?;
+                if let Some(stored) = stored {
+                    return Ok(stored.entities().to_vec());
+                }
+            }
+        }
+        let chunk = self.get_chunk_at(pos)?;
+        Ok(chunk.map_or_else(Vec::new, |chunk| chunk.embedded_entities().to_vec()))
+    }
+}
+
+fn load_region(path: &Path, options: &RegionParseOptions) -> Result<Region, WorldError> {
+    // the game leaves empty files behind for regions it never wrote to
+    if fs::metadata(path).is_ok_and(|metadata| metadata.len() == 0) {
+        return Ok(Region::empty());
+    }
+    Region::open_with(path, options).map_err(|error| open_error(path, error))
+}
+
+#[cfg(feature = "tokio")]
+async fn load_region_async(
+    path: PathBuf,
+    options: RegionParseOptions,
+) -> Result<Region, WorldError> {
+    let metadata = tokio::fs::metadata(&path).await;
+    if metadata.is_ok_and(|metadata| metadata.len() == 0) {
+        return Ok(Region::empty());
+    }
+    Region::open_async_with(&path, &options)
+        .await
+        .map_err(|error| open_error(&path, error))
+}
+
+fn open_error(path: &Path, error: RegionOpenError) -> WorldError {
+    match error {
+        RegionOpenError::Io(source) => WorldError::Io {
+            path: path.to_owned(),
+            source,
+        },
+        RegionOpenError::Parse(source) => WorldError::Parse {
+            path: path.to_owned(),
+            source,
+        },
+    }
+}
+
+/// A dimension of a world, each of which keeps its regions, entities and
+/// points of interest in a directory of its own.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Dimension {
+    Overworld,
+    Nether,
+    End,
+    /// A dimension added by a datapack, by the namespace and the path of its
+    /// id. The path may have `/`s in it, which are directories on disk.
+    Custom(String, String),
+}
+
+impl Dimension {
+    /// Takes ids like `minecraft:the_nether`, an id without a namespace is in
+    /// `minecraft`'s.
+    pub fn from_id(id: &str) -> Self {
+        let (namespace, path) = id.split_once(':').unwrap_or(("minecraft", id));
+        match (namespace, path) {
+            ("minecraft", "overworld") => Dimension::Overworld,
+            ("minecraft", "the_nether") => Dimension::Nether,
+            ("minecraft", "the_end") => Dimension::End,
+            _ => Dimension::Custom(namespace.to_owned(), path.to_owned()),
+        }
+    }
+
+    pub fn id(&self) -> String {
+        match self {
+            Dimension::Overworld => "minecraft:overworld".to_owned(),
+            Dimension::Nether => "minecraft:the_nether".to_owned(),
+            Dimension::End => "minecraft:the_end".to_owned(),
+            Dimension::Custom(namespace, path) => format!("{namespace}:{path}"),
+        }
+    }
+
+    /// The directory holding the dimension's `region`, `entities` and `poi`
+    /// directories, relative to the world's, as the game lays it out. Empty
+    /// for the overworld.
+    pub fn path(&self) -> PathBuf {
+        match self {
+            Dimension::Overworld => PathBuf::new(),
+            Dimension::Nether => "DIM-1".into(),
+            Dimension::End => "DIM1".into(),
+            Dimension::Custom(namespace, path) => {
+                let mut dir: PathBuf = ["dimensions", namespace].iter().collect();
+                dir.extend(path.split('/'));
+                dir
+            }
+        }
+    }
+
+    /// Finds the dimension's directory in the world at `dir`, `None` if it
+    /// has none of `region`, `entities` and `poi` yet. Servers based on
+    /// Bukkit keep the nether and the end as worlds of their own next to the
+    /// overworld's, in `<world>_nether/DIM-1` and `<world>_the_end/DIM1`,
+    /// which are looked in when the world itself doesn't hold them.
+    pub fn locate(&self, dir: impl AsRef<Path>) -> Option<DimensionDir> {
+        let dir = dir.as_ref();
+        let sibling = match self {
+            Dimension::Nether => Some("_nether"),
+            Dimension::End => Some("_the_end"),
+            _ => None,
+        };
+        let sibling = sibling.zip(dir.file_name()).map(|(suffix, name)| {
+            let mut name = name.to_owned();
+            name.push(suffix);
+            dir.with_file_name(name).join(self.path())
+        });
+        [dir.join(self.path())]
+            .into_iter()
+            .chain(sibling)
+            .find_map(|dir| DimensionDir::read(self.clone(), dir))
+    }
+}
+
+/// Where the files of a dimension are, see [`World::dimensions`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DimensionDir {
+    pub dimension: Dimension,
+    /// The directory the others are in, which [`World::open`] takes.
+    pub dir: PathBuf,
+    pub region: Option<PathBuf>,
+    /// The entity region files, see [`crate::entity::EntityRegion`].
+    pub entities: Option<PathBuf>,
+    /// The region files of points of interest, like beds and workstations.
+    pub poi: Option<PathBuf>,
+}
+
+impl DimensionDir {
+    /// `None` if `dir` has none of the directories.
+    fn read(dimension: Dimension, dir: PathBuf) -> Option<Self> {
+        let sub = |name| Some(dir.join(name)).filter(|path| path.is_dir());
+        let (region, entities, poi) = (sub("region"), sub("entities"), sub("poi"));
+        if region.is_none() && entities.is_none() && poi.is_none() {
+            return None;
+        }
+        Some(Self {
+            dimension,
+            dir,
+            region,
+            entities,
+            poi,
+        })
+    }
+}
+
+/// Adds the dimensions under `dir`, which is `dimensions` itself when
+/// `namespace` is `None`, to `found`. Directories that aren't a dimension are
+/// looked into, as the paths of ids may have `/`s in them.
+fn find_custom_dimensions(
+    dir: &Path,
+    namespace: Option<(&str, &str)>,
+    found: &mut Vec<DimensionDir>,
+) -> Result<(), WorldError> {
+    let io_error = |source| WorldError::Io {
+        path: dir.to_owned(),
+        source,
+    };
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(error) => return Err(io_error(error)),
+    };
+    for entry in entries {
+        let path = entry.map_err(io_error)?.path();
+        let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+            continue;
+        };
+        if !path.is_dir() {
+            continue;
+        }
+        let Some((namespace, prefix)) = namespace else {
+            find_custom_dimensions(&path, Some((name, "")), found)?;
+            continue;
+        };
+        let id_path = format!("{prefix}{name}");
+        let dimension = Dimension::Custom(namespace.to_owned(), id_path.clone());
+        match DimensionDir::read(dimension, path.clone()) {
+            Some(dimension) => found.push(dimension),
+            None => {
+                find_custom_dimensions(&path, Some((namespace, &format!("{id_path}/"))), found)?
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Which chunks [`scan_world`] visits.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ScanOptions {
+    /// The directory of the dimension within the world, like `DIM-1` for the
+    /// nether, see [`Dimension::path`]. Empty for the overworld.
+    pub dimension: PathBuf,
+    /// Only the chunks from the first position to the second, both included.
+    /// Region files outside of it aren't opened.
+    pub bounds: Option<(ChunkPos, ChunkPos)>,
+    /// Only the chunks whose timestamp is at or after the time, like
+    /// [`Region::chunks_modified_since`]. Older ones aren't decoded.
+    pub modified_since: Option<SystemTime>,
+    /// How every chunk is decoded. With [`RegionParseOptions::strict`], a
+    /// chunk that fails to decode ends the scan with its error, otherwise
+    /// it's skipped.
+    pub region: RegionParseOptions,
+}
+
+/// A chunk visited by [`scan_world`], dropped when the visitor returns.
+#[derive(Debug, Clone, Copy)]
+pub struct ChunkRef<'a> {
+    /// The position of the chunk's slot in the world, which a chunk in the
+    /// wrong slot doesn't agree with.
+    pub pos: ChunkPos,
+    /// The last modification time, in seconds since the unix epoch.
+    pub timestamp: u32,
+    pub chunk: &'a Chunk,
+}
+
+/// Visits the chunks of a world one at a time, reading them from the region
+/// files as they're needed, so memory use doesn't grow with the world. The
+/// region files are visited in the order of [`World::region_coords`], and
+/// their chunks `x` before `z`. The scan stops when `visitor` breaks.
+pub fn scan_world(
+    dir: impl AsRef<Path>,
+    options: &ScanOptions,
+    mut visitor: impl FnMut(ChunkRef<'_>) -> ControlFlow<()>,
+) -> Result<(), WorldError> {
+    let world = World::open(dir.as_ref().join(&options.dimension))?;
+    for (&coords, path) in &world.files {
+        if scan_region(coords, path, options, &mut visitor)?.is_break() {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Like [`scan_world`], but scans several region files at once on rayon's
+/// thread pool, in no particular order. Once `visitor` breaks, region files
+/// are no longer opened and the ones being scanned stop at their next chunk.
+#[cfg(feature = "rayon")]
+pub fn scan_world_par(
+    dir: impl AsRef<Path>,
+    options: &ScanOptions,
+    visitor: impl Fn(ChunkRef<'_>) -> ControlFlow<()> + Sync,
+) -> Result<(), WorldError> {
+    use rayon::prelude::*;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    let world = World::open(dir.as_ref().join(&options.dimension))?;
+    let stopped = AtomicBool::new(false);
+    (world.files.par_iter()).try_for_each(|(&coords, path)| {
+        if stopped.load(Ordering::Relaxed) {
+            return Ok(());
+        }
+        let mut visit = |chunk: ChunkRef<'_>| {
+            if stopped.load(Ordering::Relaxed) {
+                return ControlFlow::Break(());
+            }
+            let flow = visitor(chunk);
+            if flow.is_break() {
+                stopped.store(true, Ordering::Relaxed);
+            }
+            flow
+        };
+        scan_region(coords, path, options, &mut visit).map(drop)
+    })
+}
+
+/// Visits the chunks of the region file at `coords` for [`scan_world`].
+fn scan_region(
+    (region_x, region_z): (i32, i32),
+    path: &Path,
+    options: &ScanOptions,
+    visitor: &mut impl FnMut(ChunkRef<'_>) -> ControlFlow<()>,
+) -> Result<ControlFlow<()>, WorldError> {
+    let in_bounds = |min_x: i32, min_z: i32, size: i32| {
+        options.bounds.is_none_or(|(min, max)| {
+            min_x <= max.x && min.x < min_x + size && min_z <= max.z && min.z < min_z + size
+        })
+    };
+    let (min_x, min_z) = (region_x * 32, region_z * 32);
+    // the game leaves empty files behind for regions it never wrote to
+    if !in_bounds(min_x, min_z, 32) || fs::metadata(path).is_ok_and(|m| m.len() == 0) {
+        return Ok(ControlFlow::Continue(()));
+    }
+
+    let file = File::open(path).map_err(|source| WorldError::Io {
+        path: path.to_owned(),
+        source,
+    })?;
+    let mut region = RegionFile::with_options(file, options.region.clone())
+        .map_err(|error| open_error(path, error))?;
+    let since = options.modified_since.map(epoch_seconds);
+    let failed = |x, z, source| -> Result<_, WorldError> {
+        if !options.region.strict {
+            return Ok(());
+        }
+        Err(WorldError::Parse {
+            path: path.to_owned(),
+            source: RegionParseError::ChunkError { x, z, source },
+        })
+    };
+    for index in 0..1024 {
+        let (x, z) = (index % 32, index / 32);
+        let pos = ChunkPos::new(min_x + x as i32, min_z + z as i32);
+        if !in_bounds(pos.x, pos.z, 1) {
+            continue;
+        }
+        let raw = match region.read_raw_chunk(x, z) {
+            Ok(Some(raw)) => raw,
+            Ok(None) => continue,
+            Err(RegionOpenError::Parse(RegionParseError::ChunkError { source, .. })) => {
+                failed(x, z, source)?;
+                continue;
+            }
+            Err(error) => return Err(open_error(path, error)),
+        };
+        if since.is_some_and(|since| (raw.timestamp() as u64) < since) {
+            continue;
+        }
+        let chunk = match region.decode(&raw, x, z) {
+            Ok(Some(chunk)) => chunk,
+            Ok(None) => continue,
+            Err(source) => {
+                failed(x, z, source)?;
+                continue;
+            }
+        };
+        let chunk = ChunkRef {
+            pos,
+            timestamp: raw.timestamp(),
+            chunk: &chunk,
+        };
+        if visitor(chunk).is_break() {
+            return Ok(ControlFlow::Break(()));
+        }
+    }
+    Ok(ControlFlow::Continue(()))
+}
@@ -0,0 +1,338 @@
+mod common;
+
+use std::{
+    cmp::Ordering,
+    collections::{BTreeMap, HashMap, HashSet},
+    hash::{BuildHasher, RandomState},
+};
+
+use common::{compound, string};
+use mca_rs::{
+    block::{Block, BlockParseError, BlockSelector, PropertyValue},
+    region::Region,
+};
+use nbt_rs::types::{NbtString, NbtTag};
+
+fn with_properties<const N: usize>(name: &str, properties: [(&str, &str); N]) -> Block {
+    let properties = properties.map(|(key, value)| (key, NbtTag::String(string(value))));
+    Block::new(string(name), Some(compound(properties)))
+}
+
+#[test]
+fn test_properties() {
+    let wheat = with_properties(
+        "minecraft:wheat",
+        [("age", "7"), ("waterlogged", "false"), ("facing", "north")],
+    );
+    assert_eq!(wheat.get_property("facing"), Some("north"));
+    assert_eq!(wheat.get_property("half"), None);
+    assert_eq!(wheat.get_property_int("age"), Some(7));
+    assert_eq!(wheat.get_property_int("facing"), None);
+    assert_eq!(wheat.get_property_bool("waterlogged"), Some(false));
+    assert_eq!(wheat.get_property_bool("age"), None);
+
+    // sorted, whatever order they're stored in
+    assert_eq!(
+        wheat.property_keys(),
+        [string("age"), string("facing"), string("waterlogged")]
+    );
+
+    // no properties at all is the same as a missing one
+    let stone = Block::new(string("minecraft:stone"), None);
+    assert_eq!(stone.get_property("age"), None);
+    assert_eq!(stone.get_property_int("age"), None);
+    assert!(stone.property_keys().is_empty());
+
+    let odd = Block::new(
+        string("test:odd"),
+        Some(compound([("power", NbtTag::Int(15))])),
+    );
+    assert_eq!(odd.get_property("power"), None);
+}
+
+#[test]
+fn test_fluids() {
+    let block = |name: &str| Block::new(string(name), None);
+    for name in ["air", "cave_air", "void_air"] {
+        assert!(block(&format!("minecraft:{name}")).is_air());
+    }
+    assert!(!block("air").is_air());
+
+    let flowing = with_properties("minecraft:water", [("level", "3")]);
+    assert!(flowing.is_fluid() && !flowing.is_waterlogged());
+    assert!(block("minecraft:lava").is_fluid());
+    assert!(block("minecraft:bubble_column").is_fluid());
+    assert!(!block("minecraft:stone").is_fluid());
+    assert!(!block("mymod:oil").is_fluid());
+
+    let stairs =
+        |waterlogged| with_properties("minecraft:oak_stairs", [("waterlogged", waterlogged)]);
+    assert!(stairs("true").is_waterlogged());
+    assert!(!stairs("true").is_fluid());
+    assert!(!stairs("false").is_waterlogged());
+    assert!(!block("minecraft:oak_stairs").is_waterlogged());
+}
+
+#[test]
+fn test_properties_typed() {
+    let wire = with_properties(
+        "minecraft:redstone_wire",
+        [("power", "15"), ("powered", "true"), ("east", "side")],
+    );
+    // sorted by name
+    let properties: Vec<_> = wire.properties_typed().collect();
+    assert_eq!(
+        properties,
+        [
+            ("east", PropertyValue::Str("side")),
+            ("power", PropertyValue::Int(15)),
+            ("powered", PropertyValue::Bool(true)),
+        ]
+    );
+    assert!(wire.matches("power", &PropertyValue::Int(15)));
+    assert!(!wire.matches("power", &PropertyValue::Str("15")));
+    assert!(!wire.matches("power", &PropertyValue::Int(14)));
+    assert!(wire.matches("east", &PropertyValue::Str("side")));
+    assert!(!wire.matches("west", &PropertyValue::Str("side")));
+
+    // only the exact spellings are inferred
+    for (value, inferred) in [
+        ("0", PropertyValue::Int(0)),
+        ("false", PropertyValue::Bool(false)),
+        ("True", PropertyValue::Str("True")),
+        ("-1", PropertyValue::Str("-1")),
+        ("+1", PropertyValue::Str("+1")),
+        ("", PropertyValue::Str("")),
+        ("99999999999", PropertyValue::Str("99999999999")),
+    ] {
+        assert_eq!(PropertyValue::infer(value), inferred);
+    }
+
+    let odd = Block::new(
+        string("test:odd"),
+        Some(compound([("power", NbtTag::Int(15))])),
+    );
+    assert_eq!(odd.properties_typed().count(), 0);
+    assert!(!odd.matches("power", &PropertyValue::Int(15)));
+}
+
+#[test]
+fn test_display() {
+    let chest = with_properties(
+        "minecraft:chest",
+        [("waterlogged", "false"), ("facing", "north")],
+    );
+    assert_eq!(
+        chest.to_string(),
+        "minecraft:chest[facing=north,waterlogged=false]"
+    );
+    assert_eq!(
+        Block::new(string("minecraft:stone"), None).to_string(),
+        "minecraft:stone"
+    );
+}
+
+#[test]
+fn test_from_str() {
+    let log: Block = "minecraft:oak_log[axis=y]".parse().unwrap();
+    assert_eq!(log, with_properties("minecraft:oak_log", [("axis", "y")]));
+    let stone: Block = "minecraft:stone".parse().unwrap();
+    assert_eq!(stone, Block::new(string("minecraft:stone"), None));
+    // the order given is kept in the compound, the keys are sorted
+    let chest: Block = "minecraft:chest[waterlogged=true,facing=east]"
+        .parse()
+        .unwrap();
+    assert_eq!(chest.get_property("facing"), Some("east"));
+    let stored: Vec<(NbtString, NbtTag)> = chest.get_properties().clone().unwrap().into();
+    assert_eq!(stored[0].0, string("waterlogged"));
+    assert_eq!(
+        chest.property_keys(),
+        [string("facing"), string("waterlogged")]
+    );
+
+    for (input, err) in [
+        ("", BlockParseError::EmptyName),
+        ("[axis=y]", BlockParseError::EmptyName),
+        (
+            "minecraft:oak_log[axis=y",
+            BlockParseError::UnclosedProperties,
+        ),
+        (
+            "minecraft:oak_log[axis=y]x",
+            BlockParseError::TrailingCharacters("x".to_owned()),
+        ),
+        (
+            "minecraft:oak_log[axis]",
+            BlockParseError::MissingValue("axis".to_owned()),
+        ),
+        ("minecraft:oak_log[=y]", BlockParseError::EmptyKey),
+        (
+            "minecraft:oak_log[axis=]",
+            BlockParseError::EmptyValue("axis".to_owned()),
+        ),
+        (
+            "minecraft:oak_log[axis=y,axis=x]",
+            BlockParseError::DuplicateKey("axis".to_owned()),
+        ),
+    ] {
+        assert_eq!(input.parse::<Block>(), Err(err), "{input}");
+    }
+}
+
+#[test]
+fn test_string_round_trip() {
+    let region = Region::parse_bytes(include_bytes!("data/r.0.0.mca")).unwrap();
+    let mut seen = HashSet::new();
+    for (_, _, chunk) in region.iter_chunks() {
+        for (_, section) in chunk {
+            for block in section.unique_blocks() {
+                let formatted = block.to_string();
+                if !seen.insert(formatted.clone()) {
+                    continue;
+                }
+
+                let parsed: Block = formatted.parse().unwrap();
+                assert_eq!(parsed.to_string(), formatted);
+                assert_eq!(&parsed, block);
+            }
+        }
+    }
+    assert!(seen.len() > 50);
+}
+
+#[test]
+fn test_eq_ignores_property_order() {
+    let a: Block = "minecraft:oak_stairs[facing=east,half=top,shape=straight]"
+        .parse()
+        .unwrap();
+    let b: Block = "minecraft:oak_stairs[shape=straight,half=top,facing=east]"
+        .parse()
+        .unwrap();
+    let c: Block = "minecraft:oak_stairs[shape=straight,half=bottom,facing=east]"
+        .parse()
+        .unwrap();
+    assert_eq!(a, b);
+    assert_ne!(a, c);
+    assert_ne!(a, Block::new(string("minecraft:oak_stairs"), None));
+    assert_eq!(a.partial_cmp(&b), Some(Ordering::Equal));
+    assert!(c < a);
+
+    let state = RandomState::new();
+    assert_eq!(state.hash_one(&a), state.hash_one(&b));
+
+    let mut counts = HashMap::new();
+    for block in [&a, &b, &c] {
+        *counts.entry(block.clone()).or_insert(0) += 1;
+    }
+    assert_eq!(counts[&a], 2);
+    assert_eq!(counts[&c], 1);
+}
+
+#[test]
+fn test_ord_ignores_property_order() {
+    let parse = |state: &str| state.parse::<Block>().unwrap();
+    let a = parse("minecraft:oak_stairs[facing=east,half=top,shape=straight]");
+    let b = parse("minecraft:oak_stairs[shape=straight,half=top,facing=east]");
+    assert_eq!(a.cmp(&b), Ordering::Equal);
+    assert_eq!(a.partial_cmp(&b), Some(Ordering::Equal));
+
+    let mut counts = BTreeMap::new();
+    for block in [&a, &b] {
+        *counts.entry(block.clone()).or_insert(0) += 1;
+    }
+    assert_eq!(counts[&b], 2);
+
+    // the same order whatever order the blocks and their properties are in
+    let blocks = [
+        parse("minecraft:stone"),
+        parse("minecraft:oak_stairs[half=bottom,facing=east]"),
+        parse("minecraft:oak_stairs[facing=west,half=bottom]"),
+        parse("minecraft:oak_stairs"),
+        parse("minecraft:wheat[age=7]"),
+        parse("minecraft:oak_stairs[half=top,facing=east]"),
+    ];
+    let mut sorted = blocks.to_vec();
+    sorted.sort();
+    let names: Vec<_> = sorted.iter().map(Block::to_string).collect();
+    assert_eq!(
+        names,
+        [
+            "minecraft:oak_stairs",
+            "minecraft:oak_stairs[facing=east,half=bottom]",
+            "minecraft:oak_stairs[facing=east,half=top]",
+            "minecraft:oak_stairs[facing=west,half=bottom]",
+            "minecraft:stone",
+            "minecraft:wheat[age=7]",
+        ]
+    );
+    let mut reversed: Vec<_> = blocks
+        .iter()
+        .rev()
+        .map(|block| parse(&block.to_string()))
+        .collect();
+    reversed.sort();
+    assert_eq!(reversed, sorted);
+}
+
+#[test]
+fn test_ord_agrees_with_eq() {
+    let with = |tag: NbtTag| Block::new(string("minecraft:wheat"), Some(compound([("age", tag)])));
+    let string_age = with(NbtTag::String(string("1")));
+    let int_age = with(NbtTag::Int(1));
+    let byte_age = with(NbtTag::Byte(1));
+    assert_ne!(string_age, int_age);
+    assert_ne!(string_age.cmp(&int_age), Ordering::Equal);
+    assert_ne!(byte_age.cmp(&int_age), Ordering::Equal);
+    // by tag type first, whatever the values
+    assert!(with(NbtTag::Int(5)) < with(NbtTag::String(string("0"))));
+    assert!(with(NbtTag::Int(2)) < with(NbtTag::Int(10)));
+
+    let mut counts = BTreeMap::new();
+    for block in [&string_age, &int_age, &byte_age, &int_age] {
+        *counts.entry(block.clone()).or_insert(0) += 1;
+    }
+    assert_eq!(counts.len(), 3);
+    assert_eq!(counts[&int_age], 2);
+}
+
+#[test]
+fn test_shared_names() {
+    let region = Region::parse_bytes(include_bytes!("data/r.0.0.mca")).unwrap();
+    let names_of = |x, z| {
+        let mut names = HashMap::new();
+        for (_, section) in region.get_chunk(x, z).unwrap() {
+            for block in section.unique_blocks() {
+                let name = block.get_name();
+                names
+                    .entry(name)
+                    .or_insert_with(HashSet::new)
+                    .insert(name.as_ptr());
+            }
+        }
+        names
+    };
+
+    // within a chunk a name is stored once, whatever the properties
+    let names = names_of(5, 9);
+    assert!(names.values().all(|pointers| pointers.len() == 1));
+    assert!(names.len() > 10);
+}
+
+#[test]
+fn test_block_selector() {
+    let wheat = with_properties("minecraft:wheat", [("age", "7"), ("facing", "north")]);
+    let young = with_properties("minecraft:wheat", [("age", "2"), ("facing", "north")]);
+
+    let any = BlockSelector::new("wheat");
+    assert!(any.matches(&wheat) && any.matches(&young));
+    assert!(!any.matches(&Block::new(string("minecraft:carrots"), None)));
+
+    let ripe: BlockSelector = "minecraft:wheat[age=7]".parse().unwrap();
+    assert_eq!(ripe, BlockSelector::new("wheat").property("age", "7"));
+    assert!(ripe.matches(&wheat));
+    assert!(!ripe.matches(&young));
+    assert_eq!(
+        "wheat[age=]".parse::<BlockSelector>(),
+        Err(BlockParseError::EmptyValue("age".to_owned()))
+    );
+}
@@ -0,0 +1,155 @@
+mod common;
+
+use std::collections::HashMap;
+
+use common::block;
+use mca_rs::{
+    Axis, OutOfBounds,
+    block_entity::BlockEntity,
+    builder::{BuildError, ChunkBuilder},
+    region::Region,
+    section::Section,
+    status::ChunkStatus,
+};
+
+fn chest(x: i32, y: i32, z: i32) -> BlockEntity {
+    BlockEntity {
+        id: "minecraft:chest".to_owned().try_into().unwrap(),
+        x,
+        y,
+        z,
+        data: HashMap::new().into(),
+    }
+}
+
+#[test]
+fn test_build_fills_missing_sections() {
+    let chunk = ChunkBuilder::new(-3, 7)
+        .y_range(0..256)
+        .data_version(3465)
+        .status(ChunkStatus::Features)
+        .inhabited_time(1200)
+        .section(2, Section::filled(block("minecraft:stone")))
+        .build()
+        .unwrap();
+
+    assert_eq!(chunk.get_pos(), (-3, 7).into());
+    assert_eq!(chunk.section_y_range(), 0..16);
+    assert_eq!(chunk.data_version(), 3465);
+    assert_eq!(chunk.status(), &ChunkStatus::Features);
+    assert_eq!(chunk.inhabited_time(), 1200);
+    assert_eq!(chunk.last_update(), 0);
+    assert_eq!(chunk.iter_blocks_non_air().count(), 4096);
+    assert_eq!(chunk.get(0, 32, 0).unwrap().get_name(), "minecraft:stone");
+    assert!(chunk.get_section(1).unwrap().is_empty());
+}
+
+#[test]
+fn test_build_errors() {
+    let stone = || Section::filled(block("minecraft:stone"));
+
+    assert_eq!(
+        ChunkBuilder::new(0, 0)
+            .y_range(-60..320)
+            .build()
+            .unwrap_err(),
+        BuildError::InvalidYRange(-60..320)
+    );
+    assert_eq!(
+        ChunkBuilder::new(0, 0).y_range(64..64).build().unwrap_err(),
+        BuildError::InvalidYRange(64..64)
+    );
+    assert_eq!(
+        ChunkBuilder::new(0, 0)
+            .section(20, stone())
+            .build()
+            .unwrap_err(),
+        BuildError::SectionOutOfRange(20)
+    );
+    assert_eq!(
+        ChunkBuilder::new(0, 0)
+            .section(-4, stone())
+            .section(-4, stone())
+            .build()
+            .unwrap_err(),
+        BuildError::DuplicateSection(-4)
+    );
+
+    // chunk (1, 0) covers x 16..32
+    assert_eq!(
+        ChunkBuilder::new(1, 0)
+            .block_entity(chest(3, 0, 0))
+            .build()
+            .unwrap_err(),
+        BuildError::BlockEntityOutOfBounds(OutOfBounds {
+            axis: Axis::X,
+            value: 3,
+            range: 16..32,
+        })
+    );
+    assert_eq!(
+        ChunkBuilder::new(1, 0)
+            .block_entity(chest(20, 0, 0))
+            .block_entity(chest(20, 0, 0))
+            .build()
+            .unwrap_err(),
+        BuildError::DuplicateBlockEntity((20, 0, 0))
+    );
+}
+
+#[test]
+fn test_build_block_entities() {
+    let chunk = ChunkBuilder::new(-1, 2)
+        .block_entity(chest(-1, 100, 40))
+        .block_entity(chest(-16, -64, 32))
+        .build()
+        .unwrap();
+    // sorted like parsed chunks
+    let positions: Vec<_> = (chunk.block_entities().iter())
+        .map(|block_entity| (block_entity.x, block_entity.y, block_entity.z))
+        .collect();
+    assert_eq!(positions, [(-16, -64, 32), (-1, 100, 40)]);
+    assert_eq!(chunk.get_block_entity(15, 100, 8).unwrap().x, -1);
+}
+
+#[test]
+fn test_build_region() {
+    let mut region = Region::empty();
+    assert_eq!(region.count_chunks(), 0);
+    for (x, z, floor) in [
+        (0, 0, "minecraft:stone"),
+        (1, 0, "minecraft:dirt"),
+        (0, 5, "minecraft:sand"),
+    ] {
+        let mut bottom = Section::filled(block(floor));
+        bottom.fill_box(0..16, 1..16, 0..16, block("minecraft:air"));
+        let chunk = ChunkBuilder::new(x, z)
+            .data_version(3465)
+            .section(-4, bottom)
+            .compute_heightmaps()
+            .build()
+            .unwrap();
+        // the sections that weren't given share their air
+        let air = |section_y| &*chunk.get_section(section_y).unwrap().palette()[0];
+        assert!(std::ptr::eq(air(0), air(19)));
+        region.set_chunk(x as usize, z as usize, chunk).unwrap();
+    }
+
+    let parsed = Region::parse_bytes(&region.to_bytes().unwrap()).unwrap();
+    assert_eq!(parsed.count_chunks(), 3);
+    assert_eq!(parsed.get_timestamp(1, 0), Some(0));
+    for (x, z, chunk) in region.iter_chunks() {
+        let read = parsed.get_chunk(x, z).unwrap();
+        assert_eq!(read, chunk);
+        assert_eq!(read.highest_block(3, 3).unwrap().0, -64);
+    }
+    assert_eq!(
+        parsed
+            .get_chunk(0, 5)
+            .unwrap()
+            .get(15, -64, 15)
+            .unwrap()
+            .get_name(),
+        "minecraft:sand"
+    );
+}
@@ -0,0 +1,56 @@
+#![cfg(feature = "bytes")]
+
+mod common;
+
+use bytes::Bytes;
+use common::string;
+use mca_rs::{LazyRegion, block::Block, region::Region};
+
+const REGION: &[u8] = include_bytes!("data/r.0.0.mca");
+
+fn contains(bytes: &Bytes, data: &[u8]) -> bool {
+    bytes.as_ptr_range().contains(&data.as_ptr())
+}
+
+#[test]
+fn test_parse_shared() {
+    let bytes = Bytes::from(REGION.to_vec());
+    let region = Region::parse_shared(bytes.clone()).unwrap();
+    assert_eq!(region, Region::parse_bytes(REGION).unwrap());
+
+    // the raw chunks point into the region's bytes
+    let raw = region.get_chunk_raw(5, 9).unwrap();
+    assert!(contains(&bytes, raw.compressed_data()));
+    assert_eq!(
+        region.to_bytes(),
+        Region::parse_bytes(REGION).unwrap().to_bytes()
+    );
+}
+
+#[test]
+fn test_lazy_shared() {
+    // the region outlives the buffer it was handed
+    let lazy = LazyRegion::parse_shared(Bytes::from(REGION.to_vec())).unwrap();
+    let eager = Region::parse_bytes(REGION).unwrap();
+    assert_eq!(lazy.count_decoded(), 0);
+    for (x, z) in [(0, 0), (5, 9), (31, 31)] {
+        assert_eq!(lazy.get_chunk(x, z), eager.get_chunk(x, z));
+    }
+
+    let mut edited = eager.clone();
+    let sponge = Block::new(string("minecraft:sponge"), None);
+    edited
+        .get_chunk_mut(5, 9)
+        .unwrap()
+        .set(0, 100, 0, sponge)
+        .unwrap();
+    let shared = Bytes::from(edited.to_bytes().unwrap());
+    let live = LazyRegion::parse_shared(shared.clone()).unwrap();
+    let conflicts: Vec<_> = live.conflicts(&lazy).collect();
+    assert_eq!(conflicts.len(), 1);
+    let raw = conflicts[0].ours.raw().unwrap();
+    assert!(contains(&shared, raw.compressed_data()));
+    let mut written = edited.get_chunk(5, 9).unwrap().clone();
+    written.compute_heightmaps();
+    assert_eq!(raw.decode().unwrap(), written);
+}
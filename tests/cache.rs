@@ -0,0 +1,112 @@
+mod common;
+
+use common::{compound, string};
+use mca_rs::{
+    block::Block,
+    cache::CacheError,
+    chunk::{Chunk, ChunkParseOptions},
+    region::Region,
+};
+use nbt_rs::types::NbtTag;
+
+#[test]
+fn test_cache_round_trip() {
+    let region = Region::parse_bytes(include_bytes!("data/r.0.0.mca")).unwrap();
+    let chunks: Vec<_> = (0..32)
+        .flat_map(|x| (0..32).map(move |z| (x, z)))
+        .filter_map(|(x, z)| region.get_chunk(x, z))
+        .collect();
+    assert_eq!(chunks.len(), 975);
+
+    for chunk in chunks {
+        let bytes = chunk.to_cache_bytes();
+        assert_eq!(&Chunk::from_cache_bytes(&bytes).unwrap(), chunk);
+    }
+}
+
+#[test]
+fn test_cache_indices() {
+    let region = Region::parse_bytes(include_bytes!("data/r.0.0.mca")).unwrap();
+    let full = region.get_chunk(5, 9).unwrap();
+
+    // sections that were never looked at are written packed
+    let options = ChunkParseOptions {
+        lazy_sections: true,
+        ..Default::default()
+    };
+    let lazy = region
+        .get_chunk_raw(5, 9)
+        .unwrap()
+        .decode_with(&options)
+        .unwrap();
+    assert!(lazy.sections().any(|(_, section)| !section.is_decoded()));
+    let bytes = lazy.to_cache_bytes();
+    assert_eq!(&Chunk::from_cache_bytes(&bytes).unwrap(), full);
+
+    // more than 256 palette entries need two bytes per block
+    let mut edited = full.clone();
+    for i in 0..300 {
+        let block = Block::new(string(&format!("minecraft:test_{i}")), None);
+        let (x, y, z) = (i % 16, -64 + i / 256, i / 16 % 16);
+        edited.set(x as u8, y, z as u8, block).unwrap();
+    }
+    let bytes = edited.to_cache_bytes();
+    assert_eq!(Chunk::from_cache_bytes(&bytes).unwrap(), edited);
+}
+
+#[test]
+fn test_cache_palettes() {
+    let region = Region::parse_bytes(include_bytes!("data/r.0.0.mca")).unwrap();
+    let mut chunk = region.get_chunk(5, 9).unwrap().clone();
+    // blocks of the same name told apart by their properties
+    let lit = compound([("lit", NbtTag::String(string("true")))]);
+    let blocks = [
+        Block::new(string("minecraft:furnace"), None),
+        Block::new(string("minecraft:furnace"), Some(lit)),
+    ];
+    for (x, block) in blocks.iter().enumerate() {
+        chunk.set(x as u8, 0, 0, block.clone()).unwrap();
+    }
+    // the entry stays in the palette once it's no longer used
+    chunk.set(0, 0, 0, blocks[1].clone()).unwrap();
+    let palette_len = chunk.get_section(0).unwrap().palette().len();
+
+    let decoded = Chunk::from_cache_bytes(&chunk.to_cache_bytes()).unwrap();
+    assert_eq!(decoded, chunk);
+    assert_eq!(decoded.get(0, 0, 0), Some(&blocks[1]));
+    assert_eq!(decoded.get_section(0).unwrap().palette().len(), palette_len);
+    // the sections share their biome names
+    let biomes: Vec<_> = (decoded.sections())
+        .filter_map(|(_, section)| section.get_biome(0, 0, 0))
+        .collect();
+    assert!(biomes.len() > 1);
+    assert!(std::ptr::eq(biomes[0], biomes[1]));
+}
+
+#[test]
+fn test_cache_errors() {
+    let region = Region::parse_bytes(include_bytes!("data/r.0.0.mca")).unwrap();
+    let mut bytes = region.get_chunk(5, 9).unwrap().to_cache_bytes();
+
+    assert_eq!(
+        Chunk::from_cache_bytes(&bytes[..bytes.len() - 1]),
+        Err(CacheError::UnexpectedEnd)
+    );
+    bytes.push(0);
+    assert_eq!(
+        Chunk::from_cache_bytes(&bytes),
+        Err(CacheError::Invalid("trailing data"))
+    );
+    bytes[0] = 0;
+    assert_eq!(
+        Chunk::from_cache_bytes(&bytes),
+        Err(CacheError::UnsupportedVersion(0))
+    );
+    // written before palettes were kept whole
+    bytes[0] = 10;
+    assert_eq!(
+        Chunk::from_cache_bytes(&bytes),
+        Err(CacheError::UnsupportedVersion(10))
+    );
+    assert_eq!(Chunk::from_cache_bytes(&[]), Err(CacheError::UnexpectedEnd));
+}
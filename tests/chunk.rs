@@ -0,0 +1,2292 @@
+mod common;
+
+use std::{borrow::Cow, collections::HashMap, error::Error};
+
+use common::{chunk_bytes, compound, compound_list, region_bytes, string, uniform_chunk};
+use mca_rs::{
+    Axis, BlockPos, ChunkPos, OutOfBounds,
+    block::{Block, Shared},
+    block_entity::BlockEntity,
+    builder::ChunkBuilder,
+    chunk::{
+        Chunk, ChunkMetadata, ChunkParseError, ChunkParseOptions, ParseWarning, SectionDataError,
+        SetBlockError, UNKNOWN_BLOCK, parse_chunk, parse_chunk_metadata, parse_chunk_nbt,
+        parse_chunk_with, parse_chunk_with_buffer,
+    },
+    heightmap::HeightmapKind,
+    legacy::parse_alpha_chunk,
+    raw::RawChunk,
+    region::{Region, RegionParseOptions},
+    section::Section,
+    status::ChunkStatus,
+    structure::BoundingBox,
+};
+use nbt_rs::{
+    error::ParseError,
+    serialize_nbt,
+    traits::NbtSerialize,
+    types::{NbtCompound, NbtList, NbtTag},
+};
+
+#[test]
+fn test_set_block() {
+    let mut chunk = parse_chunk(&chunk_bytes(&uniform_chunk(-4, 24, "minecraft:stone"))).unwrap();
+    let dirt = parse_chunk(&chunk_bytes(&uniform_chunk(0, 1, "minecraft:dirt"))).unwrap();
+    let dirt = dirt.get(0, 0, 0).unwrap().clone();
+
+    chunk.set(3, -64, 15, dirt.clone()).unwrap();
+    chunk.set(0, 319, 0, dirt.clone()).unwrap();
+    assert_eq!(chunk.get(3, -64, 15), Some(&dirt));
+    assert_eq!(chunk.get(0, 319, 0), Some(&dirt));
+    assert_eq!(
+        chunk.get(4, -64, 15).unwrap().get_name().to_string(),
+        "minecraft:stone"
+    );
+}
+
+#[test]
+fn test_set_block_out_of_bounds() {
+    let mut chunk = parse_chunk(&chunk_bytes(&uniform_chunk(-4, 24, "minecraft:stone"))).unwrap();
+    let stone = chunk.get(0, 0, 0).unwrap().clone();
+
+    assert_eq!(
+        chunk.set(0, 320, 0, stone.clone()),
+        Err(OutOfBounds {
+            axis: Axis::Y,
+            value: 320,
+            range: -64..320
+        })
+    );
+    assert_eq!(
+        chunk.set(16, 0, 0, stone.clone()).unwrap_err().axis,
+        Axis::X
+    );
+
+    let section = &mut chunk.get_section(0).unwrap().clone();
+    assert_eq!(
+        section.set_block(0, 0, 16, stone).unwrap_err().axis,
+        Axis::Z
+    );
+}
+
+#[test]
+fn test_set_extending() {
+    let mut chunk = parse_chunk(&chunk_bytes(&uniform_chunk(0, 2, "minecraft:stone"))).unwrap();
+    let stone = chunk.get(0, 0, 0).unwrap().clone();
+    let dirt = Block::new(string("minecraft:dirt"), None);
+    let air = Block::new(string("minecraft:air"), None);
+
+    // inside the chunk it's the same as `set`
+    chunk.set_extending(1, 31, 2, dirt.clone()).unwrap();
+    assert_eq!(chunk.get_y_range(), 0..32);
+
+    chunk.set_extending(1, 40, 2, dirt.clone()).unwrap();
+    assert_eq!(chunk.get_y_range(), 0..48);
+    assert_eq!(chunk.get(1, 40, 2), Some(&dirt));
+    assert_eq!(chunk.get(1, 39, 2), Some(&air));
+    assert_eq!(chunk.get(0, 40, 2), Some(&air));
+    assert_eq!(chunk.get(1, 31, 2), Some(&dirt));
+    assert_eq!(chunk.get(1, 30, 2), Some(&stone));
+
+    chunk.set_extending(15, -20, 15, dirt.clone()).unwrap();
+    assert_eq!(chunk.get_y_range(), -32..48);
+    assert_eq!(chunk.get(15, -20, 15), Some(&dirt));
+    assert_eq!(chunk.get(15, -21, 15), Some(&air));
+    assert_eq!(chunk.get(0, 0, 0), Some(&stone));
+    assert!(chunk.get_section(-1).unwrap().is_empty());
+
+    assert_eq!(
+        chunk
+            .set_extending(0, 2032, 0, dirt.clone())
+            .unwrap_err()
+            .axis,
+        Axis::Y
+    );
+    assert_eq!(
+        chunk.set_extending(16, 0, 0, dirt).unwrap_err().axis,
+        Axis::X
+    );
+    assert_eq!(chunk.get_y_range(), -32..48);
+}
+
+#[test]
+fn test_set_block_sections() {
+    let mut chunk = parse_chunk(&chunk_bytes(&uniform_chunk(0, 2, "minecraft:stone"))).unwrap();
+    let stone = chunk.get(0, 0, 0).unwrap().clone();
+    let dirt = Block::new(string("minecraft:dirt"), None);
+
+    chunk.set_block(4, 17, 9, dirt.clone()).unwrap();
+    assert_eq!(chunk.get(4, 17, 9), Some(&dirt));
+    for (x, y, z) in [
+        (3, 17, 9),
+        (5, 17, 9),
+        (4, 16, 9),
+        (4, 18, 9),
+        (4, 17, 8),
+        (4, 17, 10),
+    ] {
+        assert_eq!(chunk.get(x, y, z), Some(&stone), "{x} {y} {z}");
+    }
+
+    // just above the top section
+    assert_eq!(
+        chunk.set_block(4, 32, 9, dirt.clone()),
+        Err(SetBlockError::OutsideSections {
+            y: 32,
+            sections: 0..32
+        })
+    );
+    assert_eq!(
+        chunk.set_block(4, -1, 9, dirt.clone()),
+        Err(SetBlockError::OutsideSections {
+            y: -1,
+            sections: 0..32
+        })
+    );
+    assert!(matches!(
+        chunk.set_block(16, 0, 0, dirt.clone()),
+        Err(SetBlockError::OutOfChunk(OutOfBounds { axis: Axis::X, .. }))
+    ));
+    assert!(matches!(
+        chunk.set_block(0, 0, 16, dirt),
+        Err(SetBlockError::OutOfChunk(OutOfBounds { axis: Axis::Z, .. }))
+    ));
+    assert_eq!(chunk.get_y_range(), 0..32);
+    assert_eq!(chunk.get(4, 31, 9), Some(&stone));
+}
+
+#[test]
+fn test_set_extending_empty() {
+    let mut fields: HashMap<_, _> = uniform_chunk(4, 0, "minecraft:air").into();
+    let sections = NbtList::Compound(Vec::new().try_into().unwrap());
+    fields.insert(string("sections"), NbtTag::List(sections));
+    fields.insert(
+        string("Heightmaps"),
+        NbtTag::Compound(compound([(
+            "WORLD_SURFACE",
+            NbtTag::LongArray(vec![0i64; 4].try_into().unwrap()),
+        )])),
+    );
+    let mut chunk = parse_chunk(&chunk_bytes(&fields.into())).unwrap();
+    assert_eq!(chunk.get_y_range(), 64..64);
+    let heightmap = chunk.get_heightmap(HeightmapKind::WorldSurface).unwrap();
+    assert_eq!(heightmap.get(1, 1), Some(64));
+
+    // the columns are still empty, at the new bottom of the chunk
+    let dirt = Block::new(string("minecraft:dirt"), None);
+    chunk.set_extending(0, -20, 0, dirt).unwrap();
+    assert_eq!(chunk.get_y_range(), -32..-16);
+    let heightmap = chunk.get_heightmap(HeightmapKind::WorldSurface).unwrap();
+    assert_eq!(heightmap.get(1, 1), Some(-32));
+}
+
+#[test]
+fn test_extend() {
+    let region = Region::parse_bytes(include_bytes!("data/r.0.0.mca")).unwrap();
+    let original = region.get_chunk(5, 9).unwrap();
+    let mut chunk = original.clone();
+    let air = Block::new(string("minecraft:air"), None);
+
+    chunk.extend_down(2).unwrap();
+    chunk.extend_up(1).unwrap();
+    assert_eq!(chunk.get_y_range(), -96..336);
+    assert_eq!(chunk.section_y_range(), -6..21);
+    for (x, z) in [(0, 0), (3, 4), (15, 15)] {
+        assert_eq!(chunk.highest_block(x, z), original.highest_block(x, z));
+        for y in original.get_y_range() {
+            assert_eq!(chunk.get(x, y, z), original.get(x, y, z));
+        }
+    }
+    assert_eq!(chunk.get(0, -96, 0), Some(&air));
+    assert_eq!(chunk.get(0, 335, 0), Some(&air));
+    assert_eq!(chunk.get(0, -97, 0), None);
+    assert_eq!(chunk.get_biome(0, -96, 0), original.get_biome(0, -64, 0));
+    assert_eq!(
+        chunk.get_heightmap(HeightmapKind::WorldSurface),
+        original.get_heightmap(HeightmapKind::WorldSurface)
+    );
+
+    let stone = chunk.get(0, -64, 0).unwrap().clone();
+    chunk.set_block(0, -90, 0, stone.clone()).unwrap();
+    assert_eq!(
+        chunk.set_block(0, -97, 0, stone.clone()),
+        Err(SetBlockError::OutsideSections {
+            y: -97,
+            sections: -96..336
+        })
+    );
+    let reparsed = parse_chunk(&chunk.to_bytes()).unwrap();
+    assert_eq!(reparsed.get_y_range(), -96..336);
+    assert_eq!(reparsed.get(0, -90, 0), Some(&stone));
+    assert_eq!(reparsed.get(0, 70, 0), original.get(0, 70, 0));
+
+    // nothing changes past what any dimension can have
+    assert_eq!(chunk.extend_down(1000).unwrap_err().axis, Axis::Y);
+    assert_eq!(chunk.extend_up(1000).unwrap_err().axis, Axis::Y);
+    assert_eq!(chunk.get_y_range(), -96..336);
+
+    // columns without blocks stay at the bottom
+    let mut empty = parse_chunk(&chunk_bytes(&uniform_chunk(0, 1, "minecraft:air"))).unwrap();
+    empty.compute_heightmaps();
+    empty.extend_down(1).unwrap();
+    let heightmap = empty.get_heightmap(HeightmapKind::WorldSurface).unwrap();
+    assert_eq!(heightmap.get(0, 0), Some(-16));
+}
+
+#[test]
+fn test_metadata() {
+    let mut root = uniform_chunk(0, 1, "minecraft:stone");
+    let chunk = parse_chunk(&chunk_bytes(&root)).unwrap();
+    assert_eq!(chunk.data_version(), 4325);
+    assert_eq!(chunk.status(), &ChunkStatus::Full);
+    assert_eq!(chunk.last_update(), 0);
+    assert_eq!(chunk.inhabited_time(), 0);
+
+    let mut fields: HashMap<_, _> = root.into();
+    fields.insert(string("Status"), NbtTag::String(string("liquid_carvers")));
+    fields.insert(string("LastUpdate"), NbtTag::Long(120_000));
+    fields.insert(string("InhabitedTime"), NbtTag::Long(3_600));
+    fields.remove("DataVersion");
+    root = fields.into();
+    let chunk = parse_chunk(&chunk_bytes(&root)).unwrap();
+    assert_eq!(chunk.data_version(), 0);
+    assert_eq!(chunk.last_update(), 120_000);
+    assert_eq!(chunk.inhabited_time(), 3_600);
+    assert_eq!(
+        chunk.status(),
+        &ChunkStatus::Other("liquid_carvers".to_owned())
+    );
+    assert_eq!(ChunkStatus::parse("noise"), ChunkStatus::Noise);
+    assert!(ChunkStatus::Noise < ChunkStatus::Full);
+    assert_eq!(ChunkStatus::Light.to_string(), "minecraft:light");
+}
+
+#[test]
+fn test_parse_chunk_metadata() {
+    let mut fields: HashMap<_, _> = uniform_chunk(-4, 1, "minecraft:stone").into();
+    fields.insert(string("Status"), NbtTag::String(string("features")));
+    fields.insert(string("InhabitedTime"), NbtTag::Long(3_600));
+    let bytes = chunk_bytes(&fields.into());
+    assert_eq!(
+        parse_chunk_metadata(&bytes).unwrap(),
+        ChunkMetadata {
+            x_pos: 0,
+            y_pos: -4,
+            z_pos: 0,
+            data_version: 4325,
+            status: ChunkStatus::Features,
+            last_update: 0,
+            inhabited_time: 3_600,
+        }
+    );
+
+    // sections aren't looked at
+    let broken = compound([
+        ("xPos", NbtTag::Int(1)),
+        ("yPos", NbtTag::Int(0)),
+        ("zPos", NbtTag::Int(2)),
+        ("sections", NbtTag::Int(0)),
+    ]);
+    let metadata = parse_chunk_metadata(&chunk_bytes(&broken)).unwrap();
+    assert_eq!((metadata.x_pos, metadata.z_pos), (1, 2));
+    assert!(parse_chunk(&chunk_bytes(&broken)).is_err());
+    assert_eq!(
+        parse_chunk_metadata(&chunk_bytes(&compound([])))
+            .unwrap_err()
+            .to_string(),
+        "the field yPos is missing or has an invalid type, expected int"
+    );
+}
+
+fn error_message(root: &NbtCompound) -> String {
+    parse_chunk(&chunk_bytes(root)).unwrap_err().to_string()
+}
+
+fn palette_section(palette: Vec<NbtCompound>, data: Option<Vec<i64>>) -> NbtCompound {
+    let mut block_states = vec![(string("palette"), compound_list(palette))];
+    if let Some(data) = data {
+        block_states.push((string("data"), NbtTag::LongArray(data.try_into().unwrap())));
+    }
+    compound([(
+        "block_states",
+        NbtTag::Compound(NbtCompound::from(
+            block_states.into_iter().collect::<HashMap<_, _>>(),
+        )),
+    )])
+}
+
+#[test]
+fn test_error_field_paths() {
+    let stone = || compound([("Name", NbtTag::String(string("minecraft:stone")))]);
+    let sections = |third: NbtCompound| {
+        compound([
+            ("xPos", NbtTag::Int(0)),
+            ("yPos", NbtTag::Int(0)),
+            ("zPos", NbtTag::Int(0)),
+            (
+                "sections",
+                compound_list(vec![
+                    palette_section(vec![stone()], None),
+                    palette_section(vec![stone()], None),
+                    third,
+                ]),
+            ),
+        ])
+    };
+
+    assert_eq!(
+        error_message(&compound([("yPos", NbtTag::String(string("0")))])),
+        "the field yPos is missing or has an invalid type, expected int"
+    );
+    assert_eq!(
+        error_message(&sections(compound([]))),
+        "the field sections[2].block_states is missing or has an invalid type, expected compound"
+    );
+    assert_eq!(
+        error_message(&sections(palette_section(
+            vec![stone(), compound([("Name", NbtTag::Int(1))])],
+            None
+        ))),
+        "the field sections[2].block_states.palette[1].Name is missing or has an invalid type, \
+         expected string"
+    );
+    assert_eq!(
+        error_message(&sections(palette_section(vec![stone(), stone()], None))),
+        "the field sections[2].block_states.data is missing or has an invalid type, \
+         expected long array"
+    );
+    assert_eq!(
+        error_message(&sections(palette_section(
+            vec![stone(), stone()],
+            Some(vec![0; 3])
+        ))),
+        "section y=2: sections[2].block_states.data: 3 longs of indices, expected at least 256 \
+         for 4 bits per index"
+    );
+
+    let mut data = vec![0; 256];
+    data[0] = 14;
+    let root = sections(palette_section(vec![stone(); 12], Some(data)));
+    let message = "section y=2: sections[2].block_states.data: palette index 14 out of range \
+                   (palette has 12 entries)";
+    assert_eq!(error_message(&root), message);
+    // the region adds the slot
+    let options = RegionParseOptions {
+        strict: true,
+        ..Default::default()
+    };
+    let bytes = region_bytes(&[(17, 4, chunk_bytes(&root))]);
+    assert_eq!(
+        Region::parse_bytes_with(&bytes, &options)
+            .unwrap_err()
+            .to_string(),
+        format!("chunk (17, 4): {message}")
+    );
+}
+
+#[test]
+fn test_malformed_input() {
+    let section = |palette: NbtList| {
+        compound([(
+            "block_states",
+            NbtTag::Compound(compound([("palette", NbtTag::List(palette))])),
+        )])
+    };
+    let chunk = |section: NbtCompound| {
+        compound([
+            ("xPos", NbtTag::Int(0)),
+            ("yPos", NbtTag::Int(0)),
+            ("zPos", NbtTag::Int(0)),
+            ("sections", compound_list(vec![section])),
+        ])
+    };
+    let stone = compound([("Name", NbtTag::String(string("minecraft:stone")))]);
+
+    // a palette needs at least one entry, and at most one per block
+    for palette in [Vec::new(), vec![stone; 4097]] {
+        let root = chunk(section(NbtList::Compound(palette.try_into().unwrap())));
+        assert!(matches!(
+            parse_chunk(&chunk_bytes(&root)),
+            Err(ChunkParseError::InvalidPalette(_))
+        ));
+    }
+
+    // a length that doesn't fit in the input
+    let mut bytes = chunk_bytes(&uniform_chunk(0, 1, "minecraft:stone"));
+    bytes[..4].copy_from_slice(&u32::MAX.to_be_bytes());
+    assert!(matches!(
+        parse_chunk(&bytes),
+        Err(ChunkParseError::InputTooShort(..))
+    ));
+    assert!(matches!(
+        parse_chunk(&[0, 0, 0, 0]),
+        Err(ChunkParseError::InputTooShort(5, 4))
+    ));
+}
+
+#[test]
+fn test_spanning_indices() {
+    // 20 blocks take 5 bits per index, which don't divide 64
+    let palette: Vec<_> = (0..20)
+        .map(|i| compound([("Name", NbtTag::String(string(&format!("test:block_{i}"))))]))
+        .collect();
+    let indices: Vec<u64> = (0..4096).map(|i| (i * 7 % 20) as u64).collect();
+
+    let mut padded = vec![0i64; 4096usize.div_ceil(12)];
+    for (i, &index) in indices.iter().enumerate() {
+        padded[i / 12] |= (index << (i % 12 * 5)) as i64;
+    }
+    let mut spanning = vec![0i64; 4096 * 5 / 64];
+    for (i, &index) in indices.iter().enumerate() {
+        let (long, offset) = (i * 5 / 64, i * 5 % 64);
+        spanning[long] |= (index << offset) as i64;
+        if offset + 5 > 64 {
+            spanning[long + 1] |= (index >> (64 - offset)) as i64;
+        }
+    }
+
+    let chunk = |data_version: i32, data: Vec<i64>| {
+        let root = compound([
+            ("DataVersion", NbtTag::Int(data_version)),
+            ("xPos", NbtTag::Int(0)),
+            ("yPos", NbtTag::Int(0)),
+            ("zPos", NbtTag::Int(0)),
+            (
+                "sections",
+                compound_list(vec![palette_section(palette.clone(), Some(data))]),
+            ),
+        ]);
+        parse_chunk(&chunk_bytes(&root))
+    };
+
+    let old = chunk(2230, spanning.clone()).unwrap();
+    let new = chunk(2586, padded).unwrap();
+    assert!(old.diff(&new).is_empty());
+    assert_eq!(old.get(1, 0, 0).unwrap().get_name(), "test:block_7");
+    // index 12 starts at bit 60 and continues in the second long
+    assert_eq!(old.get(12, 0, 0).unwrap().get_name(), "test:block_4");
+
+    // too short for padded indices
+    assert!(matches!(
+        chunk(2586, spanning),
+        Err(ChunkParseError::InvalidSectionData {
+            section_y: Some(0),
+            reason: SectionDataError::DataTooShort {
+                len: 320,
+                expected: 342,
+                bits: 5
+            },
+            ..
+        })
+    ));
+}
+
+fn level_chunk(sections: Vec<NbtCompound>) -> NbtCompound {
+    // 9 bit heights spanning longs, every column 5 blocks above the bottom
+    let mut heights = vec![0i64; 256 * 9 / 64];
+    for i in 0..256 {
+        let (long, offset) = (i * 9 / 64, i * 9 % 64);
+        heights[long] |= 5 << offset;
+        if offset + 9 > 64 {
+            heights[long + 1] |= 5 >> (64 - offset);
+        }
+    }
+    let level = compound([
+        ("xPos", NbtTag::Int(3)),
+        ("zPos", NbtTag::Int(-2)),
+        ("Status", NbtTag::String(string("features"))),
+        ("InhabitedTime", NbtTag::Long(42)),
+        ("Sections", compound_list(sections)),
+        (
+            "TileEntities",
+            compound_list(vec![compound([
+                ("id", NbtTag::String(string("minecraft:furnace"))),
+                ("x", NbtTag::Int(49)),
+                ("y", NbtTag::Int(5)),
+                ("z", NbtTag::Int(-30)),
+            ])]),
+        ),
+        (
+            "Heightmaps",
+            NbtTag::Compound(compound([(
+                "WORLD_SURFACE",
+                NbtTag::LongArray(heights.try_into().unwrap()),
+            )])),
+        ),
+    ]);
+    compound([
+        ("DataVersion", NbtTag::Int(2230)),
+        ("Level", NbtTag::Compound(level)),
+    ])
+}
+
+#[test]
+fn test_level_chunk() {
+    let name = |name: &str| compound([("Name", NbtTag::String(string(name)))]);
+    // the bottom layer is stone
+    let mut data = vec![0i64; 256];
+    data[..16].fill(0x1111_1111_1111_1111);
+    let section = |y: i8, palette: Vec<NbtCompound>, data: Vec<i64>| {
+        compound([
+            ("Y", NbtTag::Byte(y)),
+            ("Palette", compound_list(palette)),
+            ("BlockStates", NbtTag::LongArray(data.try_into().unwrap())),
+        ])
+    };
+    let root = level_chunk(vec![
+        // only light
+        compound([("Y", NbtTag::Byte(-1))]),
+        section(
+            0,
+            vec![name("minecraft:air"), name("minecraft:stone")],
+            data,
+        ),
+        section(2, vec![name("minecraft:dirt")], vec![0; 256]),
+    ]);
+
+    let chunk = parse_chunk(&chunk_bytes(&root)).unwrap();
+    assert_eq!(chunk.get_pos(), ChunkPos::new(3, -2));
+    assert_eq!(chunk.data_version(), 2230);
+    assert_eq!(chunk.status(), &ChunkStatus::Features);
+    assert_eq!(chunk.inhabited_time(), 42);
+    assert_eq!(
+        chunk.get_block_entity(1, 5, 2).unwrap().id,
+        "minecraft:furnace"
+    );
+    assert_eq!(chunk.get_y_range(), 0..256);
+    assert_eq!(chunk.get(4, 0, 9).unwrap().get_name(), "minecraft:stone");
+    assert!(chunk.get(4, 1, 9).unwrap().is_air());
+    assert!(chunk.get_section(1).unwrap().is_empty());
+    assert_eq!(chunk.get(0, 40, 0).unwrap().get_name(), "minecraft:dirt");
+    let heightmap = chunk.get_heightmap(HeightmapKind::WorldSurface).unwrap();
+    assert_eq!(heightmap.get(15, 15), Some(5));
+
+    let upper = ChunkParseOptions {
+        y_range: Some(20..100),
+        ..Default::default()
+    };
+    let upper = parse_chunk_with(&chunk_bytes(&root), &upper).unwrap();
+    assert_eq!(upper.get_y_range(), 16..112);
+    assert_eq!(upper.get(0, 40, 0).unwrap().get_name(), "minecraft:dirt");
+    assert_eq!(upper.get(4, 0, 9), None);
+
+    let empty = parse_chunk(&chunk_bytes(&level_chunk(vec![]))).unwrap();
+    assert_eq!(empty.get_y_range(), 0..256);
+    assert!(empty.get(0, 0, 0).unwrap().is_air());
+
+    let numeric = level_chunk(vec![compound([
+        ("Y", NbtTag::Byte(0)),
+        (
+            "Blocks",
+            NbtTag::ByteArray(vec![0; 4096].try_into().unwrap()),
+        ),
+    ])]);
+    assert_eq!(
+        error_message(&numeric),
+        "the field Level.Sections[0].Data is missing or has an invalid type, \
+         expected byte array"
+    );
+}
+
+#[test]
+fn test_y_range() {
+    let region = Region::parse_bytes(include_bytes!("data/r.0.0.mca")).unwrap();
+    let raw = region.get_chunk_raw(5, 9).unwrap();
+    let full = region.get_chunk(5, 9).unwrap();
+    let with_range = |y_range| {
+        let options = ChunkParseOptions {
+            y_range: Some(y_range),
+            ..Default::default()
+        };
+        raw.decode_with(&options).unwrap()
+    };
+
+    let upper = with_range(0..320);
+    assert_eq!(upper.get_y_range(), 0..320);
+    assert_eq!(upper.get(0, -1, 0), None);
+    for y in [0, 100, 319] {
+        assert_eq!(upper.get(3, y, 7), full.get(3, y, 7));
+    }
+    assert_eq!(
+        upper.get_heightmap(HeightmapKind::WorldSurface),
+        full.get_heightmap(HeightmapKind::WorldSurface)
+    );
+
+    // sections only partly in the range are decoded whole
+    let middle = with_range(-1..1);
+    assert_eq!(middle.get_y_range(), -16..16);
+    assert_eq!(middle.get(0, -16, 0), full.get(0, -16, 0));
+    assert!(middle.get(0, 16, 0).is_none());
+
+    let none = with_range(1000..2000);
+    assert_eq!(none.get_y_range().len(), 0);
+    assert!(none.get(0, 0, 0).is_none());
+}
+
+#[test]
+fn test_lazy_sections() {
+    let region = Region::parse_bytes(include_bytes!("data/r.0.0.mca")).unwrap();
+    let raw = region.get_chunk_raw(5, 9).unwrap();
+    let full = region.get_chunk(5, 9).unwrap();
+    let lazy_options = ChunkParseOptions {
+        lazy_sections: true,
+        ..Default::default()
+    };
+    let lazy = raw.decode_with(&lazy_options).unwrap();
+    let undecoded = || lazy.sections().filter(|(_, s)| !s.is_decoded()).count();
+    let packed = undecoded();
+    assert!(packed > 0);
+
+    assert_eq!(lazy.get(3, 20, 7), full.get(3, 20, 7));
+    assert!(lazy.get_section(1).unwrap().is_decoded());
+    assert_eq!(undecoded(), packed - 1);
+
+    let mut edited = lazy.clone();
+    let dirt = Block::new(string("minecraft:dirt"), None);
+    edited.set(0, -60, 0, dirt.clone()).unwrap();
+    assert_eq!(edited.get(0, -60, 0), Some(&dirt));
+    assert_eq!(edited.get(1, -60, 0), full.get(1, -60, 0));
+
+    assert_eq!(&lazy, full);
+    assert_eq!(undecoded(), 0);
+
+    // indices past the palette and data that's too short fail lazily
+    // parsed chunks like eager ones
+    let name = |name: &str| compound([("Name", NbtTag::String(string(name)))]);
+    let chunk = |data: Vec<i64>| {
+        let section = palette_section(
+            vec![name("minecraft:air"), name("minecraft:stone")],
+            Some(data),
+        );
+        chunk_bytes(&compound([
+            ("xPos", NbtTag::Int(0)),
+            ("yPos", NbtTag::Int(0)),
+            ("zPos", NbtTag::Int(0)),
+            ("sections", compound_list(vec![section])),
+        ]))
+    };
+    let out_of_range = |result: Result<Chunk, ChunkParseError>, expected: u64| {
+        matches!(
+            result,
+            Err(ChunkParseError::InvalidSectionData {
+                section_y: Some(0),
+                reason: SectionDataError::IndexOutOfRange {
+                    index,
+                    palette_len: 2
+                },
+                ..
+            }) if index == expected
+        )
+    };
+    let mut data = vec![0x1111_1111_1111_1111; 256];
+    data[0] = 3;
+    assert!(out_of_range(parse_chunk(&chunk(data.clone())), 3));
+    assert!(out_of_range(
+        parse_chunk_with(&chunk(data.clone()), &lazy_options),
+        3
+    ));
+    // the very last block
+    let mut last = vec![0x1111_1111_1111_1111; 256];
+    last[255] = 0x5111_1111_1111_1111;
+    assert!(out_of_range(
+        parse_chunk_with(&chunk(last), &lazy_options),
+        5
+    ));
+
+    // leniently they read as the first entry, with a warning either way
+    let warning = ParseWarning::IndexOutOfRange {
+        path: "sections[0].block_states.data".to_owned(),
+        index: 3,
+    };
+    for lazy_sections in [false, true] {
+        let options = ChunkParseOptions {
+            lenient: true,
+            lazy_sections,
+            ..Default::default()
+        };
+        let chunk = parse_chunk_with(&chunk(data.clone()), &options).unwrap();
+        assert_eq!(chunk.parse_warnings(), std::slice::from_ref(&warning));
+        assert!(chunk.get(0, 0, 0).unwrap().is_air());
+        assert_eq!(chunk.get(0, 1, 0).unwrap().get_name(), "minecraft:stone");
+    }
+    assert!(matches!(
+        parse_chunk_with(&chunk(vec![0; 3]), &lazy_options),
+        Err(ChunkParseError::InvalidSectionData {
+            reason: SectionDataError::DataTooShort { len: 3, .. },
+            ..
+        })
+    ));
+}
+
+#[test]
+fn test_biomes() {
+    let biomes = |palette: &[&str], data: Option<Vec<i64>>| {
+        let palette = palette.iter().map(|&name| string(name)).collect::<Vec<_>>();
+        let mut fields = vec![(
+            string("palette"),
+            NbtTag::List(NbtList::String(palette.try_into().unwrap())),
+        )];
+        if let Some(data) = data {
+            fields.push((string("data"), NbtTag::LongArray(data.try_into().unwrap())));
+        }
+        NbtTag::Compound(fields.into_iter().collect::<HashMap<_, _>>().into())
+    };
+    let chunk = |sections: Vec<NbtTag>| {
+        let mut root: HashMap<_, _> =
+            uniform_chunk(0, sections.len() as i32, "minecraft:stone").into();
+        let Some(NbtTag::List(NbtList::Compound(stored))) = root.remove("sections") else {
+            unreachable!()
+        };
+        let stored: Vec<_> = stored
+            .iter()
+            .zip(sections)
+            .map(|(section, biomes)| {
+                let mut section: HashMap<_, _> = section.clone().into();
+                section.insert(string("biomes"), biomes);
+                section.into()
+            })
+            .collect();
+        root.insert(string("sections"), compound_list(stored));
+        root.insert(string("isLightOn"), NbtTag::Byte(1));
+        parse_chunk(&chunk_bytes(&root.into()))
+    };
+
+    // 2 bits per cell, the top 4x4x4 layer is a river
+    let mut data = vec![0i64; 2];
+    data[1] = 0x5555_5555_0000_0000;
+    let parsed = chunk(vec![
+        biomes(&["minecraft:plains"], None),
+        biomes(
+            &["minecraft:plains", "minecraft:river", "minecraft:ocean"],
+            Some(data),
+        ),
+    ])
+    .unwrap();
+    assert_eq!(parsed.get_biome(3, 0, 15).unwrap(), "minecraft:plains");
+    assert_eq!(parsed.get_biome(15, 27, 0).unwrap(), "minecraft:plains");
+    assert_eq!(parsed.get_biome(15, 28, 0).unwrap(), "minecraft:river");
+    assert_eq!(parsed.get_biome(0, 32, 0), None);
+    assert_eq!(parsed.get_biome(16, 0, 0), None);
+    let section = parsed.get_section(1).unwrap();
+    assert_eq!(section.get_biome(0, 15, 0).unwrap(), "minecraft:river");
+    let counts = parsed.biome_counts();
+    assert_eq!(counts.len(), 2);
+    assert_eq!(counts[&string("minecraft:plains")], 64 + 48);
+    assert_eq!(counts[&string("minecraft:river")], 16);
+    assert_eq!(section.biome_counts().len(), 2);
+    let river = parsed.find_biome("minecraft:river");
+    assert_eq!(river.len(), 16);
+    assert_eq!(river[0], (2, 30, 2));
+    assert_eq!(river[15], (14, 30, 14));
+    assert!(parsed.find_biome("minecraft:ocean").is_empty());
+    assert_eq!(
+        parsed.to_cache_bytes(),
+        Chunk::from_cache_bytes(&parsed.to_cache_bytes())
+            .unwrap()
+            .to_cache_bytes()
+    );
+
+    let err = chunk(vec![biomes(&["minecraft:plains", "minecraft:river"], None)]).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "the field sections[0].biomes.data is missing or has an invalid type, expected long array"
+    );
+
+    let real = Region::parse_bytes(include_bytes!("data/r.0.0.mca")).unwrap();
+    let real = real.get_chunk(0, 0).unwrap();
+    assert_eq!(real.get_biome(0, 64, 0).unwrap(), "minecraft:forest");
+    let counts = real.biome_counts();
+    assert_eq!(counts.len(), 2);
+    assert_eq!(counts[&string("minecraft:forest")], 960);
+    assert_eq!(counts[&string("minecraft:river")], 576);
+    let dominant = |x, z| real.dominant_biome_at(x, z).unwrap().to_string();
+    assert_eq!(dominant(0, 0), "minecraft:forest");
+    assert_eq!(dominant(0, 15), "minecraft:forest");
+    assert_eq!(dominant(15, 4), "minecraft:river");
+    assert_eq!(dominant(15, 15), "minecraft:river");
+    assert_eq!(real.dominant_biome_at(16, 0), None);
+    assert_eq!(parsed.dominant_biome_at(0, 0).unwrap(), "minecraft:plains");
+    // sections without biomes, like ones from the builder
+    let built = ChunkBuilder::new(0, 0).build().unwrap();
+    assert_eq!(built.get_biome(0, 0, 0), None);
+    assert!(built.biome_counts().is_empty());
+    assert_eq!(built.dominant_biome_at(0, 0), None);
+}
+
+#[test]
+fn test_light() {
+    let with_light = |block_light: Vec<i8>| {
+        let mut root: HashMap<_, _> = uniform_chunk(0, 2, "minecraft:stone").into();
+        let Some(NbtTag::List(NbtList::Compound(stored))) = root.remove("sections") else {
+            unreachable!()
+        };
+        let mut stored: Vec<_> = stored.iter().cloned().collect();
+        let mut section: HashMap<_, _> = stored[1].clone().into();
+        section.insert(
+            string("BlockLight"),
+            NbtTag::ByteArray(block_light.try_into().unwrap()),
+        );
+        section.insert(
+            string("SkyLight"),
+            NbtTag::ByteArray(vec![-1i8; 2048].try_into().unwrap()),
+        );
+        stored[1] = section.into();
+        root.insert(string("sections"), compound_list(stored));
+        parse_chunk(&chunk_bytes(&root.into()))
+    };
+
+    // low nibble first, so block 1 is in the high nibble of the first byte
+    let mut block_light = vec![0i8; 2048];
+    block_light[0] = 0x7F;
+    block_light[2047] = 0x30;
+    let chunk = with_light(block_light).unwrap();
+    assert_eq!(chunk.get_block_light(0, 16, 0), Some(15));
+    assert_eq!(chunk.get_block_light(1, 16, 0), Some(7));
+    assert_eq!(chunk.get_block_light(2, 16, 0), Some(0));
+    assert_eq!(chunk.get_block_light(15, 31, 15), Some(3));
+    assert_eq!(chunk.get_sky_light(4, 20, 9), Some(15));
+    // not stored is different from unlit
+    assert_eq!(chunk.get_block_light(0, 0, 0), None);
+    assert_eq!(chunk.get_sky_light(0, 0, 0), None);
+    assert_eq!(chunk.get_block_light(0, 32, 0), None);
+    assert_eq!(chunk.get_block_light(16, 16, 0), None);
+    assert_eq!(
+        chunk.get_section(1).unwrap().get_block_light(1, 0, 0),
+        Some(7)
+    );
+    assert_eq!(
+        Chunk::from_cache_bytes(&chunk.to_cache_bytes()).unwrap(),
+        chunk
+    );
+
+    assert_eq!(
+        with_light(vec![0; 100]).unwrap_err().to_string(),
+        "section y=1: sections[1].BlockLight: 100 entries, expected 2048"
+    );
+
+    // without the raw nbt, the game lights the chunk again, edited or not
+    let mut chunk = chunk;
+    chunk
+        .set(0, 16, 0, Block::new(string("minecraft:glowstone"), None))
+        .unwrap();
+    assert!(chunk.to_nbt().get("isLightOn").is_none());
+
+    chunk.set_block_light(0, 16, 0, 200).unwrap();
+    assert_eq!(chunk.get_block_light(0, 16, 0), Some(15));
+    chunk.set_block_light(1, 16, 0, 3).unwrap();
+    assert_eq!(chunk.get_block_light(1, 16, 0), Some(3));
+    assert_eq!(chunk.get_block_light(0, 16, 0), Some(15));
+    // unlit until set
+    chunk.set_sky_light(5, 2, 5, 9).unwrap();
+    assert_eq!(chunk.get_sky_light(5, 2, 5), Some(9));
+    assert_eq!(chunk.get_sky_light(5, 3, 5), Some(0));
+    assert!(chunk.set_block_light(16, 16, 0, 1).is_err());
+    assert!(chunk.set_block_light(0, 32, 0, 1).is_err());
+
+    let read_back = parse_chunk(&chunk.to_bytes()).unwrap();
+    assert_eq!(read_back.get_block_light(1, 16, 0), Some(3));
+    chunk.clear_light();
+    assert_eq!(chunk.get_block_light(1, 16, 0), None);
+    assert_eq!(chunk.get_sky_light(5, 2, 5), None);
+}
+
+#[test]
+fn test_block_entities() {
+    let entity = |id: &str, x: i32, y: i32, z: i32| {
+        compound([
+            ("id", NbtTag::String(string(id))),
+            ("x", NbtTag::Int(x)),
+            ("y", NbtTag::Int(y)),
+            ("z", NbtTag::Int(z)),
+            ("keepPacked", NbtTag::Byte(0)),
+        ])
+    };
+    let with_entities = |entities: Vec<NbtCompound>| {
+        let mut root: HashMap<_, _> = uniform_chunk(-1, 2, "minecraft:stone").into();
+        root.insert(string("xPos"), NbtTag::Int(-2));
+        root.insert(string("zPos"), NbtTag::Int(5));
+        root.insert(string("block_entities"), compound_list(entities));
+        parse_chunk(&chunk_bytes(&root.into()))
+    };
+
+    let chunk = with_entities(vec![
+        entity("minecraft:sign", -20, 3, 95),
+        entity("minecraft:chest", -32, -16, 80),
+        entity("minecraft:chest", -31, -16, 80),
+    ])
+    .unwrap();
+    let ids: Vec<_> = chunk
+        .block_entities()
+        .iter()
+        .map(|entity| (entity.x, entity.id.to_string()))
+        .collect();
+    assert_eq!(
+        ids,
+        [
+            (-32, "minecraft:chest".to_owned()),
+            (-31, "minecraft:chest".to_owned()),
+            (-20, "minecraft:sign".to_owned())
+        ]
+    );
+    let sign = chunk.get_block_entity(12, 3, 15).unwrap();
+    assert_eq!(sign.id, "minecraft:sign");
+    assert_eq!(sign.data.get("keepPacked"), Some(&NbtTag::Byte(0)));
+    assert_eq!(chunk.get_block_entity(1, -16, 0).unwrap().x, -31);
+    assert!(chunk.get_block_entity(12, 4, 15).is_none());
+    assert!(chunk.get_block_entity(16, 3, 15).is_none());
+    assert_eq!(
+        Chunk::from_cache_bytes(&chunk.to_cache_bytes()).unwrap(),
+        chunk
+    );
+
+    let mut broken: HashMap<_, _> = entity("minecraft:chest", 0, 0, 0).into();
+    broken.remove("y");
+    assert_eq!(
+        with_entities(vec![entity("minecraft:chest", 0, 0, 0), broken.into()])
+            .unwrap_err()
+            .to_string(),
+        "the field block_entities[1].y is missing or has an invalid type, expected int"
+    );
+}
+
+#[test]
+fn test_set_block_entity() {
+    let sign = |lines: [&str; 4]| {
+        let messages = lines.map(|line| string(&format!("\"{line}\"")));
+        let text = compound([(
+            "messages",
+            NbtTag::List(NbtList::String(messages.to_vec().try_into().unwrap())),
+        )]);
+        BlockEntity {
+            id: string("minecraft:oak_sign"),
+            x: -30,
+            y: 70,
+            z: 84,
+            data: compound([
+                ("id", NbtTag::String(string("minecraft:oak_sign"))),
+                ("x", NbtTag::Int(-30)),
+                ("y", NbtTag::Int(70)),
+                ("z", NbtTag::Int(84)),
+                ("front_text", NbtTag::Compound(text)),
+            ]),
+        }
+    };
+    let mut root: HashMap<_, _> = uniform_chunk(-4, 24, "minecraft:stone").into();
+    root.insert(string("xPos"), NbtTag::Int(-2));
+    root.insert(string("zPos"), NbtTag::Int(5));
+    let mut chunk = parse_chunk(&chunk_bytes(&root.into())).unwrap();
+
+    let old = sign(["Hello", "", "", ""]);
+    assert_eq!(chunk.set_block_entity(old.clone()), Ok(None));
+    let new = sign(["Goodbye", "world", "", ""]);
+    assert_eq!(chunk.set_block_entity(new.clone()), Ok(Some(old)));
+    assert_eq!(chunk.block_entities().len(), 1);
+
+    let reparsed = parse_chunk(&chunk.to_bytes()).unwrap();
+    assert_eq!(reparsed.get_block_entity(2, 70, 4), Some(&new));
+    assert_eq!(reparsed, chunk);
+
+    let mut outside = new.clone();
+    outside.x = -33;
+    assert_eq!(
+        chunk.set_block_entity(outside),
+        Err(OutOfBounds {
+            axis: Axis::X,
+            value: -33,
+            range: -32..-16,
+        })
+    );
+    assert_eq!(chunk.remove_block_entity(2, 71, 4), None);
+    assert_eq!(chunk.remove_block_entity(2, 70, 4), Some(new));
+    assert!(chunk.block_entities().is_empty());
+    let reparsed = parse_chunk(&chunk.to_bytes()).unwrap();
+    assert!(reparsed.block_entities().is_empty());
+}
+
+#[test]
+fn test_error_source() {
+    let mut bytes = 4u32.to_be_bytes().to_vec();
+    bytes.extend_from_slice(&[3, 0xFF, 0xFF, 0xFF]);
+
+    let err = parse_chunk(&bytes).unwrap_err();
+    assert!(matches!(err, ChunkParseError::ParseFailed(_)));
+    let source = err.source().unwrap();
+    assert!(source.downcast_ref::<ParseError>().is_some());
+}
+
+#[test]
+fn test_world_coordinates() {
+    let mut chunk = parse_alpha_chunk(include_bytes!("data/c.-1.1.dat")).unwrap();
+    assert_eq!(chunk.get_pos(), ChunkPos::new(-1, 1));
+
+    // local (3, 64, 5) is the red wool
+    let wool = chunk.get(3, 64, 5).unwrap().clone();
+    assert_eq!(chunk.get_world(-13, 64, 21), Some(&wool));
+    assert_eq!(chunk.get_world(-16, 0, 16), chunk.get(0, 0, 0));
+    assert_eq!(chunk.get_world(-1, 0, 31), chunk.get(15, 0, 15));
+
+    // neighbours must not wrap around into this chunk
+    assert!(chunk.get_world(3, 64, 5).is_none());
+    assert!(chunk.get_world(0, 0, 16).is_none());
+    assert!(chunk.get_world(-17, 0, 16).is_none());
+    assert!(chunk.get_world(-1, 0, 32).is_none());
+    assert!(chunk.get_world(-1, 128, 16).is_none());
+
+    chunk.set_block_world(-1, 127, 31, wool.clone()).unwrap();
+    assert_eq!(chunk.get(15, 127, 15), Some(&wool));
+    assert_eq!(
+        chunk.set_block_world(0, 0, 16, wool.clone()),
+        Err(OutOfBounds {
+            axis: Axis::X,
+            value: 0,
+            range: -16..0
+        })
+    );
+    assert_eq!(
+        chunk
+            .set_block_world(-1, 0, 15, wool.clone())
+            .unwrap_err()
+            .axis,
+        Axis::Z
+    );
+    assert_eq!(
+        chunk
+            .set_block_world(-1, 1 << 20, 16, wool)
+            .unwrap_err()
+            .axis,
+        Axis::Y
+    );
+}
+
+#[test]
+fn test_index() {
+    let chunk = parse_chunk(&chunk_bytes(&uniform_chunk(-4, 24, "minecraft:stone"))).unwrap();
+    let section = chunk.get_section(-4).unwrap();
+    assert_eq!(
+        &section[(15, 15, 15)],
+        section.get_block(15, 15, 15).unwrap()
+    );
+    assert_eq!(chunk[(0, -64, 0)].get_name(), "minecraft:stone");
+    assert_eq!(&chunk[(15, 319, 15)], chunk.get(15, 319, 15).unwrap());
+
+    // air just past both ends of the y range
+    assert_eq!(chunk.get_or_air(0, -64, 0), &chunk[(0, -64, 0)]);
+    assert_eq!(chunk.get_or_air(0, 319, 0), &chunk[(0, 319, 0)]);
+    for y in [-65, 320, i32::MIN, i32::MAX] {
+        assert!(chunk.get(0, y, 0).is_none());
+        assert_eq!(chunk.get_or_air(0, y, 0).get_name(), "minecraft:air");
+    }
+    // the same air every time, which doesn't make the chunk differ
+    assert!(std::ptr::eq(
+        chunk.get_or_air(0, 320, 0),
+        chunk.get_or_air(3, -100, 3)
+    ));
+    assert_eq!(chunk.clone(), chunk);
+}
+
+#[test]
+#[should_panic(expected = "block (0, 16, 0) out of range for a section, coordinates go up to 15")]
+fn test_index_section_out_of_range() {
+    let _ = &Section::empty()[(0, 16, 0)];
+}
+
+#[test]
+#[should_panic(
+    expected = "block (0, 320, 0) out of range for a chunk spanning y -64..320, x and z go up to 15"
+)]
+fn test_index_chunk_out_of_range() {
+    let chunk = parse_chunk(&chunk_bytes(&uniform_chunk(-4, 24, "minecraft:stone"))).unwrap();
+    let _ = &chunk[(0, 320, 0)];
+}
+
+#[test]
+#[should_panic(
+    expected = "block (16, 0, 0) out of range for a chunk spanning y -64..320, x and z go up to 15"
+)]
+fn test_get_or_air_out_of_range() {
+    let chunk = parse_chunk(&chunk_bytes(&uniform_chunk(-4, 24, "minecraft:stone"))).unwrap();
+    chunk.get_or_air(16, 0, 0);
+}
+
+#[test]
+fn test_iterate_sections() {
+    let mut chunk = parse_chunk(&chunk_bytes(&uniform_chunk(-4, 24, "minecraft:stone"))).unwrap();
+    let ys: Vec<i32> = (&chunk).into_iter().map(|(y, _)| y).collect();
+    assert_eq!(ys, (-4..20).collect::<Vec<_>>());
+    assert_eq!(chunk.sections().len(), 24);
+    assert_eq!(chunk.section_count(), 24);
+    assert_eq!(
+        chunk.sections().next(),
+        Some((-4, chunk.get_section(-4).unwrap()))
+    );
+    assert_eq!(chunk.sections().next_back().unwrap().0, 19);
+
+    for (y, section) in &chunk {
+        assert_eq!(Some(section), chunk.get_section(y));
+    }
+
+    let dirt = parse_chunk(&chunk_bytes(&uniform_chunk(0, 1, "minecraft:dirt"))).unwrap();
+    let dirt = dirt.get(0, 0, 0).unwrap().clone();
+    for (y, section) in &mut chunk {
+        if y < 0 {
+            section.set_block(0, 0, 0, dirt.clone()).unwrap();
+        }
+    }
+    assert_eq!(chunk.get(0, -64, 0), Some(&dirt));
+    assert_eq!(chunk.get(0, -16, 0), Some(&dirt));
+    assert_ne!(chunk.get(0, 0, 0), Some(&dirt));
+}
+
+#[test]
+fn test_section_addressing() {
+    let chunk = parse_chunk(&chunk_bytes(&uniform_chunk(-4, 24, "minecraft:stone"))).unwrap();
+    assert_eq!(chunk.section_y_range(), -4..20);
+
+    let section = |y| chunk.get_section(y).unwrap() as *const _;
+    let at_block_y = |y| chunk.get_section_at_block_y(y).unwrap() as *const _;
+    assert_eq!(at_block_y(-1), section(-1));
+    assert_eq!(at_block_y(-16), section(-1));
+    assert_eq!(at_block_y(-17), section(-2));
+    assert_eq!(at_block_y(-64), section(-4));
+    assert_eq!(at_block_y(0), section(0));
+    assert_eq!(at_block_y(15), section(0));
+    assert_eq!(at_block_y(319), section(19));
+
+    assert!(chunk.get_section_at_block_y(-65).is_none());
+    assert!(chunk.get_section_at_block_y(320).is_none());
+    assert!(chunk.get_section(-5).is_none());
+    assert!(chunk.get_section(20).is_none());
+}
+
+#[test]
+fn test_section_palette_growth() {
+    let block = |i: usize| Block::new(format!("test:block_{i}").try_into().unwrap(), None);
+    let mut section = Section::filled(block(0));
+
+    // every block distinct, more than a byte can index
+    for i in 0..4096 {
+        let (x, y, z) = ((i & 0xF) as u8, (i >> 8) as u8, ((i >> 4) & 0xF) as u8);
+        section.set_block(x, y, z, block(i)).unwrap();
+    }
+    assert_eq!(section.get_block(15, 15, 15), Some(&block(4095)));
+    assert_eq!(section.get_block(3, 0, 0), Some(&block(3)));
+
+    // overwriting everything leaves the old entries unused, and new ones
+    // still fit once they're dropped
+    for round in 1..4 {
+        for i in 0..4096 {
+            let (x, y, z) = ((i & 0xF) as u8, (i >> 8) as u8, ((i >> 4) & 0xF) as u8);
+            section.set_block(x, y, z, block(round * 4096 + i)).unwrap();
+        }
+    }
+    assert_eq!(section.get_block(15, 15, 15), Some(&block(4 * 4096 - 1)));
+    assert_eq!(section.iter_blocks().count(), 4096);
+    assert_eq!(section.count_non_air(), 4096);
+
+    let mut uniform = Section::filled(block(0));
+    uniform.set_block(1, 2, 3, block(1)).unwrap();
+    uniform.set_block(1, 2, 3, block(0)).unwrap();
+    assert_eq!(uniform, Section::filled(block(0)));
+}
+
+#[test]
+fn test_palette_indices() {
+    let block = |i: usize| Block::new(format!("test:block_{i}").try_into().unwrap(), None);
+    let resolved = |section: &Section| {
+        let indices = section.palette_indices();
+        // the buffers are overwritten, whatever they held
+        let (mut copied, mut palette) = ([7; 4096], vec![Shared::new(block(9))]);
+        section.copy_blocks_into(&mut copied, &mut palette);
+        assert_eq!(copied, *indices);
+        assert_eq!(palette, section.palette());
+        (0..4096)
+            .map(|i| {
+                let (x, y, z) = ((i & 0xF) as u8, (i >> 8) as u8, ((i >> 4) & 0xF) as u8);
+                assert_eq!(
+                    &*section.palette()[indices[i] as usize],
+                    section.get_block(x, y, z).unwrap()
+                );
+            })
+            .count()
+    };
+
+    let mut section = Section::filled(block(0));
+    assert_eq!(*section.palette_indices(), [0; 4096]);
+    section.set_block(1, 2, 3, block(1)).unwrap();
+    assert_eq!(section.palette_indices()[(2 * 16 + 3) * 16 + 1], 1);
+    assert!(matches!(section.palette_indices(), Cow::Owned(_)));
+    assert_eq!(resolved(&section), 4096);
+
+    for i in 0..300 {
+        let (x, y, z) = (i as u8 & 0xF, (i >> 8) as u8, (i >> 4) as u8 & 0xF);
+        section.set_block(x, y, z, block(i)).unwrap();
+    }
+    assert!(matches!(section.palette_indices(), Cow::Borrowed(_)));
+    assert_eq!(resolved(&section), 4096);
+
+    // read lazily, the indices are unpacked on first use
+    let options = ChunkParseOptions {
+        lazy_sections: true,
+        ..Default::default()
+    };
+    let region = Region::parse_bytes(include_bytes!("data/r.0.0.mca")).unwrap();
+    let bytes = region.get_chunk(5, 9).unwrap().to_bytes();
+    let chunk = parse_chunk_with(&bytes, &options).unwrap();
+    for (_, section) in chunk.sections() {
+        assert_eq!(resolved(section), 4096);
+    }
+}
+
+#[test]
+fn test_iter_blocks_non_air() {
+    let air = parse_chunk(&chunk_bytes(&uniform_chunk(-4, 24, "minecraft:air"))).unwrap();
+    assert_eq!(air.iter_blocks().count(), 24 * 4096);
+    assert_eq!(air.iter_blocks_non_air().count(), 0);
+
+    let mut chunk = air.clone();
+    let stone = parse_chunk(&chunk_bytes(&uniform_chunk(0, 1, "minecraft:stone"))).unwrap();
+    let stone = stone.get(0, 0, 0).unwrap().clone();
+    chunk.set(15, -64, 0, stone.clone()).unwrap();
+    chunk.set(3, 100, 9, stone.clone()).unwrap();
+    chunk.set(4, 100, 9, stone.clone()).unwrap();
+    chunk
+        .set(4, 100, 9, air.get(0, 0, 0).unwrap().clone())
+        .unwrap();
+
+    let blocks: Vec<_> = chunk
+        .iter_blocks_non_air()
+        .map(|(x, y, z, block)| (x, y, z, block.clone()))
+        .collect();
+    assert_eq!(
+        blocks,
+        [(15, -64, 0, stone.clone()), (3, 100, 9, stone.clone())]
+    );
+}
+
+#[test]
+fn test_iter_blocks_non_air_matches_filter() {
+    let region = Region::parse_bytes(include_bytes!("data/r.0.0.mca")).unwrap();
+    let chunk = region.get_chunk(5, 9).unwrap();
+
+    let filtered: Vec<_> = chunk
+        .iter_blocks()
+        .filter(|(.., block)| !block.is_air())
+        .collect();
+    let non_air: Vec<_> = chunk.iter_blocks_non_air().collect();
+    assert!(!non_air.is_empty());
+    assert_eq!(non_air, filtered);
+
+    let count: usize = (0..32)
+        .flat_map(|x| (0..32).map(move |z| (x, z)))
+        .filter_map(|(x, z)| region.get_chunk(x, z))
+        .map(|chunk| chunk.iter_blocks_non_air().count())
+        .sum();
+    assert_eq!(region.iter_blocks_non_air().count(), count);
+
+    let (x, y, z, block) = region
+        .iter_blocks_non_air()
+        .find(|&(x, _, z, _)| x >= 80 && z >= 144)
+        .unwrap();
+    assert_eq!(
+        region.get_chunk(5, 9).unwrap().get_world(x, y, z),
+        Some(block)
+    );
+}
+
+#[test]
+fn test_trim_empty_sections() {
+    let air = parse_chunk(&chunk_bytes(&uniform_chunk(-4, 24, "minecraft:air"))).unwrap();
+    let stone = parse_chunk(&chunk_bytes(&uniform_chunk(0, 1, "minecraft:stone"))).unwrap();
+    let stone = stone.get(0, 0, 0).unwrap().clone();
+
+    let mut chunk = air.clone();
+    chunk.set(1, -20, 2, stone.clone()).unwrap();
+    chunk.set(3, 40, 4, stone.clone()).unwrap();
+    chunk.compute_heightmaps();
+    assert_eq!(chunk.trim_empty_sections(), 19);
+    assert_eq!(chunk.section_y_range(), -2..3);
+    assert_eq!(chunk.get_y_range(), -32..48);
+    assert_eq!(chunk.get(1, -20, 2), Some(&stone));
+    assert_eq!(chunk.get(3, 40, 4), Some(&stone));
+    assert!(chunk.get(0, -33, 0).is_none());
+    assert!(chunk.get(0, 48, 0).is_none());
+    assert!(chunk.get_section(0).unwrap().is_empty());
+    assert!(chunk.get_section(3).is_none());
+
+    let heightmap = chunk.get_heightmap(HeightmapKind::WorldSurface).unwrap();
+    assert_eq!(heightmap.get(3, 4), Some(41));
+    assert_eq!(heightmap.get(0, 0), Some(-32));
+    assert_eq!(chunk.trim_empty_sections(), 0);
+
+    let mut chunk = air;
+    assert_eq!(chunk.trim_empty_sections(), 23);
+    assert_eq!(chunk.section_y_range(), -4..-3);
+}
+
+#[test]
+fn test_extended_height() {
+    let mut fields: HashMap<_, _> = uniform_chunk(-64, 96, "minecraft:stone").into();
+    // 1536 blocks high takes 11 bits per column, every column is full
+    let heights = (0..256).map(|_| 1536i64);
+    let mut longs = vec![0i64; 256usize.div_ceil(64 / 11)];
+    for (i, height) in heights.enumerate() {
+        longs[i / 5] |= height << (i % 5 * 11);
+    }
+    fields.insert(
+        string("Heightmaps"),
+        NbtTag::Compound(compound([(
+            "WORLD_SURFACE",
+            NbtTag::LongArray(longs.try_into().unwrap()),
+        )])),
+    );
+    let mut chunk = parse_chunk(&chunk_bytes(&fields.into())).unwrap();
+
+    assert_eq!(chunk.get_y_range(), -1024..512);
+    assert_eq!(chunk.section_y_range(), -64..32);
+    let stone = chunk.get(0, -1024, 0).unwrap().clone();
+    assert_eq!(chunk.get(15, 511, 15), Some(&stone));
+    assert!(chunk.get(0, -1025, 0).is_none());
+    assert!(chunk.get(0, 512, 0).is_none());
+    assert_eq!(chunk.iter_blocks().next().unwrap().1, -1024);
+    assert_eq!(chunk.iter_blocks().last().unwrap().1, 511);
+
+    let stored = chunk.get_heightmap(HeightmapKind::WorldSurface).unwrap();
+    assert_eq!(stored.get(7, 9), Some(512));
+    let stored = stored.clone();
+    chunk.compute_heightmaps();
+    assert_eq!(
+        chunk.get_heightmap(HeightmapKind::WorldSurface),
+        Some(&stored)
+    );
+
+    chunk.set(15, 511, 15, stone.clone()).unwrap();
+    assert_eq!(chunk.set(0, 512, 0, stone).unwrap_err().range, -1024..512);
+}
+
+#[test]
+fn test_y_range_overflow() {
+    let root = uniform_chunk(i32::MAX / 16, 1, "minecraft:stone");
+    assert!(matches!(
+        parse_chunk(&chunk_bytes(&root)),
+        Err(ChunkParseError::InvalidYRange(_, 1))
+    ));
+}
+
+#[test]
+fn test_heightmap_beyond_i16() {
+    // a section at y 40000, past what a height can hold
+    let mut fields: HashMap<_, _> = uniform_chunk(2500, 1, "minecraft:stone").into();
+    fields.insert(
+        string("Heightmaps"),
+        NbtTag::Compound(compound([(
+            "WORLD_SURFACE",
+            NbtTag::LongArray(vec![0i64; 256usize.div_ceil(64 / 5)].try_into().unwrap()),
+        )])),
+    );
+    assert!(matches!(
+        parse_chunk(&chunk_bytes(&fields.into())),
+        Err(ChunkParseError::InvalidHeightmap(path)) if path == "Heightmaps.WORLD_SURFACE"
+    ));
+
+    let mut chunk = parse_chunk(&chunk_bytes(&uniform_chunk(2500, 1, "minecraft:stone"))).unwrap();
+    chunk.compute_heightmaps();
+    assert!(chunk.get_heightmap(HeightmapKind::WorldSurface).is_none());
+}
+
+#[test]
+fn test_non_air_bounds() {
+    let air = parse_chunk(&chunk_bytes(&uniform_chunk(-4, 24, "minecraft:air"))).unwrap();
+    let stone = parse_chunk(&chunk_bytes(&uniform_chunk(0, 1, "minecraft:stone"))).unwrap();
+    let stone = stone.get(0, 0, 0).unwrap().clone();
+    assert_eq!(air.non_air_bounds(), None);
+    assert_eq!(air.get_section(0).unwrap().non_air_bounds(), None);
+
+    let mut chunk = air.clone();
+    chunk.set(0, 319, 15, stone.clone()).unwrap();
+    assert_eq!(chunk.non_air_bounds(), Some(((0, 319, 15), (0, 319, 15))));
+    assert_eq!(
+        chunk.get_section(19).unwrap().non_air_bounds(),
+        Some(((0, 15, 15), (0, 15, 15)))
+    );
+
+    chunk.set(15, -64, 4, stone.clone()).unwrap();
+    chunk.set(7, 15, 0, stone.clone()).unwrap();
+    chunk.set(3, 16, 9, stone.clone()).unwrap();
+    assert_eq!(chunk.non_air_bounds(), Some(((0, -64, 0), (15, 319, 15))));
+
+    let mut chunk = air;
+    chunk.set(3, 15, 5, stone.clone()).unwrap();
+    chunk.set(12, 16, 4, stone.clone()).unwrap();
+    chunk.set(5, 17, 11, stone).unwrap();
+    assert_eq!(chunk.non_air_bounds(), Some(((3, 15, 4), (12, 17, 11))));
+    assert_eq!(
+        chunk.get_section(1).unwrap().non_air_bounds(),
+        Some(((5, 0, 4), (12, 1, 11)))
+    );
+}
+
+#[test]
+fn test_custom_codec() {
+    let nbt = serialize_nbt(&string(""), &uniform_chunk(0, 1, "minecraft:stone"));
+    let id = "example:xor";
+    let mut payload = (id.len() as u16).to_be_bytes().to_vec();
+    payload.extend_from_slice(id.as_bytes());
+    payload.extend(nbt.iter().map(|byte| byte ^ 0x55));
+    let mut bytes = (payload.len() as u32 + 1).to_be_bytes().to_vec();
+    bytes.push(127);
+    bytes.extend_from_slice(&payload);
+
+    assert_eq!(RawChunk::parse(&bytes).unwrap().custom_codec_id(), Some(id));
+    let err = parse_chunk(&bytes).unwrap_err();
+    assert!(matches!(&err, ChunkParseError::UnknownCodec(missing) if missing == id));
+
+    let mut options = ChunkParseOptions::default();
+    options
+        .custom_codecs
+        .register(id, |data| Ok(data.iter().map(|byte| byte ^ 0x55).collect()));
+    let chunk = parse_chunk_with(&bytes, &options).unwrap();
+    assert_eq!(chunk.get(0, 0, 0).unwrap().get_name(), "minecraft:stone");
+
+    options.custom_codecs.register(id, |_| {
+        Err(std::io::Error::new(std::io::ErrorKind::InvalidData, "bad"))
+    });
+    assert!(matches!(
+        parse_chunk_with(&bytes, &options),
+        Err(ChunkParseError::DecompressionFailed(_))
+    ));
+}
+
+#[test]
+fn test_parse_chunk_with_buffer() {
+    let region = Region::parse_bytes(include_bytes!("data/r.0.0.mca")).unwrap();
+    let stone = chunk_bytes(&uniform_chunk(-4, 2, "minecraft:stone"));
+    let mut truncated = stone.clone();
+    truncated.truncate(stone.len() / 2);
+    let len = truncated.len() as u32 - 4;
+    truncated[..4].copy_from_slice(&len.to_be_bytes());
+
+    let mut buffer = Vec::new();
+    for (x, z) in [(5, 9), (0, 0)] {
+        let raw = region.get_chunk_raw(x, z).unwrap();
+        let mut bytes = (raw.compressed_data().len() as u32 + 1)
+            .to_be_bytes()
+            .to_vec();
+        bytes.push(raw.compression().to_header_byte(false));
+        bytes.extend_from_slice(raw.compressed_data());
+        assert_eq!(
+            parse_chunk_with_buffer(&bytes, &mut buffer).unwrap(),
+            *region.get_chunk(x, z).unwrap()
+        );
+    }
+    // what a failed chunk leaves behind doesn't matter to the next one
+    assert!(parse_chunk_with_buffer(&truncated, &mut buffer).is_err());
+    assert_eq!(
+        parse_chunk_with_buffer(&stone, &mut buffer).unwrap(),
+        parse_chunk(&stone).unwrap()
+    );
+}
+
+#[test]
+fn test_keep_nbt() {
+    let mut fields: HashMap<_, _> = uniform_chunk(-4, 1, "minecraft:stone").into();
+    fields.insert(string("mymod:data"), NbtTag::Int(7));
+    let root = NbtCompound::from(fields);
+    let bytes = chunk_bytes(&root);
+
+    assert_eq!(parse_chunk(&bytes).unwrap().raw_nbt(), None);
+    let options = ChunkParseOptions {
+        keep_nbt: true,
+        ..Default::default()
+    };
+    let chunk = parse_chunk_with(&bytes, &options).unwrap();
+    assert_eq!(chunk.raw_nbt(), Some(&root));
+    assert_eq!(parse_chunk_nbt(&bytes).unwrap(), root);
+    assert!(parse_chunk_nbt(&bytes[..20]).is_err());
+
+    let options = RegionParseOptions {
+        chunk: options,
+        ..Default::default()
+    };
+    let region = Region::parse_bytes_with(include_bytes!("data/r.0.0.mca"), &options).unwrap();
+    let chunk = region.get_chunk(5, 9).unwrap();
+    let version = chunk.raw_nbt().unwrap().get("DataVersion").unwrap();
+    assert_eq!(version.as_int(), Some(&chunk.data_version()));
+}
+
+#[test]
+fn test_lenient() {
+    let mut data = vec![0; 256];
+    data[0] = 0x21;
+    let mut section: HashMap<_, _> = palette_section(
+        vec![
+            compound([("Name", NbtTag::String(string("stone")))]),
+            compound([("Properties", NbtTag::Compound(compound([])))]),
+            compound([("Name", NbtTag::String(string("chisel:marble")))]),
+        ],
+        Some(data),
+    )
+    .into();
+    section.insert(string("Y"), NbtTag::Byte(0));
+    let mut root: HashMap<_, _> = uniform_chunk(0, 1, "minecraft:stone").into();
+    root.insert(string("sections"), compound_list(vec![section.into()]));
+    let bytes = chunk_bytes(&root.into());
+
+    assert!(matches!(
+        parse_chunk(&bytes),
+        Err(ChunkParseError::InvalidField(path, "string"))
+            if path == "sections[0].block_states.palette[1].Name"
+    ));
+    let options = ChunkParseOptions {
+        lenient: true,
+        ..Default::default()
+    };
+    let chunk = parse_chunk_with(&bytes, &options).unwrap();
+    let name = |x| chunk.get(x, 0, 0).unwrap().get_name();
+    assert_eq!(
+        [name(0), name(1), name(2)],
+        [UNKNOWN_BLOCK, "chisel:marble", "minecraft:stone"]
+    );
+    let palette = "sections[0].block_states.palette";
+    assert_eq!(
+        chunk.parse_warnings(),
+        [
+            ParseWarning::MissingNamespace {
+                path: format!("{palette}[0]"),
+                name: "stone".to_owned()
+            },
+            ParseWarning::UnnamedBlock {
+                path: format!("{palette}[1]")
+            },
+        ]
+    );
+    assert_eq!(
+        chunk.parse_warnings()[1].to_string(),
+        format!("{palette}[1] has no name, read as mca_rs:unknown")
+    );
+
+    // nothing to work around
+    let bytes = chunk_bytes(&uniform_chunk(0, 1, "minecraft:stone"));
+    let chunk = parse_chunk_with(&bytes, &options).unwrap();
+    assert!(chunk.parse_warnings().is_empty());
+    assert_eq!(chunk, parse_chunk(&bytes).unwrap());
+}
+
+#[test]
+fn test_salvage() {
+    let mut root: HashMap<_, _> = uniform_chunk(0, 4, "minecraft:stone").into();
+    let Some(NbtTag::List(NbtList::Compound(sections))) = root.remove(&string("sections")) else {
+        unreachable!()
+    };
+    // the sections are written last by hand, so where they break off is
+    // known
+    let mut nbt = serialize_nbt(&string(""), &root.into());
+    nbt.pop();
+    nbt.push(9);
+    string("sections").serialize_nbt_payload(&mut nbt);
+    nbt.push(10);
+    nbt.extend_from_slice(&4i32.to_be_bytes());
+    let mut ends = Vec::new();
+    for section in sections.iter() {
+        section.serialize_nbt_payload(&mut nbt);
+        ends.push(nbt.len());
+    }
+    nbt.truncate(ends[2] - 10);
+    let mut bytes = (nbt.len() as u32 + 1).to_be_bytes().to_vec();
+    bytes.push(3);
+    bytes.extend_from_slice(&nbt);
+
+    assert!(matches!(
+        parse_chunk(&bytes),
+        Err(ChunkParseError::ParseFailed(
+            ParseError::UnexpectedEndOfInput
+        ))
+    ));
+    let options = ChunkParseOptions {
+        salvage: true,
+        ..Default::default()
+    };
+    let chunk = parse_chunk_with(&bytes, &options).unwrap();
+    assert!(chunk.is_partial());
+    assert_eq!(chunk.get_y_range(), 0..32);
+    assert_eq!(chunk.get(0, 31, 0).unwrap().get_name(), "minecraft:stone");
+    assert_eq!(
+        chunk.parse_warnings(),
+        [ParseWarning::Truncated {
+            offset: ends[1],
+            error: ParseError::UnexpectedEndOfInput.to_string(),
+        }]
+    );
+
+    let whole = chunk_bytes(&uniform_chunk(0, 4, "minecraft:stone"));
+    assert!(!parse_chunk_with(&whole, &options).unwrap().is_partial());
+}
+
+#[test]
+fn test_column() {
+    let block = |name: &str| Block::new(string(name), None);
+    let mut chunk = parse_chunk(&chunk_bytes(&uniform_chunk(-4, 4, "minecraft:air"))).unwrap();
+    chunk.set(3, -64, 5, block("minecraft:bedrock")).unwrap();
+    chunk.set(3, -20, 5, block("minecraft:stone")).unwrap();
+    chunk.set(3, -2, 5, block("minecraft:cave_air")).unwrap();
+    chunk.set(4, -1, 5, block("minecraft:dirt")).unwrap();
+
+    let column: Vec<_> = chunk.get_column(3, 5).collect();
+    assert_eq!(column.len(), 64);
+    assert_eq!(column[0], (-64, &block("minecraft:bedrock")));
+    assert_eq!(column[44], (-20, &block("minecraft:stone")));
+    assert_eq!(
+        chunk.get_column(3, 5).next_back(),
+        Some((-1, &Block::new(string("minecraft:air"), None)))
+    );
+    assert_eq!(chunk.get_column(16, 5).count(), 0);
+
+    assert_eq!(
+        chunk.highest_block(3, 5),
+        Some((-20, &block("minecraft:stone")))
+    );
+    assert_eq!(
+        chunk.highest_block(4, 5),
+        Some((-1, &block("minecraft:dirt")))
+    );
+    assert_eq!(chunk.highest_block(0, 0), None);
+    assert_eq!(chunk.highest_block(3, 16), None);
+    assert_eq!(
+        chunk.highest_block_matching(3, 5, |block| block.get_name().ends_with("bedrock")),
+        Some((-64, &block("minecraft:bedrock")))
+    );
+
+    // a lake of water on lava, and a fluid of a mod
+    chunk.set(3, -19, 5, block("minecraft:lava")).unwrap();
+    chunk.set(3, -18, 5, block("minecraft:seagrass")).unwrap();
+    chunk.set(3, -17, 5, block("minecraft:water")).unwrap();
+    chunk.set(3, -16, 5, block("mymod:oil")).unwrap();
+    assert_eq!(chunk.highest_block(3, 5).unwrap().0, -16);
+    assert_eq!(
+        chunk.highest_solid_block(3, 5),
+        Some((-16, &block("mymod:oil")))
+    );
+    let with_oil = |block: &Block| block.is_fluid() || block.get_name() == "mymod:oil";
+    assert_eq!(
+        chunk.highest_solid_block_with(3, 5, with_oil),
+        Some((-18, &block("minecraft:seagrass")))
+    );
+    chunk.set(3, -18, 5, block("minecraft:water")).unwrap();
+    assert_eq!(
+        chunk.highest_solid_block_with(3, 5, with_oil),
+        Some((-20, &block("minecraft:stone")))
+    );
+    assert_eq!(chunk.highest_solid_block(0, 0), None);
+}
+
+#[test]
+fn test_section_uniform() {
+    let block = |name: &str| Block::new(string(name), None);
+    let mut section = Section::filled(block("minecraft:stone"));
+    assert_eq!(section.is_uniform(), Some(&block("minecraft:stone")));
+    assert_eq!(section.unique_blocks(), [&block("minecraft:stone")]);
+    assert!(!section.is_empty());
+
+    section.set_block(1, 2, 3, block("minecraft:dirt")).unwrap();
+    assert_eq!(section.is_uniform(), None);
+    assert_eq!(
+        section.unique_blocks(),
+        [&block("minecraft:stone"), &block("minecraft:dirt")]
+    );
+
+    // the stone is gone, even if the palette still has it
+    for (x, y, z, _) in Section::empty().iter_blocks() {
+        section.set_block(x, y, z, block("minecraft:dirt")).unwrap();
+    }
+    assert_eq!(section.is_uniform(), Some(&block("minecraft:dirt")));
+    assert_eq!(section.unique_blocks(), [&block("minecraft:dirt")]);
+
+    let mut chunk = parse_chunk(&chunk_bytes(&uniform_chunk(-4, 2, "minecraft:air"))).unwrap();
+    assert!(chunk.is_empty());
+    chunk.set(0, -50, 0, block("minecraft:cave_air")).unwrap();
+    assert!(chunk.is_empty());
+    chunk.set(0, -50, 0, block("minecraft:stone")).unwrap();
+    assert!(!chunk.is_empty());
+    assert!(chunk.get_section(-4).unwrap().is_uniform().is_none());
+    assert!(chunk.get_section(-3).unwrap().is_uniform().is_some());
+}
+
+#[test]
+fn test_section_fill() {
+    let block = |name: &str| Block::new(string(name), None);
+    let region = Region::parse_bytes(include_bytes!("data/r.0.0.mca")).unwrap();
+    let mut section = region
+        .get_chunk(5, 9)
+        .unwrap()
+        .get_section(0)
+        .unwrap()
+        .clone();
+    let biome = section.get_biome(0, 0, 0).cloned();
+    section.fill(block("minecraft:stone"));
+    assert_eq!(section.is_uniform(), Some(&block("minecraft:stone")));
+    assert_eq!(section.get_biome(0, 0, 0).cloned(), biome);
+
+    section.fill_box(2..4, 0..1, 14..20, block("minecraft:dirt"));
+    let dirt = section.find_blocks(|block| block.get_name().ends_with("dirt"));
+    assert_eq!(dirt, [(2, 0, 14), (3, 0, 14), (2, 0, 15), (3, 0, 15)]);
+    assert_eq!(section.unique_blocks().len(), 2);
+    assert_eq!(section.count_non_air(), 4096);
+
+    // empty ranges set nothing
+    section.fill_box(5..5, 0..16, 0..16, block("minecraft:air"));
+    section.fill_box(16..20, 0..16, 0..16, block("minecraft:air"));
+    assert_eq!(section.count_non_air(), 4096);
+
+    section.fill_box(0..16, 8..16, 0..16, block("minecraft:air"));
+    assert_eq!(section.count_non_air(), 2048);
+    assert_eq!(section.non_air_bounds(), Some(((0, 0, 0), (15, 7, 15))));
+    assert_eq!(section.get_block(3, 0, 15), Some(&block("minecraft:dirt")));
+
+    section.fill_box(0..16, 0..16, 0..16, block("minecraft:air"));
+    assert!(section.is_empty());
+}
+
+#[test]
+fn test_chunk_fill_box() {
+    let block = |name: &str| Block::new(string(name), None);
+    let mut chunk = parse_chunk(&chunk_bytes(&uniform_chunk(-4, 24, "minecraft:air"))).unwrap();
+    let count = |chunk: &Chunk| chunk.iter_blocks().filter(|(.., b)| !b.is_air()).count();
+
+    // from the top of one section, over two whole ones, into the next
+    chunk.fill_box(0..16, -50..4, 0..16, block("minecraft:stone"));
+    assert_eq!(count(&chunk), 54 * 256);
+    assert!(chunk.get(0, -51, 0).unwrap().is_air());
+    assert!(!chunk.get(0, -50, 0).unwrap().is_air());
+    assert!(!chunk.get(15, 3, 15).unwrap().is_air());
+    assert!(chunk.get(15, 4, 15).unwrap().is_air());
+    assert!(chunk.get_section(-2).unwrap().is_uniform().is_some());
+    // every section holds the same block rather than a copy of it
+    assert!(std::ptr::eq(&chunk[(0, -50, 0)], &chunk[(0, 3, 0)]));
+
+    // clamped to the chunk, and nothing at all outside of it
+    chunk.fill_box(4..20, 300..400, 0..1, block("minecraft:dirt"));
+    assert_eq!(count(&chunk), 54 * 256 + 20 * 12);
+    assert_eq!(chunk[(15, 319, 0)].get_name(), "minecraft:dirt");
+    let before = chunk.clone();
+    chunk.fill_box(0..16, 320..400, 0..16, block("minecraft:dirt"));
+    chunk.fill_box(0..16, -200..-64, 0..16, block("minecraft:dirt"));
+    chunk.fill_box(0..16, 0..16, 16..20, block("minecraft:dirt"));
+    assert_eq!(chunk, before);
+
+    assert_eq!(
+        chunk.try_fill_box(0..16, -65..0, 0..16, block("minecraft:dirt")),
+        Err(OutOfBounds {
+            axis: Axis::Y,
+            value: -65,
+            range: -64..320
+        })
+    );
+    assert_eq!(
+        chunk
+            .try_fill_box(0..17, 0..16, 0..16, block("minecraft:dirt"))
+            .unwrap_err()
+            .value,
+        16
+    );
+    assert_eq!(chunk, before);
+    chunk
+        .try_fill_box(0..16, 316..320, 0..16, block("minecraft:sand"))
+        .unwrap();
+    assert_eq!(chunk[(0, 316, 0)].get_name(), "minecraft:sand");
+    chunk
+        .try_fill_box(0..16, 400..400, 0..16, block("minecraft:sand"))
+        .unwrap();
+}
+
+#[test]
+fn test_block_counts() {
+    let region = Region::parse_bytes(include_bytes!("data/r.0.0.mca")).unwrap();
+    let chunk = region.get_chunk(5, 9).unwrap();
+    let mut expected = HashMap::new();
+    for (.., block) in chunk.iter_blocks() {
+        *expected.entry(block).or_insert(0) += 1;
+    }
+    assert_eq!(chunk.block_counts(), expected);
+
+    let stone = Block::new(string("minecraft:stone"), None);
+    let mut section = Section::filled(stone.clone());
+    assert_eq!(section.block_counts(), HashMap::from([(&stone, 4096)]));
+    let air = Block::new(string("minecraft:air"), None);
+    section.set_block(0, 0, 0, air.clone()).unwrap();
+    section.set_block(1, 0, 0, air.clone()).unwrap();
+    assert_eq!(
+        section.block_counts(),
+        HashMap::from([(&stone, 4094), (&air, 2)])
+    );
+}
+
+#[test]
+fn test_find_blocks() {
+    let region = Region::parse_bytes(include_bytes!("data/r.0.0.mca")).unwrap();
+    let chunk = region.get_chunk(5, 9).unwrap();
+    let is_ore = |block: &Block| block.get_name().ends_with("_ore");
+    let expected: Vec<_> = chunk
+        .iter_blocks()
+        .filter(|(.., block)| is_ore(block))
+        .map(|(x, y, z, _)| (x, y, z))
+        .collect();
+    assert!(!expected.is_empty());
+    assert_eq!(chunk.find_blocks(is_ore), expected);
+
+    // once per palette entry, not per block
+    let calls = std::cell::Cell::new(0);
+    let section = Section::filled(Block::new(string("minecraft:stone"), None));
+    let found = section.find_blocks(|_| {
+        calls.set(calls.get() + 1);
+        false
+    });
+    assert!(found.is_empty());
+    assert_eq!(calls.get(), 1);
+}
+
+fn tick(target: &str, x: i32, y: i32, z: i32, delay: i32) -> NbtCompound {
+    compound([
+        ("i", NbtTag::String(string(target))),
+        ("x", NbtTag::Int(x)),
+        ("y", NbtTag::Int(y)),
+        ("z", NbtTag::Int(z)),
+        ("t", NbtTag::Int(delay)),
+        ("p", NbtTag::Int(0)),
+    ])
+}
+
+#[test]
+fn test_ticks_and_entities() {
+    let mut root: HashMap<_, _> = uniform_chunk(-1, 2, "minecraft:stone").into();
+    root.insert(
+        string("block_ticks"),
+        compound_list(vec![
+            tick("minecraft:repeater", 3, 10, -4, 2),
+            tick("minecraft:observer", 4, 10, -4, -1),
+        ]),
+    );
+    root.insert(
+        string("fluid_ticks"),
+        compound_list(vec![tick("minecraft:water", 0, 0, 0, 5)]),
+    );
+    let chunk = parse_chunk(&chunk_bytes(&root.into())).unwrap();
+    let ticks: Vec<_> = chunk
+        .block_ticks()
+        .iter()
+        .map(|tick| (tick.target.to_string(), tick.x, tick.delay))
+        .collect();
+    assert_eq!(
+        ticks,
+        [
+            ("minecraft:repeater".to_owned(), 3, 2),
+            ("minecraft:observer".to_owned(), 4, -1),
+        ]
+    );
+    assert_eq!(chunk.fluid_ticks()[0].target, "minecraft:water");
+    assert!(chunk.embedded_entities().is_empty());
+    assert_eq!(parse_chunk(&chunk.to_bytes()).unwrap(), chunk);
+    assert_eq!(
+        Chunk::from_cache_bytes(&chunk.to_cache_bytes()).unwrap(),
+        chunk
+    );
+
+    // before 1.18 they're in the level, with entities from before 1.17
+    let pos = |pos: [f64; 3]| NbtTag::List(NbtList::Double(pos.to_vec().try_into().unwrap()));
+    let mut root: HashMap<_, _> = level_chunk(vec![]).into();
+    let Some(NbtTag::Compound(level)) = root.remove("Level") else {
+        unreachable!()
+    };
+    let mut level: HashMap<_, _> = level.into();
+    level.insert(
+        string("Entities"),
+        compound_list(vec![
+            compound([
+                ("id", NbtTag::String(string("minecraft:creeper"))),
+                ("Pos", pos([50.5, 64.0, -27.5])),
+            ]),
+            compound([
+                ("id", NbtTag::String(string("minecraft:item"))),
+                ("Pos", pos([51.0, 63.0, -28.0])),
+            ]),
+        ]),
+    );
+    level.insert(
+        string("TileTicks"),
+        compound_list(vec![tick("minecraft:sand", 49, 70, -30, 1)]),
+    );
+    level.insert(
+        string("LiquidTicks"),
+        compound_list(vec![tick("minecraft:lava", 50, 12, -31, 30)]),
+    );
+    root.insert(string("Level"), NbtTag::Compound(level.into()));
+    let chunk = parse_chunk(&chunk_bytes(&root.into())).unwrap();
+
+    let creeper = &chunk.embedded_entities()[0];
+    assert_eq!(creeper.id, "minecraft:creeper");
+    assert_eq!(creeper.pos, (50.5, 64.0, -27.5));
+    assert_eq!(creeper.rotation, (0.0, 0.0));
+    assert_eq!(chunk.embedded_entities()[1].id, "minecraft:item");
+    assert_eq!(chunk.block_ticks()[0].target, "minecraft:sand");
+    assert_eq!(chunk.fluid_ticks()[0].delay, 30);
+    assert_eq!(
+        Chunk::from_cache_bytes(&chunk.to_cache_bytes()).unwrap(),
+        chunk
+    );
+
+    // written back in the level, entities included
+    let written = parse_chunk(&chunk.to_bytes()).unwrap();
+    assert_eq!(written.embedded_entities(), chunk.embedded_entities());
+    assert_eq!(written.block_ticks(), chunk.block_ticks());
+    assert_eq!(written.fluid_ticks(), chunk.fluid_ticks());
+}
+
+#[test]
+fn test_1_16_chunk() {
+    let region = Region::parse_bytes(include_bytes!("data/r.1_16.mca")).unwrap();
+    let chunk = region.get_chunk(0, 0).unwrap();
+    assert_eq!(chunk.data_version(), 2586);
+    assert_eq!(chunk.get(0, 0, 0).unwrap().get_name(), "minecraft:stone");
+    let grass = chunk.get(15, 4, 15).unwrap();
+    assert_eq!(grass.get_name(), "minecraft:grass_block");
+    assert_eq!(grass.get_property("snowy"), Some("false"));
+    assert_eq!(chunk.get(7, 5, 7).unwrap().get_name(), "minecraft:air");
+
+    let entities = chunk.embedded_entities();
+    assert_eq!(entities.len(), 2);
+    assert!(entities[0].is("zombie"));
+    assert_eq!(entities[0].pos, (3.5, 5.0, 7.5));
+    assert_eq!(entities[0].rotation, (45.0, 0.0));
+    assert_eq!(entities[0].item().unwrap().id, "minecraft:iron_shovel");
+    let item = entities[1].item().unwrap();
+    assert_eq!(item.id, "minecraft:diamond");
+    assert_eq!(item.count, 2);
+
+    let written = parse_chunk(&chunk.to_bytes()).unwrap();
+    assert_eq!(written.embedded_entities(), entities);
+}
+
+#[test]
+fn test_structures() {
+    let region = Region::parse_bytes(include_bytes!("data/r.0.0.mca")).unwrap();
+    let chunk = region.get_chunk(13, 0).unwrap();
+    let references: Vec<_> = chunk.structure_references().collect();
+    assert_eq!(references, [("minecraft:mineshaft", vec![(16, -5)])]);
+    let start = &region.get_chunk(5, 6).unwrap().structure_starts()[0];
+    assert_eq!(start.id, "minecraft:mineshaft");
+    // the union of the pieces' boxes
+    let bounding_box = start.bounding_box.unwrap();
+    assert!(bounding_box.contains_point((82, bounding_box.min.y, 98)));
+    assert!(bounding_box.max.x >= 132);
+
+    let structures: Vec<_> = region.structures().collect();
+    assert!(structures.contains(&(5, 6, start)));
+    let around = BoundingBox::new(BlockPos::new(80, 0, 96), BlockPos::new(84, 64, 100));
+    let intersecting: Vec<_> = region.structures_intersecting(around).collect();
+    assert!(intersecting.contains(&(5, 6, start)));
+    let far = BoundingBox::new(BlockPos::new(-900, 0, -900), BlockPos::new(-800, 64, -800));
+    assert_eq!(region.structures_intersecting(far).count(), 0);
+
+    let pack = |x: i32, z: i32| (x as u32 as i64) | ((z as i64) << 32);
+    let packed = [pack(-3, -7), pack(5, -1), pack(i32::MIN, i32::MAX)];
+    let level = compound([
+        ("xPos", NbtTag::Int(0)),
+        ("zPos", NbtTag::Int(0)),
+        (
+            "Structures",
+            NbtTag::Compound(compound([
+                (
+                    "References",
+                    NbtTag::Compound(compound([(
+                        "Fortress",
+                        NbtTag::LongArray(packed.to_vec().try_into().unwrap()),
+                    )])),
+                ),
+                (
+                    "Starts",
+                    NbtTag::Compound(compound([
+                        (
+                            "Fortress",
+                            NbtTag::Compound(compound([
+                                ("id", NbtTag::String(string("Fortress"))),
+                                (
+                                    "BB",
+                                    NbtTag::IntArray(vec![1, 2, 3, 40, 50, 60].try_into().unwrap()),
+                                ),
+                            ])),
+                        ),
+                        (
+                            "Monument",
+                            NbtTag::Compound(compound([("id", NbtTag::String(string("INVALID")))])),
+                        ),
+                    ])),
+                ),
+            ])),
+        ),
+    ]);
+    let root = compound([
+        ("DataVersion", NbtTag::Int(2230)),
+        ("Level", NbtTag::Compound(level)),
+    ]);
+    let chunk = parse_chunk(&chunk_bytes(&root)).unwrap();
+    let references: Vec<_> = chunk.structure_references().collect();
+    assert_eq!(
+        references,
+        [("Fortress", vec![(-3, -7), (5, -1), (i32::MIN, i32::MAX)])]
+    );
+    // invalid starts are left out
+    let [fortress] = chunk.structure_starts() else {
+        panic!("expected a single start");
+    };
+    assert_eq!(fortress.id, "Fortress");
+    assert_eq!(
+        fortress.bounding_box,
+        Some(BoundingBox::from([1, 2, 3, 40, 50, 60]))
+    );
+
+    assert_eq!(parse_chunk(&chunk.to_bytes()).unwrap(), chunk);
+    assert_eq!(
+        Chunk::from_cache_bytes(&chunk.to_cache_bytes()).unwrap(),
+        chunk
+    );
+}
+
+#[test]
+fn test_upgrade_data() {
+    let region = Region::parse_bytes(include_bytes!("data/r.0.0.mca")).unwrap();
+    let chunk = region.get_chunk(5, 9).unwrap();
+    assert!(chunk.below_zero_retrogen().is_none());
+    assert!(chunk.blending_data().is_none());
+
+    let with = |fields: Vec<(&str, NbtTag)>| {
+        let mut root: HashMap<_, _> = uniform_chunk(-4, 1, "minecraft:stone").into();
+        for (name, tag) in fields {
+            root.insert(string(name), tag);
+        }
+        parse_chunk(&chunk_bytes(&root.into()))
+    };
+    let longs = |words: &[u64]| {
+        let words: Vec<_> = words.iter().map(|&word| word as i64).collect();
+        NbtTag::LongArray(words.try_into().unwrap())
+    };
+    let retrogen = |words: Option<&[u64]>| {
+        let mut fields = HashMap::new();
+        fields.insert(
+            string("target_status"),
+            NbtTag::String(string("minecraft:spawn")),
+        );
+        if let Some(words) = words {
+            fields.insert(string("missing_bedrock"), longs(words));
+        }
+        NbtTag::Compound(fields.into())
+    };
+
+    // bit `x + z * 16`, with trailing empty words left out like the game does
+    let words = [1 | 1 << 17 | 1 << 63, 0, 1 << 36];
+    let chunk = with(vec![
+        ("below_zero_retrogen", retrogen(Some(&words))),
+        (
+            "blending_data",
+            NbtTag::Compound(compound([
+                ("min_section", NbtTag::Int(0)),
+                ("max_section", NbtTag::Int(16)),
+            ])),
+        ),
+    ])
+    .unwrap();
+    let stored = chunk.below_zero_retrogen().unwrap();
+    assert_eq!(stored.target_status, ChunkStatus::Spawn);
+    assert_eq!(
+        stored.missing_bedrock().collect::<Vec<_>>(),
+        [(0, 0), (1, 1), (15, 3), (4, 10)]
+    );
+    assert!(stored.is_missing_bedrock(4, 10));
+    assert!(!stored.is_missing_bedrock(10, 4));
+    assert!(!stored.is_missing_bedrock(16, 0));
+    assert_eq!(chunk.blending_data(), Some((0, 16)));
+
+    let mut changed = stored.clone();
+    changed.set_missing_bedrock(15, 15, true);
+    changed.set_missing_bedrock(0, 0, false);
+    assert_eq!(
+        changed.missing_bedrock().collect::<Vec<_>>(),
+        [(1, 1), (15, 3), (4, 10), (15, 15)]
+    );
+
+    let encoded = chunk.to_nbt();
+    let encoded = encoded.get("below_zero_retrogen").unwrap().as_compound();
+    assert_eq!(
+        encoded.unwrap().get("missing_bedrock"),
+        Some(&longs(&words))
+    );
+    assert_eq!(parse_chunk(&chunk.to_bytes()).unwrap(), chunk);
+    assert_eq!(
+        Chunk::from_cache_bytes(&chunk.to_cache_bytes()).unwrap(),
+        chunk
+    );
+
+    // no bedrock missing anywhere, and 1.18 blending data without sections
+    let chunk = with(vec![
+        ("below_zero_retrogen", retrogen(None)),
+        (
+            "blending_data",
+            NbtTag::Compound(compound([("old_noise", NbtTag::Byte(1))])),
+        ),
+    ])
+    .unwrap();
+    assert_eq!(
+        chunk
+            .below_zero_retrogen()
+            .unwrap()
+            .missing_bedrock()
+            .count(),
+        0
+    );
+    assert!(chunk.blending_data().is_none());
+    assert!(chunk.blending_data_nbt().is_some());
+    assert_eq!(parse_chunk(&chunk.to_bytes()).unwrap(), chunk);
+
+    let invalid = |field, tag| match with(vec![(field, tag)]) {
+        Err(ChunkParseError::InvalidField(path, _)) => path,
+        other => panic!("expected an invalid field, got {other:?}"),
+    };
+    assert_eq!(
+        invalid("below_zero_retrogen", retrogen(Some(&[0; 5]))),
+        "below_zero_retrogen.missing_bedrock"
+    );
+    assert_eq!(
+        invalid(
+            "below_zero_retrogen",
+            NbtTag::Compound(compound([("missing_bedrock", longs(&[1]))]))
+        ),
+        "below_zero_retrogen.target_status"
+    );
+    assert_eq!(invalid("blending_data", NbtTag::Int(0)), "blending_data");
+}
+
+#[test]
+fn test_relocate() {
+    let ints = |values: &[i32]| NbtTag::IntArray(values.to_vec().try_into().unwrap());
+    let mut root: HashMap<_, _> = level_chunk(vec![]).into();
+    let Some(NbtTag::Compound(level)) = root.remove("Level") else {
+        unreachable!()
+    };
+    let mut level: HashMap<_, _> = level.into();
+    level.insert(
+        string("Entities"),
+        compound_list(vec![compound([
+            ("id", NbtTag::String(string("minecraft:pig"))),
+            (
+                "Pos",
+                NbtTag::List(NbtList::Double(vec![50.5, 6.0, -27.5].try_into().unwrap())),
+            ),
+        ])]),
+    );
+    level.insert(
+        string("TileTicks"),
+        compound_list(vec![compound([
+            ("i", NbtTag::String(string("minecraft:sand"))),
+            ("x", NbtTag::Int(49)),
+            ("y", NbtTag::Int(70)),
+            ("z", NbtTag::Int(-30)),
+            ("t", NbtTag::Int(1)),
+            ("p", NbtTag::Int(0)),
+        ])]),
+    );
+    let pack = |x: i32, z: i32| (x as u32 as i64) | ((z as i64) << 32);
+    let start = compound([
+        ("id", NbtTag::String(string("Fortress"))),
+        ("ChunkX", NbtTag::Int(3)),
+        ("ChunkZ", NbtTag::Int(-2)),
+        ("BB", ints(&[40, 2, -40, 70, 50, -10])),
+        (
+            "Children",
+            compound_list(vec![compound([("BB", ints(&[40, 2, -40, 50, 10, -30]))])]),
+        ),
+    ]);
+    level.insert(
+        string("Structures"),
+        NbtTag::Compound(compound([
+            (
+                "References",
+                NbtTag::Compound(compound([(
+                    "Fortress",
+                    NbtTag::LongArray(vec![pack(3, -2), pack(2, -1)].try_into().unwrap()),
+                )])),
+            ),
+            (
+                "Starts",
+                NbtTag::Compound(compound([("Fortress", NbtTag::Compound(start))])),
+            ),
+        ])),
+    );
+    root.insert(string("Level"), NbtTag::Compound(level.into()));
+    let mut chunk = parse_chunk(&chunk_bytes(&root.into())).unwrap();
+    let original = chunk.clone();
+    assert_eq!(
+        chunk.get_block_entity(1, 5, 2).unwrap().id,
+        "minecraft:furnace"
+    );
+
+    // 4 chunks west and 6 south, so -64 and 96 blocks
+    chunk.relocate(-1, 4);
+    assert_eq!(chunk.get_pos(), ChunkPos::new(-1, 4));
+    let furnace = chunk.get_block_entity(1, 5, 2).unwrap();
+    assert_eq!((furnace.x, furnace.y, furnace.z), (-15, 5, 66));
+    assert_eq!(furnace.data.get("x"), Some(&NbtTag::Int(-15)));
+    assert_eq!(furnace.data.get("z"), Some(&NbtTag::Int(66)));
+    let tick = &chunk.block_ticks()[0];
+    assert_eq!((tick.x, tick.y, tick.z), (-15, 70, 66));
+    let pig = &chunk.embedded_entities()[0];
+    assert_eq!(pig.pos, (-13.5, 6.0, 68.5));
+    assert_eq!(
+        pig.data.get("Pos"),
+        Some(&NbtTag::List(NbtList::Double(
+            vec![-13.5, 6.0, 68.5].try_into().unwrap()
+        )))
+    );
+    let references: Vec<_> = chunk.structure_references().collect();
+    assert_eq!(references, [("Fortress", vec![(-1, 4), (-2, 5)])]);
+    let fortress = &chunk.structure_starts()[0];
+    assert_eq!(
+        fortress.bounding_box,
+        Some(BoundingBox::new(
+            BlockPos::new(-24, 2, 56),
+            BlockPos::new(6, 50, 86)
+        ))
+    );
+    assert_eq!(fortress.data.get("ChunkX"), Some(&NbtTag::Int(-1)));
+    assert_eq!(fortress.data.get("ChunkZ"), Some(&NbtTag::Int(4)));
+    assert_eq!(
+        fortress.data.get("BB"),
+        Some(&ints(&[-24, 2, 56, 6, 50, 86]))
+    );
+    let Some(NbtTag::List(NbtList::Compound(children))) = fortress.data.get("Children") else {
+        panic!("expected the pieces to be kept");
+    };
+    assert_eq!(
+        children[0].get("BB"),
+        Some(&ints(&[-24, 2, 56, -14, 10, 66]))
+    );
+    // the blocks don't move with the coordinates
+    assert_eq!(chunk.get(1, 0, 2), original.get(1, 0, 2));
+
+    let written = parse_chunk(&chunk.to_bytes()).unwrap();
+    assert_eq!(written.block_entities(), chunk.block_entities());
+    assert_eq!(written.structure_starts(), chunk.structure_starts());
+
+    // compounds don't keep their order when rewritten
+    chunk.relocate(3, -2);
+    let fields = |data: &NbtCompound| HashMap::<_, _>::from(data.clone());
+    assert_eq!(
+        fields(&chunk.block_entities()[0].data),
+        fields(&original.block_entities()[0].data)
+    );
+    assert_eq!(
+        fields(&chunk.structure_starts()[0].data),
+        fields(&original.structure_starts()[0].data)
+    );
+    assert_eq!(chunk.block_ticks(), original.block_ticks());
+    assert_eq!(
+        chunk.structure_references().collect::<Vec<_>>(),
+        original.structure_references().collect::<Vec<_>>()
+    );
+}
@@ -0,0 +1,100 @@
+#![allow(dead_code)]
+
+use std::{collections::HashMap, env, fs, io::Write, path::PathBuf, process};
+
+use flate2::{Compression, write::ZlibEncoder};
+use mca_rs::block::Block;
+use nbt_rs::{
+    serialize_nbt,
+    types::{NbtCompound, NbtList, NbtString, NbtTag},
+};
+
+pub fn string(value: &str) -> NbtString {
+    value.to_owned().try_into().unwrap()
+}
+
+/// A block without properties.
+pub fn block(name: &str) -> Block {
+    Block::new(string(name), None)
+}
+
+pub fn compound<const N: usize>(entries: [(&str, NbtTag); N]) -> NbtCompound {
+    NbtCompound::from(
+        entries
+            .into_iter()
+            .map(|(key, tag)| (string(key), tag))
+            .collect::<HashMap<_, _>>(),
+    )
+}
+
+pub fn compound_list(compounds: Vec<NbtCompound>) -> NbtTag {
+    if compounds.is_empty() {
+        return NbtTag::List(NbtList::End);
+    }
+    NbtTag::List(NbtList::Compound(compounds.try_into().unwrap()))
+}
+
+/// Wraps an nbt compound into zlib-compressed chunk bytes with the 5-byte header.
+pub fn chunk_bytes(root: &NbtCompound) -> Vec<u8> {
+    let nbt = serialize_nbt(&string(""), root);
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(&nbt).unwrap();
+    let payload = encoder.finish().unwrap();
+
+    let mut bytes = Vec::with_capacity(payload.len() + 5);
+    bytes.extend_from_slice(&(payload.len() as u32 + 1).to_be_bytes());
+    bytes.push(2);
+    bytes.extend_from_slice(&payload);
+    bytes
+}
+
+/// Builds a region file holding the given chunks, laid out sequentially.
+pub fn region_bytes(chunks: &[(usize, usize, Vec<u8>)]) -> Vec<u8> {
+    let mut bytes = vec![0u8; 8192];
+    for (x, z, chunk) in chunks {
+        let offset = bytes.len() / 4096;
+        let sectors = chunk.len().div_ceil(4096);
+        let index = (x + z * 32) * 4;
+        bytes[index..index + 3].copy_from_slice(&(offset as u32).to_be_bytes()[1..]);
+        bytes[index + 3] = sectors as u8;
+        bytes[4096 + index..4096 + index + 4].copy_from_slice(&1u32.to_be_bytes());
+        bytes.extend_from_slice(chunk);
+        bytes.resize((offset + sectors) * 4096, 0);
+    }
+    bytes
+}
+
+/// A minimal modern chunk with `sections_count` sections full of `block`.
+pub fn uniform_chunk(y_pos: i32, sections_count: i32, block: &str) -> NbtCompound {
+    let sections = (0..sections_count)
+        .map(|i| {
+            compound([
+                ("Y", NbtTag::Byte((y_pos + i) as i8)),
+                (
+                    "block_states",
+                    NbtTag::Compound(compound([(
+                        "palette",
+                        compound_list(vec![compound([("Name", NbtTag::String(string(block)))])]),
+                    )])),
+                ),
+            ])
+        })
+        .collect();
+
+    compound([
+        ("DataVersion", NbtTag::Int(4325)),
+        ("xPos", NbtTag::Int(0)),
+        ("zPos", NbtTag::Int(0)),
+        ("yPos", NbtTag::Int(y_pos)),
+        ("Status", NbtTag::String(string("minecraft:full"))),
+        ("sections", compound_list(sections)),
+    ])
+}
+
+/// An empty directory for a test to write to, named after the test.
+pub fn temp_dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("mca-rs-{name}-{}", process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
@@ -0,0 +1,147 @@
+use mca_rs::{BlockPos, ChunkPos, RegionPos, SectionPos, region::Region};
+
+#[test]
+fn test_block_to_chunk() {
+    assert_eq!(BlockPos::new(0, 0, 0).chunk(), ChunkPos::new(0, 0));
+    assert_eq!(BlockPos::new(15, 0, 16).chunk(), ChunkPos::new(0, 1));
+    assert_eq!(BlockPos::new(-1, 0, -16).chunk(), ChunkPos::new(-1, -1));
+    assert_eq!(BlockPos::new(-17, 0, -15).chunk(), ChunkPos::new(-2, -1));
+    assert_eq!(
+        ChunkPos::from(BlockPos::new(-1, 5, 1)),
+        ChunkPos::new(-1, 0)
+    );
+}
+
+#[test]
+fn test_block_offsets() {
+    assert_eq!(BlockPos::new(-1, -64, -16).chunk_local(), (15, -64, 0));
+    assert_eq!(BlockPos::new(33, 70, -17).chunk_local(), (1, 70, 15));
+    assert_eq!(BlockPos::new(-1, -1, 17).section_local(), (15, 15, 1));
+    assert_eq!(
+        BlockPos::new(-1, -1, 17).section(),
+        SectionPos::new(-1, -1, 1)
+    );
+    assert_eq!(
+        SectionPos::from(BlockPos::new(16, -64, -33)),
+        SectionPos::new(1, -4, -3)
+    );
+}
+
+#[test]
+fn test_chunk_to_region() {
+    assert_eq!(ChunkPos::new(0, 31).region(), RegionPos::new(0, 0));
+    assert_eq!(ChunkPos::new(32, -1).region(), RegionPos::new(1, -1));
+    assert_eq!(ChunkPos::new(-33, -32).region(), RegionPos::new(-2, -1));
+    assert_eq!(ChunkPos::new(-1, -33).region_local(), (31, 31));
+    assert_eq!(ChunkPos::new(-32, 33).region_local(), (0, 1));
+}
+
+#[test]
+fn test_round_trips() {
+    assert_eq!(
+        ChunkPos::new(-3, 7).min_block(10),
+        BlockPos::new(-48, 10, 112)
+    );
+    assert_eq!(
+        SectionPos::new(-1, -4, 2).min_block(),
+        BlockPos::new(-16, -64, 32)
+    );
+    assert_eq!(SectionPos::new(-1, -4, 2).chunk(), ChunkPos::new(-1, 2));
+
+    for x in -40..40 {
+        let pos = BlockPos::new(x, x, -x);
+        let (ox, _, oz) = pos.chunk_local();
+        let min = pos.chunk().min_block(pos.y);
+        assert_eq!(min.x + ox as i32, pos.x);
+        assert_eq!(min.z + oz as i32, pos.z);
+    }
+}
+
+#[test]
+fn test_positional_lookups() {
+    let region = Region::parse_bytes(include_bytes!("data/r.0.0.mca")).unwrap();
+    assert_eq!(
+        region.get_chunk_at(ChunkPos::new(-32, 33)),
+        region.get_chunk(0, 1)
+    );
+
+    let chunk = region.get_chunk(0, 0).unwrap();
+    assert_eq!(chunk.get_at(BlockPos::new(0, -64, 0)), chunk.get(0, -64, 0));
+    assert_eq!(
+        chunk.get_at(BlockPos::new(-15, 10, 17)),
+        chunk.get(1, 10, 1)
+    );
+    assert!(chunk.get_at(BlockPos::new(0, 100_000, 0)).is_none());
+}
+
+#[test]
+fn test_negative_boundaries() {
+    // block, its chunk and its offset in it
+    for (block, chunk, offset) in [
+        (0, 0, 0),
+        (15, 0, 15),
+        (16, 1, 0),
+        (-1, -1, 15),
+        (-15, -1, 1),
+        (-16, -1, 0),
+        (-17, -2, 15),
+        (-32, -2, 0),
+        (-33, -3, 15),
+    ] {
+        let pos = BlockPos::new(block, block, block);
+        assert_eq!(pos.chunk(), ChunkPos::new(chunk, chunk), "block {block}");
+        assert_eq!(pos.chunk_local(), (offset, block, offset), "block {block}");
+        assert_eq!(pos.section(), SectionPos::new(chunk, chunk, chunk));
+        assert_eq!(pos.section_local(), (offset, offset, offset));
+    }
+
+    // chunk, its region and its offset in it
+    for (chunk, region, offset) in [
+        (0, 0, 0),
+        (31, 0, 31),
+        (32, 1, 0),
+        (-1, -1, 31),
+        (-31, -1, 1),
+        (-32, -1, 0),
+        (-33, -2, 31),
+        (-64, -2, 0),
+        (-65, -3, 31),
+    ] {
+        let pos = ChunkPos::new(chunk, chunk);
+        assert_eq!(
+            pos.region(),
+            RegionPos::new(region, region),
+            "chunk {chunk}"
+        );
+        assert_eq!(RegionPos::from(pos), RegionPos::new(region, region));
+        assert_eq!(pos.region_local(), (offset, offset), "chunk {chunk}");
+        assert_eq!(RegionPos::from(pos).chunk(offset as u8, offset as u8), pos);
+    }
+
+    for (block, region) in [(-1, -1), (-512, -1), (-513, -2), (511, 0), (512, 1)] {
+        let pos = BlockPos::new(block, 0, -block);
+        assert_eq!(pos.region(), RegionPos::from(pos.chunk()), "block {block}");
+        assert_eq!(pos.region().x, region);
+    }
+    assert_eq!(RegionPos::new(-1, 2).min_chunk(), ChunkPos::new(-32, 64));
+    // slots past the region wrap around inside of it
+    assert_eq!(RegionPos::new(-1, 2).chunk(33, 255), ChunkPos::new(-31, 95));
+    assert_eq!(
+        RegionPos::new(i32::MAX, i32::MIN).chunk(0, 0),
+        ChunkPos::new(-32, 0)
+    );
+    assert_eq!(<(i32, i32)>::from(RegionPos::from((-3, 4))), (-3, 4));
+}
+
+#[test]
+fn test_converted_lookups() {
+    let region = Region::parse_bytes(include_bytes!("data/r.0.0.mca")).unwrap();
+    assert_eq!(region.get_chunk_at((5, 9)), region.get_chunk(5, 9));
+    assert_eq!(
+        region.get_chunk_at(BlockPos::new(-17, 0, 16)),
+        region.get_chunk(30, 1)
+    );
+
+    let chunk = region.get_chunk(0, 0).unwrap();
+    assert_eq!(chunk.get_at((-15, 10, 17)), chunk.get(1, 10, 1));
+}
@@ -0,0 +1,172 @@
+mod common;
+
+use std::collections::HashMap;
+
+use common::block;
+use mca_rs::{
+    block_entity::BlockEntity,
+    builder::ChunkBuilder,
+    chunk::ChunkParseOptions,
+    diff::{BlockEntityChange, ChunkChange},
+    region::{Region, RegionParseOptions},
+    section::Section,
+};
+use nbt_rs::types::{NbtCompound, NbtTag};
+
+#[test]
+fn test_diff() {
+    let region = Region::parse_bytes(include_bytes!("data/r.0.0.mca")).unwrap();
+    let before = region.get_chunk(5, 9).unwrap();
+    assert!(before.diff(before).is_empty());
+
+    let mut after = before.clone();
+    let old = before.get(3, 70, 4).unwrap().clone();
+    after.set(3, 70, 4, block("minecraft:gold_block")).unwrap();
+    after
+        .set(0, -64, 15, block("minecraft:gold_block"))
+        .unwrap();
+    // setting a block to what it already was isn't a change
+    after
+        .set(8, 0, 8, before.get(8, 0, 8).unwrap().clone())
+        .unwrap();
+
+    let diff = before.diff(&after);
+    assert_eq!(diff.len(), 2);
+    let change = &diff.changes()[1];
+    assert_eq!((change.x, change.y, change.z), (3, 70, 4));
+    assert_eq!(change.before, old);
+    assert_eq!(change.after, block("minecraft:gold_block"));
+    assert_eq!(diff.changes()[0].y, -64);
+    assert_eq!(after.diff(before).changes()[1].after, old);
+}
+
+#[test]
+fn test_diff_block_entities() {
+    let sign = |x: i32, y: i32, z: i32, text: &str| BlockEntity {
+        id: "minecraft:sign".to_owned().try_into().unwrap(),
+        x,
+        y,
+        z,
+        data: NbtCompound::from(HashMap::from([(
+            "text".to_owned().try_into().unwrap(),
+            NbtTag::String(text.to_owned().try_into().unwrap()),
+        )])),
+    };
+    let region = Region::parse_bytes(include_bytes!("data/r.0.0.mca")).unwrap();
+    let mut before = region.get_chunk(5, 9).unwrap().clone();
+    before.set_block_entity(sign(80, 70, 144, "kept")).unwrap();
+    before.set_block_entity(sign(81, 70, 144, "old")).unwrap();
+    before
+        .set_block_entity(sign(82, 60, 150, "removed"))
+        .unwrap();
+    let mut after = before.clone();
+    after.set_block_entity(sign(81, 70, 144, "new")).unwrap();
+    after.remove_block_entity(2, 60, 6).unwrap();
+    after.set_block_entity(sign(95, 71, 159, "added")).unwrap();
+
+    let diff = before.diff(&after);
+    assert!(!diff.is_empty());
+    // no blocks changed
+    assert_eq!(diff.len(), 0);
+    assert_eq!(
+        diff.block_entity_changes(),
+        [
+            BlockEntityChange::Removed(sign(82, 60, 150, "removed")),
+            BlockEntityChange::Changed {
+                before: sign(81, 70, 144, "old"),
+                after: sign(81, 70, 144, "new"),
+            },
+            BlockEntityChange::Added(sign(95, 71, 159, "added")),
+        ]
+    );
+    assert!(before.diff(&before).block_entity_changes().is_empty());
+
+    let mut edited = region.clone();
+    edited.set_chunk(5, 9, after).unwrap();
+    let mut region = region;
+    region.set_chunk(5, 9, before).unwrap();
+    let Some(ChunkChange::Changed(changed)) = region.diff(&edited).get(5, 9).cloned() else {
+        panic!("expected a changed chunk");
+    };
+    assert_eq!(changed.block_entity_changes().len(), 3);
+}
+
+#[test]
+fn test_diff_y_ranges() {
+    let low = ChunkBuilder::new(0, 0)
+        .y_range(0..32)
+        .section(1, Section::filled(block("minecraft:stone")))
+        .build()
+        .unwrap();
+    let mut section = Section::empty();
+    section.set_block(1, 2, 3, block("minecraft:dirt")).unwrap();
+    let high = ChunkBuilder::new(0, 0)
+        .y_range(16..64)
+        .section(1, Section::filled(block("minecraft:stone")))
+        .section(3, section)
+        .build()
+        .unwrap();
+
+    let diff = low.diff(&high);
+    assert_eq!(diff.len(), 1);
+    let change = diff.into_iter().next().unwrap();
+    assert_eq!((change.x, change.y, change.z), (1, 50, 3));
+    assert!(change.before.is_air());
+    assert_eq!(change.after, block("minecraft:dirt"));
+}
+
+#[test]
+fn test_diff_skips_identical_sections() {
+    let options = RegionParseOptions {
+        chunk: ChunkParseOptions {
+            lazy_sections: true,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let bytes = include_bytes!("data/r.0.0.mca");
+    let before = Region::parse_bytes_with(bytes, &options).unwrap();
+    let after = Region::parse_bytes_with(bytes, &options).unwrap();
+    let undecoded = |region: &Region| {
+        (region.iter_chunks())
+            .flat_map(|(_, _, chunk)| chunk.sections())
+            .filter(|(_, section)| !section.is_decoded())
+            .count()
+    };
+    let packed = undecoded(&before);
+    assert!(packed > 1000);
+
+    assert!(before.diff(&after).is_empty());
+    // the packed indices were compared, nothing had to be decoded
+    assert_eq!(undecoded(&before), packed);
+    assert_eq!(undecoded(&after), packed);
+}
+
+#[test]
+fn test_region_diff() {
+    let before = Region::parse_bytes(include_bytes!("data/r.0.0.mca")).unwrap();
+    let mut after = before.clone();
+    after.remove_chunk(3, 4).unwrap();
+    let moved = after.remove_chunk(5, 9).unwrap();
+    after.set_chunk(31, 31, moved.clone()).unwrap();
+    let mut edited = after.get_chunk(0, 0).unwrap().clone();
+    edited.set(1, 64, 2, block("minecraft:gold_block")).unwrap();
+    after.set_chunk(0, 0, edited).unwrap();
+
+    let diff = before.diff(&after);
+    assert_eq!(diff.len(), 4);
+    let slots: Vec<_> = diff.chunks().iter().map(|(x, z, _)| (*x, *z)).collect();
+    assert_eq!(slots, [(0, 0), (3, 4), (5, 9), (31, 31)]);
+    assert_eq!(diff.get(3, 4), Some(&ChunkChange::Removed));
+    assert_eq!(diff.get(5, 9), Some(&ChunkChange::Removed));
+    assert_eq!(diff.get(31, 31), Some(&ChunkChange::Added));
+    let Some(ChunkChange::Changed(changed)) = diff.get(0, 0) else {
+        panic!("expected a changed chunk");
+    };
+    assert_eq!(changed.len(), 1);
+    assert_eq!(changed.changes()[0].y, 64);
+    assert_eq!(diff.get(1, 1), None);
+
+    assert!(before.diff(&before).is_empty());
+    assert_eq!(after.diff(&before).get(31, 31), Some(&ChunkChange::Removed));
+}
@@ -0,0 +1,400 @@
+mod common;
+
+use std::collections::HashMap;
+
+use common::{chunk_bytes, compound, compound_list, string, uniform_chunk};
+use mca_rs::{
+    Compression, CompressionType, WriteOptions,
+    block::Block,
+    builder::ChunkBuilder,
+    chunk::{Chunk, ChunkParseOptions, parse_chunk, parse_chunk_with},
+    raw::RawChunk,
+    region::{Region, RegionBuilder},
+    section::Section,
+};
+use nbt_rs::types::{NbtList, NbtString, NbtTag};
+
+#[test]
+fn test_round_trip() {
+    let region = Region::parse_bytes(include_bytes!("data/r.0.0.mca")).unwrap();
+    let chunks: Vec<_> = (0..32)
+        .flat_map(|x| (0..32).map(move |z| (x, z)))
+        .filter_map(|(x, z)| region.get_chunk(x, z))
+        .collect();
+    assert_eq!(chunks.len(), 975);
+
+    for chunk in chunks {
+        assert_eq!(&parse_chunk(&chunk.to_bytes()).unwrap(), chunk);
+    }
+}
+
+#[test]
+fn test_edited_round_trip() {
+    let mut chunk = parse_chunk(&chunk_bytes(&uniform_chunk(-4, 24, "minecraft:stone"))).unwrap();
+    let dirt = Block::new("minecraft:dirt".to_owned().try_into().unwrap(), None);
+    for x in 0..16 {
+        chunk.set(x, 70, 3, dirt.clone()).unwrap();
+    }
+
+    let reparsed = parse_chunk(&chunk.to_bytes()).unwrap();
+    assert_eq!(reparsed, chunk);
+    assert_eq!(reparsed.get(15, 70, 3), Some(&dirt));
+    assert_eq!(
+        reparsed.get(15, 71, 3).unwrap().get_name(),
+        "minecraft:stone"
+    );
+}
+
+#[test]
+fn test_palette_encoding() {
+    let mut chunk = parse_chunk(&chunk_bytes(&uniform_chunk(0, 2, "minecraft:stone"))).unwrap();
+    // set separately, but equal to each other
+    for x in 0..3 {
+        let glass = Block::new("minecraft:glass".to_owned().try_into().unwrap(), None);
+        chunk.set(x, 17, 0, glass).unwrap();
+    }
+
+    let nbt = chunk.to_nbt();
+    let Some(NbtTag::List(NbtList::Compound(sections))) = nbt.get("sections") else {
+        panic!("no sections");
+    };
+    let states = |i: usize| {
+        sections[i]
+            .get("block_states")
+            .unwrap()
+            .as_compound()
+            .unwrap()
+    };
+
+    // a single block has no data, like the game writes it
+    let uniform = states(0);
+    assert_eq!(
+        uniform
+            .get("palette")
+            .unwrap()
+            .as_list()
+            .unwrap()
+            .as_compound()
+            .unwrap()
+            .len(),
+        1
+    );
+    assert!(uniform.get("data").is_none());
+
+    let mixed = states(1);
+    let palette = mixed
+        .get("palette")
+        .unwrap()
+        .as_list()
+        .unwrap()
+        .as_compound()
+        .unwrap();
+    assert_eq!(palette.len(), 2);
+    // at least 4 bits per index, so 16 indices per long
+    assert_eq!(
+        mixed.get("data").unwrap().as_long_array().unwrap().len(),
+        256
+    );
+    assert_eq!(sections[1].get("Y"), Some(&NbtTag::Byte(1)));
+}
+
+#[test]
+fn test_biome_encoding() {
+    let name = |name: &str| NbtString::try_from(name.to_owned()).unwrap();
+    let mut chunk = parse_chunk(&chunk_bytes(&uniform_chunk(0, 2, "minecraft:stone"))).unwrap();
+    assert_eq!(chunk.get_biome(0, 0, 0), None);
+    chunk.set_biome(0, 0, 0, name("minecraft:plains")).unwrap();
+    chunk
+        .set_biome(15, 31, 15, name("minecraft:plains"))
+        .unwrap();
+    assert!(chunk.set_biome(0, 32, 0, name("minecraft:plains")).is_err());
+    assert!(chunk.set_biome(16, 0, 0, name("minecraft:plains")).is_err());
+
+    let biomes = |chunk: &Chunk, i: usize| {
+        let nbt = chunk.to_nbt();
+        let Some(NbtTag::List(NbtList::Compound(sections))) = nbt.get("sections") else {
+            panic!("no sections");
+        };
+        sections[i]
+            .get("biomes")
+            .unwrap()
+            .as_compound()
+            .unwrap()
+            .clone()
+    };
+    // a single biome is stored without data
+    let uniform = biomes(&chunk, 0);
+    assert_eq!(
+        uniform
+            .get("palette")
+            .unwrap()
+            .as_list()
+            .unwrap()
+            .as_string()
+            .unwrap()[..],
+        [name("minecraft:plains")]
+    );
+    assert!(uniform.get("data").is_none());
+    let reparsed = parse_chunk(&chunk.to_bytes()).unwrap();
+    assert_eq!(reparsed, chunk);
+    assert_eq!(reparsed.get_biome(7, 12, 3).unwrap(), "minecraft:plains");
+
+    // two biomes take a bit per cell
+    chunk.set_biome(4, 5, 8, name("minecraft:desert")).unwrap();
+    chunk.set_biome(7, 6, 11, name("minecraft:desert")).unwrap();
+    chunk
+        .set_biome(15, 15, 15, name("minecraft:desert"))
+        .unwrap();
+    let mixed = biomes(&chunk, 0);
+    assert_eq!(
+        mixed
+            .get("palette")
+            .unwrap()
+            .as_list()
+            .unwrap()
+            .as_string()
+            .unwrap()[..],
+        [name("minecraft:plains"), name("minecraft:desert")]
+    );
+    assert_eq!(mixed.get("data").unwrap().as_long_array().unwrap().len(), 1);
+    let reparsed = parse_chunk(&chunk.to_bytes()).unwrap();
+    assert_eq!(reparsed, chunk);
+    assert_eq!(reparsed.get_biome(5, 6, 9).unwrap(), "minecraft:desert");
+    assert_eq!(reparsed.get_biome(12, 12, 12).unwrap(), "minecraft:desert");
+    assert_eq!(reparsed.get_biome(3, 4, 8).unwrap(), "minecraft:plains");
+    assert_eq!(
+        reparsed.get_section(0).unwrap().biome_counts()[&name("minecraft:desert")],
+        2
+    );
+    assert_eq!(reparsed.get_biome(0, 16, 0).unwrap(), "minecraft:plains");
+
+    // filling the section makes it a single biome again
+    (chunk.get_section_mut(0).unwrap()).fill_biome(name("minecraft:mushroom_fields"));
+    let filled = biomes(&chunk, 0);
+    assert_eq!(
+        filled
+            .get("palette")
+            .unwrap()
+            .as_list()
+            .unwrap()
+            .as_string()
+            .unwrap()[..],
+        [name("minecraft:mushroom_fields")]
+    );
+    assert!(filled.get("data").is_none());
+    let reparsed = parse_chunk(&chunk.to_bytes()).unwrap();
+    assert_eq!(reparsed, chunk);
+    assert_eq!(
+        reparsed.get_biome(12, 12, 12).unwrap(),
+        "minecraft:mushroom_fields"
+    );
+    assert_eq!(reparsed.get_biome(0, 16, 0).unwrap(), "minecraft:plains");
+}
+
+#[test]
+fn test_region_encodes_built_chunks() {
+    let chunk = ChunkBuilder::new(3, 1).build().unwrap();
+    let region = RegionBuilder::new()
+        .chunk(3, 1, chunk.clone())
+        .timestamp(3, 1, 1234)
+        .build()
+        .unwrap();
+
+    // written with the heightmaps the builder left out
+    let reparsed = Region::parse_bytes(&region.to_bytes().unwrap()).unwrap();
+    let mut expected = chunk.clone();
+    expected.compute_heightmaps();
+    assert_eq!(reparsed.get_chunk(3, 1), Some(&expected));
+    assert_eq!(reparsed.get_timestamp(3, 1), Some(1234));
+    assert_eq!(reparsed.count_chunks(), 1);
+}
+
+#[test]
+fn test_write_options() {
+    let region = Region::parse_bytes(include_bytes!("data/r.0.0.mca")).unwrap();
+    let chunk = region.get_chunk(5, 9).unwrap();
+    let mut sizes = Vec::new();
+    for (compression, level, expected) in [
+        (Compression::Zlib, 9, CompressionType::Zlib),
+        (Compression::Zlib, 0, CompressionType::Zlib),
+        (Compression::Gzip, 6, CompressionType::Gzip),
+        (Compression::None, 6, CompressionType::None),
+    ] {
+        let options = WriteOptions {
+            compression,
+            level,
+            ..Default::default()
+        };
+        let bytes = chunk.to_bytes_with(&options);
+        assert_eq!(RawChunk::parse(&bytes).unwrap().compression(), expected);
+        assert_eq!(&parse_chunk(&bytes).unwrap(), chunk);
+        sizes.push(bytes.len());
+    }
+    assert!(sizes[0] < sizes[1]);
+    assert!(sizes[1] < sizes[3] + 100);
+    let raw = RawChunk::parse(&chunk.to_bytes()).unwrap();
+    assert_eq!(raw.compression(), CompressionType::Zlib);
+
+    let built = RegionBuilder::new()
+        .chunk(5, 9, chunk.clone())
+        .build()
+        .unwrap();
+    let options = WriteOptions {
+        compression: Compression::Gzip,
+        level: 9,
+        ..Default::default()
+    };
+    let reparsed = Region::parse_bytes(&built.to_bytes_with(&options).unwrap()).unwrap();
+    let raw = reparsed.get_chunk_raw(5, 9).unwrap();
+    assert_eq!(raw.compression(), CompressionType::Gzip);
+    assert_eq!(reparsed.get_chunk(5, 9), Some(chunk));
+    // raw chunks keep the compression they were read with
+    let rewritten = Region::parse_bytes(&reparsed.to_bytes().unwrap()).unwrap();
+    let raw = rewritten.get_chunk_raw(5, 9).unwrap();
+    assert_eq!(raw.compression(), CompressionType::Gzip);
+}
+
+#[test]
+fn test_estimate_size() {
+    let region = Region::parse_bytes(include_bytes!("data/r.0.0.mca")).unwrap();
+    let mut edited = parse_chunk(&chunk_bytes(&uniform_chunk(-4, 24, "minecraft:stone"))).unwrap();
+    edited.set_block_light(0, 0, 0, 15).unwrap();
+    let chunks: Vec<_> = (region.iter_chunks().map(|(_, _, chunk)| chunk))
+        .chain([&edited])
+        .collect();
+    let name = NbtString::try_from(String::new()).unwrap();
+    for chunk in &chunks {
+        let nbt = nbt_rs::serialize_nbt(&name, &chunk.to_nbt());
+        assert_eq!(chunk.nbt_size(), nbt.len());
+        let options = WriteOptions {
+            compression: Compression::None,
+            ..Default::default()
+        };
+        assert_eq!(
+            chunk.estimate_serialized_size(Compression::None),
+            chunk.to_bytes_with(&options).len()
+        );
+    }
+
+    // chunks kept as read are counted exactly
+    assert_eq!(
+        region.estimate_file_size(),
+        region.to_bytes().unwrap().len()
+    );
+    let mut built = RegionBuilder::new();
+    for (x, z, chunk) in region.iter_chunks() {
+        built = built.chunk(x as u8, z as u8, chunk.clone());
+    }
+    let built = built.build().unwrap();
+    let (estimate, actual) = (built.estimate_file_size(), built.to_bytes().unwrap().len());
+    assert!(
+        estimate.abs_diff(actual) < actual / 10,
+        "{estimate} {actual}"
+    );
+}
+
+#[test]
+fn test_level_layout() {
+    let blocks: Vec<_> = (0..20)
+        .map(|i| Block::new(format!("minecraft:block_{i}").try_into().unwrap(), None))
+        .collect();
+    let mut section = Section::filled(blocks[0].clone());
+    for (i, block) in blocks.iter().enumerate() {
+        section
+            .set_block(i as u8 % 16, 3, i as u8 / 16, block.clone())
+            .unwrap();
+    }
+    // 1.14 has palette indices spanning longs, 1.16 doesn't, and 20
+    // blocks take 5 bits, which doesn't divide 64
+    for version in [1976, 2586] {
+        let chunk = ChunkBuilder::new(3, -2)
+            .y_range(0..256)
+            .data_version(version)
+            .section(2, section.clone())
+            .compute_heightmaps()
+            .build()
+            .unwrap();
+        let nbt = chunk.to_nbt();
+        assert_eq!(nbt.get("DataVersion"), Some(&NbtTag::Int(version)));
+        let level = nbt.get("Level").unwrap().as_compound().unwrap();
+        assert!(level.get("TileEntities").is_some());
+        assert!(level.get("Entities").is_some());
+        assert!(level.get("yPos").is_none());
+        assert_eq!(parse_chunk(&chunk.to_bytes()).unwrap(), chunk);
+    }
+}
+
+#[test]
+fn test_unknown_fields() {
+    let options = ChunkParseOptions {
+        keep_nbt: true,
+        ..Default::default()
+    };
+    let dirt = Block::new(string("minecraft:dirt"), None);
+
+    let mut fields: HashMap<_, _> = uniform_chunk(-4, 24, "minecraft:stone").into();
+    fields.insert(string("isLightOn"), NbtTag::Byte(1));
+    fields.insert(string("mymod:data"), NbtTag::Int(7));
+    let bytes = chunk_bytes(&fields.into());
+    let mut chunk = parse_chunk_with(&bytes, &options).unwrap();
+    assert_eq!(chunk.to_nbt().get("isLightOn"), Some(&NbtTag::Byte(1)));
+    chunk.set(0, 0, 0, dirt.clone()).unwrap();
+    let nbt = chunk.to_nbt();
+    // the blocks changed, so the stored light no longer counts
+    assert_eq!(nbt.get("isLightOn"), Some(&NbtTag::Byte(0)));
+    let edits: [fn(&mut Section, Block); 3] = [
+        |section, block| section.set_block(1, 2, 3, block).unwrap(),
+        |section, block| section.fill(block),
+        |section, block| section.fill_box(0..2, 0..2, 0..2, block),
+    ];
+    for edit in edits {
+        let mut chunk = parse_chunk_with(&bytes, &options).unwrap();
+        edit(chunk.get_section_mut(0).unwrap(), dirt.clone());
+        assert_eq!(chunk.to_nbt().get("isLightOn"), Some(&NbtTag::Byte(0)));
+    }
+    assert_eq!(nbt.get("mymod:data"), Some(&NbtTag::Int(7)));
+    let reparsed = parse_chunk_with(&chunk.to_bytes(), &options).unwrap();
+    assert_eq!(reparsed.get(0, 0, 0), Some(&dirt));
+    let raw = reparsed.raw_nbt().unwrap();
+    assert_eq!(raw.get("mymod:data"), Some(&NbtTag::Int(7)));
+    // nothing is kept without the nbt
+    assert_eq!(
+        parse_chunk(&bytes).unwrap().to_nbt().get("mymod:data"),
+        None
+    );
+
+    let pig = compound([
+        ("id", NbtTag::String(string("minecraft:pig"))),
+        (
+            "Pos",
+            NbtTag::List(NbtList::Double(vec![1.5, 64.0, 2.5].try_into().unwrap())),
+        ),
+        ("Health", NbtTag::Float(10.0)),
+    ]);
+    let level = compound([
+        ("xPos", NbtTag::Int(0)),
+        ("zPos", NbtTag::Int(0)),
+        ("Status", NbtTag::String(string("full"))),
+        ("Sections", compound_list(Vec::new())),
+        ("Entities", compound_list(vec![pig])),
+        ("isLightOn", NbtTag::Byte(1)),
+        ("PostProcessing", NbtTag::List(NbtList::End)),
+    ]);
+    let root = compound([
+        ("DataVersion", NbtTag::Int(2586)),
+        ("Level", NbtTag::Compound(level)),
+        ("mymod:root", NbtTag::Int(3)),
+    ]);
+    let mut chunk = parse_chunk_with(&chunk_bytes(&root), &options).unwrap();
+    chunk.set(0, 0, 0, dirt).unwrap();
+    let nbt = chunk.to_nbt();
+    assert_eq!(nbt.get("mymod:root"), Some(&NbtTag::Int(3)));
+    let level = nbt.get("Level").unwrap().as_compound().unwrap();
+    assert_eq!(level.get("isLightOn"), Some(&NbtTag::Byte(0)));
+    assert!(level.get("PostProcessing").is_some());
+
+    let reparsed = parse_chunk(&chunk.to_bytes()).unwrap();
+    assert_eq!(reparsed.embedded_entities(), chunk.embedded_entities());
+    assert_eq!(reparsed.embedded_entities()[0].pos, (1.5, 64.0, 2.5));
+    assert_eq!(reparsed.get(0, 0, 0).unwrap().get_name(), "minecraft:dirt");
+}
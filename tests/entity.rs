@@ -0,0 +1,252 @@
+mod common;
+
+use std::collections::HashMap;
+
+use common::{chunk_bytes, compound, compound_list, region_bytes, string};
+use mca_rs::{
+    ChunkPos, EntityRegion,
+    chunk::ChunkParseError,
+    region::{RegionParseError, RegionParseOptions},
+};
+use nbt_rs::types::{NbtCompound, NbtList, NbtString, NbtTag};
+
+fn entity(id: &str, pos: [f64; 3]) -> NbtCompound {
+    compound([
+        ("id", NbtTag::String(string(id))),
+        (
+            "Pos",
+            NbtTag::List(NbtList::Double(pos.to_vec().try_into().unwrap())),
+        ),
+        (
+            "Motion",
+            NbtTag::List(NbtList::Double(vec![0.0, -0.08, 0.0].try_into().unwrap())),
+        ),
+        (
+            "Rotation",
+            NbtTag::List(NbtList::Float(vec![90.0, 10.0].try_into().unwrap())),
+        ),
+        ("Health", NbtTag::Float(20.0)),
+    ])
+}
+
+fn entity_chunk(x: i32, z: i32, entities: Vec<NbtCompound>) -> Vec<u8> {
+    chunk_bytes(&compound([
+        ("DataVersion", NbtTag::Int(4325)),
+        ("Position", NbtTag::IntArray(vec![x, z].try_into().unwrap())),
+        ("Entities", compound_list(entities)),
+    ]))
+}
+
+#[test]
+fn test_entity_region() {
+    let bytes = region_bytes(&[
+        (
+            1,
+            2,
+            entity_chunk(
+                -31,
+                -30,
+                vec![
+                    entity("minecraft:zombie", [-490.5, 64.0, -470.25]),
+                    entity("minecraft:cow", [-488.0, 65.0, -475.0]),
+                ],
+            ),
+        ),
+        (3, 0, entity_chunk(-29, -32, vec![])),
+    ]);
+    let region = EntityRegion::parse_bytes(&bytes).unwrap();
+    assert_eq!(region.count_chunks(), 2);
+
+    let chunk = region.get_chunk(1, 2).unwrap();
+    assert_eq!(chunk.get_pos(), ChunkPos::new(-31, -30));
+    assert_eq!(chunk.data_version(), 4325);
+    let zombie = &region.get_entities(1, 2)[0];
+    assert_eq!(zombie.id, string("minecraft:zombie"));
+    assert_eq!(zombie.pos, (-490.5, 64.0, -470.25));
+    assert_eq!(zombie.motion, (0.0, -0.08, 0.0));
+    assert_eq!(zombie.rotation, (90.0, 10.0));
+    assert_eq!(zombie.data.get("Health"), Some(&NbtTag::Float(20.0)));
+
+    assert_eq!(
+        region.get_entities_at(ChunkPos::new(-31, -30)),
+        region.get_entities(1, 2)
+    );
+    assert!(region.get_entities(3, 0).is_empty());
+    assert!(region.get_entities(0, 0).is_empty());
+    assert!(region.get_entities(32, 0).is_empty());
+
+    let ids: Vec<_> = region
+        .iter_entities()
+        .map(|(x, z, entity)| (x, z, entity.id.to_string()))
+        .collect();
+    assert_eq!(
+        ids,
+        [
+            (1, 2, "minecraft:zombie".to_owned()),
+            (1, 2, "minecraft:cow".to_owned()),
+        ]
+    );
+}
+
+#[test]
+fn test_entity_region_errors() {
+    let no_pos = compound([("id", NbtTag::String(string("minecraft:zombie")))]);
+    let bytes = region_bytes(&[
+        (0, 0, entity_chunk(0, 0, vec![no_pos])),
+        (1, 0, entity_chunk(5, 0, vec![])),
+    ]);
+
+    // the broken chunk is left out
+    let region = EntityRegion::parse_bytes(&bytes).unwrap();
+    assert!(region.get_chunk(0, 0).is_none());
+    assert!(region.get_chunk(1, 0).is_some());
+
+    let strict = RegionParseOptions {
+        strict: true,
+        ..Default::default()
+    };
+    assert!(matches!(
+        EntityRegion::parse_bytes_with(&bytes, &strict),
+        Err(RegionParseError::ChunkError {
+            x: 0,
+            z: 0,
+            source: ChunkParseError::InvalidField(path, _),
+        }) if path == "Entities[0].Pos"
+    ));
+
+    let check_slots = RegionParseOptions {
+        check_slots: true,
+        ..Default::default()
+    };
+    let region = EntityRegion::parse_bytes_with(&bytes, &check_slots).unwrap();
+    assert_eq!(region.count_chunks(), 0);
+}
+
+fn with(entity: NbtCompound, field: &str, tag: NbtTag) -> NbtCompound {
+    let mut fields: HashMap<NbtString, NbtTag> = entity.into();
+    fields.insert(string(field), tag);
+    fields.into()
+}
+
+fn item(id: &str, count: NbtTag) -> NbtCompound {
+    compound([("id", NbtTag::String(string(id))), ("count", count)])
+}
+
+#[test]
+fn test_entity_queries() {
+    let frame = with(
+        entity("minecraft:item_frame", [5.5, 70.0, 3.03]),
+        "Item",
+        NbtTag::Compound(item("minecraft:diamond", NbtTag::Int(1))),
+    );
+    let stand = with(
+        entity("minecraft:armor_stand", [40.5, 64.0, 8.5]),
+        "HandItems",
+        compound_list(vec![
+            compound([
+                ("id", NbtTag::String(string("minecraft:torch"))),
+                ("Count", NbtTag::Byte(3)),
+            ]),
+            compound([]),
+        ]),
+    );
+    let empty_stand = with(
+        entity("minecraft:armor_stand", [41.5, 64.0, 8.5]),
+        "HandItems",
+        compound_list(vec![compound([]), compound([])]),
+    );
+    let skeleton = with(
+        entity("skeleton", [20.0, 30.0, 20.0]),
+        "equipment",
+        NbtTag::Compound(compound([(
+            "mainhand",
+            NbtTag::Compound(compound([("id", NbtTag::String(string("minecraft:bow")))])),
+        )])),
+    );
+    let bytes = region_bytes(&[
+        (
+            0,
+            0,
+            entity_chunk(
+                0,
+                0,
+                vec![
+                    entity("minecraft:zombie", [1.0, 64.0, 1.0]),
+                    entity("minecraft:zombie", [14.5, 60.0, 2.0]),
+                    frame,
+                    skeleton,
+                ],
+            ),
+        ),
+        (
+            2,
+            0,
+            entity_chunk(
+                2,
+                0,
+                vec![
+                    entity("minecraft:cow", [33.0, 64.0, 4.0]),
+                    stand,
+                    empty_stand,
+                ],
+            ),
+        ),
+    ]);
+    let region = EntityRegion::parse_bytes(&bytes).unwrap();
+
+    let chunk = region.get_chunk(0, 0).unwrap();
+    assert_eq!(chunk.entities_by_id("minecraft:zombie").count(), 2);
+    // ids without a namespace are in minecraft:
+    assert_eq!(chunk.entities_by_id("zombie").count(), 2);
+    assert_eq!(chunk.entities_by_id("minecraft:skeleton").count(), 1);
+    assert_eq!(chunk.entities_by_id("other:zombie").count(), 0);
+    let stands: Vec<_> = region
+        .entities_by_id("armor_stand")
+        .map(|(x, z, entity)| (x, z, entity.pos))
+        .collect();
+    assert_eq!(
+        stands,
+        [(2, 0, (40.5, 64.0, 8.5)), (2, 0, (41.5, 64.0, 8.5))]
+    );
+
+    let counts = region.count_entities_by_id();
+    assert_eq!(counts.len(), 5);
+    assert_eq!(counts[&string("minecraft:zombie")], 2);
+    assert_eq!(counts[&string("minecraft:armor_stand")], 2);
+    assert_eq!(counts[&string("skeleton")], 1);
+
+    let found: Vec<_> = region
+        .entities_in_box((0.0, 60.0, 0.0), (33.0, 70.0, 5.0))
+        .map(|(_, _, entity)| entity.id.to_string())
+        .collect();
+    assert_eq!(
+        found,
+        [
+            "minecraft:zombie",
+            "minecraft:zombie",
+            "minecraft:item_frame",
+            "minecraft:cow"
+        ]
+    );
+    assert_eq!(
+        region
+            .entities_in_box((100.0, 0.0, 100.0), (200.0, 100.0, 200.0))
+            .count(),
+        0
+    );
+
+    let items: Vec<_> = region
+        .iter_entities()
+        .filter_map(|(_, _, entity)| entity.item())
+        .map(|item| (item.id.to_string(), item.count))
+        .collect();
+    assert_eq!(
+        items,
+        [
+            ("minecraft:diamond".to_owned(), 1),
+            ("minecraft:bow".to_owned(), 1),
+            ("minecraft:torch".to_owned(), 3),
+        ]
+    );
+    assert_eq!(chunk.entities()[0].item(), None);
+}
@@ -0,0 +1,53 @@
+#![cfg(feature = "ffi")]
+
+use std::{env, path::PathBuf, process::Command};
+
+#[test]
+fn test_c_smoke_program() {
+    let manifest_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let tmp_dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR"));
+    // the crate only declares the rlib, so crates depending on it don't
+    // build a shared library too, and the one for C is built here
+    let target_dir = tmp_dir.join("ffi");
+    let status = Command::new(env::var("CARGO").unwrap_or_else(|_| "cargo".to_owned()))
+        .args([
+            "rustc",
+            "--lib",
+            "--features",
+            "ffi",
+            "--crate-type",
+            "cdylib",
+        ])
+        .arg("--target-dir")
+        .arg(&target_dir)
+        .current_dir(&manifest_dir)
+        .status()
+        .expect("failed to run cargo");
+    assert!(status.success());
+    let lib_dir = target_dir.join("debug");
+    let program = tmp_dir.join("ffi_smoke");
+
+    let status = Command::new(env::var("CC").unwrap_or_else(|_| "cc".to_owned()))
+        .arg("-std=c99")
+        .arg("-Wall")
+        .arg("-Werror")
+        .arg("-I")
+        .arg(manifest_dir.join("include"))
+        .arg(manifest_dir.join("tests/ffi/smoke.c"))
+        .arg("-L")
+        .arg(&lib_dir)
+        .arg("-lmca_rs")
+        .arg("-o")
+        .arg(&program)
+        .status()
+        .expect("failed to run the C compiler");
+    assert!(status.success());
+
+    let status = Command::new(&program)
+        .arg(manifest_dir.join("tests/data/r.0.0.mca"))
+        .env("LD_LIBRARY_PATH", &lib_dir)
+        .env("DYLD_LIBRARY_PATH", &lib_dir)
+        .status()
+        .unwrap();
+    assert!(status.success());
+}
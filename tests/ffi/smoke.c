@@ -0,0 +1,72 @@
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+#include "mca.h"
+
+#define CHECK(expr)                                                            \
+    do {                                                                       \
+        if (!(expr)) {                                                         \
+            fprintf(stderr, "%s:%d: check failed: %s\n", __FILE__, __LINE__,   \
+                    #expr);                                                    \
+            return 1;                                                          \
+        }                                                                      \
+    } while (0)
+
+static uint8_t *read_file(const char *path, size_t *len) {
+    FILE *file = fopen(path, "rb");
+    if (!file) {
+        return NULL;
+    }
+    fseek(file, 0, SEEK_END);
+    *len = (size_t)ftell(file);
+    fseek(file, 0, SEEK_SET);
+    uint8_t *data = malloc(*len);
+    if (data && fread(data, 1, *len, file) != *len) {
+        free(data);
+        data = NULL;
+    }
+    fclose(file);
+    return data;
+}
+
+int main(int argc, char **argv) {
+    CHECK(argc == 2);
+
+    size_t len = 0;
+    uint8_t *data = read_file(argv[1], &len);
+    CHECK(data != NULL);
+
+    mca_region *region = NULL;
+    CHECK(mca_region_parse(data, 100, &region) == MCA_ERROR_INPUT_TOO_SHORT);
+    CHECK(region == NULL);
+    CHECK(mca_region_parse(data, len, &region) == MCA_ERROR_OK);
+    free(data);
+    CHECK(mca_region_count_chunks(region) == 975);
+
+    const mca_chunk *chunk = NULL;
+    CHECK(mca_region_get_chunk(region, 32, 0, &chunk) == MCA_ERROR_OUT_OF_BOUNDS);
+    CHECK(mca_region_get_chunk(region, 0, 0, &chunk) == MCA_ERROR_OK);
+
+    mca_block_info info;
+    CHECK(mca_chunk_get_block(chunk, 0, -64, 0, &info) == MCA_ERROR_OK);
+    CHECK(info.name_len == strlen("minecraft:bedrock"));
+    CHECK(memcmp(info.name, "minecraft:bedrock", info.name_len) == 0);
+    CHECK(info.property_count == 0);
+    CHECK(mca_chunk_get_block(chunk, 16, 0, 0, &info) == MCA_ERROR_OUT_OF_BOUNDS);
+
+    CHECK(strcmp(mca_error_message(MCA_ERROR_OK), "ok") == 0);
+    CHECK(strcmp(mca_error_message(MCA_ERROR_SECTOR_OVERLAP),
+                 "chunks in the region share sectors") == 0);
+    CHECK(strcmp(mca_error_message(1000), "unknown error") == 0);
+    /* codes keep the values they were released with */
+    CHECK(MCA_ERROR_PANIC == 12);
+    CHECK(strcmp(mca_error_message(12), "internal panic") == 0);
+    CHECK(strcmp(mca_error_message(MCA_ERROR_LIMIT_EXCEEDED),
+                 "chunk exceeds a resource limit") == 0);
+    CHECK(MCA_ERROR_INVALID_HEIGHTMAP == 14);
+    CHECK(strcmp(mca_error_message(MCA_ERROR_INVALID_HEIGHTMAP), "invalid heightmap") == 0);
+
+    mca_region_free(region);
+    return 0;
+}
@@ -0,0 +1,163 @@
+mod common;
+
+use std::io::Write;
+
+use common::{chunk_bytes, region_bytes, uniform_chunk};
+use flate2::{Compression, write::ZlibEncoder};
+use mca_rs::{Chunk, region::Region};
+
+const FIXTURE: &[u8] = include_bytes!("data/r.0.0.mca");
+
+/// A xorshift generator, so failures reproduce without a dependency.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    fn bytes(&mut self, len: usize) -> Vec<u8> {
+        (0..len).map(|_| self.next() as u8).collect()
+    }
+}
+
+/// `bytes` with a few random edits: flipped bits, values near the edges of
+/// their range, removed or inserted runs, and a truncated or extended end.
+fn mutate(rng: &mut Rng, bytes: &[u8]) -> Vec<u8> {
+    let mut bytes = bytes.to_vec();
+    for _ in 0..1 + rng.below(8) {
+        if bytes.is_empty() {
+            bytes.push(rng.next() as u8);
+            continue;
+        }
+        let i = rng.below(bytes.len());
+        match rng.below(6) {
+            0 => bytes[i] ^= 1 << rng.below(8),
+            1 => bytes[i] = [0x00, 0x01, 0x7F, 0x80, 0xFF][rng.below(5)],
+            2 => {
+                let end = (i + 1 + rng.below(16)).min(bytes.len());
+                bytes.drain(i..end);
+            }
+            3 => {
+                let len = 1 + rng.below(16);
+                let run = rng.bytes(len);
+                bytes.splice(i..i, run);
+            }
+            4 => bytes.truncate(i),
+            _ => {
+                let run = [0xFF; 4].map(|byte| byte >> rng.below(8));
+                let end = (i + 4).min(bytes.len());
+                bytes.splice(i..end, run);
+            }
+        }
+    }
+    bytes
+}
+
+/// The payload of a stored chunk decompressed and stored again without
+/// compression, so mutations reach the nbt rather than the zlib stream.
+fn uncompressed(chunk: &[u8]) -> Vec<u8> {
+    let payload = mca_rs::RawChunk::parse(chunk)
+        .unwrap()
+        .decompressed_data()
+        .unwrap();
+    let mut bytes = (payload.len() as u32 + 1).to_be_bytes().to_vec();
+    bytes.push(3);
+    bytes.extend_from_slice(&payload);
+    bytes
+}
+
+/// `payload` compressed and given a chunk header.
+fn zlib(payload: &[u8]) -> Vec<u8> {
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::fast());
+    encoder.write_all(payload).unwrap();
+    let payload = encoder.finish().unwrap();
+    let mut bytes = (payload.len() as u32 + 1).to_be_bytes().to_vec();
+    bytes.push(2);
+    bytes.extend_from_slice(&payload);
+    bytes
+}
+
+fn fixture_chunks() -> Vec<Vec<u8>> {
+    let mut chunks: Vec<_> = [(0, 0), (5, 9), (17, 3), (9, 27)]
+        .into_iter()
+        .map(|(x, z)| {
+            let entry = &FIXTURE[(x + z * 32) * 4..][..4];
+            let offset = u32::from_be_bytes([0, entry[0], entry[1], entry[2]]) as usize * 4096;
+            let len = u32::from_be_bytes(FIXTURE[offset..][..4].try_into().unwrap());
+            uncompressed(&FIXTURE[offset..][..len as usize + 4])
+        })
+        .collect();
+    chunks.push(uncompressed(&chunk_bytes(&uniform_chunk(
+        -4,
+        3,
+        "minecraft:stone",
+    ))));
+    chunks
+}
+
+// any outcome but a panic or an abort passes
+#[test]
+fn test_chunk_never_panics() {
+    let mut rng = Rng(0x9E37_79B9_7F4A_7C15);
+    for len in [0, 1, 4, 5, 6, 64, 4096] {
+        for _ in 0..16 {
+            let _ = Chunk::try_from(rng.bytes(len).as_slice());
+        }
+    }
+
+    let chunks = fixture_chunks();
+    for _ in 0..4000 {
+        let chunk = &chunks[rng.below(chunks.len())];
+        let mutated = mutate(&mut rng, chunk);
+        let _ = Chunk::try_from(mutated.as_slice());
+        // the same damage behind compression, and in the payload only
+        let _ = Chunk::try_from(zlib(mutated.get(5..).unwrap_or_default()).as_slice());
+        let mut body = chunk.clone();
+        let tail = mutate(&mut rng, &chunk[5..]);
+        body.truncate(5);
+        body.extend_from_slice(&tail);
+        let _ = Chunk::try_from(body.as_slice());
+    }
+}
+
+#[test]
+fn test_region_never_panics() {
+    let mut rng = Rng(0xD1B5_4A32_D192_ED03);
+    for len in [0, 4096, 8191, 8192, 8193, 12288] {
+        for _ in 0..8 {
+            let _ = Region::try_from(rng.bytes(len).as_slice());
+        }
+    }
+
+    // the header only, so offsets and counts point anywhere
+    for _ in 0..500 {
+        let mut bytes = FIXTURE.to_vec();
+        for _ in 0..1 + rng.below(16) {
+            let i = rng.below(8192);
+            bytes[i] = rng.next() as u8;
+        }
+        bytes.truncate(8192 + rng.below(FIXTURE.len() - 8192));
+        let _ = Region::try_from(bytes.as_slice());
+    }
+
+    let chunks = fixture_chunks();
+    for _ in 0..300 {
+        let slots: Vec<_> = (0..1 + rng.below(4))
+            .map(|_| {
+                let chunk = &chunks[rng.below(chunks.len())];
+                (rng.below(32), rng.below(32), mutate(&mut rng, chunk))
+            })
+            .collect();
+        let bytes = region_bytes(&slots);
+        let _ = Region::try_from(bytes.as_slice());
+        let _ = Region::try_from(mutate(&mut rng, &bytes).as_slice());
+    }
+}
@@ -0,0 +1,216 @@
+mod common;
+
+use std::{collections::HashMap, io::Write};
+
+use common::{chunk_bytes, compound, compound_list, string, uniform_chunk};
+use flate2::{Compression, write::GzEncoder};
+use mca_rs::{
+    block::Block,
+    builder::ChunkBuilder,
+    chunk::{ChunkParseOptions, parse_chunk},
+    region::Region,
+    section::Section,
+};
+use nbt_rs::types::{NbtCompound, NbtTag};
+
+#[test]
+fn test_hash_ignores_compression() {
+    let region = Region::parse_bytes(include_bytes!("data/r.0.0.mca")).unwrap();
+    let chunk = region.get_chunk(5, 9).unwrap();
+    let nbt = region
+        .get_chunk_raw(5, 9)
+        .unwrap()
+        .decompressed_data()
+        .unwrap();
+
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::best());
+    encoder.write_all(&nbt).unwrap();
+    let payload = encoder.finish().unwrap();
+    let mut bytes = (payload.len() as u32 + 1).to_be_bytes().to_vec();
+    bytes.push(1);
+    bytes.extend_from_slice(&payload);
+
+    let gzipped = parse_chunk(&bytes).unwrap();
+    assert_eq!(&gzipped, chunk);
+    assert_eq!(gzipped.content_hash(), chunk.content_hash());
+    assert_ne!(
+        region.get_chunk(5, 10).unwrap().content_hash(),
+        chunk.content_hash()
+    );
+}
+
+#[test]
+fn test_hash_ignores_property_order() {
+    let stairs = |properties: [(&str, &str); 2]| {
+        let properties = properties.map(|(key, value)| (key, NbtTag::String(string(value))));
+        let block = Block::new(string("minecraft:oak_stairs"), Some(compound(properties)));
+        ChunkBuilder::new(0, 0)
+            .section(0, Section::filled(block))
+            .build()
+            .unwrap()
+    };
+    let a = stairs([("facing", "east"), ("half", "top")]);
+    let b = stairs([("half", "top"), ("facing", "east")]);
+    let c = stairs([("half", "bottom"), ("facing", "east")]);
+    assert_eq!(a.content_hash(), b.content_hash());
+    assert_ne!(a.content_hash(), c.content_hash());
+}
+
+#[test]
+fn test_section_eq_ignores_palette() {
+    fn same<T: Eq>(a: &T, b: &T) -> bool {
+        a == b
+    }
+
+    let stone = Block::new(string("minecraft:stone"), None);
+    let dirt = Block::new(string("minecraft:dirt"), None);
+    let mut a = Section::filled(stone.clone());
+    a.set_block(0, 0, 0, dirt.clone()).unwrap();
+    let mut b = Section::filled(dirt);
+    b.fill_box(1..16, 0..16, 0..16, stone.clone());
+    b.fill_box(0..1, 1..16, 0..16, stone.clone());
+    b.fill_box(0..1, 0..1, 1..16, stone);
+    assert_ne!(a.palette()[0], b.palette()[0]);
+    assert!(same(&a, &b));
+
+    let chunk = |section| ChunkBuilder::new(0, 0).section(0, section).build().unwrap();
+    let (a, b) = (chunk(a), chunk(b));
+    assert!(same(&a, &b));
+    assert_eq!(a.content_hash(), b.content_hash());
+}
+
+#[test]
+fn test_hash_ignores_position() {
+    let region = Region::parse_bytes(include_bytes!("data/r.0.0.mca")).unwrap();
+    // holds block entities, which move along
+    let chunk = region.get_chunk(5, 0).unwrap();
+    assert!(!chunk.block_entities().is_empty());
+    let mut moved = chunk.clone();
+    moved.relocate(-40, 17);
+
+    assert_eq!(moved.content_hash(), chunk.content_hash());
+    assert_eq!(moved.content_hash_256(), chunk.content_hash_256());
+    assert!(moved.content_eq(chunk));
+    assert_ne!(&moved, chunk);
+    assert!(!region.get_chunk(5, 9).unwrap().content_eq(chunk));
+}
+
+#[test]
+fn test_eq_ignores_parse_options() {
+    let region = Region::parse_bytes(include_bytes!("data/r.0.0.mca")).unwrap();
+    let raw = region.get_chunk_raw(5, 0).unwrap();
+    let options = ChunkParseOptions {
+        keep_nbt: true,
+        lenient: true,
+        ..Default::default()
+    };
+    let kept = raw.decode_with(&options).unwrap();
+    assert!(kept.raw_nbt().is_some());
+    assert_eq!(&kept, region.get_chunk(5, 0).unwrap());
+}
+
+#[test]
+fn test_eq_with_nan() {
+    let sign = compound([
+        ("id", NbtTag::String(string("minecraft:sign"))),
+        ("x", NbtTag::Int(1)),
+        ("y", NbtTag::Int(2)),
+        ("z", NbtTag::Int(3)),
+        ("f", NbtTag::Float(f32::NAN)),
+    ]);
+    let mut root: HashMap<_, _> = uniform_chunk(0, 1, "minecraft:stone").into();
+    root.insert(string("block_entities"), compound_list(vec![sign]));
+    let chunk = parse_chunk(&chunk_bytes(&root.into())).unwrap();
+    assert_eq!(chunk, chunk.clone());
+    assert!(chunk.content_eq(&chunk.clone()));
+}
+
+#[test]
+fn test_region_hash() {
+    let bytes = include_bytes!("data/r.0.0.mca");
+    let region = Region::parse_bytes(bytes).unwrap();
+    assert_eq!(
+        region.content_hash(),
+        Region::parse_bytes(bytes).unwrap().content_hash()
+    );
+
+    let air = Block::new(string("minecraft:air"), None);
+    let edited = region.clone().map_chunks(|x, z, mut chunk| {
+        if (x, z) == (5, 9) {
+            chunk.set(0, -64, 0, air.clone()).unwrap();
+        }
+        chunk
+    });
+    assert_ne!(edited.content_hash(), region.content_hash());
+}
+
+#[test]
+fn test_hash_covers_block_entities() {
+    let chest = |item: &str| {
+        let slot = compound([
+            ("Slot", NbtTag::Byte(0)),
+            ("id", NbtTag::String(string(item))),
+            ("count", NbtTag::Int(1)),
+        ]);
+        compound([
+            ("id", NbtTag::String(string("minecraft:chest"))),
+            ("x", NbtTag::Int(1)),
+            ("y", NbtTag::Int(2)),
+            ("z", NbtTag::Int(3)),
+            ("Items", compound_list(vec![slot])),
+        ])
+    };
+    let with_entities = |entities: Vec<NbtCompound>| {
+        let mut root: HashMap<_, _> = uniform_chunk(0, 1, "minecraft:chest").into();
+        root.insert(string("block_entities"), compound_list(entities));
+        parse_chunk(&chunk_bytes(&root.into())).unwrap()
+    };
+
+    let diamond = with_entities(vec![chest("minecraft:diamond")]);
+    // the keys come out of the map in another order
+    let again = with_entities(vec![chest("minecraft:diamond")]);
+    let dirt = with_entities(vec![chest("minecraft:dirt")]);
+    let empty = with_entities(Vec::new());
+    assert_eq!(diamond.content_hash(), again.content_hash());
+    assert_eq!(diamond.content_hash_256(), again.content_hash_256());
+    assert_ne!(diamond.content_hash(), dirt.content_hash());
+    assert_ne!(diamond.content_hash(), empty.content_hash());
+    assert_ne!(diamond.content_hash_256(), dirt.content_hash_256());
+}
+
+#[test]
+fn test_hash_covers_biomes() {
+    let stone = Block::new(string("minecraft:stone"), None);
+    let chunk = |biome: &str| {
+        let mut chunk = ChunkBuilder::new(0, 0)
+            .section(0, Section::filled(stone.clone()))
+            .build()
+            .unwrap();
+        chunk.set_biome(0, 0, 0, string(biome)).unwrap();
+        chunk
+    };
+    let plains = chunk("minecraft:plains");
+    assert_eq!(
+        plains.content_hash(),
+        chunk("minecraft:plains").content_hash()
+    );
+    assert_ne!(
+        plains.content_hash(),
+        chunk("minecraft:desert").content_hash()
+    );
+    assert_ne!(
+        plains.content_hash_256(),
+        chunk("minecraft:desert").content_hash_256()
+    );
+}
+
+#[test]
+fn test_hash_256() {
+    let region = Region::parse_bytes(include_bytes!("data/r.0.0.mca")).unwrap();
+    let chunk = region.get_chunk(5, 9).unwrap();
+    let hash = chunk.content_hash_256();
+    assert_eq!(hash, chunk.clone().content_hash_256());
+    assert_ne!(hash, region.get_chunk(5, 10).unwrap().content_hash_256());
+    // the lanes don't just repeat one another
+    assert_ne!(hash[..8], hash[8..16]);
+}
@@ -0,0 +1,169 @@
+mod common;
+
+use common::block;
+use mca_rs::{
+    block::Block, builder::ChunkBuilder, heightmap::HeightmapKind, region::Region, section::Section,
+};
+
+#[test]
+fn test_compute_heightmaps() {
+    let mut section = Section::empty();
+    section
+        .set_block(0, 0, 0, block("minecraft:stone"))
+        .unwrap();
+    section
+        .set_block(0, 1, 0, block("minecraft:water"))
+        .unwrap();
+    section
+        .set_block(0, 2, 0, block("minecraft:oak_leaves"))
+        .unwrap();
+    section
+        .set_block(0, 3, 0, block("minecraft:torch"))
+        .unwrap();
+    let chunk = ChunkBuilder::new(0, 0)
+        .section(4, section)
+        .compute_heightmaps()
+        .build()
+        .unwrap();
+
+    let height = |kind| chunk.get_heightmap(kind).unwrap().get(0, 0).unwrap();
+    assert_eq!(height(HeightmapKind::WorldSurface), 68);
+    assert_eq!(height(HeightmapKind::MotionBlocking), 67);
+    assert_eq!(height(HeightmapKind::MotionBlockingNoLeaves), 66);
+    // leaves block motion, but water doesn't
+    assert_eq!(height(HeightmapKind::OceanFloor), 67);
+
+    let empty = chunk.get_heightmap(HeightmapKind::WorldSurface).unwrap();
+    assert_eq!(empty.get(1, 0), Some(-64));
+    assert_eq!(empty.get(16, 0), None);
+}
+
+#[test]
+fn test_compute_surface() {
+    let mut section = Section::filled(block("minecraft:stone"));
+    section
+        .set_block(2, 5, 3, block("minecraft:water"))
+        .unwrap();
+    section
+        .set_block(2, 9, 3, block("minecraft:water"))
+        .unwrap();
+    let chunk = ChunkBuilder::new(0, 0)
+        .section(-1, Section::filled(block("minecraft:water")))
+        .section(0, section)
+        .build()
+        .unwrap();
+
+    let is_water = |block: &Block| block.get_name() == "minecraft:water";
+    let surface = chunk.compute_surface(is_water, None);
+    assert_eq!(surface[2][3], Some(9));
+    assert_eq!(surface[3][2], Some(-1));
+    assert_eq!(chunk.compute_surface(is_water, Some(8))[2][3], Some(5));
+    assert_eq!(chunk.compute_surface(is_water, Some(-20))[2][3], None);
+
+    let is_stone = |block: &Block| block.get_name() == "minecraft:stone";
+    assert_eq!(chunk.compute_surface(is_stone, Some(9))[2][3], Some(8));
+    assert_eq!(chunk.compute_surface(is_stone, None)[0][0], Some(15));
+}
+
+#[test]
+fn test_computed_match_stored() {
+    let region = Region::parse_bytes(include_bytes!("data/r.0.0.mca")).unwrap();
+    let (mut columns, mut mismatches) = (0, 0);
+    for x in 0..32 {
+        let Some(chunk) = region.get_chunk(x, 9) else {
+            continue;
+        };
+        let mut computed = chunk.clone();
+        computed.compute_heightmaps();
+
+        for kind in HeightmapKind::ALL {
+            let Some(stored) = chunk.get_heightmap(kind) else {
+                continue;
+            };
+            let computed = computed.get_heightmap(kind).unwrap();
+            for (x, z) in (0..16).flat_map(|x| (0..16).map(move |z| (x, z))) {
+                columns += 1;
+                if stored.get(x, z) != computed.get(x, z) {
+                    mismatches += 1;
+                }
+            }
+        }
+    }
+
+    assert!(columns > 0);
+    // cocoa beans placed by trees are left out of some maps by the game
+    assert!(mismatches * 1000 < columns, "{mismatches} of {columns}");
+}
+
+#[test]
+fn test_surface_map() {
+    let region = Region::parse_bytes(include_bytes!("data/r.0.0.mca")).unwrap();
+    let not_plant = |block: &Block| !block.is_air() && HeightmapKind::OceanFloor.matches(block);
+    for z in 0..32 {
+        let Some(chunk) = region.get_chunk(9, z) else {
+            continue;
+        };
+        assert!(chunk.get_heightmap(HeightmapKind::WorldSurface).is_some());
+        let surface = chunk.surface_map(|block| !block.is_air());
+        let ground = chunk.surface_map(not_plant);
+        for (x, z) in (0..16).flat_map(|x| (0..16).map(move |z| (x, z))) {
+            let sample = surface[x as usize][z as usize].unwrap();
+            assert_eq!(chunk.highest_block(x, z), Some((sample.y, sample.block)));
+            assert_eq!(sample.below, chunk.get(x, sample.y - 1, z));
+
+            let sample = ground[x as usize][z as usize].map(|sample| (sample.y, sample.block));
+            assert_eq!(sample, chunk.highest_block_matching(x, z, not_plant));
+        }
+    }
+
+    let map = region.surface_map(|block| !block.is_air());
+    assert_eq!(map.len(), 512 * 512);
+    let chunk = region.get_chunk(9, 2).unwrap();
+    let sample = map[9 * 16 + 4 + (2 * 16 + 7) * 512].unwrap();
+    assert_eq!(Some((sample.y, sample.block)), chunk.highest_block(4, 7));
+    // slot (31, 31) is empty
+    assert!(map[512 * 512 - 1].is_none());
+}
+
+#[test]
+fn test_surface_map_without_heightmaps() {
+    let mut section = Section::empty();
+    section
+        .set_block(2, 0, 3, block("minecraft:stone"))
+        .unwrap();
+    section.set_block(2, 1, 3, block("minecraft:snow")).unwrap();
+    let chunk = ChunkBuilder::new(0, 0)
+        .section(0, Section::filled(block("minecraft:water")))
+        .section(1, section)
+        .section(2, Section::empty())
+        .build()
+        .unwrap();
+    assert!(chunk.get_heightmap(HeightmapKind::WorldSurface).is_none());
+
+    let surface = chunk.surface_map(|block| !block.is_air());
+    let sample = surface[2][3].unwrap();
+    assert_eq!((sample.y, sample.block), (17, &block("minecraft:snow")));
+    assert_eq!(sample.below, Some(&block("minecraft:stone")));
+    let sample = surface[0][0].unwrap();
+    assert_eq!(
+        (sample.y, sample.below),
+        (15, Some(&block("minecraft:water")))
+    );
+
+    // looking through the snow
+    let no_snow = |block: &Block| !block.is_air() && block.get_name() != "minecraft:snow";
+    let sample = chunk.surface_map(no_snow)[2][3].unwrap();
+    assert_eq!(
+        (sample.y, sample.below),
+        (16, Some(&block("minecraft:water")))
+    );
+
+    let is_water = |block: &Block| block.get_name() == "minecraft:water";
+    let sample = chunk.surface_map(is_water)[2][3].unwrap();
+    assert_eq!(
+        (sample.y, sample.below),
+        (15, Some(&block("minecraft:water")))
+    );
+    assert_eq!(chunk.surface_map(is_water)[0][0].unwrap().y, 15);
+    assert!(chunk.surface_map(|block| block.get_name() == "minecraft:dirt")[0][0].is_none());
+}
@@ -0,0 +1,88 @@
+#![cfg(feature = "serde")]
+
+mod common;
+
+use std::collections::HashMap;
+
+use common::{chunk_bytes, compound, string, uniform_chunk};
+use mca_rs::{
+    chunk::{ChunkParseOptions, parse_chunk, parse_chunk_with},
+    json::{JsonOptions, nbt_to_json, nbt_to_json_with},
+};
+use nbt_rs::types::{NbtCompound, NbtList, NbtTag};
+use serde_json::json;
+
+fn sample() -> NbtCompound {
+    let inner = compound([("id", NbtTag::String(string("minecraft:chest")))]);
+    compound([
+        ("byte", NbtTag::Byte(-1)),
+        ("long", NbtTag::Long(i64::MAX)),
+        (
+            "longs",
+            NbtTag::LongArray(vec![i64::MIN, 0].try_into().unwrap()),
+        ),
+        ("bytes", NbtTag::ByteArray(vec![1i8, 2].try_into().unwrap())),
+        (
+            "list",
+            NbtTag::List(NbtList::Compound(vec![inner].try_into().unwrap())),
+        ),
+        ("empty", NbtTag::List(NbtList::End)),
+    ])
+}
+
+#[test]
+fn test_nbt_to_json() {
+    assert_eq!(
+        nbt_to_json(&sample()),
+        json!({
+            "byte": -1,
+            "long": i64::MAX,
+            "longs": [i64::MIN, 0],
+            "bytes": [1, 2],
+            "list": [{ "id": "minecraft:chest" }],
+            "empty": [],
+        })
+    );
+}
+
+#[test]
+fn test_nbt_to_json_longs_as_strings() {
+    let value = nbt_to_json_with(
+        &sample(),
+        JsonOptions {
+            longs_as_strings: true,
+        },
+    );
+    assert_eq!(value["long"], json!(i64::MAX.to_string()));
+    assert_eq!(value["longs"], json!([i64::MIN.to_string(), "0"]));
+    assert_eq!(value["byte"], json!(-1));
+}
+
+#[test]
+fn test_raw_nbt_json() {
+    let mut fields: HashMap<_, _> = uniform_chunk(-4, 1, "minecraft:stone").into();
+    fields.insert(string("LastUpdate"), NbtTag::Long(i64::MAX));
+    let root = NbtCompound::from(fields);
+    let bytes = chunk_bytes(&root);
+
+    assert_eq!(parse_chunk(&bytes).unwrap().raw_nbt_json(), None);
+    let options = ChunkParseOptions {
+        keep_nbt: true,
+        ..Default::default()
+    };
+    let chunk = parse_chunk_with(&bytes, &options).unwrap();
+    let value = chunk.raw_nbt_json().unwrap();
+    assert_eq!(value, nbt_to_json(&root));
+    assert_eq!(value["DataVersion"], json!(4325));
+    assert_eq!(value["LastUpdate"], json!(i64::MAX));
+    assert_eq!(
+        value["sections"][0]["block_states"]["palette"][0]["Name"],
+        json!("minecraft:stone")
+    );
+
+    let options = JsonOptions {
+        longs_as_strings: true,
+    };
+    let value = chunk.raw_nbt_json_with(options).unwrap();
+    assert_eq!(value["LastUpdate"], json!(i64::MAX.to_string()));
+}
@@ -0,0 +1,127 @@
+mod common;
+
+use std::collections::HashMap;
+
+use common::{chunk_bytes, region_bytes, string, uniform_chunk};
+use mca_rs::{
+    LazyRegion,
+    block::Block,
+    region::{Region, RegionParseError, RegionParseOptions, StatusFilter},
+};
+use nbt_rs::types::NbtTag;
+
+#[test]
+fn test_lazy_matches_eager() {
+    let bytes = include_bytes!("data/r.0.0.mca");
+    let eager = Region::parse_bytes(bytes).unwrap();
+    let lazy = LazyRegion::parse_bytes(bytes).unwrap();
+    assert_eq!(lazy.count_decoded(), 0);
+
+    for (x, z) in [(0, 0), (5, 9), (31, 31), (17, 3)] {
+        assert_eq!(lazy.get_chunk(x, z), eager.get_chunk(x, z));
+        assert_eq!(lazy.get_timestamp(x, z), eager.get_timestamp(x, z));
+    }
+    assert_eq!(lazy.count_decoded(), 4);
+    // decoded once and kept
+    assert!(std::ptr::eq(
+        lazy.get_chunk(0, 0).unwrap(),
+        lazy.get_chunk(0, 0).unwrap()
+    ));
+    assert_eq!(lazy.count_decoded(), 4);
+
+    assert_eq!(lazy.count_chunks(), eager.count_chunks());
+    assert!(lazy.get_chunk(32, 0).is_none());
+    assert_eq!(lazy.get_timestamp(0, 32), None);
+}
+
+#[test]
+fn test_lazy_invalid_chunks() {
+    let mut bytes = region_bytes(&[
+        (0, 0, chunk_bytes(&uniform_chunk(0, 1, "minecraft:stone"))),
+        (1, 0, vec![0, 0, 0, 2, 2, 0xFF]),
+    ]);
+    // a chunk past the end of the file
+    bytes[8..12].copy_from_slice(&[0, 1, 0, 1]);
+
+    let lazy = LazyRegion::parse_bytes(&bytes).unwrap();
+    assert_eq!(lazy.count_chunks(), 3);
+    assert!(lazy.get_chunk(0, 0).is_some());
+    assert!(lazy.get_chunk(1, 0).is_none());
+    assert!(lazy.get_chunk(2, 0).is_none());
+    assert_eq!(lazy.count_decoded(), 3);
+
+    assert!(matches!(
+        LazyRegion::parse_bytes(&[0; 4096]),
+        Err(RegionParseError::InputTooShort(4096))
+    ));
+}
+
+#[test]
+fn test_lazy_status_filter() {
+    let mut features: HashMap<_, _> = uniform_chunk(0, 1, "minecraft:stone").into();
+    features.insert(
+        string("Status"),
+        NbtTag::String(string("minecraft:features")),
+    );
+    let bytes = region_bytes(&[
+        (0, 0, chunk_bytes(&uniform_chunk(0, 1, "minecraft:stone"))),
+        (1, 0, chunk_bytes(&features.into())),
+    ]);
+    let options = RegionParseOptions {
+        status_filter: StatusFilter::OnlyFull,
+        ..Default::default()
+    };
+
+    let lazy = LazyRegion::parse_bytes_with(&bytes, options).unwrap();
+    assert_eq!(lazy.count_chunks(), 2);
+    assert!(lazy.get_chunk(0, 0).is_some());
+    assert!(lazy.get_chunk(1, 0).is_none());
+    assert_eq!(lazy.get_timestamp(1, 0), Some(1));
+}
+
+#[test]
+fn test_conflicts() {
+    let backup = include_bytes!("data/r.0.0.mca");
+    let mut region = Region::parse_bytes(backup).unwrap();
+    let sponge = Block::new(string("minecraft:sponge"), None);
+    let edited: Vec<_> = region
+        .iter_chunks()
+        .map(|(x, z, _)| (x, z))
+        .step_by(80)
+        .collect();
+    assert_eq!(edited.len(), 13);
+    for &(x, z) in &edited[1..] {
+        let chunk = region.get_chunk_mut(x, z).unwrap();
+        chunk.set(0, 100, 0, sponge.clone()).unwrap();
+    }
+    // only in the backup, which isn't a conflict
+    region.remove_chunk(edited[0].0, edited[0].1);
+    let live = region.to_bytes().unwrap();
+
+    let (live, backup) = (
+        LazyRegion::parse_bytes(&live).unwrap(),
+        LazyRegion::parse_bytes(backup).unwrap(),
+    );
+    let conflicts: Vec<_> = live.conflicts(&backup).collect();
+    let slots: Vec<_> = conflicts.iter().map(|c| (c.x, c.z)).collect();
+    assert_eq!(slots, edited[1..]);
+    assert_eq!(live.count_decoded() + backup.count_decoded(), 0);
+
+    for conflict in &conflicts {
+        let (ours, theirs) = (conflict.ours, conflict.theirs);
+        assert_eq!(ours.timestamp(), theirs.timestamp());
+        let raw = theirs.raw().unwrap();
+        assert_eq!(theirs.compressed_len(), raw.compressed_data().len());
+        assert_eq!(raw.timestamp(), theirs.timestamp());
+        assert_ne!(ours.compressed_len(), 0);
+
+        let (ours, theirs) = (ours.chunk().unwrap(), theirs.chunk().unwrap());
+        assert_eq!(ours.diff(theirs).len(), 1);
+        assert!(std::ptr::eq(
+            ours,
+            live.get_chunk(conflict.x, conflict.z).unwrap()
+        ));
+    }
+    assert_eq!(live.count_decoded() + backup.count_decoded(), 24);
+    assert_eq!(backup.conflicts(&backup).count(), 0);
+}
@@ -0,0 +1,325 @@
+mod common;
+
+use std::{collections::HashMap, io::Read, path::PathBuf};
+
+use common::{chunk_bytes, compound, compound_list, string, uniform_chunk};
+use flate2::read::GzDecoder;
+use mca_rs::{
+    block::Block,
+    chunk::{
+        ChunkParseError, ChunkParseOptions, Limit, SectionDataError, parse_chunk, parse_chunk_with,
+    },
+    legacy::{
+        LegacyMapping, LegacySection, NEWEST_DATA_VERSION, UNKNOWN_LEGACY_BLOCK, UpgradeError,
+        UpgradeOptions, alpha_chunk_path, flatten_block, parse_alpha_chunk, parse_alpha_chunk_with,
+        parse_chunk_upgraded,
+    },
+    region::Region,
+};
+use nbt_rs::{
+    parse_nbt,
+    types::{NbtCompound, NbtTag},
+};
+
+#[test]
+fn test_parse_alpha_chunk() {
+    let chunk = parse_alpha_chunk(include_bytes!("data/c.-1.1.dat")).unwrap();
+    assert_eq!(chunk.get_y_range(), 0..128);
+
+    let name = |x, y, z| chunk.get(x, y, z).unwrap().get_name().to_string();
+    assert_eq!(name(0, 0, 0), "minecraft:bedrock");
+    assert_eq!(name(15, 30, 9), "minecraft:stone");
+    assert_eq!(name(7, 61, 2), "minecraft:dirt");
+    assert_eq!(name(2, 63, 7), "minecraft:grass_block");
+    assert_eq!(name(3, 64, 5), "minecraft:red_wool");
+    assert_eq!(name(4, 64, 5), "minecraft:torch");
+    assert_eq!(name(5, 64, 5), "minecraft:air");
+    assert_eq!(name(0, 127, 0), "minecraft:air");
+    assert!(chunk.get(0, 128, 0).is_none());
+}
+
+#[test]
+fn test_parse_alpha_chunk_invalid() {
+    assert!(parse_alpha_chunk(&[]).is_err());
+    assert!(parse_alpha_chunk(b"not gzip at all").is_err());
+}
+
+#[test]
+fn test_parse_alpha_chunk_limits() {
+    let bytes = include_bytes!("data/c.-1.1.dat");
+    let defaults = ChunkParseOptions::default();
+    assert!(parse_alpha_chunk_with(bytes, &defaults).is_ok());
+    assert!(matches!(
+        parse_alpha_chunk_with(
+            bytes,
+            &ChunkParseOptions {
+                max_decompressed_len: 100,
+                ..defaults.clone()
+            }
+        ),
+        Err(ChunkParseError::DecompressedTooLarge { limit: 100 })
+    ));
+    assert!(matches!(
+        parse_alpha_chunk_with(
+            bytes,
+            &ChunkParseOptions {
+                max_depth: 1,
+                ..defaults
+            }
+        ),
+        Err(ChunkParseError::LimitExceeded(Limit::Depth))
+    ));
+}
+
+#[test]
+fn test_parse_mcregion_chunk() {
+    // McRegion chunks are alpha chunks stored in a region file
+    let mut nbt = Vec::new();
+    GzDecoder::new(&include_bytes!("data/c.-1.1.dat")[..])
+        .read_to_end(&mut nbt)
+        .unwrap();
+    let (_, root) = parse_nbt(&nbt).unwrap();
+    let bytes = chunk_bytes(&root);
+
+    let chunk = parse_chunk(&bytes).unwrap();
+    let alpha = parse_alpha_chunk(include_bytes!("data/c.-1.1.dat")).unwrap();
+    assert_eq!(chunk.get_y_range(), 0..128);
+    assert_eq!(
+        (chunk.x_pos(), chunk.z_pos()),
+        (alpha.x_pos(), alpha.z_pos())
+    );
+    assert!(chunk.iter_blocks().eq(alpha.iter_blocks()));
+
+    let options = ChunkParseOptions {
+        legacy_mapping: LegacyMapping::new(|id, data| match id {
+            7 => Block::new(string("mymod:bedrock"), None),
+            _ => flatten_block(id, data),
+        }),
+        ..Default::default()
+    };
+    let chunk = parse_chunk_with(&bytes, &options).unwrap();
+    assert_eq!(chunk.get(0, 0, 0).unwrap().get_name(), "mymod:bedrock");
+    assert_eq!(chunk.get(15, 30, 9).unwrap().get_name(), "minecraft:stone");
+}
+
+#[test]
+fn test_alpha_chunk_path() {
+    assert_eq!(alpha_chunk_path(0, 0), PathBuf::from("0/0/c.0.0.dat"));
+    assert_eq!(alpha_chunk_path(-1, 1), PathBuf::from("1r/1/c.-1.1.dat"));
+    assert_eq!(
+        alpha_chunk_path(-13, 44),
+        PathBuf::from("1f/18/c.-d.18.dat")
+    );
+    assert_eq!(
+        alpha_chunk_path(100, -100),
+        PathBuf::from("10/s/c.2s.-2s.dat")
+    );
+}
+
+#[test]
+fn test_flatten_unknown_block() {
+    let block = flatten_block(4000, 7);
+    assert_eq!(block.get_name().to_string(), UNKNOWN_LEGACY_BLOCK);
+
+    let properties = HashMap::from(block.get_properties().clone().unwrap());
+    assert_eq!(properties.len(), 2);
+}
+
+fn numeric_chunk(data_version: i32, sections: Vec<NbtCompound>) -> Vec<u8> {
+    let level = compound([
+        ("xPos", NbtTag::Int(7)),
+        ("zPos", NbtTag::Int(1)),
+        ("Sections", compound_list(sections)),
+    ]);
+    chunk_bytes(&compound([
+        ("DataVersion", NbtTag::Int(data_version)),
+        ("Level", NbtTag::Compound(level)),
+    ]))
+}
+
+fn numeric_section(y: i8, blocks: &[(usize, u16, u8)]) -> NbtCompound {
+    let (mut ids, mut data, mut add) = (vec![0i8; 4096], vec![0i8; 2048], vec![0i8; 2048]);
+    for &(i, id, value) in blocks {
+        ids[i] = id as u8 as i8;
+        data[i / 2] |= ((value as i8) & 0xF) << ((i % 2) * 4);
+        add[i / 2] |= ((id >> 8) as i8 & 0xF) << ((i % 2) * 4);
+    }
+    let mut fields = vec![
+        ("Y", NbtTag::Byte(y)),
+        ("Blocks", NbtTag::ByteArray(ids.try_into().unwrap())),
+        ("Data", NbtTag::ByteArray(data.try_into().unwrap())),
+    ];
+    if blocks.iter().any(|&(_, id, _)| id > 255) {
+        fields.push(("Add", NbtTag::ByteArray(add.try_into().unwrap())));
+    }
+    NbtCompound::from(
+        fields
+            .into_iter()
+            .map(|(key, tag)| (string(key), tag))
+            .collect::<HashMap<_, _>>(),
+    )
+}
+
+#[test]
+fn test_numeric_sections() {
+    // (x, y, z) = (1, 2, 3) is index 2 * 256 + 3 * 16 + 1
+    let bytes = numeric_chunk(
+        1343,
+        vec![
+            numeric_section(0, &[(0, 7, 0), (561, 35, 14), (562, 1000, 3)]),
+            numeric_section(3, &[(4095, 1, 1)]),
+        ],
+    );
+    let chunk = parse_chunk(&bytes).unwrap();
+    assert_eq!(chunk.get_y_range(), 0..256);
+    assert_eq!(chunk.get(0, 0, 0).unwrap().get_name(), "minecraft:bedrock");
+    assert_eq!(chunk.get(1, 2, 3).unwrap().get_name(), "minecraft:red_wool");
+    assert_eq!(chunk.get(2, 2, 3).unwrap().get_name(), UNKNOWN_LEGACY_BLOCK);
+    assert_eq!(
+        chunk.get(15, 63, 15).unwrap().get_name(),
+        "minecraft:granite"
+    );
+    assert!(chunk.get(0, 100, 0).unwrap().is_air());
+
+    let legacy = chunk.legacy_sections().unwrap();
+    assert_eq!(
+        legacy.iter().map(LegacySection::y).collect::<Vec<_>>(),
+        [0, 3]
+    );
+    assert_eq!(legacy[0].get_block_id(1, 2, 3), Some((35, 14)));
+    assert_eq!(legacy[0].get_block_id(2, 2, 3), Some((1000, 3)));
+    assert_eq!(legacy[0].get_block_id(3, 2, 3), Some((0, 0)));
+    assert_eq!(legacy[1].get_block_id(15, 15, 15), Some((1, 1)));
+    assert_eq!(legacy[0].get_block_id(16, 0, 0), None);
+
+    let section = legacy[0].to_section(&flatten_block);
+    assert_eq!(Some(&section), chunk.get_section(0));
+
+    // palette chunks have none, even from before 1.13 without a version
+    let region = Region::parse_bytes(include_bytes!("data/r.0.0.mca")).unwrap();
+    assert!(region.get_chunk(0, 0).unwrap().legacy_sections().is_none());
+    let empty = parse_chunk(&numeric_chunk(1343, vec![])).unwrap();
+    assert_eq!(empty.legacy_sections(), Some(&[][..]));
+}
+
+#[test]
+fn test_legacy_mapping() {
+    let bytes = numeric_chunk(1343, vec![numeric_section(0, &[(0, 3000, 2)])]);
+    let mapping = LegacyMapping::new(|id, data| match id {
+        3000 => Block::new(string("mymod:ore"), None),
+        _ => flatten_block(id, data),
+    });
+    let options = ChunkParseOptions {
+        legacy_mapping: mapping.clone(),
+        ..Default::default()
+    };
+    assert_ne!(options, ChunkParseOptions::default());
+    let chunk = parse_chunk_with(&bytes, &options).unwrap();
+    assert_eq!(chunk.get(0, 0, 0).unwrap().get_name(), "mymod:ore");
+    assert!(chunk.get(1, 0, 0).unwrap().is_air());
+
+    let invalid = numeric_chunk(
+        1343,
+        vec![compound([
+            ("Y", NbtTag::Byte(0)),
+            (
+                "Blocks",
+                NbtTag::ByteArray(vec![0; 100].try_into().unwrap()),
+            ),
+            ("Data", NbtTag::ByteArray(vec![0; 2048].try_into().unwrap())),
+        ])],
+    );
+    assert!(matches!(
+        parse_chunk(&invalid),
+        Err(ChunkParseError::InvalidSectionData {
+            section_y: Some(0),
+            path,
+            reason: SectionDataError::WrongLength {
+                len: 100,
+                expected: 4096
+            },
+        }) if path == "Level.Sections[0].Blocks"
+    ));
+}
+
+#[test]
+fn test_parse_chunk_upgraded() {
+    let options = UpgradeOptions {
+        parse: ChunkParseOptions {
+            legacy_mapping: LegacyMapping::new(|id, data| match id {
+                7 => Block::new(string("mymod:bedrock"), None),
+                _ => flatten_block(id, data),
+            }),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+
+    // every layout is read into the same chunk, numeric ids through the mapping
+    let modern = chunk_bytes(&uniform_chunk(0, 1, "minecraft:stone"));
+    let chunk = parse_chunk_upgraded(&modern, &options).unwrap();
+    assert_eq!(chunk, parse_chunk(&modern).unwrap());
+    let numeric = numeric_chunk(1343, vec![numeric_section(0, &[(0, 7, 0)])]);
+    let chunk = parse_chunk_upgraded(&numeric, &options).unwrap();
+    assert_eq!(chunk.get(0, 0, 0).unwrap().get_name(), "mymod:bedrock");
+    let mut nbt = Vec::new();
+    GzDecoder::new(&include_bytes!("data/c.-1.1.dat")[..])
+        .read_to_end(&mut nbt)
+        .unwrap();
+    let mcregion = chunk_bytes(&parse_nbt(&nbt).unwrap().1);
+    let chunk = parse_chunk_upgraded(&mcregion, &options).unwrap();
+    assert_eq!(chunk.data_version(), 0);
+    assert_eq!(chunk.get(0, 0, 0).unwrap().get_name(), "mymod:bedrock");
+
+    let with_version = |data_version| {
+        let mut fields: HashMap<_, _> = uniform_chunk(0, 1, "minecraft:stone").into();
+        fields.insert(string("DataVersion"), NbtTag::Int(data_version));
+        chunk_bytes(&fields.into())
+    };
+    for data_version in [-1, 50, NEWEST_DATA_VERSION + 1] {
+        let error = parse_chunk_upgraded(&with_version(data_version), &options).unwrap_err();
+        assert!(
+            matches!(error, UpgradeError::Unsupported { data_version: v } if v == data_version)
+        );
+        assert!(error.to_string().contains(&data_version.to_string()));
+    }
+    let newer = UpgradeOptions {
+        newest_data_version: NEWEST_DATA_VERSION + 1,
+        ..Default::default()
+    };
+    let bytes = with_version(NEWEST_DATA_VERSION + 1);
+    assert!(parse_chunk_upgraded(&bytes, &newer).is_ok());
+
+    assert!(matches!(
+        parse_chunk_upgraded(&[0, 0, 0, 1, 9], &options),
+        Err(UpgradeError::Parse(_))
+    ));
+}
+
+#[test]
+fn test_numeric_chunk_encoding() {
+    let bytes = numeric_chunk(
+        1343,
+        vec![numeric_section(
+            0,
+            &[(0, 7, 0), (561, 35, 14), (562, 1000, 3)],
+        )],
+    );
+    let chunk = parse_chunk(&bytes).unwrap();
+    let nbt = chunk.to_nbt();
+    // the blocks are mapped, so it's written as a chunk from 1.13
+    assert_eq!(nbt.get("DataVersion"), Some(&NbtTag::Int(1451)));
+    let level = nbt.get("Level").unwrap().as_compound().unwrap();
+    let sections = level.get("Sections").unwrap().as_list().unwrap();
+    let section = &sections.as_compound().unwrap()[0];
+    assert!(section.get("Palette").is_some());
+    assert!(section.get("Blocks").is_none());
+
+    let reparsed = parse_chunk(&chunk.to_bytes()).unwrap();
+    assert_eq!(reparsed.data_version(), 1451);
+    assert_eq!(reparsed.legacy_sections(), None);
+    assert_eq!(reparsed.get_y_range(), chunk.get_y_range());
+    for y in 0..16 {
+        assert_eq!(reparsed.get_section(y), chunk.get_section(y));
+    }
+}
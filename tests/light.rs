@@ -0,0 +1,111 @@
+mod common;
+
+use std::collections::HashMap;
+
+use common::{block, chunk_bytes, string, uniform_chunk};
+use mca_rs::{
+    builder::ChunkBuilder,
+    chunk::{ChunkParseOptions, parse_chunk_with},
+    region::Region,
+    section::Section,
+};
+use nbt_rs::types::NbtTag;
+
+#[test]
+fn test_recompute_sky_light() {
+    // a superflat chunk, with a few things on top in the first columns
+    let mut ground = Section::empty();
+    for (y, name) in [(0, "bedrock"), (1, "dirt"), (2, "dirt"), (3, "grass_block")] {
+        for x in 0..16 {
+            for z in 0..16 {
+                ground
+                    .set_block(x, y, z, block(&format!("minecraft:{name}")))
+                    .unwrap();
+            }
+        }
+    }
+    for (x, y, name) in [
+        (0, 10, "glass"),
+        (0, 8, "water"),
+        (1, 9, "oak_leaves"),
+        (1, 8, "oak_leaves"),
+        (2, 4, "torch"),
+        (3, 12, "stone"),
+    ] {
+        ground
+            .set_block(x, y, 0, block(&format!("minecraft:{name}")))
+            .unwrap();
+    }
+    let mut chunk = ChunkBuilder::new(0, 0)
+        .y_range(0..32)
+        .section(0, ground)
+        .build()
+        .unwrap();
+    assert_eq!(chunk.get_sky_light(5, 20, 5), None);
+    chunk.recompute_sky_light();
+
+    let column = |x| -> Vec<_> {
+        (0..16)
+            .map(|y| chunk.get_sky_light(x, y, 0).unwrap())
+            .collect()
+    };
+    let dark = [0; 4];
+    assert_eq!(column(5)[..4], dark);
+    assert!(column(5)[4..].iter().all(|&level| level == 15));
+    assert!((16..32).all(|y| chunk.get_sky_light(5, y, 5) == Some(15)));
+    // glass lets it through, water dims it
+    assert_eq!(column(0)[..4], dark);
+    assert_eq!(
+        column(0)[4..],
+        [14, 14, 14, 14, 14, 15, 15, 15, 15, 15, 15, 15]
+    );
+    assert_eq!(
+        column(1)[4..],
+        [13, 13, 13, 13, 13, 14, 15, 15, 15, 15, 15, 15]
+    );
+    assert_eq!(column(2)[4], 15);
+    // no light spreads under the overhang
+    assert!(column(3)[..13].iter().all(|&level| level == 0));
+    assert_eq!(column(3)[13], 15);
+    // block light is left alone
+    assert_eq!(chunk.get_block_light(5, 10, 5), None);
+}
+
+#[test]
+fn test_recompute_sky_light_matches_game() {
+    let region = Region::parse_bytes(include_bytes!("data/r.0.0.mca")).unwrap();
+    let (mut same, mut total) = (0, 0);
+    for (_, _, chunk) in region.iter_chunks().step_by(8) {
+        let mut recomputed = chunk.clone();
+        recomputed.recompute_sky_light();
+        for y in chunk.get_y_range() {
+            for x in 0..16 {
+                for z in 0..16 {
+                    if let Some(level) = chunk.get_sky_light(x, y, z) {
+                        total += 1;
+                        same += (recomputed.get_sky_light(x, y, z) == Some(level)) as usize;
+                    }
+                }
+            }
+        }
+    }
+    // light spreading sideways into caves and under trees is all that's
+    // missing
+    assert!(total > 0);
+    assert!(same * 10 >= total * 9, "{same} of {total}");
+}
+
+#[test]
+fn test_recompute_sky_light_turns_light_off() {
+    let mut fields: HashMap<_, _> = uniform_chunk(-4, 1, "minecraft:stone").into();
+    fields.insert(string("isLightOn"), NbtTag::Byte(1));
+    let options = ChunkParseOptions {
+        keep_nbt: true,
+        ..Default::default()
+    };
+    let mut chunk = parse_chunk_with(&chunk_bytes(&fields.into()), &options).unwrap();
+    assert_eq!(chunk.to_nbt().get("isLightOn"), Some(&NbtTag::Byte(1)));
+    chunk.recompute_sky_light();
+    assert_eq!(chunk.to_nbt().get("isLightOn"), Some(&NbtTag::Byte(0)));
+    assert_eq!(chunk.get_sky_light(0, -64, 0), Some(0));
+}
@@ -0,0 +1,185 @@
+mod common;
+
+use std::io::Write;
+
+use common::{chunk_bytes, region_bytes, uniform_chunk};
+use flate2::{
+    Compression,
+    write::{GzEncoder, ZlibEncoder},
+};
+use mca_rs::{
+    chunk::{ChunkParseError, ChunkParseOptions, Limit, parse_chunk, parse_chunk_with},
+    region::{MAX_REGION_LEN, Region, RegionParseError, RegionParseOptions},
+};
+
+fn limit(result: Result<impl std::fmt::Debug, ChunkParseError>) -> Limit {
+    match result {
+        Err(ChunkParseError::LimitExceeded(limit)) => limit,
+        other => panic!("expected a limit error, got {other:?}"),
+    }
+}
+
+#[test]
+fn test_hostile_fixtures() {
+    assert_eq!(
+        limit(parse_chunk(include_bytes!("data/hostile/deep_nesting.bin"))),
+        Limit::Depth
+    );
+    assert_eq!(
+        limit(parse_chunk(include_bytes!("data/hostile/huge_list.bin"))),
+        Limit::ListLength
+    );
+    assert_eq!(
+        limit(parse_chunk(include_bytes!("data/hostile/huge_array.bin"))),
+        Limit::ListLength
+    );
+    assert_eq!(
+        limit(parse_chunk(include_bytes!("data/hostile/many_tags.bin"))),
+        Limit::TagCount
+    );
+}
+
+#[test]
+fn test_configured_limits() {
+    let bytes = chunk_bytes(&uniform_chunk(-4, 24, "minecraft:stone"));
+    let defaults = ChunkParseOptions::default();
+    assert!(parse_chunk_with(&bytes, &defaults).is_ok());
+
+    assert!(matches!(
+        parse_chunk_with(
+            &bytes,
+            &ChunkParseOptions {
+                max_decompressed_len: 100,
+                ..defaults.clone()
+            }
+        ),
+        Err(ChunkParseError::DecompressedTooLarge { limit: 100 })
+    ));
+
+    let options = |options: ChunkParseOptions| limit(parse_chunk_with(&bytes, &options));
+    // root, sections list, section, block_states, palette list, palette entry
+    assert_eq!(
+        options(ChunkParseOptions {
+            max_depth: 5,
+            ..defaults.clone()
+        }),
+        Limit::Depth
+    );
+    assert_eq!(
+        options(ChunkParseOptions {
+            max_list_len: 23,
+            ..defaults.clone()
+        }),
+        Limit::ListLength
+    );
+    assert_eq!(
+        options(ChunkParseOptions {
+            max_tags: 50,
+            ..defaults.clone()
+        }),
+        Limit::TagCount
+    );
+    assert_eq!(
+        options(ChunkParseOptions {
+            max_string_len: 10,
+            ..defaults.clone()
+        }),
+        Limit::StringLength
+    );
+
+    let options = ChunkParseOptions {
+        max_depth: 6,
+        max_list_len: 24,
+        ..defaults
+    };
+    assert!(parse_chunk_with(&bytes, &options).is_ok());
+}
+
+#[test]
+fn test_limit_message() {
+    let err = parse_chunk(include_bytes!("data/hostile/deep_nesting.bin")).unwrap_err();
+    assert_eq!(err.to_string(), "the chunk exceeds the nesting depth limit");
+}
+
+#[test]
+fn test_limits_after_end_list() {
+    // a list of end tags with a length, then a list claiming a billion
+    // compounds, both in the root compound
+    let mut nbt = vec![10, 0, 0];
+    nbt.extend([9, 0, 1, b'a', 0]);
+    nbt.extend(3i32.to_be_bytes());
+    nbt.extend([9, 0, 1, b'b', 10]);
+    nbt.extend((1i32 << 30).to_be_bytes());
+    nbt.push(0);
+
+    let mut bytes = (nbt.len() as u32 + 1).to_be_bytes().to_vec();
+    bytes.push(3);
+    bytes.extend(nbt);
+    assert_eq!(limit(parse_chunk(&bytes)), Limit::ListLength);
+}
+
+#[test]
+fn test_longest_array_without_list_limit() {
+    // a long array claiming the most longs a length can, which isn't there
+    let mut nbt = vec![10, 0, 0, 12, 0, 1, b'a'];
+    nbt.extend(i32::MAX.to_be_bytes());
+    nbt.push(0);
+
+    let mut bytes = (nbt.len() as u32 + 1).to_be_bytes().to_vec();
+    bytes.push(3);
+    bytes.extend(nbt);
+    let options = ChunkParseOptions {
+        max_list_len: usize::MAX,
+        ..ChunkParseOptions::default()
+    };
+    assert!(matches!(
+        parse_chunk_with(&bytes, &options),
+        Err(ChunkParseError::ParseFailed(_))
+    ));
+}
+
+#[test]
+fn test_decompression_bomb() {
+    // 17 MiB of zeros, just past the default limit, compressed to 17 KiB
+    let zeros = vec![0; 1 << 20];
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::best());
+    for _ in 0..17 {
+        encoder.write_all(&zeros).unwrap();
+    }
+    let payload = encoder.finish().unwrap();
+    let mut bytes = (payload.len() as u32 + 1).to_be_bytes().to_vec();
+    bytes.push(2);
+    bytes.extend(payload);
+    assert!(matches!(
+        parse_chunk(&bytes),
+        Err(ChunkParseError::DecompressedTooLarge { limit }) if limit == 16 << 20
+    ));
+
+    let region = region_bytes(&[(0, 0, bytes)]);
+    let options = RegionParseOptions {
+        strict: true,
+        ..Default::default()
+    };
+    assert!(matches!(
+        Region::parse_bytes_with(&region, &options),
+        Err(RegionParseError::ChunkError {
+            source: ChunkParseError::DecompressedTooLarge { .. },
+            ..
+        })
+    ));
+}
+
+#[test]
+fn test_gzip_region_bomb() {
+    // zeros just past the largest region there can be
+    let zeros = vec![0; 1 << 20];
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::fast());
+    for _ in 0..MAX_REGION_LEN.div_ceil(1 << 20) + 1 {
+        encoder.write_all(&zeros).unwrap();
+    }
+    let bytes = encoder.finish().unwrap();
+    assert!(matches!(
+        Region::parse_maybe_compressed(&bytes),
+        Err(RegionParseError::GzipTooLarge)
+    ));
+}
@@ -0,0 +1,68 @@
+#![cfg(feature = "lz4")]
+
+use mca_rs::{
+    Compression, CompressionType, WriteOptions,
+    chunk::{ChunkParseError, parse_chunk},
+    raw::RawChunk,
+    region::Region,
+};
+
+#[test]
+fn test_lz4_chunk() {
+    // chunk (5, 9) of the region, written with lz4-java's block format
+    let bytes = include_bytes!("data/lz4_chunk.bin");
+    let raw = RawChunk::parse(bytes).unwrap();
+    assert_eq!(raw.compression(), CompressionType::Lz4);
+
+    let region = Region::parse_bytes(include_bytes!("data/r.0.0.mca")).unwrap();
+    let zlib = region.get_chunk_raw(5, 9).unwrap();
+    assert_eq!(
+        raw.decompressed_data().unwrap(),
+        zlib.decompressed_data().unwrap()
+    );
+    assert_eq!(
+        &parse_chunk(bytes).unwrap(),
+        region.get_chunk(5, 9).unwrap()
+    );
+}
+
+#[test]
+fn test_lz4_corrupt() {
+    let mut bytes = include_bytes!("data/lz4_chunk.bin").to_vec();
+    // a literal in the first block, so the checksum no longer matches
+    bytes[5 + 21 + 1] ^= 1;
+    assert!(matches!(
+        parse_chunk(&bytes),
+        Err(ChunkParseError::DecompressionFailed(_))
+    ));
+
+    let mut bytes = include_bytes!("data/lz4_chunk.bin").to_vec();
+    bytes.truncate(bytes.len() - 100);
+    let len = bytes.len() as u32 - 4;
+    bytes[..4].copy_from_slice(&len.to_be_bytes());
+    assert!(matches!(
+        parse_chunk(&bytes),
+        Err(ChunkParseError::DecompressionFailed(_))
+    ));
+}
+
+#[test]
+fn test_lz4_write() {
+    let region = Region::parse_bytes(include_bytes!("data/r.0.0.mca")).unwrap();
+    let options = WriteOptions {
+        compression: Compression::Lz4,
+        ..Default::default()
+    };
+    for (_, _, chunk) in region.iter_chunks().step_by(50) {
+        let bytes = chunk.to_bytes_with(&options);
+        let raw = RawChunk::parse(&bytes).unwrap();
+        assert_eq!(raw.compression(), CompressionType::Lz4);
+        assert_eq!(&parse_chunk(&bytes).unwrap(), chunk);
+        // chunks compress well, so the blocks shouldn't be stored as is
+        let none = WriteOptions {
+            compression: Compression::None,
+            ..Default::default()
+        };
+        assert!(bytes.len() * 2 < chunk.to_bytes_with(&none).len());
+    }
+}
@@ -0,0 +1,92 @@
+#![cfg(feature = "map_colors")]
+
+use std::collections::BTreeSet;
+
+use mca_rs::{
+    block::Block,
+    map_color::{Brightness, MapColor, map_color, map_color_of},
+    region::Region,
+};
+
+fn color_of(block: &str) -> Option<u8> {
+    map_color_of(&block.parse::<Block>().unwrap()).map(MapColor::id)
+}
+
+#[test]
+fn test_map_color() {
+    let grass: Block = "minecraft:grass_block[snowy=false]".parse().unwrap();
+    assert_eq!(map_color(&grass), Some([0x7F, 0xB2, 0x38, 255]));
+    assert_eq!(map_color_of(&grass), Some(MapColor::GRASS));
+    assert_eq!(color_of("minecraft:water[level=0]"), Some(12));
+    assert_eq!(color_of("minecraft:stone"), Some(11));
+    assert_eq!(color_of("minecraft:air"), None);
+    assert_eq!(color_of("minecraft:glass"), None);
+    assert_eq!(color_of("minecraft:oak_button"), None);
+    assert_eq!(color_of("mymod:stone"), None);
+
+    // logs show their rings from above and their bark from the side
+    assert_eq!(color_of("minecraft:oak_log[axis=y]"), Some(13));
+    assert_eq!(color_of("minecraft:oak_log[axis=x]"), Some(34));
+    assert_eq!(color_of("minecraft:stripped_oak_log[axis=z]"), Some(13));
+
+    // colored blocks
+    assert_eq!(color_of("minecraft:white_wool"), Some(8));
+    assert_eq!(color_of("minecraft:light_gray_concrete"), Some(22));
+    assert_eq!(color_of("minecraft:gray_concrete"), Some(21));
+    assert_eq!(color_of("minecraft:light_blue_terracotta"), Some(39));
+    assert_eq!(color_of("minecraft:terracotta"), Some(15));
+    assert_eq!(color_of("minecraft:red_bed[part=foot]"), Some(28));
+    assert_eq!(color_of("minecraft:red_bed[part=head]"), Some(3));
+    assert_eq!(color_of("minecraft:blue_ice"), Some(5));
+    assert_eq!(color_of("minecraft:red_sand"), Some(15));
+
+    // shapes take the color of their material
+    assert_eq!(color_of("minecraft:oak_stairs[facing=east]"), Some(13));
+    assert_eq!(color_of("minecraft:bamboo_slab"), Some(18));
+    assert_eq!(color_of("minecraft:stone_brick_wall"), Some(11));
+    assert_eq!(color_of("minecraft:quartz_slab"), Some(14));
+    assert_eq!(color_of("minecraft:spruce_wall_sign"), Some(34));
+    assert_eq!(
+        color_of("minecraft:waxed_oxidized_cut_copper_stairs"),
+        Some(58)
+    );
+}
+
+#[test]
+fn test_map_color_shades() {
+    let grass = MapColor::GRASS;
+    assert_eq!(grass.rgb(), [0x7F, 0xB2, 0x38]);
+    assert_eq!(grass.rgba(Brightness::High), [0x7F, 0xB2, 0x38, 255]);
+    assert_eq!(grass.rgba(Brightness::Normal), [109, 153, 48, 255]);
+    assert_eq!(grass.rgba(Brightness::Low), [89, 125, 39, 255]);
+    assert_eq!(grass.rgba(Brightness::Lowest), [67, 94, 29, 255]);
+    assert_eq!(grass.packed(Brightness::Low), 4);
+    assert_eq!(grass.packed(Brightness::Lowest), 7);
+    assert_eq!(MapColor::NONE.rgba(Brightness::High), [0; 4]);
+    assert_eq!(
+        MapColor::from_id(61).map(MapColor::rgb),
+        Some([0x7F, 0xA7, 0x96])
+    );
+    assert_eq!(MapColor::from_id(62), None);
+}
+
+#[test]
+fn test_map_colors_of_fixture() {
+    let region = Region::parse_bytes(include_bytes!("data/r.0.0.mca")).unwrap();
+    let map = region.surface_map(|block| !block.is_air());
+    let uncolored: BTreeSet<_> = (map.iter().flatten())
+        .filter(|sample| map_color(sample.block).is_none())
+        .map(|sample| sample.block.get_name())
+        .collect();
+    assert_eq!(uncolored, BTreeSet::from(["minecraft:leaf_litter"]));
+
+    // looking through it, like renderers do
+    let map = region.surface_map(|block| map_color(block).is_some());
+    let top = map[5 * 16 + 3 + (9 * 16 + 4) * 512].unwrap();
+    assert_eq!(top.block.get_name(), "minecraft:short_grass");
+    assert_eq!(map_color_of(top.block), Some(MapColor::PLANT));
+    let map = region
+        .surface_map(|block| map_color_of(block).is_some_and(|color| color != MapColor::PLANT));
+    let top = map[5 * 16 + 3 + (9 * 16 + 4) * 512].unwrap();
+    assert_eq!(map_color_of(top.block), Some(MapColor::GRASS));
+}
@@ -0,0 +1,99 @@
+#![cfg(feature = "ndarray")]
+
+mod common;
+
+use common::block;
+use mca_rs::{builder::ChunkBuilder, region::Region, section::Section};
+
+#[test]
+fn test_section_index_array() {
+    let mut section = Section::empty();
+    section
+        .set_block(1, 2, 3, block("minecraft:stone"))
+        .unwrap();
+    // replaced right away, so the palette keeps an entry no block uses
+    section.set_block(0, 0, 0, block("minecraft:dirt")).unwrap();
+    section.set_block(0, 0, 0, block("minecraft:air")).unwrap();
+
+    let (array, palette) = section.to_index_array();
+    assert_eq!(array.shape(), [16, 16, 16]);
+    assert_eq!(
+        palette,
+        [&block("minecraft:air"), &block("minecraft:stone")]
+    );
+    assert_eq!(
+        palette[array[[2, 3, 1]] as usize],
+        &block("minecraft:stone")
+    );
+    assert_eq!(array.iter().filter(|&&index| index == 1).count(), 1);
+}
+
+#[test]
+fn test_chunk_index_array() {
+    let region = Region::parse_bytes(include_bytes!("data/r.0.0.mca")).unwrap();
+    let chunk = region.get_chunk(9, 2).unwrap();
+    let (array, palette) = chunk.to_index_array();
+    let min_y = chunk.section_y_range().start * 16;
+    assert_eq!(array.shape(), [chunk.section_y_range().len() * 16, 16, 16]);
+
+    for (i, a) in palette.iter().enumerate() {
+        assert!(palette[i + 1..].iter().all(|b| a != b));
+    }
+    for ((y, z, x), &index) in array.indexed_iter() {
+        let y = min_y + y as i32;
+        assert_eq!(
+            Some(palette[index as usize]),
+            chunk.get(x as u8, y, z as u8)
+        );
+    }
+}
+
+#[test]
+fn test_chunk_index_array_merges_palettes() {
+    let mut section = Section::filled(block("minecraft:stone"));
+    section.set_block(4, 5, 6, block("minecraft:dirt")).unwrap();
+    let chunk = ChunkBuilder::new(0, 0)
+        .y_range(-16..32)
+        .section(-1, Section::filled(block("minecraft:dirt")))
+        .section(0, section)
+        .section(1, Section::empty())
+        .build()
+        .unwrap();
+
+    let (array, palette) = chunk.to_index_array();
+    assert_eq!(array.shape(), [48, 16, 16]);
+    assert_eq!(
+        palette,
+        [
+            &block("minecraft:dirt"),
+            &block("minecraft:stone"),
+            &block("minecraft:air")
+        ]
+    );
+    assert_eq!(array[[0, 0, 0]], 0);
+    assert_eq!(array[[16, 0, 0]], 1);
+    assert_eq!(array[[16 + 5, 6, 4]], 0);
+    assert_eq!(array[[47, 15, 15]], 2);
+}
+
+#[test]
+fn test_chunk_index_array_past_u16() {
+    // 17 sections of 4096 distinct blocks each, more than a u16 can index
+    let mut builder = ChunkBuilder::new(0, 0).y_range(0..17 * 16);
+    for section_y in 0..17 {
+        let mut section = Section::empty();
+        for i in 0..4096u16 {
+            let (x, y, z) = ((i & 15) as u8, (i >> 8) as u8, (i >> 4 & 15) as u8);
+            let name = format!("test:block_{section_y}_{i}");
+            section.set_block(x, y, z, block(&name)).unwrap();
+        }
+        builder = builder.section(section_y, section);
+    }
+    let chunk = builder.build().unwrap();
+
+    let (array, palette) = chunk.to_index_array();
+    assert_eq!(palette.len(), 17 * 4096);
+    let top = array[[17 * 16 - 1, 15, 15]];
+    assert_eq!(top, 17 * 4096 - 1);
+    assert_eq!(Some(palette[top as usize]), chunk.get(15, 17 * 16 - 1, 15));
+}
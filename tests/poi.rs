@@ -0,0 +1,159 @@
+mod common;
+
+use common::{chunk_bytes, compound, compound_list, region_bytes, string};
+use mca_rs::{
+    PoiRecord, PoiRegion,
+    chunk::ChunkParseError,
+    region::{RegionParseError, RegionParseOptions},
+};
+use nbt_rs::types::{NbtCompound, NbtTag};
+
+fn record(ty: &str, pos: [i32; 3], free_tickets: i32) -> NbtCompound {
+    compound([
+        ("type", NbtTag::String(string(ty))),
+        ("pos", NbtTag::IntArray(pos.to_vec().try_into().unwrap())),
+        ("free_tickets", NbtTag::Int(free_tickets)),
+    ])
+}
+
+fn section(valid: bool, records: Vec<NbtCompound>) -> NbtTag {
+    NbtTag::Compound(compound([
+        ("Valid", NbtTag::Byte(valid as i8)),
+        ("Records", compound_list(records)),
+    ]))
+}
+
+fn poi_chunk(sections: NbtCompound) -> Vec<u8> {
+    chunk_bytes(&compound([
+        ("DataVersion", NbtTag::Int(4325)),
+        ("Sections", NbtTag::Compound(sections)),
+    ]))
+}
+
+#[test]
+fn test_poi_region() {
+    let bytes = region_bytes(&[
+        (
+            1,
+            2,
+            poi_chunk(compound([
+                (
+                    "4",
+                    section(
+                        true,
+                        vec![
+                            record("minecraft:home", [20, 70, 36], 0),
+                            record("minecraft:farmer", [22, 70, 38], 1),
+                        ],
+                    ),
+                ),
+                (
+                    "-1",
+                    section(false, vec![record("minecraft:home", [18, -5, 40], 1)]),
+                ),
+            ])),
+        ),
+        (
+            3,
+            0,
+            poi_chunk(compound([(
+                "3",
+                section(true, vec![record("minecraft:meeting", [50, 63, 2], 32)]),
+            )])),
+        ),
+        (5, 5, poi_chunk(compound([]))),
+    ]);
+    let region = PoiRegion::parse_bytes(&bytes).unwrap();
+    assert_eq!(region.count_chunks(), 3);
+    assert!(region.get_chunk(5, 5).unwrap().sections().is_empty());
+    assert!(region.get_chunk(0, 0).is_none());
+    assert!(region.get_chunk(32, 0).is_none());
+
+    let chunk = region.get_chunk(1, 2).unwrap();
+    assert_eq!(chunk.data_version(), 4325);
+    let ys: Vec<_> = chunk.sections().iter().map(|section| section.y).collect();
+    assert_eq!(ys, [-1, 4]);
+    assert!(chunk.get_section(4).unwrap().valid);
+    assert!(chunk.get_section(0).is_none());
+    assert_eq!(
+        chunk.records_of_type("home").collect::<Vec<_>>(),
+        [
+            &PoiRecord {
+                x: 18,
+                y: -5,
+                z: 40,
+                ty: string("minecraft:home"),
+                free_tickets: 1,
+            },
+            &PoiRecord {
+                x: 20,
+                y: 70,
+                z: 36,
+                ty: string("minecraft:home"),
+                free_tickets: 0,
+            },
+        ]
+    );
+    assert_eq!(chunk.records().count(), 3);
+
+    // beds nobody claimed yet
+    let free: Vec<_> = region
+        .find("minecraft:home")
+        .filter(|(.., record)| record.has_free_tickets())
+        .map(|(x, y, z, _)| (x, y, z))
+        .collect();
+    assert_eq!(free, [(18, -5, 40)]);
+    assert_eq!(region.find("meeting").count(), 1);
+    assert_eq!(region.find("minecraft:armorer").count(), 0);
+
+    let stale: Vec<_> = region
+        .stale_sections()
+        .map(|(x, z, section)| (x, z, section.y, section.records.len()))
+        .collect();
+    assert_eq!(stale, [(1, 2, -1, 1)]);
+}
+
+#[test]
+fn test_poi_region_errors() {
+    let bytes = region_bytes(&[
+        (
+            0,
+            0,
+            poi_chunk(compound([(
+                "2",
+                section(
+                    true,
+                    vec![compound([("type", NbtTag::String(string("home")))])],
+                ),
+            )])),
+        ),
+        (1, 0, poi_chunk(compound([("top", section(true, vec![]))]))),
+        (2, 0, poi_chunk(compound([("0", section(true, vec![]))]))),
+    ]);
+
+    // the broken chunks are left out
+    let region = PoiRegion::parse_bytes(&bytes).unwrap();
+    assert_eq!(region.count_chunks(), 1);
+    assert!(region.get_chunk(2, 0).is_some());
+
+    let strict = RegionParseOptions {
+        strict: true,
+        ..Default::default()
+    };
+    assert!(matches!(
+        PoiRegion::parse_bytes_with(&bytes, &strict),
+        Err(RegionParseError::ChunkError {
+            x: 0,
+            z: 0,
+            source: ChunkParseError::InvalidField(path, _),
+        }) if path == "Sections.2.Records[0].pos"
+    ));
+    let bytes = region_bytes(&[(1, 0, poi_chunk(compound([("top", section(true, vec![]))])))]);
+    assert!(matches!(
+        PoiRegion::parse_bytes_with(&bytes, &strict),
+        Err(RegionParseError::ChunkError {
+            source: ChunkParseError::InvalidField(path, "section y"),
+            ..
+        }) if path == "Sections.top"
+    ));
+}
@@ -1,4 +1,34 @@
-use mca_rs::region::Region;
+mod common;
+
+use std::{
+    cell::Cell,
+    collections::HashMap,
+    fs,
+    io::{Cursor, ErrorKind, Read, Seek, SeekFrom, Write},
+    path::Path,
+    time::{Duration, UNIX_EPOCH},
+};
+
+use common::{chunk_bytes, region_bytes, string, temp_dir, uniform_chunk};
+use flate2::write::{GzEncoder, ZlibEncoder};
+use mca_rs::{
+    Axis, ChunkPos, Compression, CompressionType, HeaderIssue, RegionFile, WriteOptions,
+    block::{Block, BlockInterner, BlockSelector},
+    builder::ChunkBuilder,
+    chunk::{ChunkParseError, ChunkParseOptions, parse_chunk},
+    heightmap::HeightmapKind,
+    lazy::LazyRegion,
+    raw::RawChunk,
+    region::{
+        BlockCountOptions, ChunkStorage, ChunkTooLarge, DuplicateSectors, FileWriteOptions,
+        FragmentationStats, MergeDecision, MergePolicy, MergeSlot, ModifiedTime, Region,
+        RegionBuildError, RegionBuilder, RegionOpenError, RegionParseError, RegionParseOptions,
+        SlotOutcome, StatusFilter, TimestampOrder, fragmentation_stats,
+        region_coords_from_filename, storage_stats, validate_header,
+    },
+    status::ChunkStatus,
+};
+use nbt_rs::{serialize_nbt, types::NbtTag};
 
 #[test]
 fn test_parse_empty_region() {
@@ -13,3 +43,1945 @@ fn test_parse_real_region() {
     let region = Region::parse_bytes(include_bytes!("data/r.0.0.mca")).unwrap();
     assert_eq!(region.count_chunks(), 975);
 }
+
+#[test]
+fn test_raw_chunks() {
+    let region = Region::parse_bytes(include_bytes!("data/r.0.0.mca")).unwrap();
+    let raw = region.get_chunk_raw(0, 0).unwrap();
+    assert_eq!(raw.compression(), CompressionType::Zlib);
+    assert!(!raw.is_external());
+    assert_ne!(raw.timestamp(), 0);
+    assert_eq!(&raw.decode().unwrap(), region.get_chunk(0, 0).unwrap());
+    assert!(region.get_chunk_raw(32, 0).is_none());
+
+    // the root exports are the same types as the module paths
+    let raw: &mca_rs::RawChunk = raw;
+    let chunk: mca_rs::Chunk = raw.decode().unwrap();
+    // a compound tag, the root of the chunk
+    assert_eq!(raw.decompressed_data().unwrap()[0], 10);
+    assert_eq!(&chunk, region.get_chunk(0, 0).unwrap());
+}
+
+#[test]
+fn test_decode_each_compression() {
+    let nbt = serialize_nbt(&string(""), &uniform_chunk(0, 1, "minecraft:stone"));
+    let with_header = |compression: u8, payload: Vec<u8>| {
+        let mut bytes = (payload.len() as u32 + 1).to_be_bytes().to_vec();
+        bytes.push(compression);
+        bytes.extend(payload);
+        bytes
+    };
+    let mut gzip = GzEncoder::new(Vec::new(), flate2::Compression::default());
+    gzip.write_all(&nbt).unwrap();
+    let mut zlib = ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+    zlib.write_all(&nbt).unwrap();
+    let id = "example:reversed";
+    let mut custom = (id.len() as u16).to_be_bytes().to_vec();
+    custom.extend_from_slice(id.as_bytes());
+    custom.extend(nbt.iter().rev());
+
+    let mut options = ChunkParseOptions::default();
+    (options.custom_codecs).register(id, |data| Ok(data.iter().rev().copied().collect()));
+    // lz4 is in tests/lz4.rs, decoding a chunk lz4-java wrote
+    let chunks = [
+        (
+            CompressionType::Gzip,
+            with_header(1, gzip.finish().unwrap()),
+        ),
+        (
+            CompressionType::Zlib,
+            with_header(2, zlib.finish().unwrap()),
+        ),
+        (CompressionType::None, with_header(3, nbt.clone())),
+        (CompressionType::Custom, with_header(127, custom)),
+    ];
+    for (compression, bytes) in chunks {
+        let raw = RawChunk::parse(&bytes).unwrap();
+        assert_eq!(raw.compression(), compression);
+        let chunk = raw.decode_with(&options).unwrap();
+        assert_eq!(chunk.get(0, 0, 0).unwrap().get_name(), "minecraft:stone");
+        if compression != CompressionType::Custom {
+            assert_eq!(raw.decompressed_data().unwrap(), nbt);
+        }
+    }
+}
+
+#[test]
+fn test_compression_summary() {
+    let region = Region::parse_bytes(include_bytes!("data/r.0.0.mca")).unwrap();
+    let summary = region.compression_summary();
+    assert_eq!(
+        summary.into_iter().collect::<Vec<_>>(),
+        [(CompressionType::Zlib, 975)]
+    );
+
+    let chunk = parse_chunk(&chunk_bytes(&uniform_chunk(0, 1, "minecraft:stone"))).unwrap();
+    let with = |compression| {
+        chunk.to_bytes_with(&WriteOptions {
+            compression,
+            ..Default::default()
+        })
+    };
+    let gzip = with(Compression::Gzip);
+    let bytes = region_bytes(&[
+        (0, 0, with(Compression::Zlib)),
+        (1, 0, gzip.clone()),
+        (2, 0, with(Compression::None)),
+        // fails to decode, but is still counted
+        (3, 0, gzip[..gzip.len() - 4].to_vec()),
+    ]);
+    let mut region = Region::parse_bytes(&bytes).unwrap();
+    assert_eq!(region.count_chunks(), 3);
+    assert_eq!(
+        region.compression_summary().into_iter().collect::<Vec<_>>(),
+        [
+            (CompressionType::Gzip, 2),
+            (CompressionType::Zlib, 1),
+            (CompressionType::None, 1)
+        ]
+    );
+    let raw = region.get_chunk_raw(1, 0).unwrap();
+    assert_eq!(raw.compressed_data().len(), gzip.len() - 5);
+
+    // changed chunks have no raw chunk until they're written
+    region.get_chunk_mut(0, 0).unwrap();
+    assert_eq!(
+        region.compression_summary().get(&CompressionType::Zlib),
+        None
+    );
+}
+
+#[test]
+fn test_raw_chunk_unsupported_compression() {
+    let mut bytes = 4u32.to_be_bytes().to_vec();
+    bytes.extend_from_slice(&[4, 1, 2, 3]);
+
+    let raw = RawChunk::parse(&bytes).unwrap();
+    assert_eq!(raw.compression(), CompressionType::Lz4);
+    assert_eq!(raw.compressed_data(), &[1, 2, 3]);
+    #[cfg(not(feature = "lz4"))]
+    assert!(matches!(
+        raw.decompressed_data(),
+        Err(ChunkParseError::UnsupportedCompression)
+    ));
+    #[cfg(feature = "lz4")]
+    assert!(matches!(
+        raw.decompressed_data(),
+        Err(ChunkParseError::DecompressionFailed(_))
+    ));
+}
+
+#[test]
+fn test_raw_chunk_external() {
+    let mut bytes = 1u32.to_be_bytes().to_vec();
+    bytes.push(0x82);
+
+    let raw = RawChunk::parse(&bytes).unwrap();
+    assert_eq!(raw.compression(), CompressionType::Zlib);
+    assert!(raw.is_external());
+    assert!(raw.decompressed_data().is_err());
+
+    bytes[4] = 0x85;
+    assert!(matches!(
+        RawChunk::parse(&bytes),
+        Err(ChunkParseError::UnsupportedCompression)
+    ));
+}
+
+#[test]
+fn test_compression_type_bytes() {
+    for byte in [1, 2, 3, 4, 127] {
+        assert_eq!(u8::from(CompressionType::try_from(byte).unwrap()), byte);
+    }
+    assert_eq!(CompressionType::try_from(0), Err(0));
+    assert_eq!(CompressionType::try_from(0x82), Err(0x82));
+    assert_eq!(
+        CompressionType::from_header_byte(0x82),
+        Ok((CompressionType::Zlib, true))
+    );
+    assert_eq!(CompressionType::from_header_byte(0x80), Err(0x80));
+    assert_eq!(CompressionType::Gzip.to_header_byte(true), 0x81);
+}
+
+fn three_chunk_region() -> Region {
+    let chunk = || chunk_bytes(&uniform_chunk(0, 1, "minecraft:stone"));
+    let mut bytes = region_bytes(&[(0, 0, chunk()), (5, 2, chunk()), (31, 31, chunk())]);
+    for (index, timestamp) in [(0, 100u32), (5 + 2 * 32, 200), (1023, 300)] {
+        let offset = 4096 + index * 4;
+        bytes[offset..offset + 4].copy_from_slice(&timestamp.to_be_bytes());
+    }
+    Region::parse_bytes(&bytes).unwrap()
+}
+
+#[test]
+fn test_merge() {
+    let chunk = || chunk_bytes(&uniform_chunk(0, 1, "minecraft:dirt"));
+    let mut bytes = region_bytes(&[(0, 0, chunk()), (1, 0, chunk()), (5, 2, chunk())]);
+    for (index, timestamp) in [(0, 500u32), (1, 10), (5 + 2 * 32, 50)] {
+        let offset = 4096 + index * 4;
+        bytes[offset..offset + 4].copy_from_slice(&timestamp.to_be_bytes());
+    }
+    let (base, other) = (three_chunk_region(), Region::parse_bytes(&bytes).unwrap());
+    let merged = |policy| {
+        let mut region = base.clone();
+        region.merge(&other, policy);
+        region
+    };
+    let slots = |region: &Region| {
+        let mut slots = Vec::new();
+        for (x, z, chunk) in region.iter_chunks() {
+            let name = chunk.get(0, 0, 0).unwrap().get_name().to_string();
+            slots.push((x, z, name, region.get_timestamp(x, z).unwrap()));
+        }
+        slots
+    };
+    let slot = |x, z, name: &str, timestamp| (x, z, format!("minecraft:{name}"), timestamp);
+
+    let region = merged(MergePolicy::TakeOther);
+    assert_eq!(
+        slots(&region),
+        [
+            slot(0, 0, "dirt", 500),
+            slot(1, 0, "dirt", 10),
+            slot(5, 2, "dirt", 50),
+            slot(31, 31, "stone", 300)
+        ]
+    );
+    assert_eq!(region.count_chunks(), 4);
+    // the raw chunks come along, so the slots are written as they were read
+    assert_eq!(region.get_chunk_raw(1, 0), other.get_chunk_raw(1, 0));
+    assert_eq!(
+        Region::parse_bytes(&region.to_bytes().unwrap()).unwrap(),
+        region
+    );
+
+    assert_eq!(
+        slots(&merged(MergePolicy::KeepSelf)),
+        [
+            slot(0, 0, "stone", 100),
+            slot(1, 0, "dirt", 10),
+            slot(5, 2, "stone", 200),
+            slot(31, 31, "stone", 300)
+        ]
+    );
+    assert_eq!(
+        slots(&merged(MergePolicy::TakeNewer)),
+        [
+            slot(0, 0, "dirt", 500),
+            slot(1, 0, "dirt", 10),
+            slot(5, 2, "stone", 200),
+            slot(31, 31, "stone", 300)
+        ]
+    );
+
+    let calls = Cell::new(0);
+    let region = merged(MergePolicy::Custom(Box::new(|x, z, ours, theirs| {
+        calls.set(calls.get() + 1);
+        assert_eq!(ours.is_some(), [(0, 0), (5, 2), (31, 31)].contains(&(x, z)));
+        assert_eq!(theirs.is_some(), [(0, 0), (1, 0), (5, 2)].contains(&(x, z)));
+        match x {
+            0 | 31 => MergeDecision::TakeOther,
+            _ => MergeDecision::KeepSelf,
+        }
+    })));
+    assert_eq!(calls.get(), 4);
+    assert_eq!(
+        slots(&region),
+        [slot(0, 0, "dirt", 500), slot(5, 2, "stone", 200)]
+    );
+    assert_eq!(region.count_chunks(), 2);
+    assert!(region.get_chunk_raw(31, 31).is_none());
+
+    let mut region = base.clone();
+    region.merge_from(other.clone(), MergePolicy::TakeNewer);
+    assert_eq!(region, merged(MergePolicy::TakeNewer));
+}
+
+#[test]
+fn test_merge_raw_only() {
+    let source = Region::parse_bytes(include_bytes!("data/r.0.0.mca")).unwrap();
+    let raw = source.get_chunk_raw(0, 0).unwrap().clone();
+    let at = |secs| UNIX_EPOCH + Duration::from_secs(secs);
+
+    // ours is raw only at (7, 3), theirs at (9, 9), and both have (5, 2)
+    let mut base = three_chunk_region();
+    base.set_chunk_raw(7, 3, raw.clone()).unwrap();
+    base.set_timestamp(7, 3, at(400));
+    base.set_chunk_raw(5, 2, raw.clone()).unwrap();
+    base.set_timestamp(5, 2, at(400));
+    let mut other = three_chunk_region();
+    other.set_chunk_raw(9, 9, raw.clone()).unwrap();
+    other
+        .set_chunk(7, 3, ChunkBuilder::new(7, 3).build().unwrap())
+        .unwrap();
+    other.set_timestamp(7, 3, at(50));
+    let merged = |policy| {
+        let mut region = base.clone();
+        region.merge(&other, policy);
+        region
+    };
+
+    for policy in [MergePolicy::KeepSelf, MergePolicy::TakeNewer] {
+        let region = merged(policy);
+        assert!(region.get_chunk(7, 3).is_none());
+        assert_eq!(region.get_chunk_raw(7, 3), Some(&raw));
+        assert_eq!(region.get_timestamp(7, 3), Some(400));
+        assert!(region.get_chunk(5, 2).is_none());
+        assert_eq!(region.get_chunk_raw(9, 9), Some(&raw));
+    }
+
+    let region = merged(MergePolicy::TakeOther);
+    assert!(region.get_chunk(7, 3).is_some());
+    assert_eq!(region.get_timestamp(7, 3), Some(50));
+    assert!(region.get_chunk(5, 2).is_some());
+    assert_eq!(region.get_timestamp(5, 2), Some(200));
+
+    // taking theirs at (5, 2) when it's newer replaces our raw chunk
+    let mut newer = other.clone();
+    newer.set_timestamp(5, 2, at(500));
+    let mut region = base.clone();
+    region.merge_from(newer, MergePolicy::TakeNewer);
+    assert!(region.get_chunk(5, 2).is_some());
+    assert_eq!(region.get_timestamp(5, 2), Some(500));
+
+    let region = merged(MergePolicy::Custom(Box::new(|x, z, ours, theirs| {
+        let raw_only = |slot: Option<MergeSlot>| slot.is_some_and(|slot| slot.chunk.is_none());
+        assert_eq!(raw_only(ours), [(7, 3), (5, 2)].contains(&(x, z)));
+        assert_eq!(raw_only(theirs), (x, z) == (9, 9));
+        if let Some(ours) = ours.filter(|_| (x, z) == (7, 3)) {
+            assert_eq!(ours.raw, Some(&raw));
+            assert_eq!(ours.timestamp, Some(400));
+        }
+        MergeDecision::KeepSelf
+    })));
+    assert!(region.get_chunk_raw(9, 9).is_none());
+    assert_eq!(region.get_chunk_raw(7, 3), Some(&raw));
+}
+
+#[test]
+fn test_map_chunks() {
+    let air = parse_chunk(&chunk_bytes(&uniform_chunk(0, 1, "minecraft:air"))).unwrap();
+    let air = air.get(0, 0, 0).unwrap().clone();
+
+    let mut slots = Vec::new();
+    let region = three_chunk_region().map_chunks(|x, z, mut chunk| {
+        slots.push((x, z));
+        if x != 0 {
+            chunk.set(x & 0xF, 0, z & 0xF, air.clone()).unwrap();
+        }
+        chunk
+    });
+    assert_eq!(slots, [(0, 0), (5, 2), (31, 31)]);
+    assert_eq!(region.count_chunks(), 3);
+    assert_eq!(region.get_chunk(5, 2).unwrap().get(5, 0, 2), Some(&air));
+    assert_eq!(region.get_chunk(31, 31).unwrap().get(15, 0, 15), Some(&air));
+    assert!(region.get_chunk_raw(5, 2).is_none());
+    // (0, 0) came back unchanged, so it keeps its raw chunk
+    assert_eq!(region.get_chunk_raw(0, 0).unwrap().timestamp(), 100);
+    assert_eq!(region.get_timestamp(5, 2), Some(200));
+    assert_eq!(region.get_timestamp(31, 31), Some(300));
+
+    // (0, 0) came back unchanged, so its raw chunk is written back
+    let reparsed = Region::parse_bytes(&region.to_bytes().unwrap()).unwrap();
+    assert_eq!(reparsed.get_chunk(5, 2).unwrap().get(5, 0, 2), Some(&air));
+    assert_ne!(reparsed.get_chunk(0, 0).unwrap().get(0, 0, 0), Some(&air));
+    assert_eq!(reparsed.get_timestamp(31, 31), Some(300));
+}
+
+#[test]
+fn test_filter_map_chunks() {
+    let region = three_chunk_region().filter_map_chunks(|x, _, chunk| (x != 5).then_some(chunk));
+    assert_eq!(region.count_chunks(), 2);
+    assert!(region.get_chunk(5, 2).is_none());
+    assert!(region.get_chunk_raw(5, 2).is_none());
+    assert!(region.get_chunk(0, 0).is_some());
+    assert_eq!(region.dirty_chunks().count(), 0);
+    assert_eq!(region.get_chunk_raw(0, 0).unwrap().timestamp(), 100);
+    assert_eq!(region.get_chunk_raw(31, 31).unwrap().timestamp(), 300);
+}
+
+#[cfg(feature = "rayon")]
+#[test]
+fn test_par_map_chunks() {
+    let air = parse_chunk(&chunk_bytes(&uniform_chunk(0, 1, "minecraft:air"))).unwrap();
+    let air = air.get(0, 0, 0).unwrap().clone();
+
+    let region = three_chunk_region().par_map_chunks(|x, z, mut chunk| {
+        if x != 0 {
+            chunk.set(x & 0xF, 0, z & 0xF, air.clone()).unwrap();
+        }
+        chunk
+    });
+    assert_eq!(region.count_chunks(), 3);
+    assert_eq!(region.get_chunk(5, 2).unwrap().get(5, 0, 2), Some(&air));
+    assert_eq!(region.get_chunk(31, 31).unwrap().get(15, 0, 15), Some(&air));
+    assert!(region.get_chunk_raw(5, 2).is_none());
+    assert!(region.get_chunk_raw(0, 0).is_some());
+    assert_eq!(region.get_timestamp(5, 2), Some(200));
+
+    let region = region.par_filter_map_chunks(|x, _, chunk| (x != 5).then_some(chunk));
+    assert_eq!(region.count_chunks(), 2);
+    assert!(region.get_chunk(5, 2).is_none());
+    assert!(region.get_chunk_raw(5, 2).is_none());
+    assert!(region.get_timestamp(5, 2).is_none());
+    assert_eq!(region.get_chunk(31, 31).unwrap().get(15, 0, 15), Some(&air));
+    assert_eq!(region.get_timestamp(31, 31), Some(300));
+}
+
+#[test]
+fn test_set_chunk() {
+    let mut region = three_chunk_region();
+    let chunk = ChunkBuilder::new(7, 3).build().unwrap();
+    assert_eq!(region.set_chunk(7, 3, chunk.clone()), Ok(None));
+    assert_eq!(region.count_chunks(), 4);
+    assert_eq!(region.get_chunk(7, 3), Some(&chunk));
+    assert_eq!(region.get_timestamp(7, 3), Some(0));
+
+    let replaced = region.set_chunk(5, 2, chunk.clone()).unwrap();
+    assert_eq!(replaced.unwrap().get_pos(), ChunkPos::new(0, 0));
+    assert_eq!(region.count_chunks(), 4);
+    assert!(region.get_chunk_raw(5, 2).is_none());
+    assert_eq!(region.get_timestamp(5, 2), Some(200));
+    assert_eq!(region.set_chunk(0, 32, chunk).unwrap_err().axis, Axis::Z);
+
+    let reparsed = Region::parse_bytes(&region.to_bytes().unwrap()).unwrap();
+    let mut written = region.get_chunk(7, 3).unwrap().clone();
+    written.compute_heightmaps();
+    assert_eq!(reparsed.get_chunk(7, 3), Some(&written));
+    assert_eq!(reparsed.get_timestamp(5, 2), Some(200));
+}
+
+#[test]
+fn test_set_chunk_raw() {
+    let source = Region::parse_bytes(include_bytes!("data/r.0.0.mca")).unwrap();
+    let raw = source.get_chunk_raw(0, 0).unwrap().clone();
+    let mut region = three_chunk_region();
+    assert_eq!(region.set_chunk_raw(7, 3, raw.clone()), Ok(None));
+    assert!(region.get_chunk(7, 3).is_none());
+    assert_eq!(region.get_timestamp(7, 3), Some(raw.timestamp()));
+
+    let replaced = region.set_chunk_raw(5, 2, raw.clone()).unwrap();
+    assert_eq!(replaced.unwrap().timestamp(), 200);
+    assert!(region.get_chunk(5, 2).is_none());
+    assert_eq!(
+        region.set_chunk_raw(32, 0, raw.clone()).unwrap_err().axis,
+        Axis::X
+    );
+
+    // the payload is copied byte for byte
+    let reparsed = Region::parse_bytes(&region.to_bytes().unwrap()).unwrap();
+    for (x, z) in [(7, 3), (5, 2)] {
+        assert_eq!(reparsed.get_chunk_raw(x, z), Some(&raw));
+        assert_eq!(reparsed.get_chunk(x, z), source.get_chunk(0, 0));
+    }
+    assert_eq!(reparsed.count_chunks(), 4);
+}
+
+#[test]
+fn test_get_chunk_mut() {
+    let mut region = three_chunk_region();
+    let dirt = parse_chunk(&chunk_bytes(&uniform_chunk(0, 1, "minecraft:dirt"))).unwrap();
+    let dirt = dirt.get(0, 0, 0).unwrap().clone();
+
+    let section = region
+        .get_chunk_mut(5, 2)
+        .unwrap()
+        .get_section_mut(0)
+        .unwrap();
+    section.set_block(1, 2, 3, dirt.clone()).unwrap();
+    assert!(region.get_chunk_mut(32, 0).is_none());
+    assert!(region.get_chunk_mut(1, 1).is_none());
+    assert!(region.get_chunk_raw(5, 2).is_none());
+    assert!(region.get_chunk_raw(0, 0).is_some());
+
+    // the edit is written, since the chunk is encoded again
+    let reparsed = Region::parse_bytes(&region.to_bytes().unwrap()).unwrap();
+    assert_eq!(reparsed.get_chunk(5, 2).unwrap().get(1, 2, 3), Some(&dirt));
+}
+
+#[test]
+fn test_dirty_chunks() {
+    let bytes = include_bytes!("data/r.0.0.mca");
+    let original = Region::parse_bytes(bytes).unwrap();
+    let mut region = original.clone();
+    assert_eq!(region.dirty_chunks().count(), 0);
+
+    let glass = Block::new(string("minecraft:glass"), None);
+    (region.get_chunk_mut(5, 9).unwrap())
+        .set(0, 70, 0, glass.clone())
+        .unwrap();
+    let chunk = region.get_chunk(3, 3).unwrap().clone();
+    region.set_chunk(4, 4, chunk).unwrap();
+    region.remove_chunk(6, 6).unwrap();
+    assert_eq!(region.dirty_chunks().collect::<Vec<_>>(), [(4, 4), (5, 9)]);
+    assert!(region.is_dirty(5, 9));
+    assert!(!region.is_dirty(3, 3));
+    assert!(!region.is_dirty(6, 6));
+    assert!(!region.is_dirty(32, 0));
+
+    // everything else is written as it was read
+    let reparsed = Region::parse_bytes(&region.to_bytes().unwrap()).unwrap();
+    for (x, z, _) in original.iter_chunks() {
+        let raw = |region: &Region| {
+            region
+                .get_chunk_raw(x, z)
+                .map(|raw| raw.compressed_data().to_vec())
+        };
+        match (x, z) {
+            (4, 4) | (5, 9) => assert_ne!(raw(&reparsed), raw(&original)),
+            (6, 6) => assert!(!reparsed.chunk_exists(6, 6)),
+            _ => assert_eq!(raw(&reparsed), raw(&original)),
+        }
+    }
+    assert_eq!(
+        reparsed.get_chunk(5, 9).unwrap().get(0, 70, 0),
+        Some(&glass)
+    );
+}
+
+#[test]
+fn test_to_bytes_computes_heightmaps() {
+    let mut region = Region::parse_bytes(include_bytes!("data/r.0.0.mca")).unwrap();
+    let height = |region: &Region| {
+        let chunk = region.get_chunk(5, 9).unwrap();
+        let heightmap = chunk.get_heightmap(HeightmapKind::WorldSurface).unwrap();
+        heightmap.get(0, 0).unwrap()
+    };
+    let stored = height(&region);
+    assert!(stored < 200);
+    let glass = Block::new(string("minecraft:glass"), None);
+    (region.get_chunk_mut(5, 9).unwrap())
+        .set(0, 200, 0, glass)
+        .unwrap();
+
+    let reparsed = Region::parse_bytes(&region.to_bytes().unwrap()).unwrap();
+    assert_eq!(height(&reparsed), 201);
+    let options = WriteOptions {
+        compute_heightmaps: false,
+        ..Default::default()
+    };
+    let reparsed = Region::parse_bytes(&region.to_bytes_with(&options).unwrap()).unwrap();
+    assert_eq!(height(&reparsed), stored);
+    // the region itself keeps what it had
+    assert_eq!(height(&region), stored);
+}
+
+#[test]
+fn test_remove_chunk() {
+    let mut region = three_chunk_region();
+    let removed = region.remove_chunk(5, 2).unwrap();
+    assert_eq!(removed.get_pos(), ChunkPos::new(0, 0));
+    assert_eq!(region.count_chunks(), 2);
+    assert!(region.get_chunk(5, 2).is_none());
+    assert!(region.get_chunk_raw(5, 2).is_none());
+    assert_eq!(region.get_timestamp(5, 2), None);
+    assert!(region.remove_chunk(5, 2).is_none());
+    assert!(region.remove_chunk(32, 0).is_none());
+}
+
+#[test]
+fn test_retain_chunks() {
+    let mut region = three_chunk_region();
+    let mut slots = Vec::new();
+    region.retain_chunks(|x, z, _| {
+        slots.push((x, z));
+        x != 31
+    });
+    assert_eq!(slots, [(0, 0), (5, 2), (31, 31)]);
+    assert_eq!(region.count_chunks(), 2);
+    assert!(region.get_chunk(31, 31).is_none());
+    assert_eq!(region.get_timestamp(31, 31), None);
+    assert_eq!(region.get_timestamp(5, 2), Some(200));
+
+    let reparsed = Region::parse_bytes(&region.to_bytes().unwrap()).unwrap();
+    assert_eq!(reparsed, region);
+}
+
+#[test]
+fn test_timestamps() {
+    let region = Region::parse_bytes(include_bytes!("data/r.0.0.mca")).unwrap();
+    let raw = region.get_chunk_raw(0, 0).unwrap();
+    assert_eq!(region.get_timestamp(0, 0), Some(raw.timestamp()));
+    assert_eq!(region.get_timestamp(32, 0), None);
+
+    let empty = Region::parse_bytes(&[0; 8192]).unwrap();
+    assert_eq!(empty.get_timestamp(0, 0), None);
+    assert_eq!(empty.iter_timestamps().next(), None);
+
+    assert_eq!(
+        three_chunk_region().iter_timestamps().collect::<Vec<_>>(),
+        [(0, 0, 100), (5, 2, 200), (31, 31, 300)]
+    );
+    let mut bytes = region_bytes(&[(3, 1, vec![0, 0, 0, 2, 3, 0xFF])]);
+    bytes[4096 + 4 * 35..4096 + 4 * 36].copy_from_slice(&42u32.to_be_bytes());
+    let failed = Region::parse_bytes(&bytes).unwrap();
+    assert_eq!(failed.count_chunks(), 0);
+    assert_eq!(failed.iter_timestamps().collect::<Vec<_>>(), [(3, 1, 42)]);
+}
+
+#[test]
+fn test_from_chunks() {
+    let chunk = |x, z| ChunkBuilder::new(x, z).build().unwrap();
+    let region = Region::from_chunks([((0, 0), chunk(0, 0)), ((31, 2), chunk(31, 2))]).unwrap();
+    assert_eq!(region.count_chunks(), 2);
+    assert_eq!(region.get_chunk(31, 2), Some(&chunk(31, 2)));
+    assert!(region.get_chunk_raw(31, 2).is_none());
+    assert_eq!(region.get_timestamp(31, 2), Some(0));
+    assert_eq!(region.get_timestamp(1, 0), None);
+
+    assert_eq!(
+        Region::from_chunks([((3, 4), chunk(3, 4)), ((3, 4), chunk(3, 4))]).unwrap_err(),
+        RegionBuildError::DuplicateSlot(3, 4)
+    );
+    let RegionBuildError::OutOfBounds(err) =
+        Region::from_chunks([((0, 32), chunk(0, 0))]).unwrap_err()
+    else {
+        panic!("expected an out of bounds slot");
+    };
+    assert_eq!(err.axis, Axis::Z);
+}
+
+#[test]
+fn test_region_builder() {
+    let chunk = |x, z| ChunkBuilder::new(x, z).build().unwrap();
+    let region = RegionBuilder::new()
+        .position(1, -1)
+        .chunk(1, 30, chunk(33, -2))
+        .timestamp(1, 30, 1_700_000_000)
+        .timestamp(2, 30, 1_700_000_000)
+        .build()
+        .unwrap();
+    assert_eq!(region.get_timestamp(1, 30), Some(1_700_000_000));
+    assert_eq!(region.get_timestamp(2, 30), None);
+
+    assert_eq!(
+        RegionBuilder::new()
+            .position(1, -1)
+            .chunk(1, 30, chunk(1, 30))
+            .build()
+            .unwrap_err(),
+        RegionBuildError::SlotMismatch {
+            slot: (1, 30),
+            pos: ChunkPos::new(1, 30)
+        }
+    );
+    // without a position, chunks can go anywhere
+    assert!(
+        RegionBuilder::new()
+            .chunk(1, 30, chunk(1, 3))
+            .build()
+            .is_ok()
+    );
+}
+
+#[test]
+fn test_status_filter() {
+    let with_status = |status: Option<&str>| {
+        let mut fields: HashMap<_, _> = uniform_chunk(0, 1, "minecraft:stone").into();
+        fields.remove("Status");
+        if let Some(status) = status {
+            fields.insert(string("Status"), NbtTag::String(string(status)));
+        }
+        chunk_bytes(&fields.into())
+    };
+    let bytes = region_bytes(&[
+        (0, 0, with_status(Some("minecraft:full"))),
+        (1, 0, with_status(Some("minecraft:features"))),
+        (2, 0, with_status(Some("noise"))),
+        (3, 0, with_status(Some("minecraft:postprocessed"))),
+        (4, 0, with_status(None)),
+    ]);
+    let parse = |status_filter| {
+        let options = RegionParseOptions {
+            status_filter,
+            ..Default::default()
+        };
+        Region::parse_bytes_with(&bytes, &options).unwrap()
+    };
+
+    let all = parse(StatusFilter::All);
+    assert_eq!(all.count_chunks(), 5);
+    assert_eq!(all.count_skipped(), 0);
+
+    let full = parse(StatusFilter::OnlyFull);
+    assert_eq!(full.count_chunks(), 2);
+    assert_eq!(full.count_skipped(), 3);
+    assert!(full.get_chunk(4, 0).is_some());
+    assert!(full.get_chunk(1, 0).is_none());
+    assert!(full.get_chunk_raw(1, 0).is_some());
+    assert_eq!(full.get_timestamp(1, 0), Some(1));
+
+    // skipped chunks are written back as they were read
+    let written = Region::parse_bytes(&full.to_bytes().unwrap()).unwrap();
+    assert_eq!(written.count_chunks(), 5);
+    assert_eq!(written.get_chunk(1, 0), all.get_chunk(1, 0));
+
+    let features = parse(StatusFilter::MinimumStatus(ChunkStatus::Features));
+    assert_eq!(features.count_chunks(), 3);
+    assert!(features.get_chunk(1, 0).is_some());
+    assert!(features.get_chunk(2, 0).is_none());
+    assert!(features.get_chunk(3, 0).is_none());
+}
+
+#[test]
+fn test_to_bytes_round_trip() {
+    let region = Region::parse_bytes(include_bytes!("data/r.0.0.mca")).unwrap();
+    let bytes = region.to_bytes().unwrap();
+    assert_eq!(bytes.len() % 4096, 0);
+
+    let reparsed = Region::parse_bytes(&bytes).unwrap();
+    assert_eq!(reparsed.count_chunks(), 975);
+    assert_eq!(reparsed, region);
+
+    let missing = (0..1024).find(|&i| region.get_chunk_raw(i % 32, i / 32).is_none());
+    let index = missing.unwrap();
+    assert_eq!(&bytes[index * 4..index * 4 + 4], &[0; 4]);
+    assert_eq!(&bytes[4096 + index * 4..4096 + index * 4 + 4], &[0; 4]);
+    // the first chunk starts right after the header
+    assert_eq!(&bytes[..3], &[0, 0, 2]);
+
+    let empty = Region::parse_bytes(&[0; 8192]).unwrap();
+    assert_eq!(empty.to_bytes().unwrap(), vec![0; 8192]);
+}
+
+#[test]
+fn test_to_bytes_chunk_too_large() {
+    let mut region = three_chunk_region();
+    let mut bytes = (256 * 4096 + 1u32).to_be_bytes().to_vec();
+    bytes.push(3);
+    bytes.resize(bytes.len() + 256 * 4096, 0);
+    let raw = RawChunk::parse(&bytes).unwrap();
+    region.set_chunk_raw(4, 1, raw).unwrap();
+
+    let error = region.to_bytes().unwrap_err();
+    assert_eq!(
+        error,
+        ChunkTooLarge {
+            x: 4,
+            z: 1,
+            sectors: 257
+        }
+    );
+
+    let path = temp_dir("chunk_too_large").join("r.0.0.mca");
+    let error = region.write_to_file(&path).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::InvalidData);
+    assert!(!path.exists());
+}
+
+fn corrupt_region() -> Vec<u8> {
+    let mut bytes = region_bytes(&[
+        (0, 0, chunk_bytes(&uniform_chunk(0, 1, "minecraft:stone"))),
+        // zlib compressed, but the data isn't
+        (1, 0, vec![0, 0, 0, 2, 2, 0xFF]),
+        (2, 0, vec![0, 0, 0, 2, 9, 0xFF]),
+    ]);
+    // a chunk past the end of the file
+    bytes[12..16].copy_from_slice(&[0, 1, 0, 1]);
+    bytes
+}
+
+#[test]
+fn test_parse_metadata() {
+    let bytes = include_bytes!("data/r.0.0.mca");
+    let region = Region::parse_bytes(bytes).unwrap();
+    let table = Region::parse_metadata(bytes).unwrap();
+    assert_eq!(table.iter().flatten().count(), 975);
+    for (x, z, chunk) in region.iter_chunks() {
+        let metadata = table[x + z * 32].as_ref().unwrap();
+        assert_eq!(metadata.x_pos, chunk.x_pos());
+        assert_eq!(metadata.z_pos, chunk.z_pos());
+        assert_eq!(metadata.y_pos * 16, chunk.get_y_range().start);
+        assert_eq!(metadata.data_version, chunk.data_version());
+        assert_eq!(&metadata.status, chunk.status());
+        assert_eq!(metadata.inhabited_time, chunk.inhabited_time());
+    }
+    assert!(table[31 + 31 * 32].is_none());
+
+    // chunks that fail to decode are left out
+    let table = Region::parse_metadata(&corrupt_region()).unwrap();
+    assert_eq!(table.iter().flatten().count(), 1);
+}
+
+#[test]
+fn test_presence_map() {
+    let bytes = include_bytes!("data/r.0.0.mca");
+    let region = Region::parse_bytes(bytes).unwrap();
+    let map = Region::presence_map(&bytes[..8192]).unwrap();
+    assert_eq!(map, Region::presence_map(bytes).unwrap());
+    assert_eq!(map.iter().map(|word| word.count_ones()).sum::<u32>(), 975);
+    for x in 0..32 {
+        for z in 0..32 {
+            let index = x + z * 32;
+            let present = map[index / 64] >> (index % 64) & 1 == 1;
+            assert_eq!(present, region.chunk_exists(x, z));
+            assert_eq!(present, region.get_chunk(x, z).is_some());
+        }
+    }
+    assert!(!region.chunk_exists(31, 31));
+    assert!(!region.chunk_exists(32, 0));
+    assert!(!region.chunk_exists(0, 32));
+
+    // a chunk that fails to decode still exists
+    let region = Region::parse_bytes_lossy(&corrupt_region(), &RegionParseOptions::default())
+        .unwrap()
+        .0;
+    assert!(region.chunk_exists(1, 0));
+    assert!(!region.chunk_exists(2, 0));
+
+    assert!(matches!(
+        Region::presence_map(&bytes[..8191]),
+        Err(RegionParseError::InputTooShort(8191))
+    ));
+}
+
+#[test]
+fn test_bounding_box() {
+    let bytes = include_bytes!("data/r.0.0.mca");
+    let region = Region::parse_bytes(bytes).unwrap();
+    let expected = region
+        .iter_chunks()
+        .fold((31, 31, 0, 0), |bounds, (x, z, _)| {
+            (
+                bounds.0.min(x),
+                bounds.1.min(z),
+                bounds.2.max(x),
+                bounds.3.max(z),
+            )
+        });
+    assert_eq!(region.bounding_box(), Some(expected));
+    assert_eq!(
+        Region::bounding_box_from_header(&bytes[..4096]).unwrap(),
+        Some(expected)
+    );
+
+    let chunk = chunk_bytes(&uniform_chunk(-4, 1, "minecraft:stone"));
+    let bytes = region_bytes(&[(7, 2, chunk.clone()), (3, 20, chunk)]);
+    let region = Region::parse_bytes(&bytes).unwrap();
+    assert_eq!(region.bounding_box(), Some((3, 2, 7, 20)));
+    assert_eq!(
+        Region::bounding_box_from_header(&bytes).unwrap(),
+        Some((3, 2, 7, 20))
+    );
+
+    // a chunk that fails to decode still counts
+    let (region, _) =
+        Region::parse_bytes_lossy(&corrupt_region(), &RegionParseOptions::default()).unwrap();
+    assert_eq!(region.bounding_box(), Some((0, 0, 1, 0)));
+
+    assert_eq!(Region::empty().bounding_box(), None);
+    assert_eq!(Region::bounding_box_from_header(&[0; 4096]).unwrap(), None);
+    assert!(matches!(
+        Region::bounding_box_from_header(&[0; 4095]),
+        Err(RegionParseError::LocationsTooShort(4095))
+    ));
+}
+
+#[test]
+fn test_parse_lossy() {
+    let bytes = corrupt_region();
+    let (region, errors) =
+        Region::parse_bytes_lossy(&bytes, &RegionParseOptions::default()).unwrap();
+    assert_eq!(region.count_chunks(), 1);
+    // the raw chunk stays available
+    assert!(region.get_chunk_raw(1, 0).is_some());
+    assert!(region.get_chunk_raw(2, 0).is_none());
+
+    let slots: Vec<_> = errors.iter().map(|&(x, z, _)| (x, z)).collect();
+    assert_eq!(slots, [(1, 0), (2, 0), (3, 0)]);
+    assert!(matches!(
+        errors[0].2,
+        ChunkParseError::DecompressionFailed(_)
+    ));
+    assert!(matches!(
+        errors[1].2,
+        ChunkParseError::UnsupportedCompression
+    ));
+    assert!(matches!(errors[2].2, ChunkParseError::InputTooShort(..)));
+}
+
+#[test]
+fn test_parse_reported() {
+    let (region, report) =
+        Region::parse_bytes_reported(&corrupt_region(), &RegionParseOptions::default()).unwrap();
+    assert_eq!(region.count_chunks(), 1);
+    assert!(matches!(report.get(0, 0), Some(SlotOutcome::Parsed)));
+    assert!(matches!(report.get(4, 0), Some(SlotOutcome::Empty)));
+    assert!(report.get(32, 0).is_none());
+    let failed: Vec<_> = report.failed().map(|(x, z, _)| (x, z)).collect();
+    assert_eq!(failed, [(1, 0), (2, 0), (3, 0)]);
+    assert!(!report.is_clean());
+    assert_eq!(
+        (
+            report.count_parsed(),
+            report.count_failed(),
+            report.count_empty()
+        ),
+        (1, 3, 1020)
+    );
+    assert_eq!(report.iter().count(), 1024);
+
+    let options = RegionParseOptions {
+        status_filter: StatusFilter::OnlyFull,
+        ..RegionParseOptions::default()
+    };
+    let mut fields: HashMap<_, _> = uniform_chunk(0, 1, "minecraft:stone").into();
+    fields.insert(string("Status"), NbtTag::String(string("minecraft:noise")));
+    let bytes = region_bytes(&[(5, 1, chunk_bytes(&fields.into()))]);
+    let (_, report) = Region::parse_bytes_reported(&bytes, &options).unwrap();
+    assert!(matches!(report.get(5, 1), Some(SlotOutcome::Skipped)));
+    assert_eq!(report.count_skipped(), 1);
+    assert!(report.is_clean());
+}
+
+#[test]
+fn test_truncated() {
+    let bytes = include_bytes!("data/r.0.0.mca");
+    let full = Region::parse_bytes(bytes).unwrap();
+
+    // cut at a sector boundary, the chunks past the cut fail on their own
+    let cut = &bytes[..bytes.len() / 2 / 4096 * 4096];
+    let (region, errors) = Region::parse_bytes_lossy(cut, &RegionParseOptions::default()).unwrap();
+    assert!(region.count_chunks() > 0);
+    assert!(region.count_chunks() < full.count_chunks());
+    assert_eq!(
+        region.count_chunks() as usize + errors.len(),
+        full.count_chunks() as usize
+    );
+    assert!(
+        errors
+            .iter()
+            .all(|(.., err)| matches!(err, ChunkParseError::InputTooShort(..)))
+    );
+    for (x, z, chunk) in region.iter_chunks() {
+        assert_eq!(Some(chunk), full.get_chunk(x, z));
+    }
+
+    assert!(matches!(
+        Region::parse_bytes(&bytes[..bytes.len() / 2]),
+        Err(RegionParseError::InputInvalidSize(_))
+    ));
+    assert!(matches!(
+        Region::parse_bytes(&bytes[..5000]),
+        Err(RegionParseError::InputTooShort(5000))
+    ));
+}
+
+#[test]
+fn test_parse_strict() {
+    let bytes = corrupt_region();
+    assert_eq!(Region::parse_bytes(&bytes).unwrap().count_chunks(), 1);
+
+    let strict = RegionParseOptions {
+        strict: true,
+        ..Default::default()
+    };
+    assert!(matches!(
+        Region::parse_bytes_with(&bytes, &strict),
+        Err(RegionParseError::ChunkError {
+            x: 1,
+            z: 0,
+            source: ChunkParseError::DecompressionFailed(_)
+        })
+    ));
+    let (_, errors) = Region::parse_bytes_lossy(&bytes, &strict).unwrap();
+    assert_eq!(errors.len(), 3);
+}
+
+#[test]
+fn test_recover_lengths() {
+    let chunk = chunk_bytes(&uniform_chunk(0, 1, "minecraft:stone"));
+    let garbled = |len: u32| {
+        let mut bytes = chunk.clone();
+        bytes[..4].copy_from_slice(&len.to_be_bytes());
+        bytes
+    };
+    let bytes = region_bytes(&[
+        (0, 0, chunk.clone()),
+        (1, 0, garbled(10_000)),
+        (2, 0, garbled(0)),
+    ]);
+
+    let (region, errors) =
+        Region::parse_bytes_lossy(&bytes, &RegionParseOptions::default()).unwrap();
+    assert_eq!(region.count_chunks(), 1);
+    assert!(matches!(
+        errors[0],
+        (
+            1,
+            0,
+            ChunkParseError::LengthExceedsSectors {
+                declared: 10_000,
+                available: 4092
+            }
+        )
+    ));
+    // no payload left
+    assert_eq!((errors[1].0, errors[1].1), (2, 0));
+
+    let options = RegionParseOptions {
+        recover_lengths: true,
+        ..Default::default()
+    };
+    let region = Region::parse_bytes_with(&bytes, &options).unwrap();
+    assert_eq!(region.count_chunks(), 3);
+    for x in 1..3 {
+        assert_eq!(region.get_chunk(x, 0), region.get_chunk(0, 0));
+    }
+    let lazy = LazyRegion::parse_bytes_with(&bytes, options).unwrap();
+    assert!(lazy.get_chunk(1, 0).is_some());
+
+    // the sectors are known even if the file was cut short
+    assert!(matches!(
+        RawChunk::parse_sectors(&garbled(5000)[..100], 4096, false),
+        Err(ChunkParseError::LengthExceedsSectors { .. })
+    ));
+    assert!(matches!(
+        RawChunk::parse_sectors(&chunk[..100], 4096, false),
+        Err(ChunkParseError::InputTooShort(..))
+    ));
+}
+
+#[test]
+fn test_zero_sector_entries() {
+    // written by old tools, the sectors follow from the chunk's length
+    let chunk = chunk_bytes(&uniform_chunk(0, 1, "minecraft:stone"));
+    let mut bytes = region_bytes(&[(0, 0, chunk.clone()), (1, 0, chunk)]);
+    bytes[7] = 0;
+    // and one pointing past the end of the file
+    bytes[8..12].copy_from_slice(&[0, 0, 9, 0]);
+    bytes[4096 + 8..4096 + 12].copy_from_slice(&[0, 0, 0, 1]);
+
+    let (region, errors) =
+        Region::parse_bytes_lossy(&bytes, &RegionParseOptions::default()).unwrap();
+    assert_eq!(region.count_chunks(), 2);
+    assert_eq!(region.get_chunk(1, 0), region.get_chunk(0, 0));
+    assert!(matches!(
+        errors[..],
+        [(2, 0, ChunkParseError::InputTooShort(..))]
+    ));
+
+    let lazy = LazyRegion::parse_bytes(&bytes).unwrap();
+    assert_eq!(lazy.get_chunk(1, 0), region.get_chunk(1, 0));
+    let mut file = RegionFile::new(Cursor::new(&bytes)).unwrap();
+    assert_eq!(
+        file.read_chunk(1, 0).unwrap().as_ref(),
+        region.get_chunk(1, 0)
+    );
+    assert!(file.read_chunk(2, 0).is_err());
+    let seekable = Region::from_seekable(Cursor::new(&bytes)).unwrap();
+    assert_eq!(seekable.count_chunks(), 2);
+}
+
+#[test]
+fn test_zero_sector_entry_huge_length() {
+    // the length is all there is to go by, and it's nowhere near the truth
+    let chunk = chunk_bytes(&uniform_chunk(0, 1, "minecraft:stone"));
+    let mut bytes = region_bytes(&[(0, 0, chunk)]);
+    let sector = bytes.len() >> 12;
+    bytes[4..8].copy_from_slice(&[0, 0, sector as u8, 0]);
+    bytes.extend([0xFF, 0xFF, 0xFF, 0xFF, 2]);
+    bytes.resize(bytes.len().next_multiple_of(4096), 0xAB);
+
+    let (region, errors) =
+        Region::parse_bytes_lossy(&bytes, &RegionParseOptions::default()).unwrap();
+    assert_eq!(region.count_chunks(), 1);
+    // only the sector that's in the file is taken
+    assert!(matches!(
+        errors[..],
+        [(
+            1,
+            0,
+            ChunkParseError::LengthExceedsSectors {
+                declared: 0xFFFF_FFFF,
+                available: 4092
+            }
+        )]
+    ));
+
+    let mut file = RegionFile::new(Cursor::new(&bytes)).unwrap();
+    assert!(file.read_raw_chunk(1, 0).is_err());
+    assert!(file.read_chunk(0, 0).unwrap().is_some());
+    let seekable = Region::from_seekable(Cursor::new(&bytes)).unwrap();
+    assert_eq!(seekable.count_chunks(), 1);
+}
+
+#[test]
+fn test_check_slots() {
+    let mut moved: HashMap<_, _> = uniform_chunk(0, 1, "minecraft:stone").into();
+    moved.insert(string("xPos"), NbtTag::Int(-27));
+    moved.insert(string("zPos"), NbtTag::Int(34));
+    let bytes = region_bytes(&[
+        (5, 2, chunk_bytes(&moved.into())),
+        (0, 0, chunk_bytes(&uniform_chunk(0, 1, "minecraft:stone"))),
+        (3, 1, chunk_bytes(&uniform_chunk(0, 1, "minecraft:stone"))),
+    ]);
+
+    let region = Region::parse_bytes(&bytes).unwrap();
+    assert_eq!(region.count_chunks(), 3);
+    let chunk = region.get_chunk(5, 2).unwrap();
+    assert_eq!((chunk.x_pos(), chunk.z_pos()), (-27, 34));
+
+    let options = RegionParseOptions {
+        check_slots: true,
+        ..Default::default()
+    };
+    let (region, errors) = Region::parse_bytes_lossy(&bytes, &options).unwrap();
+    assert_eq!(region.count_chunks(), 2);
+    assert!(region.get_chunk_raw(3, 1).is_some());
+    assert_eq!(errors.len(), 1);
+    assert!(matches!(
+        errors[0],
+        (
+            3,
+            1,
+            ChunkParseError::SlotMismatch {
+                slot: (3, 1),
+                pos: ChunkPos { x: 0, z: 0 }
+            }
+        )
+    ));
+    assert_eq!(
+        errors[0].2.to_string(),
+        "the chunk in slot (3, 1) claims to be at (0, 0)"
+    );
+}
+
+#[test]
+fn test_fragmentation_stats() {
+    let bytes = include_bytes!("data/r.0.0.mca");
+    let stats = fragmentation_stats(bytes).unwrap();
+    assert_eq!(stats.total_sectors, bytes.len() / 4096);
+    assert_eq!(
+        stats.used_sectors + stats.wasted_sectors,
+        stats.total_sectors
+    );
+
+    let compacted = Region::parse_bytes(bytes).unwrap().to_bytes().unwrap();
+    let compacted_stats = fragmentation_stats(&compacted).unwrap();
+    assert_eq!(compacted_stats.wasted_sectors, 0);
+    assert_eq!(compacted_stats.used_sectors, stats.used_sectors);
+
+    // a gap of two sectors, and a chunk given a sector more than it needs
+    let chunk = chunk_bytes(&uniform_chunk(0, 1, "minecraft:stone"));
+    let mut bytes = region_bytes(&[(0, 0, chunk.clone()), (1, 0, chunk)]);
+    bytes[4..8].copy_from_slice(&[0, 0, 5, 2]);
+    bytes.resize(7 * 4096, 0);
+    bytes.copy_within(3 * 4096..4 * 4096, 5 * 4096);
+    assert_eq!(
+        fragmentation_stats(&bytes).unwrap(),
+        FragmentationStats {
+            total_sectors: 7,
+            used_sectors: 4,
+            wasted_sectors: 3,
+        }
+    );
+    assert!(matches!(
+        fragmentation_stats(&[0; 100]),
+        Err(RegionParseError::InputTooShort(100))
+    ));
+}
+
+#[test]
+fn test_storage_stats() {
+    // a gap of two sectors, a chunk given a sector more than it needs, and
+    // slots pointing into the header and past the end of the file
+    let chunk = chunk_bytes(&uniform_chunk(0, 1, "minecraft:stone"));
+    let mut bytes = region_bytes(&[(0, 0, chunk.clone()), (1, 0, chunk.clone())]);
+    bytes[4..8].copy_from_slice(&[0, 0, 5, 2]);
+    bytes[8..12].copy_from_slice(&[0, 0, 1, 1]);
+    bytes[12..16].copy_from_slice(&[0, 0, 9, 1]);
+    bytes[4096 + 16..4096 + 20].copy_from_slice(&7u32.to_be_bytes());
+    bytes.resize(7 * 4096, 0);
+    bytes.copy_within(3 * 4096..4 * 4096, 5 * 4096);
+
+    let length = chunk.len() as u32 - 4;
+    let stored = |x, sector_offset, sector_count| ChunkStorage {
+        slot: (x, 0),
+        sector_offset,
+        sector_count,
+        length: Some(length),
+        compression: Some(2),
+    };
+    let missing = |x, sector_offset| ChunkStorage {
+        length: None,
+        compression: None,
+        ..stored(x, sector_offset, 1)
+    };
+    let stats = storage_stats(&bytes).unwrap();
+    assert_eq!(
+        stats.chunks,
+        [
+            stored(0, 2, 1),
+            stored(1, 5, 2),
+            missing(2, 1),
+            missing(3, 9)
+        ]
+    );
+    assert_eq!(stats.chunks[1].padding(), Some(8192 - chunk.len()));
+    assert_eq!(stats.chunks[2].padding(), None);
+    assert_eq!(stats.allocated_sectors, 5);
+    assert_eq!(stats.data_bytes, 2 * length as u64);
+    assert_eq!(stats.padding_bytes, (3 * 4096 - 2 * chunk.len()) as u64);
+    assert_eq!(stats.implied_size, 10 * 4096);
+    assert_eq!(stats.orphan_sectors, [3, 4]);
+
+    let mut file = RegionFile::new(Cursor::new(&bytes)).unwrap();
+    assert_eq!(file.storage_stats().unwrap(), stats);
+
+    let fixture = include_bytes!("data/r.0.0.mca");
+    let stats = storage_stats(fixture).unwrap();
+    let region = Region::parse_bytes(fixture).unwrap();
+    assert_eq!(stats.chunks.len(), region.count_chunks() as usize);
+    assert!(
+        stats
+            .chunks
+            .iter()
+            .all(|chunk| chunk.compression == Some(2))
+    );
+    let stats = storage_stats(&region.to_bytes().unwrap()).unwrap();
+    assert!(stats.orphan_sectors.is_empty());
+    assert!(
+        stats
+            .chunks
+            .iter()
+            .all(|chunk| chunk.padding() < Some(4096))
+    );
+    assert!(matches!(
+        storage_stats(&[0; 100]),
+        Err(RegionParseError::InputTooShort(100))
+    ));
+}
+
+#[test]
+fn test_validate_header() {
+    assert_eq!(
+        validate_header(include_bytes!("data/r.0.0.mca")).unwrap(),
+        []
+    );
+
+    let chunk = chunk_bytes(&uniform_chunk(0, 1, "minecraft:stone"));
+    let mut bytes = region_bytes(&[(0, 0, chunk.clone()), (1, 0, chunk.clone())]);
+    assert_eq!(validate_header(&bytes).unwrap(), []);
+    // (2, 0) shares the sector of (1, 0), (3, 0) starts in the header, (4, 0)
+    // past the end, (5, 0) runs past it, and (0, 1) has no sectors
+    bytes[8..12].copy_from_slice(&[0, 0, 3, 1]);
+    bytes[12..16].copy_from_slice(&[0, 0, 1, 1]);
+    bytes[16..20].copy_from_slice(&[0, 0, 9, 1]);
+    bytes[20..24].copy_from_slice(&[0, 0, 3, 2]);
+    bytes[128..132].copy_from_slice(&[0, 0, 2, 0]);
+    // and (1, 0) claims more bytes than its sector holds
+    bytes[3 * 4096..3 * 4096 + 4].copy_from_slice(&5000u32.to_be_bytes());
+    assert_eq!(
+        validate_header(&bytes).unwrap(),
+        [
+            HeaderIssue::DeclaredLengthExceedsSectors {
+                slot: (1, 0),
+                length: 5004,
+                sectors: 1,
+            },
+            HeaderIssue::DeclaredLengthExceedsSectors {
+                slot: (2, 0),
+                length: 5004,
+                sectors: 1,
+            },
+            HeaderIssue::OffsetIntoHeader { slot: (3, 0) },
+            HeaderIssue::OffsetPastEof { slot: (4, 0) },
+            HeaderIssue::SectorsPastEof { slot: (5, 0) },
+            HeaderIssue::DeclaredLengthExceedsSectors {
+                slot: (0, 1),
+                length: chunk.len(),
+                sectors: 0,
+            },
+            HeaderIssue::SectorOverlap {
+                chunks: ((1, 0), (2, 0)),
+            },
+            HeaderIssue::SectorOverlap {
+                chunks: ((1, 0), (5, 0)),
+            },
+            HeaderIssue::SectorOverlap {
+                chunks: ((2, 0), (5, 0)),
+            },
+        ]
+    );
+
+    // none of them fail the region
+    let (region, errors) =
+        Region::parse_bytes_lossy(&bytes, &RegionParseOptions::default()).unwrap();
+    assert!(region.get_chunk(0, 0).is_some());
+    // the sectors of (0, 1) follow from its length
+    assert!(region.get_chunk(0, 1).is_some());
+    assert_eq!(errors.len(), 5);
+}
+
+#[test]
+fn test_duplicate_sectors() {
+    let chunk = chunk_bytes(&uniform_chunk(0, 1, "minecraft:stone"));
+    let mut bytes = region_bytes(&[(0, 0, chunk.clone()), (1, 0, chunk)]);
+    // (2, 0) was copied from (1, 0), (3, 0) only has a timestamp and (4, 0)
+    // only a sector count
+    bytes.copy_within(4..8, 8);
+    bytes[4096 + 12..4096 + 16].copy_from_slice(&7u32.to_be_bytes());
+    bytes[19] = 1;
+    assert_eq!(
+        validate_header(&bytes).unwrap(),
+        [
+            HeaderIssue::MissingOffset { slot: (3, 0) },
+            HeaderIssue::MissingOffset { slot: (4, 0) },
+            HeaderIssue::SectorOverlap {
+                chunks: ((1, 0), (2, 0)),
+            },
+        ]
+    );
+
+    let parse = |duplicate_sectors| {
+        let options = RegionParseOptions {
+            duplicate_sectors,
+            ..RegionParseOptions::default()
+        };
+        Region::parse_bytes_reported(&bytes, &options)
+    };
+    let (region, report) = parse(DuplicateSectors::KeepAll).unwrap();
+    assert!(report.is_clean());
+    assert_eq!(report.count_parsed(), 3);
+    assert!(matches!(report.get(3, 0), Some(SlotOutcome::Empty)));
+    assert_eq!(region.get_timestamp(3, 0), None);
+
+    let (region, report) = parse(DuplicateSectors::FirstWins).unwrap();
+    assert!(region.chunk_exists(1, 0) && !region.chunk_exists(2, 0));
+    assert!(matches!(report.get(2, 0), Some(SlotOutcome::Overlapping)));
+    assert_eq!(report.count_overlapping(), 1);
+    let (region, _) = parse(DuplicateSectors::LastWins).unwrap();
+    assert!(!region.chunk_exists(1, 0) && region.chunk_exists(2, 0));
+    assert!(matches!(
+        parse(DuplicateSectors::Error),
+        Err(RegionParseError::SectorOverlap {
+            chunks: ((1, 0), (2, 0))
+        })
+    ));
+
+    let options = RegionParseOptions {
+        duplicate_sectors: DuplicateSectors::FirstWins,
+        ..RegionParseOptions::default()
+    };
+    assert_eq!(
+        LazyRegion::parse_bytes_with(&bytes, options)
+            .unwrap()
+            .count_chunks(),
+        2
+    );
+}
+
+#[test]
+fn test_chunk_range() {
+    let chunk = chunk_bytes(&uniform_chunk(0, 1, "minecraft:stone"));
+    let mut bytes = region_bytes(&[
+        (0, 0, chunk.clone()),
+        (7, 7, chunk.clone()),
+        (8, 0, chunk.clone()),
+        (3, 9, chunk),
+    ]);
+    // the chunk at (8, 0) fails to decode, so reading it fails strict parsing
+    bytes[4 * 4096 + 5..5 * 4096].fill(0xFF);
+    let options = RegionParseOptions {
+        strict: true,
+        chunk_range: Some((0..8, 0..8)),
+        ..RegionParseOptions::default()
+    };
+
+    let region = Region::parse_bytes_with(&bytes, &options).unwrap();
+    assert_eq!(region.count_chunks(), 2);
+    assert!(region.chunk_exists(7, 7) && !region.chunk_exists(3, 9));
+    assert_eq!(Region::count_present_in_header(&bytes).unwrap(), 4);
+    let seeked = Region::from_seekable_with(Cursor::new(&bytes), &options).unwrap();
+    assert_eq!(seeked.count_chunks(), 2);
+    assert_eq!(
+        LazyRegion::parse_bytes_with(&bytes, options.clone())
+            .unwrap()
+            .count_chunks(),
+        2
+    );
+
+    let (_, report) = Region::parse_bytes_reported(&bytes, &options).unwrap();
+    assert_eq!(report.count_outside_range(), 2);
+    assert_eq!(report.count_empty(), 1020);
+    assert!(matches!(report.get(8, 0), Some(SlotOutcome::OutsideRange)));
+    assert!(
+        Region::parse_bytes_with(
+            &bytes,
+            &RegionParseOptions {
+                chunk_range: None,
+                ..options
+            }
+        )
+        .is_err()
+    );
+}
+
+#[test]
+fn test_intern_blocks() {
+    let bytes = include_bytes!("data/r.0.0.mca");
+    let options = RegionParseOptions {
+        intern_blocks: true,
+        ..Default::default()
+    };
+    let interned = Region::parse_bytes_with(bytes, &options).unwrap();
+    let region = Region::parse_bytes(bytes).unwrap();
+    assert_eq!(interned, region);
+
+    // the bottom of the world is bedrock everywhere
+    let bottom = |region: &Region, x, z| {
+        let chunk = region.get_chunk(x, z).unwrap();
+        chunk.get(0, chunk.get_y_range().start, 0).unwrap() as *const _
+    };
+    assert_eq!(bottom(&interned, 0, 0), bottom(&interned, 5, 9));
+    assert_ne!(bottom(&region, 0, 0), bottom(&region, 5, 9));
+
+    let mut interner = BlockInterner::new();
+    let first = region.get_chunk_raw(0, 0).unwrap();
+    let first = first
+        .decode_interned(&Default::default(), &mut interner)
+        .unwrap();
+    let distinct = interner.len();
+    let second = region.get_chunk_raw(5, 9).unwrap();
+    let second = second
+        .decode_interned(&Default::default(), &mut interner)
+        .unwrap();
+    assert!(interner.len() >= distinct);
+    assert_eq!(
+        first.get(0, -64, 0).unwrap() as *const _,
+        second.get(0, -64, 0).unwrap() as *const _
+    );
+}
+
+#[test]
+fn test_open() {
+    let data = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data");
+    let region = Region::open(data.join("r.0.0.mca")).unwrap();
+    assert_eq!(
+        region,
+        Region::parse_bytes(include_bytes!("data/r.0.0.mca")).unwrap()
+    );
+
+    assert!(matches!(
+        Region::open(data.join("missing.mca")),
+        Err(RegionOpenError::Io(err)) if err.kind() == ErrorKind::NotFound
+    ));
+    assert!(matches!(
+        Region::open(data.join("lz4_chunk.bin")),
+        Err(RegionOpenError::Parse(RegionParseError::InputInvalidSize(
+            13184
+        )))
+    ));
+
+    // an empty file, which memory maps have to treat specially
+    let dir = temp_dir("open");
+    let path = dir.join("r.0.0.mca");
+    fs::write(&path, []).unwrap();
+    assert_eq!(
+        format!("{:?}", Region::open(&path)),
+        format!(
+            "{:?}",
+            Region::parse_bytes(&[]).map_err(RegionOpenError::from)
+        )
+    );
+    fs::remove_dir_all(dir).unwrap();
+}
+
+/// Hands out at most 1000 bytes per read, and counts them.
+struct Trickle<R> {
+    inner: R,
+    read: usize,
+}
+
+impl<R: Read> Read for Trickle<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let len = buf.len().min(1000);
+        let read = self.inner.read(&mut buf[..len])?;
+        self.read += read;
+        Ok(read)
+    }
+}
+
+impl<R: Seek> Seek for Trickle<R> {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        self.inner.seek(pos)
+    }
+}
+
+#[test]
+fn test_from_reader() {
+    let bytes = include_bytes!("data/r.0.0.mca");
+    let expected = Region::parse_bytes(bytes).unwrap();
+    let mut reader = Trickle {
+        inner: &bytes[..],
+        read: 0,
+    };
+    assert_eq!(Region::from_reader(&mut reader).unwrap(), expected);
+    assert_eq!(reader.read, bytes.len());
+
+    assert!(matches!(
+        Region::from_reader(&bytes[..4096]),
+        Err(RegionOpenError::Parse(RegionParseError::InputTooShort(
+            4096
+        )))
+    ));
+}
+
+#[test]
+fn test_parse_maybe_compressed() {
+    let bytes = include_bytes!("data/r.0.0.mca");
+    let expected = Region::parse_bytes(bytes).unwrap();
+    let gzip = |bytes: &[u8]| {
+        let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(bytes).unwrap();
+        encoder.finish().unwrap()
+    };
+    let wrapped = gzip(bytes);
+    assert_eq!(Region::parse_maybe_compressed(&wrapped).unwrap(), expected);
+    assert_eq!(Region::parse_maybe_compressed(bytes).unwrap(), expected);
+    assert_eq!(Region::from_reader(&wrapped[..]).unwrap(), expected);
+
+    let dir = temp_dir("maybe_compressed");
+    fs::write(dir.join("r.0.0.mca.gz"), &wrapped).unwrap();
+    assert_eq!(Region::open(dir.join("r.0.0.mca.gz")).unwrap(), expected);
+
+    // the size checks apply to what's inside
+    assert!(matches!(
+        Region::parse_maybe_compressed(&gzip(&bytes[..8192 + 100])),
+        Err(RegionParseError::InputInvalidSize(8292))
+    ));
+    assert!(matches!(
+        Region::parse_maybe_compressed(&wrapped[..wrapped.len() / 2]),
+        Err(RegionParseError::GzipFailed(_))
+    ));
+    assert!(matches!(
+        Region::from_reader(&[0x1F, 0x8B, 0, 0][..]),
+        Err(RegionOpenError::Parse(RegionParseError::GzipFailed(_)))
+    ));
+}
+
+#[test]
+fn test_from_seekable_skips_holes() {
+    let chunk = chunk_bytes(&uniform_chunk(-4, 2, "minecraft:stone"));
+    let mut bytes = region_bytes(&[(0, 0, chunk.clone()), (3, 1, chunk)]);
+    let used = bytes.len();
+    // unused sectors, like a fragmented file would have
+    bytes.resize(used + 16 * 4096, 0xAB);
+
+    let mut reader = Trickle {
+        inner: Cursor::new(&bytes),
+        read: 0,
+    };
+    let region = Region::from_seekable(&mut reader).unwrap();
+    assert_eq!(region, Region::parse_bytes(&bytes).unwrap());
+    assert_eq!(region.count_chunks(), 2);
+    assert_eq!(reader.read, used);
+
+    assert!(matches!(
+        Region::from_seekable(Cursor::new(&bytes[..used + 100])),
+        Err(RegionOpenError::Parse(RegionParseError::InputInvalidSize(
+            _
+        )))
+    ));
+}
+
+#[test]
+fn test_iter_chunks() {
+    let chunk = |block| chunk_bytes(&uniform_chunk(-4, 1, block));
+    let bytes = region_bytes(&[
+        (3, 1, chunk("minecraft:dirt")),
+        (0, 0, chunk("minecraft:stone")),
+        (31, 31, chunk("minecraft:sand")),
+    ]);
+    let region = Region::parse_bytes(&bytes).unwrap();
+
+    let slots: Vec<_> = region.iter_chunks().map(|(x, z, _)| (x, z)).collect();
+    assert_eq!(slots, [(0, 0), (3, 1), (31, 31)]);
+    assert_eq!(region.iter_chunks().count(), region.count_chunks() as usize);
+    for (x, z, chunk) in region.iter_chunks() {
+        assert_eq!(Some(chunk), region.get_chunk(x, z));
+    }
+
+    let expected: Vec<_> = region
+        .iter_chunks()
+        .map(|(x, z, chunk)| (x, z, chunk.clone()))
+        .collect();
+    assert_eq!(region.clone().into_chunks().collect::<Vec<_>>(), expected);
+    assert_eq!(region.clone().into_chunks().size_hint(), (3, Some(3)));
+
+    let mut region = region;
+    assert_eq!(region.remove_chunk(3, 1).as_ref(), Some(&expected[1].2));
+    let mut moved = Vec::new();
+    for (x, z, chunk) in region {
+        moved.push((x, z, chunk));
+    }
+    assert_eq!(moved, [expected[0].clone(), expected[2].clone()]);
+}
+
+#[test]
+fn test_region_size() {
+    // slots are only stored for chunks that are there
+    assert!(size_of::<Region>() <= 128);
+    let mut regions = vec![Region::empty(); 64];
+    regions[5]
+        .set_chunk(
+            4,
+            2,
+            parse_chunk(&chunk_bytes(&uniform_chunk(0, 1, "minecraft:stone"))).unwrap(),
+        )
+        .unwrap();
+    assert_eq!(regions.iter().map(Region::count_chunks).sum::<u16>(), 1);
+    assert_eq!(regions.remove(5).get_timestamp(4, 2), Some(0));
+}
+
+#[test]
+fn test_region_block_counts() {
+    let region = Region::parse_bytes(include_bytes!("data/r.0.0.mca")).unwrap();
+    let counts = region.block_counts();
+    let sections: usize = region
+        .iter_chunks()
+        .map(|(_, _, chunk)| chunk.sections().len())
+        .sum();
+    assert_eq!(counts.values().sum::<u32>() as usize, sections * 4096);
+
+    let stone = Block::new(string("minecraft:stone"), None);
+    let expected: u32 = region
+        .iter_chunks()
+        .filter_map(|(_, _, chunk)| chunk.block_counts().get(&stone).copied())
+        .sum();
+    assert!(expected > 0);
+    assert_eq!(counts[&stone], expected);
+}
+
+#[test]
+fn test_count_blocks() {
+    let region = Region::parse_bytes(include_bytes!("data/r.0.0.mca")).unwrap();
+    let all = region.count_blocks(None);
+    let expected: HashMap<Block, u64> = (region.block_counts().into_iter())
+        .map(|(block, count)| (block.clone(), count as u64))
+        .collect();
+    assert_eq!(all, expected);
+
+    let upright: BlockSelector = "oak_log[axis=y]".parse().unwrap();
+    let counts = region.count_blocks(Some(&upright));
+    assert_eq!(counts.len(), 1);
+    let (log, &count) = counts.iter().next().unwrap();
+    assert_eq!(log.get_name(), "minecraft:oak_log");
+    assert_eq!(log.get_property("axis"), Some("y"));
+    assert_eq!(all[log], count);
+
+    // any axis, counted together
+    let options = BlockCountOptions { by_name: true };
+    let logs = region.count_blocks_with(Some(&BlockSelector::new("oak_log")), &options);
+    let oak_log = Block::new(string("minecraft:oak_log"), None);
+    assert_eq!(logs.len(), 1);
+    let expected: u64 = (all.iter())
+        .filter(|(block, _)| block.get_name() == "minecraft:oak_log")
+        .map(|(_, count)| count)
+        .sum();
+    assert!(expected > count);
+    assert_eq!(logs[&oak_log], expected);
+    let names = region.count_blocks_with(None, &options);
+    assert_eq!(names[&oak_log], expected);
+    assert_eq!(names.values().sum::<u64>(), all.values().sum::<u64>());
+
+    let cherry = BlockSelector::new("minecraft:cherry_log");
+    assert!(region.count_blocks(Some(&cherry)).is_empty());
+}
+
+#[cfg(feature = "rayon")]
+#[test]
+fn test_par_count_blocks() {
+    let region = Region::parse_bytes(include_bytes!("data/r.0.0.mca")).unwrap();
+    assert_eq!(region.par_count_blocks(None), region.count_blocks(None));
+    let logs = BlockSelector::new("oak_log");
+    let options = BlockCountOptions { by_name: true };
+    assert_eq!(
+        region.par_count_blocks_with(Some(&logs), &options),
+        region.count_blocks_with(Some(&logs), &options)
+    );
+}
+
+#[test]
+fn test_region_biomes() {
+    let region = Region::parse_bytes(include_bytes!("data/r.0.0.mca")).unwrap();
+    let counts = region.biome_counts();
+    let sections: usize = region
+        .iter_chunks()
+        .map(|(_, _, chunk)| chunk.sections().len())
+        .sum();
+    assert_eq!(counts.values().sum::<u32>() as usize, sections * 64);
+    assert!(counts[&string("minecraft:forest")] > 0);
+
+    let found = region.find_biome("minecraft:forest");
+    assert_eq!(found.len(), counts[&string("minecraft:forest")] as usize);
+    for (x, y, z) in found {
+        assert_eq!((x % 4, y.rem_euclid(4), z % 4), (2, 2, 2));
+        let chunk = region
+            .get_chunk((x / 16) as usize, (z / 16) as usize)
+            .unwrap();
+        let biome = chunk.get_biome((x % 16) as u8, y, (z % 16) as u8).unwrap();
+        assert_eq!(biome, "minecraft:forest");
+    }
+    assert!(region.find_biome("minecraft:cherry_grove").is_empty());
+}
+
+#[test]
+fn test_region_find_blocks() {
+    let region = Region::parse_bytes(include_bytes!("data/r.0.0.mca")).unwrap();
+    let is_diamond = |block: &Block| block.get_name().ends_with("diamond_ore");
+    let mut expected: Vec<_> = region
+        .iter_blocks_non_air()
+        .filter(|(.., block)| is_diamond(block))
+        .map(|(x, y, z, _)| (x, y, z))
+        .collect();
+    assert!(!expected.is_empty());
+
+    let mut found = region.find_blocks(is_diamond);
+    found.sort();
+    expected.sort();
+    assert_eq!(found, expected);
+    for (x, y, z) in found {
+        let (cx, cz) = (x.div_euclid(16), z.div_euclid(16));
+        let chunk = region
+            .iter_chunks()
+            .map(|(_, _, chunk)| chunk)
+            .find(|chunk| (chunk.x_pos(), chunk.z_pos()) == (cx, cz))
+            .unwrap();
+        assert!(is_diamond(chunk.get_world(x, y, z).unwrap()));
+    }
+}
+
+#[test]
+fn test_iter_blocks_world() {
+    let stone = chunk_bytes(&uniform_chunk(-1, 2, "minecraft:stone"));
+    let air = chunk_bytes(&uniform_chunk(0, 1, "minecraft:air"));
+    let bytes = region_bytes(&[(31, 0, stone), (0, 31, air)]);
+    let mut region = Region::parse_bytes(&bytes).unwrap();
+    region.set_position(-1, -2);
+
+    let blocks: Vec<_> = region.iter_blocks().collect();
+    assert_eq!(blocks.len(), 3 * 4096);
+    let (stone, air): (Vec<_>, Vec<_>) = blocks
+        .into_iter()
+        .partition(|(.., block)| block.get_name() == "minecraft:stone");
+    let bounds = |blocks: &[(i32, i32, i32, &Block)]| {
+        let (xs, ys, zs) = (
+            blocks.iter().map(|block| block.0),
+            blocks.iter().map(|block| block.1),
+            blocks.iter().map(|block| block.2),
+        );
+        let (x, y, z) = (
+            xs.clone().min().unwrap(),
+            ys.clone().min().unwrap(),
+            zs.clone().min().unwrap(),
+        );
+        (
+            (x, y, z),
+            (xs.max().unwrap(), ys.max().unwrap(), zs.max().unwrap()),
+        )
+    };
+    // region -1 starts at chunk -32, so slot 31 is chunk -1
+    assert_eq!(
+        bounds(&stone),
+        ((-16, -16, -64 * 16), (-1, 15, -63 * 16 - 1))
+    );
+    assert_eq!(
+        bounds(&air),
+        ((-32 * 16, 0, -33 * 16), (-31 * 16 - 1, 15, -32 * 16 - 1))
+    );
+
+    let upper =
+        region.iter_blocks_in_sections(|section_y, section| section_y >= 0 && !section.is_empty());
+    assert!(upper.map(|(_, y, ..)| y).eq((0..16).flat_map(|y| [y; 256])));
+}
+
+#[test]
+fn test_region_coords_from_filename() {
+    assert_eq!(region_coords_from_filename("r.0.0.mca"), Some((0, 0)));
+    assert_eq!(region_coords_from_filename("r.-3.7.mca"), Some((-3, 7)));
+    assert_eq!(
+        region_coords_from_filename("r.-2147483648.12.mca"),
+        Some((i32::MIN, 12))
+    );
+    for name in [
+        "r.0.mca",
+        "r.0.0.0.mca",
+        "r.+1.0.mca",
+        "r.-.0.mca",
+        "r..0.mca",
+        "r.1.x.mca",
+        "r.0.0.mcr",
+        "c.0.0.mca",
+        "region/r.0.0.mca",
+        "r.2147483648.0.mca",
+    ] {
+        assert_eq!(region_coords_from_filename(name), None, "{name}");
+    }
+}
+
+#[test]
+fn test_region_position() {
+    let bytes = include_bytes!("data/r.0.0.mca");
+    let region = Region::parse_bytes(bytes).unwrap();
+    assert_eq!(region.position(), None);
+    assert_eq!(region.chunk_pos(5, 9), None);
+    // without a position, the chunks' own positions are used
+    assert!(
+        region
+            .iter_chunks_world()
+            .all(|(pos, chunk)| pos == chunk.get_pos())
+    );
+
+    let mut moved = region.clone();
+    moved.set_position(-3, 7);
+    assert_eq!(moved.position(), Some((-3, 7)));
+    assert_eq!(moved.chunk_pos(5, 9), Some(ChunkPos::new(-91, 233)));
+    assert_eq!(moved.chunk_pos(32, 0), None);
+    let mut far = region.clone();
+    far.set_position(i32::MAX / 32, i32::MIN / 32);
+    assert_eq!(
+        far.chunk_pos(31, 0),
+        Some(ChunkPos::new(i32::MAX, i32::MIN))
+    );
+    far.set_position(i32::MAX / 32 + 1, 0);
+    assert_eq!(far.chunk_pos(0, 0), None);
+    let (pos, _) = moved.iter_chunks_world().next().unwrap();
+    assert_eq!(pos, ChunkPos::new(-96, 224));
+
+    let is_log = |block: &Block| block.get_name().ends_with("_log");
+    let found = region.find_blocks(is_log);
+    assert!(!found.is_empty());
+    let shifted: Vec<_> = found
+        .iter()
+        .map(|&(x, y, z)| (x - 96 * 16, y, z + 224 * 16))
+        .collect();
+    assert_eq!(moved.find_blocks(is_log), shifted);
+
+    assert_eq!(Region::parse_bytes_at(bytes, -3, 7).unwrap(), moved);
+    let built = RegionBuilder::new().position(2, 1).build().unwrap();
+    assert_eq!(built.position(), Some((2, 1)));
+}
+
+#[test]
+fn test_write_to_file() {
+    let dir = temp_dir("write_region");
+    let path = dir.join("r.0.0.mca");
+    let region = three_chunk_region();
+    region.write_to_file(&path).unwrap();
+    assert_eq!(fs::read(&path).unwrap(), region.to_bytes().unwrap());
+    assert_eq!(Region::open(&path).unwrap(), region);
+
+    // an existing file is only replaced when asked to
+    let empty = Region::parse_bytes(&[0; 8192]).unwrap();
+    let error = empty.write_to_file(&path).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::AlreadyExists);
+    assert_eq!(Region::open(&path).unwrap(), region);
+
+    let original = fs::metadata(&path).unwrap().modified().unwrap();
+    let options = |modified| FileWriteOptions {
+        overwrite: true,
+        modified,
+        ..FileWriteOptions::default()
+    };
+    empty
+        .write_to_file_with(&path, &options(ModifiedTime::KeepOriginal))
+        .unwrap();
+    assert_eq!(Region::open(&path).unwrap().count_chunks(), 0);
+    assert_eq!(fs::metadata(&path).unwrap().modified().unwrap(), original);
+
+    region
+        .write_to_file_with(&path, &options(ModifiedTime::NewestChunk))
+        .unwrap();
+    assert_eq!(
+        fs::metadata(&path).unwrap().modified().unwrap(),
+        UNIX_EPOCH + Duration::from_secs(300)
+    );
+    // no temporary files are left behind
+    assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_timestamp_times() {
+    let mut region = three_chunk_region();
+    let at = |secs| UNIX_EPOCH + Duration::from_secs(secs);
+    assert_eq!(region.get_timestamp_time(5, 2), Some(at(200)));
+    assert_eq!(region.get_timestamp_time(7, 3), None);
+
+    let slots = |region: &Region, since| {
+        (region.chunks_modified_since(since))
+            .map(|(x, z, _)| (x, z))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(slots(&region, at(200)), [(5, 2), (31, 31)]);
+    // a chunk from the same second may still have been written after
+    assert_eq!(
+        slots(&region, at(200) + Duration::from_millis(500)),
+        [(5, 2), (31, 31)]
+    );
+    assert_eq!(slots(&region, at(301)), []);
+    assert_eq!(slots(&region, UNIX_EPOCH - Duration::from_secs(1)).len(), 3);
+
+    assert!(region.set_timestamp(0, 0, at(1000)));
+    assert!(!region.set_timestamp(7, 3, at(1000)));
+    assert_eq!(slots(&region, at(1000)), [(0, 0)]);
+    assert_eq!(region.get_timestamp(7, 3), None);
+    assert!(!region.set_timestamp(32, 0, at(0)));
+
+    assert!(region.set_timestamp(5, 2, at(1 << 40)));
+    assert_eq!(region.get_timestamp(5, 2), Some(u32::MAX));
+    assert!(region.set_timestamp(5, 2, UNIX_EPOCH - Duration::from_secs(10)));
+    assert_eq!(region.get_timestamp(5, 2), Some(0));
+
+    let reparsed = Region::parse_bytes(&region.to_bytes().unwrap()).unwrap();
+    assert_eq!(reparsed.get_timestamp_time(0, 0), Some(at(1000)));
+}
+
+#[test]
+fn test_chunks_by_timestamp() {
+    let mut region = three_chunk_region();
+    let at = |secs| UNIX_EPOCH + Duration::from_secs(secs);
+    let slots = |region: &Region, order| {
+        (region.iter_chunks_by_timestamp(order))
+            .map(|(x, z, timestamp, _)| (x, z, timestamp))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        slots(&region, TimestampOrder::NewestFirst),
+        [(31, 31, 300), (5, 2, 200), (0, 0, 100)]
+    );
+    assert_eq!(
+        slots(&region, TimestampOrder::OldestFirst),
+        [(0, 0, 100), (5, 2, 200), (31, 31, 300)]
+    );
+    assert_eq!(region.newest_timestamp(), Some(300));
+    assert_eq!(region.oldest_timestamp(), Some(100));
+
+    // ties keep the slot order either way
+    assert!(region.set_timestamp(0, 0, at(300)));
+    assert_eq!(
+        slots(&region, TimestampOrder::NewestFirst),
+        [(0, 0, 300), (31, 31, 300), (5, 2, 200)]
+    );
+    assert_eq!(
+        slots(&region, TimestampOrder::OldestFirst),
+        [(5, 2, 200), (0, 0, 300), (31, 31, 300)]
+    );
+    let (x, z, _, chunk) = region
+        .iter_chunks_by_timestamp(TimestampOrder::default())
+        .next()
+        .unwrap();
+    assert!(std::ptr::eq(chunk, region.get_chunk(x, z).unwrap()));
+
+    let empty = Region::parse_bytes(&region_bytes(&[])).unwrap();
+    assert_eq!(
+        empty
+            .iter_chunks_by_timestamp(TimestampOrder::OldestFirst)
+            .count(),
+        0
+    );
+    assert_eq!(empty.newest_timestamp(), None);
+    assert_eq!(empty.oldest_timestamp(), None);
+}
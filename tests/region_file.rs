@@ -0,0 +1,162 @@
+mod common;
+
+use std::{
+    collections::HashMap,
+    fs,
+    io::{self, Cursor},
+    path::Path,
+};
+
+use common::{chunk_bytes, region_bytes, string, temp_dir, uniform_chunk};
+use mca_rs::{
+    ChunkTooLarge, RegionFile,
+    region::{Region, RegionOpenError, RegionParseError, RegionParseOptions, StatusFilter},
+};
+use nbt_rs::types::NbtTag;
+
+#[test]
+fn test_read_chunk_matches_region() {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data/r.0.0.mca");
+    let region = Region::open(&path).unwrap();
+    let mut file = RegionFile::open(&path).unwrap();
+
+    for (x, z) in [(0, 0), (5, 9), (17, 3), (31, 31)] {
+        assert_eq!(
+            file.read_chunk(x, z).unwrap().as_ref(),
+            region.get_chunk(x, z)
+        );
+        assert_eq!(
+            file.read_raw_chunk(x, z).unwrap().as_ref(),
+            region.get_chunk_raw(x, z)
+        );
+    }
+    assert!(file.read_chunk(32, 0).unwrap().is_none());
+    assert!(file.read_raw_chunk(0, 32).unwrap().is_none());
+}
+
+#[test]
+fn test_read_chunk_errors() {
+    let mut garbage = chunk_bytes(&uniform_chunk(-4, 1, "minecraft:stone"));
+    garbage[5..].fill(0xAB);
+    let bytes = region_bytes(&[(2, 1, garbage)]);
+
+    let mut file = RegionFile::new(Cursor::new(&bytes)).unwrap();
+    assert!(file.read_raw_chunk(2, 1).unwrap().is_some());
+    assert!(matches!(
+        file.read_chunk(2, 1),
+        Err(RegionOpenError::Parse(RegionParseError::ChunkError {
+            x: 2,
+            z: 1,
+            ..
+        }))
+    ));
+    assert!(file.read_chunk(1, 2).unwrap().is_none());
+
+    assert!(matches!(
+        RegionFile::new(Cursor::new(&bytes[..100])),
+        Err(RegionOpenError::Parse(RegionParseError::InputTooShort(100)))
+    ));
+}
+
+#[test]
+fn test_read_chunk_with_options() {
+    let mut fields: HashMap<_, _> = uniform_chunk(-4, 1, "minecraft:stone").into();
+    fields.insert(
+        string("Status"),
+        NbtTag::String(string("minecraft:features")),
+    );
+    let bytes = region_bytes(&[(0, 0, chunk_bytes(&fields.into()))]);
+    let options = RegionParseOptions {
+        status_filter: StatusFilter::OnlyFull,
+        ..Default::default()
+    };
+
+    let mut file = RegionFile::with_options(Cursor::new(&bytes), options).unwrap();
+    assert!(file.read_chunk(0, 0).unwrap().is_none());
+    assert!(file.read_raw_chunk(0, 0).unwrap().is_some());
+}
+
+#[test]
+fn test_defragment() {
+    let original = include_bytes!("data/r.0.0.mca");
+    let region = Region::parse_bytes(original).unwrap();
+
+    // move a few chunks to the end of the file, first into more sectors than
+    // they need, then again into fewer, leaving their old sectors unused
+    let mut bytes = original.to_vec();
+    for (x, z) in [(0, 0), (5, 9), (17, 3), (5, 9)] {
+        let at = (x + z * 32) * 4;
+        let location = u32::from_be_bytes(bytes[at..at + 4].try_into().unwrap());
+        let (offset, sectors) = ((location >> 8) as usize, (location & 0xFF) as usize);
+        let data = bytes[offset * 4096..(offset + sectors) * 4096].to_vec();
+        for extra in [2, 0] {
+            let moved = bytes.len() / 4096;
+            bytes.extend_from_slice(&data);
+            bytes.resize(bytes.len() + extra * 4096, 0);
+            let location = (moved as u32) << 8 | (sectors + extra) as u32;
+            bytes[at..at + 4].copy_from_slice(&location.to_be_bytes());
+        }
+    }
+    let dir = temp_dir("defragment");
+    let path = dir.join("r.0.0.mca");
+    fs::write(&path, &bytes).unwrap();
+
+    let mut file = RegionFile::open(&path).unwrap();
+    let report = file.defragment().unwrap();
+    assert_eq!(report.chunks, region.count_chunks() as usize);
+    assert_eq!(report.old_len, bytes.len() as u64);
+    assert_eq!(report.new_len, fs::metadata(&path).unwrap().len());
+    assert!(report.reclaimed() >= 4 * 4096);
+
+    // the reopened file reads the same chunks
+    for (x, z) in [(0, 0), (5, 9), (17, 3), (31, 31)] {
+        assert_eq!(
+            file.read_chunk(x, z).unwrap().as_ref(),
+            region.get_chunk(x, z)
+        );
+    }
+    let defragmented = Region::open(&path).unwrap();
+    assert_eq!(defragmented.count_chunks(), region.count_chunks());
+    for (x, z, chunk) in region.iter_chunks() {
+        assert_eq!(defragmented.get_chunk(x, z), Some(chunk));
+        assert_eq!(defragmented.get_timestamp(x, z), region.get_timestamp(x, z));
+    }
+    // nothing is left between the chunks
+    let header = fs::read(&path).unwrap();
+    let used: usize = (0..1024).map(|index| header[index * 4 + 3] as usize).sum();
+    assert_eq!(report.new_len as usize, (used + 2) * 4096);
+
+    // a file opened without its path can't be replaced
+    assert_eq!(
+        RegionFile::new(fs::File::open(&path).unwrap())
+            .unwrap()
+            .defragment()
+            .unwrap_err()
+            .kind(),
+        io::ErrorKind::Unsupported
+    );
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_defragment_chunk_too_large() {
+    // a chunk without sectors in the header, longer than 255 of them
+    let mut bytes = region_bytes(&[(0, 0, chunk_bytes(&uniform_chunk(0, 1, "minecraft:stone")))]);
+    let at = bytes.len();
+    bytes[4..8].copy_from_slice(&[0, 0, (at >> 12) as u8, 0]);
+    bytes.extend((256 * 4096 + 1u32).to_be_bytes());
+    bytes.push(3);
+    bytes.resize(at + 257 * 4096, 0);
+    let dir = temp_dir("defragment_too_large");
+    let path = dir.join("r.0.0.mca");
+    fs::write(&path, &bytes).unwrap();
+
+    let mut file = RegionFile::open(&path).unwrap();
+    let error = file.defragment().unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    assert!(error.get_ref().unwrap().is::<ChunkTooLarge>());
+    // the file is left alone and can still be read
+    assert_eq!(fs::read(&path).unwrap(), bytes);
+    assert!(file.read_chunk(0, 0).unwrap().is_some());
+    fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,433 @@
+mod common;
+
+use std::{
+    collections::HashMap,
+    io::{Read, Write},
+};
+
+use common::{compound, compound_list, string};
+use flate2::{Compression, read::GzDecoder, write::GzEncoder};
+use mca_rs::{
+    Axis, ChunkPos,
+    block::Block,
+    chunk::Limit,
+    legacy::UNKNOWN_LEGACY_BLOCK,
+    region::Region,
+    schematic::{PasteOptions, SchemError, Schematic, import_legacy, import_sponge},
+};
+use nbt_rs::{
+    get_field, parse_nbt, serialize_nbt,
+    types::{NbtCompound, NbtTag},
+};
+
+fn properties(block: &Block) -> HashMap<String, String> {
+    let Some(properties) = block.get_properties().clone() else {
+        return HashMap::new();
+    };
+    HashMap::from(properties)
+        .into_iter()
+        .map(|(key, value)| match value {
+            NbtTag::String(value) => (key.to_string(), value.to_string()),
+            other => panic!("unexpected property value {other:?}"),
+        })
+        .collect()
+}
+
+#[test]
+fn test_import_legacy_schematic() {
+    let schematic = import_legacy(include_bytes!("data/legacy.schematic")).unwrap();
+    assert_eq!(schematic.get_size(), (3, 2, 2));
+    assert!(schematic.get_block(3, 0, 0).is_none());
+
+    let name = |x, y, z| schematic.get_block(x, y, z).unwrap().get_name().to_string();
+    assert_eq!(name(0, 0, 0), "minecraft:stone");
+    assert_eq!(name(0, 0, 1), "minecraft:spruce_planks");
+    assert_eq!(name(2, 1, 1), "minecraft:air");
+
+    let stairs = schematic.get_block(1, 0, 0).unwrap();
+    assert_eq!(stairs.get_name().to_string(), "minecraft:oak_stairs");
+    assert_eq!(properties(stairs)["facing"], "south");
+    assert_eq!(properties(stairs)["half"], "bottom");
+    let upside_down = schematic.get_block(1, 1, 1).unwrap();
+    assert_eq!(properties(upside_down)["half"], "top");
+
+    let chest = schematic.get_block(2, 0, 0).unwrap();
+    assert_eq!(chest.get_name().to_string(), "minecraft:chest");
+    assert_eq!(properties(chest)["facing"], "south");
+
+    // id 300 only exists through AddBlocks and has no modern equivalent
+    let added = schematic.get_block(0, 1, 0).unwrap();
+    assert_eq!(added.get_name().to_string(), UNKNOWN_LEGACY_BLOCK);
+    assert_eq!(properties(added)["id"], "300");
+    assert_eq!(properties(added)["data"], "1");
+}
+
+#[test]
+fn test_import_legacy_tile_entities() {
+    let schematic = import_legacy(include_bytes!("data/legacy.schematic")).unwrap();
+    assert_eq!(schematic.get_block_entities().len(), 1);
+
+    let chest = schematic.get_block_entity(2, 0, 0).unwrap();
+    assert_eq!(
+        get_field!(chest, "id", as_string).unwrap().to_string(),
+        "Chest"
+    );
+    assert_eq!(
+        get_field!(chest, "Items", as_list.as_compound)
+            .unwrap()
+            .len(),
+        1
+    );
+    assert!(schematic.get_block_entity(0, 0, 0).is_none());
+}
+
+#[test]
+fn test_import_legacy_invalid() {
+    assert!(matches!(
+        import_legacy(b"definitely not gzip"),
+        Err(SchemError::DecompressionFailed(_))
+    ));
+}
+
+#[test]
+fn test_import_limits() {
+    // 17 MiB of zeros, past the default limit of chunks
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::best());
+    for _ in 0..17 {
+        encoder.write_all(&[0; 1 << 20]).unwrap();
+    }
+    let bomb = encoder.finish().unwrap();
+    assert!(matches!(
+        import_legacy(&bomb),
+        Err(SchemError::DecompressedTooLarge { limit }) if limit == 16 << 20
+    ));
+    assert!(matches!(
+        import_sponge(&bomb),
+        Err(SchemError::DecompressedTooLarge { .. })
+    ));
+
+    let mut nested = NbtTag::Compound(compound([]));
+    for _ in 0..600 {
+        nested = NbtTag::Compound(compound([("a", nested)]));
+    }
+    let deep = gzip(&compound([("a", nested)]));
+    assert!(matches!(
+        import_legacy(&deep),
+        Err(SchemError::LimitExceeded(Limit::Depth))
+    ));
+    assert!(matches!(
+        import_sponge(&deep),
+        Err(SchemError::LimitExceeded(Limit::Depth))
+    ));
+}
+
+#[test]
+fn test_extract_chunk() {
+    let region = Region::parse_bytes(include_bytes!("data/r.0.0.mca")).unwrap();
+    let chunk = region.get_chunk(5, 0).unwrap();
+    let schematic = chunk.extract(2..12, -45..-42, 0..16).unwrap();
+    assert_eq!(schematic.get_size(), (10, 3, 16));
+    assert_eq!(schematic.data_version(), chunk.data_version());
+    for y in 0..3 {
+        for z in 0..16 {
+            for x in 0..10 {
+                assert_eq!(
+                    schematic.get_block(x, y, z),
+                    chunk.get(x as u8 + 2, y as i32 - 45, z as u8)
+                );
+            }
+        }
+    }
+
+    // the catalyst is at (84, -43, 3), the sensor and shrieker at y -42 are
+    // left out
+    assert_eq!(schematic.get_block_entities().len(), 1);
+    let catalyst = schematic.get_block_entity(2, 2, 3).unwrap();
+    assert_eq!(
+        get_field!(catalyst, "id", as_string).unwrap().to_string(),
+        "minecraft:sculk_catalyst"
+    );
+    assert_eq!(get_field!(catalyst, "x", as_int), Some(&2));
+    assert_eq!(get_field!(catalyst, "y", as_int), Some(&2));
+
+    let err = chunk.extract(0..17, 0..1, 0..1).unwrap_err();
+    assert!(matches!(err, SchemError::OutOfBounds(err) if err.axis == Axis::X));
+    let err = chunk.extract(0..1, 300..330, 0..1).unwrap_err();
+    assert!(matches!(err, SchemError::OutOfBounds(err) if err.axis == Axis::Y));
+    assert_eq!(
+        chunk.extract(0..0, 0..10, 0..1).unwrap().get_size(),
+        (0, 10, 1)
+    );
+}
+
+#[test]
+fn test_extract_region() {
+    let region = Region::parse_bytes(include_bytes!("data/r.0.0.mca")).unwrap();
+    let schematic = region.extract(84..107, -45..-35, 0..8).unwrap();
+    assert_eq!(schematic.get_size(), (23, 10, 8));
+    for (x, y, z) in [(0, 0, 0), (11, 3, 7), (12, 9, 2), (22, 5, 5)] {
+        assert_eq!(
+            schematic.get_block(x, y, z),
+            region.get_chunk((x as usize + 84) / 16, 0).unwrap().get(
+                (x as u8 + 84) % 16,
+                y as i32 - 45,
+                z as u8
+            )
+        );
+    }
+    assert_eq!(schematic.get_block_entities().len(), 3);
+    assert!(schematic.get_block_entity(6, 3, 6).is_some());
+
+    let err = region.extract(500..520, 0..1, 500..512).unwrap_err();
+    assert!(matches!(
+        err,
+        SchemError::MissingChunk(ChunkPos { x: 31, z: 31 })
+    ));
+    // chunks are looked up by their offset, but have to be in the box
+    let err = region.extract(-16..0, 0..1, 0..1).unwrap_err();
+    assert!(matches!(
+        err,
+        SchemError::MissingChunk(ChunkPos { x: -1, z: 0 })
+    ));
+}
+
+#[test]
+fn test_sponge_export() {
+    let region = Region::parse_bytes(include_bytes!("data/r.0.0.mca")).unwrap();
+    let schematic = region.extract(84..107, -45..-35, 0..8).unwrap();
+    let mut nbt = Vec::new();
+    GzDecoder::new(&schematic.to_bytes()[..])
+        .read_to_end(&mut nbt)
+        .unwrap();
+    let (_, root) = parse_nbt(&nbt).unwrap();
+    let root = get_field!(root, "Schematic", as_compound).unwrap();
+    assert_eq!(get_field!(root, "Version", as_int), Some(&3));
+    assert_eq!(get_field!(root, "Width", as_short), Some(&23));
+    assert_eq!(get_field!(root, "Height", as_short), Some(&10));
+    assert_eq!(get_field!(root, "Length", as_short), Some(&8));
+
+    let blocks = get_field!(root, "Blocks", as_compound).unwrap();
+    let palette = get_field!(blocks, "Palette", as_compound).unwrap().clone();
+    let palette: HashMap<i32, String> = Vec::from(palette)
+        .into_iter()
+        .map(|(name, id)| (*id.as_int().unwrap(), name.to_string()))
+        .collect();
+    assert_eq!(palette.len(), schematic.get_palette().len());
+
+    let mut data = get_field!(blocks, "Data", as_byte_array).unwrap().iter();
+    let mut ids = Vec::new();
+    while data.len() > 0 {
+        let (mut id, mut shift) = (0, 0);
+        for &byte in data.by_ref() {
+            id |= (byte as i32 & 0x7F) << shift;
+            shift += 7;
+            if byte >= 0 {
+                break;
+            }
+        }
+        ids.push(id);
+    }
+    assert_eq!(ids.len(), 23 * 10 * 8);
+    for (i, id) in ids.into_iter().enumerate() {
+        let (x, y, z) = (i % 23, i / (23 * 8), i / 23 % 8);
+        let block = schematic.get_block(x as u16, y as u16, z as u16).unwrap();
+        assert_eq!(palette[&id], block.to_string());
+    }
+
+    let block_entities = get_field!(blocks, "BlockEntities", as_list.as_compound).unwrap();
+    assert_eq!(block_entities.len(), 3);
+    let catalyst = &block_entities[0];
+    assert_eq!(
+        get_field!(catalyst, "Id", as_string).unwrap().to_string(),
+        "minecraft:sculk_catalyst"
+    );
+    assert_eq!(
+        &get_field!(catalyst, "Pos", as_int_array).unwrap()[..],
+        [0, 2, 3]
+    );
+    let data = get_field!(catalyst, "Data", as_compound).unwrap();
+    assert!(data.get("x").is_none() && data.get("id").is_none());
+}
+
+fn gzip(root: &NbtCompound) -> Vec<u8> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder
+        .write_all(&serialize_nbt(&string("Schematic"), root))
+        .unwrap();
+    encoder.finish().unwrap()
+}
+
+fn assert_same_blocks(a: &Schematic, b: &Schematic) {
+    let (width, height, length) = a.get_size();
+    assert_eq!(b.get_size(), (width, height, length));
+    for y in 0..height {
+        for z in 0..length {
+            for x in 0..width {
+                assert_eq!(a.get_block(x, y, z), b.get_block(x, y, z), "{x} {y} {z}");
+            }
+        }
+    }
+}
+
+#[test]
+fn test_sponge_round_trip() {
+    let region = Region::parse_bytes(include_bytes!("data/r.0.0.mca")).unwrap();
+    let schematic = region.extract(84..107, -45..-35, 0..8).unwrap();
+    let imported = import_sponge(&schematic.to_bytes()).unwrap();
+    assert_same_blocks(&schematic, &imported);
+    assert_eq!(imported.data_version(), schematic.data_version());
+
+    assert_eq!(imported.get_block_entities().len(), 3);
+    for (pos, block_entity) in schematic.get_block_entities() {
+        let imported = imported.get_block_entity(pos.0, pos.1, pos.2).unwrap();
+        let mut a: Vec<_> = block_entity.clone().into();
+        let mut b: Vec<_> = imported.clone().into();
+        a.sort_by(|a, b| a.0.cmp(&b.0));
+        b.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(a, b);
+    }
+}
+
+#[test]
+fn test_import_sponge_v2() {
+    let palette = compound([
+        ("minecraft:air", NbtTag::Int(0)),
+        ("minecraft:chest[facing=west]", NbtTag::Int(1)),
+        ("minecraft:stone", NbtTag::Int(2)),
+    ]);
+    let root = |palette: NbtCompound, data: Vec<i8>| {
+        let chest = compound([
+            ("Pos", NbtTag::IntArray(vec![1, 0, 0].try_into().unwrap())),
+            ("Id", NbtTag::String(string("minecraft:chest"))),
+            ("Lock", NbtTag::String(string("key"))),
+        ]);
+        compound([
+            ("Version", NbtTag::Int(2)),
+            ("DataVersion", NbtTag::Int(3465)),
+            ("Width", NbtTag::Short(2)),
+            ("Height", NbtTag::Short(1)),
+            ("Length", NbtTag::Short(2)),
+            ("Palette", NbtTag::Compound(palette)),
+            ("BlockData", NbtTag::ByteArray(data.try_into().unwrap())),
+            ("BlockEntities", compound_list(vec![chest])),
+        ])
+    };
+
+    // a varint can take more bytes than it needs
+    let data = vec![0, 1, -126, 0, 0];
+    let schematic = import_sponge(&gzip(&root(palette.clone(), data))).unwrap();
+    assert_eq!(schematic.get_size(), (2, 1, 2));
+    assert_eq!(schematic.data_version(), 3465);
+    let name = |x, z| schematic.get_block(x, 0, z).unwrap().get_name().to_owned();
+    assert_eq!(name(0, 0), "minecraft:air");
+    assert_eq!(name(1, 0), "minecraft:chest");
+    assert_eq!(name(0, 1), "minecraft:stone");
+    assert_eq!(name(1, 1), "minecraft:air");
+    let chest = schematic.get_block_entity(1, 0, 0).unwrap();
+    assert_eq!(get_field!(chest, "Lock", as_string).unwrap(), "key");
+    assert_eq!(
+        get_field!(chest, "id", as_string).unwrap(),
+        "minecraft:chest"
+    );
+    assert_eq!(get_field!(chest, "x", as_int), Some(&1));
+
+    for data in [vec![0, 1, 2], vec![0, 1, 2, -126], vec![0, 1, 3, 0]] {
+        assert!(matches!(
+            import_sponge(&gzip(&root(palette.clone(), data))),
+            Err(SchemError::InvalidBlockData)
+        ));
+    }
+    let broken = compound([("minecraft:stone[", NbtTag::Int(0))]);
+    assert!(matches!(
+        import_sponge(&gzip(&root(broken, vec![0, 0, 0, 0]))),
+        Err(SchemError::InvalidField(field)) if field == "Palette"
+    ));
+}
+
+#[test]
+fn test_import_sponge_hostile_size() {
+    // 65535 blocks along each axis, but data for only one of them
+    let root = compound([
+        ("Version", NbtTag::Int(2)),
+        ("Width", NbtTag::Short(-1)),
+        ("Height", NbtTag::Short(-1)),
+        ("Length", NbtTag::Short(-1)),
+        (
+            "Palette",
+            NbtTag::Compound(compound([("minecraft:stone", NbtTag::Int(0))])),
+        ),
+        ("BlockData", NbtTag::ByteArray(vec![0].try_into().unwrap())),
+    ]);
+    assert!(matches!(
+        import_sponge(&gzip(&root)),
+        Err(SchemError::InvalidBlockData)
+    ));
+}
+
+#[test]
+fn test_paste() {
+    let region = Region::parse_bytes(include_bytes!("data/r.0.0.mca")).unwrap();
+    let schematic = region.extract(84..107, -45..-35, 0..8).unwrap();
+
+    // across four chunks
+    let mut pasted = region.clone();
+    pasted.paste(&schematic, (260, 100, 60)).unwrap();
+    let copy = pasted.extract(260..283, 100..110, 60..68).unwrap();
+    assert_same_blocks(&schematic, &copy);
+    assert_eq!(copy.get_block_entities().len(), 3);
+    let catalyst = pasted
+        .get_chunk(16, 3)
+        .unwrap()
+        .get_block_entity(4, 102, 15)
+        .unwrap();
+    assert_eq!(catalyst.id, "minecraft:sculk_catalyst");
+    assert_eq!((catalyst.x, catalyst.y, catalyst.z), (260, 102, 63));
+    assert_eq!(
+        region.diff(&pasted).len(),
+        4,
+        "only the covered chunks change"
+    );
+
+    // pasting over the original drops its block entities first
+    let mut moved = region.clone();
+    moved.paste(&schematic, (84, -44, 0)).unwrap();
+    let chunk = moved.get_chunk(5, 0).unwrap();
+    assert!(chunk.get_block_entity(4, -43, 3).is_none());
+    assert_eq!(chunk.get_block_entity(4, -42, 3).unwrap().y, -42);
+    assert_eq!(chunk.block_entities().len(), 3);
+
+    // nothing changes when a chunk is missing or the y range is left
+    let mut failed = region.clone();
+    let err = failed.paste(&schematic, (500, 0, 500)).unwrap_err();
+    assert!(matches!(
+        err,
+        SchemError::MissingChunk(ChunkPos { x: 31, z: 31 })
+    ));
+    let err = failed.paste(&schematic, (84, 315, 0)).unwrap_err();
+    assert!(matches!(err, SchemError::OutOfBounds(err) if err.axis == Axis::Y));
+    assert!(region.diff(&failed).is_empty());
+}
+
+#[test]
+fn test_paste_skip_air() {
+    let region = Region::parse_bytes(include_bytes!("data/r.0.0.mca")).unwrap();
+    let mut chunk = region.get_chunk(5, 9).unwrap().clone();
+    let schematic = chunk.extract(0..16, 60..80, 0..16).unwrap();
+    let (x, y, z) = (0..20)
+        .flat_map(|y| (0..16).flat_map(move |z| (0..16).map(move |x| (x, y, z))))
+        .find(|&(x, y, z)| schematic.get_block(x, y, z).unwrap().is_air())
+        .unwrap();
+    let (x, y, z) = (x as u8, y as i32 + 40, z as u8);
+    let stone: Block = "minecraft:stone".parse().unwrap();
+    chunk.set(x, y, z, stone.clone()).unwrap();
+
+    let mut replaced = chunk.clone();
+    replaced.paste(&schematic, (0, 40, 0)).unwrap();
+    assert!(replaced.get(x, y, z).unwrap().is_air());
+    let options = PasteOptions { skip_air: true };
+    chunk.paste_with(&schematic, (0, 40, 0), &options).unwrap();
+    assert_eq!(chunk.get(x, y, z), Some(&stone));
+
+    let err = chunk.paste(&schematic, (1, 0, 0)).unwrap_err();
+    assert!(matches!(err, SchemError::OutOfBounds(err) if err.axis == Axis::X));
+}
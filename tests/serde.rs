@@ -0,0 +1,94 @@
+#![cfg(feature = "serde")]
+
+use std::{collections::HashMap, ptr};
+
+use mca_rs::{
+    block::Block,
+    chunk::Chunk,
+    region::{Region, RegionBuilder},
+    section::Section,
+};
+
+fn fixture() -> Region {
+    Region::parse_bytes(include_bytes!("data/r.0.0.mca")).unwrap()
+}
+
+#[test]
+fn test_block_json() {
+    let log: Block = "minecraft:oak_log[axis=y]".parse().unwrap();
+    let json = serde_json::to_string(&log).unwrap();
+    assert_eq!(
+        json,
+        r#"{"name":"minecraft:oak_log","properties":{"axis":"y"}}"#
+    );
+    assert_eq!(serde_json::from_str::<Block>(&json).unwrap(), log);
+
+    let stone: Block = "minecraft:stone".parse().unwrap();
+    let json = serde_json::to_string(&stone).unwrap();
+    assert_eq!(json, r#"{"name":"minecraft:stone"}"#);
+    assert_eq!(serde_json::from_str::<Block>(&json).unwrap(), stone);
+
+    // the order of the properties is kept in json, only the keys are sorted
+    let json = r#"{"name":"minecraft:chest","properties":{"waterlogged":"true","facing":"east"}}"#;
+    let chest: Block = serde_json::from_str(json).unwrap();
+    assert_eq!(serde_json::to_string(&chest).unwrap(), json);
+    assert_eq!(
+        chest.to_string(),
+        "minecraft:chest[facing=east,waterlogged=true]"
+    );
+    assert_eq!(chest.property_keys()[0].to_string(), "facing");
+}
+
+#[test]
+fn test_chunk_round_trip() {
+    let region = fixture();
+    let chunk = region.get_chunk(5, 9).unwrap();
+    let json = serde_json::to_string(chunk).unwrap();
+    let parsed: Chunk = serde_json::from_str(&json).unwrap();
+    assert_eq!(&parsed, chunk);
+    assert_eq!(serde_json::to_string(&parsed).unwrap(), json);
+}
+
+#[test]
+fn test_section_sharing() {
+    let region = fixture();
+    let chunk = region.get_chunk(0, 0).unwrap();
+    let (_, section) = chunk.sections().next().unwrap();
+    let parsed: Section = serde_json::from_str(&serde_json::to_string(section).unwrap()).unwrap();
+    assert_eq!(&parsed, section);
+
+    let mut first: HashMap<&Block, &Block> = HashMap::new();
+    for (_, _, _, block) in parsed.iter_blocks() {
+        let seen = first.entry(block).or_insert(block);
+        assert!(ptr::eq(*seen, block));
+    }
+    assert_eq!(first.len(), section.unique_blocks().len());
+}
+
+#[test]
+fn test_region_round_trip() {
+    // two chunks keep this fast in debug builds
+    let fixture = fixture();
+    let region = RegionBuilder::new()
+        .chunk(0, 0, fixture.get_chunk(0, 0).unwrap().clone())
+        .chunk(5, 9, fixture.get_chunk(5, 9).unwrap().clone())
+        .timestamp(5, 9, 1234)
+        .build()
+        .unwrap();
+    let json = serde_json::to_string(&region).unwrap();
+    let parsed: Region = serde_json::from_str(&json).unwrap();
+    assert_eq!(parsed, region);
+    assert_eq!(parsed.get_timestamp(5, 9), Some(1234));
+    assert!(parsed.get_chunk(1, 0).is_none());
+}
+
+#[test]
+fn test_invalid_section() {
+    let out_of_range = format!(
+        r#"{{"palette":[{{"name":"a"}},{{"name":"b"}}],"indices":{:?},"biomes":[],"block_light":null,"sky_light":null}}"#,
+        [2u16; 4096]
+    );
+    assert!(serde_json::from_str::<Section>(&out_of_range).is_err());
+    let short = r#"{"palette":[{"name":"a"},{"name":"b"}],"indices":[0,1],"biomes":[],"block_light":null,"sky_light":null}"#;
+    assert!(serde_json::from_str::<Section>(short).is_err());
+}
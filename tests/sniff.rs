@@ -0,0 +1,75 @@
+mod common;
+
+use common::{chunk_bytes, compound, compound_list, region_bytes, string, uniform_chunk};
+use mca_rs::{AnvilKind, sniff};
+use nbt_rs::types::{NbtArray, NbtList, NbtTag};
+
+#[test]
+fn test_sniff_block_region() {
+    assert_eq!(
+        sniff(include_bytes!("data/r.0.0.mca")),
+        AnvilKind::BlockRegion
+    );
+
+    let chunk = chunk_bytes(&uniform_chunk(-4, 24, "minecraft:stone"));
+    assert_eq!(
+        sniff(&region_bytes(&[(3, 7, chunk)])),
+        AnvilKind::BlockRegion
+    );
+}
+
+#[test]
+fn test_sniff_empty_and_invalid() {
+    assert_eq!(sniff(&[]), AnvilKind::Unknown);
+    assert_eq!(sniff(&[0u8; 8192]), AnvilKind::Unknown);
+
+    // a broken first chunk must not hide a valid second one
+    let garbage = vec![0xFFu8; 100];
+    let chunk = chunk_bytes(&uniform_chunk(0, 1, "minecraft:stone"));
+    let bytes = region_bytes(&[(0, 0, garbage), (1, 0, chunk)]);
+    assert_eq!(sniff(&bytes), AnvilKind::BlockRegion);
+}
+
+#[test]
+fn test_sniff_entity_and_poi_regions() {
+    let zombie = compound([("id", NbtTag::String(string("minecraft:zombie")))]);
+    let entities = compound([
+        ("DataVersion", NbtTag::Int(4325)),
+        ("Position", NbtTag::IntArray(vec![0, 0].try_into().unwrap())),
+        ("Entities", compound_list(vec![zombie])),
+    ]);
+    let bytes = region_bytes(&[(0, 0, chunk_bytes(&entities))]);
+    assert_eq!(sniff(&bytes), AnvilKind::EntityRegion);
+
+    let poi = compound([
+        ("DataVersion", NbtTag::Int(4325)),
+        ("Sections", NbtTag::Compound(compound([]))),
+    ]);
+    let bytes = region_bytes(&[(0, 0, chunk_bytes(&poi))]);
+    assert_eq!(sniff(&bytes), AnvilKind::PoiRegion);
+}
+
+#[test]
+fn test_sniff_old_formats() {
+    let blocks: NbtArray<i8> = vec![0i8; 32768].try_into().unwrap();
+    let mcregion = compound([(
+        "Level",
+        NbtTag::Compound(compound([
+            ("xPos", NbtTag::Int(0)),
+            ("zPos", NbtTag::Int(0)),
+            ("Blocks", NbtTag::ByteArray(blocks)),
+        ])),
+    )]);
+    let bytes = region_bytes(&[(0, 0, chunk_bytes(&mcregion))]);
+    assert_eq!(sniff(&bytes), AnvilKind::McRegion);
+
+    let anvil = compound([
+        ("DataVersion", NbtTag::Int(2586)),
+        (
+            "Level",
+            NbtTag::Compound(compound([("Sections", NbtTag::List(NbtList::End))])),
+        ),
+    ]);
+    let bytes = region_bytes(&[(0, 0, chunk_bytes(&anvil))]);
+    assert_eq!(sniff(&bytes), AnvilKind::BlockRegion);
+}
@@ -0,0 +1,44 @@
+#![cfg(feature = "sync")]
+
+use std::thread;
+
+use mca_rs::{LazyRegion, block::Block, chunk::Chunk, region::Region, section::Section};
+
+fn assert_send<T: Send>() {}
+fn assert_sync<T: Sync>() {}
+
+#[test]
+fn test_send_sync() {
+    assert_send::<Block>();
+    assert_sync::<Block>();
+    assert_send::<Section>();
+    assert_sync::<Section>();
+    assert_send::<Chunk>();
+    assert_sync::<Chunk>();
+    assert_send::<Region>();
+    assert_sync::<Region>();
+    // decoding on first access needs interior mutability
+    assert_send::<LazyRegion<'static>>();
+}
+
+#[test]
+fn test_chunks_across_threads() {
+    let region = Region::parse_bytes(include_bytes!("data/r.0.0.mca")).unwrap();
+    let counts: Vec<usize> = thread::scope(|scope| {
+        let handles: Vec<_> = [(0, 0), (5, 9), (17, 3)]
+            .map(|(x, z)| {
+                let chunk = region.get_chunk(x, z).unwrap();
+                scope.spawn(move || chunk.iter_blocks_non_air().count())
+            })
+            .into_iter()
+            .collect();
+        handles
+            .into_iter()
+            .map(|handle| handle.join().unwrap())
+            .collect()
+    });
+    for ((x, z), count) in [(0, 0), (5, 9), (17, 3)].into_iter().zip(counts) {
+        let chunk = region.get_chunk(x, z).unwrap();
+        assert_eq!(count, chunk.iter_blocks_non_air().count());
+    }
+}
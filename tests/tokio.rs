@@ -0,0 +1,122 @@
+#![cfg(feature = "tokio")]
+
+mod common;
+
+use std::{fs, io::Cursor, path::Path};
+
+use common::{region_bytes, temp_dir};
+use mca_rs::{
+    CacheStats, RegionFile, World, WorldError,
+    region::{Region, RegionOpenError, RegionParseError},
+};
+
+fn fixture_path() -> &'static Path {
+    Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/r.0.0.mca"))
+}
+
+#[tokio::test]
+async fn test_open_region_async() {
+    let region = Region::open_async(fixture_path()).await.unwrap();
+    assert_eq!(region, Region::open(fixture_path()).unwrap());
+
+    let missing = fixture_path().with_file_name("missing.mca");
+    assert!(matches!(
+        Region::open_async(missing).await,
+        Err(RegionOpenError::Io(_))
+    ));
+
+    let dir = temp_dir("tokio_region");
+    let path = dir.join("r.0.0.mca");
+    fs::write(&path, [0; 4096]).unwrap();
+    assert!(matches!(
+        Region::open_async(&path).await,
+        Err(RegionOpenError::Parse(RegionParseError::InputTooShort(
+            4096
+        )))
+    ));
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+async fn test_read_chunk_async() {
+    let region = Region::open(fixture_path()).unwrap();
+    let mut file = RegionFile::open_async(fixture_path()).await.unwrap();
+    for (x, z) in [(0, 0), (5, 9), (17, 3), (31, 31)] {
+        assert_eq!(
+            file.read_chunk_async(x, z).await.unwrap().as_ref(),
+            region.get_chunk(x, z)
+        );
+        assert_eq!(
+            file.read_raw_chunk_async(x, z).await.unwrap().as_ref(),
+            region.get_chunk_raw(x, z)
+        );
+    }
+    assert!(file.read_chunk_async(32, 0).await.unwrap().is_none());
+
+    let bytes = region_bytes(&[]);
+    let mut file = RegionFile::new_async(Cursor::new(bytes)).await.unwrap();
+    assert!(file.read_chunk_async(0, 0).await.unwrap().is_none());
+    assert!(matches!(
+        RegionFile::new_async(Cursor::new(vec![0; 100])).await,
+        Err(RegionOpenError::Parse(RegionParseError::InputTooShort(100)))
+    ));
+
+    // no sectors in the header and a length far past the end of the file
+    let mut bytes = region_bytes(&[]);
+    bytes[..4].copy_from_slice(&[0, 0, 2, 0]);
+    bytes.extend([0xFF, 0xFF, 0xFF, 0xFF, 2]);
+    bytes.resize(3 * 4096, 0);
+    let mut file = RegionFile::new_async(Cursor::new(bytes)).await.unwrap();
+    assert!(matches!(
+        file.read_raw_chunk_async(0, 0).await,
+        Err(RegionOpenError::Parse(RegionParseError::ChunkError {
+            x: 0,
+            z: 0,
+            ..
+        }))
+    ));
+}
+
+#[tokio::test]
+async fn test_world_async() {
+    let dir = temp_dir("tokio_world");
+    let regions = dir.join("region");
+    fs::create_dir(&regions).unwrap();
+    fs::copy(fixture_path(), regions.join("r.0.0.mca")).unwrap();
+    fs::write(regions.join("r.3.0.mca"), []).unwrap();
+    fs::write(regions.join("r.1.0.mca"), [0; 100]).unwrap();
+
+    let mut world = World::open_async(&dir).await.unwrap();
+    assert_eq!(
+        world.region_coords().collect::<Vec<_>>(),
+        [(0, 0), (1, 0), (3, 0)]
+    );
+
+    let fixture = Region::open(fixture_path()).unwrap();
+    assert_eq!(
+        world.get_chunk_async(5, 9).await.unwrap(),
+        fixture.get_chunk(5, 9)
+    );
+    assert_eq!(world.get_chunk(17, 3).unwrap(), fixture.get_chunk(17, 3));
+    let region = world.get_region_async(3, 0).await.unwrap().unwrap();
+    assert_eq!(region.count_chunks(), 0);
+    assert!(world.get_region_async(4, 0).await.unwrap().is_none());
+    assert!(matches!(
+        world.get_region_async(1, 0).await,
+        Err(WorldError::Parse { .. })
+    ));
+    assert_eq!(
+        world.cache_stats(),
+        CacheStats {
+            hits: 1,
+            misses: 2,
+            evictions: 0
+        }
+    );
+
+    assert!(matches!(
+        World::open_async(dir.join("region")).await,
+        Err(WorldError::Io { .. })
+    ));
+    fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,162 @@
+#![cfg(feature = "tracing")]
+
+mod common;
+
+use std::{
+    collections::HashMap,
+    fmt::Debug,
+    sync::{Arc, Mutex},
+};
+
+use common::{chunk_bytes, region_bytes, string, uniform_chunk};
+use mca_rs::{
+    chunk::{ChunkParseOptions, parse_chunk_with},
+    region::{Region, RegionParseOptions, StatusFilter},
+};
+use nbt_rs::types::NbtTag;
+use tracing::{
+    Event, Metadata, Subscriber,
+    field::{Field, Visit},
+    span::{Attributes, Id, Record},
+};
+
+#[derive(Default)]
+struct Recorded {
+    // span name and its fields, in creation order
+    spans: Vec<(&'static Metadata<'static>, HashMap<String, String>)>,
+    events: Vec<HashMap<String, String>>,
+}
+
+#[derive(Default, Clone)]
+struct Recorder {
+    recorded: Arc<Mutex<Recorded>>,
+}
+
+struct Fields<'a>(&'a mut HashMap<String, String>);
+
+impl Visit for Fields<'_> {
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        self.0.insert(field.name().to_owned(), format!("{value:?}"));
+    }
+}
+
+impl Subscriber for Recorder {
+    fn enabled(&self, _: &Metadata<'_>) -> bool {
+        true
+    }
+
+    fn new_span(&self, span: &Attributes<'_>) -> Id {
+        let mut fields = HashMap::new();
+        span.record(&mut Fields(&mut fields));
+        let mut recorded = self.recorded.lock().unwrap();
+        recorded.spans.push((span.metadata(), fields));
+        Id::from_u64(recorded.spans.len() as u64)
+    }
+
+    fn record(&self, span: &Id, values: &Record<'_>) {
+        let mut recorded = self.recorded.lock().unwrap();
+        let (_, fields) = &mut recorded.spans[span.into_u64() as usize - 1];
+        values.record(&mut Fields(fields));
+    }
+
+    fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+    fn event(&self, event: &Event<'_>) {
+        let mut fields = HashMap::new();
+        event.record(&mut Fields(&mut fields));
+        self.recorded.lock().unwrap().events.push(fields);
+    }
+
+    fn enter(&self, _: &Id) {}
+
+    fn exit(&self, _: &Id) {}
+}
+
+#[test]
+fn test_region_spans() {
+    let chunk = chunk_bytes(&uniform_chunk(-4, 24, "minecraft:stone"));
+    let mut bytes = region_bytes(&[(0, 0, chunk), (1, 0, vec![0xFF; 100])]);
+    // the second chunk also lacks a timestamp
+    bytes[4100..4104].fill(0);
+
+    let recorder = Recorder::default();
+    tracing::subscriber::with_default(recorder.clone(), || {
+        Region::parse_bytes(&bytes).unwrap();
+    });
+
+    let recorded = recorder.recorded.lock().unwrap();
+    let (metadata, region) = &recorded.spans[0];
+    assert_eq!(metadata.name(), "parse_region");
+    assert_eq!(region["chunks"], "1");
+    assert_eq!(region["failed"], "1");
+    assert!(region.contains_key("elapsed_us"));
+
+    let chunks: Vec<_> = recorded
+        .spans
+        .iter()
+        .filter(|(metadata, _)| metadata.name() == "parse_chunk")
+        .collect();
+    // the broken chunk is already rejected by its header
+    assert_eq!(chunks.len(), 1);
+    assert_eq!(chunks[0].1["compression"], "2");
+    assert_eq!(chunks[0].1["data_version"], "4325");
+    assert_eq!(chunks[0].1["sections"], "24");
+
+    let messages: Vec<_> = recorded
+        .events
+        .iter()
+        .map(|event| event["message"].as_str())
+        .collect();
+    assert_eq!(messages, ["chunk has a zero timestamp", "skipping chunk"]);
+}
+
+fn messages(recorder: &Recorder) -> Vec<String> {
+    let recorded = recorder.recorded.lock().unwrap();
+    (recorded.events.iter())
+        .map(|event| event["message"].clone())
+        .collect()
+}
+
+#[test]
+fn test_recoverable_events() {
+    // the lower section's blocks lack a namespace, the upper one is left out
+    let bytes = chunk_bytes(&uniform_chunk(-4, 2, "stone"));
+    let options = ChunkParseOptions {
+        lenient: true,
+        y_range: Some(-64..-48),
+        ..ChunkParseOptions::default()
+    };
+    let recorder = Recorder::default();
+    tracing::subscriber::with_default(recorder.clone(), || {
+        parse_chunk_with(&bytes, &options).unwrap();
+    });
+    assert_eq!(
+        messages(&recorder),
+        [
+            "substituting a palette entry",
+            "skipping section outside the y range"
+        ]
+    );
+    let recorded = recorder.recorded.lock().unwrap();
+    assert!(recorded.events[0]["warning"].contains("read as minecraft:stone"));
+    assert_eq!(recorded.events[1]["section_y"], "-3");
+}
+
+#[test]
+fn test_status_filter_events() {
+    let mut fields: HashMap<_, _> = uniform_chunk(0, 1, "minecraft:stone").into();
+    fields.insert(string("Status"), NbtTag::String(string("minecraft:noise")));
+    let bytes = region_bytes(&[(0, 0, chunk_bytes(&fields.into()))]);
+    let options = RegionParseOptions {
+        status_filter: StatusFilter::OnlyFull,
+        ..RegionParseOptions::default()
+    };
+    let recorder = Recorder::default();
+    tracing::subscriber::with_default(recorder.clone(), || {
+        Region::parse_bytes_with(&bytes, &options).unwrap();
+    });
+    assert_eq!(
+        messages(&recorder),
+        ["skipping chunk left out by the status filter"]
+    );
+}
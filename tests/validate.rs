@@ -0,0 +1,194 @@
+mod common;
+
+use std::{collections::HashMap, io};
+
+use common::{chunk_bytes, compound, compound_list, region_bytes, string, temp_dir, uniform_chunk};
+use mca_rs::{
+    BlockPos, ChunkPos, ValidationIssue,
+    block::Block,
+    chunk::parse_chunk,
+    heightmap::HeightmapKind,
+    region::{FileWriteOptions, Region},
+    validate::{ValidationFailed, ValidationOptions},
+};
+use nbt_rs::types::NbtTag;
+
+#[test]
+fn test_validate_chunk() {
+    let valid = parse_chunk(&chunk_bytes(&uniform_chunk(-4, 24, "minecraft:stone"))).unwrap();
+    assert_eq!(valid.validate(), []);
+
+    let unnamed = parse_chunk(&chunk_bytes(&uniform_chunk(-1, 2, ""))).unwrap();
+    assert_eq!(
+        unnamed.validate(),
+        [
+            ValidationIssue::EmptyBlockName {
+                section_y: -1,
+                count: 4096
+            },
+            ValidationIssue::EmptyBlockName {
+                section_y: 0,
+                count: 4096
+            },
+        ]
+    );
+
+    let too_low = parse_chunk(&chunk_bytes(&uniform_chunk(-128, 2, "minecraft:stone"))).unwrap();
+    assert_eq!(
+        too_low.validate(),
+        [ValidationIssue::SectionsOutOfRange { range: -128..-126 }]
+    );
+}
+
+#[test]
+fn test_validate_region() {
+    let region = Region::parse_bytes(include_bytes!("data/r.0.0.mca")).unwrap();
+    assert_eq!(region.validate(), []);
+
+    let chunk = chunk_bytes(&uniform_chunk(0, 1, ""));
+    let region = Region::parse_bytes(&region_bytes(&[(3, 7, chunk)])).unwrap();
+    let issues = region.validate();
+    assert_eq!(
+        issues,
+        [
+            (
+                (3, 7),
+                ValidationIssue::SlotMismatch {
+                    slot: (3, 7),
+                    pos: ChunkPos::new(0, 0)
+                }
+            ),
+            (
+                (3, 7),
+                ValidationIssue::EmptyBlockName {
+                    section_y: 0,
+                    count: 4096
+                }
+            ),
+        ]
+    );
+    assert_eq!(
+        issues[0].1.to_string(),
+        "chunk in slot (3, 7) claims to be at (0, 0)"
+    );
+}
+
+#[test]
+fn test_validate_region_position() {
+    let bytes = include_bytes!("data/r.0.0.mca");
+    let region = Region::parse_bytes_at(bytes, 0, 0).unwrap();
+    assert_eq!(region.validate(), []);
+
+    // the chunks claim to be in region (0, 0)
+    let region = Region::parse_bytes_at(bytes, -1, 2).unwrap();
+    let issues = region.validate();
+    assert_eq!(issues.len(), region.count_chunks() as usize);
+    assert!(issues.contains(&(
+        (5, 9),
+        ValidationIssue::SlotMismatch {
+            slot: (5, 9),
+            pos: ChunkPos::new(5, 9)
+        }
+    )));
+}
+
+#[test]
+fn test_validate_block_entities() {
+    let entity = |x: i32, y: i32, z: i32| {
+        compound([
+            ("id", NbtTag::String(string("minecraft:chest"))),
+            ("x", NbtTag::Int(x)),
+            ("y", NbtTag::Int(y)),
+            ("z", NbtTag::Int(z)),
+        ])
+    };
+    let mut root: HashMap<_, _> = uniform_chunk(-1, 2, "minecraft:stone").into();
+    root.insert(string("xPos"), NbtTag::Int(-2));
+    root.insert(
+        string("block_entities"),
+        compound_list(vec![
+            entity(-32, 0, 15),
+            entity(-16, 0, 15),
+            entity(-20, 0, 16),
+            entity(-20, 40, 3),
+        ]),
+    );
+    let chunk = parse_chunk(&chunk_bytes(&root.into())).unwrap();
+    let issues = chunk.validate();
+    let outside = |x, y, z| ValidationIssue::BlockEntityOutsideChunk {
+        id: "minecraft:chest".to_owned(),
+        pos: BlockPos::new(x, y, z),
+    };
+    assert_eq!(
+        issues,
+        [
+            outside(-16, 0, 15),
+            outside(-20, 0, 16),
+            outside(-20, 40, 3)
+        ]
+    );
+    assert_eq!(
+        issues[0].to_string(),
+        "block entity minecraft:chest at (-16, 0, 15) is outside the chunk"
+    );
+}
+
+#[test]
+fn test_validate_heightmaps() {
+    let mut chunk = parse_chunk(&chunk_bytes(&uniform_chunk(-4, 8, "minecraft:stone"))).unwrap();
+    chunk.compute_heightmaps();
+    let options = ValidationOptions { heightmaps: true };
+    assert_eq!(chunk.validate_with(&options), []);
+
+    let air = Block::new(string("minecraft:air"), None);
+    chunk.set(3, 63, 4, air.clone()).unwrap();
+    chunk.set(4, 63, 4, air).unwrap();
+    // only checked when asked for
+    assert_eq!(chunk.validate(), []);
+    let issues = chunk.validate_with(&options);
+    let expected: Vec<_> = HeightmapKind::ALL
+        .into_iter()
+        .map(|kind| ValidationIssue::HeightmapMismatch { kind, columns: 2 })
+        .collect();
+    assert_eq!(issues, expected);
+    assert_eq!(
+        issues[0].to_string(),
+        "the WORLD_SURFACE heightmap doesn't match the blocks in 2 columns"
+    );
+
+    chunk.compute_heightmaps();
+    assert_eq!(chunk.validate_with(&options), []);
+}
+
+#[test]
+fn test_write_refuses_invalid() {
+    let chunk = chunk_bytes(&uniform_chunk(0, 1, ""));
+    let region = Region::parse_bytes(&region_bytes(&[(3, 7, chunk)])).unwrap();
+    let dir = temp_dir("write_refuses_invalid");
+    let path = dir.join("r.0.0.mca");
+
+    let options = FileWriteOptions {
+        validate: Some(ValidationOptions::default()),
+        ..FileWriteOptions::default()
+    };
+    let error = region.write_to_file_with(&path, &options).unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    let failed = error.get_ref().unwrap().downcast_ref::<ValidationFailed>();
+    assert_eq!(failed.unwrap().issues, region.validate());
+    assert_eq!(
+        error.to_string(),
+        "the region has 2 validation issues, the first in slot (3, 7): \
+         chunk in slot (3, 7) claims to be at (0, 0)"
+    );
+    // built by hand, there may be nothing to report
+    assert_eq!(
+        ValidationFailed { issues: vec![] }.to_string(),
+        "the region has no validation issues"
+    );
+    assert!(!path.exists());
+
+    // written without validating
+    region.write_to_file(&path).unwrap();
+    assert!(path.exists());
+    std::fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,381 @@
+mod common;
+
+use std::{
+    collections::HashMap,
+    fs,
+    io::ErrorKind,
+    ops::ControlFlow,
+    path::Path,
+    time::{Duration, UNIX_EPOCH},
+};
+
+use common::{chunk_bytes, compound, compound_list, region_bytes, string, temp_dir, uniform_chunk};
+use mca_rs::{
+    BlockPos, CacheStats, ChunkPos, ChunkRef, Dimension, Entity, RegionPos, ScanOptions, World,
+    WorldError,
+    chunk::ChunkParseError,
+    region::{Region, RegionParseError, RegionParseOptions},
+    scan_world,
+};
+use nbt_rs::types::{NbtList, NbtTag};
+
+fn chunk_at(x: i32, z: i32, block: &str) -> Vec<u8> {
+    let mut fields: HashMap<_, _> = uniform_chunk(-4, 1, block).into();
+    fields.insert(string("xPos"), NbtTag::Int(x));
+    fields.insert(string("zPos"), NbtTag::Int(z));
+    chunk_bytes(&fields.into())
+}
+
+#[test]
+fn test_world() {
+    let dir = temp_dir("world");
+    let regions = dir.join("region");
+    fs::create_dir(&regions).unwrap();
+    fs::write(regions.join("r.0.0.mca"), include_bytes!("data/r.0.0.mca")).unwrap();
+    let bytes = region_bytes(&[(31, 31, chunk_at(-1, -1, "minecraft:stone"))]);
+    fs::write(regions.join("r.-1.-1.mca"), bytes).unwrap();
+    fs::write(regions.join("r.3.0.mca"), []).unwrap();
+    fs::write(regions.join("notes.txt"), "not a region").unwrap();
+
+    let mut world = World::open(&dir).unwrap();
+    assert_eq!(
+        world.region_coords().collect::<Vec<_>>(),
+        [(-1, -1), (0, 0), (3, 0)]
+    );
+
+    let fixture = Region::parse_bytes(include_bytes!("data/r.0.0.mca")).unwrap();
+    assert_eq!(world.get_chunk(5, 9).unwrap(), fixture.get_chunk(5, 9));
+    assert_eq!(
+        world.get_block(5 * 16 + 3, 70, 9 * 16 + 4).unwrap(),
+        fixture.get_chunk(5, 9).unwrap().get(3, 70, 4)
+    );
+
+    // negative coordinates round down, not towards zero
+    let stone = world.get_block(-1, -60, -16).unwrap().unwrap();
+    assert_eq!(stone.get_name(), &string("minecraft:stone"));
+    assert!(world.get_block(-1, -40, -16).unwrap().is_none());
+    assert!(world.get_block(-17, -60, -16).unwrap().is_none());
+    // y past what an i16 holds is just outside the chunk
+    assert!(world.get_block(-1, 40_000, -16).unwrap().is_none());
+    assert!(world.get_chunk(-33, 0).unwrap().is_none());
+    let at = world.get_block_at(BlockPos::new(-1, -60, -16)).unwrap();
+    assert_eq!(at.unwrap().get_name(), &string("minecraft:stone"));
+    assert!(
+        world
+            .get_chunk_at(BlockPos::new(-16, 0, -1))
+            .unwrap()
+            .is_some()
+    );
+    assert!(world.get_chunk_at((-1, -2)).unwrap().is_none());
+    let region = world.get_region_at(ChunkPos::new(-32, -1)).unwrap();
+    assert_eq!(region.unwrap().position(), Some((-1, -1)));
+    assert!(world.get_region_at(RegionPos::new(3, 0)).unwrap().is_some());
+
+    // empty files are regions without chunks
+    assert_eq!(world.get_region(3, 0).unwrap().unwrap().count_chunks(), 0);
+    assert!(world.get_region(4, 0).unwrap().is_none());
+    let region = world.get_region(-1, -1).unwrap().unwrap();
+    assert_eq!(region.position(), Some((-1, -1)));
+    assert_eq!(region.validate(), []);
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_dimensions() {
+    let root = temp_dir("dimensions");
+    let dir = root.join("world");
+    let stone = region_bytes(&[(0, 0, chunk_at(0, 0, "minecraft:stone"))]);
+    for sub in [
+        "region",
+        "DIM-1/entities",
+        "dimensions/example/sky/region",
+        "dimensions/example/deep/caves/poi",
+        "dimensions/example/empty",
+        // a server keeping the end next to the overworld
+        "../world_the_end/DIM1/region",
+    ] {
+        fs::create_dir_all(dir.join(sub)).unwrap();
+    }
+    fs::write(dir.join("dimensions/example/sky/region/r.0.0.mca"), &stone).unwrap();
+    fs::write(root.join("world_the_end/DIM1/region/r.0.0.mca"), &stone).unwrap();
+
+    let found = World::dimensions(&dir).unwrap();
+    let ids: Vec<_> = found.iter().map(|found| found.dimension.id()).collect();
+    assert_eq!(
+        ids,
+        [
+            "minecraft:overworld",
+            "minecraft:the_nether",
+            "minecraft:the_end",
+            "example:deep/caves",
+            "example:sky",
+        ]
+    );
+    assert_eq!(found[1].dir, dir.join("DIM-1"));
+    assert_eq!(found[1].region, None);
+    assert_eq!(found[1].entities, Some(dir.join("DIM-1/entities")));
+    assert_eq!(found[2].dir, root.join("world_the_end/DIM1"));
+    assert_eq!(
+        found[3].poi,
+        Some(dir.join("dimensions/example/deep/caves/poi"))
+    );
+
+    let custom = Dimension::from_id("example:deep/caves");
+    assert_eq!(found[3].dimension, custom);
+    assert_eq!(custom.path(), Path::new("dimensions/example/deep/caves"));
+    assert_eq!(Dimension::from_id("the_nether"), Dimension::Nether);
+    assert_eq!(Dimension::Overworld.path(), Path::new(""));
+
+    let sky = Dimension::Custom("example".to_owned(), "sky".to_owned());
+    for dimension in [sky, Dimension::End] {
+        let mut world = World::open_dimension(&dir, &dimension).unwrap();
+        assert!(world.get_chunk(0, 0).unwrap().is_some());
+    }
+    assert!(matches!(
+        World::open_dimension(&dir, &Dimension::from_id("example:missing")),
+        Err(WorldError::Io { source, .. }) if source.kind() == ErrorKind::NotFound
+    ));
+
+    fs::remove_dir_all(root).unwrap();
+}
+
+#[test]
+fn test_world_errors() {
+    let dir = temp_dir("world-errors");
+    assert!(matches!(
+        World::open(&dir),
+        Err(WorldError::Io { source, .. }) if source.kind() == ErrorKind::NotFound
+    ));
+
+    let regions = dir.join("region");
+    fs::create_dir(&regions).unwrap();
+    fs::write(regions.join("r.0.0.mca"), [0; 100]).unwrap();
+    let mut world = World::open(&dir).unwrap();
+    assert!(matches!(
+        world.get_chunk(0, 0),
+        Err(WorldError::Parse {
+            source: RegionParseError::InputTooShort(100),
+            ..
+        })
+    ));
+
+    fs::write(regions.join("r.0.x.mca"), []).unwrap();
+    assert!(matches!(
+        World::open(&dir),
+        Err(WorldError::InvalidFileName(path)) if path.ends_with("r.0.x.mca")
+    ));
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_world_cache() {
+    let dir = temp_dir("world-cache");
+    let regions = dir.join("region");
+    fs::create_dir(&regions).unwrap();
+    for (x, block) in [
+        (0, "minecraft:stone"),
+        (1, "minecraft:dirt"),
+        (2, "minecraft:sand"),
+    ] {
+        let bytes = region_bytes(&[(0, 0, chunk_at(x * 32, 0, block))]);
+        fs::write(regions.join(format!("r.{x}.0.mca")), bytes).unwrap();
+    }
+
+    let mut world = World::open(&dir).unwrap().with_cache_size(2);
+    let mut name = |x: i32| {
+        let block = world.get_block(x * 512, -64, 0).unwrap().unwrap();
+        block.get_name().to_string()
+    };
+    assert_eq!(name(0), "minecraft:stone");
+    assert_eq!(name(1), "minecraft:dirt");
+    assert_eq!(name(0), "minecraft:stone");
+    // evicts region 1, the least recently used
+    assert_eq!(name(2), "minecraft:sand");
+    assert_eq!(name(0), "minecraft:stone");
+    assert_eq!(name(1), "minecraft:dirt");
+    assert_eq!(
+        world.cache_stats(),
+        CacheStats {
+            hits: 2,
+            misses: 4,
+            evictions: 2,
+        }
+    );
+
+    // shrinking the cache evicts right away
+    let world = world.with_cache_size(0);
+    assert_eq!(world.cache_stats().evictions, 3);
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_world_entities() {
+    let dir = temp_dir("world-entities");
+    let (regions, entities) = (dir.join("region"), dir.join("entities"));
+    fs::create_dir(&regions).unwrap();
+    fs::create_dir(&entities).unwrap();
+    // a chunk from 1.16, with its entities in the chunk itself
+    fs::write(regions.join("r.0.0.mca"), include_bytes!("data/r.1_16.mca")).unwrap();
+    fs::write(entities.join("r.0.0.mca"), []).unwrap();
+    let bytes = region_bytes(&[
+        (31, 0, chunk_at(-1, 0, "minecraft:stone")),
+        (30, 0, chunk_at(-2, 0, "minecraft:stone")),
+    ]);
+    fs::write(regions.join("r.-1.0.mca"), bytes).unwrap();
+    let cow = compound([
+        ("id", NbtTag::String(string("minecraft:cow"))),
+        (
+            "Pos",
+            NbtTag::List(NbtList::Double(vec![-7.5, 64.0, 3.5].try_into().unwrap())),
+        ),
+    ]);
+    let entity_chunk = chunk_bytes(&compound([
+        ("DataVersion", NbtTag::Int(4325)),
+        (
+            "Position",
+            NbtTag::IntArray(vec![-1, 0].try_into().unwrap()),
+        ),
+        ("Entities", compound_list(vec![cow])),
+    ]));
+    let bytes = region_bytes(&[(31, 0, entity_chunk)]);
+    fs::write(entities.join("r.-1.0.mca"), bytes).unwrap();
+    fs::write(entities.join("r.1.0.mca"), "not a region").unwrap();
+
+    let mut world = World::open(&dir).unwrap();
+    let ids = |entities: Vec<Entity>| -> Vec<_> {
+        entities
+            .iter()
+            .map(|entity| entity.id.to_string())
+            .collect()
+    };
+    assert_eq!(
+        ids(world.get_entities(0, 0).unwrap()),
+        ["minecraft:zombie", "minecraft:item"]
+    );
+    assert_eq!(ids(world.get_entities(-1, 0).unwrap()), ["minecraft:cow"]);
+    let at = world.get_entities_at(BlockPos::new(-5, 64, 3)).unwrap();
+    assert_eq!(at[0].pos, (-7.5, 64.0, 3.5));
+    assert!(world.get_entities(-2, 0).unwrap().is_empty());
+    assert!(world.get_entities(5, 5).unwrap().is_empty());
+    assert!(world.get_entities(-40, 0).unwrap().is_empty());
+    let error = world.get_entities(32, 0).unwrap_err();
+    assert!(matches!(error, WorldError::Parse { path, .. } if path == entities.join("r.1.0.mca")));
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_scan_world() {
+    let dir = temp_dir("scan_world");
+    let regions = dir.join("region");
+    fs::create_dir(&regions).unwrap();
+    fs::write(regions.join("r.0.0.mca"), include_bytes!("data/r.0.0.mca")).unwrap();
+    let bytes = region_bytes(&[
+        (31, 31, chunk_at(-1, -1, "minecraft:stone")),
+        (0, 0, vec![0, 0, 0, 2, 2, 0xFF]),
+    ]);
+    fs::write(regions.join("r.-1.-1.mca"), bytes).unwrap();
+    fs::write(regions.join("r.3.0.mca"), []).unwrap();
+    let nether = dir.join("DIM-1").join("region");
+    fs::create_dir_all(&nether).unwrap();
+    let bytes = region_bytes(&[(2, 1, chunk_at(2, 1, "minecraft:netherrack"))]);
+    fs::write(nether.join("r.0.0.mca"), bytes).unwrap();
+
+    let scan = |options: &ScanOptions| {
+        let mut seen = Vec::new();
+        scan_world(&dir, options, |chunk: ChunkRef| {
+            seen.push((chunk.pos, chunk.timestamp, chunk.chunk.get_pos()));
+            ControlFlow::Continue(())
+        })
+        .map(|()| seen)
+    };
+
+    let fixture = Region::parse_bytes(include_bytes!("data/r.0.0.mca")).unwrap();
+    let bounds = Some((ChunkPos::new(-1, -1), ChunkPos::new(2, 1)));
+    let seen = scan(&ScanOptions {
+        bounds,
+        ..Default::default()
+    })
+    .unwrap();
+    let expected = fixture.iter_chunks().filter(|&(x, z, _)| x <= 2 && z <= 1);
+    assert_eq!(seen.len(), expected.count() + 1);
+    // region files in order, and the broken chunk skipped
+    assert_eq!(seen[0], (ChunkPos::new(-1, -1), 1, ChunkPos::new(-1, -1)));
+    assert!(seen[1..].iter().all(|&(pos, _, own)| pos == own));
+
+    let mut visited = 0;
+    scan_world(&dir, &ScanOptions::default(), |_| {
+        visited += 1;
+        if visited == 3 {
+            ControlFlow::Break(())
+        } else {
+            ControlFlow::Continue(())
+        }
+    })
+    .unwrap();
+    assert_eq!(visited, 3);
+
+    let newest = fixture.newest_timestamp().unwrap();
+    let seen = scan(&ScanOptions {
+        modified_since: Some(UNIX_EPOCH + Duration::from_secs(newest as u64)),
+        ..Default::default()
+    })
+    .unwrap();
+    assert!(!seen.is_empty());
+    assert!(seen.iter().all(|&(_, timestamp, _)| timestamp == newest));
+
+    let seen = scan(&ScanOptions {
+        dimension: "DIM-1".into(),
+        ..Default::default()
+    })
+    .unwrap();
+    assert_eq!(seen, [(ChunkPos::new(2, 1), 1, ChunkPos::new(2, 1))]);
+
+    let strict = ScanOptions {
+        bounds: Some((ChunkPos::new(-32, -32), ChunkPos::new(-1, -1))),
+        region: RegionParseOptions {
+            strict: true,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    assert!(matches!(
+        scan(&strict),
+        Err(WorldError::Parse {
+            source: RegionParseError::ChunkError {
+                x: 0,
+                z: 0,
+                source: ChunkParseError::DecompressionFailed(_)
+            },
+            ..
+        })
+    ));
+
+    #[cfg(feature = "rayon")]
+    {
+        use std::sync::Mutex;
+
+        let seen = Mutex::new(Vec::new());
+        let options = ScanOptions {
+            bounds,
+            ..Default::default()
+        };
+        mca_rs::world::scan_world_par(&dir, &options, |chunk| {
+            seen.lock().unwrap().push(chunk.pos);
+            ControlFlow::Continue(())
+        })
+        .unwrap();
+        let mut seen = seen.into_inner().unwrap();
+        seen.sort();
+        let mut expected: Vec<_> = (scan(&options).unwrap().into_iter())
+            .map(|(pos, ..)| pos)
+            .collect();
+        expected.sort();
+        assert_eq!(seen, expected);
+    }
+
+    fs::remove_dir_all(dir).unwrap();
+}